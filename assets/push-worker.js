@@ -0,0 +1,17 @@
+// Shows outage alerts pushed by the uptime monitor, even when the dashboard is closed.
+self.addEventListener('push', (event) => {
+    const alert = event.data ? event.data.json() : { title: 'Outage detected', body: '' };
+
+    event.waitUntil(
+        self.registration.showNotification(alert.title, {
+            body: alert.body,
+            data: { url: alert.url || '/' },
+            tag: alert.url,
+        })
+    );
+});
+
+self.addEventListener('notificationclick', (event) => {
+    event.notification.close();
+    event.waitUntil(clients.openWindow(event.notification.data.url));
+});
@@ -0,0 +1,83 @@
+-- An incident is a run of consecutive failed queries for an origin, resolved by the next
+-- successful query (if one has happened yet). They are kept up to date as queries are recorded,
+-- so reading them never has to scan the history of every origin.
+CREATE TABLE incident (
+	id BIGINT GENERATED ALWAYS AS IDENTITY,
+	origin_id BIGINT NOT NULL,
+	started_at TIMESTAMP WITH TIME ZONE NOT NULL,
+	resolved_at TIMESTAMP WITH TIME ZONE,
+	failure_count BIGINT NOT NULL,
+
+	CONSTRAINT pk_incident PRIMARY KEY (id),
+	CONSTRAINT fk_incident_origin_id FOREIGN KEY (origin_id) REFERENCES origin(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_incident_started_at ON incident (started_at);
+CREATE INDEX idx_incident_origin_id_started_at ON incident (origin_id, started_at);
+
+-- Each origin is in at most one incident at a time, which concurrent failures are counted against
+CREATE UNIQUE INDEX uk_incident_origin_id_ongoing ON incident (origin_id) WHERE resolved_at IS NULL;
+
+-- Incidents for the history recorded so far
+INSERT INTO incident (origin_id, started_at, resolved_at, failure_count)
+WITH outcome AS (
+	SELECT origin_id, queried_at, FALSE AS failed
+	FROM query
+	UNION ALL
+	SELECT origin_id, queried_at, TRUE AS failed
+	FROM query_failure
+),
+streak AS (
+	SELECT
+		origin_id,
+		queried_at,
+		failed,
+		ROW_NUMBER() OVER (PARTITION BY origin_id ORDER BY queried_at)
+			- ROW_NUMBER() OVER (PARTITION BY origin_id, failed ORDER BY queried_at) AS streak
+	FROM outcome
+)
+SELECT
+	s.origin_id,
+	MIN(s.queried_at),
+	(
+		SELECT MIN(q.queried_at)
+		FROM query q
+		WHERE q.origin_id = s.origin_id
+		AND q.queried_at > MAX(s.queried_at)
+	),
+	COUNT(*)
+FROM streak s
+WHERE s.failed
+GROUP BY s.origin_id, s.streak;
+
+CREATE FUNCTION record_incident_failure() RETURNS TRIGGER AS $$
+BEGIN
+	INSERT INTO incident (origin_id, started_at, failure_count)
+	VALUES (NEW.origin_id, NEW.queried_at, 1)
+	ON CONFLICT (origin_id) WHERE resolved_at IS NULL DO UPDATE
+	SET started_at = LEAST(incident.started_at, EXCLUDED.started_at),
+		failure_count = incident.failure_count + 1;
+
+	RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE FUNCTION resolve_incident() RETURNS TRIGGER AS $$
+BEGIN
+	UPDATE incident
+	SET resolved_at = NEW.queried_at
+	WHERE origin_id = NEW.origin_id
+	AND resolved_at IS NULL
+	AND started_at < NEW.queried_at;
+
+	RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER query_failure_incident
+AFTER INSERT ON query_failure
+FOR EACH ROW EXECUTE FUNCTION record_incident_failure();
+
+CREATE TRIGGER query_incident
+AFTER INSERT ON query
+FOR EACH ROW EXECUTE FUNCTION resolve_incident();
@@ -0,0 +1,3 @@
+ALTER TABLE origin
+ADD COLUMN visibility TEXT NOT NULL DEFAULT 'private',
+ADD CONSTRAINT ck_origin_visibility CHECK (visibility IN ('public', 'private'));
@@ -0,0 +1,12 @@
+ALTER TABLE origin
+ADD COLUMN pinned BOOLEAN NOT NULL DEFAULT FALSE,
+ADD COLUMN display_order INTEGER NOT NULL DEFAULT 0;
+
+-- Preserve the existing alphabetical ordering for any origins that already exist
+UPDATE origin o
+SET display_order = ordered.position
+FROM (
+	SELECT id, ROW_NUMBER() OVER (ORDER BY uri) AS position
+	FROM origin
+) ordered
+WHERE o.id = ordered.id;
@@ -0,0 +1,12 @@
+CREATE TABLE health_score (
+	id BIGINT GENERATED ALWAYS AS IDENTITY,
+	origin_id BIGINT NOT NULL,
+	score SMALLINT NOT NULL,
+	computed_at TIMESTAMP WITH TIME ZONE NOT NULL,
+
+	CONSTRAINT pk_health_score PRIMARY KEY (id),
+	CONSTRAINT fk_health_score_origin_id FOREIGN KEY (origin_id) REFERENCES origin (id),
+	CONSTRAINT ck_health_score_score CHECK (score BETWEEN 0 AND 100)
+);
+
+CREATE INDEX idx_health_score_origin_id_computed_at ON health_score (origin_id, computed_at DESC);
@@ -0,0 +1,3 @@
+ALTER TABLE origin
+ADD COLUMN cost_per_minute_cents BIGINT,
+ADD CONSTRAINT ck_origin_cost_per_minute_cents CHECK (cost_per_minute_cents >= 0);
@@ -0,0 +1,12 @@
+CREATE TABLE configuration_draft (
+	id BIGINT GENERATED ALWAYS AS IDENTITY,
+	draft_uid UUID NOT NULL,
+	name TEXT NOT NULL,
+	document JSONB NOT NULL,
+	created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+	updated_at TIMESTAMP WITH TIME ZONE NOT NULL,
+	promoted_at TIMESTAMP WITH TIME ZONE,
+
+	CONSTRAINT pk_configuration_draft PRIMARY KEY (id),
+	CONSTRAINT uk_configuration_draft_draft_uid UNIQUE (draft_uid)
+);
@@ -0,0 +1,29 @@
+ALTER TABLE origin
+ADD COLUMN alerts_enabled BOOLEAN NOT NULL DEFAULT TRUE;
+
+CREATE TABLE pending_change (
+	id BIGINT GENERATED ALWAYS AS IDENTITY,
+	change_uid UUID NOT NULL,
+	kind TEXT NOT NULL,
+	-- The origin or draft the change applies to, kept without a foreign key so the history
+	-- survives the target being deleted
+	target_uid UUID NOT NULL,
+	description TEXT NOT NULL,
+	status TEXT NOT NULL DEFAULT 'pending',
+	requested_by TEXT NOT NULL,
+	requested_at TIMESTAMP WITH TIME ZONE NOT NULL,
+	decided_by TEXT,
+	decided_at TIMESTAMP WITH TIME ZONE,
+
+	CONSTRAINT pk_pending_change PRIMARY KEY (id),
+	CONSTRAINT uk_pending_change_change_uid UNIQUE (change_uid),
+	CONSTRAINT ck_pending_change_kind CHECK (kind IN ('delete_origin', 'disable_alerts', 'promote_draft', 'import_configuration')),
+	CONSTRAINT ck_pending_change_status CHECK (status IN ('pending', 'approved', 'rejected'))
+);
+
+-- Only one request for the same change can be awaiting approval at a time
+CREATE UNIQUE INDEX uk_pending_change_kind_target_uid_pending
+ON pending_change (kind, target_uid)
+WHERE status = 'pending';
+
+CREATE INDEX idx_pending_change_requested_at ON pending_change (requested_at DESC);
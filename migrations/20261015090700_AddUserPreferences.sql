@@ -0,0 +1,27 @@
+ALTER TABLE origin
+ADD COLUMN tags TEXT[] NOT NULL DEFAULT '{}';
+
+CREATE TABLE user_preference (
+	id BIGINT GENERATED ALWAYS AS IDENTITY,
+	username TEXT NOT NULL,
+	timezone TEXT NOT NULL DEFAULT 'UTC',
+	-- NULL shows every origin on a single page
+	page_size INTEGER,
+	tag_filter TEXT[] NOT NULL DEFAULT '{}',
+	updated_at TIMESTAMP WITH TIME ZONE NOT NULL,
+
+	CONSTRAINT pk_user_preference PRIMARY KEY (id),
+	CONSTRAINT uk_user_preference_username UNIQUE (username),
+	CONSTRAINT ck_user_preference_page_size CHECK (page_size > 0)
+);
+
+CREATE TABLE user_favorite (
+	id BIGINT GENERATED ALWAYS AS IDENTITY,
+	username TEXT NOT NULL,
+	origin_id BIGINT NOT NULL,
+	created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+
+	CONSTRAINT pk_user_favorite PRIMARY KEY (id),
+	CONSTRAINT uk_user_favorite_username_origin_id UNIQUE (username, origin_id),
+	CONSTRAINT fk_user_favorite_origin_id FOREIGN KEY (origin_id) REFERENCES origin (id) ON DELETE CASCADE
+);
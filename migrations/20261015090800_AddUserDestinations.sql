@@ -0,0 +1,12 @@
+CREATE TABLE user_destination (
+	id BIGINT GENERATED ALWAYS AS IDENTITY,
+	username TEXT NOT NULL,
+	kind TEXT NOT NULL,
+	-- An SNS topic ARN or a Slack incoming webhook URL, depending on the kind
+	target TEXT NOT NULL,
+	updated_at TIMESTAMP WITH TIME ZONE NOT NULL,
+
+	CONSTRAINT pk_user_destination PRIMARY KEY (id),
+	CONSTRAINT uk_user_destination_username UNIQUE (username),
+	CONSTRAINT ck_user_destination_kind CHECK (kind IN ('sns', 'slack'))
+);
@@ -0,0 +1,2 @@
+-- Checks that are not made over HTTP, such as TCP connects, succeed without a status code
+ALTER TABLE query ALTER COLUMN status DROP NOT NULL;
@@ -0,0 +1,13 @@
+CREATE TABLE push_subscription (
+	id BIGINT GENERATED ALWAYS AS IDENTITY,
+	username TEXT NOT NULL,
+	-- The push service URL the browser gave us, which uniquely identifies the subscription
+	endpoint TEXT NOT NULL,
+	-- The browser's base64url encoded P-256 public key and authentication secret
+	p256dh TEXT NOT NULL,
+	auth TEXT NOT NULL,
+	created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+
+	CONSTRAINT pk_push_subscription PRIMARY KEY (id),
+	CONSTRAINT uk_push_subscription_endpoint UNIQUE (endpoint)
+);
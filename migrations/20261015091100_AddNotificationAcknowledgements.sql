@@ -0,0 +1,2 @@
+ALTER TABLE notification ADD COLUMN acknowledged_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE notification ADD COLUMN acknowledged_by TEXT;
@@ -0,0 +1,5 @@
+ALTER TABLE origin
+ADD COLUMN method TEXT NOT NULL DEFAULT 'GET',
+ADD COLUMN request_body TEXT,
+ADD COLUMN request_content_type TEXT,
+ADD CONSTRAINT ck_origin_method CHECK (method IN ('GET', 'HEAD', 'POST', 'PUT'));
@@ -0,0 +1,11 @@
+-- Lets the poller pick up new and edited origins straight away rather than on its next round.
+CREATE FUNCTION notify_origin_changed() RETURNS TRIGGER AS $$
+BEGIN
+	PERFORM pg_notify('origin_changed', NEW.origin_uid::TEXT);
+	RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER origin_changed
+AFTER INSERT OR UPDATE OF uri, method, request_body, request_content_type ON origin
+FOR EACH ROW EXECUTE FUNCTION notify_origin_changed();
@@ -0,0 +1,6 @@
+ALTER TABLE origin
+ADD COLUMN forbidden_text TEXT;
+
+INSERT INTO query_failure_reason (name)
+VALUES
+	('ForbiddenContent');
@@ -0,0 +1,6 @@
+ALTER TABLE origin
+ADD COLUMN json_assertion TEXT;
+
+INSERT INTO query_failure_reason (name)
+VALUES
+	('JsonAssertionFailed');
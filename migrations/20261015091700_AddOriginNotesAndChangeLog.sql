@@ -0,0 +1,35 @@
+ALTER TABLE origin
+ADD COLUMN notes TEXT;
+
+CREATE TABLE origin_change (
+	id BIGINT GENERATED ALWAYS AS IDENTITY,
+	origin_id BIGINT NOT NULL,
+	field TEXT NOT NULL,
+	old_value TEXT,
+	new_value TEXT,
+	changed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+	CONSTRAINT pk_origin_change PRIMARY KEY (id),
+	CONSTRAINT fk_origin_change_origin_id FOREIGN KEY (origin_id) REFERENCES origin(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_origin_change_origin_id_changed_at ON origin_change (origin_id, changed_at DESC);
+
+-- Records every edited column, so the log stays complete however the origin was changed. Notes
+-- are annotations rather than configuration, so editing them is not logged.
+CREATE FUNCTION record_origin_changes() RETURNS TRIGGER AS $$
+BEGIN
+	INSERT INTO origin_change (origin_id, field, old_value, new_value)
+	SELECT NEW.id, new_column.key, old_column.value, new_column.value
+	FROM jsonb_each_text(to_jsonb(OLD)) old_column
+	JOIN jsonb_each_text(to_jsonb(NEW)) new_column USING (key)
+	WHERE old_column.value IS DISTINCT FROM new_column.value
+	AND new_column.key NOT IN ('id', 'origin_uid', 'notes');
+
+	RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER origin_change_log
+AFTER UPDATE ON origin
+FOR EACH ROW EXECUTE FUNCTION record_origin_changes();
@@ -0,0 +1,7 @@
+-- Responses with other statuses are recorded as failures. Origins without a list accept any status.
+ALTER TABLE origin
+ADD COLUMN expected_status TEXT;
+
+INSERT INTO query_failure_reason (name)
+VALUES
+	('UnexpectedStatus');
@@ -0,0 +1,9 @@
+-- A baseline is another origin, such as a static endpoint in the same region, whose latency is
+-- subtracted from this origin's to separate network-wide slowness from regressions in the service.
+ALTER TABLE origin
+ADD COLUMN baseline_origin_uid UUID,
+ADD COLUMN relative_latency_limit_millis BIGINT,
+-- Deferred so configuration imports can reference origins that are created later in the import
+ADD CONSTRAINT fk_origin_baseline_origin_uid FOREIGN KEY (baseline_origin_uid) REFERENCES origin(origin_uid) ON DELETE SET NULL DEFERRABLE INITIALLY DEFERRED,
+ADD CONSTRAINT ck_origin_baseline_origin_uid CHECK (baseline_origin_uid <> origin_uid),
+ADD CONSTRAINT ck_origin_relative_latency_limit_millis CHECK (relative_latency_limit_millis >= 0);
@@ -0,0 +1,10 @@
+-- Cooldowns only hold back alerts of the same kind, so notifications record which kind they were.
+-- Everything sent before then is taken to be an outage, as those are what cooldowns were for.
+ALTER TABLE notification
+ADD COLUMN kind TEXT NOT NULL DEFAULT 'outage',
+ADD CONSTRAINT ck_notification_kind CHECK (kind IN ('outage', 'latency_regression'));
+
+ALTER TABLE notification
+ALTER COLUMN kind DROP DEFAULT;
+
+CREATE INDEX idx_notification_origin_id_kind_created_at ON notification (origin_id, kind, created_at DESC);
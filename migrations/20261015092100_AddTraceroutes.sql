@@ -0,0 +1,15 @@
+-- The network path to an origin, captured part way through an incident caused by connection errors.
+CREATE TABLE traceroute (
+	id BIGINT GENERATED ALWAYS AS IDENTITY,
+	origin_id BIGINT NOT NULL,
+	-- Incidents are identified by the origin and when they started
+	incident_started_at TIMESTAMP WITH TIME ZONE NOT NULL,
+	host TEXT NOT NULL,
+	output TEXT NOT NULL,
+	captured_at TIMESTAMP WITH TIME ZONE NOT NULL,
+
+	CONSTRAINT pk_traceroute PRIMARY KEY (id),
+	CONSTRAINT fk_traceroute_origin_id FOREIGN KEY (origin_id) REFERENCES origin(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_traceroute_origin_id_captured_at ON traceroute (origin_id, captured_at DESC);
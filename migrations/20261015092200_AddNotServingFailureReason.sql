@@ -0,0 +1,3 @@
+INSERT INTO query_failure_reason (name)
+VALUES
+	('NotServing');
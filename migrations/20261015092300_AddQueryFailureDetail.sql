@@ -0,0 +1,3 @@
+-- Extra context about why a check failed, such as the DNS resolver used and the answers it gave.
+ALTER TABLE query_failure
+ADD COLUMN detail TEXT;
@@ -0,0 +1,7 @@
+-- Validating origins send conditional requests using the validators from their previous check.
+ALTER TABLE origin
+ADD COLUMN cache_validation BOOLEAN NOT NULL DEFAULT false;
+
+INSERT INTO query_failure_reason (name)
+VALUES
+	('CacheValidationFailed');
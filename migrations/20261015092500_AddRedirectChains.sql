@@ -0,0 +1,16 @@
+-- The redirects followed by redirect audits, recorded whenever they differ from the previous chain.
+CREATE TABLE redirect_chain (
+	id BIGINT GENERATED ALWAYS AS IDENTITY,
+	origin_id BIGINT NOT NULL,
+	chain TEXT NOT NULL,
+	recorded_at TIMESTAMP WITH TIME ZONE NOT NULL,
+
+	CONSTRAINT pk_redirect_chain PRIMARY KEY (id),
+	CONSTRAINT fk_redirect_chain_origin_id FOREIGN KEY (origin_id) REFERENCES origin(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_redirect_chain_origin_id_recorded_at ON redirect_chain (origin_id, recorded_at DESC);
+
+ALTER TABLE notification
+DROP CONSTRAINT ck_notification_kind,
+ADD CONSTRAINT ck_notification_kind CHECK (kind IN ('outage', 'latency_regression', 'redirect_chain_changed'));
@@ -0,0 +1,9 @@
+-- Origins without a limit follow up to 10 redirects, and a limit of 0 records the redirect itself.
+ALTER TABLE origin
+ADD COLUMN redirect_limit INTEGER CHECK (redirect_limit >= 0),
+ADD COLUMN expected_location TEXT;
+
+-- Where checks that were redirected ended up, and the responses along the way.
+ALTER TABLE query
+ADD COLUMN final_url TEXT,
+ADD COLUMN redirect_chain TEXT;
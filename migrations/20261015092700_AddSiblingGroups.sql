@@ -0,0 +1,6 @@
+-- Siblings are the HTTP, HTTPS, apex and www forms of the same site, created together and shown
+-- with a single aggregate status.
+ALTER TABLE origin
+ADD COLUMN sibling_group_uid UUID;
+
+CREATE INDEX idx_origin_sibling_group_uid ON origin (sibling_group_uid);
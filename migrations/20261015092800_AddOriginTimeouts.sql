@@ -0,0 +1,4 @@
+-- Origins without a timeout use the default of the poller.
+ALTER TABLE origin
+ADD COLUMN timeout_millis BIGINT,
+ADD CONSTRAINT ck_origin_timeout_millis CHECK (timeout_millis > 0);
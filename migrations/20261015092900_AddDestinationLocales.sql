@@ -0,0 +1,4 @@
+-- Destinations without a locale receive alerts in the locale of the deployment.
+ALTER TABLE user_destination
+ADD COLUMN locale TEXT,
+ADD CONSTRAINT ck_user_destination_locale CHECK (locale IN ('en', 'de', 'fr', 'es'));
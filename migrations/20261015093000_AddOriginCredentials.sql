@@ -0,0 +1,3 @@
+-- Credentials are encrypted by the application, so only the nonce and ciphertext are stored.
+ALTER TABLE origin
+ADD COLUMN encrypted_credentials BYTEA;
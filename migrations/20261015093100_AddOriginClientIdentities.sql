@@ -0,0 +1,3 @@
+-- The private key and certificate are encrypted together by the application, like credentials.
+ALTER TABLE origin
+ADD COLUMN encrypted_client_identity BYTEA;
@@ -0,0 +1,12 @@
+-- Origins checked over both families record which one each check used.
+ALTER TABLE origin
+ADD COLUMN address_family TEXT NOT NULL DEFAULT 'any',
+ADD CONSTRAINT ck_origin_address_family CHECK (address_family IN ('any', 'ipv4', 'ipv6', 'both'));
+
+ALTER TABLE query
+ADD COLUMN address_family TEXT,
+ADD CONSTRAINT ck_query_address_family CHECK (address_family IN ('ipv4', 'ipv6'));
+
+ALTER TABLE query_failure
+ADD COLUMN address_family TEXT,
+ADD CONSTRAINT ck_query_failure_address_family CHECK (address_family IN ('ipv4', 'ipv6'));
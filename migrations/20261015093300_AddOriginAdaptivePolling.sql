@@ -0,0 +1,3 @@
+-- Adaptive origins are checked more often while they are failing so recoveries are seen sooner.
+ALTER TABLE origin
+ADD COLUMN adaptive_polling BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,3 @@
+-- Origins only reachable through a bastion can be checked through a proxy of their own.
+ALTER TABLE origin
+ADD COLUMN proxy TEXT;
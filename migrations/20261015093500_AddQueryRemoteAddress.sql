@@ -0,0 +1,10 @@
+-- Successful HTTP checks record the address they were served from, so unexpected moves can be spotted.
+ALTER TABLE query
+ADD COLUMN remote_address TEXT;
+
+ALTER TABLE origin
+ADD COLUMN expected_networks TEXT;
+
+ALTER TABLE notification
+DROP CONSTRAINT ck_notification_kind,
+ADD CONSTRAINT ck_notification_kind CHECK (kind IN ('outage', 'latency_regression', 'redirect_chain_changed', 'unexpected_address'));
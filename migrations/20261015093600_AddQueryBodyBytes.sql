@@ -0,0 +1,3 @@
+-- Successful HTTP checks record how large the response body was, as a sudden drop often means an error page.
+ALTER TABLE query
+ADD COLUMN body_bytes BIGINT;
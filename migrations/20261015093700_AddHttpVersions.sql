@@ -0,0 +1,7 @@
+-- Origins can force the HTTP version checks use, and successful checks record the one negotiated.
+ALTER TABLE origin
+ADD COLUMN http_version TEXT NOT NULL DEFAULT 'auto',
+ADD CONSTRAINT ck_origin_http_version CHECK (http_version IN ('auto', 'http1', 'http2'));
+
+ALTER TABLE query
+ADD COLUMN http_version TEXT;
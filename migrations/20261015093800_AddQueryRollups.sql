@@ -0,0 +1,16 @@
+-- Old checks can be compacted into one row per origin and period, keeping enough to report on them.
+CREATE TABLE query_rollup (
+	id BIGINT GENERATED ALWAYS AS IDENTITY,
+	origin_id BIGINT NOT NULL,
+	period_start TIMESTAMP WITH TIME ZONE NOT NULL,
+	resolution_seconds INTEGER NOT NULL,
+	successes BIGINT NOT NULL,
+	failures BIGINT NOT NULL,
+	total_latency_millis BIGINT NOT NULL,
+	max_latency_millis BIGINT,
+
+	CONSTRAINT pk_query_rollup PRIMARY KEY (id),
+	CONSTRAINT uk_query_rollup_origin_id_period_start UNIQUE (origin_id, period_start),
+	CONSTRAINT fk_query_rollup_origin_id FOREIGN KEY (origin_id) REFERENCES origin(id) ON DELETE CASCADE,
+	CONSTRAINT ck_query_rollup_resolution_seconds CHECK (resolution_seconds > 0)
+);
@@ -0,0 +1,10 @@
+-- Origins can retry failed checks before recording them, and checks record how many attempts they took.
+ALTER TABLE origin
+ADD COLUMN retries INTEGER NOT NULL DEFAULT 0,
+ADD CONSTRAINT ck_origin_retries CHECK (retries BETWEEN 0 AND 3);
+
+ALTER TABLE query
+ADD COLUMN attempts INTEGER;
+
+ALTER TABLE query_failure
+ADD COLUMN attempts INTEGER;
@@ -0,0 +1,9 @@
+-- Origins can list response headers to record with each check, for context when looking into an outage.
+ALTER TABLE origin
+ADD COLUMN captured_headers TEXT;
+
+ALTER TABLE query
+ADD COLUMN captured_headers TEXT;
+
+ALTER TABLE query_failure
+ADD COLUMN captured_headers TEXT;
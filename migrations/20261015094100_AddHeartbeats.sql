@@ -0,0 +1,20 @@
+-- Heartbeat origins are pinged by the job they monitor and fail if a ping does not arrive in time.
+CREATE TABLE heartbeat (
+	id BIGINT GENERATED ALWAYS AS IDENTITY,
+	origin_id BIGINT NOT NULL,
+	-- Part of the URL the job pings, so anyone who knows it can ping the heartbeat
+	token TEXT NOT NULL,
+	grace_seconds INTEGER NOT NULL,
+	created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+	last_ping_at TIMESTAMP WITH TIME ZONE,
+
+	CONSTRAINT pk_heartbeat PRIMARY KEY (id),
+	CONSTRAINT uk_heartbeat_origin_id UNIQUE (origin_id),
+	CONSTRAINT uk_heartbeat_token UNIQUE (token),
+	CONSTRAINT ck_heartbeat_grace_seconds CHECK (grace_seconds > 0),
+	CONSTRAINT fk_heartbeat_origin_id FOREIGN KEY (origin_id) REFERENCES origin(id) ON DELETE CASCADE
+);
+
+INSERT INTO query_failure_reason (name)
+VALUES
+	('HeartbeatMissed');
@@ -0,0 +1,22 @@
+-- Journey origins make an ordered sequence of requests, passing cookies and extracted values between them.
+CREATE TABLE journey_step (
+	id BIGINT GENERATED ALWAYS AS IDENTITY,
+	journey_step_uid UUID NOT NULL,
+	origin_id BIGINT NOT NULL,
+	position INTEGER NOT NULL,
+	method TEXT NOT NULL,
+	url TEXT NOT NULL,
+	-- One `Name: value` header per line
+	request_headers TEXT,
+	request_body TEXT,
+	request_content_type TEXT,
+	expected_status TEXT,
+	-- Saves part of the response for later steps, such as `token = $.access_token`
+	extraction TEXT,
+
+	CONSTRAINT pk_journey_step PRIMARY KEY (id),
+	CONSTRAINT uk_journey_step_journey_step_uid UNIQUE (journey_step_uid),
+	CONSTRAINT uk_journey_step_origin_id_position UNIQUE (origin_id, position),
+	CONSTRAINT ck_journey_step_method CHECK (method IN ('GET', 'HEAD', 'POST', 'PUT')),
+	CONSTRAINT fk_journey_step_origin_id FOREIGN KEY (origin_id) REFERENCES origin(id) ON DELETE CASCADE
+);
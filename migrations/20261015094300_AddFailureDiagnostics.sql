@@ -0,0 +1,3 @@
+-- DNS results, connection timings and the network path gathered when an origin could not be reached.
+ALTER TABLE query_failure
+ADD COLUMN diagnostics TEXT;
@@ -0,0 +1,4 @@
+-- Origins can evaluate their alert rules and reliability over calendar periods rather than rolling windows.
+ALTER TABLE origin
+ADD COLUMN alert_window TEXT NOT NULL DEFAULT 'rolling',
+ADD CONSTRAINT ck_origin_alert_window CHECK (alert_window IN ('rolling', 'day', 'week', 'month'));
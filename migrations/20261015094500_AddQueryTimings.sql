@@ -0,0 +1,6 @@
+-- Successful HTTP checks record how long each phase took, so slowness can be put down to DNS, connecting, TLS or the application.
+ALTER TABLE query
+ADD COLUMN dns_millis BIGINT,
+ADD COLUMN connect_millis BIGINT,
+ADD COLUMN tls_millis BIGINT,
+ADD COLUMN first_byte_millis BIGINT;
@@ -0,0 +1,3 @@
+-- Failed checks that reached their origin record the address they connected to, so a bad backend behind DNS round-robin can be told apart from the rest.
+ALTER TABLE query_failure
+ADD COLUMN remote_address TEXT;
@@ -0,0 +1,11 @@
+-- Pollers can be tagged with the location they check from, which is recorded with their checks.
+ALTER TABLE query
+ADD COLUMN location TEXT;
+
+ALTER TABLE query_failure
+ADD COLUMN location TEXT;
+
+-- Origins limited to some locations are only checked by the pollers there, where `local` is any
+-- poller without a location.
+ALTER TABLE origin
+ADD COLUMN probe_locations TEXT;
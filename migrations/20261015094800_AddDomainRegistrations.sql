@@ -0,0 +1,16 @@
+-- When the domain each origin is served from expires, as last looked up over RDAP.
+CREATE TABLE domain_registration (
+	origin_id BIGINT NOT NULL,
+	-- The registered domain, which may be a parent of the origin's host
+	domain TEXT NOT NULL,
+	-- Not every registry publishes an expiry, and internal hosts are not registered at all
+	expires_at TIMESTAMP WITH TIME ZONE,
+	checked_at TIMESTAMP WITH TIME ZONE NOT NULL,
+
+	CONSTRAINT pk_domain_registration PRIMARY KEY (origin_id),
+	CONSTRAINT fk_domain_registration_origin_id FOREIGN KEY (origin_id) REFERENCES origin(id) ON DELETE CASCADE
+);
+
+ALTER TABLE notification
+DROP CONSTRAINT ck_notification_kind,
+ADD CONSTRAINT ck_notification_kind CHECK (kind IN ('outage', 'latency_regression', 'redirect_chain_changed', 'unexpected_address', 'domain_expiring'));
@@ -0,0 +1,5 @@
+-- The User-Agent HTTP checks of an origin send instead of the poller's, and whether they add a
+-- random query parameter so caches in front of the origin cannot answer for it.
+ALTER TABLE origin
+ADD COLUMN user_agent TEXT,
+ADD COLUMN cache_busting BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,8 @@
+-- Origins can check over a fresh connection every time, or measure a cold connection alongside the
+-- reused one, so pooled connections do not hide connect and TLS regressions.
+ALTER TABLE origin
+ADD COLUMN connection_mode TEXT NOT NULL DEFAULT 'reused',
+ADD CONSTRAINT ck_origin_connection_mode CHECK (connection_mode IN ('reused', 'fresh', 'both'));
+
+ALTER TABLE query
+ADD COLUMN cold_latency_millis BIGINT;
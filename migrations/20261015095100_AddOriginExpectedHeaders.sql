@@ -0,0 +1,8 @@
+-- Origins can require response headers, such as Strict-Transport-Security, failing checks of
+-- responses without them.
+ALTER TABLE origin
+ADD COLUMN expected_headers TEXT;
+
+INSERT INTO query_failure_reason (name)
+VALUES
+	('HeaderAssertionFailed');
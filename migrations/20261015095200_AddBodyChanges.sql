@@ -0,0 +1,20 @@
+-- Origins can hash their response bodies, recording whenever the hash changes so defacements and
+-- unexpected deploys show up.
+ALTER TABLE origin
+ADD COLUMN body_change_detection BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE body_change (
+	id BIGINT GENERATED ALWAYS AS IDENTITY,
+	origin_id BIGINT NOT NULL,
+	body_hash TEXT NOT NULL,
+	detected_at TIMESTAMP WITH TIME ZONE NOT NULL,
+
+	CONSTRAINT pk_body_change PRIMARY KEY (id),
+	CONSTRAINT fk_body_change_origin_id FOREIGN KEY (origin_id) REFERENCES origin(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_body_change_origin_id_detected_at ON body_change (origin_id, detected_at DESC);
+
+ALTER TABLE notification
+DROP CONSTRAINT ck_notification_kind,
+ADD CONSTRAINT ck_notification_kind CHECK (kind IN ('outage', 'latency_regression', 'redirect_chain_changed', 'unexpected_address', 'body_changed', 'domain_expiring'));
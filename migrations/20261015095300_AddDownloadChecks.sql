@@ -0,0 +1,8 @@
+-- Download checks measure how fast a large asset arrives, failing when it is slower than the
+-- origin's minimum.
+ALTER TABLE query
+ADD COLUMN throughput_bytes_per_second BIGINT;
+
+INSERT INTO query_failure_reason (name)
+VALUES
+	('SlowDownload');
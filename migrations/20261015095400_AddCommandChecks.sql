@@ -0,0 +1,5 @@
+-- Command checks run a script from the poller's commands directory, failing when it exits with
+-- a non-zero status.
+INSERT INTO query_failure_reason (name)
+VALUES
+	('CommandFailed');
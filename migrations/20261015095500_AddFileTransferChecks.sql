@@ -0,0 +1,4 @@
+-- File transfer checks log into FTP servers, failing when the server rejects the login.
+INSERT INTO query_failure_reason (name)
+VALUES
+	('AuthenticationFailed');
@@ -0,0 +1,4 @@
+-- Origins can be checked on a cron schedule, such as only during working hours, instead of every
+-- round.
+ALTER TABLE origin
+ADD COLUMN schedule TEXT;
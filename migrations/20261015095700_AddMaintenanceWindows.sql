@@ -0,0 +1,26 @@
+-- Maintenance windows cover planned work on an origin, or on every origin with a tag, either
+-- skipping its checks or recording them without counting towards alerts.
+CREATE TABLE maintenance_window (
+	id BIGINT GENERATED ALWAYS AS IDENTITY,
+	maintenance_window_uid UUID NOT NULL,
+	origin_id BIGINT,
+	tag TEXT,
+	starts_at TIMESTAMP WITH TIME ZONE NOT NULL,
+	ends_at TIMESTAMP WITH TIME ZONE NOT NULL,
+	-- Recurring windows repeat a day or a week after the previous one started
+	recurrence TEXT NOT NULL,
+	mode TEXT NOT NULL,
+	description TEXT,
+
+	CONSTRAINT pk_maintenance_window PRIMARY KEY (id),
+	CONSTRAINT uk_maintenance_window_maintenance_window_uid UNIQUE (maintenance_window_uid),
+	CONSTRAINT ck_maintenance_window_target CHECK ((origin_id IS NULL) <> (tag IS NULL)),
+	CONSTRAINT ck_maintenance_window_ends_after_start CHECK (ends_at > starts_at),
+	CONSTRAINT ck_maintenance_window_recurrence CHECK (recurrence IN ('once', 'daily', 'weekly')),
+	CONSTRAINT ck_maintenance_window_mode CHECK (mode IN ('skip', 'silence')),
+	CONSTRAINT fk_maintenance_window_origin_id FOREIGN KEY (origin_id) REFERENCES origin(id) ON DELETE CASCADE
+);
+
+-- Failures recorded during a window are kept for history but do not count towards alerts
+ALTER TABLE query_failure
+ADD COLUMN during_maintenance BOOLEAN NOT NULL DEFAULT FALSE;
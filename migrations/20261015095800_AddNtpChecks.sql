@@ -0,0 +1,8 @@
+-- NTP checks record how far a time server's clock is from the poller's, failing when it drifts
+-- further than the origin allows.
+ALTER TABLE query
+ADD COLUMN clock_offset_millis BIGINT;
+
+INSERT INTO query_failure_reason (name)
+VALUES
+	('ClockDrift');
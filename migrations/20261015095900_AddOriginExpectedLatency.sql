@@ -0,0 +1,5 @@
+-- Origins can be annotated with the latency they are expected to stay under, so tools reading the
+-- exports can compare results against it.
+ALTER TABLE origin
+ADD COLUMN expected_latency_millis BIGINT,
+ADD CONSTRAINT ck_origin_expected_latency_millis CHECK (expected_latency_millis > 0);
@@ -0,0 +1,3 @@
+-- Origins can be paused so the poller stops checking them without losing their history.
+ALTER TABLE origin
+ADD COLUMN paused BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,20 @@
+-- API clients can send an Idempotency-Key with mutations, so a retried request returns the
+-- response of the first rather than making the change twice.
+CREATE TABLE idempotency_key (
+	id BIGINT GENERATED ALWAYS AS IDENTITY,
+	idempotency_key TEXT NOT NULL,
+	-- The method, path and body the key was first used with, which retries must match
+	request_hash TEXT NOT NULL,
+	-- Missing until the first request has finished
+	status SMALLINT,
+	content_type TEXT,
+	response_body BYTEA,
+	created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+	completed_at TIMESTAMP WITH TIME ZONE,
+
+	CONSTRAINT pk_idempotency_key PRIMARY KEY (id),
+	CONSTRAINT uk_idempotency_key_idempotency_key UNIQUE (idempotency_key)
+);
+
+CREATE INDEX idx_idempotency_key_created_at
+ON idempotency_key (created_at);
@@ -0,0 +1,12 @@
+-- Origins are claimed by one poller in each location at a time, so running several instances
+-- checks each once a round from every location.
+CREATE TABLE origin_claim (
+	origin_id BIGINT NOT NULL,
+	-- Empty for pollers without a location
+	location TEXT NOT NULL,
+	claimed_by UUID NOT NULL,
+	claimed_until TIMESTAMP WITH TIME ZONE NOT NULL,
+
+	CONSTRAINT pk_origin_claim PRIMARY KEY (origin_id, location),
+	CONSTRAINT fk_origin_claim_origin_id FOREIGN KEY (origin_id) REFERENCES origin(id) ON DELETE CASCADE
+);
@@ -0,0 +1,37 @@
+-- Origins count their edits, so edits made from an outdated copy of one can be refused.
+ALTER TABLE origin
+ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
+
+-- Bumps the version in the statement making the edit, so it cannot fall out of step with it, and
+-- only when the edit changes something. Reordering the dashboard moves its neighbours too, which
+-- is not an edit of them, and statements setting the version themselves have already bumped it.
+CREATE FUNCTION increment_origin_version() RETURNS TRIGGER AS $$
+BEGIN
+	NEW.version := OLD.version + 1;
+
+	RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER origin_version
+BEFORE UPDATE ON origin
+FOR EACH ROW
+WHEN (
+	to_jsonb(OLD) - 'display_order' - 'version'
+	IS DISTINCT FROM to_jsonb(NEW) - 'display_order' - 'version'
+)
+EXECUTE FUNCTION increment_origin_version();
+
+-- The version is not itself an edit worth logging.
+CREATE OR REPLACE FUNCTION record_origin_changes() RETURNS TRIGGER AS $$
+BEGIN
+	INSERT INTO origin_change (origin_id, field, old_value, new_value)
+	SELECT NEW.id, new_column.key, old_column.value, new_column.value
+	FROM jsonb_each_text(to_jsonb(OLD)) old_column
+	JOIN jsonb_each_text(to_jsonb(NEW)) new_column USING (key)
+	WHERE old_column.value IS DISTINCT FROM new_column.value
+	AND new_column.key NOT IN ('id', 'origin_uid', 'notes', 'version');
+
+	RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
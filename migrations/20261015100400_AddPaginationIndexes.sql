@@ -0,0 +1,5 @@
+-- History and notifications are paged through by their time and id, newest first.
+CREATE INDEX idx_query_origin_id_queried_at_id ON query (origin_id, queried_at DESC, id DESC);
+CREATE INDEX idx_query_failure_origin_id_queried_at_id ON query_failure (origin_id, queried_at DESC, id DESC);
+CREATE INDEX idx_notification_created_at_id ON notification (created_at DESC, id DESC);
+CREATE INDEX idx_notification_origin_id_created_at_id ON notification (origin_id, created_at DESC, id DESC);
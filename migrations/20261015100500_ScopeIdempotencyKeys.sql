@@ -0,0 +1,9 @@
+-- Keys are only unique to the user who sent them, so nobody can replay the response to someone
+-- else's request. Requests without a user, and keys claimed before now, have an empty username.
+ALTER TABLE idempotency_key
+ADD COLUMN username TEXT NOT NULL DEFAULT '',
+DROP CONSTRAINT uk_idempotency_key_idempotency_key,
+ADD CONSTRAINT uk_idempotency_key_username_idempotency_key UNIQUE (username, idempotency_key);
+
+ALTER TABLE idempotency_key
+ALTER COLUMN username DROP DEFAULT;
@@ -0,0 +1,290 @@
+use std::collections::HashSet;
+use std::fmt::{self, Display};
+
+use color_eyre::eyre::{eyre, Result};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::persistence::Transaction;
+
+/// Destructive actions that may need a second admin to sign them off.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    DeleteOrigin,
+    DisableAlerts,
+    PromoteDraft,
+    /// Importing a configuration held as a draft, which unlike promoting it keeps other origins.
+    ImportConfiguration,
+}
+
+impl ChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::DeleteOrigin => "delete_origin",
+            Self::DisableAlerts => "disable_alerts",
+            Self::PromoteDraft => "promote_draft",
+            Self::ImportConfiguration => "import_configuration",
+        }
+    }
+
+    fn from_column(value: &str) -> Option<Self> {
+        match value {
+            "delete_origin" => Some(Self::DeleteOrigin),
+            "disable_alerts" => Some(Self::DisableAlerts),
+            "promote_draft" => Some(Self::PromoteDraft),
+            "import_configuration" => Some(Self::ImportConfiguration),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Decision {
+    Approve,
+    Reject,
+}
+
+impl Decision {
+    fn status(&self) -> &'static str {
+        match self {
+            Self::Approve => "approved",
+            Self::Reject => "rejected",
+        }
+    }
+}
+
+/// Why a user is not allowed to request or decide on a change.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PolicyViolation {
+    /// The request did not identify who made it.
+    Unauthenticated,
+    /// The user is not in the list of admins.
+    NotAdmin,
+    /// The user tried to approve a change they requested themselves.
+    SelfApproval,
+}
+
+impl Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            Self::Unauthenticated => "the request did not identify a user",
+            Self::NotAdmin => "only admins can request or approve destructive changes",
+            Self::SelfApproval => "changes must be approved by a different admin",
+        };
+
+        write!(f, "{message}")
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ApprovalPolicy {
+    /// Whether destructive changes need a second admin to approve them before being applied.
+    required: bool,
+    admins: HashSet<String>,
+}
+
+impl ApprovalPolicy {
+    pub fn new<I: IntoIterator<Item = String>>(required: bool, admins: I) -> Self {
+        Self {
+            required,
+            admins: admins.into_iter().collect(),
+        }
+    }
+
+    /// Reads the policy from `REQUIRE_APPROVAL` and the comma separated `ADMIN_USERS`, both of
+    /// which are optional.
+    pub fn from_env() -> Result<Self> {
+        let required = match std::env::var("REQUIRE_APPROVAL") {
+            Ok(value) => value.parse()?,
+            Err(_) => false,
+        };
+
+        let admins: Vec<_> = std::env::var("ADMIN_USERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|admin| !admin.is_empty())
+            .map(String::from)
+            .collect();
+
+        if required && admins.len() < 2 {
+            return Err(eyre!(
+                "REQUIRE_APPROVAL needs at least two users in ADMIN_USERS"
+            ));
+        }
+
+        Ok(Self::new(required, admins))
+    }
+
+    pub fn required(&self) -> bool {
+        self.required
+    }
+
+    pub fn is_admin(&self, user: &str) -> bool {
+        self.admins.contains(user)
+    }
+
+    /// Checks whether `user` may request a destructive change.
+    pub fn check_request(&self, user: Option<&str>) -> Result<(), PolicyViolation> {
+        let user = user.ok_or(PolicyViolation::Unauthenticated)?;
+
+        if !self.is_admin(user) {
+            return Err(PolicyViolation::NotAdmin);
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether `user` may decide on a change requested by `requested_by`.
+    pub fn check_decision(
+        &self,
+        user: Option<&str>,
+        requested_by: &str,
+        decision: Decision,
+    ) -> Result<(), PolicyViolation> {
+        self.check_request(user)?;
+
+        // Requesters can withdraw their own changes, but not approve them
+        if decision == Decision::Approve && user == Some(requested_by) {
+            return Err(PolicyViolation::SelfApproval);
+        }
+
+        Ok(())
+    }
+}
+
+pub enum DecisionOutcome {
+    Decided,
+    /// The change does not exist or has already been decided.
+    NotFound,
+    Forbidden(PolicyViolation),
+}
+
+/// Applies a change as part of an existing transaction.
+async fn apply(tx: &mut Transaction, kind: ChangeKind, target_uid: Uuid) -> Result<()> {
+    match kind {
+        ChangeKind::DeleteOrigin => crate::persistence::delete_origin(tx, target_uid).await,
+        ChangeKind::DisableAlerts => {
+            crate::persistence::set_origin_alerts_enabled(tx, target_uid, false).await
+        }
+        ChangeKind::PromoteDraft => {
+            if !crate::config::promote(tx, target_uid).await? {
+                return Err(eyre!(
+                    "draft {target_uid} does not exist or was already promoted"
+                ));
+            }
+
+            Ok(())
+        }
+        ChangeKind::ImportConfiguration => {
+            if !crate::config::import_draft(tx, target_uid).await? {
+                return Err(eyre!(
+                    "imported configuration {target_uid} does not exist or was already imported"
+                ));
+            }
+
+            Ok(())
+        }
+    }
+}
+
+/// Applies a change straight away, for when approval is not required.
+pub async fn apply_now(pool: &PgPool, kind: ChangeKind, target_uid: Uuid) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    apply(&mut tx, kind, target_uid).await?;
+    tx.commit().await?;
+
+    tracing::info!(kind = kind.as_str(), %target_uid, "applied a change");
+
+    Ok(())
+}
+
+/// Records a decision on a pending change, applying it if it was approved.
+pub async fn decide(
+    pool: &PgPool,
+    policy: &ApprovalPolicy,
+    user: Option<&str>,
+    change_uid: Uuid,
+    decision: Decision,
+) -> Result<DecisionOutcome> {
+    let mut tx = pool.begin().await?;
+
+    let Some(change) = crate::persistence::lock_pending_change(&mut tx, change_uid).await? else {
+        return Ok(DecisionOutcome::NotFound);
+    };
+
+    if let Err(violation) = policy.check_decision(user, &change.requested_by, decision) {
+        return Ok(DecisionOutcome::Forbidden(violation));
+    }
+
+    // `check_decision` has already ensured there is a user
+    let decided_by = user.unwrap_or_default();
+
+    if decision == Decision::Approve {
+        let kind = ChangeKind::from_column(&change.kind)
+            .ok_or_else(|| eyre!("unknown change kind '{}'", change.kind))?;
+
+        apply(&mut tx, kind, change.target_uid).await?;
+    }
+
+    crate::persistence::record_change_decision(&mut tx, change_uid, decision.status(), decided_by)
+        .await?;
+
+    tx.commit().await?;
+
+    tracing::info!(
+        %change_uid,
+        kind = %change.kind,
+        status = decision.status(),
+        %decided_by,
+        "decided on a pending change"
+    );
+
+    Ok(DecisionOutcome::Decided)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::approval::{ApprovalPolicy, Decision, PolicyViolation};
+
+    fn policy() -> ApprovalPolicy {
+        ApprovalPolicy::new(true, [String::from("alice"), String::from("bob")])
+    }
+
+    #[test]
+    fn only_admins_can_request_changes() {
+        let policy = policy();
+
+        assert_eq!(policy.check_request(Some("alice")), Ok(()));
+        assert_eq!(
+            policy.check_request(Some("mallory")),
+            Err(PolicyViolation::NotAdmin)
+        );
+        assert_eq!(
+            policy.check_request(None),
+            Err(PolicyViolation::Unauthenticated)
+        );
+    }
+
+    #[test]
+    fn changes_need_a_second_admin_to_approve_them() {
+        let policy = policy();
+
+        assert_eq!(
+            policy.check_decision(Some("alice"), "alice", Decision::Approve),
+            Err(PolicyViolation::SelfApproval)
+        );
+        assert_eq!(
+            policy.check_decision(Some("bob"), "alice", Decision::Approve),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn requesters_can_reject_their_own_changes() {
+        assert_eq!(
+            policy().check_decision(Some("alice"), "alice", Decision::Reject),
+            Ok(())
+        );
+    }
+}
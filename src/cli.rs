@@ -0,0 +1,432 @@
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use sqlx::PgPool;
+
+use crate::approval::{ApprovalPolicy, ChangeKind};
+use crate::config::{MonitorConfiguration, IMPORT_DRAFT_NAME};
+
+const USAGE: &str = "usage: uptime [doctor | export-config [PATH] | import-config [PATH] [--as ADMIN] | compact --older-than AGE [--resolution PERIOD]]";
+
+/// The period checks are rolled up into when compacting without a resolution.
+const DEFAULT_RESOLUTION: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// Runs the poller and web server.
+    Serve,
+    /// Checks the configuration, database, topic and network are ready for the server.
+    Doctor,
+    /// Writes the configuration to the path, or to stdout if none is given.
+    ExportConfig { path: Option<PathBuf> },
+    /// Reads a configuration from the path, or from stdin if none is given, and restores it.
+    /// Imports that would disable alerts are requested as the given admin when approval is required.
+    ImportConfig {
+        path: Option<PathBuf>,
+        requested_by: Option<String>,
+    },
+    /// Replaces checks older than a given age with one rollup per origin and period.
+    Compact {
+        older_than: Duration,
+        resolution: Duration,
+    },
+}
+
+impl Command {
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self> {
+        let mut args = args.into_iter();
+
+        let command = match args.next().as_deref() {
+            None => Self::Serve,
+            Some("doctor") => Self::Doctor,
+            Some("export-config") => Self::ExportConfig {
+                path: args.next().map(PathBuf::from),
+            },
+            Some("import-config") => {
+                let mut path = None;
+                let mut requested_by = None;
+
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--as" => {
+                            let admin = args
+                                .next()
+                                .ok_or_else(|| eyre!("'--as' needs a value, {USAGE}"))?;
+
+                            requested_by = Some(admin);
+                        }
+                        _ if path.is_none() => path = Some(PathBuf::from(arg)),
+                        _ => return Err(eyre!("unexpected argument '{arg}', {USAGE}")),
+                    }
+                }
+
+                Self::ImportConfig { path, requested_by }
+            }
+            Some("compact") => {
+                let mut older_than = None;
+                let mut resolution = DEFAULT_RESOLUTION;
+
+                while let Some(flag) = args.next() {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| eyre!("'{flag}' needs a value, {USAGE}"))?;
+
+                    match flag.as_str() {
+                        "--older-than" => older_than = Some(parse_duration(&value)?),
+                        "--resolution" => resolution = parse_duration(&value)?,
+                        _ => return Err(eyre!("unexpected argument '{flag}', {USAGE}")),
+                    }
+                }
+
+                let older_than =
+                    older_than.ok_or_else(|| eyre!("compact needs --older-than, {USAGE}"))?;
+
+                if resolution.as_secs() == 0 || i32::try_from(resolution.as_secs()).is_err() {
+                    return Err(eyre!(
+                        "the resolution must be between a second and a few decades"
+                    ));
+                }
+
+                Self::Compact {
+                    older_than,
+                    resolution,
+                }
+            }
+            Some(other) => return Err(eyre!("unknown command '{other}', {USAGE}")),
+        };
+
+        if let Some(extra) = args.next() {
+            return Err(eyre!("unexpected argument '{extra}', {USAGE}"));
+        }
+
+        Ok(command)
+    }
+}
+
+fn parse_duration(value: &str) -> Result<Duration> {
+    humantime::parse_duration(value)
+        .map_err(|e| eyre!("'{value}' is not a duration such as 90d: {e}"))
+}
+
+pub async fn export_config(pool: &PgPool, path: Option<PathBuf>) -> Result<()> {
+    let configuration = crate::config::export(pool).await?;
+    let serialized = serde_json::to_string_pretty(&configuration)?;
+
+    match path {
+        Some(path) => std::fs::write(path, serialized)?,
+        None => writeln!(std::io::stdout(), "{serialized}")?,
+    }
+
+    Ok(())
+}
+
+pub async fn import_config(
+    pool: &PgPool,
+    path: Option<PathBuf>,
+    requested_by: Option<String>,
+) -> Result<()> {
+    let serialized = match path {
+        Some(path) => std::fs::read_to_string(path)?,
+        None => {
+            let mut buffer = String::new();
+            std::io::stdin().read_to_string(&mut buffer)?;
+            buffer
+        }
+    };
+
+    let configuration: MonitorConfiguration = serde_json::from_str(&serialized)?;
+    let policy = ApprovalPolicy::from_env()?;
+
+    let imported = import(pool, &policy, requested_by.as_deref(), &configuration).await?;
+
+    if !imported {
+        writeln!(
+            std::io::stdout(),
+            "the import disables alerts, so it is waiting for a second admin to approve it"
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Imports a configuration, or holds it for a second admin to approve if it would disable alerts
+/// and the policy requires approval. Returns whether it was imported.
+async fn import(
+    pool: &PgPool,
+    policy: &ApprovalPolicy,
+    requested_by: Option<&str>,
+    configuration: &MonitorConfiguration,
+) -> Result<bool> {
+    configuration.check_version()?;
+
+    if !policy.required() || !crate::config::import_is_destructive(pool, configuration).await? {
+        crate::config::import(pool, configuration).await?;
+
+        return Ok(true);
+    }
+
+    policy
+        .check_request(requested_by)
+        .map_err(|violation| eyre!("{violation}, pass --as to request approval of the import"))?;
+
+    let requested_by = requested_by.unwrap_or_default();
+
+    // The configuration is kept as a draft until the import is approved
+    let draft_uid =
+        crate::persistence::insert_draft(pool, IMPORT_DRAFT_NAME, configuration).await?;
+
+    let description = format!(
+        "Import a configuration of {} origins",
+        configuration.origins.len()
+    );
+
+    let change_uid = crate::persistence::insert_pending_change(
+        pool,
+        ChangeKind::ImportConfiguration.as_str(),
+        draft_uid,
+        &description,
+        requested_by,
+    )
+    .await?;
+
+    tracing::info!(?change_uid, %draft_uid, %requested_by, "requested an import");
+
+    Ok(false)
+}
+
+/// The time checks are compacted before, rounded down to a whole number of periods so that each
+/// period is complete by the time it is rolled up.
+fn compaction_cutoff(
+    now: DateTime<Utc>,
+    older_than: Duration,
+    resolution: Duration,
+) -> Result<DateTime<Utc>> {
+    let cutoff = now - chrono::Duration::from_std(older_than)?;
+    let resolution_seconds = resolution.as_secs() as i64;
+    let timestamp = cutoff.timestamp() - cutoff.timestamp().rem_euclid(resolution_seconds);
+
+    DateTime::from_timestamp(timestamp, 0).ok_or_else(|| eyre!("--older-than is too long ago"))
+}
+
+pub async fn compact(pool: &PgPool, older_than: Duration, resolution: Duration) -> Result<()> {
+    let before = compaction_cutoff(Utc::now(), older_than, resolution)?;
+    let resolution_seconds = i64::try_from(resolution.as_secs())?;
+
+    let mut tx = pool.begin().await?;
+    let compaction =
+        crate::persistence::compact_checks(&mut tx, before, resolution_seconds).await?;
+    tx.commit().await?;
+
+    writeln!(
+        std::io::stdout(),
+        "compacted {} checks from before {before} into {} rollups",
+        compaction.checks,
+        compaction.rollups
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use chrono::{TimeZone, Utc};
+    use color_eyre::eyre::Result;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    use crate::approval::{ApprovalPolicy, Decision};
+    use crate::cli::Command;
+    use crate::persistence::Compaction;
+    use crate::poller::FailureReason;
+
+    fn parse(args: &[&str]) -> Option<Command> {
+        Command::parse(args.iter().map(ToString::to_string)).ok()
+    }
+
+    #[test]
+    fn no_arguments_runs_the_server() {
+        assert_eq!(parse(&[]), Some(Command::Serve));
+    }
+
+    #[test]
+    fn doctor_takes_no_arguments() {
+        assert_eq!(parse(&["doctor"]), Some(Command::Doctor));
+        assert_eq!(parse(&["doctor", "--fix"]), None);
+    }
+
+    #[test]
+    fn configuration_commands_take_an_optional_path() {
+        assert_eq!(
+            parse(&["export-config"]),
+            Some(Command::ExportConfig { path: None })
+        );
+
+        assert_eq!(
+            parse(&["import-config", "backup.json"]),
+            Some(Command::ImportConfig {
+                path: Some(PathBuf::from("backup.json")),
+                requested_by: None,
+            })
+        );
+
+        assert_eq!(
+            parse(&["import-config", "backup.json", "--as", "alice"]),
+            Some(Command::ImportConfig {
+                path: Some(PathBuf::from("backup.json")),
+                requested_by: Some(String::from("alice")),
+            })
+        );
+
+        assert_eq!(parse(&["import-config", "--as"]), None);
+    }
+
+    #[test]
+    fn unknown_arguments_are_rejected() {
+        assert_eq!(parse(&["frobnicate"]), None);
+        assert_eq!(parse(&["export-config", "a.json", "b.json"]), None);
+    }
+
+    #[test]
+    fn compaction_needs_an_age_and_defaults_to_hourly_rollups() {
+        assert_eq!(
+            parse(&["compact", "--older-than", "90d", "--resolution", "1day"]),
+            Some(Command::Compact {
+                older_than: Duration::from_secs(90 * 24 * 60 * 60),
+                resolution: Duration::from_secs(24 * 60 * 60),
+            })
+        );
+
+        assert_eq!(
+            parse(&["compact", "--older-than", "30d"]),
+            Some(Command::Compact {
+                older_than: Duration::from_secs(30 * 24 * 60 * 60),
+                resolution: Duration::from_secs(60 * 60),
+            })
+        );
+
+        assert_eq!(parse(&["compact"]), None);
+        assert_eq!(parse(&["compact", "--older-than"]), None);
+        assert_eq!(parse(&["compact", "--older-than", "soon"]), None);
+        assert_eq!(
+            parse(&["compact", "--older-than", "90d", "--resolution", "0s"]),
+            None
+        );
+    }
+
+    #[test]
+    fn compaction_stops_at_the_start_of_a_period() -> Result<()> {
+        let now = Utc.with_ymd_and_hms(2026, 10, 15, 12, 34, 56).unwrap();
+        let cutoff = super::compaction_cutoff(
+            now,
+            Duration::from_secs(24 * 60 * 60),
+            Duration::from_secs(60 * 60),
+        )?;
+
+        assert_eq!(
+            cutoff,
+            Utc.with_ymd_and_hms(2026, 10, 14, 12, 0, 0).unwrap()
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn old_checks_are_compacted_without_changing_reports(pool: PgPool) -> Result<()> {
+        let origin_uid = Uuid::new_v4();
+        crate::persistence::insert_origin(&pool, origin_uid, "https://example.com").await?;
+
+        let old = Utc.with_ymd_and_hms(2026, 1, 1, 10, 0, 0).unwrap();
+        let recent = Utc::now() - chrono::Duration::hours(1);
+
+        let mut tx = pool.begin().await?;
+
+        for minutes in [5, 25, 65] {
+            let queried_at = old + chrono::Duration::minutes(minutes);
+            crate::persistence::insert_query(&mut tx, origin_uid, Some(200), 40, queried_at)
+                .await?;
+        }
+
+        crate::persistence::insert_query_failure(
+            &mut tx,
+            origin_uid,
+            FailureReason::RequestTimeout,
+            None,
+            old + chrono::Duration::minutes(45),
+        )
+        .await?;
+
+        crate::persistence::insert_query(&mut tx, origin_uid, Some(200), 40, recent).await?;
+        tx.commit().await?;
+
+        let start = old - chrono::Duration::days(1);
+        let end = Utc::now();
+        let before = crate::persistence::fetch_origin_reports(&pool, start, end).await?;
+
+        let mut tx = pool.begin().await?;
+        let compaction = crate::persistence::compact_checks(
+            &mut tx,
+            Utc::now() - chrono::Duration::days(1),
+            60 * 60,
+        )
+        .await?;
+        tx.commit().await?;
+
+        // Two hours of old checks become two rollups, and the recent check is kept as it is
+        assert_eq!(
+            compaction,
+            Compaction {
+                checks: 4,
+                rollups: 2
+            }
+        );
+
+        let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 10).await?;
+        assert_eq!(checks.len(), 1);
+
+        let after = crate::persistence::fetch_origin_reports(&pool, start, end).await?;
+        assert_eq!(after[0].successes, before[0].successes);
+        assert_eq!(after[0].failures, before[0].failures);
+        assert_eq!((after[0].successes, after[0].failures), (4, 1));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn imports_that_disable_alerts_are_held_for_approval(pool: PgPool) -> Result<()> {
+        let origin_uid = Uuid::new_v4();
+        crate::persistence::insert_origin(&pool, origin_uid, "https://example.com").await?;
+
+        let mut configuration = crate::config::export(&pool).await?;
+        configuration.origins[0].alerts_enabled = false;
+
+        let policy = ApprovalPolicy::new(true, [String::from("alice"), String::from("bob")]);
+
+        // Imports that need approval have to say who is requesting them
+        assert!(super::import(&pool, &policy, None, &configuration)
+            .await
+            .is_err());
+
+        assert!(!super::import(&pool, &policy, Some("alice"), &configuration).await?);
+
+        let changes = crate::persistence::fetch_pending_changes(&pool, 10).await?;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, "import_configuration");
+        assert_eq!(changes[0].status, "pending");
+
+        // Nothing is imported until a second admin approves it
+        assert!(crate::config::export(&pool).await?.origins[0].alerts_enabled);
+
+        let change_uid = changes[0].change_uid;
+        crate::approval::decide(&pool, &policy, Some("bob"), change_uid, Decision::Approve).await?;
+
+        assert!(!crate::config::export(&pool).await?.origins[0].alerts_enabled);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,904 @@
+use std::collections::HashMap;
+
+use color_eyre::eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::persistence::{
+    HeartbeatConfiguration, JourneyStepConfiguration, MaintenanceWindow, OriginConfiguration,
+    Transaction, UserDestinationConfiguration,
+};
+
+/// The version of the configuration format written by this build.
+const CURRENT_VERSION: u32 = 1;
+
+/// The name of the drafts imports are held in while they wait for approval.
+pub const IMPORT_DRAFT_NAME: &str = "Imported configuration";
+
+/// A complete snapshot of how the monitor is configured, used for backups and for cloning one
+/// environment into another.
+///
+/// Journey steps, heartbeats, maintenance windows and personal destinations are carried alongside
+/// the origins. Importing only touches those the configuration mentions, so a journey's steps are
+/// replaced only if the configuration lists steps for it.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MonitorConfiguration {
+    pub version: u32,
+    #[serde(default)]
+    pub origins: Vec<OriginConfiguration>,
+    #[serde(default)]
+    pub journey_steps: Vec<JourneyStepConfiguration>,
+    #[serde(default)]
+    pub heartbeats: Vec<HeartbeatConfiguration>,
+    #[serde(default)]
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+    #[serde(default)]
+    pub user_destinations: Vec<UserDestinationConfiguration>,
+}
+
+impl MonitorConfiguration {
+    /// Ensures the configuration was written in a format this build understands.
+    pub fn check_version(&self) -> Result<()> {
+        if self.version == 0 || self.version > CURRENT_VERSION {
+            return Err(eyre!(
+                "unsupported configuration version {} (expected at most {CURRENT_VERSION})",
+                self.version
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+pub async fn export(pool: &PgPool) -> Result<MonitorConfiguration> {
+    let origins = crate::persistence::fetch_origin_configurations(pool).await?;
+    let journey_steps = crate::persistence::fetch_journey_step_configurations(pool).await?;
+    let heartbeats = crate::persistence::fetch_heartbeat_configurations(pool).await?;
+    let maintenance_windows = crate::persistence::fetch_maintenance_windows(pool).await?;
+    let user_destinations = crate::persistence::fetch_user_destination_configurations(pool).await?;
+
+    Ok(MonitorConfiguration {
+        version: CURRENT_VERSION,
+        origins,
+        journey_steps,
+        heartbeats,
+        maintenance_windows,
+        user_destinations,
+    })
+}
+
+/// Restores everything configured alongside the origins, once the origins themselves exist.
+async fn import_related(tx: &mut Transaction, configuration: &MonitorConfiguration) -> Result<()> {
+    let mut journeys: HashMap<Uuid, Vec<_>> = HashMap::new();
+
+    for JourneyStepConfiguration { origin_uid, step } in &configuration.journey_steps {
+        journeys.entry(*origin_uid).or_default().push(step);
+    }
+
+    for (origin_uid, steps) in journeys {
+        crate::persistence::replace_journey_steps(tx, origin_uid, &steps).await?;
+    }
+
+    for heartbeat in &configuration.heartbeats {
+        crate::persistence::upsert_heartbeat_configuration(tx, heartbeat).await?;
+    }
+
+    for window in &configuration.maintenance_windows {
+        crate::persistence::upsert_maintenance_window(tx, window).await?;
+    }
+
+    for destination in &configuration.user_destinations {
+        crate::persistence::upsert_user_destination_configuration(tx, destination).await?;
+    }
+
+    Ok(())
+}
+
+/// Restores a configuration, creating any origins that are missing and overwriting the settings of
+/// those that already exist. Either everything is imported or nothing is.
+pub async fn import(pool: &PgPool, configuration: &MonitorConfiguration) -> Result<()> {
+    configuration.check_version()?;
+
+    let mut tx = pool.begin().await?;
+
+    for origin in &configuration.origins {
+        crate::persistence::upsert_origin_configuration(&mut tx, origin).await?;
+    }
+
+    import_related(&mut tx, configuration).await?;
+
+    tx.commit().await?;
+
+    tracing::info!(
+        origins = configuration.origins.len(),
+        "imported the monitor configuration"
+    );
+
+    Ok(())
+}
+
+/// Whether importing a configuration would stop alerts from being sent for any origin, in which
+/// case it may need approving first. Imports never remove origins, so only changes count.
+pub async fn import_is_destructive(
+    pool: &PgPool,
+    configuration: &MonitorConfiguration,
+) -> Result<bool> {
+    let live = crate::persistence::fetch_origin_configurations(pool).await?;
+
+    let diff = ConfigurationDiff {
+        removed: Vec::new(),
+        ..diff(&live, &configuration.origins)
+    };
+
+    Ok(diff.is_destructive())
+}
+
+/// Imports a configuration that was held as a draft while it waited for approval, marking the
+/// draft as promoted so it cannot be imported twice.
+///
+/// Returns `false` if the draft does not exist or has already been promoted.
+pub async fn import_draft(tx: &mut Transaction, draft_uid: Uuid) -> Result<bool> {
+    let Some(configuration) = crate::persistence::lock_unpromoted_draft(tx, draft_uid).await?
+    else {
+        return Ok(false);
+    };
+
+    configuration.check_version()?;
+
+    for origin in &configuration.origins {
+        crate::persistence::upsert_origin_configuration(tx, origin).await?;
+    }
+
+    import_related(tx, &configuration).await?;
+
+    crate::persistence::mark_draft_promoted(tx, draft_uid).await?;
+
+    tracing::info!(
+        %draft_uid,
+        origins = configuration.origins.len(),
+        "imported an approved configuration"
+    );
+
+    Ok(true)
+}
+
+/// A single setting that differs between two versions of an origin.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct OriginChange {
+    pub origin_uid: Uuid,
+    pub uri: String,
+    pub changes: Vec<FieldChange>,
+}
+
+/// The changes that would be made to the live configuration by promoting a draft.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct ConfigurationDiff {
+    pub added: Vec<OriginConfiguration>,
+    pub removed: Vec<OriginConfiguration>,
+    pub changed: Vec<OriginChange>,
+}
+
+impl ConfigurationDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Whether promoting would remove origins or stop alerts from being sent for them, either by
+    /// disabling their alerts or by pausing them so they are no longer checked.
+    pub fn is_destructive(&self) -> bool {
+        let disables_alerts = self.changed.iter().any(|origin| {
+            origin.changes.iter().any(|change| {
+                (change.field == "alerts_enabled" && change.to == "false")
+                    || (change.field == "paused" && change.to == "true")
+            })
+        });
+
+        !self.removed.is_empty() || disables_alerts
+    }
+}
+
+fn format_cost(cost: Option<i64>) -> String {
+    cost.map_or_else(|| String::from("none"), crate::cost::format_cents)
+}
+
+fn format_optional(value: Option<&str>) -> String {
+    value.map_or_else(|| String::from("none"), str::to_owned)
+}
+
+fn compare(live: &OriginConfiguration, draft: &OriginConfiguration) -> Vec<FieldChange> {
+    let fields = [
+        ("uri", live.uri.clone(), draft.uri.clone()),
+        (
+            "visibility",
+            live.visibility.as_str().to_owned(),
+            draft.visibility.as_str().to_owned(),
+        ),
+        ("pinned", live.pinned.to_string(), draft.pinned.to_string()),
+        (
+            "display_order",
+            live.display_order.to_string(),
+            draft.display_order.to_string(),
+        ),
+        (
+            "cost_per_minute",
+            format_cost(live.cost_per_minute_cents),
+            format_cost(draft.cost_per_minute_cents),
+        ),
+        (
+            "alerts_enabled",
+            live.alerts_enabled.to_string(),
+            draft.alerts_enabled.to_string(),
+        ),
+        ("tags", live.tags.join(", "), draft.tags.join(", ")),
+        (
+            "method",
+            live.method.as_str().to_owned(),
+            draft.method.as_str().to_owned(),
+        ),
+        (
+            "request_body",
+            format_optional(live.request_body.as_deref()),
+            format_optional(draft.request_body.as_deref()),
+        ),
+        (
+            "request_content_type",
+            format_optional(live.request_content_type.as_deref()),
+            format_optional(draft.request_content_type.as_deref()),
+        ),
+        (
+            "forbidden_text",
+            format_optional(live.forbidden_text.as_deref()),
+            format_optional(draft.forbidden_text.as_deref()),
+        ),
+        (
+            "json_assertion",
+            format_optional(live.json_assertion.as_deref()),
+            format_optional(draft.json_assertion.as_deref()),
+        ),
+        (
+            "notes",
+            format_optional(live.notes.as_deref()),
+            format_optional(draft.notes.as_deref()),
+        ),
+        (
+            "expected_status",
+            format_optional(live.expected_status.as_deref()),
+            format_optional(draft.expected_status.as_deref()),
+        ),
+        (
+            "baseline_origin_uid",
+            format_optional(
+                live.baseline_origin_uid
+                    .map(|uid| uid.to_string())
+                    .as_deref(),
+            ),
+            format_optional(
+                draft
+                    .baseline_origin_uid
+                    .map(|uid| uid.to_string())
+                    .as_deref(),
+            ),
+        ),
+        (
+            "relative_latency_limit_millis",
+            format_optional(
+                live.relative_latency_limit_millis
+                    .map(|limit| limit.to_string())
+                    .as_deref(),
+            ),
+            format_optional(
+                draft
+                    .relative_latency_limit_millis
+                    .map(|limit| limit.to_string())
+                    .as_deref(),
+            ),
+        ),
+        (
+            "cache_validation",
+            live.cache_validation.to_string(),
+            draft.cache_validation.to_string(),
+        ),
+        (
+            "redirect_limit",
+            format_optional(
+                live.redirect_limit
+                    .map(|limit| limit.to_string())
+                    .as_deref(),
+            ),
+            format_optional(
+                draft
+                    .redirect_limit
+                    .map(|limit| limit.to_string())
+                    .as_deref(),
+            ),
+        ),
+        (
+            "expected_location",
+            format_optional(live.expected_location.as_deref()),
+            format_optional(draft.expected_location.as_deref()),
+        ),
+        (
+            "sibling_group_uid",
+            format_optional(live.sibling_group_uid.map(|uid| uid.to_string()).as_deref()),
+            format_optional(
+                draft
+                    .sibling_group_uid
+                    .map(|uid| uid.to_string())
+                    .as_deref(),
+            ),
+        ),
+        (
+            "timeout_millis",
+            format_optional(
+                live.timeout_millis
+                    .map(|timeout| timeout.to_string())
+                    .as_deref(),
+            ),
+            format_optional(
+                draft
+                    .timeout_millis
+                    .map(|timeout| timeout.to_string())
+                    .as_deref(),
+            ),
+        ),
+        (
+            "address_family",
+            live.address_family.as_str().to_owned(),
+            draft.address_family.as_str().to_owned(),
+        ),
+        (
+            "adaptive_polling",
+            live.adaptive_polling.to_string(),
+            draft.adaptive_polling.to_string(),
+        ),
+        (
+            "proxy",
+            format_optional(live.proxy.as_deref()),
+            format_optional(draft.proxy.as_deref()),
+        ),
+        (
+            "expected_networks",
+            format_optional(live.expected_networks.as_deref()),
+            format_optional(draft.expected_networks.as_deref()),
+        ),
+        (
+            "http_version",
+            live.http_version.as_str().to_owned(),
+            draft.http_version.as_str().to_owned(),
+        ),
+        (
+            "retries",
+            live.retries.to_string(),
+            draft.retries.to_string(),
+        ),
+        (
+            "captured_headers",
+            format_optional(live.captured_headers.as_deref()),
+            format_optional(draft.captured_headers.as_deref()),
+        ),
+        (
+            "alert_window",
+            live.alert_window.as_str().to_owned(),
+            draft.alert_window.as_str().to_owned(),
+        ),
+        (
+            "probe_locations",
+            format_optional(live.probe_locations.as_deref()),
+            format_optional(draft.probe_locations.as_deref()),
+        ),
+        (
+            "user_agent",
+            format_optional(live.user_agent.as_deref()),
+            format_optional(draft.user_agent.as_deref()),
+        ),
+        (
+            "cache_busting",
+            live.cache_busting.to_string(),
+            draft.cache_busting.to_string(),
+        ),
+        (
+            "connection_mode",
+            live.connection_mode.as_str().to_owned(),
+            draft.connection_mode.as_str().to_owned(),
+        ),
+        (
+            "expected_headers",
+            format_optional(live.expected_headers.as_deref()),
+            format_optional(draft.expected_headers.as_deref()),
+        ),
+        (
+            "body_change_detection",
+            live.body_change_detection.to_string(),
+            draft.body_change_detection.to_string(),
+        ),
+        (
+            "schedule",
+            format_optional(live.schedule.as_deref()),
+            format_optional(draft.schedule.as_deref()),
+        ),
+        (
+            "expected_latency_millis",
+            format_optional(
+                live.expected_latency_millis
+                    .map(|latency| latency.to_string())
+                    .as_deref(),
+            ),
+            format_optional(
+                draft
+                    .expected_latency_millis
+                    .map(|latency| latency.to_string())
+                    .as_deref(),
+            ),
+        ),
+        ("paused", live.paused.to_string(), draft.paused.to_string()),
+    ];
+
+    fields
+        .into_iter()
+        .filter(|(_, from, to)| from != to)
+        .map(|(field, from, to)| FieldChange { field, from, to })
+        .collect()
+}
+
+pub fn diff(live: &[OriginConfiguration], draft: &[OriginConfiguration]) -> ConfigurationDiff {
+    let live_by_uid: HashMap<_, _> = live.iter().map(|o| (o.origin_uid, o)).collect();
+    let draft_by_uid: HashMap<_, _> = draft.iter().map(|o| (o.origin_uid, o)).collect();
+
+    let mut diff = ConfigurationDiff::default();
+
+    for origin in draft {
+        match live_by_uid.get(&origin.origin_uid) {
+            None => diff.added.push(origin.clone()),
+            Some(existing) => {
+                let changes = compare(existing, origin);
+
+                if !changes.is_empty() {
+                    diff.changed.push(OriginChange {
+                        origin_uid: origin.origin_uid,
+                        uri: origin.uri.clone(),
+                        changes,
+                    });
+                }
+            }
+        }
+    }
+
+    diff.removed = live
+        .iter()
+        .filter(|origin| !draft_by_uid.contains_key(&origin.origin_uid))
+        .cloned()
+        .collect();
+
+    diff
+}
+
+/// Replaces the live configuration with the contents of a draft, removing any origins the draft
+/// does not contain.
+///
+/// Returns `false` if the draft does not exist or has already been promoted.
+pub async fn promote(tx: &mut Transaction, draft_uid: Uuid) -> Result<bool> {
+    let Some(configuration) = crate::persistence::lock_unpromoted_draft(tx, draft_uid).await?
+    else {
+        return Ok(false);
+    };
+
+    configuration.check_version()?;
+
+    let retained: Vec<_> = configuration
+        .origins
+        .iter()
+        .map(|origin| origin.origin_uid)
+        .collect();
+
+    let removed = crate::persistence::delete_origins_except(tx, &retained).await?;
+
+    for origin in &configuration.origins {
+        crate::persistence::upsert_origin_configuration(tx, origin).await?;
+    }
+
+    import_related(tx, &configuration).await?;
+
+    crate::persistence::mark_draft_promoted(tx, draft_uid).await?;
+
+    tracing::info!(
+        %draft_uid,
+        origins = configuration.origins.len(),
+        %removed,
+        "promoted a configuration draft"
+    );
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use color_eyre::eyre::Result;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    use crate::config::{FieldChange, MonitorConfiguration, CURRENT_VERSION};
+    use crate::locale::Locale;
+    use crate::persistence::{
+        AddressFamily, AlertWindow, ConnectionMode, DestinationKind, HeartbeatConfiguration,
+        HttpMethod, HttpVersion, JourneyStep, JourneyStepConfiguration, MaintenanceMode,
+        MaintenanceWindow, OriginConfiguration, Recurrence, UserDestination,
+        UserDestinationConfiguration, Visibility,
+    };
+
+    fn origin(uri: &str) -> OriginConfiguration {
+        OriginConfiguration {
+            origin_uid: Uuid::new_v4(),
+            uri: uri.to_owned(),
+            visibility: Visibility::Private,
+            pinned: false,
+            display_order: 1,
+            cost_per_minute_cents: None,
+            alerts_enabled: true,
+            tags: Vec::new(),
+            method: HttpMethod::Get,
+            request_body: None,
+            request_content_type: None,
+            forbidden_text: None,
+            json_assertion: None,
+            notes: None,
+            expected_status: None,
+            baseline_origin_uid: None,
+            relative_latency_limit_millis: None,
+            cache_validation: false,
+            redirect_limit: None,
+            expected_location: None,
+            sibling_group_uid: None,
+            timeout_millis: None,
+            address_family: AddressFamily::Any,
+            adaptive_polling: false,
+            proxy: None,
+            expected_networks: None,
+            http_version: HttpVersion::Auto,
+            retries: 0,
+            captured_headers: None,
+            alert_window: AlertWindow::Rolling,
+            probe_locations: None,
+            user_agent: None,
+            cache_busting: false,
+            connection_mode: ConnectionMode::Reused,
+            expected_headers: None,
+            body_change_detection: false,
+            schedule: None,
+            expected_latency_millis: None,
+            paused: false,
+            encrypted_credentials: None,
+            encrypted_client_identity: None,
+        }
+    }
+
+    fn step(url: &str, extraction: Option<&str>) -> JourneyStep {
+        JourneyStep {
+            journey_step_uid: Uuid::new_v4(),
+            method: HttpMethod::Get,
+            url: url.to_owned(),
+            request_headers: None,
+            request_body: None,
+            request_content_type: None,
+            expected_status: Some(String::from("200")),
+            extraction: extraction.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn diffs_report_added_removed_and_changed_origins() {
+        let kept = origin("https://kept.com");
+        let removed = origin("https://removed.com");
+        let added = origin("https://added.com");
+
+        let mut changed = origin("https://changed.com");
+        let live = vec![kept.clone(), removed.clone(), changed.clone()];
+
+        changed.visibility = Visibility::Public;
+        changed.cost_per_minute_cents = Some(150);
+        let draft = vec![kept, changed.clone(), added.clone()];
+
+        let diff = super::diff(&live, &draft);
+
+        assert_eq!(diff.added, vec![added]);
+        assert_eq!(diff.removed, vec![removed]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].origin_uid, changed.origin_uid);
+        assert_eq!(
+            diff.changed[0].changes,
+            vec![
+                FieldChange {
+                    field: "visibility",
+                    from: String::from("private"),
+                    to: String::from("public"),
+                },
+                FieldChange {
+                    field: "cost_per_minute",
+                    from: String::from("none"),
+                    to: String::from("1.50"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn removing_origins_or_disabling_alerts_is_destructive() {
+        let mut quiet = origin("https://quiet.com");
+        let live = vec![origin("https://removed.com"), quiet.clone()];
+
+        assert!(super::diff(&live, &[quiet.clone()]).is_destructive());
+
+        quiet.alerts_enabled = false;
+        assert!(super::diff(&live[1..], &[quiet.clone()]).is_destructive());
+
+        let pinned = OriginConfiguration {
+            pinned: true,
+            ..quiet.clone()
+        };
+        assert!(!super::diff(&[quiet], &[pinned]).is_destructive());
+    }
+
+    #[test]
+    fn pausing_origins_is_destructive() {
+        let live = vec![origin("https://example.com")];
+        let paused = vec![OriginConfiguration {
+            paused: true,
+            ..live[0].clone()
+        }];
+
+        assert!(super::diff(&live, &paused).is_destructive());
+
+        // Resuming them starts checking them again, so it is not
+        assert!(!super::diff(&paused, &live).is_destructive());
+    }
+
+    #[test]
+    fn identical_configurations_have_no_diff() {
+        let live = vec![origin("https://example.com")];
+
+        assert!(super::diff(&live, &live).is_empty());
+    }
+
+    #[test]
+    fn unknown_versions_are_rejected() {
+        let configuration = MonitorConfiguration {
+            version: CURRENT_VERSION + 1,
+            ..Default::default()
+        };
+
+        assert!(configuration.check_version().is_err());
+    }
+
+    #[sqlx::test]
+    async fn configuration_survives_a_round_trip(pool: PgPool) -> Result<()> {
+        // Referenced before it is created, which the deferred foreign key allows
+        let baseline_origin_uid = Uuid::new_v4();
+
+        let configuration = MonitorConfiguration {
+            version: CURRENT_VERSION,
+            origins: vec![
+                OriginConfiguration {
+                    origin_uid: Uuid::new_v4(),
+                    uri: String::from("https://example.com"),
+                    visibility: Visibility::Public,
+                    pinned: true,
+                    display_order: 1,
+                    cost_per_minute_cents: Some(2500),
+                    alerts_enabled: true,
+                    tags: vec![String::from("production")],
+                    method: HttpMethod::Post,
+                    request_body: Some(String::from("{}")),
+                    request_content_type: Some(String::from("application/json")),
+                    forbidden_text: Some(String::from("maintenance mode")),
+                    json_assertion: Some(String::from(r#"$.status == "ok""#)),
+                    notes: Some(String::from("Behind Cloudflare")),
+                    expected_status: Some(String::from("200-299,301")),
+                    baseline_origin_uid: Some(baseline_origin_uid),
+                    relative_latency_limit_millis: Some(250),
+                    cache_validation: true,
+                    redirect_limit: Some(0),
+                    expected_location: Some(String::from("https://www.example.com/")),
+                    sibling_group_uid: Some(Uuid::new_v4()),
+                    timeout_millis: Some(10_000),
+                    address_family: AddressFamily::Both,
+                    adaptive_polling: true,
+                    proxy: Some(String::from("socks5h://bastion.internal:1080")),
+                    expected_networks: Some(String::from("203.0.113.0/24")),
+                    http_version: HttpVersion::Http2,
+                    retries: 2,
+                    captured_headers: Some(String::from("X-Request-Id, Server")),
+                    alert_window: AlertWindow::Month,
+                    probe_locations: Some(String::from("local, eu-west")),
+                    user_agent: Some(String::from(
+                        "uptime-probe/1.0 (+https://status.example.com)",
+                    )),
+                    cache_busting: true,
+                    connection_mode: ConnectionMode::Both,
+                    expected_headers: Some(String::from("Strict-Transport-Security")),
+                    body_change_detection: true,
+                    schedule: Some(String::from("*/5 9-17 * * 1-5")),
+                    expected_latency_millis: Some(250),
+                    paused: false,
+                    encrypted_credentials: Some(vec![1, 2, 3, 4]),
+                    encrypted_client_identity: Some(vec![5, 6, 7, 8]),
+                },
+                OriginConfiguration {
+                    origin_uid: baseline_origin_uid,
+                    uri: String::from("https://example.org"),
+                    visibility: Visibility::Private,
+                    pinned: false,
+                    display_order: 2,
+                    cost_per_minute_cents: None,
+                    alerts_enabled: false,
+                    tags: Vec::new(),
+                    method: HttpMethod::Get,
+                    request_body: None,
+                    request_content_type: None,
+                    forbidden_text: None,
+                    json_assertion: None,
+                    notes: None,
+                    expected_status: None,
+                    baseline_origin_uid: None,
+                    relative_latency_limit_millis: None,
+                    cache_validation: false,
+                    redirect_limit: None,
+                    expected_location: None,
+                    sibling_group_uid: None,
+                    timeout_millis: None,
+                    address_family: AddressFamily::Any,
+                    adaptive_polling: false,
+                    proxy: None,
+                    expected_networks: None,
+                    http_version: HttpVersion::Auto,
+                    retries: 0,
+                    captured_headers: None,
+                    alert_window: AlertWindow::Rolling,
+                    probe_locations: None,
+                    user_agent: None,
+                    cache_busting: false,
+                    connection_mode: ConnectionMode::Reused,
+                    expected_headers: None,
+                    body_change_detection: false,
+                    schedule: None,
+                    expected_latency_millis: None,
+                    paused: true,
+                    encrypted_credentials: None,
+                    encrypted_client_identity: None,
+                },
+            ],
+            journey_steps: vec![
+                JourneyStepConfiguration {
+                    origin_uid: baseline_origin_uid,
+                    step: step("https://example.org/login", Some("token = $.access_token")),
+                },
+                JourneyStepConfiguration {
+                    origin_uid: baseline_origin_uid,
+                    step: step("https://example.org/account", None),
+                },
+            ],
+            heartbeats: vec![HeartbeatConfiguration {
+                origin_uid: baseline_origin_uid,
+                token: String::from("nightly-backup"),
+                grace_seconds: 300,
+            }],
+            maintenance_windows: vec![
+                MaintenanceWindow {
+                    maintenance_window_uid: Uuid::new_v4(),
+                    origin_uid: Some(baseline_origin_uid),
+                    tag: None,
+                    starts_at: Utc.with_ymd_and_hms(2026, 10, 17, 2, 0, 0).unwrap(),
+                    ends_at: Utc.with_ymd_and_hms(2026, 10, 17, 3, 0, 0).unwrap(),
+                    recurrence: Recurrence::Weekly,
+                    mode: MaintenanceMode::Silence,
+                    description: Some(String::from("Database upgrades")),
+                },
+                MaintenanceWindow {
+                    maintenance_window_uid: Uuid::new_v4(),
+                    origin_uid: None,
+                    tag: Some(String::from("production")),
+                    starts_at: Utc.with_ymd_and_hms(2026, 10, 18, 2, 0, 0).unwrap(),
+                    ends_at: Utc.with_ymd_and_hms(2026, 10, 18, 2, 30, 0).unwrap(),
+                    recurrence: Recurrence::Once,
+                    mode: MaintenanceMode::Skip,
+                    description: None,
+                },
+            ],
+            user_destinations: vec![UserDestinationConfiguration {
+                username: String::from("alice"),
+                destination: UserDestination {
+                    kind: DestinationKind::Slack,
+                    target: String::from("https://hooks.slack.com/services/T0/B0/x"),
+                    locale: Some(Locale::De),
+                },
+            }],
+        };
+
+        super::import(&pool, &configuration).await?;
+
+        // Importing twice should update the existing origins rather than duplicating them
+        super::import(&pool, &configuration).await?;
+
+        let exported = super::export(&pool).await?;
+        assert_eq!(exported, configuration);
+
+        // The export should also survive being written out and read back in
+        let serialized = serde_json::to_string(&exported)?;
+        assert_eq!(
+            serde_json::from_str::<MonitorConfiguration>(&serialized)?,
+            configuration
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn importing_without_credentials_keeps_the_existing_ones(pool: PgPool) -> Result<()> {
+        let mut protected = origin("https://staging.example.com");
+        protected.encrypted_credentials = Some(vec![1, 2, 3, 4]);
+
+        let configuration = MonitorConfiguration {
+            version: CURRENT_VERSION,
+            origins: vec![protected.clone()],
+            ..Default::default()
+        };
+
+        super::import(&pool, &configuration).await?;
+
+        let configuration = MonitorConfiguration {
+            version: CURRENT_VERSION,
+            origins: vec![OriginConfiguration {
+                encrypted_credentials: None,
+                ..protected.clone()
+            }],
+            ..Default::default()
+        };
+
+        super::import(&pool, &configuration).await?;
+
+        assert_eq!(super::export(&pool).await?.origins, [protected]);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn promoting_a_draft_replaces_the_live_configuration(pool: PgPool) -> Result<()> {
+        let removed = origin("https://removed.com");
+        let kept = origin("https://kept.com");
+
+        let live = MonitorConfiguration {
+            version: CURRENT_VERSION,
+            origins: vec![removed, kept.clone()],
+            ..Default::default()
+        };
+
+        super::import(&pool, &live).await?;
+
+        let draft = MonitorConfiguration {
+            version: CURRENT_VERSION,
+            origins: vec![kept, origin("https://added.com")],
+            ..Default::default()
+        };
+
+        let draft_uid = crate::persistence::insert_draft(&pool, "cleanup", &draft).await?;
+
+        let mut tx = pool.begin().await?;
+        assert!(super::promote(&mut tx, draft_uid).await?);
+        tx.commit().await?;
+
+        assert_eq!(super::export(&pool).await?, draft);
+
+        // Drafts can only be promoted once
+        let mut tx = pool.begin().await?;
+        assert!(!super::promote(&mut tx, draft_uid).await?);
+
+        Ok(())
+    }
+}
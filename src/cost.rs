@@ -0,0 +1,69 @@
+use chrono::Duration;
+
+/// Estimates the cost of being down for `downtime`, rounding to the nearest cent.
+pub fn estimate(downtime: Duration, cost_per_minute_cents: i64) -> i64 {
+    let minutes = downtime.num_milliseconds() as f64 / 60_000.0;
+
+    (minutes * cost_per_minute_cents as f64).round() as i64
+}
+
+/// Converts a user supplied amount, such as `12.50`, into cents, or `None` if it is negative or
+/// not a number.
+pub fn to_cents(amount: f64) -> Option<i64> {
+    let cents = (amount * 100.0).round();
+
+    (cents.is_finite() && (0.0..=i64::MAX as f64).contains(&cents)).then_some(cents as i64)
+}
+
+/// Formats an amount in cents for display, such as `1,234.50`.
+pub fn format_cents(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.unsigned_abs();
+
+    let whole = (cents / 100).to_string();
+    let mut grouped = String::new();
+
+    // Separators go before every digit that starts a group of three from the right
+    let first_group = whole.len() % 3;
+
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && i % 3 == first_group {
+            grouped.push(',');
+        }
+
+        grouped.push(digit);
+    }
+
+    format!("{sign}{grouped}.{:02}", cents % 100)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    #[test]
+    fn costs_scale_with_downtime() {
+        assert_eq!(super::estimate(Duration::minutes(10), 250), 2500);
+        assert_eq!(super::estimate(Duration::seconds(90), 100), 150);
+    }
+
+    #[test]
+    fn amounts_are_converted_to_cents() {
+        assert_eq!(super::to_cents(12.5), Some(1250));
+        assert_eq!(super::to_cents(0.1 + 0.2), Some(30));
+        assert_eq!(super::to_cents(0.0), Some(0));
+
+        assert_eq!(super::to_cents(-0.5), None);
+        assert_eq!(super::to_cents(f64::NAN), None);
+        assert_eq!(super::to_cents(f64::INFINITY), None);
+        assert_eq!(super::to_cents(1e300), None);
+    }
+
+    #[test]
+    fn cents_are_formatted_with_separators() {
+        assert_eq!(super::format_cents(5), "0.05");
+        assert_eq!(super::format_cents(123_456), "1,234.56");
+        assert_eq!(super::format_cents(100_000_000), "1,000,000.00");
+        assert_eq!(super::format_cents(-1050), "-10.50");
+    }
+}
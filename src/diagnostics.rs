@@ -0,0 +1,349 @@
+use std::collections::VecDeque;
+use std::fmt::{Debug, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use serde::Serialize;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::Layer;
+
+/// The version of the application, from its manifest.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The commit the application was built from, if `GIT_COMMIT` was set when building it.
+pub const COMMIT: Option<&str> = option_env!("GIT_COMMIT");
+
+/// How many internal errors are kept for the diagnostics page if `RECENT_ERROR_LIMIT` is not set.
+const DEFAULT_RECENT_ERROR_LIMIT: usize = 50;
+
+/// The environment variables the application reads, and whether their values are secret. Anything
+/// that may embed credentials, such as a URL, counts as secret.
+const VARIABLES: &[(&str, bool)] = &[
+    ("ADMIN_USERS", false),
+    ("APP_DATABASE", false),
+    ("APP_PASSWORD", true),
+    ("APP_USERNAME", false),
+    ("CHECK_JITTER_MILLIS", false),
+    ("CHECK_PROXY", true),
+    ("COMMAND_CHECKS_DIR", false),
+    ("CREDENTIALS_KEY", true),
+    ("DATABASE_HOST", false),
+    ("DATABASE_PORT", false),
+    ("DEFAULT_TIMEOUT_MILLIS", false),
+    ("DOCTOR_REACHABILITY_URL", false),
+    ("DOMAIN_EXPIRY_WARN_DAYS", false),
+    ("EMBED_FRAME_ANCESTORS", false),
+    ("EVENT_BUS_URL", true),
+    ("FAILURE_DIAGNOSTICS", false),
+    ("FALLBACK_WEBHOOK_URL", true),
+    ("FIREHOSE_URL", true),
+    ("LOCALE", false),
+    ("MAINTENANCE_TIME", false),
+    ("NOTIFIER", false),
+    ("POLL_CONCURRENCY", false),
+    ("RDAP_URL", false),
+    ("RECENT_ERROR_LIMIT", false),
+    ("REQUIRE_APPROVAL", false),
+    ("ROOT_DATABASE", false),
+    ("ROOT_PASSWORD", true),
+    ("ROOT_USERNAME", false),
+    ("RUST_LOG", false),
+    ("SERVER_ADDR", false),
+    ("SHARE_LINK_SECRET", true),
+    ("SLACK_WEBHOOK_URL", true),
+    ("SNS_TOPIC", false),
+    ("TRACEROUTE_AFTER_FAILURES", false),
+    ("TRACEROUTE_COMMAND", false),
+    ("USER_AGENT", false),
+    ("VAPID_PRIVATE_KEY", true),
+    ("VAPID_PUBLIC_KEY", false),
+    ("VAPID_SUBJECT", false),
+];
+
+/// What a configuration variable is set to, with secrets replaced.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct ConfigurationValue {
+    pub name: &'static str,
+    /// The value, `(redacted)` for secrets, or `None` if it is not set.
+    pub value: Option<String>,
+}
+
+/// Reads the configuration of the application from the environment, redacting secrets.
+pub fn configuration() -> Vec<ConfigurationValue> {
+    configuration_from(|name| std::env::var(name).ok())
+}
+
+fn configuration_from(lookup: impl Fn(&str) -> Option<String>) -> Vec<ConfigurationValue> {
+    VARIABLES
+        .iter()
+        .map(|&(name, secret)| ConfigurationValue {
+            name,
+            value: lookup(name).map(|value| {
+                if secret {
+                    String::from("(redacted)")
+                } else {
+                    value
+                }
+            }),
+        })
+        .collect()
+}
+
+/// A warning or error logged by the application.
+#[derive(Clone, Debug)]
+pub struct InternalError {
+    pub logged_at: DateTime<Utc>,
+    pub level: Level,
+    /// The module that logged it, such as `uptime::poller`.
+    pub target: String,
+    pub message: String,
+    /// Every other field of the event, formatted as `name=value`.
+    pub fields: String,
+}
+
+/// Keeps the most recent warnings and errors logged by the application, so they can be seen
+/// without access to its logs.
+#[derive(Clone)]
+pub struct RecentErrors {
+    errors: Arc<Mutex<VecDeque<InternalError>>>,
+    limit: usize,
+    /// How many have been recorded since the application started, including those dropped since.
+    recorded: Arc<AtomicU64>,
+}
+
+impl Default for RecentErrors {
+    fn default() -> Self {
+        Self::new(DEFAULT_RECENT_ERROR_LIMIT)
+    }
+}
+
+impl RecentErrors {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            errors: Arc::new(Mutex::new(VecDeque::with_capacity(limit))),
+            limit,
+            recorded: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Keeps the number of errors in `RECENT_ERROR_LIMIT`, or 50 if it is not set.
+    pub fn from_env() -> Result<Self> {
+        let limit = match std::env::var("RECENT_ERROR_LIMIT") {
+            Ok(value) => value
+                .parse()
+                .ok()
+                .filter(|limit| *limit > 0)
+                .ok_or_else(|| {
+                    eyre!("RECENT_ERROR_LIMIT must be a positive number, not '{value}'")
+                })?,
+            Err(_) => DEFAULT_RECENT_ERROR_LIMIT,
+        };
+
+        Ok(Self::new(limit))
+    }
+
+    /// The most recent warnings and errors, newest first.
+    pub fn recent(&self) -> Vec<InternalError> {
+        let errors = self.errors.lock().expect("recent errors lock was poisoned");
+
+        errors.iter().rev().cloned().collect()
+    }
+
+    /// The most errors that are kept at once.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// How many warnings and errors have been logged since the application started.
+    pub fn recorded(&self) -> u64 {
+        self.recorded.load(Ordering::Relaxed)
+    }
+
+    fn record(&self, error: InternalError) {
+        let mut errors = self.errors.lock().expect("recent errors lock was poisoned");
+
+        if errors.len() == self.limit {
+            errors.pop_front();
+        }
+
+        errors.push_back(error);
+        self.recorded.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Logs panics as errors before the default hook prints them, so requests that failed on a
+/// database error show up with the other internal errors.
+pub fn log_panics() {
+    let previous = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info
+            .payload()
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown panic");
+
+        let location = info
+            .location()
+            .map(|location| format!("{}:{}", location.file(), location.line()));
+
+        tracing::error!(?location, "panicked: {payload}");
+
+        previous(info);
+    }));
+}
+
+#[derive(Default)]
+struct EventVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for EventVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+            return;
+        }
+
+        if !self.fields.is_empty() {
+            self.fields.push(' ');
+        }
+
+        let _ = write!(self.fields, "{}={value:?}", field.name());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        // Avoid quoting strings, which their debug representation would do
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            self.record_debug(field, &format_args!("{value}"));
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for RecentErrors {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        let metadata = event.metadata();
+
+        // More verbose levels compare as greater
+        if *metadata.level() > Level::WARN {
+            return;
+        }
+
+        let mut visitor = EventVisitor::default();
+        event.record(&mut visitor);
+
+        self.record(InternalError {
+            logged_at: Utc::now(),
+            level: *metadata.level(),
+            target: metadata.target().to_owned(),
+            message: visitor.message,
+            fields: visitor.fields,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing::Level;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use crate::diagnostics::{ConfigurationValue, RecentErrors, DEFAULT_RECENT_ERROR_LIMIT};
+
+    #[test]
+    fn secrets_are_redacted() {
+        let configuration = super::configuration_from(|name| match name {
+            "SNS_TOPIC" => Some(String::from("arn:aws:sns:eu-west-1:123456789012:uptime")),
+            "SHARE_LINK_SECRET" => Some(String::from("hunter2")),
+            _ => None,
+        });
+
+        let find = |name| {
+            configuration
+                .iter()
+                .find(|value| value.name == name)
+                .unwrap()
+        };
+
+        assert_eq!(
+            find("SNS_TOPIC"),
+            &ConfigurationValue {
+                name: "SNS_TOPIC",
+                value: Some(String::from("arn:aws:sns:eu-west-1:123456789012:uptime")),
+            }
+        );
+        assert_eq!(
+            find("SHARE_LINK_SECRET").value.as_deref(),
+            Some("(redacted)")
+        );
+        assert_eq!(find("LOCALE").value, None);
+    }
+
+    #[test]
+    fn only_warnings_and_errors_are_kept() {
+        let errors = RecentErrors::default();
+        let subscriber = tracing_subscriber::registry().with(errors.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("checked an origin");
+            tracing::warn!(attempts = 3, uri = "https://example.com", "failed to check");
+            tracing::error!("lost the database");
+        });
+
+        let recent = errors.recent();
+
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].level, Level::ERROR);
+        assert_eq!(recent[0].message, "lost the database");
+        assert_eq!(recent[1].level, Level::WARN);
+        assert_eq!(recent[1].message, "failed to check");
+        assert_eq!(recent[1].fields, "attempts=3 uri=https://example.com");
+    }
+
+    #[test]
+    fn only_the_most_recent_errors_are_kept() {
+        let errors = RecentErrors::default();
+        let subscriber = tracing_subscriber::registry().with(errors.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            for i in 0..DEFAULT_RECENT_ERROR_LIMIT + 5 {
+                tracing::warn!("warning {i}");
+            }
+        });
+
+        let recent = errors.recent();
+
+        assert_eq!(recent.len(), DEFAULT_RECENT_ERROR_LIMIT);
+        assert_eq!(
+            recent[0].message,
+            format!("warning {}", DEFAULT_RECENT_ERROR_LIMIT + 4)
+        );
+    }
+
+    #[test]
+    fn dropped_errors_are_still_counted() {
+        let errors = RecentErrors::new(2);
+        let subscriber = tracing_subscriber::registry().with(errors.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!("first");
+            tracing::error!("second");
+            tracing::info!("not an error");
+            tracing::warn!("third");
+        });
+
+        let recent = errors.recent();
+
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].message, "third");
+        assert_eq!(recent[1].message, "second");
+        assert_eq!(errors.recorded(), 3);
+    }
+}
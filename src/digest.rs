@@ -0,0 +1,269 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, Datelike, Duration, NaiveTime, Utc};
+use color_eyre::eyre::Result;
+use sqlx::PgPool;
+
+use crate::persistence::DailyLatency;
+use crate::poller::Notifier;
+
+const SUBJECT: &str = "Weekly uptime digest";
+
+#[derive(Clone, Debug)]
+pub struct DigestConfiguration {
+    topic: String,
+    /// The number of days of latency history to analyse.
+    lookback_days: i64,
+    /// The minimum number of days with data for a trend to be considered.
+    minimum_days: usize,
+    /// The relative increase in latency over the lookback period that counts as degrading.
+    drift_threshold: f64,
+}
+
+impl DigestConfiguration {
+    pub fn new<T: Into<String>>(topic: T) -> Self {
+        Self {
+            topic: topic.into(),
+            lookback_days: 14,
+            minimum_days: 5,
+            drift_threshold: 0.2,
+        }
+    }
+}
+
+/// An origin whose latency has been trending upwards.
+struct DegradingOrigin {
+    uri: String,
+    start_latency_millis: f64,
+    end_latency_millis: f64,
+    days: usize,
+}
+
+impl DegradingOrigin {
+    fn drift(&self) -> f64 {
+        (self.end_latency_millis - self.start_latency_millis) / self.start_latency_millis
+    }
+}
+
+/// Fits a line through the points using least squares, returning the slope and intercept.
+fn linear_regression(points: &[(f64, f64)]) -> Option<(f64, f64)> {
+    let n = points.len() as f64;
+
+    if points.len() < 2 {
+        return None;
+    }
+
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+
+    let covariance: f64 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+
+    if variance == 0.0 {
+        return None;
+    }
+
+    let slope = covariance / variance;
+    let intercept = mean_y - slope * mean_x;
+
+    Some((slope, intercept))
+}
+
+fn find_degrading_origins(
+    latencies: &[DailyLatency],
+    configuration: &DigestConfiguration,
+) -> Vec<DegradingOrigin> {
+    latencies
+        .chunk_by(|a, b| a.origin_uid == b.origin_uid)
+        .filter(|days| days.len() >= configuration.minimum_days)
+        .filter_map(|days| {
+            let first_day = days[0].day;
+
+            let points: Vec<_> = days
+                .iter()
+                .map(|d| {
+                    (
+                        (d.day - first_day).num_days() as f64,
+                        d.average_latency_millis,
+                    )
+                })
+                .collect();
+
+            let (slope, intercept) = linear_regression(&points)?;
+            let last_x = points.last()?.0;
+
+            let origin = DegradingOrigin {
+                uri: days[0].uri.clone(),
+                start_latency_millis: intercept,
+                end_latency_millis: intercept + slope * last_x,
+                days: days.len(),
+            };
+
+            (intercept > 0.0 && origin.drift() >= configuration.drift_threshold).then_some(origin)
+        })
+        .collect()
+}
+
+fn render(degrading: &[DegradingOrigin]) -> String {
+    let mut message = String::from("Degrading services\n==================\n\n");
+
+    if degrading.is_empty() {
+        message.push_str("No services have shown a sustained increase in latency.\n");
+    }
+
+    for origin in degrading {
+        let _ = writeln!(
+            message,
+            "- {}: {:.0}ms -> {:.0}ms over {} days ({:+.0}%)",
+            origin.uri,
+            origin.start_latency_millis,
+            origin.end_latency_millis,
+            origin.days,
+            origin.drift() * 100.0
+        );
+    }
+
+    message
+}
+
+/// Finds the next Monday at 09:00 UTC strictly after `now`.
+fn next_run(now: DateTime<Utc>) -> DateTime<Utc> {
+    let send_time = NaiveTime::from_hms_opt(9, 0, 0).expect("valid time");
+    let days_until_monday = (7 - now.weekday().num_days_from_monday()) % 7;
+
+    let candidate = (now.date_naive() + Duration::days(i64::from(days_until_monday)))
+        .and_time(send_time)
+        .and_utc();
+
+    if candidate > now {
+        candidate
+    } else {
+        candidate + Duration::weeks(1)
+    }
+}
+
+pub struct Digest<N> {
+    pool: PgPool,
+    notifier: N,
+    configuration: DigestConfiguration,
+}
+
+impl<N: Notifier> Digest<N> {
+    pub fn new(pool: PgPool, notifier: N, configuration: DigestConfiguration) -> Self {
+        Self {
+            pool,
+            notifier,
+            configuration,
+        }
+    }
+
+    pub async fn run(&self) {
+        loop {
+            let now = Utc::now();
+            let delay = (next_run(now) - now).to_std().unwrap_or_default();
+
+            tracing::info!(?delay, "waiting to send the next weekly digest");
+            tokio::time::sleep(delay).await;
+
+            if let Err(e) = self.send().await {
+                tracing::warn!(%e, "failed to send the weekly digest");
+            }
+        }
+    }
+
+    async fn send(&self) -> Result<()> {
+        let since = Utc::now() - Duration::days(self.configuration.lookback_days);
+        let latencies = crate::persistence::fetch_daily_latencies(&self.pool, since).await?;
+
+        let degrading = find_degrading_origins(&latencies, &self.configuration);
+        let message = render(&degrading);
+
+        self.notifier
+            .notify(&self.configuration.topic, SUBJECT, &message)
+            .await?;
+
+        tracing::info!(degrading = degrading.len(), "sent the weekly digest");
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use uuid::Uuid;
+
+    use crate::digest::DigestConfiguration;
+    use crate::persistence::DailyLatency;
+
+    fn daily(origin_uid: Uuid, latencies: &[f64]) -> Vec<DailyLatency> {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+
+        latencies
+            .iter()
+            .enumerate()
+            .map(|(i, latency)| DailyLatency {
+                origin_uid,
+                uri: format!("https://{origin_uid}.com"),
+                day: start + chrono::Duration::days(i as i64),
+                average_latency_millis: *latency,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn regression_finds_the_line_of_best_fit() {
+        let points = [(0.0, 1.0), (1.0, 3.0), (2.0, 5.0)];
+
+        assert_eq!(super::linear_regression(&points), Some((2.0, 1.0)));
+    }
+
+    #[test]
+    fn regression_requires_multiple_points() {
+        assert_eq!(super::linear_regression(&[(0.0, 1.0)]), None);
+    }
+
+    #[test]
+    fn creeping_latency_is_reported() {
+        let configuration = DigestConfiguration::new("topic");
+
+        let creeping = Uuid::new_v4();
+        let stable = Uuid::new_v4();
+
+        let mut latencies = daily(creeping, &[100.0, 110.0, 120.0, 130.0, 140.0, 150.0]);
+        latencies.extend(daily(stable, &[100.0, 102.0, 98.0, 101.0, 99.0, 100.0]));
+
+        let degrading = super::find_degrading_origins(&latencies, &configuration);
+
+        assert_eq!(degrading.len(), 1);
+        assert_eq!(degrading[0].uri, format!("https://{creeping}.com"));
+        assert_eq!(degrading[0].days, 6);
+    }
+
+    #[test]
+    fn short_histories_are_ignored() {
+        let configuration = DigestConfiguration::new("topic");
+        let latencies = daily(Uuid::new_v4(), &[100.0, 200.0, 300.0]);
+
+        assert!(super::find_degrading_origins(&latencies, &configuration).is_empty());
+    }
+
+    #[test]
+    fn digests_are_sent_on_monday_mornings() {
+        // 2024-01-03 was a Wednesday
+        let now = Utc.with_ymd_and_hms(2024, 1, 3, 12, 0, 0).unwrap();
+        let expected = Utc.with_ymd_and_hms(2024, 1, 8, 9, 0, 0).unwrap();
+
+        assert_eq!(super::next_run(now), expected);
+
+        // Mondays after the send time roll over to the following week
+        let now = Utc.with_ymd_and_hms(2024, 1, 8, 9, 0, 0).unwrap();
+        let expected = Utc.with_ymd_and_hms(2024, 1, 15, 9, 0, 0).unwrap();
+
+        assert_eq!(super::next_run(now), expected);
+    }
+}
@@ -0,0 +1,387 @@
+use std::fmt::Write;
+use std::io::Write as _;
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::str::FromStr;
+use std::time::Duration;
+
+use color_eyre::eyre::{eyre, Result};
+use reqwest::header::HeaderValue;
+use sqlx::PgPool;
+
+use crate::approval::ApprovalPolicy;
+use crate::credentials::CredentialCipher;
+use crate::diagnostics::RecentErrors;
+use crate::locale::Locale;
+use crate::poller::{
+    DomainExpiry, EventBus, ExternalCommands, FailureDiagnostics, Firehose, NotifierKind,
+    Traceroute, VapidKeys,
+};
+use crate::share::ShareLinkSigner;
+use crate::templates::TemplateEngine;
+
+/// The URL fetched to check the host can reach the internet, if `DOCTOR_REACHABILITY_URL` is not
+/// set.
+const DEFAULT_REACHABILITY_URL: &str = "https://example.com";
+
+/// How long each check that goes over the network has before it is reported as failing.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The variables the server will not start without.
+const REQUIRED_VARIABLES: &[&str] = &[
+    "ROOT_USERNAME",
+    "ROOT_PASSWORD",
+    "ROOT_DATABASE",
+    "APP_USERNAME",
+    "APP_PASSWORD",
+    "APP_DATABASE",
+    "DATABASE_HOST",
+    "DATABASE_PORT",
+    "SERVER_ADDR",
+];
+
+/// The result of one of the doctor's checks.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub name: &'static str,
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Passed(String),
+    /// What went wrong, and what is likely to fix it.
+    Failed {
+        problem: String,
+        hint: &'static str,
+    },
+    /// The check could not run, such as because an earlier one failed.
+    Skipped(&'static str),
+}
+
+impl Finding {
+    fn passed(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            outcome: Outcome::Passed(detail.into()),
+        }
+    }
+
+    fn failed(name: &'static str, problem: impl ToString, hint: &'static str) -> Self {
+        Self {
+            name,
+            outcome: Outcome::Failed {
+                problem: problem.to_string(),
+                hint,
+            },
+        }
+    }
+
+    fn skipped(name: &'static str, reason: &'static str) -> Self {
+        Self {
+            name,
+            outcome: Outcome::Skipped(reason),
+        }
+    }
+}
+
+/// Checks everything the server needs before it can run, printing what was found and failing if
+/// anything is wrong, so problems are found at once rather than one runtime error at a time.
+pub async fn run() -> Result<()> {
+    let mut findings = vec![check_configuration()];
+
+    match crate::persistence::connect().await {
+        Ok(pool) => {
+            findings.push(Finding::passed("Database", "connected"));
+            findings.push(check_migrations(&pool).await);
+        }
+        Err(e) => {
+            findings.push(Finding::failed(
+                "Database",
+                e,
+                "check the database is running and the DATABASE_* and *_USERNAME/*_PASSWORD variables",
+            ));
+            findings.push(Finding::skipped(
+                "Migrations",
+                "the database could not be reached",
+            ));
+        }
+    }
+
+    findings.push(check_topic().await);
+    findings.push(check_templates());
+    findings.push(check_reachability().await);
+
+    std::io::stdout().write_all(render(&findings).as_bytes())?;
+
+    let failures = findings
+        .iter()
+        .filter(|finding| matches!(finding.outcome, Outcome::Failed { .. }))
+        .count();
+
+    match failures {
+        0 => Ok(()),
+        1 => Err(eyre!("1 check failed")),
+        n => Err(eyre!("{n} checks failed")),
+    }
+}
+
+/// Formats findings as one line each, with hints for how to fix any that failed.
+fn render(findings: &[Finding]) -> String {
+    let mut output = String::new();
+
+    for finding in findings {
+        let _ = match &finding.outcome {
+            Outcome::Passed(detail) => writeln!(output, "ok    {}: {detail}", finding.name),
+            Outcome::Failed { problem, hint } => writeln!(
+                output,
+                "FAIL  {}: {problem}\n      hint: {hint}",
+                finding.name
+            ),
+            Outcome::Skipped(reason) => writeln!(output, "skip  {}: {reason}", finding.name),
+        };
+    }
+
+    output
+}
+
+/// Checks required variables are set and that every optional one parses the way the server would
+/// parse it.
+fn check_configuration() -> Finding {
+    let mut problems: Vec<String> = REQUIRED_VARIABLES
+        .iter()
+        .filter(|key| std::env::var(key).is_err())
+        .map(|key| format!("{key} is not set"))
+        .collect();
+
+    let parsed: [Result<()>; 14] = [
+        Locale::from_env().map(drop),
+        RecentErrors::from_env().map(drop),
+        ApprovalPolicy::from_env().map(drop),
+        ShareLinkSigner::from_env().map(drop),
+        CredentialCipher::from_env().map(drop),
+        VapidKeys::from_env().map(drop),
+        Traceroute::from_env().map(drop),
+        ExternalCommands::from_env().map(drop),
+        FailureDiagnostics::from_env().map(drop),
+        DomainExpiry::from_env(reqwest::Client::new()).map(drop),
+        EventBus::from_env().map(drop),
+        Firehose::from_env(reqwest::Client::new()).map(drop),
+        parse_server_variables(),
+        parse_notifier_variables(),
+    ];
+
+    problems.extend(
+        parsed
+            .into_iter()
+            .filter_map(|result| result.err())
+            .map(|e| e.to_string()),
+    );
+
+    if problems.is_empty() {
+        return Finding::passed("Configuration", "all variables are set and valid");
+    }
+
+    Finding::failed(
+        "Configuration",
+        problems.join("; "),
+        "set the variables in the environment or a .env file",
+    )
+}
+
+fn parse_server_variables() -> Result<()> {
+    if let Ok(addr) = std::env::var("SERVER_ADDR") {
+        SocketAddr::from_str(&addr)
+            .map_err(|e| eyre!("SERVER_ADDR '{addr}' is not an address: {e}"))?;
+    }
+
+    if let Ok(timeout_millis) = std::env::var("DEFAULT_TIMEOUT_MILLIS") {
+        timeout_millis
+            .parse::<u64>()
+            .map_err(|e| eyre!("DEFAULT_TIMEOUT_MILLIS '{timeout_millis}' is not a number: {e}"))?;
+    }
+
+    if let Ok(concurrency) = std::env::var("POLL_CONCURRENCY") {
+        concurrency
+            .parse::<NonZeroUsize>()
+            .map_err(|e| eyre!("POLL_CONCURRENCY '{concurrency}' is not a positive number: {e}"))?;
+    }
+
+    if let Ok(jitter_millis) = std::env::var("CHECK_JITTER_MILLIS") {
+        jitter_millis
+            .parse::<u64>()
+            .map_err(|e| eyre!("CHECK_JITTER_MILLIS '{jitter_millis}' is not a number: {e}"))?;
+    }
+
+    if let Ok(location_limit) = std::env::var("ALERT_LOCATIONS") {
+        location_limit
+            .parse::<u16>()
+            .map_err(|e| eyre!("ALERT_LOCATIONS '{location_limit}' is not a number: {e}"))?;
+    }
+
+    if let Ok(recheck_interval_millis) = std::env::var("RECHECK_INTERVAL_MILLIS") {
+        recheck_interval_millis.parse::<u64>().map_err(|e| {
+            eyre!("RECHECK_INTERVAL_MILLIS '{recheck_interval_millis}' is not a number: {e}")
+        })?;
+    }
+
+    if let Ok(stable_checks) = std::env::var("RECHECK_STABLE_CHECKS") {
+        stable_checks
+            .parse::<u16>()
+            .map_err(|e| eyre!("RECHECK_STABLE_CHECKS '{stable_checks}' is not a number: {e}"))?;
+    }
+
+    if let Ok(proxy) = std::env::var("CHECK_PROXY") {
+        reqwest::Proxy::all(proxy).map_err(|e| eyre!("CHECK_PROXY is not a proxy URL: {e}"))?;
+    }
+
+    if let Ok(user_agent) = std::env::var("USER_AGENT") {
+        HeaderValue::from_str(&user_agent)
+            .map_err(|_| eyre!("USER_AGENT '{user_agent}' cannot be sent as a header"))?;
+    }
+
+    Ok(())
+}
+
+/// Checks the notifier was compiled in and knows where to send alerts.
+fn parse_notifier_variables() -> Result<()> {
+    let kind = NotifierKind::from_env()?;
+    let topic_variable = kind.topic_variable();
+
+    if std::env::var(topic_variable).is_err() {
+        return Err(eyre!("{topic_variable} is not set"));
+    }
+
+    Ok(())
+}
+
+/// Applies any migrations that have not been yet, as starting the server would.
+async fn check_migrations(pool: &PgPool) -> Finding {
+    let migrator = sqlx::migrate!();
+
+    match migrator.run(pool).await {
+        Ok(()) => Finding::passed(
+            "Migrations",
+            format!("all {} migrations are applied", migrator.iter().count()),
+        ),
+        Err(e) => Finding::failed(
+            "Migrations",
+            e,
+            "a migration may have failed part way, or the database was migrated by a newer version",
+        ),
+    }
+}
+
+/// Checks alerts can be sent where the notifier is configured to send them.
+async fn check_topic() -> Finding {
+    match NotifierKind::from_env() {
+        #[cfg(feature = "sns")]
+        Ok(NotifierKind::Sns) => check_sns_topic().await,
+        Ok(NotifierKind::Slack) => Finding::skipped(
+            "Notifier",
+            "Slack webhooks cannot be checked without posting to them",
+        ),
+        Err(_) => Finding::skipped("Notifier", "NOTIFIER is not valid"),
+    }
+}
+
+/// Checks the topic exists and the credentials can see it. SNS has no way to publish without
+/// delivering, so this reads the topic's attributes instead.
+#[cfg(feature = "sns")]
+async fn check_sns_topic() -> Finding {
+    let Ok(topic) = std::env::var("SNS_TOPIC") else {
+        return Finding::skipped("SNS topic", "SNS_TOPIC is not set");
+    };
+
+    let sdk_config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+    let client = aws_sdk_sns::Client::new(&sdk_config);
+
+    let request = client.get_topic_attributes().topic_arn(&topic).send();
+
+    match tokio::time::timeout(TIMEOUT, request).await {
+        Ok(Ok(_)) => Finding::passed("SNS topic", format!("{topic} is reachable")),
+        Ok(Err(e)) => Finding::failed(
+            "SNS topic",
+            aws_sdk_sns::error::DisplayErrorContext(e),
+            "check the AWS credentials and region, and that they allow sns:GetTopicAttributes and sns:Publish on the topic",
+        ),
+        Err(_) => Finding::failed(
+            "SNS topic",
+            format!("no response within {}s", TIMEOUT.as_secs()),
+            "check the host can reach AWS",
+        ),
+    }
+}
+
+fn check_templates() -> Finding {
+    match TemplateEngine::new() {
+        Ok(_) => Finding::passed("Templates", "all templates parsed"),
+        Err(e) => Finding::failed(
+            "Templates",
+            e,
+            "run from the directory containing templates/, or fix the template named in the error",
+        ),
+    }
+}
+
+/// Fetches a well-known URL the same way checks are sent, including through `CHECK_PROXY`.
+async fn check_reachability() -> Finding {
+    let url = std::env::var("DOCTOR_REACHABILITY_URL")
+        .unwrap_or_else(|_| String::from(DEFAULT_REACHABILITY_URL));
+
+    let mut builder = reqwest::Client::builder().timeout(TIMEOUT);
+
+    if let Ok(proxy) = std::env::var("CHECK_PROXY") {
+        match reqwest::Proxy::all(proxy) {
+            Ok(proxy) => builder = builder.proxy(proxy),
+            Err(_) => return Finding::skipped("Network", "CHECK_PROXY is not valid"),
+        }
+    }
+
+    let response = match builder.build() {
+        Ok(client) => client.get(&url).send().await,
+        Err(e) => return Finding::failed("Network", e, "this is a bug, please report it"),
+    };
+
+    match response {
+        Ok(response) => Finding::passed(
+            "Network",
+            format!("{url} responded with {}", response.status()),
+        ),
+        Err(e) => Finding::failed(
+            "Network",
+            e,
+            "check outbound firewall rules, DNS and CHECK_PROXY, or set DOCTOR_REACHABILITY_URL to a URL the host should reach",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use crate::doctor::{Finding, Outcome};
+
+    #[test]
+    fn failures_are_rendered_with_a_hint() {
+        let findings = [
+            Finding::passed("Database", "connected"),
+            Finding::failed("SNS topic", "access denied", "check the credentials"),
+            Finding::skipped("Migrations", "the database could not be reached"),
+        ];
+
+        assert_eq!(
+            super::render(&findings),
+            "ok    Database: connected\n\
+             FAIL  SNS topic: access denied\n      hint: check the credentials\n\
+             skip  Migrations: the database could not be reached\n"
+        );
+    }
+
+    #[sqlx::test]
+    async fn migrated_databases_pass(pool: PgPool) {
+        let finding = super::check_migrations(&pool).await;
+
+        assert!(matches!(finding.outcome, Outcome::Passed(_)));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::persistence::HealthInputs;
+
+/// How much of the score is determined by the proportion of successful queries.
+const AVAILABILITY_WEIGHT: f64 = 60.0;
+/// How much of the score is determined by latency relative to the baseline.
+const LATENCY_WEIGHT: f64 = 25.0;
+/// How much of the score is determined by how often the origin flips between up and down.
+const STABILITY_WEIGHT: f64 = 15.0;
+
+/// Latency at or below the baseline scores fully, dropping to nothing at this multiple of it.
+const LATENCY_TOLERANCE: f64 = 3.0;
+/// The number of transitions within the window at which an origin is considered fully unstable.
+const TRANSITION_LIMIT: f64 = 6.0;
+
+/// The window used to calculate availability and stability.
+pub const AVAILABILITY_WINDOW: chrono::Duration = chrono::Duration::hours(24);
+/// The window used to calculate the current latency.
+pub const LATENCY_WINDOW: chrono::Duration = chrono::Duration::hours(1);
+/// The window used to calculate the latency the current value is compared against.
+pub const BASELINE_WINDOW: chrono::Duration = chrono::Duration::days(7);
+/// How long scores are kept once newer ones are recorded, far longer than any trend shows.
+pub const RETENTION: chrono::Duration = chrono::Duration::days(30);
+
+/// Combines availability, latency and stability into a single score between 0 and 100.
+///
+/// Returns `None` if the origin has not been queried within the availability window.
+pub fn score(inputs: &HealthInputs) -> Option<i16> {
+    if inputs.checks == 0 {
+        return None;
+    }
+
+    let availability = inputs.successes as f64 / inputs.checks as f64;
+
+    let latency = match (inputs.recent_latency_millis, inputs.baseline_latency_millis) {
+        (Some(recent), Some(baseline)) if baseline > 0.0 => {
+            let ratio = recent / baseline;
+            ((LATENCY_TOLERANCE - ratio) / (LATENCY_TOLERANCE - 1.0)).clamp(0.0, 1.0)
+        }
+        (Some(_), _) => 1.0,
+        // No successful queries recently, so there is no latency to reward
+        (None, _) => 0.0,
+    };
+
+    let stability = 1.0 - (inputs.transitions as f64 / TRANSITION_LIMIT).min(1.0);
+
+    let score = AVAILABILITY_WEIGHT * availability
+        + LATENCY_WEIGHT * latency
+        + STABILITY_WEIGHT * stability;
+
+    Some(score.round() as i16)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use color_eyre::eyre::Result;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    use crate::health::RETENTION;
+    use crate::persistence::HealthInputs;
+
+    fn inputs(checks: i64, successes: i64, transitions: i64) -> HealthInputs {
+        HealthInputs {
+            checks,
+            successes,
+            transitions,
+            recent_latency_millis: Some(100.0),
+            baseline_latency_millis: Some(100.0),
+        }
+    }
+
+    #[test]
+    fn healthy_origins_score_fully() {
+        assert_eq!(super::score(&inputs(100, 100, 0)), Some(100));
+    }
+
+    #[test]
+    fn unqueried_origins_have_no_score() {
+        assert_eq!(super::score(&inputs(0, 0, 0)), None);
+    }
+
+    #[test]
+    fn failures_reduce_the_score() {
+        assert_eq!(super::score(&inputs(100, 50, 0)), Some(70));
+    }
+
+    #[test]
+    fn flapping_reduces_the_score() {
+        assert_eq!(super::score(&inputs(100, 100, 3)), Some(93));
+        assert_eq!(super::score(&inputs(100, 100, 20)), Some(85));
+    }
+
+    #[test]
+    fn latency_regressions_reduce_the_score() {
+        let mut inputs = inputs(100, 100, 0);
+
+        inputs.recent_latency_millis = Some(200.0);
+        assert_eq!(super::score(&inputs), Some(88));
+
+        inputs.recent_latency_millis = Some(500.0);
+        assert_eq!(super::score(&inputs), Some(75));
+    }
+
+    #[test]
+    fn origins_that_are_down_score_poorly() {
+        let mut inputs = inputs(100, 0, 0);
+        inputs.recent_latency_millis = None;
+
+        assert_eq!(super::score(&inputs), Some(15));
+    }
+
+    #[sqlx::test]
+    async fn old_scores_are_removed_as_new_ones_are_recorded(pool: PgPool) -> Result<()> {
+        let origin_uid = Uuid::new_v4();
+        crate::persistence::insert_origin(&pool, origin_uid, "https://example.com").await?;
+
+        let now = Utc::now();
+        let expired = now - RETENTION - Duration::hours(1);
+
+        crate::persistence::insert_health_score(&pool, origin_uid, 40, expired).await?;
+        crate::persistence::insert_health_score(&pool, origin_uid, 90, now).await?;
+
+        let scores = crate::persistence::fetch_recent_health_scores(&pool, 10).await?;
+        let scores: Vec<_> = scores.iter().map(|score| score.score).collect();
+
+        assert_eq!(scores, [90]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,210 @@
+//! Checks origins on a schedule, records how they responded in Postgres and alerts when they
+//! keep failing.
+//!
+//! The `uptime` binary is a thin wrapper around this crate, which can also be embedded in other
+//! services or used to build binaries with extra notifiers:
+//!
+//! - [`persistence::bootstrap`] connects to the database and migrates it.
+//! - [`poller::Poller`] checks origins, sending alerts through any [`poller::Notifier`].
+//! - [`router::build`] serves the web interface and API, controlling the poller through its
+//!   [`poller::PollerHandle`].
+//!
+//! [`serve`] wires these together the way the binary does, configured from the environment.
+
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+use color_eyre::eyre::{eyre, Result};
+use reqwest::header::HeaderValue;
+use reqwest::Client;
+use sqlx::PgPool;
+use tokio::net::TcpListener;
+
+pub mod approval;
+pub mod cli;
+mod config;
+mod cost;
+pub mod credentials;
+pub mod diagnostics;
+mod digest;
+pub mod doctor;
+mod health;
+pub mod locale;
+mod maintenance;
+pub mod persistence;
+pub mod poller;
+mod regions;
+mod reliability;
+pub mod router;
+pub mod share;
+mod siblings;
+mod templates;
+mod utils;
+
+use crate::approval::ApprovalPolicy;
+use crate::credentials::CredentialCipher;
+use crate::diagnostics::RecentErrors;
+use crate::digest::{Digest, DigestConfiguration};
+use crate::locale::Locale;
+use crate::maintenance::Maintenance;
+use crate::poller::{
+    AlertThreshold, DomainExpiry, EventBus, ExternalCommands, FailureDiagnostics, FallbackChannel,
+    Firehose, NotifierKind, Poller, PollerConfiguration, Traceroute, VapidKeys, WebPush,
+};
+use crate::router::{EmbedSettings, RouterSettings};
+use crate::share::ShareLinkSigner;
+use crate::utils::get_env_var;
+
+/// Runs the poller, digests, maintenance and web server until one of them stops, alerting
+/// through the notifier in `NOTIFIER` and configuring everything else from the environment.
+pub async fn serve(pool: PgPool, recent_errors: RecentErrors) -> Result<()> {
+    let http_client = Client::new();
+
+    let notifier_kind = NotifierKind::from_env()?;
+    let topic = get_env_var(notifier_kind.topic_variable())?;
+    let notifier = notifier_kind.build(http_client.clone()).await;
+
+    let mut alert_threshold = AlertThreshold::default();
+
+    if let Ok(location_limit) = std::env::var("ALERT_LOCATIONS") {
+        alert_threshold = alert_threshold.with_location_limit(location_limit.parse()?);
+    }
+
+    if let Ok(recheck_interval_millis) = std::env::var("RECHECK_INTERVAL_MILLIS") {
+        let recheck_interval = Duration::from_millis(recheck_interval_millis.parse()?);
+        alert_threshold = alert_threshold.with_recheck_interval(recheck_interval);
+    }
+
+    if let Ok(stable_checks) = std::env::var("RECHECK_STABLE_CHECKS") {
+        alert_threshold = alert_threshold.with_stable_checks(stable_checks.parse()?);
+    }
+
+    if std::env::var("RECHECK_ALL_ORIGINS").is_ok_and(|value| value == "true") {
+        alert_threshold = alert_threshold.with_all_origins_adaptive();
+    }
+
+    let mut configuration = PollerConfiguration::new(alert_threshold, &topic);
+
+    if let Ok(timeout_millis) = std::env::var("DEFAULT_TIMEOUT_MILLIS") {
+        let timeout = Duration::from_millis(timeout_millis.parse()?);
+        configuration = configuration.with_default_timeout(timeout);
+    }
+
+    if let Ok(concurrency) = std::env::var("POLL_CONCURRENCY") {
+        configuration = configuration.with_concurrency(concurrency.parse()?);
+    }
+
+    if let Ok(jitter_millis) = std::env::var("CHECK_JITTER_MILLIS") {
+        let jitter = Duration::from_millis(jitter_millis.parse()?);
+        configuration = configuration.with_jitter(jitter);
+    }
+
+    if let Ok(proxy) = std::env::var("CHECK_PROXY") {
+        configuration = configuration.with_proxy(reqwest::Proxy::all(proxy)?);
+    }
+
+    if let Some(location) = std::env::var("POLLER_LOCATION")
+        .ok()
+        .filter(|location| !location.is_empty())
+    {
+        configuration = configuration.with_location(location);
+    }
+
+    if let Ok(user_agent) = std::env::var("USER_AGENT") {
+        let user_agent = HeaderValue::from_str(&user_agent)
+            .map_err(|_| eyre!("USER_AGENT '{user_agent}' cannot be sent as a header"))?;
+
+        configuration = configuration.with_user_agent(user_agent);
+    }
+
+    let locale = Locale::from_env()?;
+    configuration = configuration.with_locale(locale);
+
+    let vapid_keys = VapidKeys::from_env()?;
+    let push_public_key = vapid_keys.as_ref().map(VapidKeys::public_key);
+
+    let mut poller = Poller::new(
+        pool.clone(),
+        http_client.clone(),
+        notifier.clone(),
+        configuration,
+    );
+
+    if let Some(events) = EventBus::from_env()? {
+        poller = poller.with_event_bus(events);
+    }
+
+    if let Some(firehose) = Firehose::from_env(http_client.clone())? {
+        poller = poller.with_firehose(firehose);
+    }
+
+    if let Some(traceroute) = Traceroute::from_env()? {
+        poller = poller.with_traceroute(traceroute);
+    }
+
+    if let Some(domain_expiry) = DomainExpiry::from_env(http_client.clone())? {
+        poller = poller.with_domain_expiry(domain_expiry);
+    }
+
+    if let Some(commands) = ExternalCommands::from_env()? {
+        poller = poller.with_external_commands(commands);
+    }
+
+    if let Some(diagnostics) = FailureDiagnostics::from_env()? {
+        poller = poller.with_failure_diagnostics(diagnostics);
+    }
+
+    if let Some(fallback) = FallbackChannel::from_env() {
+        poller = poller.with_fallback_channel(fallback);
+    }
+
+    if let Some(keys) = vapid_keys {
+        poller = poller.with_web_push(WebPush::new(http_client, keys));
+    }
+
+    let credentials = CredentialCipher::from_env()?;
+
+    if let Some(credentials) = &credentials {
+        poller = poller.with_credentials(credentials.clone());
+    }
+
+    let digest = Digest::new(pool.clone(), notifier, DigestConfiguration::new(topic));
+    let maintenance = Maintenance::from_env(pool.clone())?;
+
+    let settings = RouterSettings {
+        approval_policy: ApprovalPolicy::from_env()?,
+        share_links: ShareLinkSigner::from_env()?,
+        embed: EmbedSettings::from_env(),
+        push_public_key,
+        locale,
+        credentials,
+    };
+
+    let router = crate::router::build(
+        pool.clone(),
+        settings,
+        poller.handle(),
+        poller.inspector(),
+        recent_errors,
+    )?;
+    let addr = SocketAddr::from_str(&get_env_var("SERVER_ADDR")?)?;
+    let listener = TcpListener::bind(addr).await?;
+
+    tracing::info!(%addr, "listening for incoming requests");
+
+    let maintenance = async {
+        if let Some(maintenance) = &maintenance {
+            maintenance.run().await;
+        }
+    };
+
+    let _ = tokio::join!(
+        poller.run(),
+        digest.run(),
+        maintenance,
+        axum::serve(listener, router)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,438 @@
+use std::str::FromStr;
+
+use color_eyre::eyre::{eyre, Report, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::persistence::AlertKind;
+
+/// A language that alerts and the public status page can be written in.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+    Fr,
+    Es,
+}
+
+impl Locale {
+    pub const ALL: [Self; 4] = [Self::En, Self::De, Self::Fr, Self::Es];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::De => "de",
+            Self::Fr => "fr",
+            Self::Es => "es",
+        }
+    }
+
+    pub fn from_column(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|locale| locale.as_str() == value)
+    }
+
+    /// The name of the language in the language itself, so people can find their own.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::En => "English",
+            Self::De => "Deutsch",
+            Self::Fr => "Français",
+            Self::Es => "Español",
+        }
+    }
+
+    /// Reads the locale of the deployment from `LOCALE`, defaulting to English if it is not set.
+    pub fn from_env() -> Result<Self> {
+        let Ok(value) = std::env::var("LOCALE") else {
+            return Ok(Self::default());
+        };
+
+        value.trim().parse()
+    }
+
+    pub fn status_page(&self) -> StatusPageText {
+        match self {
+            Self::En => StatusPageText {
+                lang: "en",
+                title: "Status",
+                description: "Current status of our public services",
+                all_operational: "All systems operational",
+                some_issues: "Some systems are experiencing issues",
+                services: "Services",
+                operational: "Operational",
+                outage: "Outage",
+                nothing_published: "No services are currently published.",
+            },
+            Self::De => StatusPageText {
+                lang: "de",
+                title: "Status",
+                description: "Aktueller Status unserer öffentlichen Dienste",
+                all_operational: "Alle Systeme betriebsbereit",
+                some_issues: "Bei einigen Systemen treten Probleme auf",
+                services: "Dienste",
+                operational: "Betriebsbereit",
+                outage: "Ausfall",
+                nothing_published: "Derzeit sind keine Dienste veröffentlicht.",
+            },
+            Self::Fr => StatusPageText {
+                lang: "fr",
+                title: "État",
+                description: "État actuel de nos services publics",
+                all_operational: "Tous les systèmes sont opérationnels",
+                some_issues: "Certains systèmes rencontrent des problèmes",
+                services: "Services",
+                operational: "Opérationnel",
+                outage: "Panne",
+                nothing_published: "Aucun service n'est publié pour le moment.",
+            },
+            Self::Es => StatusPageText {
+                lang: "es",
+                title: "Estado",
+                description: "Estado actual de nuestros servicios públicos",
+                all_operational: "Todos los sistemas operativos",
+                some_issues: "Algunos sistemas tienen problemas",
+                services: "Servicios",
+                operational: "Operativo",
+                outage: "Interrupción",
+                nothing_published: "No hay servicios publicados en este momento.",
+            },
+        }
+    }
+
+    /// Says how long ago something was checked, such as `checked 5m ago`.
+    pub fn checked_ago(&self, elapsed: &str) -> String {
+        match self {
+            Self::En => format!("checked {elapsed} ago"),
+            Self::De => format!("vor {elapsed} geprüft"),
+            Self::Fr => format!("vérifié il y a {elapsed}"),
+            Self::Es => format!("comprobado hace {elapsed}"),
+        }
+    }
+}
+
+impl FromStr for Locale {
+    type Err = Report;
+
+    fn from_str(value: &str) -> Result<Self> {
+        Self::from_column(value)
+            .ok_or_else(|| eyre!("unsupported locale '{value}', expected one of en, de, fr or es"))
+    }
+}
+
+/// Everything written on the public status page, in one language.
+#[derive(Serialize)]
+pub struct StatusPageText {
+    lang: &'static str,
+    title: &'static str,
+    description: &'static str,
+    all_operational: &'static str,
+    some_issues: &'static str,
+    services: &'static str,
+    operational: &'static str,
+    outage: &'static str,
+    nothing_published: &'static str,
+}
+
+/// Something worth alerting about, which is written out in the locale of whoever receives it.
+pub enum Alert<'a> {
+    Outage {
+        uri: &'a str,
+        /// What was found when the latest failed check could not reach the origin.
+        diagnostics: Option<&'a str>,
+    },
+    LatencyRegression {
+        uri: &'a str,
+        relative_latency_millis: i64,
+        limit_millis: i64,
+    },
+    RedirectChainChanged {
+        uri: &'a str,
+        previous: &'a str,
+        current: &'a str,
+    },
+    UnexpectedAddress {
+        uri: &'a str,
+        address: &'a str,
+    },
+    BodyChanged {
+        uri: &'a str,
+        /// The SHA-256 of the previous body, as hex.
+        previous: &'a str,
+        current: &'a str,
+    },
+    DomainExpiring {
+        uri: &'a str,
+        domain: &'a str,
+        expires_on: &'a str,
+        /// How many whole days are left, which is negative once the domain has expired.
+        days_left: i64,
+    },
+}
+
+impl Alert<'_> {
+    pub fn kind(&self) -> AlertKind {
+        match self {
+            Self::Outage { .. } => AlertKind::Outage,
+            Self::LatencyRegression { .. } => AlertKind::LatencyRegression,
+            Self::RedirectChainChanged { .. } => AlertKind::RedirectChainChanged,
+            Self::UnexpectedAddress { .. } => AlertKind::UnexpectedAddress,
+            Self::BodyChanged { .. } => AlertKind::BodyChanged,
+            Self::DomainExpiring { .. } => AlertKind::DomainExpiring,
+        }
+    }
+
+    pub fn subject(&self, locale: Locale) -> &'static str {
+        match self {
+            Self::Outage { .. } => match locale {
+                Locale::En => "Outage detected",
+                Locale::De => "Ausfall erkannt",
+                Locale::Fr => "Panne détectée",
+                Locale::Es => "Interrupción detectada",
+            },
+            Self::LatencyRegression { .. } => match locale {
+                Locale::En => "Latency regression detected",
+                Locale::De => "Latenzverschlechterung erkannt",
+                Locale::Fr => "Dégradation de la latence détectée",
+                Locale::Es => "Empeoramiento de la latencia detectado",
+            },
+            Self::RedirectChainChanged { .. } => match locale {
+                Locale::En => "Redirect chain changed",
+                Locale::De => "Weiterleitungskette geändert",
+                Locale::Fr => "Chaîne de redirection modifiée",
+                Locale::Es => "Cadena de redirección modificada",
+            },
+            Self::UnexpectedAddress { .. } => match locale {
+                Locale::En => "Unexpected address detected",
+                Locale::De => "Unerwartete Adresse erkannt",
+                Locale::Fr => "Adresse inattendue détectée",
+                Locale::Es => "Dirección inesperada detectada",
+            },
+            Self::BodyChanged { .. } => match locale {
+                Locale::En => "Content changed",
+                Locale::De => "Inhalt geändert",
+                Locale::Fr => "Contenu modifié",
+                Locale::Es => "Contenido modificado",
+            },
+            Self::DomainExpiring { .. } => match locale {
+                Locale::En => "Domain registration expiring",
+                Locale::De => "Domainregistrierung läuft ab",
+                Locale::Fr => "Enregistrement de domaine bientôt expiré",
+                Locale::Es => "Registro de dominio a punto de caducar",
+            },
+        }
+    }
+
+    pub fn message(&self, locale: Locale) -> String {
+        match self {
+            Self::Outage { uri, diagnostics } => {
+                let message = match locale {
+                    Locale::En => format!("The failure rate of {uri} exceeds the SLA"),
+                    Locale::De => format!("Die Fehlerrate von {uri} überschreitet das SLA"),
+                    Locale::Fr => format!("Le taux d'échec de {uri} dépasse le SLA"),
+                    Locale::Es => format!("La tasa de fallos de {uri} supera el SLA"),
+                };
+
+                let Some(diagnostics) = diagnostics else {
+                    return message;
+                };
+
+                let heading = match locale {
+                    Locale::En => "Diagnostics from the latest failure",
+                    Locale::De => "Diagnose des letzten Fehlers",
+                    Locale::Fr => "Diagnostic du dernier échec",
+                    Locale::Es => "Diagnóstico del último fallo",
+                };
+
+                format!("{message}\n\n{heading}:\n{diagnostics}")
+            }
+            Self::LatencyRegression {
+                uri,
+                relative_latency_millis,
+                limit_millis,
+            } => match locale {
+                Locale::En => format!(
+                    "{uri} is {relative_latency_millis}ms slower than its baseline, over the limit of {limit_millis}ms"
+                ),
+                Locale::De => format!(
+                    "{uri} ist {relative_latency_millis}ms langsamer als seine Referenz und überschreitet das Limit von {limit_millis}ms"
+                ),
+                Locale::Fr => format!(
+                    "{uri} est {relative_latency_millis}ms plus lent que sa référence, au-delà de la limite de {limit_millis}ms"
+                ),
+                Locale::Es => format!(
+                    "{uri} es {relative_latency_millis}ms más lento que su referencia, por encima del límite de {limit_millis}ms"
+                ),
+            },
+            Self::RedirectChainChanged {
+                uri,
+                previous,
+                current,
+            } => match locale {
+                Locale::En => format!(
+                    "The redirects for {uri} changed from:\n{previous}\n\nto:\n{current}"
+                ),
+                Locale::De => format!(
+                    "Die Weiterleitungen für {uri} haben sich geändert von:\n{previous}\n\nzu:\n{current}"
+                ),
+                Locale::Fr => format!(
+                    "Les redirections de {uri} sont passées de :\n{previous}\n\nà :\n{current}"
+                ),
+                Locale::Es => format!(
+                    "Las redirecciones de {uri} cambiaron de:\n{previous}\n\na:\n{current}"
+                ),
+            },
+            Self::UnexpectedAddress { uri, address } => match locale {
+                Locale::En => {
+                    format!("{uri} was served from {address}, outside of its expected networks")
+                }
+                Locale::De => format!(
+                    "{uri} wurde von {address} ausgeliefert, außerhalb seiner erwarteten Netzwerke"
+                ),
+                Locale::Fr => format!(
+                    "{uri} a été servi depuis {address}, en dehors de ses réseaux attendus"
+                ),
+                Locale::Es => format!(
+                    "{uri} se sirvió desde {address}, fuera de sus redes esperadas"
+                ),
+            },
+            Self::BodyChanged {
+                uri,
+                previous,
+                current,
+            } => {
+                // The whole hash is more than anyone will compare by eye
+                let previous = &previous[..previous.len().min(12)];
+                let current = &current[..current.len().min(12)];
+
+                match locale {
+                    Locale::En => format!(
+                        "The response body of {uri} changed, from hash {previous} to {current}"
+                    ),
+                    Locale::De => format!(
+                        "Der Antwortinhalt von {uri} hat sich geändert, von Hash {previous} zu {current}"
+                    ),
+                    Locale::Fr => format!(
+                        "Le corps de la réponse de {uri} a changé, du hash {previous} à {current}"
+                    ),
+                    Locale::Es => format!(
+                        "El cuerpo de la respuesta de {uri} cambió, del hash {previous} a {current}"
+                    ),
+                }
+            }
+            Self::DomainExpiring {
+                uri,
+                domain,
+                expires_on,
+                days_left,
+            } if *days_left < 0 => match locale {
+                Locale::En => format!("The domain {domain} of {uri} expired on {expires_on}"),
+                Locale::De => format!("Die Domain {domain} von {uri} ist am {expires_on} abgelaufen"),
+                Locale::Fr => format!("Le domaine {domain} de {uri} a expiré le {expires_on}"),
+                Locale::Es => format!("El dominio {domain} de {uri} caducó el {expires_on}"),
+            },
+            Self::DomainExpiring {
+                uri,
+                domain,
+                expires_on,
+                days_left,
+            } => match locale {
+                Locale::En => format!(
+                    "The domain {domain} of {uri} expires on {expires_on}, in {days_left} days"
+                ),
+                Locale::De => format!(
+                    "Die Domain {domain} von {uri} läuft am {expires_on} ab, in {days_left} Tagen"
+                ),
+                Locale::Fr => format!(
+                    "Le domaine {domain} de {uri} expire le {expires_on}, dans {days_left} jours"
+                ),
+                Locale::Es => format!(
+                    "El dominio {domain} de {uri} caduca el {expires_on}, dentro de {days_left} días"
+                ),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::locale::{Alert, Locale};
+
+    #[test]
+    fn locales_are_parsed_from_their_codes() {
+        for locale in Locale::ALL {
+            assert_eq!(Locale::from_column(locale.as_str()), Some(locale));
+        }
+
+        assert_eq!(Locale::from_column("EN"), None);
+        assert_eq!(Locale::from_column("pt"), None);
+    }
+
+    #[test]
+    fn alerts_are_written_in_the_requested_locale() {
+        let alert = Alert::Outage {
+            uri: "https://example.com",
+            diagnostics: None,
+        };
+
+        assert_eq!(alert.subject(Locale::En), "Outage detected");
+        assert_eq!(
+            alert.message(Locale::En),
+            "The failure rate of https://example.com exceeds the SLA"
+        );
+
+        assert_eq!(alert.subject(Locale::De), "Ausfall erkannt");
+        assert_eq!(
+            alert.message(Locale::De),
+            "Die Fehlerrate von https://example.com überschreitet das SLA"
+        );
+    }
+
+    #[test]
+    fn outage_alerts_include_any_diagnostics() {
+        let alert = Alert::Outage {
+            uri: "https://example.com",
+            diagnostics: Some("DNS: example.com resolved in 3ms to 93.184.215.14"),
+        };
+
+        assert_eq!(
+            alert.message(Locale::En),
+            "The failure rate of https://example.com exceeds the SLA\n\nDiagnostics from the latest failure:\nDNS: example.com resolved in 3ms to 93.184.215.14"
+        );
+    }
+
+    #[test]
+    fn domain_alerts_say_whether_the_domain_has_already_expired() {
+        let expiring = Alert::DomainExpiring {
+            uri: "https://shop.example.com",
+            domain: "example.com",
+            expires_on: "2026-11-01",
+            days_left: 16,
+        };
+
+        assert_eq!(
+            expiring.message(Locale::En),
+            "The domain example.com of https://shop.example.com expires on 2026-11-01, in 16 days"
+        );
+
+        let expired = Alert::DomainExpiring {
+            uri: "https://shop.example.com",
+            domain: "example.com",
+            expires_on: "2026-11-01",
+            days_left: -1,
+        };
+
+        assert_eq!(
+            expired.message(Locale::En),
+            "The domain example.com of https://shop.example.com expired on 2026-11-01"
+        );
+    }
+
+    #[test]
+    fn elapsed_times_follow_the_word_order_of_each_locale() {
+        assert_eq!(Locale::En.checked_ago("5m"), "checked 5m ago");
+        assert_eq!(Locale::Fr.checked_ago("5m"), "vérifié il y a 5m");
+    }
+}
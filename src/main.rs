@@ -1,27 +1,12 @@
-use std::net::SocketAddr;
-use std::str::FromStr;
-
-use aws_config::BehaviorVersion;
 use color_eyre::eyre::Result;
-use poller::{AlertThreshold, PollerConfiguration};
-use reqwest::Client;
-use sqlx::PgPool;
-use tokio::net::TcpListener;
 use tracing::level_filters::LevelFilter;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
+use uptime::cli::Command;
+use uptime::diagnostics::RecentErrors;
 
-mod persistence;
-mod poller;
-mod router;
-mod templates;
-mod utils;
-
-use crate::poller::Poller;
-use crate::utils::get_env_var;
-
-async fn setup() -> Result<PgPool> {
+fn setup() -> Result<RecentErrors> {
     dotenvy::dotenv().ok();
 
     color_eyre::install()?;
@@ -31,36 +16,40 @@ async fn setup() -> Result<PgPool> {
         .with_default_directive(LevelFilter::INFO.into())
         .from_env()?;
 
+    let recent_errors = RecentErrors::from_env()?;
+
     tracing_subscriber::registry()
         .with(fmt_layer)
         .with(env_filter_layer)
+        .with(recent_errors.clone())
         .init();
 
-    let pool = crate::persistence::bootstrap().await?;
+    uptime::diagnostics::log_panics();
 
-    Ok(pool)
+    Ok(recent_errors)
 }
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
-    let pool = setup().await?;
-
-    let sdk_config = aws_config::load_defaults(BehaviorVersion::latest()).await;
-    let sns_client = aws_sdk_sns::Client::new(&sdk_config);
-
-    let topic = get_env_var("SNS_TOPIC")?;
-    let configuration = PollerConfiguration::new(AlertThreshold::default(), topic);
-
-    let http_client = Client::new();
-    let poller = Poller::new(pool.clone(), http_client, sns_client, configuration);
-
-    let router = crate::router::build(pool.clone())?;
-    let addr = SocketAddr::from_str(&get_env_var("SERVER_ADDR")?)?;
-    let listener = TcpListener::bind(addr).await?;
-
-    tracing::info!(%addr, "listening for incoming requests");
-
-    let _ = tokio::join!(poller.run(), axum::serve(listener, router));
-
-    Ok(())
+    let command = Command::parse(std::env::args().skip(1))?;
+    let recent_errors = setup()?;
+
+    // The doctor reports a database that cannot be reached, so it is the one command that does
+    // not bootstrap it first
+    let bootstrap = uptime::persistence::bootstrap;
+
+    match command {
+        Command::Serve => uptime::serve(bootstrap().await?, recent_errors).await,
+        Command::Doctor => uptime::doctor::run().await,
+        Command::ExportConfig { path } => {
+            uptime::cli::export_config(&bootstrap().await?, path).await
+        }
+        Command::ImportConfig { path, requested_by } => {
+            uptime::cli::import_config(&bootstrap().await?, path, requested_by).await
+        }
+        Command::Compact {
+            older_than,
+            resolution,
+        } => uptime::cli::compact(&bootstrap().await?, older_than, resolution).await,
+    }
 }
@@ -0,0 +1,109 @@
+use std::time::Instant;
+
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use sqlx::PgPool;
+
+/// Analyzes and reindexes the tables written to by every check once a day, as the steady stream
+/// of writes leaves their statistics and indexes worse than autovacuum alone keeps them.
+pub struct Maintenance {
+    pool: PgPool,
+    /// The time of day in UTC that maintenance runs at.
+    at: NaiveTime,
+}
+
+impl Maintenance {
+    pub fn new(pool: PgPool, at: NaiveTime) -> Self {
+        Self { pool, at }
+    }
+
+    /// Schedules maintenance for the time in `MAINTENANCE_TIME`, such as `03:30`, or returns
+    /// `None` if it is not set.
+    pub fn from_env(pool: PgPool) -> Result<Option<Self>> {
+        let Ok(at) = std::env::var("MAINTENANCE_TIME") else {
+            return Ok(None);
+        };
+
+        let at = NaiveTime::parse_from_str(&at, "%H:%M")
+            .map_err(|_| eyre!("MAINTENANCE_TIME must be a time such as 03:30, not '{at}'"))?;
+
+        Ok(Some(Self::new(pool, at)))
+    }
+
+    pub async fn run(&self) {
+        loop {
+            let now = Utc::now();
+            let delay = (next_run(now, self.at) - now).to_std().unwrap_or_default();
+
+            tracing::info!(?delay, "waiting to run database maintenance");
+            tokio::time::sleep(delay).await;
+
+            if let Err(e) = self.perform().await {
+                tracing::warn!(%e, "failed to run database maintenance");
+            }
+        }
+    }
+
+    async fn perform(&self) -> Result<()> {
+        let start = Instant::now();
+        crate::persistence::analyze_hot_tables(&self.pool).await?;
+        let analyzed_in = start.elapsed();
+
+        let start = Instant::now();
+        crate::persistence::reindex_hot_tables(&self.pool).await?;
+        let reindexed_in = start.elapsed();
+
+        tracing::info!(?analyzed_in, ?reindexed_in, "ran database maintenance");
+
+        Ok(())
+    }
+}
+
+/// Finds the next time of day `at` strictly after `now`.
+fn next_run(now: DateTime<Utc>, at: NaiveTime) -> DateTime<Utc> {
+    let candidate = now.date_naive().and_time(at).and_utc();
+
+    if candidate > now {
+        candidate
+    } else {
+        candidate + Duration::days(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveTime, TimeZone, Utc};
+    use color_eyre::eyre::Result;
+    use sqlx::PgPool;
+
+    #[test]
+    fn maintenance_runs_at_the_next_occurrence_of_its_time() {
+        let at = NaiveTime::from_hms_opt(3, 30, 0).unwrap();
+
+        let before = Utc.with_ymd_and_hms(2024, 1, 3, 1, 0, 0).unwrap();
+        let expected = Utc.with_ymd_and_hms(2024, 1, 3, 3, 30, 0).unwrap();
+        assert_eq!(super::next_run(before, at), expected);
+
+        let after = Utc.with_ymd_and_hms(2024, 1, 3, 12, 0, 0).unwrap();
+        let expected = Utc.with_ymd_and_hms(2024, 1, 4, 3, 30, 0).unwrap();
+        assert_eq!(super::next_run(after, at), expected);
+
+        // Running exactly on time should not run again straight away
+        let exactly = Utc.with_ymd_and_hms(2024, 1, 3, 3, 30, 0).unwrap();
+        let expected = Utc.with_ymd_and_hms(2024, 1, 4, 3, 30, 0).unwrap();
+        assert_eq!(super::next_run(exactly, at), expected);
+    }
+
+    #[sqlx::test]
+    async fn maintenance_analyzes_the_hot_tables(pool: PgPool) -> Result<()> {
+        let at = NaiveTime::from_hms_opt(3, 30, 0).unwrap();
+        super::Maintenance::new(pool.clone(), at).perform().await?;
+
+        let tables = crate::persistence::fetch_table_sizes(&pool).await?;
+        let query = tables.iter().find(|table| table.name == "query").unwrap();
+
+        assert!(query.last_analyzed_at.is_some());
+
+        Ok(())
+    }
+}
@@ -1,19 +1,35 @@
+use std::net::IpAddr;
 use std::ops::DerefMut;
 
-use chrono::Duration;
+use chrono::{Datelike, Duration, NaiveTime};
 use color_eyre::eyre::Result;
-use serde::Serialize;
-use sqlx::types::chrono::{DateTime, Utc};
+use futures_util::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+use sqlx::types::chrono::{DateTime, NaiveDate, Utc};
+use sqlx::types::Json;
 use sqlx::{PgPool, Postgres};
 use sqlx_bootstrap::{ApplicationConfig, BootstrapConfig, ConnectionConfig, RootConfig};
 use uuid::Uuid;
 
-use crate::poller::FailureReason;
+use crate::config::MonitorConfiguration;
+use crate::locale::Locale;
+use crate::poller::{FailureReason, Timings};
 use crate::utils::get_env_var;
 
-type Transaction = sqlx::Transaction<'static, Postgres>;
+pub type Transaction = sqlx::Transaction<'static, Postgres>;
 
+/// Connects to the database, creating the application's user and database if needed, and applies
+/// any migrations that have not been yet.
 pub async fn bootstrap() -> Result<PgPool> {
+    let pool = connect().await?;
+
+    sqlx::migrate!().run(&pool).await?;
+
+    Ok(pool)
+}
+
+/// Connects to the database, creating the application's user and database if needed.
+pub async fn connect() -> Result<PgPool> {
     let root_username = get_env_var("ROOT_USERNAME")?;
     let root_password = get_env_var("ROOT_PASSWORD")?;
     let root_database = get_env_var("ROOT_DATABASE")?;
@@ -32,22 +48,382 @@ pub async fn bootstrap() -> Result<PgPool> {
     let config = BootstrapConfig::new(root_config, app_config, conn_config);
     let pool = config.bootstrap().await?;
 
-    sqlx::migrate!().run(&pool).await?;
-
     Ok(pool)
 }
 
+/// Controls whether an origin appears on the public status page and API.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    Public,
+    #[default]
+    Private,
+}
+
+impl Visibility {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Public => "public",
+            Self::Private => "private",
+        }
+    }
+
+    fn from_column(value: &str) -> Self {
+        match value {
+            "public" => Self::Public,
+            _ => Self::Private,
+        }
+    }
+}
+
+/// The HTTP method used when checking an origin.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HttpMethod {
+    #[default]
+    Get,
+    Head,
+    Post,
+    Put,
+}
+
+impl HttpMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Get => "GET",
+            Self::Head => "HEAD",
+            Self::Post => "POST",
+            Self::Put => "PUT",
+        }
+    }
+
+    fn from_column(value: &str) -> Self {
+        match value {
+            "HEAD" => Self::Head,
+            "POST" => Self::Post,
+            "PUT" => Self::Put,
+            _ => Self::Get,
+        }
+    }
+
+    /// Whether requests made with this method can carry a body.
+    pub fn allows_body(&self) -> bool {
+        matches!(self, Self::Post | Self::Put)
+    }
+}
+
+/// Which version of HTTP checks of an origin are made with.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpVersion {
+    /// Whichever version the server negotiates, which is HTTP/2 over TLS if it supports it.
+    #[default]
+    Auto,
+    Http1,
+    /// HTTP/2 without falling back, so a broken HTTP/2 path cannot hide behind HTTP/1.1.
+    Http2,
+}
+
+impl HttpVersion {
+    pub const ALL: [Self; 3] = [Self::Auto, Self::Http1, Self::Http2];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Http1 => "http1",
+            Self::Http2 => "http2",
+        }
+    }
+
+    pub fn from_column(value: &str) -> Self {
+        match value {
+            "http1" => Self::Http1,
+            "http2" => Self::Http2,
+            _ => Self::Auto,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Auto => "Negotiated",
+            Self::Http1 => "HTTP/1.1 only",
+            Self::Http2 => "HTTP/2 only",
+        }
+    }
+}
+
+/// Whether HTTP checks of an origin reuse connections from earlier checks, which hides how long
+/// connecting and the TLS handshake take.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionMode {
+    /// Reuses connections that were kept alive, as most clients of the origin would.
+    #[default]
+    Reused,
+    /// Opens a new connection for every check.
+    Fresh,
+    /// Reuses connections, then also times a request over a new connection to compare.
+    Both,
+}
+
+impl ConnectionMode {
+    pub const ALL: [Self; 3] = [Self::Reused, Self::Fresh, Self::Both];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Reused => "reused",
+            Self::Fresh => "fresh",
+            Self::Both => "both",
+        }
+    }
+
+    pub fn from_column(value: &str) -> Self {
+        match value {
+            "fresh" => Self::Fresh,
+            "both" => Self::Both,
+            _ => Self::Reused,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Reused => "Reuse connections",
+            Self::Fresh => "New connection every check",
+            Self::Both => "Measure warm and cold",
+        }
+    }
+}
+
+/// The period an origin's alert rules and reliability are evaluated over, either ending now or
+/// aligned to the calendar, such as for SLAs that are defined per calendar month.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertWindow {
+    /// The configured length of time up to now, such as the last 5 minutes.
+    #[default]
+    Rolling,
+    /// Since midnight UTC.
+    Day,
+    /// Since midnight UTC on Monday.
+    Week,
+    /// Since midnight UTC on the first of the month.
+    Month,
+}
+
+impl AlertWindow {
+    pub const ALL: [Self; 4] = [Self::Rolling, Self::Day, Self::Week, Self::Month];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Rolling => "rolling",
+            Self::Day => "day",
+            Self::Week => "week",
+            Self::Month => "month",
+        }
+    }
+
+    pub fn from_column(value: &str) -> Self {
+        match value {
+            "day" => Self::Day,
+            "week" => Self::Week,
+            "month" => Self::Month,
+            _ => Self::Rolling,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Rolling => "Rolling",
+            Self::Day => "Calendar day",
+            Self::Week => "Calendar week",
+            Self::Month => "Calendar month",
+        }
+    }
+
+    /// When the window that `now` falls in starts, where rolling windows are `rolling` long.
+    pub fn start(&self, now: DateTime<Utc>, rolling: Duration) -> DateTime<Utc> {
+        let today = now.date_naive();
+
+        let first_day = match self {
+            Self::Rolling => return now - rolling,
+            Self::Day => today,
+            Self::Week => today - Duration::days(today.weekday().num_days_from_monday().into()),
+            Self::Month => today.with_day(1).unwrap_or(today),
+        };
+
+        first_day.and_time(NaiveTime::MIN).and_utc()
+    }
+}
+
+/// Which IP versions HTTP checks of an origin connect over.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressFamily {
+    /// Whichever address connects first, which can hide an outage of one family behind the other.
+    #[default]
+    Any,
+    Ipv4,
+    Ipv6,
+    /// Checks over IPv4 and IPv6 separately, recording each.
+    Both,
+}
+
+impl AddressFamily {
+    pub const ALL: [Self; 4] = [Self::Any, Self::Ipv4, Self::Ipv6, Self::Both];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Any => "any",
+            Self::Ipv4 => "ipv4",
+            Self::Ipv6 => "ipv6",
+            Self::Both => "both",
+        }
+    }
+
+    pub fn from_column(value: &str) -> Self {
+        match value {
+            "ipv4" => Self::Ipv4,
+            "ipv6" => Self::Ipv6,
+            "both" => Self::Both,
+            _ => Self::Any,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Any => "IPv4 or IPv6",
+            Self::Ipv4 => "IPv4",
+            Self::Ipv6 => "IPv6",
+            Self::Both => "IPv4 and IPv6",
+        }
+    }
+
+    /// The families to make separate checks over, where `Any` leaves it to the connection.
+    pub fn checks(&self) -> &'static [Self] {
+        match self {
+            Self::Any => &[Self::Any],
+            Self::Ipv4 => &[Self::Ipv4],
+            Self::Ipv6 => &[Self::Ipv6],
+            Self::Both => &[Self::Ipv4, Self::Ipv6],
+        }
+    }
+
+    pub fn allows(&self, ip: IpAddr) -> bool {
+        match self {
+            Self::Ipv4 => ip.is_ipv4(),
+            Self::Ipv6 => ip.is_ipv6(),
+            Self::Any | Self::Both => true,
+        }
+    }
+
+    /// The family recorded against a check, which is only known when one was required.
+    fn recorded(&self) -> Option<&'static str> {
+        matches!(self, Self::Ipv4 | Self::Ipv6).then(|| self.as_str())
+    }
+}
+
 #[derive(Serialize)]
 pub struct Origin {
     pub origin_uid: Uuid,
     pub uri: String,
+    pub alerts_enabled: bool,
+    pub method: HttpMethod,
+    pub request_body: Option<String>,
+    pub request_content_type: Option<String>,
+    /// Text that fails the check if it appears in the response body, such as "maintenance mode".
+    pub forbidden_text: Option<String>,
+    /// A JSONPath assertion the response must satisfy, such as `$.status == "ok"`.
+    pub json_assertion: Option<String>,
+    /// Free-form notes for whoever is looking at the origin, such as known quirks.
+    pub notes: Option<String>,
+    /// The status codes that count as a successful check, such as `200-299,301`.
+    pub expected_status: Option<String>,
+    /// Another origin whose latency is subtracted from this one's to give its relative latency.
+    pub baseline_origin_uid: Option<Uuid>,
+    /// The relative latency above which an alert is sent.
+    pub relative_latency_limit_millis: Option<i64>,
+    /// Whether checks send conditional requests and verify the caching behaviour of responses.
+    pub cache_validation: bool,
+    /// The most redirects a check follows, where `0` records the redirect itself.
+    pub redirect_limit: Option<i32>,
+    /// Where the first response must redirect to, such as `https://example.com/`.
+    pub expected_location: Option<String>,
+    /// How long a check can take before it fails, overriding the default of the poller.
+    pub timeout_millis: Option<i64>,
+    /// Credentials sent with HTTP checks, encrypted with the key of the deployment.
+    #[serde(skip)]
+    pub encrypted_credentials: Option<Vec<u8>>,
+    /// The private key and certificate presented for mutual TLS, encrypted like credentials.
+    #[serde(skip)]
+    pub encrypted_client_identity: Option<Vec<u8>>,
+    pub address_family: AddressFamily,
+    /// The HTTP or SOCKS5 proxy HTTP checks are sent through, overriding the one of the poller.
+    pub proxy: Option<String>,
+    /// The networks HTTP checks are expected to be served from, such as `203.0.113.0/24`.
+    pub expected_networks: Option<String>,
+    pub http_version: HttpVersion,
+    /// How many times a failed check is retried before the failure is recorded.
+    pub retries: i32,
+    /// The response headers recorded with each HTTP check, such as `X-Request-Id, Server`.
+    pub captured_headers: Option<String>,
+    pub alert_window: AlertWindow,
+    /// The locations of the pollers that check the origin, such as `local, eu-west`, or every
+    /// poller if unset.
+    pub probe_locations: Option<String>,
+    /// The User-Agent HTTP checks send, overriding the one of the poller.
+    pub user_agent: Option<String>,
+    /// Whether HTTP checks add a random query parameter, so caches cannot answer for the origin.
+    pub cache_busting: bool,
+    pub connection_mode: ConnectionMode,
+    /// Headers responses must have, one per line as a name or `name: value`.
+    pub expected_headers: Option<String>,
+    /// Whether checks hash the response body and record when it changes.
+    pub body_change_detection: bool,
+    /// When the origin is checked, as a cron expression such as `*/5 9-17 * * 1-5`, instead of
+    /// every round.
+    pub schedule: Option<String>,
+    pub tags: Vec<String>,
+    /// The latency checks are expected to stay under, recorded for the exports rather than alerted
+    /// on.
+    pub expected_latency_millis: Option<i64>,
+    /// Whether the poller has stopped checking the origin, keeping what has been recorded so far.
+    pub paused: bool,
+}
+
+/// What an origin's checks are expected to see, for tools that read the configuration alongside
+/// the results.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Expectations {
+    pub expected_status: Option<String>,
+    pub expected_latency_millis: Option<i64>,
+    pub relative_latency_limit_millis: Option<i64>,
+    pub json_assertion: Option<String>,
+    pub forbidden_text: Option<String>,
+    pub expected_headers: Option<String>,
+    pub expected_location: Option<String>,
+    pub expected_networks: Option<String>,
+}
+
+impl Origin {
+    pub fn expectations(&self) -> Expectations {
+        Expectations {
+            expected_status: self.expected_status.clone(),
+            expected_latency_millis: self.expected_latency_millis,
+            relative_latency_limit_millis: self.relative_latency_limit_millis,
+            json_assertion: self.json_assertion.clone(),
+            forbidden_text: self.forbidden_text.clone(),
+            expected_headers: self.expected_headers.clone(),
+            expected_location: self.expected_location.clone(),
+            expected_networks: self.expected_networks.clone(),
+        }
+    }
 }
 
 pub async fn insert_origin(pool: &PgPool, origin_uid: Uuid, uri: &str) -> Result<()> {
     sqlx::query!(
         r#"
-            INSERT INTO origin (origin_uid, uri)
-            VALUES ($1, $2)
+            INSERT INTO origin (origin_uid, uri, display_order)
+            VALUES ($1, $2, (SELECT COALESCE(MAX(display_order), 0) + 1 FROM origin))
         "#,
         origin_uid,
         uri,
@@ -58,226 +434,4907 @@ pub async fn insert_origin(pool: &PgPool, origin_uid: Uuid, uri: &str) -> Result
     Ok(())
 }
 
-pub async fn fetch_origins(pool: &PgPool) -> Result<Vec<Origin>> {
-    let origins = sqlx::query_as!(
-        Origin,
+/// The channel notified by a trigger whenever an origin is added or how it is checked changes.
+pub const ORIGIN_CHANGED: &str = "origin_changed";
+
+pub async fn set_origin_visibility(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    visibility: Visibility,
+) -> Result<()> {
+    sqlx::query!(
         r#"
-            SELECT origin_uid, uri
-            FROM origin
-        "#
+            UPDATE origin
+            SET visibility = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        visibility.as_str(),
     )
-    .fetch_all(pool)
+    .execute(pool)
     .await?;
 
-    Ok(origins)
-}
-
-pub struct IndexOrigin {
-    pub uri: String,
-    pub status: i16,
-    pub latency_millis: i64,
-    pub queried_at: DateTime<Utc>,
+    Ok(())
 }
 
-pub async fn fetch_origins_with_most_recent_success_metrics(
-    pool: &PgPool,
-) -> Result<Vec<IndexOrigin>> {
-    let origins = sqlx::query_as!(
-        IndexOrigin,
+pub async fn set_origin_pinned(pool: &PgPool, origin_uid: Uuid, pinned: bool) -> Result<()> {
+    sqlx::query!(
         r#"
-            SELECT DISTINCT ON (o.uri)
-                o.uri,
-                q.status,
-                q.latency_millis,
-                q.queried_at
-            FROM origin o
-            JOIN query q ON o.id = q.origin_id
-            ORDER BY o.uri, q.queried_at DESC
-        "#
+            UPDATE origin
+            SET pinned = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        pinned,
     )
-    .fetch_all(pool)
+    .execute(pool)
     .await?;
 
-    Ok(origins)
+    Ok(())
 }
 
-pub struct OriginFailure {
-    pub uri: String,
-    pub failure_reason: String,
-    pub queried_at: DateTime<Utc>,
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Up,
+    Down,
 }
 
-pub async fn fetch_origins_with_most_recent_failure_metrics(
+/// Swaps the display order of an origin with its neighbour in the given direction, keeping pinned
+/// and unpinned origins separate from each other.
+pub async fn move_origin(pool: &PgPool, origin_uid: Uuid, direction: Direction) -> Result<()> {
+    let query = match direction {
+        Direction::Up => sqlx::query!(
+            r#"
+                WITH current AS (
+                    SELECT id, pinned, display_order
+                    FROM origin
+                    WHERE origin_uid = $1
+                ), neighbour AS (
+                    SELECT o.id, o.display_order
+                    FROM origin o, current c
+                    WHERE o.pinned = c.pinned
+                    AND o.display_order < c.display_order
+                    ORDER BY o.display_order DESC
+                    LIMIT 1
+                )
+                UPDATE origin o
+                SET display_order = CASE WHEN o.id = c.id THEN n.display_order ELSE c.display_order END
+                FROM current c, neighbour n
+                WHERE o.id IN (c.id, n.id)
+            "#,
+            origin_uid,
+        ),
+        Direction::Down => sqlx::query!(
+            r#"
+                WITH current AS (
+                    SELECT id, pinned, display_order
+                    FROM origin
+                    WHERE origin_uid = $1
+                ), neighbour AS (
+                    SELECT o.id, o.display_order
+                    FROM origin o, current c
+                    WHERE o.pinned = c.pinned
+                    AND o.display_order > c.display_order
+                    ORDER BY o.display_order ASC
+                    LIMIT 1
+                )
+                UPDATE origin o
+                SET display_order = CASE WHEN o.id = c.id THEN n.display_order ELSE c.display_order END
+                FROM current c, neighbour n
+                WHERE o.id IN (c.id, n.id)
+            "#,
+            origin_uid,
+        ),
+    };
+
+    query.execute(pool).await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_cost(
     pool: &PgPool,
-) -> Result<Vec<OriginFailure>> {
-    let origins = sqlx::query_as!(
-        OriginFailure,
+    origin_uid: Uuid,
+    cost_per_minute_cents: Option<i64>,
+) -> Result<()> {
+    sqlx::query!(
         r#"
-            SELECT DISTINCT ON (o.uri)
-                o.uri,
-                qfr.name AS failure_reason,
-                qf.queried_at
-            FROM origin o
-            JOIN query_failure qf ON o.id = qf.origin_id
-            JOIN query_failure_reason qfr ON qfr.id = qf.failure_reason_id
-            ORDER BY o.uri, qf.queried_at DESC
-        "#
+            UPDATE origin
+            SET cost_per_minute_cents = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        cost_per_minute_cents,
     )
-    .fetch_all(pool)
+    .execute(pool)
     .await?;
 
-    Ok(origins)
+    Ok(())
 }
 
-pub async fn insert_query(
-    tx: &mut Transaction,
-    origin_uid: Uuid,
-    status: u16,
-    latency_millis: i64,
-    queried_at: DateTime<Utc>,
-) -> Result<Uuid> {
-    let query_uid = Uuid::new_v4();
-
+pub async fn set_origin_tags(pool: &PgPool, origin_uid: Uuid, tags: &[String]) -> Result<()> {
     sqlx::query!(
         r#"
-            INSERT INTO query (query_uid, origin_id, status, latency_millis, queried_at)
-            VALUES (
-                $1,
-                (SELECT id FROM origin WHERE origin_uid = $2),
-                $3,
-                $4,
-                $5
-            )
+            UPDATE origin
+            SET tags = $2
+            WHERE origin_uid = $1
         "#,
-        query_uid,
         origin_uid,
-        status as i16,
-        latency_millis,
-        queried_at
+        tags,
     )
-    .execute(tx.deref_mut())
+    .execute(pool)
     .await?;
 
-    Ok(query_uid)
+    Ok(())
 }
 
-pub async fn insert_query_failure(
+pub async fn set_origin_alerts_enabled(
     tx: &mut Transaction,
     origin_uid: Uuid,
-    failure_reason: FailureReason,
-    queried_at: DateTime<Utc>,
-) -> Result<Uuid> {
-    let query_failure_uid = Uuid::new_v4();
-
+    alerts_enabled: bool,
+) -> Result<()> {
     sqlx::query!(
         r#"
-            INSERT INTO query_failure (query_failure_uid, origin_id, failure_reason_id, queried_at)
-            VALUES (
-                $1,
-                (SELECT id FROM origin WHERE origin_uid = $2),
-                (SELECT id FROM query_failure_reason WHERE name = $3),
-                $4
-            )
+            UPDATE origin
+            SET alerts_enabled = $2
+            WHERE origin_uid = $1
         "#,
-        query_failure_uid,
         origin_uid,
-        failure_reason.as_str(),
-        queried_at
+        alerts_enabled,
     )
     .execute(tx.deref_mut())
     .await?;
 
-    Ok(query_failure_uid)
+    Ok(())
 }
 
-pub async fn failure_rate_exceeded(
-    pool: &PgPool,
-    origin_uid: Uuid,
-    limit: u16,
-    period: Duration,
-) -> Result<bool> {
-    let end = Utc::now();
-    let start = end - period;
+pub async fn set_origin_paused(pool: &PgPool, origin_uid: Uuid, paused: bool) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET paused = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        paused,
+    )
+    .execute(pool)
+    .await?;
 
-    let exceeded = sqlx::query_scalar!(
+    Ok(())
+}
+
+/// The version of an origin, which goes up by one each time an edit changes it.
+pub async fn fetch_origin_version(pool: &PgPool, origin_uid: Uuid) -> Result<Option<i64>> {
+    let version = sqlx::query_scalar!(
         r#"
-            SELECT COUNT(*) >= $2
-            FROM query_failure qf
-            JOIN origin o ON o.id = qf.origin_id
-            WHERE o.origin_uid = $1
-            AND qf.queried_at BETWEEN $3 AND $4
+            SELECT version
+            FROM origin
+            WHERE origin_uid = $1
         "#,
         origin_uid,
-        limit as i32,
-        start,
-        end,
     )
-    .fetch_one(pool)
-    .await?
-    .expect("Count returned a null value");
+    .fetch_optional(pool)
+    .await?;
 
-    Ok(exceeded)
+    Ok(version)
 }
 
-pub async fn insert_notification(
-    pool: &PgPool,
-    origin_uid: Uuid,
-    topic: &str,
-    subject: &str,
-    message: &str,
-    created_at: DateTime<Utc>,
-) -> Result<Uuid> {
-    let notification_uid = Uuid::new_v4();
+/// Claims `version` of an origin for an edit made from it by incrementing it, in a single
+/// statement so that of several edits made from the same version only one can claim it. Returns
+/// whether the origin was still at `version`.
+pub async fn claim_origin_version(pool: &PgPool, origin_uid: Uuid, version: i64) -> Result<bool> {
+    let result = sqlx::query!(
+        r#"
+            UPDATE origin
+            SET version = version + 1
+            WHERE origin_uid = $1 AND version = $2
+        "#,
+        origin_uid,
+        version,
+    )
+    .execute(pool)
+    .await?;
 
+    Ok(result.rows_affected() == 1)
+}
+
+/// Removes an origin along with everything recorded about it.
+pub async fn delete_origin(tx: &mut Transaction, origin_uid: Uuid) -> Result<()> {
     sqlx::query!(
         r#"
-            INSERT INTO notification (notification_uid, origin_id, topic, subject, message, created_at)
-            VALUES (
-                $1,
-                (SELECT id FROM origin WHERE origin_uid = $2),
-                $3,
-                $4,
-                $5,
-                $6
+            WITH removed AS (
+                SELECT id
+                FROM origin
+                WHERE origin_uid = $1
+            ), deleted_queries AS (
+                DELETE FROM query
+                WHERE origin_id IN (SELECT id FROM removed)
+            ), deleted_query_failures AS (
+                DELETE FROM query_failure
+                WHERE origin_id IN (SELECT id FROM removed)
+            ), deleted_notifications AS (
+                DELETE FROM notification
+                WHERE origin_id IN (SELECT id FROM removed)
+            ), deleted_health_scores AS (
+                DELETE FROM health_score
+                WHERE origin_id IN (SELECT id FROM removed)
             )
+            DELETE FROM origin
+            WHERE id IN (SELECT id FROM removed)
         "#,
-        notification_uid,
         origin_uid,
-        topic,
-        subject,
-        message,
-        created_at
     )
-    .execute(pool)
+    .execute(tx.deref_mut())
     .await?;
 
-    Ok(notification_uid)
+    Ok(())
 }
 
-pub async fn latest_notification_older_than(
+pub async fn fetch_origins(pool: &PgPool) -> Result<Vec<Origin>> {
+    let origins = sqlx::query!(
+        r#"
+            SELECT
+                origin_uid,
+                uri,
+                alerts_enabled,
+                method,
+                request_body,
+                request_content_type,
+                forbidden_text,
+                json_assertion,
+                notes,
+                expected_status,
+                baseline_origin_uid,
+                relative_latency_limit_millis,
+                cache_validation,
+                redirect_limit,
+                expected_location,
+                timeout_millis,
+                encrypted_credentials,
+                encrypted_client_identity,
+                address_family,
+                proxy,
+                expected_networks,
+                http_version,
+                retries,
+                captured_headers,
+                alert_window,
+                probe_locations,
+                user_agent,
+                cache_busting,
+                connection_mode,
+                expected_headers,
+                body_change_detection,
+                schedule,
+                expected_latency_millis,
+                paused,
+                tags
+            FROM origin
+        "#
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| Origin {
+        origin_uid: row.origin_uid,
+        uri: row.uri,
+        alerts_enabled: row.alerts_enabled,
+        method: HttpMethod::from_column(&row.method),
+        request_body: row.request_body,
+        request_content_type: row.request_content_type,
+        forbidden_text: row.forbidden_text,
+        json_assertion: row.json_assertion,
+        notes: row.notes,
+        expected_status: row.expected_status,
+        baseline_origin_uid: row.baseline_origin_uid,
+        relative_latency_limit_millis: row.relative_latency_limit_millis,
+        cache_validation: row.cache_validation,
+        redirect_limit: row.redirect_limit,
+        expected_location: row.expected_location,
+        timeout_millis: row.timeout_millis,
+        encrypted_credentials: row.encrypted_credentials,
+        encrypted_client_identity: row.encrypted_client_identity,
+        address_family: AddressFamily::from_column(&row.address_family),
+        proxy: row.proxy,
+        expected_networks: row.expected_networks,
+        http_version: HttpVersion::from_column(&row.http_version),
+        retries: row.retries,
+        captured_headers: row.captured_headers,
+        alert_window: AlertWindow::from_column(&row.alert_window),
+        probe_locations: row.probe_locations,
+        user_agent: row.user_agent,
+        cache_busting: row.cache_busting,
+        connection_mode: ConnectionMode::from_column(&row.connection_mode),
+        expected_headers: row.expected_headers,
+        body_change_detection: row.body_change_detection,
+        schedule: row.schedule,
+        tags: row.tags,
+        expected_latency_millis: row.expected_latency_millis,
+        paused: row.paused,
+    })
+    .collect();
+
+    Ok(origins)
+}
+
+/// Sets how the poller builds the HTTP request it checks an origin with.
+pub async fn set_origin_request(
     pool: &PgPool,
     origin_uid: Uuid,
-    cooldown: Duration,
-) -> Result<bool> {
-    let boundary = Utc::now() - cooldown;
-
-    let notification = sqlx::query_scalar!(
+    method: HttpMethod,
+    request_body: Option<&str>,
+    request_content_type: Option<&str>,
+) -> Result<()> {
+    sqlx::query!(
         r#"
-            SELECT NOT EXISTS (
-                SELECT
-                FROM notification n
-                JOIN origin o ON o.id = n.origin_id
-                WHERE o.origin_uid = $1
-                AND n.created_at > $2
-                LIMIT 1
-            )
+            UPDATE origin
+            SET method = $2, request_body = $3, request_content_type = $4
+            WHERE origin_uid = $1
         "#,
         origin_uid,
-        boundary,
+        method.as_str(),
+        request_body,
+        request_content_type,
     )
-    .fetch_one(pool)
-    .await?
-    .expect("Exists returned a null value");
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_forbidden_text(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    forbidden_text: Option<&str>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET forbidden_text = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        forbidden_text,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_json_assertion(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    json_assertion: Option<&str>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET json_assertion = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        json_assertion,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_expected_status(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    expected_status: Option<&str>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET expected_status = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        expected_status,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_baseline(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    baseline_origin_uid: Option<Uuid>,
+    relative_latency_limit_millis: Option<i64>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET baseline_origin_uid = $2, relative_latency_limit_millis = $3
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        baseline_origin_uid,
+        relative_latency_limit_millis,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Fetches how much slower an origin was than its baseline on average since `since`, or `None` if
+/// either has no successful checks in that time.
+pub async fn fetch_relative_latency(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    baseline_origin_uid: Uuid,
+    since: DateTime<Utc>,
+) -> Result<Option<i64>> {
+    let relative_latency_millis = sqlx::query_scalar!(
+        r#"
+            WITH average_latency AS (
+                SELECT o.origin_uid, AVG(q.latency_millis) AS latency_millis
+                FROM query q
+                JOIN origin o ON o.id = q.origin_id
+                WHERE o.origin_uid IN ($1, $2)
+                AND q.queried_at >= $3
+                GROUP BY o.origin_uid
+            )
+            SELECT ROUND(origin.latency_millis - baseline.latency_millis)::BIGINT
+            FROM average_latency origin, average_latency baseline
+            WHERE origin.origin_uid = $1
+            AND baseline.origin_uid = $2
+        "#,
+        origin_uid,
+        baseline_origin_uid,
+        since,
+    )
+    .fetch_optional(pool)
+    .await?
+    .flatten();
+
+    Ok(relative_latency_millis)
+}
+
+pub async fn set_origin_proxy(pool: &PgPool, origin_uid: Uuid, proxy: Option<&str>) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET proxy = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        proxy,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_expected_networks(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    expected_networks: Option<&str>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET expected_networks = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        expected_networks,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_probe_locations(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    probe_locations: Option<&str>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET probe_locations = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        probe_locations,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_schedule(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    schedule: Option<&str>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET schedule = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        schedule,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_expected_latency(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    expected_latency_millis: Option<i64>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET expected_latency_millis = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        expected_latency_millis,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_body_change_detection(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    body_change_detection: bool,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET body_change_detection = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        body_change_detection,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_expected_headers(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    expected_headers: Option<&str>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET expected_headers = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        expected_headers,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_captured_headers(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    captured_headers: Option<&str>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET captured_headers = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        captured_headers,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_adaptive_polling(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    adaptive_polling: bool,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET adaptive_polling = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        adaptive_polling,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_cache_validation(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    cache_validation: bool,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET cache_validation = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        cache_validation,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_redirect_policy(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    redirect_limit: Option<i32>,
+    expected_location: Option<&str>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET redirect_limit = $2, expected_location = $3
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        redirect_limit,
+        expected_location,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_sibling_group(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    sibling_group_uid: Option<Uuid>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET sibling_group_uid = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        sibling_group_uid,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_address_family(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    address_family: AddressFamily,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET address_family = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        address_family.as_str(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_http_version(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    http_version: HttpVersion,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET http_version = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        http_version.as_str(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_connection_mode(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    connection_mode: ConnectionMode,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET connection_mode = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        connection_mode.as_str(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_alert_window(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    alert_window: AlertWindow,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET alert_window = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        alert_window.as_str(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_request_options(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    user_agent: Option<&str>,
+    cache_busting: bool,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET user_agent = $2, cache_busting = $3
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        user_agent,
+        cache_busting,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_retries(pool: &PgPool, origin_uid: Uuid, retries: i32) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET retries = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        retries,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_timeout(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    timeout_millis: Option<i64>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET timeout_millis = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        timeout_millis,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_credentials(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    encrypted_credentials: Option<&[u8]>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET encrypted_credentials = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        encrypted_credentials,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_origin_client_identity(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    encrypted_client_identity: Option<&[u8]>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET encrypted_client_identity = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        encrypted_client_identity,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// An origin that belongs to a sibling group, with whether its latest check failed.
+pub struct SiblingGroupMember {
+    pub sibling_group_uid: Uuid,
+    pub origin_uid: Uuid,
+    pub uri: String,
+    pub failing: bool,
+}
+
+/// Fetches every origin in a sibling group, in display order.
+pub async fn fetch_sibling_group_members(pool: &PgPool) -> Result<Vec<SiblingGroupMember>> {
+    let members = sqlx::query_as!(
+        SiblingGroupMember,
+        r#"
+            SELECT
+                o.sibling_group_uid AS "sibling_group_uid!",
+                o.origin_uid,
+                o.uri,
+                EXISTS (
+                    SELECT 1
+                    FROM incident i
+                    WHERE i.origin_id = o.id
+                    AND i.resolved_at IS NULL
+                ) AS "failing!"
+            FROM origin o
+            WHERE o.sibling_group_uid IS NOT NULL
+            ORDER BY o.display_order, o.id
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(members)
+}
+
+pub async fn set_origin_notes(pool: &PgPool, origin_uid: Uuid, notes: Option<&str>) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE origin
+            SET notes = $2
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+        notes,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// A configuration edit to an origin, recorded by a trigger whenever one of its columns changes.
+#[derive(Serialize)]
+pub struct OriginChange {
+    pub field: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// Fetches the most recent `limit` changes to an origin, newest first.
+pub async fn fetch_origin_changes(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    limit: i64,
+) -> Result<Vec<OriginChange>> {
+    let changes = sqlx::query_as!(
+        OriginChange,
+        r#"
+            SELECT oc.field, oc.old_value, oc.new_value, oc.changed_at
+            FROM origin_change oc
+            JOIN origin o ON o.id = oc.origin_id
+            WHERE o.origin_uid = $1
+            ORDER BY oc.changed_at DESC, oc.id DESC
+            LIMIT $2
+        "#,
+        origin_uid,
+        limit,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(changes)
+}
+
+/// Everything that can be configured about an origin, as stored in configuration exports.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OriginConfiguration {
+    pub origin_uid: Uuid,
+    pub uri: String,
+    #[serde(default)]
+    pub visibility: Visibility,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub display_order: i32,
+    #[serde(default)]
+    pub cost_per_minute_cents: Option<i64>,
+    #[serde(default = "enabled")]
+    pub alerts_enabled: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub method: HttpMethod,
+    #[serde(default)]
+    pub request_body: Option<String>,
+    #[serde(default)]
+    pub request_content_type: Option<String>,
+    #[serde(default)]
+    pub forbidden_text: Option<String>,
+    #[serde(default)]
+    pub json_assertion: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub expected_status: Option<String>,
+    #[serde(default)]
+    pub baseline_origin_uid: Option<Uuid>,
+    #[serde(default)]
+    pub relative_latency_limit_millis: Option<i64>,
+    #[serde(default)]
+    pub cache_validation: bool,
+    #[serde(default)]
+    pub redirect_limit: Option<i32>,
+    #[serde(default)]
+    pub expected_location: Option<String>,
+    #[serde(default)]
+    pub sibling_group_uid: Option<Uuid>,
+    #[serde(default)]
+    pub timeout_millis: Option<i64>,
+    #[serde(default)]
+    pub address_family: AddressFamily,
+    #[serde(default)]
+    pub adaptive_polling: bool,
+    #[serde(default)]
+    pub proxy: Option<String>,
+    #[serde(default)]
+    pub expected_networks: Option<String>,
+    #[serde(default)]
+    pub http_version: HttpVersion,
+    #[serde(default)]
+    pub retries: i32,
+    #[serde(default)]
+    pub captured_headers: Option<String>,
+    #[serde(default)]
+    pub alert_window: AlertWindow,
+    #[serde(default)]
+    pub probe_locations: Option<String>,
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    pub cache_busting: bool,
+    #[serde(default)]
+    pub connection_mode: ConnectionMode,
+    #[serde(default)]
+    pub expected_headers: Option<String>,
+    #[serde(default)]
+    pub body_change_detection: bool,
+    #[serde(default)]
+    pub schedule: Option<String>,
+    #[serde(default)]
+    pub expected_latency_millis: Option<i64>,
+    #[serde(default)]
+    pub paused: bool,
+    /// Encrypted with the key of the deployment, so they only work where the same key is used.
+    /// Origins imported without them keep whatever they already had.
+    #[serde(default, with = "crate::credentials::encoded")]
+    pub encrypted_credentials: Option<Vec<u8>>,
+    #[serde(default, with = "crate::credentials::encoded")]
+    pub encrypted_client_identity: Option<Vec<u8>>,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+pub async fn fetch_origin_configurations(pool: &PgPool) -> Result<Vec<OriginConfiguration>> {
+    let origins = sqlx::query!(
+        r#"
+            SELECT
+                origin_uid,
+                uri,
+                visibility,
+                pinned,
+                display_order,
+                cost_per_minute_cents,
+                alerts_enabled,
+                tags,
+                method,
+                request_body,
+                request_content_type,
+                forbidden_text,
+                json_assertion,
+                notes,
+                expected_status,
+                baseline_origin_uid,
+                relative_latency_limit_millis,
+                cache_validation,
+                redirect_limit,
+                expected_location,
+                sibling_group_uid,
+                timeout_millis,
+                address_family,
+                adaptive_polling,
+                proxy,
+                expected_networks,
+                http_version,
+                retries,
+                captured_headers,
+                alert_window,
+                probe_locations,
+                user_agent,
+                cache_busting,
+                connection_mode,
+                expected_headers,
+                body_change_detection,
+                schedule,
+                expected_latency_millis,
+                paused,
+                encrypted_credentials,
+                encrypted_client_identity
+            FROM origin
+            ORDER BY display_order, id
+        "#
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| OriginConfiguration {
+        origin_uid: row.origin_uid,
+        uri: row.uri,
+        visibility: Visibility::from_column(&row.visibility),
+        pinned: row.pinned,
+        display_order: row.display_order,
+        cost_per_minute_cents: row.cost_per_minute_cents,
+        alerts_enabled: row.alerts_enabled,
+        tags: row.tags,
+        method: HttpMethod::from_column(&row.method),
+        request_body: row.request_body,
+        request_content_type: row.request_content_type,
+        forbidden_text: row.forbidden_text,
+        json_assertion: row.json_assertion,
+        notes: row.notes,
+        expected_status: row.expected_status,
+        baseline_origin_uid: row.baseline_origin_uid,
+        relative_latency_limit_millis: row.relative_latency_limit_millis,
+        cache_validation: row.cache_validation,
+        redirect_limit: row.redirect_limit,
+        expected_location: row.expected_location,
+        sibling_group_uid: row.sibling_group_uid,
+        timeout_millis: row.timeout_millis,
+        address_family: AddressFamily::from_column(&row.address_family),
+        adaptive_polling: row.adaptive_polling,
+        proxy: row.proxy,
+        expected_networks: row.expected_networks,
+        http_version: HttpVersion::from_column(&row.http_version),
+        retries: row.retries,
+        captured_headers: row.captured_headers,
+        alert_window: AlertWindow::from_column(&row.alert_window),
+        probe_locations: row.probe_locations,
+        user_agent: row.user_agent,
+        cache_busting: row.cache_busting,
+        connection_mode: ConnectionMode::from_column(&row.connection_mode),
+        expected_headers: row.expected_headers,
+        body_change_detection: row.body_change_detection,
+        schedule: row.schedule,
+        expected_latency_millis: row.expected_latency_millis,
+        paused: row.paused,
+        encrypted_credentials: row.encrypted_credentials,
+        encrypted_client_identity: row.encrypted_client_identity,
+    })
+    .collect();
+
+    Ok(origins)
+}
+
+/// Creates the origin if it does not exist yet, otherwise overwrites its configuration.
+pub async fn upsert_origin_configuration(
+    tx: &mut Transaction,
+    origin: &OriginConfiguration,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            INSERT INTO origin (
+                origin_uid, uri, visibility, pinned, display_order, cost_per_minute_cents, alerts_enabled, tags,
+                method, request_body, request_content_type, forbidden_text, json_assertion,
+                notes, expected_status, baseline_origin_uid, relative_latency_limit_millis, cache_validation,
+                redirect_limit, expected_location, sibling_group_uid, timeout_millis, address_family,
+                adaptive_polling, proxy, expected_networks, http_version, retries, captured_headers,
+                alert_window, probe_locations, user_agent, cache_busting, connection_mode,
+                expected_headers, body_change_detection, schedule, expected_latency_millis,
+                paused, encrypted_credentials, encrypted_client_identity
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31, $32, $33, $34, $35, $36, $37, $38, $39, $40, $41)
+            ON CONFLICT (origin_uid) DO UPDATE
+            SET uri = EXCLUDED.uri,
+                visibility = EXCLUDED.visibility,
+                pinned = EXCLUDED.pinned,
+                display_order = EXCLUDED.display_order,
+                cost_per_minute_cents = EXCLUDED.cost_per_minute_cents,
+                alerts_enabled = EXCLUDED.alerts_enabled,
+                tags = EXCLUDED.tags,
+                method = EXCLUDED.method,
+                request_body = EXCLUDED.request_body,
+                request_content_type = EXCLUDED.request_content_type,
+                forbidden_text = EXCLUDED.forbidden_text,
+                json_assertion = EXCLUDED.json_assertion,
+                notes = EXCLUDED.notes,
+                expected_status = EXCLUDED.expected_status,
+                baseline_origin_uid = EXCLUDED.baseline_origin_uid,
+                relative_latency_limit_millis = EXCLUDED.relative_latency_limit_millis,
+                cache_validation = EXCLUDED.cache_validation,
+                redirect_limit = EXCLUDED.redirect_limit,
+                expected_location = EXCLUDED.expected_location,
+                sibling_group_uid = EXCLUDED.sibling_group_uid,
+                timeout_millis = EXCLUDED.timeout_millis,
+                address_family = EXCLUDED.address_family,
+                adaptive_polling = EXCLUDED.adaptive_polling,
+                proxy = EXCLUDED.proxy,
+                expected_networks = EXCLUDED.expected_networks,
+                http_version = EXCLUDED.http_version,
+                retries = EXCLUDED.retries,
+                captured_headers = EXCLUDED.captured_headers,
+                alert_window = EXCLUDED.alert_window,
+                probe_locations = EXCLUDED.probe_locations,
+                user_agent = EXCLUDED.user_agent,
+                cache_busting = EXCLUDED.cache_busting,
+                connection_mode = EXCLUDED.connection_mode,
+                expected_headers = EXCLUDED.expected_headers,
+                body_change_detection = EXCLUDED.body_change_detection,
+                schedule = EXCLUDED.schedule,
+                expected_latency_millis = EXCLUDED.expected_latency_millis,
+                paused = EXCLUDED.paused,
+                encrypted_credentials = COALESCE(EXCLUDED.encrypted_credentials, origin.encrypted_credentials),
+                encrypted_client_identity = COALESCE(EXCLUDED.encrypted_client_identity, origin.encrypted_client_identity)
+        "#,
+        origin.origin_uid,
+        origin.uri,
+        origin.visibility.as_str(),
+        origin.pinned,
+        origin.display_order,
+        origin.cost_per_minute_cents,
+        origin.alerts_enabled,
+        &origin.tags,
+        origin.method.as_str(),
+        origin.request_body,
+        origin.request_content_type,
+        origin.forbidden_text,
+        origin.json_assertion,
+        origin.notes,
+        origin.expected_status,
+        origin.baseline_origin_uid,
+        origin.relative_latency_limit_millis,
+        origin.cache_validation,
+        origin.redirect_limit,
+        origin.expected_location,
+        origin.sibling_group_uid,
+        origin.timeout_millis,
+        origin.address_family.as_str(),
+        origin.adaptive_polling,
+        origin.proxy,
+        origin.expected_networks,
+        origin.http_version.as_str(),
+        origin.retries,
+        origin.captured_headers,
+        origin.alert_window.as_str(),
+        origin.probe_locations,
+        origin.user_agent,
+        origin.cache_busting,
+        origin.connection_mode.as_str(),
+        origin.expected_headers,
+        origin.body_change_detection,
+        origin.schedule,
+        origin.expected_latency_millis,
+        origin.paused,
+        origin.encrypted_credentials,
+        origin.encrypted_client_identity,
+    )
+    .execute(tx.deref_mut())
+    .await?;
+
+    Ok(())
+}
+
+/// Removes every origin not in `retained`, along with everything recorded about them.
+pub async fn delete_origins_except(tx: &mut Transaction, retained: &[Uuid]) -> Result<u64> {
+    let result = sqlx::query!(
+        r#"
+            WITH removed AS (
+                SELECT id
+                FROM origin
+                WHERE NOT (origin_uid = ANY($1))
+            ), deleted_queries AS (
+                DELETE FROM query
+                WHERE origin_id IN (SELECT id FROM removed)
+            ), deleted_query_failures AS (
+                DELETE FROM query_failure
+                WHERE origin_id IN (SELECT id FROM removed)
+            ), deleted_notifications AS (
+                DELETE FROM notification
+                WHERE origin_id IN (SELECT id FROM removed)
+            ), deleted_health_scores AS (
+                DELETE FROM health_score
+                WHERE origin_id IN (SELECT id FROM removed)
+            )
+            DELETE FROM origin
+            WHERE id IN (SELECT id FROM removed)
+        "#,
+        retained,
+    )
+    .execute(tx.deref_mut())
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub async fn fetch_origin(pool: &PgPool, origin_uid: Uuid) -> Result<Option<Origin>> {
+    let origin = sqlx::query!(
+        r#"
+            SELECT
+                origin_uid,
+                uri,
+                alerts_enabled,
+                method,
+                request_body,
+                request_content_type,
+                forbidden_text,
+                json_assertion,
+                notes,
+                expected_status,
+                baseline_origin_uid,
+                relative_latency_limit_millis,
+                cache_validation,
+                redirect_limit,
+                expected_location,
+                timeout_millis,
+                encrypted_credentials,
+                encrypted_client_identity,
+                address_family,
+                proxy,
+                expected_networks,
+                http_version,
+                retries,
+                captured_headers,
+                alert_window,
+                probe_locations,
+                user_agent,
+                cache_busting,
+                connection_mode,
+                expected_headers,
+                body_change_detection,
+                schedule,
+                expected_latency_millis,
+                paused,
+                tags
+            FROM origin
+            WHERE origin_uid = $1
+        "#,
+        origin_uid,
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(|row| Origin {
+        origin_uid: row.origin_uid,
+        uri: row.uri,
+        alerts_enabled: row.alerts_enabled,
+        method: HttpMethod::from_column(&row.method),
+        request_body: row.request_body,
+        request_content_type: row.request_content_type,
+        forbidden_text: row.forbidden_text,
+        json_assertion: row.json_assertion,
+        notes: row.notes,
+        expected_status: row.expected_status,
+        baseline_origin_uid: row.baseline_origin_uid,
+        relative_latency_limit_millis: row.relative_latency_limit_millis,
+        cache_validation: row.cache_validation,
+        redirect_limit: row.redirect_limit,
+        expected_location: row.expected_location,
+        timeout_millis: row.timeout_millis,
+        encrypted_credentials: row.encrypted_credentials,
+        encrypted_client_identity: row.encrypted_client_identity,
+        address_family: AddressFamily::from_column(&row.address_family),
+        proxy: row.proxy,
+        expected_networks: row.expected_networks,
+        http_version: HttpVersion::from_column(&row.http_version),
+        retries: row.retries,
+        captured_headers: row.captured_headers,
+        alert_window: AlertWindow::from_column(&row.alert_window),
+        probe_locations: row.probe_locations,
+        user_agent: row.user_agent,
+        cache_busting: row.cache_busting,
+        connection_mode: ConnectionMode::from_column(&row.connection_mode),
+        expected_headers: row.expected_headers,
+        body_change_detection: row.body_change_detection,
+        schedule: row.schedule,
+        tags: row.tags,
+        expected_latency_millis: row.expected_latency_millis,
+        paused: row.paused,
+    });
+
+    Ok(origin)
+}
+
+pub struct IndexOrigin {
+    pub origin_uid: Uuid,
+    pub uri: String,
+    pub visibility: String,
+    pub pinned: bool,
+    pub alerts_enabled: bool,
+    pub paused: bool,
+    pub tags: Vec<String>,
+    /// The HTTP status, which is missing for checks that are not made over HTTP.
+    pub status: Option<i16>,
+    pub latency_millis: i64,
+    pub queried_at: DateTime<Utc>,
+}
+
+pub async fn fetch_origins_with_most_recent_success_metrics(
+    pool: &PgPool,
+) -> Result<Vec<IndexOrigin>> {
+    let origins = sqlx::query_as!(
+        IndexOrigin,
+        r#"
+            SELECT DISTINCT ON (o.pinned, o.display_order, o.id)
+                o.origin_uid,
+                o.uri,
+                o.visibility,
+                o.pinned,
+                o.alerts_enabled,
+                o.paused,
+                o.tags,
+                q.status,
+                q.latency_millis,
+                q.queried_at
+            FROM origin o
+            JOIN query q ON o.id = q.origin_id
+            ORDER BY o.pinned DESC, o.display_order, o.id, q.queried_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(origins)
+}
+
+pub struct OriginFailure {
+    pub origin_uid: Uuid,
+    pub uri: String,
+    pub visibility: String,
+    pub pinned: bool,
+    pub alerts_enabled: bool,
+    pub paused: bool,
+    pub tags: Vec<String>,
+    pub failure_reason: String,
+    pub queried_at: DateTime<Utc>,
+}
+
+pub async fn fetch_origins_with_most_recent_failure_metrics(
+    pool: &PgPool,
+) -> Result<Vec<OriginFailure>> {
+    let origins = sqlx::query_as!(
+        OriginFailure,
+        r#"
+            SELECT DISTINCT ON (o.pinned, o.display_order, o.id)
+                o.origin_uid,
+                o.uri,
+                o.visibility,
+                o.pinned,
+                o.alerts_enabled,
+                o.paused,
+                o.tags,
+                qfr.name AS failure_reason,
+                qf.queried_at
+            FROM origin o
+            JOIN query_failure qf ON o.id = qf.origin_id
+            JOIN query_failure_reason qfr ON qfr.id = qf.failure_reason_id
+            ORDER BY o.pinned DESC, o.display_order, o.id, qf.queried_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(origins)
+}
+
+/// A single check of an origin, which either succeeded with a status or failed for a reason.
+pub struct RecentCheck {
+    pub status: Option<i16>,
+    pub latency_millis: Option<i64>,
+    pub final_url: Option<String>,
+    pub failure_reason: Option<String>,
+    pub failure_detail: Option<String>,
+    /// The family the check was made over, if it was required to use one.
+    pub address_family: Option<String>,
+    /// The address the check connected to, if it got as far as connecting.
+    pub remote_address: Option<String>,
+    /// The size of the response body of a successful HTTP check.
+    pub body_bytes: Option<i64>,
+    /// The version of HTTP a successful HTTP check was made with, such as `HTTP/2`.
+    pub http_version: Option<String>,
+    /// How many attempts the check took, if it had to be retried.
+    pub attempts: Option<i32>,
+    /// The response headers recorded by the check, one `name: value` per line.
+    pub captured_headers: Option<String>,
+    /// How long each phase of a successful HTTP check took.
+    pub dns_millis: Option<i64>,
+    pub connect_millis: Option<i64>,
+    pub tls_millis: Option<i64>,
+    pub first_byte_millis: Option<i64>,
+    /// The location of the poller that made the check, if it had one.
+    pub location: Option<String>,
+    /// How long the request took over a new connection, for origins measuring both.
+    pub cold_latency_millis: Option<i64>,
+    /// How fast the body downloaded, for download checks.
+    pub throughput_bytes_per_second: Option<i64>,
+    /// How far ahead of the poller's clock the origin's was, for NTP checks.
+    pub clock_offset_millis: Option<i64>,
+    pub queried_at: DateTime<Utc>,
+}
+
+/// Fetches the most recent `limit` checks of an origin, oldest first.
+pub async fn fetch_recent_checks(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    limit: i64,
+) -> Result<Vec<RecentCheck>> {
+    let checks = sqlx::query_as!(
+        RecentCheck,
+        r#"
+            SELECT
+                status,
+                latency_millis,
+                final_url,
+                failure_reason,
+                failure_detail,
+                address_family,
+                remote_address,
+                body_bytes,
+                http_version,
+                attempts,
+                captured_headers,
+                dns_millis,
+                connect_millis,
+                tls_millis,
+                first_byte_millis,
+                location,
+                cold_latency_millis,
+                throughput_bytes_per_second,
+                clock_offset_millis,
+                queried_at AS "queried_at!"
+            FROM (
+                SELECT
+                    q.status,
+                    q.latency_millis,
+                    q.final_url,
+                    NULL AS failure_reason,
+                    NULL AS failure_detail,
+                    q.address_family,
+                    q.remote_address,
+                    q.body_bytes,
+                    q.http_version,
+                    q.attempts,
+                    q.captured_headers,
+                    q.dns_millis,
+                    q.connect_millis,
+                    q.tls_millis,
+                    q.first_byte_millis,
+                    q.location,
+                    q.cold_latency_millis,
+                    q.throughput_bytes_per_second,
+                    q.clock_offset_millis,
+                    q.queried_at
+                FROM query q
+                JOIN origin o ON o.id = q.origin_id
+                WHERE o.origin_uid = $1
+                UNION ALL
+                SELECT NULL, NULL, NULL, qfr.name, qf.detail, qf.address_family, qf.remote_address, NULL, NULL, qf.attempts, qf.captured_headers, NULL, NULL, NULL, NULL, qf.location, NULL, NULL, NULL, qf.queried_at
+                FROM query_failure qf
+                JOIN origin o ON o.id = qf.origin_id
+                JOIN query_failure_reason qfr ON qfr.id = qf.failure_reason_id
+                WHERE o.origin_uid = $1
+                ORDER BY queried_at DESC
+                LIMIT $2
+            ) recent
+            ORDER BY queried_at
+        "#,
+        origin_uid,
+        limit,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(checks)
+}
+
+/// The position of a check in an origin's history, which is ordered newest first with failures
+/// before successes made at the same time.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryPosition {
+    pub queried_at: DateTime<Utc>,
+    pub failed: bool,
+    /// The id of the row in `query` or `query_failure`, depending on whether the check failed.
+    pub id: i64,
+}
+
+#[derive(Serialize)]
+pub struct HistoryEntry {
+    #[serde(skip)]
+    pub id: i64,
+    pub failed: bool,
+    pub status: Option<i16>,
+    pub latency_millis: Option<i64>,
+    pub failure_reason: Option<String>,
+    pub failure_detail: Option<String>,
+    /// The location of the poller that made the check, if it had one.
+    pub location: Option<String>,
+    pub queried_at: DateTime<Utc>,
+}
+
+impl HistoryEntry {
+    pub fn position(&self) -> HistoryPosition {
+        HistoryPosition {
+            queried_at: self.queried_at,
+            failed: self.failed,
+            id: self.id,
+        }
+    }
+}
+
+/// Fetches up to `limit` checks of an origin, newest first, starting after `after` if given.
+///
+/// Pages are found by seeking rather than by offset, so deep pages stay as fast as the first and
+/// checks recorded while paging do not shift the later pages.
+pub async fn fetch_history_page(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    after: Option<HistoryPosition>,
+    limit: i64,
+) -> Result<Vec<HistoryEntry>> {
+    let (queried_at, failed, id) = match after {
+        Some(position) => (Some(position.queried_at), position.failed, position.id),
+        None => (None, false, 0),
+    };
+
+    // Failures come before successes at the same time, so after a failure every success made at
+    // that time is still to come, and after a success every failure made at that time has passed
+    let entries = sqlx::query_as!(
+        HistoryEntry,
+        r#"
+            SELECT
+                id AS "id!",
+                failed AS "failed!",
+                status,
+                latency_millis,
+                failure_reason,
+                failure_detail,
+                location,
+                queried_at AS "queried_at!"
+            FROM (
+                (
+                    SELECT
+                        q.id,
+                        FALSE AS failed,
+                        q.status,
+                        q.latency_millis,
+                        NULL::TEXT AS failure_reason,
+                        NULL::TEXT AS failure_detail,
+                        q.location,
+                        q.queried_at
+                    FROM query q
+                    WHERE q.origin_id = (SELECT id FROM origin WHERE origin_uid = $1)
+                    AND (q.queried_at, q.id) < (
+                        COALESCE($2::timestamptz, 'infinity'),
+                        CASE WHEN $3::BOOLEAN THEN 9223372036854775807 ELSE $4::BIGINT END
+                    )
+                    ORDER BY q.queried_at DESC, q.id DESC
+                    LIMIT $5
+                )
+                UNION ALL
+                (
+                    SELECT qf.id, TRUE, NULL, NULL, qfr.name, qf.detail, qf.location, qf.queried_at
+                    FROM query_failure qf
+                    JOIN query_failure_reason qfr ON qfr.id = qf.failure_reason_id
+                    WHERE qf.origin_id = (SELECT id FROM origin WHERE origin_uid = $1)
+                    AND (qf.queried_at, qf.id) < (
+                        COALESCE($2::timestamptz, 'infinity'),
+                        CASE WHEN $3::BOOLEAN THEN $4::BIGINT ELSE 0 END
+                    )
+                    ORDER BY qf.queried_at DESC, qf.id DESC
+                    LIMIT $5
+                )
+            ) history
+            ORDER BY queried_at DESC, failed DESC, id DESC
+            LIMIT $5
+        "#,
+        origin_uid,
+        queried_at,
+        failed,
+        id,
+        limit,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(entries)
+}
+
+#[derive(Serialize)]
+pub struct PublicOrigin {
+    pub uri: String,
+    pub operational: bool,
+    pub queried_at: DateTime<Utc>,
+}
+
+/// Fetches the most recent outcome for each public origin, never returning private ones.
+pub async fn fetch_public_origins(pool: &PgPool) -> Result<Vec<PublicOrigin>> {
+    let origins = sqlx::query_as!(
+        PublicOrigin,
+        r#"
+            SELECT DISTINCT ON (o.pinned, o.display_order, o.id)
+                o.uri,
+                c.operational AS "operational!",
+                c.queried_at AS "queried_at!"
+            FROM origin o
+            JOIN (
+                SELECT origin_id, TRUE AS operational, queried_at
+                FROM query
+                UNION ALL
+                SELECT origin_id, FALSE AS operational, queried_at
+                FROM query_failure
+            ) c ON c.origin_id = o.id
+            WHERE o.visibility = 'public'
+            ORDER BY o.pinned DESC, o.display_order, o.id, c.queried_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(origins)
+}
+
+/// Fetches the URI of an origin if it is public, so pages outside the dashboard cannot reveal
+/// private ones.
+pub async fn fetch_public_origin_uri(pool: &PgPool, origin_uid: Uuid) -> Result<Option<String>> {
+    let uri = sqlx::query_scalar!(
+        r#"
+            SELECT uri
+            FROM origin
+            WHERE origin_uid = $1 AND visibility = 'public'
+        "#,
+        origin_uid
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(uri)
+}
+
+pub async fn insert_query(
+    tx: &mut Transaction,
+    origin_uid: Uuid,
+    status: Option<u16>,
+    latency_millis: i64,
+    queried_at: DateTime<Utc>,
+) -> Result<Uuid> {
+    let query_uid = Uuid::new_v4();
+
+    sqlx::query!(
+        r#"
+            INSERT INTO query (query_uid, origin_id, status, latency_millis, queried_at)
+            VALUES (
+                $1,
+                (SELECT id FROM origin WHERE origin_uid = $2),
+                $3,
+                $4,
+                $5
+            )
+        "#,
+        query_uid,
+        origin_uid,
+        status.map(|status| status as i16),
+        latency_millis,
+        queried_at
+    )
+    .execute(tx.deref_mut())
+    .await?;
+
+    Ok(query_uid)
+}
+
+/// Everything recorded about a successful check besides its status and latency, most of which is
+/// only known for some kinds of check.
+#[derive(Debug, Default)]
+pub struct QueryDetails<'a> {
+    /// The family the check was made over, which is only recorded if it was required to use one.
+    pub address_family: AddressFamily,
+    /// How many attempts the check took, when it had to be retried.
+    pub attempts: Option<i32>,
+    /// The location of the poller that made the check.
+    pub location: Option<&'a str>,
+    pub remote_address: Option<IpAddr>,
+    pub body_bytes: Option<i64>,
+    pub http_version: Option<&'a str>,
+    pub timings: Option<&'a Timings>,
+    /// How fast a download check received the body of its asset.
+    pub throughput_bytes_per_second: Option<i64>,
+    /// How far the clock of a time server was from the poller's.
+    pub clock_offset_millis: Option<i64>,
+    /// How long the check took over a new connection, alongside the reused one.
+    pub cold_latency_millis: Option<i64>,
+    /// The headers captured from the response, one per line.
+    pub captured_headers: Option<&'a str>,
+    /// Where the check ended up after being redirected.
+    pub final_url: Option<&'a str>,
+    pub redirect_chain: Option<&'a str>,
+}
+
+/// Records the details of a successful check in a single update.
+pub async fn set_query_details(
+    tx: &mut Transaction,
+    query_uid: Uuid,
+    details: &QueryDetails<'_>,
+) -> Result<()> {
+    let timings = details.timings.copied().unwrap_or_default();
+
+    sqlx::query!(
+        r#"
+            UPDATE query
+            SET
+                address_family = $2,
+                attempts = $3,
+                location = $4,
+                remote_address = $5,
+                body_bytes = $6,
+                http_version = $7,
+                dns_millis = $8,
+                connect_millis = $9,
+                tls_millis = $10,
+                first_byte_millis = $11,
+                throughput_bytes_per_second = $12,
+                clock_offset_millis = $13,
+                cold_latency_millis = $14,
+                captured_headers = $15,
+                final_url = $16,
+                redirect_chain = $17
+            WHERE query_uid = $1
+        "#,
+        query_uid,
+        details.address_family.recorded(),
+        details.attempts,
+        details.location,
+        details.remote_address.map(|address| address.to_string()),
+        details.body_bytes,
+        details.http_version,
+        timings.dns_millis,
+        timings.connect_millis,
+        timings.tls_millis,
+        timings.first_byte_millis,
+        details.throughput_bytes_per_second,
+        details.clock_offset_millis,
+        details.cold_latency_millis,
+        details.captured_headers,
+        details.final_url,
+        details.redirect_chain,
+    )
+    .execute(tx.deref_mut())
+    .await?;
+
+    Ok(())
+}
+
+pub async fn insert_query_failure(
+    tx: &mut Transaction,
+    origin_uid: Uuid,
+    failure_reason: FailureReason,
+    detail: Option<&str>,
+    queried_at: DateTime<Utc>,
+) -> Result<Uuid> {
+    let query_failure_uid = Uuid::new_v4();
+
+    sqlx::query!(
+        r#"
+            INSERT INTO query_failure (
+                query_failure_uid, origin_id, failure_reason_id, detail, queried_at
+            )
+            VALUES (
+                $1,
+                (SELECT id FROM origin WHERE origin_uid = $2),
+                (SELECT id FROM query_failure_reason WHERE name = $3),
+                $4,
+                $5
+            )
+        "#,
+        query_failure_uid,
+        origin_uid,
+        failure_reason.as_str(),
+        detail,
+        queried_at
+    )
+    .execute(tx.deref_mut())
+    .await?;
+
+    Ok(query_failure_uid)
+}
+
+/// Everything recorded about a failed check besides its reason and detail.
+#[derive(Debug, Default)]
+pub struct QueryFailureDetails<'a> {
+    /// Whether the check was made during a maintenance window, so it does not count towards
+    /// alerts.
+    pub during_maintenance: bool,
+    /// The family the check was made over, which is only recorded if it was required to use one.
+    pub address_family: AddressFamily,
+    /// How many attempts the check took, when it was retried.
+    pub attempts: Option<i32>,
+    /// The location of the poller that made the check.
+    pub location: Option<&'a str>,
+    /// The address the check connected to, such as when its status was unexpected.
+    pub remote_address: Option<IpAddr>,
+    /// The headers captured from the response, such as when its status was unexpected.
+    pub captured_headers: Option<&'a str>,
+    pub diagnostics: Option<&'a str>,
+}
+
+/// Records the details of a failed check in a single update.
+pub async fn set_query_failure_details(
+    tx: &mut Transaction,
+    query_failure_uid: Uuid,
+    details: &QueryFailureDetails<'_>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE query_failure
+            SET
+                during_maintenance = $2,
+                address_family = $3,
+                attempts = $4,
+                location = $5,
+                remote_address = $6,
+                captured_headers = $7,
+                diagnostics = $8
+            WHERE query_failure_uid = $1
+        "#,
+        query_failure_uid,
+        details.during_maintenance,
+        details.address_family.recorded(),
+        details.attempts,
+        details.location,
+        details.remote_address.map(|address| address.to_string()),
+        details.captured_headers,
+        details.diagnostics,
+    )
+    .execute(tx.deref_mut())
+    .await?;
+
+    Ok(())
+}
+
+/// Fetches the diagnostics gathered for the most recent failure of an origin, or `None` if there
+/// were none.
+pub async fn fetch_latest_failure_diagnostics(
+    pool: &PgPool,
+    origin_uid: Uuid,
+) -> Result<Option<String>> {
+    let diagnostics = sqlx::query_scalar!(
+        r#"
+            SELECT qf.diagnostics
+            FROM query_failure qf
+            JOIN origin o ON o.id = qf.origin_id
+            WHERE o.origin_uid = $1
+            ORDER BY qf.queried_at DESC
+            LIMIT 1
+        "#,
+        origin_uid,
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(diagnostics.flatten())
+}
+
+/// Whether the most recent check of an origin succeeded, or `None` if it has never been checked.
+/// Fetches whether the latest check of an origin over a family succeeded, so origins checked over
+/// both families track each separately.
+pub async fn fetch_latest_outcome(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    address_family: AddressFamily,
+) -> Result<Option<bool>> {
+    let outcome = sqlx::query_scalar!(
+        r#"
+            SELECT outcome.succeeded AS "succeeded!"
+            FROM (
+                (
+                    SELECT q.queried_at, TRUE AS succeeded
+                    FROM query q
+                    JOIN origin o ON o.id = q.origin_id
+                    WHERE o.origin_uid = $1 AND q.address_family IS NOT DISTINCT FROM $2
+                    ORDER BY q.queried_at DESC
+                    LIMIT 1
+                )
+                UNION ALL
+                (
+                    SELECT qf.queried_at, FALSE AS succeeded
+                    FROM query_failure qf
+                    JOIN origin o ON o.id = qf.origin_id
+                    WHERE o.origin_uid = $1 AND qf.address_family IS NOT DISTINCT FROM $2
+                    ORDER BY qf.queried_at DESC
+                    LIMIT 1
+                )
+            ) outcome
+            ORDER BY outcome.queried_at DESC
+            LIMIT 1
+        "#,
+        origin_uid,
+        address_family.recorded(),
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(outcome)
+}
+
+/// Finds the adaptive origins, or every origin if `all_origins`, that have failed within their last
+/// `stable_checks` checks, which are either still down or yet to stay up for long enough.
+pub async fn fetch_unstable_origin_uids(
+    pool: &PgPool,
+    stable_checks: i64,
+    all_origins: bool,
+) -> Result<Vec<Uuid>> {
+    let origin_uids = sqlx::query_scalar!(
+        r#"
+            SELECT o.origin_uid
+            FROM origin o
+            CROSS JOIN LATERAL (
+                SELECT bool_and(recent.succeeded) AS stable
+                FROM (
+                    (
+                        SELECT q.queried_at, TRUE AS succeeded
+                        FROM query q
+                        WHERE q.origin_id = o.id
+                        ORDER BY q.queried_at DESC
+                        LIMIT $1
+                    )
+                    UNION ALL
+                    (
+                        SELECT qf.queried_at, FALSE AS succeeded
+                        FROM query_failure qf
+                        WHERE qf.origin_id = o.id
+                        ORDER BY qf.queried_at DESC
+                        LIMIT $1
+                    )
+                    ORDER BY queried_at DESC
+                    LIMIT $1
+                ) recent
+            ) outcome
+            WHERE (o.adaptive_polling OR $2) AND NOT o.paused AND NOT outcome.stable
+            ORDER BY o.display_order, o.id
+        "#,
+        stable_checks,
+        all_origins,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(origin_uids)
+}
+
+/// Claims whichever of `origin_uids` no other poller in `location` has a claim on at `now`, until
+/// `claimed_until`, returning those it claimed. Pollers without a location share the empty one.
+///
+/// Origins another poller is claiming at the same time are skipped rather than waited for, so
+/// each origin is only ever claimed by one poller in each location.
+pub async fn claim_origins(
+    pool: &PgPool,
+    claimed_by: Uuid,
+    location: &str,
+    origin_uids: &[Uuid],
+    now: DateTime<Utc>,
+    claimed_until: DateTime<Utc>,
+) -> Result<Vec<Uuid>> {
+    // Origins that have never been claimed in the location are inserted, and the rest are taken
+    // over once their claims expire
+    let claimed = sqlx::query_scalar!(
+        r#"
+            WITH inserted AS (
+                INSERT INTO origin_claim (origin_id, location, claimed_by, claimed_until)
+                SELECT id, $2, $1, $5
+                FROM origin
+                WHERE origin_uid = ANY($3)
+                ON CONFLICT (origin_id, location) DO NOTHING
+                RETURNING origin_id
+            ), updated AS (
+                UPDATE origin_claim
+                SET claimed_by = $1, claimed_until = $5
+                WHERE (origin_id, location) IN (
+                    SELECT c.origin_id, c.location
+                    FROM origin_claim c
+                    JOIN origin o ON o.id = c.origin_id
+                    WHERE o.origin_uid = ANY($3)
+                    AND c.location = $2
+                    AND (c.claimed_until <= $4 OR c.claimed_by = $1)
+                    FOR UPDATE OF c SKIP LOCKED
+                )
+                RETURNING origin_id
+            )
+            SELECT origin_uid
+            FROM origin
+            WHERE id IN (SELECT origin_id FROM inserted UNION SELECT origin_id FROM updated)
+        "#,
+        claimed_by,
+        location,
+        origin_uids,
+        now,
+        claimed_until,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(claimed)
+}
+
+/// Takes the session-level advisory lock `key` on `connection` if nobody else holds it, returning
+/// whether it did. The lock is held until the connection closes.
+pub async fn try_advisory_lock(connection: &mut sqlx::PgConnection, key: i64) -> Result<bool> {
+    let locked = sqlx::query_scalar!(
+        r#"
+            SELECT pg_try_advisory_lock($1) AS "locked!"
+        "#,
+        key,
+    )
+    .fetch_one(connection)
+    .await?;
+
+    Ok(locked)
+}
+
+/// Whether an origin has failed at least `limit` times between `start` and `end`, from at least
+/// `location_limit` locations. Failures from pollers without a location count as one location.
+pub async fn failure_rate_exceeded(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    limit: u16,
+    location_limit: u16,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<bool> {
+    let exceeded = sqlx::query_scalar!(
+        r#"
+            SELECT COUNT(*) >= $2 AND COUNT(DISTINCT COALESCE(qf.location, '')) >= $5
+            FROM query_failure qf
+            JOIN origin o ON o.id = qf.origin_id
+            WHERE o.origin_uid = $1
+            AND qf.queried_at BETWEEN $3 AND $4
+            AND NOT qf.during_maintenance
+        "#,
+        origin_uid,
+        limit as i32,
+        start,
+        end,
+        location_limit as i32,
+    )
+    .fetch_one(pool)
+    .await?
+    .expect("Count returned a null value");
+
+    Ok(exceeded)
+}
+
+/// What a notification alerted about, so cooldowns can apply to each kind of alert separately.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AlertKind {
+    Outage,
+    LatencyRegression,
+    RedirectChainChanged,
+    UnexpectedAddress,
+    BodyChanged,
+    DomainExpiring,
+}
+
+impl AlertKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Outage => "outage",
+            Self::LatencyRegression => "latency_regression",
+            Self::RedirectChainChanged => "redirect_chain_changed",
+            Self::UnexpectedAddress => "unexpected_address",
+            Self::BodyChanged => "body_changed",
+            Self::DomainExpiring => "domain_expiring",
+        }
+    }
+}
+
+/// Holds the lock on alerting for an origin until `tx` ends, so pollers in different locations
+/// cannot both decide to send the same alert.
+pub async fn lock_origin_alerts(tx: &mut Transaction, origin_uid: Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+            SELECT 1 AS "locked!"
+            FROM pg_advisory_xact_lock(hashtextextended($1::UUID::TEXT, 1))
+        "#,
+        origin_uid,
+    )
+    .fetch_one(tx.deref_mut())
+    .await?;
+
+    Ok(())
+}
+
+pub async fn insert_notification(
+    tx: &mut Transaction,
+    origin_uid: Uuid,
+    kind: AlertKind,
+    topic: &str,
+    subject: &str,
+    message: &str,
+    created_at: DateTime<Utc>,
+) -> Result<Uuid> {
+    let notification_uid = Uuid::new_v4();
+
+    sqlx::query!(
+        r#"
+            INSERT INTO notification (notification_uid, origin_id, kind, topic, subject, message, created_at)
+            VALUES (
+                $1,
+                (SELECT id FROM origin WHERE origin_uid = $2),
+                $3,
+                $4,
+                $5,
+                $6,
+                $7
+            )
+        "#,
+        notification_uid,
+        origin_uid,
+        kind.as_str(),
+        topic,
+        subject,
+        message,
+        created_at
+    )
+    .execute(tx.deref_mut())
+    .await?;
+
+    Ok(notification_uid)
+}
+
+/// Removes a notification that could not be sent after it was recorded.
+pub async fn delete_notification(pool: &PgPool, notification_uid: Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+            DELETE FROM notification
+            WHERE notification_uid = $1
+        "#,
+        notification_uid,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Marks a notification as acknowledged, returning `false` if it does not exist or already was.
+pub async fn acknowledge_notification(
+    pool: &PgPool,
+    notification_uid: Uuid,
+    acknowledged_by: Option<&str>,
+) -> Result<bool> {
+    let result = sqlx::query!(
+        r#"
+            UPDATE notification
+            SET acknowledged_at = $2, acknowledged_by = $3
+            WHERE notification_uid = $1
+            AND acknowledged_at IS NULL
+        "#,
+        notification_uid,
+        Utc::now(),
+        acknowledged_by,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub struct UnacknowledgedNotification {
+    pub notification_uid: Uuid,
+    pub uri: String,
+    pub subject: String,
+    pub created_at: DateTime<Utc>,
+}
+
+pub async fn fetch_unacknowledged_notifications(
+    pool: &PgPool,
+    limit: i64,
+) -> Result<Vec<UnacknowledgedNotification>> {
+    let notifications = sqlx::query_as!(
+        UnacknowledgedNotification,
+        r#"
+            SELECT n.notification_uid, o.uri, n.subject, n.created_at
+            FROM notification n
+            JOIN origin o ON o.id = n.origin_id
+            WHERE n.acknowledged_at IS NULL
+            ORDER BY n.created_at DESC
+            LIMIT $1
+        "#,
+        limit,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(notifications)
+}
+
+/// How noisy the alerts for an origin were during a week.
+pub struct AlertFatigue {
+    pub uri: String,
+    /// The Monday the week started on.
+    pub week: NaiveDate,
+    pub notifications: i64,
+    pub acknowledged: i64,
+    /// Notifications for incidents that resolved themselves within the recovery window.
+    pub auto_recovered: i64,
+}
+
+pub async fn fetch_alert_fatigue(
+    pool: &PgPool,
+    since: DateTime<Utc>,
+    recovery_window: Duration,
+) -> Result<Vec<AlertFatigue>> {
+    let fatigue = sqlx::query_as!(
+        AlertFatigue,
+        r#"
+            SELECT
+                o.uri,
+                DATE_TRUNC('week', n.created_at AT TIME ZONE 'UTC')::DATE AS "week!",
+                COUNT(*) AS "notifications!",
+                COUNT(n.acknowledged_at) AS "acknowledged!",
+                COUNT(*) FILTER (WHERE EXISTS (
+                    SELECT 1
+                    FROM incident i
+                    WHERE i.origin_id = n.origin_id
+                    AND i.started_at <= n.created_at
+                    AND i.resolved_at >= n.created_at
+                    AND i.resolved_at <= n.created_at + make_interval(secs => $2)
+                )) AS "auto_recovered!"
+            FROM notification n
+            JOIN origin o ON o.id = n.origin_id
+            WHERE n.created_at >= $1
+            GROUP BY o.id, 2
+            ORDER BY 2 DESC, o.pinned DESC, o.display_order, o.id
+        "#,
+        since,
+        recovery_window.num_seconds() as f64,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(fatigue)
+}
+
+/// Whether the latest notification of `kind` for the origin was sent more than `cooldown` ago, if
+/// one was sent at all.
+pub async fn latest_notification_older_than(
+    tx: &mut Transaction,
+    origin_uid: Uuid,
+    kind: AlertKind,
+    cooldown: Duration,
+    now: DateTime<Utc>,
+) -> Result<bool> {
+    let boundary = now - cooldown;
+
+    let notification = sqlx::query_scalar!(
+        r#"
+            SELECT NOT EXISTS (
+                SELECT
+                FROM notification n
+                JOIN origin o ON o.id = n.origin_id
+                WHERE o.origin_uid = $1
+                AND n.kind = $3
+                AND n.created_at > $2
+                LIMIT 1
+            )
+        "#,
+        origin_uid,
+        boundary,
+        kind.as_str(),
+    )
+    .fetch_one(tx.deref_mut())
+    .await?
+    .expect("Exists returned a null value");
 
     Ok(notification)
 }
+
+pub struct NotificationExport {
+    pub notification_uid: Uuid,
+    pub origin_uid: Uuid,
+    pub uri: String,
+    pub topic: String,
+    pub subject: String,
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+}
+
+pub fn stream_notifications(
+    pool: &PgPool,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+) -> BoxStream<'_, sqlx::Result<NotificationExport>> {
+    sqlx::query_as!(
+        NotificationExport,
+        r#"
+            SELECT
+                n.notification_uid,
+                o.origin_uid,
+                o.uri,
+                n.topic,
+                n.subject,
+                n.message,
+                n.created_at
+            FROM notification n
+            JOIN origin o ON o.id = n.origin_id
+            WHERE ($1::timestamptz IS NULL OR n.created_at >= $1)
+            AND ($2::timestamptz IS NULL OR n.created_at < $2)
+            ORDER BY n.created_at
+        "#,
+        start,
+        end,
+    )
+    .fetch(pool)
+}
+
+/// The position of a notification in the list of them, which is ordered newest first.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationPosition {
+    pub created_at: DateTime<Utc>,
+    pub id: i64,
+}
+
+#[derive(Serialize)]
+pub struct NotificationEntry {
+    #[serde(skip)]
+    pub id: i64,
+    pub notification_uid: Uuid,
+    pub origin_uid: Uuid,
+    pub uri: String,
+    pub topic: String,
+    pub subject: String,
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    pub acknowledged_by: Option<String>,
+}
+
+impl NotificationEntry {
+    pub fn position(&self) -> NotificationPosition {
+        NotificationPosition {
+            created_at: self.created_at,
+            id: self.id,
+        }
+    }
+}
+
+/// Fetches up to `limit` notifications, newest first, starting after `after` if given and only
+/// including those about `origin_uid` if given.
+pub async fn fetch_notification_page(
+    pool: &PgPool,
+    origin_uid: Option<Uuid>,
+    after: Option<NotificationPosition>,
+    limit: i64,
+) -> Result<Vec<NotificationEntry>> {
+    let (created_at, id) = match after {
+        Some(position) => (Some(position.created_at), position.id),
+        None => (None, 0),
+    };
+
+    let entries = sqlx::query_as!(
+        NotificationEntry,
+        r#"
+            SELECT
+                n.id,
+                n.notification_uid,
+                o.origin_uid,
+                o.uri,
+                n.topic,
+                n.subject,
+                n.message,
+                n.created_at,
+                n.acknowledged_at,
+                n.acknowledged_by
+            FROM notification n
+            JOIN origin o ON o.id = n.origin_id
+            WHERE ($1::UUID IS NULL OR o.origin_uid = $1)
+            AND (n.created_at, n.id) < (COALESCE($2::timestamptz, 'infinity'), $3::BIGINT)
+            ORDER BY n.created_at DESC, n.id DESC
+            LIMIT $4
+        "#,
+        origin_uid,
+        created_at,
+        id,
+        limit,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(entries)
+}
+
+pub struct Incident {
+    pub origin_uid: Uuid,
+    pub uri: String,
+    pub started_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub failure_count: i64,
+    pub cost_per_minute_cents: Option<i64>,
+}
+
+impl Incident {
+    /// How long the incident lasted, or has lasted so far if it is ongoing.
+    pub fn duration(&self) -> Duration {
+        self.resolved_at.unwrap_or_else(Utc::now) - self.started_at
+    }
+
+    pub fn estimated_cost_cents(&self) -> Option<i64> {
+        self.cost_per_minute_cents
+            .map(|cost| crate::cost::estimate(self.duration(), cost))
+    }
+}
+
+pub fn stream_incidents(
+    pool: &PgPool,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+) -> BoxStream<'_, sqlx::Result<Incident>> {
+    sqlx::query_as!(
+        Incident,
+        r#"
+            SELECT
+                o.origin_uid,
+                o.uri,
+                i.started_at AS "started_at!",
+                i.resolved_at,
+                i.failure_count AS "failure_count!",
+                o.cost_per_minute_cents
+            FROM incident i
+            JOIN origin o ON o.id = i.origin_id
+            WHERE ($1::timestamptz IS NULL OR i.started_at >= $1)
+            AND ($2::timestamptz IS NULL OR i.started_at < $2)
+            ORDER BY i.started_at
+        "#,
+        start,
+        end,
+    )
+    .fetch(pool)
+}
+
+/// Fetches the incidents for an origin which started after `since`, oldest first.
+pub async fn fetch_origin_incidents(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    since: DateTime<Utc>,
+) -> Result<Vec<Incident>> {
+    let incidents = sqlx::query_as!(
+        Incident,
+        r#"
+            SELECT
+                o.origin_uid,
+                o.uri,
+                i.started_at AS "started_at!",
+                i.resolved_at,
+                i.failure_count AS "failure_count!",
+                o.cost_per_minute_cents
+            FROM incident i
+            JOIN origin o ON o.id = i.origin_id
+            WHERE o.origin_uid = $1
+            AND i.started_at >= $2
+            ORDER BY i.started_at
+        "#,
+        origin_uid,
+        since,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(incidents)
+}
+
+/// The incident an origin is currently in the middle of.
+pub struct OngoingIncident {
+    pub started_at: DateTime<Utc>,
+    pub failure_count: i64,
+}
+
+/// Fetches the unresolved incident for an origin, if its latest check failed.
+pub async fn fetch_ongoing_incident(
+    pool: &PgPool,
+    origin_uid: Uuid,
+) -> Result<Option<OngoingIncident>> {
+    let incident = sqlx::query_as!(
+        OngoingIncident,
+        r#"
+            SELECT i.started_at AS "started_at!", i.failure_count AS "failure_count!"
+            FROM incident i
+            JOIN origin o ON o.id = i.origin_id
+            WHERE o.origin_uid = $1
+            AND i.resolved_at IS NULL
+            ORDER BY i.started_at DESC
+            LIMIT 1
+        "#,
+        origin_uid,
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(incident)
+}
+
+#[derive(Serialize)]
+pub struct TracerouteCapture {
+    pub incident_started_at: DateTime<Utc>,
+    pub host: String,
+    pub output: String,
+    pub captured_at: DateTime<Utc>,
+}
+
+pub async fn insert_traceroute(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    capture: &TracerouteCapture,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            INSERT INTO traceroute (origin_id, incident_started_at, host, output, captured_at)
+            VALUES (
+                (SELECT id FROM origin WHERE origin_uid = $1),
+                $2,
+                $3,
+                $4,
+                $5
+            )
+        "#,
+        origin_uid,
+        capture.incident_started_at,
+        capture.host,
+        capture.output,
+        capture.captured_at,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Fetches the traceroutes captured for an origin's incidents since `since`, newest first.
+pub async fn fetch_traceroutes(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    since: DateTime<Utc>,
+) -> Result<Vec<TracerouteCapture>> {
+    let captures = sqlx::query_as!(
+        TracerouteCapture,
+        r#"
+            SELECT t.incident_started_at, t.host, t.output, t.captured_at
+            FROM traceroute t
+            JOIN origin o ON o.id = t.origin_id
+            WHERE o.origin_uid = $1
+            AND t.captured_at >= $2
+            ORDER BY t.captured_at DESC
+        "#,
+        origin_uid,
+        since,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(captures)
+}
+
+#[derive(Serialize)]
+pub struct RecordedRedirectChain {
+    pub chain: String,
+    pub recorded_at: DateTime<Utc>,
+}
+
+pub async fn insert_redirect_chain(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    chain: &str,
+    recorded_at: DateTime<Utc>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            INSERT INTO redirect_chain (origin_id, chain, recorded_at)
+            VALUES (
+                (SELECT id FROM origin WHERE origin_uid = $1),
+                $2,
+                $3
+            )
+        "#,
+        origin_uid,
+        chain,
+        recorded_at,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Fetches the most recently recorded redirect chains for an origin, newest first.
+pub async fn fetch_redirect_chains(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    limit: i64,
+) -> Result<Vec<RecordedRedirectChain>> {
+    let chains = sqlx::query_as!(
+        RecordedRedirectChain,
+        r#"
+            SELECT rc.chain, rc.recorded_at
+            FROM redirect_chain rc
+            JOIN origin o ON o.id = rc.origin_id
+            WHERE o.origin_uid = $1
+            ORDER BY rc.recorded_at DESC
+            LIMIT $2
+        "#,
+        origin_uid,
+        limit,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(chains)
+}
+
+#[derive(Serialize)]
+pub struct BodyChange {
+    /// The SHA-256 of the body, as lowercase hex.
+    pub body_hash: String,
+    pub detected_at: DateTime<Utc>,
+}
+
+pub async fn insert_body_change(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    body_hash: &str,
+    detected_at: DateTime<Utc>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            INSERT INTO body_change (origin_id, body_hash, detected_at)
+            VALUES (
+                (SELECT id FROM origin WHERE origin_uid = $1),
+                $2,
+                $3
+            )
+        "#,
+        origin_uid,
+        body_hash,
+        detected_at,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Fetches the most recent changes to the response body of an origin, newest first.
+pub async fn fetch_body_changes(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    limit: i64,
+) -> Result<Vec<BodyChange>> {
+    let changes = sqlx::query_as!(
+        BodyChange,
+        r#"
+            SELECT bc.body_hash, bc.detected_at
+            FROM body_change bc
+            JOIN origin o ON o.id = bc.origin_id
+            WHERE o.origin_uid = $1
+            ORDER BY bc.detected_at DESC
+            LIMIT $2
+        "#,
+        origin_uid,
+        limit,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(changes)
+}
+
+/// When the domain an origin is served from expires, as of the last time it was looked up.
+#[derive(Clone, Debug)]
+pub struct DomainRegistration {
+    pub domain: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub checked_at: DateTime<Utc>,
+}
+
+pub async fn upsert_domain_registration(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    registration: &DomainRegistration,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            INSERT INTO domain_registration (origin_id, domain, expires_at, checked_at)
+            VALUES ((SELECT id FROM origin WHERE origin_uid = $1), $2, $3, $4)
+            ON CONFLICT (origin_id) DO UPDATE
+            SET domain = EXCLUDED.domain,
+                expires_at = EXCLUDED.expires_at,
+                checked_at = EXCLUDED.checked_at
+        "#,
+        origin_uid,
+        registration.domain,
+        registration.expires_at,
+        registration.checked_at,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn fetch_domain_registration(
+    pool: &PgPool,
+    origin_uid: Uuid,
+) -> Result<Option<DomainRegistration>> {
+    let registration = sqlx::query_as!(
+        DomainRegistration,
+        r#"
+            SELECT dr.domain, dr.expires_at, dr.checked_at
+            FROM domain_registration dr
+            JOIN origin o ON o.id = dr.origin_id
+            WHERE o.origin_uid = $1
+        "#,
+        origin_uid,
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(registration)
+}
+
+/// The inbound pings expected by a heartbeat origin.
+#[derive(Clone, Debug)]
+pub struct Heartbeat {
+    pub token: String,
+    pub grace_seconds: i32,
+    pub created_at: DateTime<Utc>,
+    pub last_ping_at: Option<DateTime<Utc>>,
+}
+
+pub async fn fetch_heartbeat(pool: &PgPool, origin_uid: Uuid) -> Result<Option<Heartbeat>> {
+    let heartbeat = sqlx::query_as!(
+        Heartbeat,
+        r#"
+            SELECT h.token, h.grace_seconds, h.created_at, h.last_ping_at
+            FROM heartbeat h
+            JOIN origin o ON o.id = h.origin_id
+            WHERE o.origin_uid = $1
+        "#,
+        origin_uid,
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(heartbeat)
+}
+
+/// Gives an origin a new heartbeat token, replacing any previous one so it stops working.
+pub async fn upsert_heartbeat(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    token: &str,
+    grace_seconds: i32,
+    created_at: DateTime<Utc>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            INSERT INTO heartbeat (origin_id, token, grace_seconds, created_at)
+            VALUES ((SELECT id FROM origin WHERE origin_uid = $1), $2, $3, $4)
+            ON CONFLICT (origin_id) DO UPDATE
+            SET token = EXCLUDED.token
+        "#,
+        origin_uid,
+        token,
+        grace_seconds,
+        created_at,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// A heartbeat as stored in configuration exports, keeping its token so jobs that already ping it
+/// carry on working after a restore.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeartbeatConfiguration {
+    pub origin_uid: Uuid,
+    pub token: String,
+    pub grace_seconds: i32,
+}
+
+pub async fn fetch_heartbeat_configurations(pool: &PgPool) -> Result<Vec<HeartbeatConfiguration>> {
+    let heartbeats = sqlx::query_as!(
+        HeartbeatConfiguration,
+        r#"
+            SELECT o.origin_uid, h.token, h.grace_seconds
+            FROM heartbeat h
+            JOIN origin o ON o.id = h.origin_id
+            ORDER BY o.display_order, o.id
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(heartbeats)
+}
+
+pub async fn upsert_heartbeat_configuration(
+    tx: &mut Transaction,
+    heartbeat: &HeartbeatConfiguration,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            INSERT INTO heartbeat (origin_id, token, grace_seconds, created_at)
+            VALUES ((SELECT id FROM origin WHERE origin_uid = $1), $2, $3, $4)
+            ON CONFLICT (origin_id) DO UPDATE
+            SET token = EXCLUDED.token,
+                grace_seconds = EXCLUDED.grace_seconds
+        "#,
+        heartbeat.origin_uid,
+        heartbeat.token,
+        heartbeat.grace_seconds,
+        Utc::now(),
+    )
+    .execute(tx.deref_mut())
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_heartbeat_grace(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    grace_seconds: i32,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE heartbeat
+            SET grace_seconds = $2
+            FROM origin o
+            WHERE o.id = heartbeat.origin_id
+            AND o.origin_uid = $1
+        "#,
+        origin_uid,
+        grace_seconds,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Records a ping for the heartbeat with `token`, returning the origin it belongs to or `None` if
+/// the token is unknown.
+pub async fn record_heartbeat_ping(
+    pool: &PgPool,
+    token: &str,
+    pinged_at: DateTime<Utc>,
+) -> Result<Option<Uuid>> {
+    let origin_uid = sqlx::query_scalar!(
+        r#"
+            UPDATE heartbeat
+            SET last_ping_at = $2
+            FROM origin o
+            WHERE o.id = heartbeat.origin_id
+            AND heartbeat.token = $1
+            RETURNING o.origin_uid
+        "#,
+        token,
+        pinged_at,
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(origin_uid)
+}
+
+/// One request in a journey origin's sequence.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JourneyStep {
+    pub journey_step_uid: Uuid,
+    pub method: HttpMethod,
+    pub url: String,
+    /// One `Name: value` header per line, which can refer to extracted values.
+    pub request_headers: Option<String>,
+    pub request_body: Option<String>,
+    pub request_content_type: Option<String>,
+    /// The status codes the step must respond with, such as `200-299,302`.
+    pub expected_status: Option<String>,
+    /// Saves part of the response for later steps, such as `token = $.access_token`.
+    pub extraction: Option<String>,
+}
+
+/// Fetches the steps of a journey origin in the order they are made.
+pub async fn fetch_journey_steps(pool: &PgPool, origin_uid: Uuid) -> Result<Vec<JourneyStep>> {
+    let rows = sqlx::query!(
+        r#"
+            SELECT
+                js.journey_step_uid,
+                js.method,
+                js.url,
+                js.request_headers,
+                js.request_body,
+                js.request_content_type,
+                js.expected_status,
+                js.extraction
+            FROM journey_step js
+            JOIN origin o ON o.id = js.origin_id
+            WHERE o.origin_uid = $1
+            ORDER BY js.position
+        "#,
+        origin_uid,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let steps = rows
+        .into_iter()
+        .map(|row| JourneyStep {
+            journey_step_uid: row.journey_step_uid,
+            method: HttpMethod::from_column(&row.method),
+            url: row.url,
+            request_headers: row.request_headers,
+            request_body: row.request_body,
+            request_content_type: row.request_content_type,
+            expected_status: row.expected_status,
+            extraction: row.extraction,
+        })
+        .collect();
+
+    Ok(steps)
+}
+
+/// Adds a step to the end of a journey origin's sequence.
+pub async fn insert_journey_step(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    step: &JourneyStep,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            INSERT INTO journey_step (
+                journey_step_uid,
+                origin_id,
+                position,
+                method,
+                url,
+                request_headers,
+                request_body,
+                request_content_type,
+                expected_status,
+                extraction
+            )
+            SELECT
+                $2,
+                o.id,
+                COALESCE((SELECT MAX(position) FROM journey_step WHERE origin_id = o.id), 0) + 1,
+                $3,
+                $4,
+                $5,
+                $6,
+                $7,
+                $8,
+                $9
+            FROM origin o
+            WHERE o.origin_uid = $1
+        "#,
+        origin_uid,
+        step.journey_step_uid,
+        step.method.as_str(),
+        step.url,
+        step.request_headers,
+        step.request_body,
+        step.request_content_type,
+        step.expected_status,
+        step.extraction,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// A journey step as stored in configuration exports, along with the origin making it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JourneyStepConfiguration {
+    pub origin_uid: Uuid,
+    #[serde(flatten)]
+    pub step: JourneyStep,
+}
+
+/// Fetches the steps of every journey origin, each origin's in the order they are made.
+pub async fn fetch_journey_step_configurations(
+    pool: &PgPool,
+) -> Result<Vec<JourneyStepConfiguration>> {
+    let rows = sqlx::query!(
+        r#"
+            SELECT
+                o.origin_uid,
+                js.journey_step_uid,
+                js.method,
+                js.url,
+                js.request_headers,
+                js.request_body,
+                js.request_content_type,
+                js.expected_status,
+                js.extraction
+            FROM journey_step js
+            JOIN origin o ON o.id = js.origin_id
+            ORDER BY o.display_order, o.id, js.position
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let steps = rows
+        .into_iter()
+        .map(|row| JourneyStepConfiguration {
+            origin_uid: row.origin_uid,
+            step: JourneyStep {
+                journey_step_uid: row.journey_step_uid,
+                method: HttpMethod::from_column(&row.method),
+                url: row.url,
+                request_headers: row.request_headers,
+                request_body: row.request_body,
+                request_content_type: row.request_content_type,
+                expected_status: row.expected_status,
+                extraction: row.extraction,
+            },
+        })
+        .collect();
+
+    Ok(steps)
+}
+
+/// Replaces the steps of a journey origin with `steps`, made in the order given.
+pub async fn replace_journey_steps(
+    tx: &mut Transaction,
+    origin_uid: Uuid,
+    steps: &[&JourneyStep],
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            DELETE FROM journey_step
+            USING origin o
+            WHERE o.id = journey_step.origin_id
+            AND o.origin_uid = $1
+        "#,
+        origin_uid,
+    )
+    .execute(tx.deref_mut())
+    .await?;
+
+    for (position, step) in (1..).zip(steps) {
+        sqlx::query!(
+            r#"
+                INSERT INTO journey_step (
+                    journey_step_uid,
+                    origin_id,
+                    position,
+                    method,
+                    url,
+                    request_headers,
+                    request_body,
+                    request_content_type,
+                    expected_status,
+                    extraction
+                )
+                SELECT $2, o.id, $3, $4, $5, $6, $7, $8, $9, $10
+                FROM origin o
+                WHERE o.origin_uid = $1
+            "#,
+            origin_uid,
+            step.journey_step_uid,
+            position,
+            step.method.as_str(),
+            step.url,
+            step.request_headers,
+            step.request_body,
+            step.request_content_type,
+            step.expected_status,
+            step.extraction,
+        )
+        .execute(tx.deref_mut())
+        .await?;
+    }
+
+    Ok(())
+}
+
+pub async fn delete_journey_step(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    journey_step_uid: Uuid,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            DELETE FROM journey_step
+            USING origin o
+            WHERE o.id = journey_step.origin_id
+            AND o.origin_uid = $1
+            AND journey_step.journey_step_uid = $2
+        "#,
+        origin_uid,
+        journey_step_uid,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// How often a maintenance window repeats.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Recurrence {
+    #[default]
+    Once,
+    Daily,
+    Weekly,
+}
+
+impl Recurrence {
+    pub const ALL: [Self; 3] = [Self::Once, Self::Daily, Self::Weekly];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Once => "once",
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+        }
+    }
+
+    fn from_column(value: &str) -> Self {
+        match value {
+            "daily" => Self::Daily,
+            "weekly" => Self::Weekly,
+            _ => Self::Once,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Once => "Once",
+            Self::Daily => "Every day",
+            Self::Weekly => "Every week",
+        }
+    }
+
+    /// How long after one window starts the next one does, or `None` if it does not repeat.
+    pub fn period(&self) -> Option<Duration> {
+        match self {
+            Self::Once => None,
+            Self::Daily => Some(Duration::days(1)),
+            Self::Weekly => Some(Duration::weeks(1)),
+        }
+    }
+}
+
+/// What the poller does with the origins a maintenance window covers while it is underway.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MaintenanceMode {
+    /// Checks are not made at all.
+    #[default]
+    Skip,
+    /// Checks are made and recorded, but do not count towards alerts or send any.
+    Silence,
+}
+
+impl MaintenanceMode {
+    pub const ALL: [Self; 2] = [Self::Skip, Self::Silence];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Skip => "skip",
+            Self::Silence => "silence",
+        }
+    }
+
+    fn from_column(value: &str) -> Self {
+        match value {
+            "silence" => Self::Silence,
+            _ => Self::Skip,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Skip => "Skip checks",
+            Self::Silence => "Record checks without alerting",
+        }
+    }
+}
+
+/// A period of planned work on an origin, or on every origin with a tag, such as a deploy.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    pub maintenance_window_uid: Uuid,
+    /// The origin the window covers, unless it covers every origin with `tag` instead.
+    pub origin_uid: Option<Uuid>,
+    pub tag: Option<String>,
+    /// When the first occurrence of the window starts and ends.
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub recurrence: Recurrence,
+    pub mode: MaintenanceMode,
+    pub description: Option<String>,
+}
+
+impl MaintenanceWindow {
+    /// Whether the window, or a repeat of it, is underway at `at`.
+    pub fn is_active(&self, at: DateTime<Utc>) -> bool {
+        if at < self.starts_at {
+            return false;
+        }
+
+        let Some(period) = self.recurrence.period() else {
+            return at < self.ends_at;
+        };
+
+        let into_period = (at - self.starts_at).num_seconds() % period.num_seconds();
+
+        into_period < (self.ends_at - self.starts_at).num_seconds()
+    }
+
+    pub fn covers(&self, origin: &Origin) -> bool {
+        self.origin_uid == Some(origin.origin_uid)
+            || self
+                .tag
+                .as_ref()
+                .is_some_and(|tag| origin.tags.contains(tag))
+    }
+}
+
+pub async fn fetch_maintenance_windows(pool: &PgPool) -> Result<Vec<MaintenanceWindow>> {
+    let rows = sqlx::query!(
+        r#"
+            SELECT
+                mw.maintenance_window_uid,
+                o.origin_uid AS "origin_uid?",
+                mw.tag,
+                mw.starts_at,
+                mw.ends_at,
+                mw.recurrence,
+                mw.mode,
+                mw.description
+            FROM maintenance_window mw
+            LEFT JOIN origin o ON o.id = mw.origin_id
+            ORDER BY mw.starts_at, mw.id
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let windows = rows
+        .into_iter()
+        .map(|row| MaintenanceWindow {
+            maintenance_window_uid: row.maintenance_window_uid,
+            origin_uid: row.origin_uid,
+            tag: row.tag,
+            starts_at: row.starts_at,
+            ends_at: row.ends_at,
+            recurrence: Recurrence::from_column(&row.recurrence),
+            mode: MaintenanceMode::from_column(&row.mode),
+            description: row.description,
+        })
+        .collect();
+
+    Ok(windows)
+}
+
+pub async fn insert_maintenance_window(pool: &PgPool, window: &MaintenanceWindow) -> Result<()> {
+    sqlx::query!(
+        r#"
+            INSERT INTO maintenance_window (
+                maintenance_window_uid,
+                origin_id,
+                tag,
+                starts_at,
+                ends_at,
+                recurrence,
+                mode,
+                description
+            )
+            VALUES (
+                $1,
+                (SELECT id FROM origin WHERE origin_uid = $2),
+                $3,
+                $4,
+                $5,
+                $6,
+                $7,
+                $8
+            )
+        "#,
+        window.maintenance_window_uid,
+        window.origin_uid,
+        window.tag,
+        window.starts_at,
+        window.ends_at,
+        window.recurrence.as_str(),
+        window.mode.as_str(),
+        window.description,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Creates the window if it does not exist yet, otherwise overwrites it.
+pub async fn upsert_maintenance_window(
+    tx: &mut Transaction,
+    window: &MaintenanceWindow,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            INSERT INTO maintenance_window (
+                maintenance_window_uid,
+                origin_id,
+                tag,
+                starts_at,
+                ends_at,
+                recurrence,
+                mode,
+                description
+            )
+            VALUES (
+                $1,
+                (SELECT id FROM origin WHERE origin_uid = $2),
+                $3,
+                $4,
+                $5,
+                $6,
+                $7,
+                $8
+            )
+            ON CONFLICT (maintenance_window_uid) DO UPDATE
+            SET origin_id = EXCLUDED.origin_id,
+                tag = EXCLUDED.tag,
+                starts_at = EXCLUDED.starts_at,
+                ends_at = EXCLUDED.ends_at,
+                recurrence = EXCLUDED.recurrence,
+                mode = EXCLUDED.mode,
+                description = EXCLUDED.description
+        "#,
+        window.maintenance_window_uid,
+        window.origin_uid,
+        window.tag,
+        window.starts_at,
+        window.ends_at,
+        window.recurrence.as_str(),
+        window.mode.as_str(),
+        window.description,
+    )
+    .execute(tx.deref_mut())
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete_maintenance_window(pool: &PgPool, maintenance_window_uid: Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+            DELETE FROM maintenance_window
+            WHERE maintenance_window_uid = $1
+        "#,
+        maintenance_window_uid,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The raw measurements a health score is derived from.
+pub struct HealthInputs {
+    /// The number of queries made within the availability window.
+    pub checks: i64,
+    /// The number of those queries which succeeded.
+    pub successes: i64,
+    /// The number of times the origin flipped between succeeding and failing.
+    pub transitions: i64,
+    /// The average latency within the latency window.
+    pub recent_latency_millis: Option<f64>,
+    /// The average latency within the baseline window.
+    pub baseline_latency_millis: Option<f64>,
+}
+
+pub async fn fetch_health_inputs(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    availability_window: Duration,
+    latency_window: Duration,
+    baseline_window: Duration,
+    now: DateTime<Utc>,
+) -> Result<HealthInputs> {
+    let inputs = sqlx::query_as!(
+        HealthInputs,
+        r#"
+            WITH outcome AS (
+                SELECT q.queried_at, TRUE AS success
+                FROM query q
+                JOIN origin o ON o.id = q.origin_id
+                WHERE o.origin_uid = $1
+                AND q.queried_at > $2
+                UNION ALL
+                SELECT qf.queried_at, FALSE AS success
+                FROM query_failure qf
+                JOIN origin o ON o.id = qf.origin_id
+                WHERE o.origin_uid = $1
+                AND qf.queried_at > $2
+            ), ordered AS (
+                SELECT success, LAG(success) OVER (ORDER BY queried_at) AS previous
+                FROM outcome
+            )
+            SELECT
+                (SELECT COUNT(*) FROM outcome) AS "checks!",
+                (SELECT COUNT(*) FROM outcome WHERE success) AS "successes!",
+                (SELECT COUNT(*) FROM ordered WHERE success <> previous) AS "transitions!",
+                (
+                    SELECT AVG(q.latency_millis)::FLOAT8
+                    FROM query q
+                    JOIN origin o ON o.id = q.origin_id
+                    WHERE o.origin_uid = $1
+                    AND q.queried_at > $3
+                ) AS recent_latency_millis,
+                (
+                    SELECT AVG(q.latency_millis)::FLOAT8
+                    FROM query q
+                    JOIN origin o ON o.id = q.origin_id
+                    WHERE o.origin_uid = $1
+                    AND q.queried_at > $4
+                ) AS baseline_latency_millis
+        "#,
+        origin_uid,
+        now - availability_window,
+        now - latency_window,
+        now - baseline_window,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(inputs)
+}
+
+/// Records a health score, removing those of the origin computed more than
+/// [`crate::health::RETENTION`] before it so each origin only keeps a bounded number.
+pub async fn insert_health_score(
+    pool: &PgPool,
+    origin_uid: Uuid,
+    score: i16,
+    computed_at: DateTime<Utc>,
+) -> Result<()> {
+    let expired_before = computed_at - crate::health::RETENTION;
+
+    sqlx::query!(
+        r#"
+            WITH o AS (
+                SELECT id FROM origin WHERE origin_uid = $1
+            ), inserted AS (
+                INSERT INTO health_score (origin_id, score, computed_at)
+                SELECT id, $2, $3
+                FROM o
+            )
+            DELETE FROM health_score
+            WHERE origin_id = (SELECT id FROM o)
+            AND computed_at < $4
+        "#,
+        origin_uid,
+        score,
+        computed_at,
+        expired_before,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub struct HealthScore {
+    pub origin_uid: Uuid,
+    pub score: i16,
+}
+
+/// Fetches up to `limit` of the most recent health scores for each origin, oldest first.
+pub async fn fetch_recent_health_scores(pool: &PgPool, limit: i64) -> Result<Vec<HealthScore>> {
+    let scores = sqlx::query_as!(
+        HealthScore,
+        r#"
+            SELECT o.origin_uid, recent.score
+            FROM origin o
+            CROSS JOIN LATERAL (
+                SELECT hs.score, hs.computed_at
+                FROM health_score hs
+                WHERE hs.origin_id = o.id
+                ORDER BY hs.computed_at DESC
+                LIMIT $1
+            ) recent
+            ORDER BY o.id, recent.computed_at
+        "#,
+        limit,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(scores)
+}
+
+/// Availability and latency for an origin over the most recent period and the one before it.
+pub struct PeriodComparison {
+    pub origin_uid: Uuid,
+    pub current_uptime: Option<f64>,
+    pub previous_uptime: Option<f64>,
+    pub current_latency_millis: Option<f64>,
+    pub previous_latency_millis: Option<f64>,
+}
+
+pub async fn fetch_period_comparisons(
+    pool: &PgPool,
+    period: Duration,
+) -> Result<Vec<PeriodComparison>> {
+    let current_start = Utc::now() - period;
+    let previous_start = current_start - period;
+
+    let comparisons = sqlx::query_as!(
+        PeriodComparison,
+        r#"
+            WITH outcome AS (
+                SELECT origin_id, queried_at, TRUE AS success, latency_millis
+                FROM query
+                WHERE queried_at > $1
+                UNION ALL
+                SELECT origin_id, queried_at, FALSE AS success, NULL AS latency_millis
+                FROM query_failure
+                WHERE queried_at > $1
+            )
+            SELECT
+                o.origin_uid,
+                AVG(CASE WHEN c.success THEN 1.0 ELSE 0.0 END) FILTER (WHERE c.queried_at > $2)::FLOAT8 AS current_uptime,
+                AVG(CASE WHEN c.success THEN 1.0 ELSE 0.0 END) FILTER (WHERE c.queried_at <= $2)::FLOAT8 AS previous_uptime,
+                AVG(c.latency_millis) FILTER (WHERE c.queried_at > $2)::FLOAT8 AS current_latency_millis,
+                AVG(c.latency_millis) FILTER (WHERE c.queried_at <= $2)::FLOAT8 AS previous_latency_millis
+            FROM origin o
+            JOIN outcome c ON c.origin_id = o.id
+            GROUP BY o.id
+        "#,
+        previous_start,
+        current_start,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(comparisons)
+}
+
+/// How an origin has fared from one of the locations it is checked from.
+pub struct LocationStatus {
+    pub origin_uid: Uuid,
+    pub location: String,
+    /// Whether the most recent check from the location passed.
+    pub operational: bool,
+    /// The fraction of checks from the location that passed.
+    pub uptime: f64,
+    pub queried_at: DateTime<Utc>,
+}
+
+/// The status and uptime since `since` of each origin from each location it was checked from, or
+/// only those of `origin_uid`. Checks made by pollers without a location are left out.
+pub async fn fetch_location_statuses(
+    pool: &PgPool,
+    origin_uid: Option<Uuid>,
+    since: DateTime<Utc>,
+) -> Result<Vec<LocationStatus>> {
+    let statuses = sqlx::query_as!(
+        LocationStatus,
+        r#"
+            WITH outcome AS (
+                SELECT origin_id, location, queried_at, TRUE AS success
+                FROM query
+                WHERE location IS NOT NULL
+                AND queried_at > $2
+                UNION ALL
+                SELECT origin_id, location, queried_at, FALSE AS success
+                FROM query_failure
+                WHERE location IS NOT NULL
+                AND queried_at > $2
+            )
+            SELECT
+                o.origin_uid,
+                c.location AS "location!",
+                (ARRAY_AGG(c.success ORDER BY c.queried_at DESC))[1] AS "operational!",
+                AVG(CASE WHEN c.success THEN 1.0 ELSE 0.0 END)::FLOAT8 AS "uptime!",
+                MAX(c.queried_at) AS "queried_at!"
+            FROM origin o
+            JOIN outcome c ON c.origin_id = o.id
+            WHERE ($1::UUID IS NULL OR o.origin_uid = $1)
+            GROUP BY o.id, c.location
+            ORDER BY o.id, c.location
+        "#,
+        origin_uid,
+        since,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(statuses)
+}
+
+/// The spread of an origin's latency from one of the locations it is checked from.
+pub struct LocationLatency {
+    pub origin_uid: Uuid,
+    pub uri: String,
+    pub location: String,
+    pub p50_millis: f64,
+    pub p95_millis: f64,
+    /// How many successful checks the percentiles were taken from.
+    pub samples: i64,
+}
+
+/// The median and 95th percentile latency since `since` of each origin from each location it was
+/// checked from. Checks made by pollers without a location are left out.
+pub async fn fetch_location_latencies(
+    pool: &PgPool,
+    since: DateTime<Utc>,
+) -> Result<Vec<LocationLatency>> {
+    let latencies = sqlx::query_as!(
+        LocationLatency,
+        r#"
+            SELECT
+                o.origin_uid,
+                o.uri,
+                q.location AS "location!",
+                PERCENTILE_CONT(0.5) WITHIN GROUP (ORDER BY q.latency_millis) AS "p50_millis!",
+                PERCENTILE_CONT(0.95) WITHIN GROUP (ORDER BY q.latency_millis) AS "p95_millis!",
+                COUNT(*) AS "samples!"
+            FROM origin o
+            JOIN query q ON q.origin_id = o.id
+            WHERE q.location IS NOT NULL
+            AND q.queried_at > $1
+            GROUP BY o.id, q.location
+            ORDER BY o.id, q.location
+        "#,
+        since,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(latencies)
+}
+
+pub struct DailyLatency {
+    pub origin_uid: Uuid,
+    pub uri: String,
+    pub day: NaiveDate,
+    pub average_latency_millis: f64,
+}
+
+pub async fn fetch_daily_latencies(
+    pool: &PgPool,
+    since: DateTime<Utc>,
+) -> Result<Vec<DailyLatency>> {
+    let latencies = sqlx::query_as!(
+        DailyLatency,
+        r#"
+            SELECT
+                o.origin_uid,
+                o.uri,
+                (q.queried_at AT TIME ZONE 'UTC')::DATE AS "day!",
+                AVG(q.latency_millis)::FLOAT8 AS "average_latency_millis!"
+            FROM origin o
+            JOIN query q ON o.id = q.origin_id
+            WHERE q.queried_at > $1
+            GROUP BY o.id, 3
+            ORDER BY o.id, 3
+        "#,
+        since,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(latencies)
+}
+
+/// How many checks were compacted, and into how many rollups.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Compaction {
+    pub checks: i64,
+    pub rollups: i64,
+}
+
+/// Replaces every check made before `before` with one rollup per origin and period of
+/// `resolution_seconds`, adding to any rollup that already exists for the period.
+pub async fn compact_checks(
+    tx: &mut Transaction,
+    before: DateTime<Utc>,
+    resolution_seconds: i64,
+) -> Result<Compaction> {
+    let compaction = sqlx::query_as!(
+        Compaction,
+        r#"
+            WITH compacted_queries AS (
+                DELETE FROM query
+                WHERE queried_at < $1
+                RETURNING origin_id, queried_at, latency_millis
+            ), compacted_query_failures AS (
+                DELETE FROM query_failure
+                WHERE queried_at < $1
+                RETURNING origin_id, queried_at
+            ), outcome AS (
+                SELECT origin_id, queried_at, TRUE AS success, latency_millis
+                FROM compacted_queries
+                UNION ALL
+                SELECT origin_id, queried_at, FALSE AS success, NULL AS latency_millis
+                FROM compacted_query_failures
+            ), rolled_up AS (
+                INSERT INTO query_rollup (
+                    origin_id, period_start, resolution_seconds, successes, failures,
+                    total_latency_millis, max_latency_millis
+                )
+                SELECT
+                    origin_id,
+                    TO_TIMESTAMP((FLOOR(EXTRACT(EPOCH FROM queried_at))::BIGINT / $2 * $2)::FLOAT8),
+                    $2,
+                    COUNT(*) FILTER (WHERE success),
+                    COUNT(*) FILTER (WHERE NOT success),
+                    COALESCE(SUM(latency_millis), 0),
+                    MAX(latency_millis)
+                FROM outcome
+                GROUP BY 1, 2
+                ON CONFLICT (origin_id, period_start) DO UPDATE
+                SET successes = query_rollup.successes + EXCLUDED.successes,
+                    failures = query_rollup.failures + EXCLUDED.failures,
+                    total_latency_millis = query_rollup.total_latency_millis + EXCLUDED.total_latency_millis,
+                    max_latency_millis = GREATEST(query_rollup.max_latency_millis, EXCLUDED.max_latency_millis)
+                RETURNING id
+            )
+            SELECT
+                (SELECT COUNT(*) FROM outcome) AS "checks!",
+                (SELECT COUNT(*) FROM rolled_up) AS "rollups!"
+        "#,
+        before,
+        resolution_seconds,
+    )
+    .fetch_one(tx.deref_mut())
+    .await?;
+
+    Ok(compaction)
+}
+
+/// Refreshes the planner statistics of the tables written to by every check, which drift as they
+/// grow faster than autovacuum keeps up with.
+pub async fn analyze_hot_tables(pool: &PgPool) -> Result<()> {
+    sqlx::query!(
+        r#"
+            ANALYZE query, query_failure, health_score
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Rebuilds the indexes of the tables written to by every check, without blocking the writes.
+pub async fn reindex_hot_tables(pool: &PgPool) -> Result<()> {
+    sqlx::query!(
+        r#"
+            REINDEX TABLE CONCURRENTLY query
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query!(
+        r#"
+            REINDEX TABLE CONCURRENTLY query_failure
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query!(
+        r#"
+            REINDEX TABLE CONCURRENTLY health_score
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// How much space a table takes up and how recently its statistics were refreshed.
+pub struct TableSize {
+    pub name: String,
+    /// The planner's estimate, which is only as fresh as the last analysis.
+    pub estimated_rows: i64,
+    pub table_bytes: i64,
+    pub index_bytes: i64,
+    pub dead_rows: i64,
+    pub last_analyzed_at: Option<DateTime<Utc>>,
+    pub last_vacuumed_at: Option<DateTime<Utc>>,
+}
+
+/// Fetches the size of every table, largest first.
+pub async fn fetch_table_sizes(pool: &PgPool) -> Result<Vec<TableSize>> {
+    let tables = sqlx::query_as!(
+        TableSize,
+        r#"
+            SELECT
+                s.relname::TEXT AS "name!",
+                GREATEST(c.reltuples, 0)::BIGINT AS "estimated_rows!",
+                pg_table_size(s.relid) AS "table_bytes!",
+                pg_indexes_size(s.relid) AS "index_bytes!",
+                s.n_dead_tup AS "dead_rows!",
+                GREATEST(s.last_analyze, s.last_autoanalyze) AS last_analyzed_at,
+                GREATEST(s.last_vacuum, s.last_autovacuum) AS last_vacuumed_at
+            FROM pg_stat_user_tables s
+            JOIN pg_class c ON c.oid = s.relid
+            ORDER BY pg_total_relation_size(s.relid) DESC, s.relname
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(tables)
+}
+
+/// A migration that has been run against the database.
+pub struct AppliedMigration {
+    pub version: i64,
+    pub description: String,
+    pub installed_on: DateTime<Utc>,
+    /// Whether the migration finished, as one that failed part way is left recorded.
+    pub success: bool,
+}
+
+pub async fn fetch_applied_migrations(pool: &PgPool) -> Result<Vec<AppliedMigration>> {
+    let migrations = sqlx::query_as!(
+        AppliedMigration,
+        r#"
+            SELECT version, description, installed_on, success
+            FROM _sqlx_migrations
+            ORDER BY version
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(migrations)
+}
+
+/// Fetches how much space the whole database takes up.
+pub async fn fetch_database_bytes(pool: &PgPool) -> Result<i64> {
+    let bytes = sqlx::query_scalar!(
+        r#"
+            SELECT pg_database_size(current_database()) AS "bytes!"
+        "#
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(bytes)
+}
+
+/// How much space an index takes up and how often it is used.
+pub struct IndexSize {
+    pub name: String,
+    pub table_name: String,
+    pub bytes: i64,
+    pub scans: i64,
+}
+
+/// Fetches the size of every index, largest first.
+pub async fn fetch_index_sizes(pool: &PgPool) -> Result<Vec<IndexSize>> {
+    let indexes = sqlx::query_as!(
+        IndexSize,
+        r#"
+            SELECT
+                s.indexrelname::TEXT AS "name!",
+                s.relname::TEXT AS "table_name!",
+                pg_relation_size(s.indexrelid) AS "bytes!",
+                s.idx_scan AS "scans!"
+            FROM pg_stat_user_indexes s
+            ORDER BY pg_relation_size(s.indexrelid) DESC, s.indexrelname
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(indexes)
+}
+
+/// Availability and downtime for an origin within a reporting period.
+pub struct OriginReport {
+    pub uri: String,
+    pub successes: i64,
+    pub failures: i64,
+    pub incidents: i64,
+    /// The time spent in incidents, clamped to the reporting period.
+    pub downtime_seconds: f64,
+    pub cost_per_minute_cents: Option<i64>,
+}
+
+pub async fn fetch_origin_reports(
+    pool: &PgPool,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<OriginReport>> {
+    let reports = sqlx::query_as!(
+        OriginReport,
+        r#"
+            SELECT
+                o.uri,
+                (
+                    SELECT COUNT(*)
+                    FROM query q
+                    WHERE q.origin_id = o.id
+                    AND q.queried_at >= $1
+                    AND q.queried_at < $2
+                ) + (
+                    SELECT COALESCE(SUM(r.successes), 0)
+                    FROM query_rollup r
+                    WHERE r.origin_id = o.id
+                    AND r.period_start >= $1
+                    AND r.period_start < $2
+                )::BIGINT AS "successes!",
+                (
+                    SELECT COUNT(*)
+                    FROM query_failure qf
+                    WHERE qf.origin_id = o.id
+                    AND qf.queried_at >= $1
+                    AND qf.queried_at < $2
+                ) + (
+                    SELECT COALESCE(SUM(r.failures), 0)
+                    FROM query_rollup r
+                    WHERE r.origin_id = o.id
+                    AND r.period_start >= $1
+                    AND r.period_start < $2
+                )::BIGINT AS "failures!",
+                (
+                    SELECT COUNT(*)
+                    FROM incident i
+                    WHERE i.origin_id = o.id
+                    AND i.started_at >= $1
+                    AND i.started_at < $2
+                ) AS "incidents!",
+                (
+                    SELECT COALESCE(SUM(EXTRACT(EPOCH FROM
+                        LEAST(COALESCE(i.resolved_at, NOW()), $2) - GREATEST(i.started_at, $1)
+                    )), 0)::FLOAT8
+                    FROM incident i
+                    WHERE i.origin_id = o.id
+                    AND i.started_at < $2
+                    AND COALESCE(i.resolved_at, NOW()) > $1
+                ) AS "downtime_seconds!",
+                o.cost_per_minute_cents
+            FROM origin o
+            ORDER BY o.pinned DESC, o.display_order, o.id
+        "#,
+        start,
+        end,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(reports)
+}
+
+#[derive(Serialize)]
+pub struct DraftSummary {
+    pub draft_uid: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub promoted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct Draft {
+    pub draft_uid: Uuid,
+    pub name: String,
+    pub document: Json<MonitorConfiguration>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub promoted_at: Option<DateTime<Utc>>,
+}
+
+pub async fn insert_draft(
+    pool: &PgPool,
+    name: &str,
+    document: &MonitorConfiguration,
+) -> Result<Uuid> {
+    let draft_uid = Uuid::new_v4();
+    let now = Utc::now();
+
+    sqlx::query!(
+        r#"
+            INSERT INTO configuration_draft (draft_uid, name, document, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $4)
+        "#,
+        draft_uid,
+        name,
+        Json(document) as _,
+        now,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(draft_uid)
+}
+
+pub async fn fetch_drafts(pool: &PgPool) -> Result<Vec<DraftSummary>> {
+    let drafts = sqlx::query_as!(
+        DraftSummary,
+        r#"
+            SELECT draft_uid, name, created_at, updated_at, promoted_at
+            FROM configuration_draft
+            ORDER BY created_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(drafts)
+}
+
+pub async fn fetch_draft(pool: &PgPool, draft_uid: Uuid) -> Result<Option<Draft>> {
+    let draft = sqlx::query_as!(
+        Draft,
+        r#"
+            SELECT
+                draft_uid,
+                name,
+                document AS "document: Json<MonitorConfiguration>",
+                created_at,
+                updated_at,
+                promoted_at
+            FROM configuration_draft
+            WHERE draft_uid = $1
+        "#,
+        draft_uid,
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(draft)
+}
+
+/// Replaces the contents of a draft, returning `false` if it does not exist or was already
+/// promoted.
+pub async fn update_draft_document(
+    pool: &PgPool,
+    draft_uid: Uuid,
+    document: &MonitorConfiguration,
+) -> Result<bool> {
+    let result = sqlx::query!(
+        r#"
+            UPDATE configuration_draft
+            SET document = $2, updated_at = $3
+            WHERE draft_uid = $1
+            AND promoted_at IS NULL
+        "#,
+        draft_uid,
+        Json(document) as _,
+        Utc::now(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Locks a draft that has not been promoted yet for the rest of the transaction.
+pub async fn lock_unpromoted_draft(
+    tx: &mut Transaction,
+    draft_uid: Uuid,
+) -> Result<Option<MonitorConfiguration>> {
+    let document = sqlx::query_scalar!(
+        r#"
+            SELECT document AS "document: Json<MonitorConfiguration>"
+            FROM configuration_draft
+            WHERE draft_uid = $1
+            AND promoted_at IS NULL
+            FOR UPDATE
+        "#,
+        draft_uid,
+    )
+    .fetch_optional(tx.deref_mut())
+    .await?;
+
+    Ok(document.map(|Json(document)| document))
+}
+
+pub async fn mark_draft_promoted(tx: &mut Transaction, draft_uid: Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE configuration_draft
+            SET promoted_at = $2
+            WHERE draft_uid = $1
+        "#,
+        draft_uid,
+        Utc::now(),
+    )
+    .execute(tx.deref_mut())
+    .await?;
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+pub struct PendingChange {
+    pub change_uid: Uuid,
+    pub kind: String,
+    pub target_uid: Uuid,
+    pub description: String,
+    pub status: String,
+    pub requested_by: String,
+    pub requested_at: DateTime<Utc>,
+    pub decided_by: Option<String>,
+    pub decided_at: Option<DateTime<Utc>>,
+}
+
+/// Records a request for a change, returning `None` if the same change is already awaiting
+/// approval.
+pub async fn insert_pending_change(
+    pool: &PgPool,
+    kind: &str,
+    target_uid: Uuid,
+    description: &str,
+    requested_by: &str,
+) -> Result<Option<Uuid>> {
+    let change_uid = sqlx::query_scalar!(
+        r#"
+            INSERT INTO pending_change (change_uid, kind, target_uid, description, requested_by, requested_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (kind, target_uid) WHERE status = 'pending' DO NOTHING
+            RETURNING change_uid
+        "#,
+        Uuid::new_v4(),
+        kind,
+        target_uid,
+        description,
+        requested_by,
+        Utc::now(),
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(change_uid)
+}
+
+pub async fn fetch_pending_changes(pool: &PgPool, limit: i64) -> Result<Vec<PendingChange>> {
+    let changes = sqlx::query_as!(
+        PendingChange,
+        r#"
+            SELECT
+                change_uid,
+                kind,
+                target_uid,
+                description,
+                status,
+                requested_by,
+                requested_at,
+                decided_by,
+                decided_at
+            FROM pending_change
+            ORDER BY status = 'pending' DESC, requested_at DESC
+            LIMIT $1
+        "#,
+        limit,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(changes)
+}
+
+/// Locks a change that is still awaiting a decision for the rest of the transaction.
+pub async fn lock_pending_change(
+    tx: &mut Transaction,
+    change_uid: Uuid,
+) -> Result<Option<PendingChange>> {
+    let change = sqlx::query_as!(
+        PendingChange,
+        r#"
+            SELECT
+                change_uid,
+                kind,
+                target_uid,
+                description,
+                status,
+                requested_by,
+                requested_at,
+                decided_by,
+                decided_at
+            FROM pending_change
+            WHERE change_uid = $1
+            AND status = 'pending'
+            FOR UPDATE
+        "#,
+        change_uid,
+    )
+    .fetch_optional(tx.deref_mut())
+    .await?;
+
+    Ok(change)
+}
+
+pub async fn record_change_decision(
+    tx: &mut Transaction,
+    change_uid: Uuid,
+    status: &str,
+    decided_by: &str,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE pending_change
+            SET status = $2, decided_by = $3, decided_at = $4
+            WHERE change_uid = $1
+        "#,
+        change_uid,
+        status,
+        decided_by,
+        Utc::now(),
+    )
+    .execute(tx.deref_mut())
+    .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+pub struct UserPreferences {
+    pub timezone: String,
+    pub page_size: Option<i32>,
+    pub tag_filter: Vec<String>,
+}
+
+impl Default for UserPreferences {
+    fn default() -> Self {
+        Self {
+            timezone: String::from("UTC"),
+            page_size: None,
+            tag_filter: Vec::new(),
+        }
+    }
+}
+
+pub async fn fetch_user_preferences(pool: &PgPool, username: &str) -> Result<UserPreferences> {
+    let preferences = sqlx::query_as!(
+        UserPreferences,
+        r#"
+            SELECT timezone, page_size, tag_filter
+            FROM user_preference
+            WHERE username = $1
+        "#,
+        username,
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(preferences.unwrap_or_default())
+}
+
+pub async fn upsert_user_preferences(
+    pool: &PgPool,
+    username: &str,
+    preferences: &UserPreferences,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            INSERT INTO user_preference (username, timezone, page_size, tag_filter, updated_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (username) DO UPDATE
+            SET timezone = EXCLUDED.timezone,
+                page_size = EXCLUDED.page_size,
+                tag_filter = EXCLUDED.tag_filter,
+                updated_at = EXCLUDED.updated_at
+        "#,
+        username,
+        preferences.timezone,
+        preferences.page_size,
+        &preferences.tag_filter,
+        Utc::now(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn fetch_favorite_origins(pool: &PgPool, username: &str) -> Result<Vec<Uuid>> {
+    let favorites = sqlx::query_scalar!(
+        r#"
+            SELECT o.origin_uid
+            FROM user_favorite uf
+            JOIN origin o ON o.id = uf.origin_id
+            WHERE uf.username = $1
+        "#,
+        username,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(favorites)
+}
+
+pub async fn set_favorite_origin(
+    pool: &PgPool,
+    username: &str,
+    origin_uid: Uuid,
+    favorite: bool,
+) -> Result<()> {
+    let query = if favorite {
+        sqlx::query!(
+            r#"
+                INSERT INTO user_favorite (username, origin_id, created_at)
+                SELECT $1, id, $3
+                FROM origin
+                WHERE origin_uid = $2
+                ON CONFLICT (username, origin_id) DO NOTHING
+            "#,
+            username,
+            origin_uid,
+            Utc::now(),
+        )
+    } else {
+        sqlx::query!(
+            r#"
+                DELETE FROM user_favorite
+                WHERE username = $1
+                AND origin_id = (SELECT id FROM origin WHERE origin_uid = $2)
+            "#,
+            username,
+            origin_uid,
+        )
+    };
+
+    query.execute(pool).await?;
+
+    Ok(())
+}
+
+/// Where a user's personal alerts are delivered.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DestinationKind {
+    /// An SNS topic, which can fan out to the user's email address or phone.
+    Sns,
+    /// A Slack incoming webhook, such as one that posts to the user's direct messages.
+    Slack,
+}
+
+impl DestinationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sns => "sns",
+            Self::Slack => "slack",
+        }
+    }
+
+    fn from_column(value: &str) -> Option<Self> {
+        match value {
+            "sns" => Some(Self::Sns),
+            "slack" => Some(Self::Slack),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UserDestination {
+    pub kind: DestinationKind,
+    pub target: String,
+    /// The language alerts are written in, overriding the locale of the deployment.
+    pub locale: Option<Locale>,
+}
+
+pub async fn fetch_user_destination(
+    pool: &PgPool,
+    username: &str,
+) -> Result<Option<UserDestination>> {
+    let destination = sqlx::query!(
+        r#"
+            SELECT kind, target, locale
+            FROM user_destination
+            WHERE username = $1
+        "#,
+        username,
+    )
+    .fetch_optional(pool)
+    .await?
+    .and_then(|row| {
+        DestinationKind::from_column(&row.kind).map(|kind| UserDestination {
+            kind,
+            target: row.target,
+            locale: row.locale.as_deref().and_then(Locale::from_column),
+        })
+    });
+
+    Ok(destination)
+}
+
+pub async fn upsert_user_destination(
+    pool: &PgPool,
+    username: &str,
+    destination: &UserDestination,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            INSERT INTO user_destination (username, kind, target, locale, updated_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (username) DO UPDATE
+            SET kind = EXCLUDED.kind,
+                target = EXCLUDED.target,
+                locale = EXCLUDED.locale,
+                updated_at = EXCLUDED.updated_at
+        "#,
+        username,
+        destination.kind.as_str(),
+        destination.target,
+        destination.locale.map(|locale| locale.as_str()),
+        Utc::now(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// A user's personal destination as stored in configuration exports.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UserDestinationConfiguration {
+    pub username: String,
+    #[serde(flatten)]
+    pub destination: UserDestination,
+}
+
+pub async fn fetch_user_destination_configurations(
+    pool: &PgPool,
+) -> Result<Vec<UserDestinationConfiguration>> {
+    let destinations = sqlx::query!(
+        r#"
+            SELECT username, kind, target, locale
+            FROM user_destination
+            ORDER BY username
+        "#
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .filter_map(|row| {
+        DestinationKind::from_column(&row.kind).map(|kind| UserDestinationConfiguration {
+            username: row.username,
+            destination: UserDestination {
+                kind,
+                target: row.target,
+                locale: row.locale.as_deref().and_then(Locale::from_column),
+            },
+        })
+    })
+    .collect();
+
+    Ok(destinations)
+}
+
+pub async fn upsert_user_destination_configuration(
+    tx: &mut Transaction,
+    configuration: &UserDestinationConfiguration,
+) -> Result<()> {
+    let UserDestinationConfiguration {
+        username,
+        destination,
+    } = configuration;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO user_destination (username, kind, target, locale, updated_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (username) DO UPDATE
+            SET kind = EXCLUDED.kind,
+                target = EXCLUDED.target,
+                locale = EXCLUDED.locale,
+                updated_at = EXCLUDED.updated_at
+        "#,
+        username,
+        destination.kind.as_str(),
+        destination.target,
+        destination.locale.map(|locale| locale.as_str()),
+        Utc::now(),
+    )
+    .execute(tx.deref_mut())
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete_user_destination(pool: &PgPool, username: &str) -> Result<()> {
+    sqlx::query!(
+        r#"
+            DELETE FROM user_destination
+            WHERE username = $1
+        "#,
+        username,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// A user who has an origin on their watchlist and wants to be alerted about it personally.
+pub struct Watcher {
+    pub username: String,
+    pub destination: UserDestination,
+}
+
+pub async fn fetch_watchers(pool: &PgPool, origin_uid: Uuid) -> Result<Vec<Watcher>> {
+    let watchers = sqlx::query!(
+        r#"
+            SELECT ud.username, ud.kind, ud.target, ud.locale
+            FROM user_favorite uf
+            JOIN origin o ON o.id = uf.origin_id
+            JOIN user_destination ud ON ud.username = uf.username
+            WHERE o.origin_uid = $1
+            ORDER BY ud.username
+        "#,
+        origin_uid,
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .filter_map(|row| {
+        let kind = DestinationKind::from_column(&row.kind)?;
+
+        Some(Watcher {
+            username: row.username,
+            destination: UserDestination {
+                kind,
+                target: row.target,
+                locale: row.locale.as_deref().and_then(Locale::from_column),
+            },
+        })
+    })
+    .collect();
+
+    Ok(watchers)
+}
+
+/// An origin on a user's watchlist.
+#[derive(Serialize)]
+pub struct WatchedOrigin {
+    pub origin_uid: Uuid,
+    pub uri: String,
+    pub alerts_enabled: bool,
+}
+
+pub async fn fetch_watchlist(pool: &PgPool, username: &str) -> Result<Vec<WatchedOrigin>> {
+    let origins = sqlx::query_as!(
+        WatchedOrigin,
+        r#"
+            SELECT o.origin_uid, o.uri, o.alerts_enabled
+            FROM user_favorite uf
+            JOIN origin o ON o.id = uf.origin_id
+            WHERE uf.username = $1
+            ORDER BY o.pinned DESC, o.display_order, o.id
+        "#,
+        username,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(origins)
+}
+
+/// A browser's Web Push subscription, with keys encoded as base64url.
+#[derive(Clone, Debug, Deserialize)]
+pub struct PushSubscription {
+    pub endpoint: String,
+    pub p256dh: String,
+    pub auth: String,
+}
+
+pub async fn upsert_push_subscription(
+    pool: &PgPool,
+    username: &str,
+    subscription: &PushSubscription,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            INSERT INTO push_subscription (username, endpoint, p256dh, auth, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (endpoint) DO UPDATE
+            SET username = EXCLUDED.username,
+                p256dh = EXCLUDED.p256dh,
+                auth = EXCLUDED.auth
+        "#,
+        username,
+        subscription.endpoint,
+        subscription.p256dh,
+        subscription.auth,
+        Utc::now(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Removes a subscription, returning whether the user owned it.
+pub async fn remove_push_subscription(
+    pool: &PgPool,
+    username: &str,
+    endpoint: &str,
+) -> Result<bool> {
+    let result = sqlx::query!(
+        r#"
+            DELETE FROM push_subscription
+            WHERE username = $1 AND endpoint = $2
+        "#,
+        username,
+        endpoint,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Removes a subscription the push service has told us no longer exists.
+pub async fn expire_push_subscription(pool: &PgPool, endpoint: &str) -> Result<()> {
+    sqlx::query!(
+        r#"
+            DELETE FROM push_subscription
+            WHERE endpoint = $1
+        "#,
+        endpoint,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn fetch_push_subscriptions(pool: &PgPool) -> Result<Vec<PushSubscription>> {
+    let subscriptions = sqlx::query_as!(
+        PushSubscription,
+        r#"
+            SELECT endpoint, p256dh, auth
+            FROM push_subscription
+            ORDER BY id
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(subscriptions)
+}
+
+pub async fn count_push_subscriptions(pool: &PgPool, username: &str) -> Result<i64> {
+    let count = sqlx::query_scalar!(
+        r#"
+            SELECT COUNT(*) AS "count!"
+            FROM push_subscription
+            WHERE username = $1
+        "#,
+        username,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+/// The first request made with an idempotency key, along with its response once it has finished.
+pub struct IdempotencyRecord {
+    pub request_hash: String,
+    pub status: Option<i16>,
+    pub content_type: Option<String>,
+    pub response_body: Option<Vec<u8>>,
+}
+
+/// Claims `idempotency_key` for a request by `username`, returning `None` if they had not used it
+/// or what they first used it for otherwise.
+pub async fn claim_idempotency_key(
+    pool: &PgPool,
+    username: &str,
+    idempotency_key: &str,
+    request_hash: &str,
+    created_at: DateTime<Utc>,
+) -> Result<Option<IdempotencyRecord>> {
+    let claimed = sqlx::query!(
+        r#"
+            INSERT INTO idempotency_key (username, idempotency_key, request_hash, created_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (username, idempotency_key) DO NOTHING
+        "#,
+        username,
+        idempotency_key,
+        request_hash,
+        created_at,
+    )
+    .execute(pool)
+    .await?
+    .rows_affected()
+        == 1;
+
+    if claimed {
+        return Ok(None);
+    }
+
+    let record = sqlx::query_as!(
+        IdempotencyRecord,
+        r#"
+            SELECT request_hash, status, content_type, response_body
+            FROM idempotency_key
+            WHERE username = $1
+            AND idempotency_key = $2
+        "#,
+        username,
+        idempotency_key,
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    // The first request released the key in between, so it is as good as still in progress
+    Ok(Some(record.unwrap_or(IdempotencyRecord {
+        request_hash: request_hash.to_owned(),
+        status: None,
+        content_type: None,
+        response_body: None,
+    })))
+}
+
+/// Records the response to the first request made with `idempotency_key`, for retries to replay.
+pub async fn complete_idempotency_key(
+    pool: &PgPool,
+    username: &str,
+    idempotency_key: &str,
+    status: i16,
+    content_type: Option<&str>,
+    response_body: &[u8],
+    completed_at: DateTime<Utc>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            UPDATE idempotency_key
+            SET status = $3, content_type = $4, response_body = $5, completed_at = $6
+            WHERE username = $1
+            AND idempotency_key = $2
+        "#,
+        username,
+        idempotency_key,
+        status,
+        content_type,
+        response_body,
+        completed_at,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Frees `idempotency_key` after its request failed, so it can be retried.
+pub async fn release_idempotency_key(
+    pool: &PgPool,
+    username: &str,
+    idempotency_key: &str,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            DELETE FROM idempotency_key
+            WHERE username = $1
+            AND idempotency_key = $2
+        "#,
+        username,
+        idempotency_key,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Removes keys claimed before `expired_before`, along with any whose request was claimed before
+/// `abandoned_before` and never finished.
+pub async fn delete_expired_idempotency_keys(
+    pool: &PgPool,
+    expired_before: DateTime<Utc>,
+    abandoned_before: DateTime<Utc>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+            DELETE FROM idempotency_key
+            WHERE created_at < $1
+            OR (completed_at IS NULL AND created_at < $2)
+        "#,
+        expired_before,
+        abandoned_before,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,222 @@
+use color_eyre::eyre::{eyre, Result};
+use serde_json::Value;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Operator {
+    Equals,
+    NotEquals,
+}
+
+/// A check against a JSON response, such as `$.status == "ok"` or `$.checks[0].healthy`.
+///
+/// Paths support `.key`, `['key']` and `[index]` segments. Without a comparison, the assertion
+/// only requires the path to exist.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonAssertion {
+    path: Vec<Segment>,
+    comparison: Option<(Operator, Value)>,
+}
+
+/// Finds the comparison operator, ignoring anything inside quotes.
+fn find_operator(expression: &str) -> Option<(usize, Operator)> {
+    let mut quote = None;
+    let bytes = expression.as_bytes();
+
+    for (i, &byte) in bytes.iter().enumerate() {
+        match (quote, byte) {
+            (None, b'"' | b'\'') => quote = Some(byte),
+            (Some(open), _) if byte == open => quote = None,
+            (None, b'=' | b'!') if bytes.get(i + 1) == Some(&b'=') => {
+                let operator = if byte == b'=' {
+                    Operator::Equals
+                } else {
+                    Operator::NotEquals
+                };
+
+                return Some((i, operator));
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn parse_path(path: &str) -> Result<Vec<Segment>> {
+    let rest = path
+        .strip_prefix('$')
+        .ok_or_else(|| eyre!("paths must start with $"))?;
+
+    let mut segments = Vec::new();
+    let mut chars = rest.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => {
+                let mut key = String::new();
+
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_' || c == '-') {
+                        break;
+                    }
+
+                    key.push(c);
+                    chars.next();
+                }
+
+                if key.is_empty() {
+                    return Err(eyre!("expected a key after '.'"));
+                }
+
+                segments.push(Segment::Key(key));
+            }
+            '[' => {
+                let mut inner = String::new();
+
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+
+                    inner.push(c);
+                }
+
+                let quoted = inner
+                    .strip_prefix('\'')
+                    .and_then(|key| key.strip_suffix('\''))
+                    .or_else(|| {
+                        inner
+                            .strip_prefix('"')
+                            .and_then(|key| key.strip_suffix('"'))
+                    });
+
+                let segment = match quoted {
+                    Some(key) => Segment::Key(key.to_owned()),
+                    None => Segment::Index(
+                        inner
+                            .trim()
+                            .parse()
+                            .map_err(|_| eyre!("'{inner}' is not a quoted key or an index"))?,
+                    ),
+                };
+
+                segments.push(segment);
+            }
+            c => return Err(eyre!("unexpected '{c}' in path")),
+        }
+    }
+
+    Ok(segments)
+}
+
+impl JsonAssertion {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let expression = expression.trim();
+
+        let (path, comparison) = match find_operator(expression) {
+            Some((at, operator)) => {
+                let literal = expression[at + 2..].trim();
+                let expected = serde_json::from_str(literal).map_err(|_| {
+                    eyre!("'{literal}' is not a JSON value, strings must be double quoted")
+                })?;
+
+                (&expression[..at], Some((operator, expected)))
+            }
+            None => (expression, None),
+        };
+
+        Ok(Self {
+            path: parse_path(path.trim())?,
+            comparison,
+        })
+    }
+
+    /// Whether the assertion is only a path, without a comparison.
+    pub fn is_path(&self) -> bool {
+        self.comparison.is_none()
+    }
+
+    /// The value at the path in the document, if there is one.
+    pub fn select<'a>(&self, document: &'a Value) -> Option<&'a Value> {
+        self.path
+            .iter()
+            .try_fold(document, |value, segment| match segment {
+                Segment::Key(key) => value.get(key),
+                Segment::Index(index) => value.get(index),
+            })
+    }
+
+    /// Whether the document satisfies the assertion.
+    pub fn holds(&self, document: &Value) -> bool {
+        let selected = self.select(document);
+
+        match (&self.comparison, selected) {
+            (None, selected) => selected.is_some(),
+            (Some((Operator::Equals, expected)), selected) => selected == Some(expected),
+            (Some((Operator::NotEquals, expected)), selected) => selected != Some(expected),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use color_eyre::eyre::Result;
+    use serde_json::json;
+
+    use crate::poller::assertion::JsonAssertion;
+
+    #[test]
+    fn equality_is_checked_against_the_selected_value() -> Result<()> {
+        let document = json!({ "status": "ok", "checks": [{ "name": "db", "healthy": true }] });
+
+        assert!(JsonAssertion::parse(r#"$.status == "ok""#)?.holds(&document));
+        assert!(!JsonAssertion::parse(r#"$.status == "degraded""#)?.holds(&document));
+        assert!(JsonAssertion::parse("$.checks[0].healthy == true")?.holds(&document));
+        assert!(JsonAssertion::parse(r#"$['checks'][0]["name"] != "cache""#)?.holds(&document));
+
+        Ok(())
+    }
+
+    #[test]
+    fn paths_without_a_comparison_must_exist() -> Result<()> {
+        let document = json!({ "status": null });
+
+        assert!(JsonAssertion::parse("$.status")?.holds(&document));
+        assert!(!JsonAssertion::parse("$.uptime")?.holds(&document));
+
+        Ok(())
+    }
+
+    #[test]
+    fn missing_values_only_satisfy_inequality() -> Result<()> {
+        let document = json!([]);
+
+        assert!(!JsonAssertion::parse("$[3] == 1")?.holds(&document));
+        assert!(JsonAssertion::parse("$[3] != 1")?.holds(&document));
+
+        Ok(())
+    }
+
+    #[test]
+    fn operators_inside_quotes_are_ignored() -> Result<()> {
+        let document = json!({ "a==b": "x != y" });
+
+        assert!(JsonAssertion::parse(r#"$['a==b'] == "x != y""#)?.holds(&document));
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_expressions_are_rejected() {
+        assert!(JsonAssertion::parse("status == \"ok\"").is_err());
+        assert!(JsonAssertion::parse("$.status == ok").is_err());
+        assert!(JsonAssertion::parse("$.").is_err());
+        assert!(JsonAssertion::parse("$[first]").is_err());
+    }
+}
@@ -0,0 +1,320 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use reqwest::header::{
+    HeaderMap, HeaderValue, CACHE_CONTROL, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use reqwest::StatusCode;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::persistence::Origin;
+use crate::poller::http::{self, Checked, HttpFailure, Verified};
+use crate::poller::FailureReason;
+
+/// The validators from the most recent response of each origin, sent back on its next check.
+pub type ValidatorCache = Mutex<HashMap<Uuid, Validators>>;
+
+/// The headers a cache uses to revalidate a response it already has.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Validators {
+    etag: Option<HeaderValue>,
+    last_modified: Option<HeaderValue>,
+}
+
+impl Validators {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        Self {
+            etag: headers.get(ETAG).cloned(),
+            last_modified: headers.get(LAST_MODIFIED).cloned(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+fn display(value: &HeaderValue) -> Cow<'_, str> {
+    String::from_utf8_lossy(value.as_bytes())
+}
+
+/// Compares entity tags the way `If-None-Match` does, where weak tags match their strong form.
+fn etags_match(left: &HeaderValue, right: &HeaderValue) -> bool {
+    let strip = |value: &HeaderValue| {
+        let bytes = value.as_bytes();
+        bytes.strip_prefix(b"W/").unwrap_or(bytes).to_vec()
+    };
+
+    strip(left) == strip(right)
+}
+
+/// Checks a response to a request made with the `previous` validators, returning the validators
+/// to use next time or an explanation of what the origin got wrong.
+fn validate(
+    previous: Option<&Validators>,
+    status: StatusCode,
+    headers: &HeaderMap,
+) -> Result<Option<Validators>, String> {
+    let current = Validators::from_headers(headers);
+
+    if status == StatusCode::NOT_MODIFIED {
+        let Some(previous) = previous else {
+            return Err(String::from(
+                "responded 304 Not Modified to a request without validators",
+            ));
+        };
+
+        // Servers must repeat the tag the full response would have had
+        if let (Some(sent), Some(received)) = (&previous.etag, &current.etag) {
+            if !etags_match(sent, received) {
+                return Err(format!(
+                    "responded 304 Not Modified with ETag {} to If-None-Match {}",
+                    display(received),
+                    display(sent)
+                ));
+            }
+        }
+
+        return Ok(Some(Validators {
+            etag: current.etag.or_else(|| previous.etag.clone()),
+            last_modified: current
+                .last_modified
+                .or_else(|| previous.last_modified.clone()),
+        }));
+    }
+
+    // Errors are left to the usual status checks, as they are not expected to be cached
+    if !status.is_success() {
+        return Ok(None);
+    }
+
+    if !headers.contains_key(CACHE_CONTROL) {
+        return Err(format!("responded {status} without a Cache-Control header"));
+    }
+
+    if current.is_empty() {
+        return Err(format!(
+            "responded {status} without an ETag or Last-Modified header to validate against"
+        ));
+    }
+
+    if let Some(previous) = previous {
+        // `If-None-Match` takes precedence, so `If-Modified-Since` only matters without a tag
+        if let (Some(sent), Some(received)) = (&previous.etag, &current.etag) {
+            if etags_match(sent, received) {
+                return Err(format!(
+                    "responded {status} to If-None-Match {} despite sending the same ETag",
+                    display(sent)
+                ));
+            }
+        } else if let (None, Some(sent)) = (&previous.etag, &previous.last_modified) {
+            if current.last_modified.as_ref() == Some(sent) {
+                return Err(format!(
+                    "responded {status} to If-Modified-Since {} despite being unmodified",
+                    display(sent)
+                ));
+            }
+        }
+    }
+
+    Ok(Some(current))
+}
+
+/// Makes the request configured for an origin with any extra `headers` conditional on its
+/// previous response, verifying the origin revalidates correctly before checking the response as
+/// usual.
+pub async fn check(
+    http_client: &reqwest::Client,
+    origin: &Origin,
+    uri: &str,
+    timeout: Duration,
+    mut headers: HeaderMap,
+    cache: &ValidatorCache,
+) -> Result<Checked, HttpFailure> {
+    let previous = cache.lock().await.get(&origin.origin_uid).cloned();
+
+    if let Some(previous) = &previous {
+        if let Some(etag) = &previous.etag {
+            headers.insert(IF_NONE_MATCH, etag.clone());
+        }
+
+        if let Some(last_modified) = &previous.last_modified {
+            headers.insert(IF_MODIFIED_SINCE, last_modified.clone());
+        }
+    }
+
+    let start = Instant::now();
+    let (response, redirects) = http::send(http_client, origin, uri, timeout, headers).await?;
+    let first_byte_millis = start.elapsed().as_millis() as i64;
+    let remote_address = response.remote_addr().map(|addr| addr.ip());
+    let http_version = http::version_name(response.version());
+    let captured_headers = http::capture_headers(origin, response.headers());
+    let status = response.status();
+
+    let validators =
+        validate(previous.as_ref(), status, response.headers()).map_err(|detail| HttpFailure {
+            reason: FailureReason::CacheValidationFailed,
+            detail: Some(detail),
+            captured_headers: captured_headers.clone(),
+            remote_address,
+        })?;
+
+    if let Some(validators) = validators {
+        cache.lock().await.insert(origin.origin_uid, validators);
+    }
+
+    // There is no content to check, and the status is exactly what was asked for. Nor does a
+    // not modified response have to repeat headers such as the content type
+    let Verified {
+        status,
+        body_bytes,
+        body_hash,
+    } = match status {
+        StatusCode::NOT_MODIFIED => Verified {
+            status: status.as_u16(),
+            body_bytes: None,
+            body_hash: None,
+        },
+        _ => {
+            let expected_headers = http::check_headers(origin, response.headers());
+
+            let verified = http::verify(origin, response)
+                .await
+                .map_err(|reason| HttpFailure {
+                    reason,
+                    detail: None,
+                    captured_headers: captured_headers.clone(),
+                    remote_address,
+                })?;
+
+            expected_headers.map_err(|failure| HttpFailure {
+                captured_headers: captured_headers.clone(),
+                remote_address,
+                ..failure
+            })?;
+
+            verified
+        }
+    };
+
+    Ok(Checked {
+        status,
+        redirects,
+        remote_address,
+        body_bytes,
+        http_version,
+        captured_headers,
+        first_byte_millis,
+        body_hash,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::{HeaderMap, HeaderValue, CACHE_CONTROL, ETAG, LAST_MODIFIED};
+    use reqwest::StatusCode;
+
+    use crate::poller::cache::Validators;
+
+    fn headers(pairs: &[(reqwest::header::HeaderName, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.clone(), HeaderValue::from_static(value)))
+            .collect()
+    }
+
+    fn tagged(etag: &'static str) -> Validators {
+        Validators {
+            etag: Some(HeaderValue::from_static(etag)),
+            last_modified: None,
+        }
+    }
+
+    #[test]
+    fn first_responses_need_cache_headers() {
+        let cacheable = headers(&[(CACHE_CONTROL, "max-age=60"), (ETAG, "\"v1\"")]);
+
+        assert_eq!(
+            super::validate(None, StatusCode::OK, &cacheable),
+            Ok(Some(tagged("\"v1\"")))
+        );
+
+        let uncacheable = headers(&[(ETAG, "\"v1\"")]);
+
+        assert_eq!(
+            super::validate(None, StatusCode::OK, &uncacheable),
+            Err(String::from(
+                "responded 200 OK without a Cache-Control header"
+            ))
+        );
+
+        let unvalidated = headers(&[(CACHE_CONTROL, "max-age=60")]);
+
+        assert!(super::validate(None, StatusCode::OK, &unvalidated).is_err());
+    }
+
+    #[test]
+    fn conditional_requests_must_not_return_unchanged_content() {
+        let previous = tagged("\"v1\"");
+
+        // Weak and strong forms of the same tag describe the same content
+        let unchanged = headers(&[(CACHE_CONTROL, "max-age=60"), (ETAG, "W/\"v1\"")]);
+
+        assert_eq!(
+            super::validate(Some(&previous), StatusCode::OK, &unchanged),
+            Err(String::from(
+                "responded 200 OK to If-None-Match \"v1\" despite sending the same ETag"
+            ))
+        );
+
+        let changed = headers(&[(CACHE_CONTROL, "max-age=60"), (ETAG, "\"v2\"")]);
+
+        assert_eq!(
+            super::validate(Some(&previous), StatusCode::OK, &changed),
+            Ok(Some(tagged("\"v2\"")))
+        );
+    }
+
+    #[test]
+    fn not_modified_responses_keep_their_validators() {
+        let previous = Validators {
+            etag: Some(HeaderValue::from_static("\"v1\"")),
+            last_modified: Some(HeaderValue::from_static("Wed, 14 Oct 2026 09:00:00 GMT")),
+        };
+
+        assert_eq!(
+            super::validate(Some(&previous), StatusCode::NOT_MODIFIED, &HeaderMap::new()),
+            Ok(Some(previous.clone()))
+        );
+
+        let mismatched = headers(&[(ETAG, "\"v2\"")]);
+
+        assert!(super::validate(Some(&previous), StatusCode::NOT_MODIFIED, &mismatched).is_err());
+        assert!(super::validate(None, StatusCode::NOT_MODIFIED, &HeaderMap::new()).is_err());
+    }
+
+    #[test]
+    fn modification_dates_are_used_without_tags() {
+        let previous = Validators {
+            etag: None,
+            last_modified: Some(HeaderValue::from_static("Wed, 14 Oct 2026 09:00:00 GMT")),
+        };
+
+        let unchanged = headers(&[
+            (CACHE_CONTROL, "no-cache"),
+            (LAST_MODIFIED, "Wed, 14 Oct 2026 09:00:00 GMT"),
+        ]);
+
+        assert!(super::validate(Some(&previous), StatusCode::OK, &unchanged).is_err());
+    }
+
+    #[test]
+    fn errors_are_left_to_status_checks() {
+        assert_eq!(
+            super::validate(None, StatusCode::INTERNAL_SERVER_ERROR, &HeaderMap::new()),
+            Ok(None)
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use sqlx::types::chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::credentials::CredentialCipher;
+use crate::persistence::{AddressFamily, Origin};
+use crate::poller::clients::HttpClients;
+use crate::poller::clock::Clock;
+use crate::poller::database::DatabaseChecker;
+use crate::poller::dns::DnsChecker;
+use crate::poller::download::DownloadChecker;
+use crate::poller::external::CommandChecker;
+use crate::poller::ftp::FileTransferChecker;
+use crate::poller::grpc::GrpcChecker;
+use crate::poller::heartbeat::HeartbeatChecker;
+use crate::poller::http::{self, HttpChecker};
+use crate::poller::icmp::IcmpChecker;
+use crate::poller::journey::JourneyChecker;
+use crate::poller::keyvalue::KeyValueChecker;
+use crate::poller::mqtt::MqttChecker;
+use crate::poller::ntp::NtpChecker;
+use crate::poller::redirect::RedirectChecker;
+use crate::poller::smtp::SmtpChecker;
+use crate::poller::tcp::TcpChecker;
+use crate::poller::timing::Timings;
+use crate::poller::websocket::WebSocketChecker;
+use crate::poller::{CheckKind, FailureReason};
+
+/// A way of checking origins, such as making an HTTP request or opening a TCP connection.
+pub trait Check: Send + Sync {
+    fn execute<'a>(
+        &'a self,
+        origin: &'a Origin,
+        context: &'a CheckContext<'a>,
+    ) -> BoxFuture<'a, CheckOutcome>;
+}
+
+/// Everything a check can use besides the origin itself.
+pub struct CheckContext<'a> {
+    pub pool: &'a PgPool,
+    pub http_clients: &'a HttpClients,
+    pub credentials: Option<&'a CredentialCipher>,
+    /// The family to connect over, which only HTTP checks can be made over.
+    pub family: AddressFamily,
+    pub timeout: Duration,
+    pub clock: &'a dyn Clock,
+    pub started_at: DateTime<Utc>,
+}
+
+impl CheckContext<'_> {
+    /// How long the check has taken so far.
+    pub fn elapsed_millis(&self) -> i64 {
+        (self.clock.now() - self.started_at).num_milliseconds()
+    }
+}
+
+/// What a check found, along with anything else it learnt about the origin along the way.
+pub struct CheckOutcome {
+    /// The status and latency of a successful check.
+    pub result: Result<(Option<u16>, i64), FailureReason>,
+    /// Why the check failed, for checks that can explain it.
+    pub failure_detail: Option<String>,
+    pub redirect_chain: Option<String>,
+    pub redirects: Option<http::Redirects>,
+    pub remote_address: Option<IpAddr>,
+    pub body_bytes: Option<i64>,
+    pub http_version: Option<&'static str>,
+    pub captured_headers: Option<String>,
+    pub timings: Option<Timings>,
+    /// How long the check took over a new connection, for origins measuring both.
+    pub cold_latency_millis: Option<i64>,
+    /// The SHA-256 of the response body, for origins detecting changes to it.
+    pub body_hash: Option<String>,
+    /// How fast the body downloaded, for checks of large assets.
+    pub throughput_bytes_per_second: Option<i64>,
+    /// How far ahead of the poller's clock the origin's is, for checks of time servers.
+    pub clock_offset_millis: Option<i64>,
+}
+
+impl CheckOutcome {
+    pub fn new(result: Result<(Option<u16>, i64), FailureReason>) -> Self {
+        Self {
+            result,
+            failure_detail: None,
+            redirect_chain: None,
+            redirects: None,
+            remote_address: None,
+            body_bytes: None,
+            http_version: None,
+            captured_headers: None,
+            timings: None,
+            cold_latency_millis: None,
+            body_hash: None,
+            throughput_bytes_per_second: None,
+            clock_offset_millis: None,
+        }
+    }
+
+    pub fn failed(reason: FailureReason, detail: String) -> Self {
+        Self {
+            failure_detail: Some(detail),
+            ..Self::new(Err(reason))
+        }
+    }
+}
+
+/// The checks origins are made with, keyed by the name of the kind of origin each one handles.
+pub struct CheckRegistry {
+    checks: HashMap<&'static str, Box<dyn Check>>,
+}
+
+impl CheckRegistry {
+    /// A registry with a check for every kind of origin.
+    pub fn builtin() -> Self {
+        Self {
+            checks: HashMap::new(),
+        }
+        .with("http", HttpChecker::default())
+        .with("tcp", TcpChecker)
+        .with("icmp", IcmpChecker)
+        .with("dns", DnsChecker)
+        .with("grpc", GrpcChecker::default())
+        .with("websocket", WebSocketChecker::default())
+        .with("smtp", SmtpChecker::default())
+        .with("mqtt", MqttChecker::default())
+        .with("keyvalue", KeyValueChecker::default())
+        .with("database", DatabaseChecker)
+        .with("ntp", NtpChecker)
+        .with("redirect", RedirectChecker)
+        .with("download", DownloadChecker)
+        .with("ftp", FileTransferChecker)
+        .with("command", CommandChecker::default())
+        .with("heartbeat", HeartbeatChecker)
+        .with("journey", JourneyChecker)
+    }
+
+    /// Registers `check` for origins of `kind`, replacing any check already registered for them.
+    pub fn with<C: Check + 'static>(mut self, kind: &'static str, check: C) -> Self {
+        self.checks.insert(kind, Box::new(check));
+        self
+    }
+
+    /// The check for origins of `kind`, if one is registered.
+    pub fn get(&self, kind: &CheckKind) -> Option<&dyn Check> {
+        self.checks.get(kind.name()).map(Box::as_ref)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::poller::check::CheckRegistry;
+    use crate::poller::CheckKind;
+
+    #[test]
+    fn every_kind_of_origin_has_a_check() {
+        let registry = CheckRegistry::builtin();
+
+        let uris = [
+            "https://example.com",
+            "tcp://db.internal:5432",
+            "icmp://router.internal",
+            "dns://example.com?type=MX",
+            "grpcs://api.example.com/payments.v1.Payments",
+            "wss://realtime.example.com/socket",
+            "smtp://mail.example.com:587",
+            "mqtts://broker.example.com?topic=uptime/probe",
+            "redis://cache.internal",
+            "memcached://sessions.internal",
+            "postgres://uptime@db.internal/app",
+            "ntp://time.example.com",
+            "redirect://www.example.com",
+            "download+https://mirror.example.com/ubuntu.iso",
+            "ftp://files.example.com/outgoing?list",
+            "sftp://files.example.com",
+            "command://check_vpn.sh",
+            "heartbeat://nightly-backup",
+            "journey://checkout",
+        ];
+
+        for uri in uris {
+            assert!(
+                registry.get(&CheckKind::from_uri(uri)).is_some(),
+                "{uri} has no check"
+            );
+        }
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::{Identity, Proxy, Url};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::credentials::{CredentialCipher, Credentials};
+use crate::persistence::{AddressFamily, HttpVersion, Origin};
+use crate::poller::http::HttpFailure;
+use crate::poller::{redirect, FailureReason};
+
+/// The User-Agent checks send unless the poller or origin sets another, as some firewalls block
+/// requests without one.
+pub const DEFAULT_USER_AGENT: &str = concat!("uptime/", env!("CARGO_PKG_VERSION"));
+
+/// Resolves hosts to the addresses of one family only, so connections cannot quietly fall back
+/// to the other.
+struct FamilyResolver {
+    family: AddressFamily,
+}
+
+impl Resolve for FamilyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let family = self.family;
+
+        Box::pin(async move {
+            let host = name.as_str();
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0))
+                .await?
+                .filter(|addr| family.allows(addr.ip()))
+                .collect();
+
+            if addrs.is_empty() {
+                return Err(format!("{host} has no {} addresses", family.name()).into());
+            }
+
+            Ok::<Addrs, Box<dyn Error + Send + Sync>>(Box::new(addrs.into_iter()))
+        })
+    }
+}
+
+/// The IP address an HTTP URI connects to directly, without resolving a host.
+pub fn literal_ip(uri: &str) -> Option<IpAddr> {
+    let url = Url::parse(uri).ok()?;
+    let host = url.host_str()?;
+
+    // IPv6 addresses keep their brackets when written as a host
+    host.trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .ok()
+}
+
+/// The headers that authenticate HTTP checks of an origin, if it has credentials.
+pub fn authentication(
+    origin: &Origin,
+    cipher: Option<&CredentialCipher>,
+) -> Result<HeaderMap, HttpFailure> {
+    let mut headers = HeaderMap::new();
+
+    let Some(encrypted) = &origin.encrypted_credentials else {
+        return Ok(headers);
+    };
+
+    let failure = |detail: &str| HttpFailure {
+        reason: FailureReason::BadRequest,
+        detail: Some(detail.to_owned()),
+        captured_headers: None,
+        remote_address: None,
+    };
+
+    let Some(cipher) = cipher else {
+        return Err(failure(
+            "the origin has credentials but CREDENTIALS_KEY is not set",
+        ));
+    };
+
+    let authorization = cipher
+        .decrypt(origin.origin_uid, encrypted)
+        .and_then(|credentials| credentials.authorization())
+        .map_err(|e| failure(&format!("the credentials of the origin are unusable: {e}")))?;
+
+    headers.insert(AUTHORIZATION, authorization);
+
+    Ok(headers)
+}
+
+/// The username and password of an origin, for checks that log in with them rather than sending
+/// an `Authorization` header, explaining why if the origin's credentials cannot be used.
+pub fn username_and_password(
+    origin: &Origin,
+    cipher: Option<&CredentialCipher>,
+) -> Result<Option<(String, String)>, String> {
+    let Some(encrypted) = &origin.encrypted_credentials else {
+        return Ok(None);
+    };
+
+    let Some(cipher) = cipher else {
+        return Err(String::from(
+            "the origin has credentials but CREDENTIALS_KEY is not set",
+        ));
+    };
+
+    let credentials = cipher
+        .decrypt(origin.origin_uid, encrypted)
+        .map_err(|e| format!("the credentials of the origin are unusable: {e}"))?;
+
+    match credentials {
+        Credentials::Basic { username, password } => Ok(Some((username, password))),
+        Credentials::Bearer { .. } => Err(String::from(
+            "the origin has a token, but only a username and password can be logged in with",
+        )),
+    }
+}
+
+/// Starts building a client for checks that only connect over `family` with `version` of HTTP,
+/// identifying as `user_agent` and sending them through `proxy` if there is one. With `fresh`,
+/// connections are closed after each request so every check connects and handshakes again.
+///
+/// Proxies resolve the hosts they connect to themselves, so with one only the connection to the
+/// proxy is limited to `family`.
+fn builder(
+    family: AddressFamily,
+    proxy: Option<&Proxy>,
+    version: HttpVersion,
+    user_agent: &HeaderValue,
+    fresh: bool,
+) -> reqwest::ClientBuilder {
+    let mut builder = redirect::builder().user_agent(user_agent.clone());
+
+    if fresh {
+        builder = builder.pool_max_idle_per_host(0);
+    }
+
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(proxy.clone());
+    }
+
+    builder = match version {
+        HttpVersion::Auto => builder,
+        HttpVersion::Http1 => builder.http1_only(),
+        // Only HTTP/2 is offered during the TLS handshake, and plaintext connections skip the
+        // upgrade, so servers that cannot speak it fail rather than falling back
+        HttpVersion::Http2 => builder.http2_prior_knowledge(),
+    };
+
+    match family {
+        AddressFamily::Ipv4 | AddressFamily::Ipv6 => {
+            builder.dns_resolver(Arc::new(FamilyResolver { family }))
+        }
+        AddressFamily::Any | AddressFamily::Both => builder,
+    }
+}
+
+/// A client built for an origin with a client certificate, proxy or HTTP version of its own, or
+/// that opens fresh connections, along with what it was built from so it can be rebuilt when any
+/// of them change.
+struct DedicatedClient {
+    encrypted_identity: Option<Vec<u8>>,
+    proxy: Option<String>,
+    http_version: HttpVersion,
+    client: reqwest::Client,
+}
+
+/// The clients HTTP checks are made with, which are shared for each address family unless an
+/// origin presents a client certificate for mutual TLS, has its own proxy, forces a version of
+/// HTTP or is checked over fresh connections.
+pub struct HttpClients {
+    /// The proxy checks are sent through unless an origin has its own.
+    proxy: Option<Proxy>,
+    /// The User-Agent checks send unless an origin has its own.
+    user_agent: HeaderValue,
+    shared: HashMap<AddressFamily, reqwest::Client>,
+    dedicated: Mutex<HashMap<(Uuid, AddressFamily, bool), DedicatedClient>>,
+}
+
+impl HttpClients {
+    pub fn new(proxy: Option<Proxy>, user_agent: HeaderValue) -> Self {
+        let shared = [AddressFamily::Any, AddressFamily::Ipv4, AddressFamily::Ipv6]
+            .into_iter()
+            .map(|family| {
+                let client = builder(
+                    family,
+                    proxy.as_ref(),
+                    HttpVersion::Auto,
+                    &user_agent,
+                    false,
+                )
+                .build()
+                .expect("failed to build the redirect client");
+
+                (family, client)
+            })
+            .collect();
+
+        Self {
+            proxy,
+            user_agent,
+            shared,
+            dedicated: Mutex::default(),
+        }
+    }
+
+    /// The client without a client certificate that connects over either family, for checks
+    /// that do not need anything else.
+    pub fn shared(&self) -> &reqwest::Client {
+        &self.shared[&AddressFamily::Any]
+    }
+
+    /// Whether requests for an origin are sent through a proxy, which hides the origin's address.
+    pub fn proxied(&self, origin: &Origin) -> bool {
+        origin.proxy.is_some() || self.proxy.is_some()
+    }
+
+    /// The client and headers to make HTTP checks of an origin over `family` with, which
+    /// authenticate it with any credentials or client certificate it has and open a new connection
+    /// for every request if `fresh`.
+    pub async fn prepare(
+        &self,
+        origin: &Origin,
+        uri: &str,
+        family: AddressFamily,
+        fresh: bool,
+        cipher: Option<&CredentialCipher>,
+    ) -> Result<(reqwest::Client, HeaderMap), HttpFailure> {
+        // Addresses are never resolved, so the client cannot keep them to one family
+        if let Some(ip) = literal_ip(uri).filter(|ip| !family.allows(*ip)) {
+            return Err(HttpFailure {
+                reason: FailureReason::BadRequest,
+                detail: Some(format!("{ip} is not an {} address", family.name())),
+                captured_headers: None,
+                remote_address: None,
+            });
+        }
+
+        let headers = authentication(origin, cipher)?;
+        let client = self.for_origin(origin, family, fresh, cipher).await?;
+
+        Ok((client, headers))
+    }
+
+    /// The client to check an origin with over `family`, building one with its client
+    /// certificate, proxy or HTTP version, or that does not keep connections alive if `fresh`,
+    /// when needed.
+    pub async fn for_origin(
+        &self,
+        origin: &Origin,
+        family: AddressFamily,
+        fresh: bool,
+        cipher: Option<&CredentialCipher>,
+    ) -> Result<reqwest::Client, HttpFailure> {
+        let key = (origin.origin_uid, family, fresh);
+        let mut dedicated = self.dedicated.lock().await;
+
+        if !fresh
+            && origin.encrypted_client_identity.is_none()
+            && origin.proxy.is_none()
+            && origin.http_version == HttpVersion::Auto
+        {
+            // Drop any client left over from something that has since been removed
+            dedicated.remove(&key);
+
+            let shared = self.shared.get(&family).unwrap_or_else(|| self.shared());
+            return Ok(shared.clone());
+        }
+
+        if let Some(existing) = dedicated.get(&key) {
+            if existing.encrypted_identity == origin.encrypted_client_identity
+                && existing.proxy == origin.proxy
+                && existing.http_version == origin.http_version
+            {
+                return Ok(existing.client.clone());
+            }
+        }
+
+        let failure = |detail: String| HttpFailure {
+            reason: FailureReason::BadRequest,
+            detail: Some(detail),
+            captured_headers: None,
+            remote_address: None,
+        };
+
+        let proxy = match &origin.proxy {
+            Some(proxy) => Some(
+                Proxy::all(proxy)
+                    .map_err(|e| failure(format!("the proxy of the origin is unusable: {e}")))?,
+            ),
+            None => self.proxy.clone(),
+        };
+
+        let builder = builder(
+            family,
+            proxy.as_ref(),
+            origin.http_version,
+            &self.user_agent,
+            fresh,
+        );
+
+        let client = match &origin.encrypted_client_identity {
+            Some(encrypted_identity) => {
+                let Some(cipher) = cipher else {
+                    return Err(failure(String::from(
+                        "the origin has a client certificate but CREDENTIALS_KEY is not set",
+                    )));
+                };
+
+                cipher
+                    .decrypt_identity(origin.origin_uid, encrypted_identity)
+                    .and_then(|pem| Ok(Identity::from_pem(&pem)?))
+                    .and_then(|identity| Ok(builder.identity(identity).build()?))
+                    .map_err(|e| {
+                        failure(format!(
+                            "the client certificate of the origin is unusable: {e}"
+                        ))
+                    })?
+            }
+            None => builder
+                .build()
+                .map_err(|e| failure(format!("failed to build a client for the origin: {e}")))?,
+        };
+
+        dedicated.insert(
+            key,
+            DedicatedClient {
+                encrypted_identity: origin.encrypted_client_identity.clone(),
+                proxy: origin.proxy.clone(),
+                http_version: origin.http_version,
+                client: client.clone(),
+            },
+        );
+
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn only_addresses_are_literal() {
+        assert_eq!(
+            super::literal_ip("http://127.0.0.1:8080/health"),
+            Some(IpAddr::V4(Ipv4Addr::LOCALHOST))
+        );
+        assert_eq!(
+            super::literal_ip("https://[::1]/"),
+            Some(IpAddr::V6(Ipv6Addr::LOCALHOST))
+        );
+        assert_eq!(super::literal_ip("https://example.com/"), None);
+    }
+}
@@ -0,0 +1,154 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc, watch};
+use uuid::Uuid;
+
+use crate::poller::firehose::CheckResult;
+use crate::poller::scheduler::SchedulerState;
+
+/// The number of commands that can be waiting for the poller before new ones are rejected.
+pub const QUEUE_CAPACITY: usize = 64;
+
+/// The number of check results kept for those waiting on them before the oldest are dropped.
+pub const COMPLETED_CAPACITY: usize = 256;
+
+/// How many alerts in a row have to fail to send before the notifier is considered broken.
+pub const FAILING_AFTER: u32 = 3;
+
+/// Instructions that can be sent to a running poller.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Checks an origin straight away, even while paused.
+    CheckNow(Uuid),
+    /// Stops the poller from checking origins until it is resumed.
+    Pause,
+    /// Starts checking origins again, beginning with a new round.
+    Resume,
+    /// Refreshes the set of origins and starts a new round immediately.
+    Reload,
+}
+
+/// Why a check asked for with [`PollerHandle::check_now`] did not produce a result.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CheckNowError {
+    /// Too many commands were already waiting for the poller.
+    Busy,
+    /// The check did not finish in time, or was skipped by a maintenance window.
+    TimedOut,
+}
+
+/// How sending alerts to the topic has been going, so a broken notifier is noticed before it
+/// causes an outage to be missed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NotifierHealth {
+    pub last_sent_at: Option<DateTime<Utc>>,
+    pub last_failed_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// How many alerts in a row have failed to send.
+    pub consecutive_failures: u32,
+}
+
+impl NotifierHealth {
+    pub fn record_sent(&mut self, sent_at: DateTime<Utc>) {
+        self.last_sent_at = Some(sent_at);
+        self.consecutive_failures = 0;
+    }
+
+    pub fn record_failure(&mut self, failed_at: DateTime<Utc>, error: String) {
+        self.last_failed_at = Some(failed_at);
+        self.last_error = Some(error);
+        self.consecutive_failures += 1;
+    }
+
+    /// Whether enough alerts in a row have failed that outages are likely going unnoticed.
+    pub fn is_failing(&self) -> bool {
+        self.consecutive_failures >= FAILING_AFTER
+    }
+}
+
+/// Lets the rest of the application control the poller and see what it is doing.
+#[derive(Clone)]
+pub struct PollerHandle {
+    sender: mpsc::Sender<Command>,
+    paused: Arc<AtomicBool>,
+    scheduler: watch::Receiver<SchedulerState>,
+    notifier: watch::Receiver<NotifierHealth>,
+    completed: broadcast::Sender<CheckResult>,
+}
+
+impl PollerHandle {
+    pub fn new(
+        sender: mpsc::Sender<Command>,
+        paused: Arc<AtomicBool>,
+        scheduler: watch::Receiver<SchedulerState>,
+        notifier: watch::Receiver<NotifierHealth>,
+        completed: broadcast::Sender<CheckResult>,
+    ) -> Self {
+        Self {
+            sender,
+            paused,
+            scheduler,
+            notifier,
+            completed,
+        }
+    }
+
+    /// Queues a command for the poller, returning `false` if it could not be queued.
+    pub fn send(&self, command: Command) -> bool {
+        match self.sender.try_send(command) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!(?command, %e, "failed to send a command to the poller");
+                false
+            }
+        }
+    }
+
+    /// Checks an origin straight away, waiting up to `timeout` for the result.
+    pub async fn check_now(
+        &self,
+        origin_uid: Uuid,
+        timeout: Duration,
+    ) -> Result<CheckResult, CheckNowError> {
+        // Subscribing before sending the command means the result cannot be missed
+        let mut completed = self.completed.subscribe();
+
+        if !self.send(Command::CheckNow(origin_uid)) {
+            return Err(CheckNowError::Busy);
+        }
+
+        let result = async {
+            loop {
+                match completed.recv().await {
+                    Ok(result) if result.origin_uid == origin_uid => return Some(result),
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        };
+
+        tokio::time::timeout(timeout, result)
+            .await
+            .ok()
+            .flatten()
+            .ok_or(CheckNowError::TimedOut)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// The latest state published by the scheduler of the poller.
+    pub fn scheduler(&self) -> SchedulerState {
+        self.scheduler.borrow().clone()
+    }
+
+    /// How sending alerts to the topic has been going.
+    pub fn notifier_health(&self) -> NotifierHealth {
+        self.notifier.borrow().clone()
+    }
+}
@@ -0,0 +1,254 @@
+use std::fmt;
+
+use chrono::{DateTime, Datelike, DurationRound, TimeDelta, Timelike, Utc};
+use chrono_tz::Tz;
+
+/// The most minutes looked through for a match, as a schedule that has not matched in a day is
+/// not going to match in the minutes before it either.
+const MAX_MINUTES_SEARCHED: i32 = 24 * 60;
+
+/// Why a schedule could not be parsed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct CronError(String);
+
+impl fmt::Display for CronError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A field of a schedule, with a bit set for each value it matches.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Field {
+    values: u64,
+    /// Whether the field was `*`, which matters for the days a schedule matches.
+    any: bool,
+}
+
+impl Field {
+    fn parse(text: &str, name: &str, min: u32, max: u32) -> Result<Self, CronError> {
+        let invalid = |detail: String| CronError(format!("the {name} field {detail}"));
+        let mut values = 0;
+
+        for part in text.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => {
+                    let step: u32 = step
+                        .parse()
+                        .ok()
+                        .filter(|step| *step > 0)
+                        .ok_or_else(|| invalid(format!("has an invalid step in '{part}'")))?;
+
+                    (range, step)
+                }
+                None => (part, 1),
+            };
+
+            let number = |value: &str| {
+                value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|value| (min..=max).contains(value))
+                    .ok_or_else(|| {
+                        invalid(format!("must be between {min} and {max}, not '{value}'"))
+                    })
+            };
+
+            let (start, end) = match range.split_once('-') {
+                _ if range == "*" => (min, max),
+                Some((start, end)) => (number(start)?, number(end)?),
+                // A single value with a step runs to the end of the field, as in `5/15`
+                None if step > 1 => (number(range)?, max),
+                None => (number(range)?, number(range)?),
+            };
+
+            if start > end {
+                return Err(invalid(format!("has a backwards range in '{part}'")));
+            }
+
+            for value in (start..=end).step_by(step as usize) {
+                values |= 1 << value;
+            }
+        }
+
+        Ok(Self {
+            values,
+            any: text == "*",
+        })
+    }
+
+    fn contains(&self, value: u32) -> bool {
+        self.values & (1 << value) != 0
+    }
+}
+
+/// When an origin is checked, written as the five fields of a cron expression such as
+/// `*/5 9-17 * * 1-5`, optionally preceded by `CRON_TZ=Europe/London` to use a timezone other
+/// than UTC.
+///
+/// Fields can be `*`, values, ranges such as `9-17`, lists such as `1,15` and steps such as
+/// `*/5`. As in cron, a schedule that restricts both the day of the month and the day of the
+/// week matches days that either allows.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: Field,
+    hours: Field,
+    days_of_month: Field,
+    months: Field,
+    days_of_week: Field,
+    timezone: Tz,
+}
+
+impl CronSchedule {
+    pub fn parse(text: &str) -> Result<Self, CronError> {
+        let mut text = text.trim();
+        let mut timezone = Tz::UTC;
+
+        if let Some(rest) = text.strip_prefix("CRON_TZ=") {
+            let (name, fields) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+
+            timezone = name
+                .parse()
+                .map_err(|_| CronError(format!("'{name}' is not a timezone")))?;
+            text = fields.trim_start();
+        }
+
+        let fields: Vec<_> = text.split_whitespace().collect();
+
+        let [minutes, hours, days_of_month, months, days_of_week] = fields[..] else {
+            return Err(CronError(format!(
+                "expected 5 fields but found {}",
+                fields.len()
+            )));
+        };
+
+        let mut days_of_week = Field::parse(days_of_week, "day of week", 0, 7)?;
+
+        // Sunday can be written as either 0 or 7
+        if days_of_week.contains(7) {
+            days_of_week.values |= 1;
+        }
+
+        Ok(Self {
+            minutes: Field::parse(minutes, "minute", 0, 59)?,
+            hours: Field::parse(hours, "hour", 0, 23)?,
+            days_of_month: Field::parse(days_of_month, "day of month", 1, 31)?,
+            months: Field::parse(months, "month", 1, 12)?,
+            days_of_week,
+            timezone,
+        })
+    }
+
+    /// Whether the schedule matches the minute `at` falls in.
+    pub fn matches(&self, at: DateTime<Utc>) -> bool {
+        let local = at.with_timezone(&self.timezone);
+
+        let day_of_month = self.days_of_month.contains(local.day());
+        let day_of_week = self
+            .days_of_week
+            .contains(local.weekday().num_days_from_sunday());
+
+        let day = match (self.days_of_month.any, self.days_of_week.any) {
+            (false, false) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week,
+        };
+
+        day && self.minutes.contains(local.minute())
+            && self.hours.contains(local.hour())
+            && self.months.contains(local.month())
+    }
+
+    /// Whether the schedule matches any minute after `after`, up to and including the minute of
+    /// `until`.
+    pub fn matches_between(&self, after: DateTime<Utc>, until: DateTime<Utc>) -> bool {
+        let minute = TimeDelta::minutes(1);
+        let Ok(until) = until.duration_trunc(minute) else {
+            return false;
+        };
+
+        (0..MAX_MINUTES_SEARCHED)
+            .map(|minutes_before| until - minute * minutes_before)
+            .take_while(|at| *at > after)
+            .any(|at| self.matches(at))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::poller::cron::CronSchedule;
+
+    #[test]
+    fn schedules_match_the_minutes_they_describe() {
+        let schedule = CronSchedule::parse("*/15 9-17 * * 1-5").unwrap();
+
+        // Wednesday 15 October 2025
+        assert!(schedule.matches(Utc.with_ymd_and_hms(2025, 10, 15, 9, 0, 0).unwrap()));
+        assert!(schedule.matches(Utc.with_ymd_and_hms(2025, 10, 15, 17, 45, 30).unwrap()));
+        assert!(!schedule.matches(Utc.with_ymd_and_hms(2025, 10, 15, 9, 5, 0).unwrap()));
+        assert!(!schedule.matches(Utc.with_ymd_and_hms(2025, 10, 15, 18, 0, 0).unwrap()));
+
+        // Saturday 18 October 2025
+        assert!(!schedule.matches(Utc.with_ymd_and_hms(2025, 10, 18, 9, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn restricting_both_days_matches_either() {
+        let schedule = CronSchedule::parse("0 0 1 * 7").unwrap();
+
+        // The first of the month, a Wednesday
+        assert!(schedule.matches(Utc.with_ymd_and_hms(2025, 10, 1, 0, 0, 0).unwrap()));
+        // A Sunday, written as 7
+        assert!(schedule.matches(Utc.with_ymd_and_hms(2025, 10, 5, 0, 0, 0).unwrap()));
+        assert!(!schedule.matches(Utc.with_ymd_and_hms(2025, 10, 6, 0, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn schedules_can_use_other_timezones() {
+        let schedule = CronSchedule::parse("CRON_TZ=Europe/London 0 9 * * *").unwrap();
+
+        // London is an hour ahead of UTC in summer and level with it in winter
+        assert!(schedule.matches(Utc.with_ymd_and_hms(2025, 7, 1, 8, 0, 0).unwrap()));
+        assert!(schedule.matches(Utc.with_ymd_and_hms(2025, 12, 1, 9, 0, 0).unwrap()));
+        assert!(!schedule.matches(Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn matches_are_found_between_rounds() {
+        let schedule = CronSchedule::parse("30 * * * *").unwrap();
+        let at = |minute, second| {
+            Utc.with_ymd_and_hms(2025, 10, 15, 12, minute, second)
+                .unwrap()
+        };
+
+        // Rounds drift, so a round can start just after the minute that matched
+        assert!(schedule.matches_between(at(29, 50), at(31, 5)));
+        assert!(schedule.matches_between(at(29, 0), at(30, 0)));
+        assert!(!schedule.matches_between(at(30, 0), at(30, 59)));
+        assert!(!schedule.matches_between(at(31, 0), at(32, 0)));
+    }
+
+    #[test]
+    fn invalid_schedules_are_explained() {
+        let error = |text| CronSchedule::parse(text).unwrap_err().to_string();
+
+        assert_eq!(error("* * * *"), "expected 5 fields but found 4");
+        assert_eq!(
+            error("60 * * * *"),
+            "the minute field must be between 0 and 59, not '60'"
+        );
+        assert_eq!(
+            error("* 17-9 * * *"),
+            "the hour field has a backwards range in '17-9'"
+        );
+        assert_eq!(
+            error("*/0 * * * *"),
+            "the minute field has an invalid step in '*/0'"
+        );
+        assert_eq!(
+            error("CRON_TZ=Mars/Olympus * * * * *"),
+            "'Mars/Olympus' is not a timezone"
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use color_eyre::eyre::{eyre, Result};
+use tokio::net::TcpStream;
+
+use crate::poller::traceroute::{self, Traceroute};
+
+/// The most resolved addresses a connection is attempted to, so hosts with many records do not
+/// hold up the check.
+const MAX_ADDRESSES: usize = 4;
+
+/// Gathers context when an origin cannot be reached: what its host resolves to, how connecting to
+/// each address went and, if a traceroute command is configured, the network path to it.
+#[derive(Clone, Debug)]
+pub struct FailureDiagnostics {
+    traceroute: Option<Traceroute>,
+}
+
+impl FailureDiagnostics {
+    pub fn new(traceroute: Option<Traceroute>) -> Self {
+        Self { traceroute }
+    }
+
+    /// Enables diagnostics if `FAILURE_DIAGNOSTICS` is `true`, also tracing the path to the host
+    /// with the command in `TRACEROUTE_COMMAND` if it is set.
+    pub fn from_env() -> Result<Option<Self>> {
+        let enabled = match std::env::var("FAILURE_DIAGNOSTICS") {
+            Ok(value) => value
+                .parse()
+                .map_err(|_| eyre!("FAILURE_DIAGNOSTICS must be true or false, not '{value}'"))?,
+            Err(_) => false,
+        };
+
+        if !enabled {
+            return Ok(None);
+        }
+
+        Ok(Some(Self::new(Traceroute::from_env()?)))
+    }
+
+    /// Describes how reaching the host of `uri` went, or `None` for checks without a single host
+    /// to reach.
+    pub async fn diagnose(&self, uri: &str, timeout: Duration) -> Option<String> {
+        let host = traceroute::host(uri)?;
+        let port = port(uri);
+
+        let mut output = String::new();
+
+        let start = Instant::now();
+        let resolved = tokio::time::timeout(
+            timeout,
+            tokio::net::lookup_host((host.as_str(), port.unwrap_or(0))),
+        )
+        .await;
+        let resolved_in = start.elapsed();
+
+        let addresses: Vec<SocketAddr> = match resolved {
+            Ok(Ok(addresses)) => addresses.collect(),
+            Ok(Err(e)) => {
+                let _ = writeln!(
+                    output,
+                    "DNS: {host} failed to resolve after {}ms: {e}",
+                    resolved_in.as_millis()
+                );
+                Vec::new()
+            }
+            Err(_) => {
+                let _ = writeln!(
+                    output,
+                    "DNS: {host} did not resolve within {}ms",
+                    timeout.as_millis()
+                );
+                Vec::new()
+            }
+        };
+
+        if !addresses.is_empty() {
+            let ips: Vec<_> = addresses
+                .iter()
+                .map(|address| address.ip().to_string())
+                .collect();
+
+            let _ = writeln!(
+                output,
+                "DNS: {host} resolved in {}ms to {}",
+                resolved_in.as_millis(),
+                ips.join(", ")
+            );
+        }
+
+        // Checks without a port, such as ICMP pings, do not connect
+        if port.is_some() {
+            for address in addresses.iter().take(MAX_ADDRESSES) {
+                let _ = writeln!(
+                    output,
+                    "TCP {address}: {}",
+                    connect(*address, timeout).await
+                );
+            }
+        }
+
+        if let Some(traceroute) = &self.traceroute {
+            match traceroute.capture(&host).await {
+                Ok(path) => {
+                    let _ = write!(output, "Traceroute:\n{path}");
+                }
+                Err(e) => {
+                    let _ = writeln!(output, "Traceroute: {e}");
+                }
+            }
+        }
+
+        Some(output.trim_end().to_owned())
+    }
+}
+
+/// The port checks of `uri` connect to, or `None` if they do not use one.
+fn port(uri: &str) -> Option<u16> {
+    let url = reqwest::Url::parse(uri).ok()?;
+
+    if let Some(port) = url.port_or_known_default() {
+        return Some(port);
+    }
+
+    match url.scheme() {
+        "grpc" => Some(80),
+        "grpcs" => Some(443),
+        "smtp" => Some(25),
+        "smtps" => Some(465),
+        "sftp" => Some(22),
+        "mqtt" => Some(1883),
+        "mqtts" => Some(8883),
+        "redis" | "rediss" => Some(6379),
+        "memcached" => Some(11211),
+        "postgres" | "postgresql" => Some(5432),
+        "mysql" | "mariadb" => Some(3306),
+        "redirect" => Some(443),
+        "download+http" => Some(80),
+        "download+https" => Some(443),
+        _ => None,
+    }
+}
+
+/// Describes how connecting to an address went.
+async fn connect(address: SocketAddr, timeout: Duration) -> String {
+    let start = Instant::now();
+
+    match tokio::time::timeout(timeout, TcpStream::connect(address)).await {
+        Ok(Ok(_)) => format!("connected in {}ms", start.elapsed().as_millis()),
+        Ok(Err(e)) => format!("failed after {}ms: {e}", start.elapsed().as_millis()),
+        Err(_) => format!("timed out after {}ms", timeout.as_millis()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use color_eyre::eyre::Result;
+    use tokio::net::TcpListener;
+
+    use crate::poller::diagnosis::FailureDiagnostics;
+
+    #[test]
+    fn ports_are_taken_from_each_kind_of_origin() {
+        assert_eq!(super::port("https://example.com/health"), Some(443));
+        assert_eq!(super::port("http://example.com:8080"), Some(8080));
+        assert_eq!(super::port("tcp://db.internal:5432"), Some(5432));
+        assert_eq!(super::port("smtps://mail.example.com"), Some(465));
+        assert_eq!(super::port("ftp://files.example.com"), Some(21));
+        assert_eq!(super::port("sftp://files.example.com"), Some(22));
+        assert_eq!(super::port("mqtts://broker.example.com"), Some(8883));
+        assert_eq!(super::port("memcached://sessions.internal"), Some(11211));
+        assert_eq!(super::port("mysql://uptime@db.internal/app"), Some(3306));
+        assert_eq!(
+            super::port("download+https://mirror.example.com/ubuntu.iso"),
+            Some(443)
+        );
+        assert_eq!(super::port("icmp://router.internal"), None);
+    }
+
+    #[tokio::test]
+    async fn connections_are_timed_for_each_address() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let open = listener.local_addr()?;
+
+        // Binding and dropping a listener leaves a port that refuses connections
+        let closed = TcpListener::bind("127.0.0.1:0").await?.local_addr()?;
+
+        let diagnostics = FailureDiagnostics::new(None);
+        let timeout = Duration::from_secs(1);
+
+        let output = diagnostics
+            .diagnose(&format!("tcp://{open}"), timeout)
+            .await
+            .unwrap();
+
+        assert!(output.starts_with("DNS: 127.0.0.1 resolved in"));
+        assert!(output.contains(&format!("TCP {open}: connected in")));
+
+        let output = diagnostics
+            .diagnose(&format!("tcp://{closed}"), timeout)
+            .await
+            .unwrap();
+
+        assert!(output.contains(&format!("TCP {closed}: failed after")));
+
+        assert_eq!(
+            diagnostics.diagnose("dns://example.com", timeout).await,
+            None
+        );
+
+        Ok(())
+    }
+}
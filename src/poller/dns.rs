@@ -0,0 +1,647 @@
+use std::collections::BTreeSet;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use reqwest::Url;
+use tokio::net::UdpSocket;
+
+use crate::persistence::Origin;
+use crate::poller::check::{Check, CheckContext, CheckOutcome};
+use crate::poller::FailureReason;
+
+/// The resolver used when neither the origin nor `/etc/resolv.conf` names one.
+const FALLBACK_NAMESERVER: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)), 53);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RecordType {
+    A,
+    Aaaa,
+    Cname,
+    Mx,
+    Txt,
+}
+
+impl RecordType {
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_uppercase().as_str() {
+            "A" => Some(Self::A),
+            "AAAA" => Some(Self::Aaaa),
+            "CNAME" => Some(Self::Cname),
+            "MX" => Some(Self::Mx),
+            "TXT" => Some(Self::Txt),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::A => "A",
+            Self::Aaaa => "AAAA",
+            Self::Cname => "CNAME",
+            Self::Mx => "MX",
+            Self::Txt => "TXT",
+        }
+    }
+
+    fn code(&self) -> u16 {
+        match self {
+            Self::A => 1,
+            Self::Cname => 5,
+            Self::Mx => 15,
+            Self::Txt => 16,
+            Self::Aaaa => 28,
+        }
+    }
+}
+
+/// A DNS lookup written as `dns://name?type=MX&expect=mail.example.com`.
+///
+/// The record type defaults to `A`. Each `expect` parameter is one value the answer must contain,
+/// and if any are given the answer must contain exactly those values. A `server` parameter
+/// overrides the nameserver from `/etc/resolv.conf`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct DnsCheck {
+    name: String,
+    record_type: RecordType,
+    expected: BTreeSet<String>,
+    server: Option<SocketAddr>,
+}
+
+impl DnsCheck {
+    pub fn parse(uri: &str) -> Option<Self> {
+        let url = Url::parse(uri).ok()?;
+        let name = url.host_str()?.trim_end_matches('.').to_ascii_lowercase();
+
+        let mut record_type = RecordType::A;
+        let mut values = Vec::new();
+        let mut server = None;
+
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "type" => record_type = RecordType::parse(&value)?,
+                "expect" => values.push(value.into_owned()),
+                "server" => server = Some(parse_server(&value)?),
+                _ => return None,
+            }
+        }
+
+        let expected = values
+            .into_iter()
+            .map(|value| normalise(record_type, &value))
+            .collect();
+
+        Some(Self {
+            name,
+            record_type,
+            expected,
+            server,
+        })
+    }
+}
+
+fn parse_server(value: &str) -> Option<SocketAddr> {
+    value.parse().ok().or_else(|| {
+        value
+            .parse::<IpAddr>()
+            .ok()
+            .map(|ip| SocketAddr::new(ip, 53))
+    })
+}
+
+/// Puts values into a comparable form, as names are case insensitive but TXT records are not.
+fn normalise(record_type: RecordType, value: &str) -> String {
+    match record_type {
+        RecordType::Txt => value.to_owned(),
+        _ => value.trim_end_matches('.').to_ascii_lowercase(),
+    }
+}
+
+fn system_nameserver() -> Option<SocketAddr> {
+    let config = std::fs::read_to_string("/etc/resolv.conf").ok()?;
+
+    config
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("nameserver"))
+        .find_map(|address| address.trim().parse().ok())
+        .map(|ip| SocketAddr::new(ip, 53))
+}
+
+/// Why a DNS check failed, along with the resolver and answers involved so intermittent problems
+/// can be diagnosed afterwards.
+#[derive(Debug, PartialEq, Eq)]
+pub struct DnsFailure {
+    pub reason: FailureReason,
+    pub detail: String,
+}
+
+/// A record from the answer section of a response.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Answer {
+    value: String,
+    ttl: u32,
+}
+
+fn response_code_name(code: u8) -> String {
+    match code {
+        1 => String::from("FORMERR"),
+        2 => String::from("SERVFAIL"),
+        3 => String::from("NXDOMAIN"),
+        4 => String::from("NOTIMP"),
+        5 => String::from("REFUSED"),
+        code => format!("response code {code}"),
+    }
+}
+
+fn format_answers(answers: &[Answer]) -> String {
+    if answers.is_empty() {
+        return String::from("no records");
+    }
+
+    answers
+        .iter()
+        .map(|answer| format!("{} (ttl {}s)", answer.value, answer.ttl))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Resolves the records for a check and compares them against the expected values.
+pub async fn resolve(check: &DnsCheck, timeout: Duration) -> Result<(), DnsFailure> {
+    let server = check
+        .server
+        .or_else(system_nameserver)
+        .unwrap_or(FALLBACK_NAMESERVER);
+
+    let question = format!("{} {}", check.name, check.record_type.name());
+    let failure = |reason, detail: String| DnsFailure {
+        reason,
+        detail: format!("resolver {server} {detail}"),
+    };
+
+    let id = rand::random();
+    let query = encode_query(id, &check.name, check.record_type).map_err(|reason| {
+        failure(
+            reason,
+            format!("could not be asked for {question} as the name is invalid"),
+        )
+    })?;
+
+    let response = match tokio::time::timeout(timeout, exchange(server, &query)).await {
+        Ok(Ok(response)) => response,
+        Ok(Err(reason)) => return Err(failure(reason, String::from("could not be reached"))),
+        Err(_) => {
+            return Err(failure(
+                FailureReason::RequestTimeout,
+                format!("did not answer {question} within {}ms", timeout.as_millis()),
+            ))
+        }
+    };
+
+    let answers = parse_response(&response, id, check.record_type).map_err(|reason| {
+        let detail = match response.get(3).map(|flags| flags & 0x0f) {
+            Some(code) if code != 0 => {
+                format!("returned {} for {question}", response_code_name(code))
+            }
+            _ => format!("sent an unreadable response for {question}"),
+        };
+
+        failure(reason, detail)
+    })?;
+
+    if check.expected.is_empty() {
+        return if answers.is_empty() {
+            Err(failure(
+                FailureReason::ConnectionFailure,
+                format!("returned no records for {question}"),
+            ))
+        } else {
+            Ok(())
+        };
+    }
+
+    let values: BTreeSet<_> = answers
+        .iter()
+        .map(|answer| normalise(check.record_type, &answer.value))
+        .collect();
+
+    if values != check.expected {
+        tracing::warn!(name = %check.name, ?values, expected = ?check.expected, "DNS answers did not match");
+
+        let expected: Vec<_> = check.expected.iter().map(String::as_str).collect();
+
+        return Err(failure(
+            FailureReason::DnsMismatch,
+            format!(
+                "answered {question} with {} but expected {}",
+                format_answers(&answers),
+                expected.join(", ")
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+async fn exchange(server: SocketAddr, query: &[u8]) -> Result<Vec<u8>, FailureReason> {
+    let local = match server {
+        SocketAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+        SocketAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+    };
+
+    let socket = UdpSocket::bind(local)
+        .await
+        .map_err(|_| FailureReason::Unknown)?;
+
+    socket
+        .connect(server)
+        .await
+        .map_err(|_| FailureReason::ConnectionFailure)?;
+
+    socket
+        .send(query)
+        .await
+        .map_err(|_| FailureReason::ConnectionFailure)?;
+
+    let mut buffer = vec![0; 4096];
+    let length = socket
+        .recv(&mut buffer)
+        .await
+        .map_err(|_| FailureReason::ConnectionFailure)?;
+
+    buffer.truncate(length);
+
+    Ok(buffer)
+}
+
+fn encode_query(id: u16, name: &str, record_type: RecordType) -> Result<Vec<u8>, FailureReason> {
+    // Ask for recursion with a single question and no other records
+    let mut query = Vec::with_capacity(name.len() + 18);
+    query.extend_from_slice(&id.to_be_bytes());
+    query.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(FailureReason::BadRequest);
+        }
+
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+
+    query.push(0);
+    query.extend_from_slice(&record_type.code().to_be_bytes());
+    query.extend_from_slice(&1_u16.to_be_bytes());
+
+    Ok(query)
+}
+
+/// Reads DNS messages, failing with `InvalidBody` if anything is out of bounds.
+struct Reader<'a> {
+    message: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], FailureReason> {
+        let bytes = self
+            .message
+            .get(self.position..self.position + length)
+            .ok_or(FailureReason::InvalidBody)?;
+
+        self.position += length;
+
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, FailureReason> {
+        let bytes = self.bytes(2)?;
+
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Reads a possibly compressed name, leaving the reader after it.
+    fn name(&mut self) -> Result<String, FailureReason> {
+        let mut labels = Vec::new();
+        let mut position = self.position;
+        let mut resume_at = None;
+
+        // Bound the number of jumps so a malicious pointer loop cannot hang the poller
+        for _ in 0..128 {
+            let length = *self
+                .message
+                .get(position)
+                .ok_or(FailureReason::InvalidBody)?;
+
+            match length {
+                0 => {
+                    self.position = resume_at.unwrap_or(position + 1);
+                    return Ok(labels.join("."));
+                }
+                length if length & 0xc0 == 0xc0 => {
+                    let low = *self
+                        .message
+                        .get(position + 1)
+                        .ok_or(FailureReason::InvalidBody)?;
+
+                    resume_at.get_or_insert(position + 2);
+                    position = usize::from(u16::from_be_bytes([length & 0x3f, low]));
+                }
+                length => {
+                    let start = position + 1;
+                    let end = start + usize::from(length);
+                    let label = self
+                        .message
+                        .get(start..end)
+                        .ok_or(FailureReason::InvalidBody)?;
+
+                    labels.push(String::from_utf8_lossy(label).into_owned());
+                    position = end;
+                }
+            }
+        }
+
+        Err(FailureReason::InvalidBody)
+    }
+}
+
+/// Returns the answers of the requested type, ignoring any others such as the CNAME records that
+/// lead to an A record.
+fn parse_response(
+    message: &[u8],
+    id: u16,
+    record_type: RecordType,
+) -> Result<Vec<Answer>, FailureReason> {
+    let mut reader = Reader {
+        message,
+        position: 0,
+    };
+
+    if reader.u16()? != id {
+        return Err(FailureReason::InvalidBody);
+    }
+
+    let flags = reader.u16()?;
+
+    if flags & 0x8000 == 0 {
+        return Err(FailureReason::InvalidBody);
+    }
+
+    // NXDOMAIN and server failures mean the name could not be resolved at all
+    if flags & 0x000f != 0 {
+        return Err(FailureReason::ConnectionFailure);
+    }
+
+    let questions = reader.u16()?;
+    let answers = reader.u16()?;
+    reader.bytes(4)?;
+
+    for _ in 0..questions {
+        reader.name()?;
+        reader.bytes(4)?;
+    }
+
+    let mut values = Vec::new();
+
+    for _ in 0..answers {
+        reader.name()?;
+        let kind = reader.u16()?;
+        // Skip the class, which is always IN
+        reader.u16()?;
+        let ttl = reader.bytes(4)?;
+        let ttl = u32::from_be_bytes([ttl[0], ttl[1], ttl[2], ttl[3]]);
+        let length = usize::from(reader.u16()?);
+        let end = reader.position + length;
+
+        if kind != record_type.code() {
+            reader.bytes(length)?;
+            continue;
+        }
+
+        let value = match record_type {
+            RecordType::A => {
+                let bytes: [u8; 4] = reader
+                    .bytes(length)?
+                    .try_into()
+                    .map_err(|_| FailureReason::InvalidBody)?;
+
+                Ipv4Addr::from(bytes).to_string()
+            }
+            RecordType::Aaaa => {
+                let bytes: [u8; 16] = reader
+                    .bytes(length)?
+                    .try_into()
+                    .map_err(|_| FailureReason::InvalidBody)?;
+
+                Ipv6Addr::from(bytes).to_string()
+            }
+            RecordType::Cname => reader.name()?,
+            RecordType::Mx => {
+                // Only the exchange matters, not its preference
+                reader.u16()?;
+                reader.name()?
+            }
+            RecordType::Txt => {
+                let mut text = Vec::new();
+
+                while reader.position < end {
+                    let length = usize::from(reader.bytes(1)?[0]);
+                    text.extend_from_slice(reader.bytes(length)?);
+                }
+
+                String::from_utf8_lossy(&text).into_owned()
+            }
+        };
+
+        reader.position = end;
+        values.push(Answer { value, ttl });
+    }
+
+    Ok(values)
+}
+
+/// Resolves the records of origins written as `dns://name?type=MX`.
+pub struct DnsChecker;
+
+impl Check for DnsChecker {
+    fn execute<'a>(
+        &'a self,
+        origin: &'a Origin,
+        context: &'a CheckContext<'a>,
+    ) -> BoxFuture<'a, CheckOutcome> {
+        Box::pin(async move {
+            let Some(check) = DnsCheck::parse(&origin.uri) else {
+                return CheckOutcome::new(Err(FailureReason::BadRequest));
+            };
+
+            match resolve(&check, context.timeout).await {
+                Ok(()) => CheckOutcome::new(Ok((None, context.elapsed_millis()))),
+                Err(failure) => CheckOutcome::failed(failure.reason, failure.detail),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::poller::dns::{Answer, DnsCheck, RecordType};
+    use crate::poller::FailureReason;
+
+    fn values(answers: Vec<Answer>) -> Vec<String> {
+        answers.into_iter().map(|answer| answer.value).collect()
+    }
+
+    /// Builds a response to `query` with the given answers, each pointing back at the question.
+    pub fn response(query: &[u8], answers: &[(RecordType, Vec<u8>)]) -> Vec<u8> {
+        let mut message = query.to_vec();
+
+        message[2] |= 0x80;
+        message[6..8].copy_from_slice(&(answers.len() as u16).to_be_bytes());
+
+        for (record_type, data) in answers {
+            message.extend_from_slice(&[0xc0, 12]);
+            message.extend_from_slice(&record_type.code().to_be_bytes());
+            message.extend_from_slice(&[0, 1, 0, 0, 0, 60]);
+            message.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            message.extend_from_slice(data);
+        }
+
+        message
+    }
+
+    #[test]
+    fn checks_are_parsed_from_uris() {
+        let check = DnsCheck::parse(
+            "dns://Example.com?type=mx&expect=Mail.example.com.&expect=alt.example.com&server=9.9.9.9",
+        )
+        .unwrap();
+
+        assert_eq!(check.name, "example.com");
+        assert_eq!(check.record_type, RecordType::Mx);
+        assert_eq!(
+            check.expected,
+            BTreeSet::from([
+                String::from("alt.example.com"),
+                String::from("mail.example.com")
+            ])
+        );
+        assert_eq!(check.server, Some("9.9.9.9:53".parse().unwrap()));
+
+        assert_eq!(
+            DnsCheck::parse("dns://example.com").unwrap().record_type,
+            RecordType::A
+        );
+        assert!(DnsCheck::parse("dns://example.com?type=SRV").is_none());
+        assert!(DnsCheck::parse("dns://example.com?colour=blue").is_none());
+    }
+
+    #[test]
+    fn queries_encode_names_as_labels() {
+        let query = super::encode_query(0xabcd, "example.com", RecordType::A).unwrap();
+
+        assert_eq!(&query[..2], &[0xab, 0xcd]);
+        assert_eq!(&query[12..], b"\x07example\x03com\x00\x00\x01\x00\x01");
+
+        assert_eq!(
+            super::encode_query(1, "bad..name", RecordType::A),
+            Err(FailureReason::BadRequest)
+        );
+    }
+
+    #[test]
+    fn answers_of_the_requested_type_are_returned() {
+        let query = super::encode_query(7, "www.example.com", RecordType::A).unwrap();
+
+        // A CNAME pointing back at the question name, followed by the address it resolves to
+        let message = response(
+            &query,
+            &[
+                (RecordType::Cname, vec![0xc0, 12]),
+                (RecordType::A, vec![93, 184, 216, 34]),
+            ],
+        );
+
+        assert_eq!(
+            super::parse_response(&message, 7, RecordType::A).map(values),
+            Ok(vec![String::from("93.184.216.34")])
+        );
+        assert_eq!(
+            super::parse_response(&message, 7, RecordType::Cname).map(values),
+            Ok(vec![String::from("www.example.com")])
+        );
+    }
+
+    #[test]
+    fn txt_and_mx_records_are_decoded() {
+        let query = super::encode_query(7, "example.com", RecordType::Txt).unwrap();
+        let message = response(
+            &query,
+            &[(RecordType::Txt, b"\x07v=spf1 \x04-all".to_vec())],
+        );
+
+        assert_eq!(
+            super::parse_response(&message, 7, RecordType::Txt).map(values),
+            Ok(vec![String::from("v=spf1 -all")])
+        );
+
+        let query = super::encode_query(7, "example.com", RecordType::Mx).unwrap();
+        let message = response(
+            &query,
+            &[(RecordType::Mx, b"\x00\x0a\x04mail\xc0\x0c".to_vec())],
+        );
+
+        assert_eq!(
+            super::parse_response(&message, 7, RecordType::Mx).map(values),
+            Ok(vec![String::from("mail.example.com")])
+        );
+    }
+
+    #[test]
+    fn mismatched_ids_and_errors_are_rejected() {
+        let query = super::encode_query(7, "example.com", RecordType::A).unwrap();
+        let mut message = response(&query, &[]);
+
+        assert_eq!(
+            super::parse_response(&message, 8, RecordType::A),
+            Err(FailureReason::InvalidBody)
+        );
+
+        // NXDOMAIN
+        message[3] |= 0x03;
+
+        assert_eq!(
+            super::parse_response(&message, 7, RecordType::A),
+            Err(FailureReason::ConnectionFailure)
+        );
+    }
+
+    #[test]
+    fn ttls_are_kept_with_answers() {
+        let query = super::encode_query(7, "example.com", RecordType::A).unwrap();
+        let message = response(&query, &[(RecordType::A, vec![192, 0, 2, 1])]);
+
+        assert_eq!(
+            super::parse_response(&message, 7, RecordType::A),
+            Ok(vec![Answer {
+                value: String::from("192.0.2.1"),
+                ttl: 60
+            }])
+        );
+    }
+
+    #[test]
+    fn pointer_loops_are_rejected() {
+        let query = super::encode_query(7, "example.com", RecordType::A).unwrap();
+        let mut message = response(&query, &[(RecordType::A, vec![1, 2, 3, 4])]);
+
+        // Point the answer's name at itself
+        let answer = query.len();
+        message[answer + 1] = answer as u8;
+
+        assert_eq!(
+            super::parse_response(&message, 7, RecordType::A),
+            Err(FailureReason::InvalidBody)
+        );
+    }
+}
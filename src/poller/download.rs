@@ -0,0 +1,267 @@
+use std::time::Instant;
+
+use futures_util::future::BoxFuture;
+use reqwest::Url;
+
+use crate::persistence::{ConnectionMode, Origin};
+use crate::poller::check::{Check, CheckContext, CheckOutcome};
+use crate::poller::http;
+use crate::poller::timing::Timings;
+use crate::poller::FailureReason;
+
+/// How much of an asset is downloaded if the origin does not set a limit.
+const DEFAULT_LIMIT_MB: u64 = 100;
+
+/// Bytes in a megabyte, in the binary sense sizes are shown in elsewhere.
+const BYTES_PER_MB: u64 = 1024 * 1024;
+
+/// A large asset written as `download+https://host/path`, such as a release on a download mirror.
+///
+/// The fragment can set how many megabytes to download at most with `limit_mb`, and the slowest
+/// acceptable throughput in megabytes per second with `min_mb_per_second`, as in
+/// `download+https://mirror.example.com/ubuntu.iso#limit_mb=50&min_mb_per_second=10`.
+#[derive(Debug, PartialEq)]
+pub struct DownloadCheck {
+    url: Url,
+    limit_bytes: u64,
+    min_mb_per_second: Option<f64>,
+}
+
+impl DownloadCheck {
+    pub fn parse(uri: &str) -> Option<Self> {
+        let mut url = Url::parse(uri.strip_prefix("download+")?).ok()?;
+
+        if !matches!(url.scheme(), "http" | "https") {
+            return None;
+        }
+
+        let mut limit_mb = DEFAULT_LIMIT_MB;
+        let mut min_mb_per_second = None;
+
+        // Fragments are never sent to the server, so they are free to configure the check
+        if let Some(fragment) = url.fragment() {
+            for pair in fragment.split('&') {
+                match pair.split_once('=')? {
+                    ("limit_mb", value) => limit_mb = value.parse().ok().filter(|mb| *mb > 0)?,
+                    ("min_mb_per_second", value) => {
+                        min_mb_per_second = Some(value.parse().ok().filter(|mb: &f64| *mb > 0.0)?)
+                    }
+                    _ => return None,
+                }
+            }
+        }
+
+        url.set_fragment(None);
+
+        Some(Self {
+            url,
+            limit_bytes: limit_mb * BYTES_PER_MB,
+            min_mb_per_second,
+        })
+    }
+}
+
+/// How a download went.
+#[derive(Debug, PartialEq)]
+struct Downloaded {
+    status: u16,
+    bytes: u64,
+    first_byte_millis: i64,
+    /// How long the body took to arrive after the headers, which throughput is measured over.
+    transfer_millis: i64,
+}
+
+impl Downloaded {
+    fn bytes_per_second(&self) -> i64 {
+        // Small assets can arrive within a millisecond, which is not the same as instantly
+        (self.bytes * 1000 / self.transfer_millis.max(1) as u64) as i64
+    }
+}
+
+/// Formats a throughput in megabytes per second, such as `12.5 MB/s`.
+pub fn format_throughput(bytes_per_second: i64) -> String {
+    format!("{:.1} MB/s", bytes_per_second as f64 / BYTES_PER_MB as f64)
+}
+
+/// Downloads an asset until its limit is reached or the origin's timeout runs out, measuring the
+/// throughput of however much arrived.
+pub struct DownloadChecker;
+
+impl Check for DownloadChecker {
+    fn execute<'a>(
+        &'a self,
+        origin: &'a Origin,
+        context: &'a CheckContext<'a>,
+    ) -> BoxFuture<'a, CheckOutcome> {
+        Box::pin(async move {
+            let Some(check) = DownloadCheck::parse(&origin.uri) else {
+                return CheckOutcome::new(Err(FailureReason::BadRequest));
+            };
+
+            let prepared = context
+                .http_clients
+                .prepare(
+                    origin,
+                    check.url.as_str(),
+                    context.family,
+                    origin.connection_mode == ConnectionMode::Fresh,
+                    context.credentials,
+                )
+                .await;
+
+            let (client, headers) = match prepared {
+                Ok(prepared) => prepared,
+                Err(failure) => {
+                    return CheckOutcome {
+                        failure_detail: failure.detail,
+                        ..CheckOutcome::new(Err(failure.reason))
+                    }
+                }
+            };
+
+            let start = Instant::now();
+            let deadline = tokio::time::Instant::from_std(start + context.timeout);
+
+            let response = match tokio::time::timeout_at(
+                deadline,
+                client.get(check.url.clone()).headers(headers).send(),
+            )
+            .await
+            {
+                Ok(Ok(response)) => response,
+                Ok(Err(e)) => return CheckOutcome::new(Err(e.into())),
+                Err(_) => return CheckOutcome::new(Err(FailureReason::RequestTimeout)),
+            };
+
+            let first_byte = Instant::now();
+            let status = response.status();
+            let remote_address = response.remote_addr().map(|addr| addr.ip());
+            let http_version = http::version_name(response.version());
+
+            if !status.is_success() {
+                return CheckOutcome {
+                    remote_address,
+                    ..CheckOutcome::new(Err(FailureReason::UnexpectedStatus))
+                };
+            }
+
+            let downloaded = match download(response, &check, deadline).await {
+                Ok(bytes) => Downloaded {
+                    status: status.as_u16(),
+                    bytes,
+                    first_byte_millis: (first_byte - start).as_millis() as i64,
+                    transfer_millis: first_byte.elapsed().as_millis() as i64,
+                },
+                Err(reason) => {
+                    return CheckOutcome {
+                        remote_address,
+                        ..CheckOutcome::new(Err(reason))
+                    }
+                }
+            };
+
+            let bytes_per_second = downloaded.bytes_per_second();
+
+            if let Some(detail) = too_slow(&check, bytes_per_second) {
+                return CheckOutcome {
+                    remote_address,
+                    ..CheckOutcome::failed(FailureReason::SlowDownload, detail)
+                };
+            }
+
+            CheckOutcome {
+                remote_address,
+                body_bytes: Some(downloaded.bytes as i64),
+                http_version: Some(http_version),
+                timings: Some(Timings {
+                    first_byte_millis: Some(downloaded.first_byte_millis),
+                    ..Timings::default()
+                }),
+                throughput_bytes_per_second: Some(bytes_per_second),
+                ..CheckOutcome::new(Ok((Some(downloaded.status), context.elapsed_millis())))
+            }
+        })
+    }
+}
+
+/// Reads the body until the limit of the check, the end of the body or the deadline, returning
+/// how many bytes arrived.
+async fn download(
+    mut response: reqwest::Response,
+    check: &DownloadCheck,
+    deadline: tokio::time::Instant,
+) -> Result<u64, FailureReason> {
+    let mut bytes = 0;
+
+    while bytes < check.limit_bytes {
+        // Running out of time is not a failure, as what arrived still says how fast it came
+        let Ok(chunk) = tokio::time::timeout_at(deadline, response.chunk()).await else {
+            break;
+        };
+
+        let Some(chunk) = chunk? else {
+            break;
+        };
+
+        bytes += chunk.len() as u64;
+    }
+
+    if bytes == 0 {
+        return Err(FailureReason::InvalidBody);
+    }
+
+    Ok(bytes)
+}
+
+/// Explains why a throughput is too slow for the check, if it is.
+fn too_slow(check: &DownloadCheck, bytes_per_second: i64) -> Option<String> {
+    let min_mb_per_second = check.min_mb_per_second?;
+    let mb_per_second = bytes_per_second as f64 / BYTES_PER_MB as f64;
+
+    (mb_per_second < min_mb_per_second).then(|| {
+        format!(
+            "downloaded at {}, below the minimum of {min_mb_per_second} MB/s",
+            format_throughput(bytes_per_second)
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::poller::download::DownloadCheck;
+
+    #[test]
+    fn checks_are_parsed_from_uris() {
+        let check = DownloadCheck::parse(
+            "download+https://mirror.example.com/ubuntu.iso#limit_mb=50&min_mb_per_second=2.5",
+        )
+        .unwrap();
+
+        assert_eq!(check.url.as_str(), "https://mirror.example.com/ubuntu.iso");
+        assert_eq!(check.limit_bytes, 50 * 1024 * 1024);
+        assert_eq!(check.min_mb_per_second, Some(2.5));
+
+        let check = DownloadCheck::parse("download+http://localhost:8080/asset.bin").unwrap();
+
+        assert_eq!(check.limit_bytes, 100 * 1024 * 1024);
+        assert_eq!(check.min_mb_per_second, None);
+
+        assert!(DownloadCheck::parse("https://example.com/asset.bin").is_none());
+        assert!(DownloadCheck::parse("download+ftp://example.com/asset.bin").is_none());
+        assert!(DownloadCheck::parse("download+https://example.com/#limit_mb=0").is_none());
+        assert!(DownloadCheck::parse("download+https://example.com/#speed=fast").is_none());
+    }
+
+    #[test]
+    fn slow_downloads_are_explained() {
+        let check =
+            DownloadCheck::parse("download+https://cdn.example.com/video.mp4#min_mb_per_second=10")
+                .unwrap();
+
+        assert_eq!(super::too_slow(&check, 12 * 1024 * 1024), None);
+        assert_eq!(
+            super::too_slow(&check, 5 * 1024 * 1024 / 2).as_deref(),
+            Some("downloaded at 2.5 MB/s, below the minimum of 10 MB/s")
+        );
+    }
+}
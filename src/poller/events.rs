@@ -0,0 +1,192 @@
+use color_eyre::eyre::{eyre, Result};
+use reqwest::Url;
+use serde::Serialize;
+use sqlx::types::chrono::{DateTime, Utc};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::poller::firehose::CheckResult;
+use crate::poller::kafka::KafkaConnection;
+use crate::poller::nats::NatsConnection;
+
+/// The number of events that can be waiting for delivery before new ones are dropped.
+const QUEUE_CAPACITY: usize = 1024;
+
+/// Something that happened in the poller which other systems may want to react to.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type")]
+pub enum Event {
+    /// An origin was checked, whether or not the check succeeded.
+    #[serde(rename = "check.completed")]
+    CheckCompleted(CheckResult),
+    /// An origin went from succeeding to failing, or back again.
+    #[serde(rename = "state.changed")]
+    StateChanged {
+        origin_uid: Uuid,
+        uri: String,
+        up: bool,
+        changed_at: DateTime<Utc>,
+    },
+    /// An origin failed after succeeding, or on its first check.
+    #[serde(rename = "incident.opened")]
+    IncidentOpened {
+        origin_uid: Uuid,
+        uri: String,
+        started_at: DateTime<Utc>,
+    },
+    /// An outage alert was sent for an origin.
+    #[serde(rename = "notification.sent")]
+    NotificationSent {
+        origin_uid: Uuid,
+        notification_uid: Uuid,
+        subject: String,
+        sent_at: DateTime<Utc>,
+    },
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::CheckCompleted(_) => "check.completed",
+            Self::StateChanged { .. } => "state.changed",
+            Self::IncidentOpened { .. } => "incident.opened",
+            Self::NotificationSent { .. } => "notification.sent",
+        }
+    }
+
+    fn origin_uid(&self) -> Uuid {
+        match self {
+            Self::CheckCompleted(result) => result.origin_uid,
+            Self::StateChanged { origin_uid, .. }
+            | Self::IncidentOpened { origin_uid, .. }
+            | Self::NotificationSent { origin_uid, .. } => *origin_uid,
+        }
+    }
+}
+
+/// Where events are published, with each event going to `{prefix}.{event name}`.
+pub enum Publisher {
+    Nats(NatsConnection),
+    Kafka(KafkaConnection),
+}
+
+impl Publisher {
+    /// Parses `nats://host:port/prefix` or `kafka://host:port/prefix`.
+    pub fn parse(url: &str) -> Result<(Self, String)> {
+        let url = Url::parse(url)?;
+
+        match url.scheme() {
+            "nats" => {
+                let (connection, prefix) = NatsConnection::from_url(&url)?;
+                Ok((Self::Nats(connection), prefix))
+            }
+            "kafka" => {
+                let (connection, prefix) = KafkaConnection::from_url(&url)?;
+                Ok((Self::Kafka(connection), prefix))
+            }
+            scheme => Err(eyre!("unsupported event bus scheme '{scheme}'")),
+        }
+    }
+
+    async fn publish(&mut self, topic: &str, key: &[u8], payload: &[u8]) -> Result<()> {
+        match self {
+            Self::Nats(connection) => connection.publish(topic, payload).await,
+            // Keying by origin keeps each origin's events in order if the topic is repartitioned
+            Self::Kafka(connection) => connection.produce(topic, key, payload).await,
+        }
+    }
+}
+
+/// Publishes domain events in the background, so a slow or unavailable bus never delays checks.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: mpsc::Sender<Event>,
+}
+
+impl EventBus {
+    pub fn spawn(mut publisher: Publisher, prefix: String) -> Self {
+        let (sender, mut receiver) = mpsc::channel::<Event>(QUEUE_CAPACITY);
+
+        tokio::spawn(async move {
+            while let Some(event) = receiver.recv().await {
+                let topic = format!("{prefix}.{}", event.name());
+                let origin_uid = event.origin_uid();
+
+                let payload = match serde_json::to_vec(&event) {
+                    Ok(payload) => payload,
+                    Err(e) => {
+                        tracing::warn!(%e, "failed to serialize an event");
+                        continue;
+                    }
+                };
+
+                if let Err(e) = publisher
+                    .publish(&topic, origin_uid.as_bytes(), &payload)
+                    .await
+                {
+                    tracing::warn!(%origin_uid, %topic, %e, "failed to publish an event");
+                }
+            }
+        });
+
+        Self { sender }
+    }
+
+    /// Configures the event bus from `EVENT_BUS_URL`, such as `nats://localhost:4222/uptime`.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Ok(url) = std::env::var("EVENT_BUS_URL") else {
+            return Ok(None);
+        };
+
+        let (publisher, prefix) = Publisher::parse(&url)?;
+
+        Ok(Some(Self::spawn(publisher, prefix)))
+    }
+
+    /// Queues an event for publishing, dropping it if the bus has fallen too far behind.
+    pub fn publish(&self, event: Event) {
+        if let Err(e) = self.sender.try_send(event) {
+            tracing::warn!(%e, "dropped an event from the event bus");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use color_eyre::eyre::Result;
+    use sqlx::types::chrono::Utc;
+    use uuid::Uuid;
+
+    use crate::poller::events::{Event, Publisher};
+
+    #[test]
+    fn events_are_tagged_with_their_name() -> Result<()> {
+        let event = Event::IncidentOpened {
+            origin_uid: Uuid::nil(),
+            uri: String::from("https://example.com"),
+            started_at: Utc::now(),
+        };
+
+        let value = serde_json::to_value(&event)?;
+
+        assert_eq!(value["type"], event.name());
+        assert_eq!(value["uri"], "https://example.com");
+
+        Ok(())
+    }
+
+    #[test]
+    fn publishers_are_chosen_by_scheme() -> Result<()> {
+        let (publisher, prefix) = Publisher::parse("kafka://localhost:9092/uptime")?;
+
+        assert!(matches!(publisher, Publisher::Kafka(_)));
+        assert_eq!(prefix, "uptime");
+
+        let (publisher, _) = Publisher::parse("nats://localhost/uptime")?;
+
+        assert!(matches!(publisher, Publisher::Nats(_)));
+        assert!(Publisher::parse("https://example.com/uptime").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+use std::path::PathBuf;
+use std::process::Stdio;
+
+use color_eyre::eyre::{eyre, Result};
+use futures_util::future::BoxFuture;
+use tokio::process::Command;
+
+use crate::persistence::Origin;
+use crate::poller::check::{Check, CheckContext, CheckOutcome};
+use crate::poller::FailureReason;
+
+/// The most of what a command printed that is kept with a failure, from the end where errors
+/// usually are.
+const MAX_OUTPUT_CHARS: usize = 1000;
+
+/// The only variables commands get from the environment of the poller, which otherwise holds
+/// secrets such as `CREDENTIALS_KEY` and the database credentials.
+const INHERITED_VARIABLES: &[&str] = &["PATH", "HOME", "LANG", "LC_ALL", "TZ", "TMPDIR"];
+
+/// The directory commands are run from, which keeps origins from running anything else on the
+/// machine the poller runs on.
+#[derive(Clone, Debug)]
+pub struct ExternalCommands {
+    directory: PathBuf,
+}
+
+impl ExternalCommands {
+    pub fn new<T: Into<PathBuf>>(directory: T) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    /// Reads the directory commands are run from out of `COMMAND_CHECKS_DIR`, returning `None` if
+    /// it is not set.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Ok(directory) = std::env::var("COMMAND_CHECKS_DIR") else {
+            return Ok(None);
+        };
+
+        if !std::path::Path::new(&directory).is_dir() {
+            return Err(eyre!("COMMAND_CHECKS_DIR '{directory}' is not a directory"));
+        }
+
+        Ok(Some(Self::new(directory)))
+    }
+}
+
+/// A command written as `command://name?arg=first&arg=second`, which runs the executable `name`
+/// from the commands directory with the arguments in order.
+#[derive(Debug, PartialEq, Eq)]
+pub struct CommandCheck {
+    name: String,
+    args: Vec<String>,
+}
+
+impl CommandCheck {
+    pub fn parse(uri: &str) -> Option<Self> {
+        let rest = uri.strip_prefix("command://")?;
+        let name = rest.split(['?', '#']).next()?.trim_end_matches('/');
+
+        // Only a file directly inside the directory can be named, never a path out of it
+        let valid = !name.is_empty()
+            && !name.starts_with('.')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+
+        if !valid {
+            return None;
+        }
+
+        let url = reqwest::Url::parse(uri).ok()?;
+        let mut args = Vec::new();
+
+        for (key, value) in url.query_pairs() {
+            if key != "arg" {
+                return None;
+            }
+
+            args.push(value.into_owned());
+        }
+
+        Some(Self {
+            name: name.to_owned(),
+            args,
+        })
+    }
+}
+
+/// Keeps the end of what a command printed, where errors usually are.
+fn tail(output: &str) -> &str {
+    let output = output.trim();
+    let start = output
+        .char_indices()
+        .rev()
+        .nth(MAX_OUTPUT_CHARS - 1)
+        .map_or(0, |(index, _)| index);
+
+    &output[start..]
+}
+
+/// Runs origins written as `command://name`, succeeding if the command exits with status 0
+/// within the timeout of the origin.
+#[derive(Default)]
+pub struct CommandChecker {
+    commands: Option<ExternalCommands>,
+}
+
+impl CommandChecker {
+    pub fn new(commands: ExternalCommands) -> Self {
+        Self {
+            commands: Some(commands),
+        }
+    }
+}
+
+impl Check for CommandChecker {
+    fn execute<'a>(
+        &'a self,
+        origin: &'a Origin,
+        context: &'a CheckContext<'a>,
+    ) -> BoxFuture<'a, CheckOutcome> {
+        Box::pin(async move {
+            let Some(commands) = &self.commands else {
+                return CheckOutcome::failed(
+                    FailureReason::BadRequest,
+                    String::from("command checks are disabled as COMMAND_CHECKS_DIR is not set"),
+                );
+            };
+
+            let Some(check) = CommandCheck::parse(&origin.uri) else {
+                return CheckOutcome::new(Err(FailureReason::BadRequest));
+            };
+
+            let inherited = INHERITED_VARIABLES
+                .iter()
+                .filter_map(|name| Some((name, std::env::var_os(name)?)));
+
+            let child = Command::new(commands.directory.join(&check.name))
+                .args(&check.args)
+                .env_clear()
+                .envs(inherited)
+                .env("UPTIME_ORIGIN_UID", origin.origin_uid.to_string())
+                .stdin(Stdio::null())
+                .kill_on_drop(true)
+                .output();
+
+            let output = match tokio::time::timeout(context.timeout, child).await {
+                Ok(Ok(output)) => output,
+                Ok(Err(e)) => {
+                    return CheckOutcome::failed(
+                        FailureReason::BadRequest,
+                        format!("could not run {}: {e}", check.name),
+                    )
+                }
+                Err(_) => {
+                    return CheckOutcome::failed(
+                        FailureReason::RequestTimeout,
+                        format!(
+                            "{} did not finish within {}ms",
+                            check.name,
+                            context.timeout.as_millis()
+                        ),
+                    )
+                }
+            };
+
+            if output.status.success() {
+                return CheckOutcome::new(Ok((None, context.elapsed_millis())));
+            }
+
+            let mut printed = String::from_utf8_lossy(&output.stdout).into_owned();
+            printed.push_str(&String::from_utf8_lossy(&output.stderr));
+
+            // Commands killed by a signal have no exit code
+            let exited = match output.status.code() {
+                Some(code) => format!("exited with status {code}"),
+                None => String::from("was killed by a signal"),
+            };
+
+            let detail = match tail(&printed) {
+                "" => format!("{} {exited}", check.name),
+                printed => format!("{} {exited}: {printed}", check.name),
+            };
+
+            CheckOutcome::failed(FailureReason::CommandFailed, detail)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::poller::external::CommandCheck;
+
+    #[test]
+    fn checks_are_parsed_from_uris() {
+        assert_eq!(
+            CommandCheck::parse("command://check_vpn.sh?arg=10.0.0.1&arg=--verbose"),
+            Some(CommandCheck {
+                name: String::from("check_vpn.sh"),
+                args: vec![String::from("10.0.0.1"), String::from("--verbose")],
+            })
+        );
+        assert_eq!(
+            CommandCheck::parse("command://Backups/"),
+            Some(CommandCheck {
+                name: String::from("Backups"),
+                args: Vec::new(),
+            })
+        );
+
+        assert!(CommandCheck::parse("command://").is_none());
+        assert!(CommandCheck::parse("command://../../bin/sh").is_none());
+        assert!(CommandCheck::parse("command://.hidden").is_none());
+        assert!(CommandCheck::parse("command://check?flag=1").is_none());
+    }
+
+    #[test]
+    fn only_the_end_of_long_output_is_kept() {
+        let output = format!("{}error: disk full\n", "progress\n".repeat(500));
+        let kept = super::tail(&output);
+
+        assert_eq!(kept.chars().count(), super::MAX_OUTPUT_CHARS);
+        assert!(kept.ends_with("error: disk full"));
+        assert_eq!(super::tail("  ok \n"), "ok");
+    }
+}
@@ -0,0 +1,112 @@
+use crate::poller::NotifierHealth;
+
+/// A Slack-compatible webhook that is told when alerts stop sending to the topic, so broken
+/// alerting is noticed before the next outage rather than during it.
+#[derive(Clone, Debug)]
+pub struct FallbackChannel {
+    webhook_url: String,
+}
+
+/// An alert about the notifier itself, rather than any origin.
+#[derive(Debug, PartialEq, Eq)]
+pub struct MetaAlert {
+    pub subject: &'static str,
+    pub message: String,
+}
+
+impl FallbackChannel {
+    pub fn new<T: Into<String>>(webhook_url: T) -> Self {
+        Self {
+            webhook_url: webhook_url.into(),
+        }
+    }
+
+    /// Configures the fallback channel from `FALLBACK_WEBHOOK_URL`, if it is set.
+    pub fn from_env() -> Option<Self> {
+        std::env::var("FALLBACK_WEBHOOK_URL").ok().map(Self::new)
+    }
+
+    pub fn webhook_url(&self) -> &str {
+        &self.webhook_url
+    }
+}
+
+/// The alert to send through the fallback channel after sending an alert to `topic` changed the
+/// notifier's health from `previous` to `current`, if any.
+///
+/// This is only sent when the notifier starts or stops failing, so a long streak of failures
+/// produces one alert rather than one for every missed outage.
+pub fn meta_alert(
+    topic: &str,
+    previous: &NotifierHealth,
+    current: &NotifierHealth,
+) -> Option<MetaAlert> {
+    match (previous.is_failing(), current.is_failing()) {
+        (false, true) => Some(MetaAlert {
+            subject: "Alerts are failing to send",
+            message: format!(
+                "The last {} alerts failed to send to {topic}, so outages may be going unnoticed. The last error was: {}",
+                current.consecutive_failures,
+                current.last_error.as_deref().unwrap_or("unknown")
+            ),
+        }),
+        (true, false) => Some(MetaAlert {
+            subject: "Alerts are sending again",
+            message: format!(
+                "Alerts are sending to {topic} again after {} failed in a row",
+                previous.consecutive_failures
+            ),
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::poller::fallback::MetaAlert;
+    use crate::poller::NotifierHealth;
+
+    #[test]
+    fn meta_alerts_are_only_sent_when_failing_starts_or_stops() {
+        let at = Utc.with_ymd_and_hms(2024, 1, 3, 12, 0, 0).unwrap();
+        let mut health = NotifierHealth::default();
+
+        for _ in 0..2 {
+            let previous = health.clone();
+            health.record_failure(at, String::from("access denied"));
+
+            assert_eq!(super::meta_alert("alerts", &previous, &health), None);
+        }
+
+        let previous = health.clone();
+        health.record_failure(at, String::from("access denied"));
+
+        assert_eq!(
+            super::meta_alert("alerts", &previous, &health),
+            Some(MetaAlert {
+                subject: "Alerts are failing to send",
+                message: String::from(
+                    "The last 3 alerts failed to send to alerts, so outages may be going unnoticed. The last error was: access denied"
+                ),
+            })
+        );
+
+        let previous = health.clone();
+        health.record_failure(at, String::from("access denied"));
+
+        assert_eq!(super::meta_alert("alerts", &previous, &health), None);
+
+        let previous = health.clone();
+        health.record_sent(at);
+
+        assert_eq!(
+            super::meta_alert("alerts", &previous, &health),
+            Some(MetaAlert {
+                subject: "Alerts are sending again",
+                message: String::from("Alerts are sending to alerts again after 4 failed in a row"),
+            })
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use color_eyre::eyre::{eyre, Result};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Url;
+use serde::Serialize;
+use sqlx::types::chrono::{DateTime, Utc};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::poller::nats::NatsConnection;
+
+/// The number of results that can be waiting for delivery before new ones are dropped.
+const QUEUE_CAPACITY: usize = 1024;
+
+/// The outcome of a single check, as streamed to the firehose.
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckResult {
+    pub origin_uid: Uuid,
+    pub uri: String,
+    pub queried_at: DateTime<Utc>,
+    pub success: bool,
+    pub status: Option<u16>,
+    pub latency_millis: Option<i64>,
+    pub failure_reason: Option<&'static str>,
+}
+
+/// Somewhere check results are delivered to.
+pub enum Sink {
+    /// Posts each result as JSON to an HTTP endpoint.
+    Webhook {
+        http_client: reqwest::Client,
+        url: Url,
+    },
+    /// Publishes each result as JSON to a NATS subject, written as `nats://host:port/subject`.
+    Nats {
+        connection: NatsConnection,
+        subject: String,
+    },
+}
+
+impl Sink {
+    pub fn parse(http_client: reqwest::Client, url: &str) -> Result<Self> {
+        let url = Url::parse(url)?;
+
+        match url.scheme() {
+            "http" | "https" => Ok(Self::Webhook { http_client, url }),
+            "nats" => {
+                let (connection, subject) = NatsConnection::from_url(&url)?;
+
+                Ok(Self::Nats {
+                    connection,
+                    subject,
+                })
+            }
+            scheme => Err(eyre!("unsupported firehose scheme '{scheme}'")),
+        }
+    }
+
+    async fn publish(&mut self, payload: &[u8]) -> Result<()> {
+        match self {
+            Self::Webhook { http_client, url } => {
+                http_client
+                    .post(url.clone())
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(payload.to_vec())
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+            Self::Nats {
+                connection,
+                subject,
+            } => connection.publish(subject, payload).await?,
+        }
+
+        Ok(())
+    }
+}
+
+/// Streams every check result to a sink in the background, so a slow sink never delays checks.
+#[derive(Clone)]
+pub struct Firehose {
+    sender: mpsc::Sender<CheckResult>,
+}
+
+impl Firehose {
+    pub fn spawn(mut sink: Sink) -> Self {
+        let (sender, mut receiver) = mpsc::channel::<CheckResult>(QUEUE_CAPACITY);
+
+        tokio::spawn(async move {
+            while let Some(result) = receiver.recv().await {
+                let payload = match serde_json::to_vec(&result) {
+                    Ok(payload) => payload,
+                    Err(e) => {
+                        tracing::warn!(%e, "failed to serialize a check result");
+                        continue;
+                    }
+                };
+
+                if let Err(e) = sink.publish(&payload).await {
+                    tracing::warn!(origin_uid = %result.origin_uid, %e, "failed to stream a check result");
+                }
+            }
+        });
+
+        Self { sender }
+    }
+
+    /// Configures the firehose from `FIREHOSE_URL`, which is either an HTTP endpoint or a NATS
+    /// subject written as `nats://host:port/subject`.
+    pub fn from_env(http_client: reqwest::Client) -> Result<Option<Self>> {
+        let Ok(url) = std::env::var("FIREHOSE_URL") else {
+            return Ok(None);
+        };
+
+        let sink = Sink::parse(http_client, &url)?;
+
+        Ok(Some(Self::spawn(sink)))
+    }
+
+    /// Queues a result for delivery, dropping it if the sink has fallen too far behind.
+    pub fn record(&self, result: CheckResult) {
+        if let Err(e) = self.sender.try_send(result) {
+            tracing::warn!(%e, "dropped a check result from the firehose");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use color_eyre::eyre::Result;
+
+    use crate::poller::firehose::Sink;
+
+    #[test]
+    fn sinks_are_chosen_by_scheme() -> Result<()> {
+        let http_client = reqwest::Client::new();
+
+        assert!(matches!(
+            Sink::parse(http_client.clone(), "https://example.com/checks")?,
+            Sink::Webhook { .. }
+        ));
+
+        let Sink::Nats { subject, .. } =
+            Sink::parse(http_client.clone(), "nats://localhost/uptime/checks")?
+        else {
+            panic!("expected a NATS sink");
+        };
+
+        assert_eq!(subject, "uptime.checks");
+
+        assert!(Sink::parse(http_client.clone(), "nats://localhost:4222").is_err());
+        assert!(Sink::parse(http_client, "ftp://localhost/checks").is_err());
+
+        Ok(())
+    }
+}
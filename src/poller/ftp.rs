@@ -0,0 +1,542 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use reqwest::Url;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufStream};
+use tokio::net::TcpStream;
+
+use crate::credentials::CredentialCipher;
+use crate::persistence::Origin;
+use crate::poller::check::{Check, CheckContext, CheckOutcome};
+use crate::poller::protocol::{host_and_port, ProtocolFailure};
+use crate::poller::FailureReason;
+use crate::poller::{clients, smtp};
+
+/// The longest reply line that will be read, far above what servers send in practice.
+const MAX_LINE_BYTES: u64 = 4096;
+
+/// The most lines a single reply can have, as servers only send short banners and feature lists.
+const MAX_REPLY_LINES: usize = 64;
+
+/// The most lines read while waiting for an SSH server to identify itself, which it may send other
+/// lines before.
+const MAX_BANNER_LINES: usize = 16;
+
+/// The most of a directory listing that is read, which is enough to know the server can list it.
+const MAX_LISTING_BYTES: u64 = 1024 * 1024;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Protocol {
+    Ftp,
+    Sftp,
+}
+
+/// A file exchange endpoint written as `ftp://host:port/path` or `sftp://host:port`, defaulting to
+/// ports 21 and 22 respectively.
+///
+/// FTP servers are logged into with the credentials of the origin, or anonymously without any,
+/// and a `list` parameter also lists the directory at the path. SFTP servers are checked as far as
+/// identifying themselves, as logging into them needs a full SSH client.
+#[derive(Debug, PartialEq, Eq)]
+pub struct FileTransferCheck {
+    host: String,
+    port: u16,
+    protocol: Protocol,
+    list: Option<String>,
+}
+
+impl FileTransferCheck {
+    pub fn parse(uri: &str) -> Option<Self> {
+        let url = Url::parse(uri).ok()?;
+
+        let (protocol, default_port) = match url.scheme() {
+            "ftp" => (Protocol::Ftp, 21),
+            "sftp" => (Protocol::Sftp, 22),
+            _ => return None,
+        };
+
+        let mut list = None;
+
+        for (key, _) in url.query_pairs() {
+            match key.as_ref() {
+                "list" if protocol == Protocol::Ftp => list = Some(url.path().to_owned()),
+                _ => return None,
+            }
+        }
+
+        let (host, port) = host_and_port(&url, default_port)?;
+
+        Some(Self {
+            host,
+            port,
+            protocol,
+            list,
+        })
+    }
+}
+
+/// The server closing the connection while it was owed a reply.
+fn closed() -> ProtocolFailure {
+    ProtocolFailure::new(
+        FailureReason::ConnectionFailure,
+        String::from("connection was closed by the server"),
+    )
+}
+
+/// The username and password an FTP server is logged into with.
+#[derive(Debug, PartialEq, Eq)]
+struct Login {
+    username: String,
+    password: String,
+}
+
+impl Login {
+    /// The login for an origin, which is anonymous unless it has a username and password.
+    fn for_origin(
+        origin: &Origin,
+        cipher: Option<&CredentialCipher>,
+    ) -> Result<Self, ProtocolFailure> {
+        match clients::username_and_password(origin, cipher) {
+            Ok(Some((username, password))) => Ok(Self { username, password }),
+            Ok(None) => Ok(Self {
+                username: String::from("anonymous"),
+                password: String::from("anonymous@"),
+            }),
+            Err(detail) => Err(ProtocolFailure::new(FailureReason::BadRequest, detail)),
+        }
+    }
+}
+
+/// A complete reply from the server, which may have spanned several lines.
+#[derive(Debug, PartialEq, Eq)]
+struct Reply {
+    code: u16,
+    lines: Vec<String>,
+}
+
+impl Reply {
+    fn summary(&self) -> String {
+        format!(
+            "{} {}",
+            self.code,
+            self.lines.first().map_or("", String::as_str)
+        )
+    }
+}
+
+/// Finds the port in a reply to `EPSV`, such as `Entering Extended Passive Mode (|||6446|)`.
+fn extended_passive_port(text: &str) -> Option<u16> {
+    let start = text.find("(|||")? + 4;
+    let end = start + text[start..].find('|')?;
+
+    text[start..end].parse().ok()
+}
+
+/// Finds the port in a reply to `PASV`, such as `Entering Passive Mode (127,0,0,1,25,46)`.
+///
+/// The address is ignored, as servers behind NAT often advertise one that cannot be reached.
+fn passive_port(text: &str) -> Option<u16> {
+    let start = text.find('(')? + 1;
+    let end = start + text[start..].find(')')?;
+
+    let numbers: Vec<u8> = text[start..end]
+        .split(',')
+        .map(|number| number.trim().parse())
+        .collect::<Result<_, _>>()
+        .ok()?;
+
+    match numbers[..] {
+        [_, _, _, _, high, low] => Some((u16::from(high) << 8) | u16::from(low)),
+        _ => None,
+    }
+}
+
+struct Session {
+    stream: BufStream<TcpStream>,
+}
+
+impl Session {
+    fn new(stream: TcpStream) -> Self {
+        Self {
+            stream: BufStream::new(stream),
+        }
+    }
+
+    async fn line(&mut self) -> Result<String, ProtocolFailure> {
+        let mut line = String::new();
+
+        let length = (&mut self.stream)
+            .take(MAX_LINE_BYTES)
+            .read_line(&mut line)
+            .await
+            .map_err(ProtocolFailure::disconnected)?;
+
+        if length == 0 {
+            return Err(closed());
+        }
+
+        Ok(line)
+    }
+
+    async fn reply(&mut self) -> Result<Reply, ProtocolFailure> {
+        let first = self.line().await?;
+
+        let (code, last, text) = smtp::parse_reply_line(&first).ok_or_else(|| {
+            ProtocolFailure::new(
+                FailureReason::InvalidBody,
+                format!("sent a malformed reply: {}", first.trim_end()),
+            )
+        })?;
+
+        let mut lines = vec![text.to_owned()];
+
+        if last {
+            return Ok(Reply { code, lines });
+        }
+
+        while lines.len() < MAX_REPLY_LINES {
+            let line = self.line().await?;
+
+            // Lines in the middle of a reply can say anything, and only the code followed by a
+            // space ends it
+            match smtp::parse_reply_line(&line) {
+                Some((line_code, true, text)) if line_code == code => {
+                    lines.push(text.to_owned());
+                    return Ok(Reply { code, lines });
+                }
+                _ => lines.push(line.trim_end().to_owned()),
+            }
+        }
+
+        Err(ProtocolFailure::new(
+            FailureReason::InvalidBody,
+            format!("sent a reply longer than {MAX_REPLY_LINES} lines"),
+        ))
+    }
+
+    async fn send(&mut self, command: &str) -> Result<Reply, ProtocolFailure> {
+        self.stream
+            .write_all(format!("{command}\r\n").as_bytes())
+            .await
+            .map_err(ProtocolFailure::disconnected)?;
+
+        self.stream
+            .flush()
+            .await
+            .map_err(ProtocolFailure::disconnected)?;
+
+        self.reply().await
+    }
+
+    async fn greeting(&mut self) -> Result<(), ProtocolFailure> {
+        let reply = self.reply().await?;
+
+        // Servers that are up but not accepting users greet with 421 instead
+        if reply.code != 220 {
+            return Err(ProtocolFailure::new(
+                FailureReason::NotServing,
+                format!("greeted with {} instead of 220", reply.summary()),
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn login(&mut self, login: &Login) -> Result<(), ProtocolFailure> {
+        let mut reply = self.send(&format!("USER {}", login.username)).await?;
+
+        // Servers ask for a password unless the username is enough on its own
+        if reply.code == 331 {
+            reply = self.send(&format!("PASS {}", login.password)).await?;
+        }
+
+        match reply.code {
+            230 | 202 => Ok(()),
+            530 => Err(ProtocolFailure::new(
+                FailureReason::AuthenticationFailed,
+                format!(
+                    "rejected the login as {}: {}",
+                    login.username,
+                    reply.summary()
+                ),
+            )),
+            _ => Err(ProtocolFailure::new(
+                FailureReason::UnexpectedStatus,
+                format!("answered the login with {}", reply.summary()),
+            )),
+        }
+    }
+
+    /// Asks the server which port to open a data connection to, preferring `EPSV` as it also works
+    /// over IPv6 and falling back to `PASV` for servers without it.
+    async fn passive(&mut self) -> Result<u16, ProtocolFailure> {
+        let mut reply = self.send("EPSV").await?;
+
+        let port = if reply.code == 229 {
+            extended_passive_port(&reply.lines.join(" "))
+        } else {
+            reply = self.send("PASV").await?;
+
+            if reply.code != 227 {
+                return Err(ProtocolFailure::new(
+                    FailureReason::UnexpectedStatus,
+                    format!("answered PASV with {} instead of 227", reply.summary()),
+                ));
+            }
+
+            passive_port(&reply.lines.join(" "))
+        };
+
+        port.ok_or_else(|| {
+            ProtocolFailure::new(
+                FailureReason::InvalidBody,
+                format!("sent a passive reply without a port: {}", reply.summary()),
+            )
+        })
+    }
+
+    /// Lists `path` over a data connection, returning how many bytes the listing was.
+    async fn list(&mut self, address: SocketAddr, path: &str) -> Result<u64, ProtocolFailure> {
+        let port = self.passive().await?;
+
+        let mut data = TcpStream::connect(SocketAddr::new(address.ip(), port))
+            .await
+            .map_err(|e| {
+                ProtocolFailure::new(
+                    FailureReason::ConnectionFailure,
+                    format!("could not open a data connection: {e}"),
+                )
+            })?;
+
+        let reply = self.send(&format!("LIST {path}")).await?;
+
+        if !matches!(reply.code, 125 | 150) {
+            return Err(ProtocolFailure::new(
+                FailureReason::UnexpectedStatus,
+                format!("could not list {path}: {}", reply.summary()),
+            ));
+        }
+
+        let mut listing = Vec::new();
+
+        (&mut data)
+            .take(MAX_LISTING_BYTES)
+            .read_to_end(&mut listing)
+            .await
+            .map_err(ProtocolFailure::disconnected)?;
+
+        drop(data);
+
+        let reply = self.reply().await?;
+
+        if !matches!(reply.code, 226 | 250) {
+            return Err(ProtocolFailure::new(
+                FailureReason::UnexpectedStatus,
+                format!("did not finish listing {path}: {}", reply.summary()),
+            ));
+        }
+
+        Ok(listing.len() as u64)
+    }
+
+    /// Ends the session politely, ignoring servers that hang up without answering.
+    async fn quit(mut self) {
+        let _ = self.send("QUIT").await;
+    }
+}
+
+async fn connect(check: &FileTransferCheck) -> Result<TcpStream, ProtocolFailure> {
+    TcpStream::connect((check.host.as_str(), check.port))
+        .await
+        .map_err(|e| {
+            ProtocolFailure::new(
+                FailureReason::ConnectionFailure,
+                format!("could not connect: {e}"),
+            )
+        })
+}
+
+/// Logs into an FTP server, listing the directory of the check if it has one and returning the
+/// size of the listing.
+async fn converse_ftp(
+    check: &FileTransferCheck,
+    login: &Login,
+) -> Result<Option<u64>, ProtocolFailure> {
+    let stream = connect(check).await?;
+    let address = stream.peer_addr().map_err(ProtocolFailure::disconnected)?;
+
+    let mut session = Session::new(stream);
+    session.greeting().await?;
+    session.login(login).await?;
+
+    let listed = match &check.list {
+        Some(path) => Some(session.list(address, path).await?),
+        None => None,
+    };
+
+    session.quit().await;
+
+    Ok(listed)
+}
+
+/// Waits for an SSH server to identify itself, which SFTP servers do before anything else.
+async fn converse_sftp(check: &FileTransferCheck) -> Result<(), ProtocolFailure> {
+    let mut session = Session::new(connect(check).await?);
+
+    for _ in 0..MAX_BANNER_LINES {
+        let line = session.line().await?;
+
+        // Servers may send other lines first, but must identify themselves before anything else
+        if !line.starts_with("SSH-") {
+            continue;
+        }
+
+        if line.starts_with("SSH-2.0-") || line.starts_with("SSH-1.99-") {
+            return Ok(());
+        }
+
+        return Err(ProtocolFailure::new(
+            FailureReason::NotServing,
+            format!("identified as {}, which is not SSH 2", line.trim_end()),
+        ));
+    }
+
+    Err(ProtocolFailure::new(
+        FailureReason::NotServing,
+        format!("did not identify as an SSH server within {MAX_BANNER_LINES} lines"),
+    ))
+}
+
+/// Connects to the server and holds the configured conversation with it, returning the size of
+/// the directory listing if one was made.
+async fn check(
+    check: &FileTransferCheck,
+    login: &Login,
+    timeout: Duration,
+) -> Result<Option<u64>, ProtocolFailure> {
+    let conversation = async {
+        match check.protocol {
+            Protocol::Ftp => converse_ftp(check, login).await,
+            Protocol::Sftp => converse_sftp(check).await.map(|()| None),
+        }
+    };
+
+    tokio::time::timeout(timeout, conversation)
+        .await
+        .map_err(|_| {
+            ProtocolFailure::new(
+                FailureReason::RequestTimeout,
+                format!("did not finish the conversation within {timeout:?}"),
+            )
+        })?
+}
+
+/// Logs into origins written as `ftp://host:port` and waits for origins written as
+/// `sftp://host:port` to identify themselves.
+pub struct FileTransferChecker;
+
+impl Check for FileTransferChecker {
+    fn execute<'a>(
+        &'a self,
+        origin: &'a Origin,
+        context: &'a CheckContext<'a>,
+    ) -> BoxFuture<'a, CheckOutcome> {
+        Box::pin(async move {
+            let Some(parsed) = FileTransferCheck::parse(&origin.uri) else {
+                return CheckOutcome::new(Err(FailureReason::BadRequest));
+            };
+
+            if parsed.protocol == Protocol::Sftp && origin.encrypted_credentials.is_some() {
+                return CheckOutcome::failed(
+                    FailureReason::BadRequest,
+                    String::from("logging into SFTP servers with credentials is not supported"),
+                );
+            }
+
+            let login = match Login::for_origin(origin, context.credentials) {
+                Ok(login) => login,
+                Err(failure) => return CheckOutcome::failed(failure.reason, failure.detail),
+            };
+
+            match check(&parsed, &login, context.timeout).await {
+                Ok(listed) => CheckOutcome {
+                    body_bytes: listed.map(|bytes| bytes as i64),
+                    ..CheckOutcome::new(Ok((None, context.elapsed_millis())))
+                },
+                Err(failure) => CheckOutcome::failed(failure.reason, failure.detail),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::poller::ftp::{FileTransferCheck, Protocol};
+
+    #[test]
+    fn checks_are_parsed_from_uris() {
+        assert_eq!(
+            FileTransferCheck::parse("ftp://files.example.com"),
+            Some(FileTransferCheck {
+                host: String::from("files.example.com"),
+                port: 21,
+                protocol: Protocol::Ftp,
+                list: None,
+            })
+        );
+        assert_eq!(
+            FileTransferCheck::parse("ftp://files.example.com:2121/outgoing?list"),
+            Some(FileTransferCheck {
+                host: String::from("files.example.com"),
+                port: 2121,
+                protocol: Protocol::Ftp,
+                list: Some(String::from("/outgoing")),
+            })
+        );
+        assert_eq!(
+            FileTransferCheck::parse("sftp://[::1]"),
+            Some(FileTransferCheck {
+                host: String::from("::1"),
+                port: 22,
+                protocol: Protocol::Sftp,
+                list: None,
+            })
+        );
+
+        assert_eq!(
+            FileTransferCheck::parse("sftp://files.example.com?list"),
+            None
+        );
+        assert_eq!(
+            FileTransferCheck::parse("ftp://files.example.com?upload"),
+            None
+        );
+        assert_eq!(FileTransferCheck::parse("https://files.example.com"), None);
+    }
+
+    #[test]
+    fn data_ports_are_found_in_passive_replies() {
+        assert_eq!(
+            super::extended_passive_port("Entering Extended Passive Mode (|||6446|)"),
+            Some(6446)
+        );
+        assert_eq!(
+            super::passive_port("Entering Passive Mode (10,0,0,5,25,46)."),
+            Some(25 * 256 + 46)
+        );
+
+        assert_eq!(
+            super::extended_passive_port("Entering Extended Passive Mode"),
+            None
+        );
+        assert_eq!(
+            super::passive_port("Entering Passive Mode (10,0,0,5,25)"),
+            None
+        );
+        assert_eq!(
+            super::passive_port("Entering Passive Mode (10,0,0,5,256,1)"),
+            None
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use reqwest::header::{CONTENT_TYPE, TE};
+use reqwest::Url;
+
+use crate::persistence::Origin;
+use crate::poller::check::{Check, CheckContext, CheckOutcome};
+use crate::poller::FailureReason;
+
+/// The method every server implementing the standard health checking protocol exposes.
+const HEALTH_CHECK_PATH: &str = "/grpc.health.v1.Health/Check";
+
+/// The port `grpcs://` origins use if they do not give one.
+const DEFAULT_TLS_PORT: u16 = 443;
+
+/// The serving status reported in a `grpc.health.v1.HealthCheckResponse`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ServingStatus {
+    Unknown,
+    Serving,
+    NotServing,
+    ServiceUnknown,
+}
+
+impl ServingStatus {
+    fn from_code(code: u64) -> Self {
+        match code {
+            1 => Self::Serving,
+            2 => Self::NotServing,
+            3 => Self::ServiceUnknown,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// A health check written as `grpc://host:port/service`, or `grpcs://` to connect over TLS.
+///
+/// Without a service, the health of the server as a whole is checked.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GrpcCheck {
+    endpoint: String,
+    service: String,
+}
+
+impl GrpcCheck {
+    pub fn parse(uri: &str) -> Option<Self> {
+        let url = Url::parse(uri).ok()?;
+
+        let (scheme, default_port) = match url.scheme() {
+            "grpc" => ("http", None),
+            "grpcs" => ("https", Some(DEFAULT_TLS_PORT)),
+            _ => return None,
+        };
+
+        let host = url.host_str()?;
+        let port = url.port().or(default_port)?;
+
+        Some(Self {
+            endpoint: format!("{scheme}://{host}:{port}"),
+            service: url.path().trim_matches('/').to_owned(),
+        })
+    }
+}
+
+/// Builds a client that only speaks HTTP/2, which gRPC requires even without TLS.
+pub fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .http2_prior_knowledge()
+        .build()
+        .expect("failed to build the gRPC client")
+}
+
+fn write_varint(mut value: u64, buffer: &mut Vec<u8>) {
+    while value >= 0x80 {
+        buffer.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+
+    buffer.push(value as u8);
+}
+
+fn read_varint(buffer: &[u8]) -> Option<(u64, &[u8])> {
+    let mut value = 0;
+
+    for (i, byte) in buffer.iter().enumerate().take(10) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+
+        if byte & 0x80 == 0 {
+            return Some((value, &buffer[i + 1..]));
+        }
+    }
+
+    None
+}
+
+/// Encodes a `HealthCheckRequest` for the service as a length prefixed gRPC message.
+fn encode_request(service: &str) -> Vec<u8> {
+    let mut message = Vec::new();
+
+    // Empty strings are the default in proto3, so they are left out entirely
+    if !service.is_empty() {
+        message.push(0x0a);
+        write_varint(service.len() as u64, &mut message);
+        message.extend_from_slice(service.as_bytes());
+    }
+
+    let mut frame = Vec::with_capacity(5 + message.len());
+    frame.push(0);
+    frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&message);
+
+    frame
+}
+
+/// Decodes the status from the first message of a response, skipping any unknown fields.
+fn decode_response(body: &[u8]) -> Option<ServingStatus> {
+    // Compressed messages are never requested, so servers should not send them
+    let (&0, rest) = body.split_first()? else {
+        return None;
+    };
+
+    let length = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+    let mut message = rest.get(4..4 + length)?;
+    let mut status = 0;
+
+    while !message.is_empty() {
+        let (key, rest) = read_varint(message)?;
+
+        message = match (key >> 3, key & 0x07) {
+            (1, 0) => {
+                let (value, rest) = read_varint(rest)?;
+                status = value;
+                rest
+            }
+            (_, 0) => read_varint(rest)?.1,
+            (_, 1) => rest.get(8..)?,
+            (_, 2) => {
+                let (length, rest) = read_varint(rest)?;
+                rest.get(length as usize..)?
+            }
+            (_, 5) => rest.get(4..)?,
+            _ => return None,
+        };
+    }
+
+    Some(ServingStatus::from_code(status))
+}
+
+/// Calls `grpc.health.v1.Health/Check`, succeeding only if the service reports `SERVING`.
+pub async fn check(
+    client: &reqwest::Client,
+    check: &GrpcCheck,
+    timeout: Duration,
+) -> Result<(), FailureReason> {
+    let response = client
+        .post(format!("{}{HEALTH_CHECK_PATH}", check.endpoint))
+        .header(CONTENT_TYPE, "application/grpc")
+        .header(TE, "trailers")
+        .timeout(timeout)
+        .body(encode_request(&check.service))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(FailureReason::BadRequest);
+    }
+
+    // Errors are sent in the headers alone when there is no message to go with them
+    if let Some(grpc_status) = response.headers().get("grpc-status") {
+        match grpc_status.as_bytes() {
+            b"0" => {}
+            // NOT_FOUND for unknown services and UNAVAILABLE for servers shutting down
+            b"5" | b"14" => return Err(FailureReason::NotServing),
+            // Most likely UNIMPLEMENTED, as the server does not support health checks
+            _ => return Err(FailureReason::BadRequest),
+        }
+    }
+
+    let body = response.bytes().await?;
+
+    match decode_response(&body) {
+        Some(ServingStatus::Serving) => Ok(()),
+        Some(status) => {
+            tracing::debug!(?status, service = %check.service, "service is not serving");
+            Err(FailureReason::NotServing)
+        }
+        None => Err(FailureReason::InvalidBody),
+    }
+}
+
+/// Calls the health checking service of origins written as `grpc://host:port/service`.
+pub struct GrpcChecker {
+    client: reqwest::Client,
+}
+
+impl Default for GrpcChecker {
+    fn default() -> Self {
+        Self { client: client() }
+    }
+}
+
+impl Check for GrpcChecker {
+    fn execute<'a>(
+        &'a self,
+        origin: &'a Origin,
+        context: &'a CheckContext<'a>,
+    ) -> BoxFuture<'a, CheckOutcome> {
+        Box::pin(async move {
+            let Some(parsed) = GrpcCheck::parse(&origin.uri) else {
+                return CheckOutcome::new(Err(FailureReason::BadRequest));
+            };
+
+            let result = check(&self.client, &parsed, context.timeout)
+                .await
+                .map(|()| (None, context.elapsed_millis()));
+
+            CheckOutcome::new(result)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::poller::grpc::{GrpcCheck, ServingStatus};
+
+    #[test]
+    fn checks_are_parsed_from_uris() {
+        assert_eq!(
+            GrpcCheck::parse("grpc://payments.internal:50051/payments.v1.Payments"),
+            Some(GrpcCheck {
+                endpoint: String::from("http://payments.internal:50051"),
+                service: String::from("payments.v1.Payments"),
+            })
+        );
+        assert_eq!(
+            GrpcCheck::parse("grpcs://api.example.com"),
+            Some(GrpcCheck {
+                endpoint: String::from("https://api.example.com:443"),
+                service: String::new(),
+            })
+        );
+
+        // Plaintext servers have no conventional port to fall back to
+        assert_eq!(GrpcCheck::parse("grpc://payments.internal"), None);
+        assert_eq!(GrpcCheck::parse("https://example.com"), None);
+    }
+
+    #[test]
+    fn requests_are_framed_protobuf_messages() {
+        assert_eq!(super::encode_request(""), [0, 0, 0, 0, 0]);
+        assert_eq!(
+            super::encode_request("svc"),
+            [0, 0, 0, 0, 5, 0x0a, 3, b's', b'v', b'c']
+        );
+    }
+
+    #[test]
+    fn statuses_are_decoded_from_responses() {
+        assert_eq!(
+            super::decode_response(&[0, 0, 0, 0, 2, 0x08, 1]),
+            Some(ServingStatus::Serving)
+        );
+        assert_eq!(
+            super::decode_response(&[0, 0, 0, 0, 2, 0x08, 2]),
+            Some(ServingStatus::NotServing)
+        );
+
+        // Unknown fields are skipped and a missing status is the proto3 default
+        assert_eq!(
+            super::decode_response(&[0, 0, 0, 0, 3, 0x12, 1, b'x']),
+            Some(ServingStatus::Unknown)
+        );
+
+        assert_eq!(super::decode_response(&[]), None);
+        assert_eq!(super::decode_response(&[1, 0, 0, 0, 0]), None);
+        assert_eq!(super::decode_response(&[0, 0, 0, 0, 2, 0x08]), None);
+    }
+}
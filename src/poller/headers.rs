@@ -0,0 +1,213 @@
+use color_eyre::eyre::{eyre, Result};
+use reqwest::header::{HeaderMap, HeaderName};
+
+/// The most of a single header value that is recorded, so an oversized header cannot bloat the
+/// history of an origin.
+const MAX_VALUE_CHARS: usize = 256;
+
+/// The response headers recorded with each check of an origin, such as `X-Request-Id` or `Via`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CapturedHeaders {
+    names: Vec<HeaderName>,
+}
+
+impl CapturedHeaders {
+    /// Parses a comma separated list of header names.
+    pub fn parse(value: &str) -> Result<Self> {
+        let names = value
+            .split(',')
+            .map(|name| {
+                let name = name.trim();
+
+                HeaderName::from_bytes(name.as_bytes())
+                    .map_err(|_| eyre!("'{name}' is not a valid header name"))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { names })
+    }
+
+    /// Formats the captured headers present in `headers` as one `name: value` line each, or
+    /// returns `None` if the response had none of them.
+    pub fn capture(&self, headers: &HeaderMap) -> Option<String> {
+        let lines: Vec<_> = self
+            .names
+            .iter()
+            .flat_map(|name| {
+                headers.get_all(name).iter().map(move |value| {
+                    let value = String::from_utf8_lossy(value.as_bytes());
+                    let value: String = value.chars().take(MAX_VALUE_CHARS).collect();
+
+                    format!("{name}: {value}")
+                })
+            })
+            .collect();
+
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+}
+
+/// A header responses of an origin must have, optionally with a value it must contain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ExpectedHeader {
+    name: HeaderName,
+    value: Option<String>,
+}
+
+/// The response headers an origin must send for its checks to succeed, such as
+/// `Strict-Transport-Security` being present or `Content-Type` containing `application/json`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExpectedHeaders {
+    headers: Vec<ExpectedHeader>,
+}
+
+impl ExpectedHeaders {
+    /// Parses one header per line, either as a name that must be present or as `name: value` for
+    /// a header whose value must contain `value`, ignoring case.
+    pub fn parse(value: &str) -> Result<Self> {
+        let headers: Vec<_> = value
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let (name, value) = match line.split_once(':') {
+                    Some((name, value)) => (name.trim(), Some(value.trim())),
+                    None => (line, None),
+                };
+
+                let name = HeaderName::from_bytes(name.as_bytes())
+                    .map_err(|_| eyre!("'{name}' is not a valid header name"))?;
+
+                let value = match value {
+                    Some("") => return Err(eyre!("the value expected of {name} is empty")),
+                    Some(value) => Some(value.to_lowercase()),
+                    None => None,
+                };
+
+                Ok(ExpectedHeader { name, value })
+            })
+            .collect::<Result<_>>()?;
+
+        if headers.is_empty() {
+            return Err(eyre!("no headers are expected"));
+        }
+
+        Ok(Self { headers })
+    }
+
+    /// Checks `headers` has every expected header, describing the first that is missing or has
+    /// the wrong value otherwise.
+    pub fn check(&self, headers: &HeaderMap) -> std::result::Result<(), String> {
+        for expected in &self.headers {
+            let values: Vec<_> = headers
+                .get_all(&expected.name)
+                .iter()
+                .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+                .collect();
+
+            let Some(first) = values.first() else {
+                return Err(format!("the {} header is missing", expected.name));
+            };
+
+            let Some(expected_value) = &expected.value else {
+                continue;
+            };
+
+            if !values
+                .iter()
+                .any(|value| value.to_lowercase().contains(expected_value))
+            {
+                let first: String = first.chars().take(MAX_VALUE_CHARS).collect();
+
+                return Err(format!(
+                    "the {} header is '{first}' rather than containing '{expected_value}'",
+                    expected.name
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use color_eyre::eyre::Result;
+    use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE, SERVER, VIA};
+
+    use crate::poller::headers::{CapturedHeaders, ExpectedHeaders};
+
+    #[test]
+    fn only_listed_headers_are_captured() -> Result<()> {
+        let captured = CapturedHeaders::parse("X-Request-Id, Server,via")?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-id", HeaderValue::from_static("abc123"));
+        headers.insert(SERVER, HeaderValue::from_static("nginx"));
+        headers.append(VIA, HeaderValue::from_static("1.1 edge-1"));
+        headers.append(VIA, HeaderValue::from_static("1.1 edge-2"));
+        headers.insert("x-powered-by", HeaderValue::from_static("php"));
+
+        assert_eq!(
+            captured.capture(&headers).as_deref(),
+            Some("x-request-id: abc123\nserver: nginx\nvia: 1.1 edge-1\nvia: 1.1 edge-2")
+        );
+
+        assert_eq!(captured.capture(&HeaderMap::new()), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_lists_are_rejected() {
+        assert!(CapturedHeaders::parse("").is_err());
+        assert!(CapturedHeaders::parse("X-Request-Id,").is_err());
+        assert!(CapturedHeaders::parse("Not A Header").is_err());
+    }
+
+    #[test]
+    fn expected_headers_must_be_present_with_their_values() -> Result<()> {
+        let expected = ExpectedHeaders::parse(
+            "Content-Type: application/json\n\nStrict-Transport-Security\n",
+        )?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("Application/JSON; charset=utf-8"),
+        );
+        headers.insert(
+            "strict-transport-security",
+            HeaderValue::from_static("max-age=31536000"),
+        );
+
+        assert_eq!(expected.check(&headers), Ok(()));
+
+        headers.remove("strict-transport-security");
+
+        assert_eq!(
+            expected.check(&headers),
+            Err(String::from(
+                "the strict-transport-security header is missing"
+            ))
+        );
+
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/html"));
+
+        assert_eq!(
+            expected.check(&headers),
+            Err(String::from(
+                "the content-type header is 'text/html' rather than containing 'application/json'"
+            ))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_expected_headers_are_rejected() {
+        assert!(ExpectedHeaders::parse("").is_err());
+        assert!(ExpectedHeaders::parse("Content-Type:").is_err());
+        assert!(ExpectedHeaders::parse("Not A Header: value").is_err());
+    }
+}
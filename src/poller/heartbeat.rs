@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use futures_util::future::BoxFuture;
+use humantime::format_duration;
+
+use crate::persistence::{Heartbeat, Origin};
+use crate::poller::check::{Check, CheckContext, CheckOutcome};
+use crate::poller::FailureReason;
+
+/// Why a heartbeat check failed, with how long it has been since the last ping.
+#[derive(Debug, PartialEq, Eq)]
+pub struct HeartbeatFailure {
+    pub reason: FailureReason,
+    pub detail: String,
+}
+
+/// Checks that a heartbeat was pinged within its grace period of `now`.
+///
+/// Heartbeats that have never been pinged are measured from when they were created, so a new one
+/// has a full grace period to receive its first ping.
+pub fn verify(heartbeat: &Heartbeat, now: DateTime<Utc>) -> Result<(), HeartbeatFailure> {
+    let since = heartbeat.last_ping_at.unwrap_or(heartbeat.created_at);
+    let grace = Duration::from_secs(heartbeat.grace_seconds.max(0) as u64);
+    let elapsed = Duration::from_secs((now - since).num_seconds().max(0) as u64);
+
+    if elapsed <= grace {
+        return Ok(());
+    }
+
+    let detail = match heartbeat.last_ping_at {
+        Some(_) => format!(
+            "last ping was {} ago, expected within {}",
+            format_duration(elapsed),
+            format_duration(grace)
+        ),
+        None => format!(
+            "never pinged since being created {} ago, expected within {}",
+            format_duration(elapsed),
+            format_duration(grace)
+        ),
+    };
+
+    Err(HeartbeatFailure {
+        reason: FailureReason::HeartbeatMissed,
+        detail,
+    })
+}
+
+/// Checks when origins written as `heartbeat://name` were last pinged, rather than making a
+/// request.
+pub struct HeartbeatChecker;
+
+impl Check for HeartbeatChecker {
+    fn execute<'a>(
+        &'a self,
+        origin: &'a Origin,
+        context: &'a CheckContext<'a>,
+    ) -> BoxFuture<'a, CheckOutcome> {
+        Box::pin(async move {
+            let heartbeat =
+                crate::persistence::fetch_heartbeat(context.pool, origin.origin_uid).await;
+
+            match heartbeat {
+                Ok(Some(heartbeat)) => match verify(&heartbeat, context.started_at) {
+                    Ok(()) => CheckOutcome::new(Ok((None, 0))),
+                    Err(failure) => CheckOutcome::failed(failure.reason, failure.detail),
+                },
+                Ok(None) => CheckOutcome::failed(
+                    FailureReason::BadRequest,
+                    String::from("the heartbeat has no ping URL"),
+                ),
+                Err(e) => {
+                    tracing::warn!(%e, "failed to fetch heartbeat");
+                    CheckOutcome::new(Err(FailureReason::Unknown))
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use crate::persistence::Heartbeat;
+    use crate::poller::heartbeat::HeartbeatFailure;
+    use crate::poller::FailureReason;
+
+    fn heartbeat(last_ping_minutes_ago: Option<i64>) -> Heartbeat {
+        let now = Utc.with_ymd_and_hms(2024, 1, 3, 12, 0, 0).unwrap();
+
+        Heartbeat {
+            token: String::from("abc123"),
+            grace_seconds: 3600,
+            created_at: now - Duration::days(1),
+            last_ping_at: last_ping_minutes_ago.map(|minutes| now - Duration::minutes(minutes)),
+        }
+    }
+
+    #[test]
+    fn recent_pings_pass() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 3, 12, 0, 0).unwrap();
+
+        assert_eq!(super::verify(&heartbeat(Some(5)), now), Ok(()));
+        assert_eq!(super::verify(&heartbeat(Some(60)), now), Ok(()));
+    }
+
+    #[test]
+    fn late_pings_fail() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 3, 12, 0, 0).unwrap();
+
+        assert_eq!(
+            super::verify(&heartbeat(Some(90)), now),
+            Err(HeartbeatFailure {
+                reason: FailureReason::HeartbeatMissed,
+                detail: String::from("last ping was 1h 30m ago, expected within 1h"),
+            })
+        );
+
+        assert_eq!(
+            super::verify(&heartbeat(None), now),
+            Err(HeartbeatFailure {
+                reason: FailureReason::HeartbeatMissed,
+                detail: String::from(
+                    "never pinged since being created 1day ago, expected within 1h"
+                ),
+            })
+        );
+    }
+}
@@ -0,0 +1,581 @@
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use futures_util::future::BoxFuture;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_LENGTH, CONTENT_TYPE, USER_AGENT};
+use reqwest::{Method, StatusCode, Url, Version};
+use serde_json::Value;
+use tokio_rustls::TlsConnector;
+
+use crate::persistence::{ConnectionMode, HttpMethod, Origin};
+use crate::poller::assertion::JsonAssertion;
+use crate::poller::cache::{self, ValidatorCache};
+use crate::poller::check::{Check, CheckContext, CheckOutcome};
+use crate::poller::headers::{CapturedHeaders, ExpectedHeaders};
+use crate::poller::redirect::{self, Chain, RedirectFailure, Request};
+use crate::poller::status::ExpectedStatus;
+use crate::poller::timing::{self, Timings};
+use crate::poller::FailureReason;
+
+/// The most of a response body that will be read when checking its content.
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// The query parameter added to the URLs of origins with cache busting, which is given a random
+/// value on every request.
+const CACHE_BUSTING_PARAMETER: &str = "uptime_cache_bust";
+
+/// Why an HTTP check failed, with an explanation where the reason alone is not enough to go on.
+#[derive(Debug, PartialEq, Eq)]
+pub struct HttpFailure {
+    pub reason: FailureReason,
+    pub detail: Option<String>,
+    /// The headers captured from the response that failed the check, if there was one.
+    pub captured_headers: Option<String>,
+    /// The address the response that failed the check came from, if there was one.
+    pub remote_address: Option<IpAddr>,
+}
+
+impl From<FailureReason> for HttpFailure {
+    fn from(reason: FailureReason) -> Self {
+        Self {
+            reason,
+            detail: None,
+            captured_headers: None,
+            remote_address: None,
+        }
+    }
+}
+
+impl From<RedirectFailure> for HttpFailure {
+    fn from(failure: RedirectFailure) -> Self {
+        Self {
+            reason: failure.reason,
+            detail: Some(failure.detail),
+            captured_headers: None,
+            remote_address: None,
+        }
+    }
+}
+
+/// Where a request ended up, if it was redirected along the way.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Redirects {
+    pub final_url: String,
+    pub chain: String,
+}
+
+/// The status an origin responded with, and how the request got there.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Checked {
+    pub status: u16,
+    pub redirects: Option<Redirects>,
+    /// The address the final response came from, which is the proxy if there is one.
+    pub remote_address: Option<IpAddr>,
+    /// The size of the response body, as declared by `Content-Length` or otherwise downloaded.
+    pub body_bytes: Option<i64>,
+    /// The version of HTTP the final response was sent with, such as `HTTP/2`.
+    pub http_version: &'static str,
+    /// The headers of the final response the origin is configured to capture.
+    pub captured_headers: Option<String>,
+    /// How long the headers of the final response took to arrive, including any redirects.
+    pub first_byte_millis: i64,
+    /// The SHA-256 of the body as lowercase hex, for origins detecting changes to it.
+    pub body_hash: Option<String>,
+}
+
+/// Makes the request configured for an origin with any extra `headers`, returning the status if
+/// it was expected and the response passes any content checks.
+pub async fn check(
+    http_client: &reqwest::Client,
+    origin: &Origin,
+    uri: &str,
+    timeout: Duration,
+    headers: HeaderMap,
+) -> Result<Checked, HttpFailure> {
+    let start = Instant::now();
+    let (response, redirects) = send(http_client, origin, uri, timeout, headers).await?;
+    let first_byte_millis = start.elapsed().as_millis() as i64;
+    let remote_address = response.remote_addr().map(|addr| addr.ip());
+    let http_version = version_name(response.version());
+    let captured_headers = capture_headers(origin, response.headers());
+
+    // Checked before the body is read but reported after, so a bad status takes precedence
+    let expected_headers = check_headers(origin, response.headers());
+
+    let Verified {
+        status,
+        body_bytes,
+        body_hash,
+    } = verify(origin, response)
+        .await
+        .map_err(|reason| HttpFailure {
+            reason,
+            detail: None,
+            captured_headers: captured_headers.clone(),
+            remote_address,
+        })?;
+
+    expected_headers.map_err(|failure| HttpFailure {
+        captured_headers: captured_headers.clone(),
+        remote_address,
+        ..failure
+    })?;
+
+    Ok(Checked {
+        status,
+        redirects,
+        remote_address,
+        body_bytes,
+        http_version,
+        captured_headers,
+        first_byte_millis,
+        body_hash,
+    })
+}
+
+/// Formats the headers of a response the origin is configured to capture, if any.
+pub fn capture_headers(origin: &Origin, headers: &HeaderMap) -> Option<String> {
+    // Lists are validated when they are saved, but a bad import should not fail the check itself
+    let captured = CapturedHeaders::parse(origin.captured_headers.as_deref()?).ok()?;
+
+    captured.capture(headers)
+}
+
+/// Checks a response has the headers its origin expects, explaining which is missing or wrong.
+pub fn check_headers(origin: &Origin, headers: &HeaderMap) -> Result<(), HttpFailure> {
+    let Some(expected_headers) = &origin.expected_headers else {
+        return Ok(());
+    };
+
+    // Lists are validated when they are saved, but imported configurations may not be
+    let expected = ExpectedHeaders::parse(expected_headers).map_err(|e| HttpFailure {
+        reason: FailureReason::BadRequest,
+        detail: Some(format!(
+            "the expected headers of the origin are invalid: {e}"
+        )),
+        captured_headers: None,
+        remote_address: None,
+    })?;
+
+    expected.check(headers).map_err(|detail| HttpFailure {
+        reason: FailureReason::HeaderAssertionFailed,
+        detail: Some(detail),
+        captured_headers: None,
+        remote_address: None,
+    })
+}
+
+/// The name of an HTTP version as it is usually written, such as `HTTP/2` rather than the
+/// `HTTP/2.0` it is formatted as.
+pub fn version_name(version: Version) -> &'static str {
+    match version {
+        Version::HTTP_09 => "HTTP/0.9",
+        Version::HTTP_10 => "HTTP/1.0",
+        Version::HTTP_11 => "HTTP/1.1",
+        Version::HTTP_2 => "HTTP/2",
+        Version::HTTP_3 => "HTTP/3",
+        _ => "HTTP",
+    }
+}
+
+/// Makes the request configured for an origin with any extra `headers`, following redirects as
+/// its policy allows.
+pub async fn send(
+    http_client: &reqwest::Client,
+    origin: &Origin,
+    uri: &str,
+    timeout: Duration,
+    mut headers: HeaderMap,
+) -> Result<(reqwest::Response, Option<Redirects>), HttpFailure> {
+    let mut url = Url::parse(uri).map_err(|_| FailureReason::BadRequest)?;
+
+    if origin.cache_busting {
+        bust_cache(&mut url);
+    }
+
+    if let Some(content_type) = &origin.request_content_type {
+        let content_type =
+            HeaderValue::from_str(content_type).map_err(|_| FailureReason::BadRequest)?;
+
+        headers.insert(CONTENT_TYPE, content_type);
+    }
+
+    // Replaces the User-Agent of the client, which is the poller's
+    if let Some(user_agent) = &origin.user_agent {
+        let user_agent =
+            HeaderValue::from_str(user_agent).map_err(|_| FailureReason::BadRequest)?;
+
+        headers.insert(USER_AGENT, user_agent);
+    }
+
+    let request = Request {
+        method: origin.method.into(),
+        headers,
+        body: origin.request_body.clone(),
+    };
+
+    let max_hops = origin
+        .redirect_limit
+        .map_or(redirect::MAX_HOPS, |limit| limit as usize);
+
+    let (mut chain, mut response) =
+        redirect::follow(http_client, request.clone(), url.clone(), max_hops, timeout).await?;
+
+    // Some servers refuse HEAD requests, so fall back to the GET they stand in for. The body is
+    // still left unread unless the origin checks its content
+    if origin.method == HttpMethod::Head && rejects_head(response.status()) {
+        tracing::debug!(%uri, status = %response.status(), "origin rejected a HEAD request, retrying with GET");
+
+        let request = Request {
+            method: Method::GET,
+            ..request
+        };
+
+        (chain, response) = redirect::follow(http_client, request, url, max_hops, timeout).await?;
+    }
+
+    if let Some(expected_location) = &origin.expected_location {
+        check_location(&chain, expected_location)?;
+    }
+
+    let redirects = (!chain.redirects().is_empty()).then(|| Redirects {
+        final_url: chain.destination().url.to_string(),
+        chain: chain.to_string(),
+    });
+
+    Ok((response, redirects))
+}
+
+/// Adds a query parameter with a random value to `url`, so caches in front of the origin cannot
+/// answer the request in its place and hide an outage.
+fn bust_cache(url: &mut Url) {
+    let value = format!("{:016x}", rand::random::<u64>());
+
+    url.query_pairs_mut()
+        .append_pair(CACHE_BUSTING_PARAMETER, &value);
+}
+
+/// Whether a response to a HEAD request means the server does not support them, rather than the
+/// origin being unhealthy.
+fn rejects_head(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+    )
+}
+
+/// Checks the first response redirected to the expected location, which may be relative to the
+/// URL of the origin.
+fn check_location(chain: &Chain, expected_location: &str) -> Result<(), RedirectFailure> {
+    let first = chain.first();
+
+    // Locations are validated when they are saved, but imported configurations may not be
+    let Ok(expected) = first.url.join(expected_location) else {
+        return Err(RedirectFailure {
+            reason: FailureReason::BadRequest,
+            detail: format!("{expected_location} is not a valid location"),
+        });
+    };
+
+    let detail = match &first.location {
+        Some(location) if *location == expected => return Ok(()),
+        Some(location) => format!("redirected to {location} instead of {expected}"),
+        None => format!(
+            "responded {} instead of redirecting to {expected}",
+            first.status.as_u16()
+        ),
+    };
+
+    Err(RedirectFailure {
+        reason: FailureReason::Redirection,
+        detail,
+    })
+}
+
+/// What is known of a response that passed the checks configured for its origin.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Verified {
+    pub status: u16,
+    /// The size of the body, as declared by `Content-Length` or otherwise downloaded.
+    pub body_bytes: Option<i64>,
+    /// The SHA-256 of the whole body as lowercase hex, for origins detecting changes to it.
+    pub body_hash: Option<String>,
+}
+
+/// The body of a response, along with facts about all of it where only the start is kept.
+struct Body {
+    /// Up to [`MAX_BODY_BYTES`] of the body.
+    text: String,
+    downloaded_bytes: i64,
+    sha256: String,
+}
+
+/// Checks the status and content of a response against the configuration of its origin,
+/// returning the status along with the size and hash of the body.
+pub async fn verify(
+    origin: &Origin,
+    response: reqwest::Response,
+) -> Result<Verified, FailureReason> {
+    let status = response.status().as_u16();
+
+    if let Some(expected_status) = &origin.expected_status {
+        check_status(status, expected_status)?;
+    }
+
+    let declared_bytes = response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok()?.parse().ok());
+
+    // Trust the declared size rather than downloading a body nothing needs to look at
+    let needs_body = origin.forbidden_text.is_some()
+        || origin.json_assertion.is_some()
+        || origin.body_change_detection;
+
+    if !needs_body && (declared_bytes.is_some() || origin.method == HttpMethod::Head) {
+        return Ok(Verified {
+            status,
+            body_bytes: declared_bytes,
+            body_hash: None,
+        });
+    }
+
+    let body = read_body(response).await?;
+    let body_bytes = declared_bytes.or(Some(body.downloaded_bytes));
+
+    if let Some(forbidden_text) = &origin.forbidden_text {
+        if contains_forbidden_text(&body.text, forbidden_text) {
+            return Err(FailureReason::ForbiddenContent);
+        }
+    }
+
+    if let Some(expression) = &origin.json_assertion {
+        check_json_assertion(&body.text, expression)?;
+    }
+
+    Ok(Verified {
+        status,
+        body_bytes,
+        body_hash: origin.body_change_detection.then_some(body.sha256),
+    })
+}
+
+fn check_status(status: u16, expected_status: &str) -> Result<(), FailureReason> {
+    // Lists are validated when they are saved, but imported configurations may not be
+    let expected = ExpectedStatus::parse(expected_status).map_err(|_| FailureReason::BadRequest)?;
+
+    if !expected.contains(status) {
+        return Err(FailureReason::UnexpectedStatus);
+    }
+
+    Ok(())
+}
+
+fn check_json_assertion(body: &str, expression: &str) -> Result<(), FailureReason> {
+    // Assertions are validated when they are saved, but imported configurations may not be
+    let assertion = JsonAssertion::parse(expression).map_err(|_| FailureReason::BadRequest)?;
+    let document: Value = serde_json::from_str(body).map_err(|_| FailureReason::InvalidBody)?;
+
+    if !assertion.holds(&document) {
+        return Err(FailureReason::JsonAssertionFailed);
+    }
+
+    Ok(())
+}
+
+/// Downloads the whole body of a response, keeping up to [`MAX_BODY_BYTES`] of it while counting
+/// and hashing all of it.
+async fn read_body(mut response: reqwest::Response) -> Result<Body, FailureReason> {
+    let mut body = Vec::new();
+    let mut downloaded_bytes = 0;
+    let mut digest = ring::digest::Context::new(&ring::digest::SHA256);
+
+    while let Some(chunk) = response.chunk().await? {
+        downloaded_bytes += chunk.len() as i64;
+        digest.update(&chunk);
+
+        let remaining = MAX_BODY_BYTES - body.len();
+        body.extend_from_slice(&chunk[..chunk.len().min(remaining)]);
+    }
+
+    Ok(Body {
+        text: String::from_utf8_lossy(&body).into_owned(),
+        downloaded_bytes,
+        sha256: hex::encode(digest.finish()),
+    })
+}
+
+fn contains_forbidden_text(body: &str, forbidden_text: &str) -> bool {
+    body.to_lowercase().contains(&forbidden_text.to_lowercase())
+}
+
+/// Makes the request configured for origins with an HTTP or HTTPS URL, revalidating with the
+/// previous response if the origin checks caching.
+pub struct HttpChecker {
+    cache_validators: ValidatorCache,
+    /// Completes the handshakes that time TLS, separately from the request itself.
+    connector: TlsConnector,
+}
+
+impl Default for HttpChecker {
+    fn default() -> Self {
+        Self {
+            cache_validators: ValidatorCache::default(),
+            connector: crate::poller::smtp::connector(),
+        }
+    }
+}
+
+impl HttpChecker {
+    /// Times the request again over a new connection, so the cost of connecting and handshaking
+    /// can be compared with the reused one, or `None` if it fails as the check itself succeeded.
+    async fn measure_cold(&self, origin: &Origin, context: &CheckContext<'_>) -> Option<i64> {
+        let uri = &origin.uri;
+
+        let (client, headers) = context
+            .http_clients
+            .prepare(origin, uri, context.family, true, context.credentials)
+            .await
+            .ok()?;
+
+        let start = Instant::now();
+        check(&client, origin, uri, context.timeout, headers)
+            .await
+            .ok()?;
+
+        Some(start.elapsed().as_millis() as i64)
+    }
+}
+
+impl Check for HttpChecker {
+    fn execute<'a>(
+        &'a self,
+        origin: &'a Origin,
+        context: &'a CheckContext<'a>,
+    ) -> BoxFuture<'a, CheckOutcome> {
+        Box::pin(async move {
+            let uri = &origin.uri;
+            let timeout = context.timeout;
+
+            let prepared = context
+                .http_clients
+                .prepare(
+                    origin,
+                    uri,
+                    context.family,
+                    origin.connection_mode == ConnectionMode::Fresh,
+                    context.credentials,
+                )
+                .await;
+
+            let checked = match prepared {
+                Ok((client, headers)) if origin.cache_validation => {
+                    cache::check(
+                        &client,
+                        origin,
+                        uri,
+                        timeout,
+                        headers,
+                        &self.cache_validators,
+                    )
+                    .await
+                }
+                Ok((client, headers)) => check(&client, origin, uri, timeout, headers).await,
+                Err(failure) => Err(failure),
+            };
+
+            match checked {
+                Ok(checked) => {
+                    let latency_millis = context.elapsed_millis();
+
+                    // Timed after the request so its latency is not held up, and not behind a
+                    // proxy as the phases would be those of reaching the proxy
+                    let timings = if context.http_clients.proxied(origin) {
+                        Timings::default()
+                    } else {
+                        timing::measure(&self.connector, uri, context.family, timeout).await
+                    };
+
+                    let cold_latency_millis = if origin.connection_mode == ConnectionMode::Both {
+                        self.measure_cold(origin, context).await
+                    } else {
+                        None
+                    };
+
+                    CheckOutcome {
+                        redirects: checked.redirects,
+                        remote_address: checked.remote_address,
+                        body_bytes: checked.body_bytes,
+                        http_version: Some(checked.http_version),
+                        captured_headers: checked.captured_headers,
+                        timings: Some(Timings {
+                            first_byte_millis: Some(checked.first_byte_millis),
+                            ..timings
+                        }),
+                        cold_latency_millis,
+                        body_hash: checked.body_hash,
+                        ..CheckOutcome::new(Ok((Some(checked.status), latency_millis)))
+                    }
+                }
+                Err(failure) => CheckOutcome {
+                    failure_detail: failure.detail,
+                    captured_headers: failure.captured_headers,
+                    remote_address: failure.remote_address,
+                    ..CheckOutcome::new(Err(failure.reason))
+                },
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+
+    use crate::poller::FailureReason;
+
+    #[test]
+    fn cache_busting_keeps_the_query_and_changes_every_time() {
+        let url = Url::parse("https://example.com/health?verbose=1").unwrap();
+
+        let mut first = url.clone();
+        super::bust_cache(&mut first);
+
+        let mut second = url;
+        super::bust_cache(&mut second);
+
+        assert!(first
+            .as_str()
+            .starts_with("https://example.com/health?verbose=1&"));
+        assert!(first
+            .query_pairs()
+            .any(|(name, _)| name == super::CACHE_BUSTING_PARAMETER));
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn forbidden_text_is_matched_case_insensitively() {
+        let body = "<h1>Down for Maintenance</h1>";
+
+        assert!(super::contains_forbidden_text(body, "down for maintenance"));
+        assert!(!super::contains_forbidden_text(body, "stack trace"));
+    }
+
+    #[test]
+    fn json_assertions_distinguish_mismatches_from_invalid_bodies() {
+        let expression = r#"$.status == "ok""#;
+
+        assert_eq!(
+            super::check_json_assertion(r#"{"status":"ok"}"#, expression),
+            Ok(())
+        );
+        assert_eq!(
+            super::check_json_assertion(r#"{"status":"down"}"#, expression),
+            Err(FailureReason::JsonAssertionFailed)
+        );
+        assert_eq!(
+            super::check_json_assertion("<html></html>", expression),
+            Err(FailureReason::InvalidBody)
+        );
+    }
+}
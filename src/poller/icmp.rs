@@ -0,0 +1,204 @@
+use std::io::{ErrorKind, Read};
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use futures_util::future::BoxFuture;
+use socket2::{Domain, Protocol, Socket, Type};
+
+use crate::persistence::Origin;
+use crate::poller::check::{Check, CheckContext, CheckOutcome};
+use crate::poller::{CheckKind, FailureReason};
+
+const ECHO_REQUEST_V4: u8 = 8;
+const ECHO_REPLY_V4: u8 = 0;
+const ECHO_REQUEST_V6: u8 = 128;
+const ECHO_REPLY_V6: u8 = 129;
+
+const PAYLOAD: &[u8; 8] = b"uptime!!";
+
+/// Sends an echo request to `host` and returns the round trip time of the reply.
+///
+/// This uses unprivileged ICMP sockets so the poller does not need to run as root, which on Linux
+/// requires its group to be within `net.ipv4.ping_group_range`.
+pub async fn ping(host: &str, timeout: Duration) -> Result<Duration, FailureReason> {
+    // IPv6 addresses are written in brackets, as they would be in a URL
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    if host.is_empty() {
+        return Err(FailureReason::BadRequest);
+    }
+
+    let address = tokio::time::timeout(timeout, tokio::net::lookup_host((host, 0)))
+        .await
+        .map_err(|_| FailureReason::RequestTimeout)?
+        .ok()
+        .and_then(|mut addresses| addresses.next())
+        .ok_or(FailureReason::ConnectionFailure)?;
+
+    let sequence = rand::random();
+
+    tokio::task::spawn_blocking(move || echo(address.ip(), sequence, timeout))
+        .await
+        .map_err(|_| FailureReason::Unknown)?
+}
+
+fn echo(ip: IpAddr, sequence: u16, timeout: Duration) -> Result<Duration, FailureReason> {
+    let (domain, protocol, request, reply) = match ip {
+        IpAddr::V4(_) => (
+            Domain::IPV4,
+            Protocol::ICMPV4,
+            ECHO_REQUEST_V4,
+            ECHO_REPLY_V4,
+        ),
+        IpAddr::V6(_) => (
+            Domain::IPV6,
+            Protocol::ICMPV6,
+            ECHO_REQUEST_V6,
+            ECHO_REPLY_V6,
+        ),
+    };
+
+    let socket = Socket::new(domain, Type::DGRAM, Some(protocol)).map_err(|e| {
+        tracing::warn!(%e, "failed to open an ICMP socket, check net.ipv4.ping_group_range");
+        FailureReason::Unknown
+    })?;
+
+    socket
+        .connect(&SocketAddr::new(ip, 0).into())
+        .map_err(|_| FailureReason::ConnectionFailure)?;
+
+    let start = Instant::now();
+
+    socket
+        .send(&echo_request(request, sequence))
+        .map_err(|_| FailureReason::ConnectionFailure)?;
+
+    let mut buffer = [0; 1024];
+
+    // Replies to other pings from this process can arrive first, so keep reading until ours does
+    loop {
+        let remaining = timeout
+            .checked_sub(start.elapsed())
+            .filter(|remaining| !remaining.is_zero())
+            .ok_or(FailureReason::RequestTimeout)?;
+
+        socket
+            .set_read_timeout(Some(remaining))
+            .map_err(|_| FailureReason::Unknown)?;
+
+        match (&socket).read(&mut buffer) {
+            Ok(length) if is_echo_reply(&buffer[..length], reply, sequence) => {
+                return Ok(start.elapsed())
+            }
+            Ok(_) => continue,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                return Err(FailureReason::RequestTimeout)
+            }
+            Err(_) => return Err(FailureReason::ConnectionFailure),
+        }
+    }
+}
+
+fn echo_request(kind: u8, sequence: u16) -> [u8; 16] {
+    let mut packet = [0; 16];
+
+    // The identifier in bytes 4 and 5 is chosen by the kernel for unprivileged sockets
+    packet[0] = kind;
+    packet[6..8].copy_from_slice(&sequence.to_be_bytes());
+    packet[8..].copy_from_slice(PAYLOAD);
+
+    let checksum = checksum(&packet);
+    packet[2..4].copy_from_slice(&checksum.to_be_bytes());
+
+    packet
+}
+
+/// Computes the Internet checksum from RFC 1071.
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|chunk| u32::from(u16::from_be_bytes([chunk[0], *chunk.get(1).unwrap_or(&0)])))
+        .sum();
+
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    !(sum as u16)
+}
+
+fn is_echo_reply(packet: &[u8], reply: u8, sequence: u16) -> bool {
+    // Some platforms include the IPv4 header when reading from datagram ICMP sockets
+    let packet = match packet.first() {
+        Some(byte) if reply == ECHO_REPLY_V4 && byte >> 4 == 4 => {
+            let header_length = usize::from(byte & 0x0f) * 4;
+            &packet[header_length.min(packet.len())..]
+        }
+        _ => packet,
+    };
+
+    packet.len() >= 8 && packet[0] == reply && packet[6..8] == sequence.to_be_bytes()
+}
+
+/// Pings origins written as `icmp://host`.
+pub struct IcmpChecker;
+
+impl Check for IcmpChecker {
+    fn execute<'a>(
+        &'a self,
+        origin: &'a Origin,
+        context: &'a CheckContext<'a>,
+    ) -> BoxFuture<'a, CheckOutcome> {
+        Box::pin(async move {
+            let CheckKind::Icmp(host) = CheckKind::from_uri(&origin.uri) else {
+                return CheckOutcome::new(Err(FailureReason::BadRequest));
+            };
+
+            // Use the round trip time rather than including the time spent resolving the host
+            let result = ping(host, context.timeout)
+                .await
+                .map(|round_trip| (None, round_trip.as_millis() as i64));
+
+            CheckOutcome::new(result)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::poller::icmp::{ECHO_REPLY_V4, ECHO_REQUEST_V4};
+
+    #[test]
+    fn checksums_match_the_rfc_example() {
+        let data = [0x00, 0x01, 0xf2, 0x03, 0xf4, 0xf5, 0xf6, 0xf7];
+
+        assert_eq!(super::checksum(&data), !0xddf2);
+    }
+
+    #[test]
+    fn echo_requests_have_valid_checksums() {
+        let packet = super::echo_request(ECHO_REQUEST_V4, 42);
+
+        assert_eq!(packet[0], ECHO_REQUEST_V4);
+        assert_eq!(super::checksum(&packet), 0);
+    }
+
+    #[test]
+    fn replies_are_matched_by_sequence() {
+        let mut reply = super::echo_request(ECHO_REPLY_V4, 42);
+        reply[0] = ECHO_REPLY_V4;
+
+        assert!(super::is_echo_reply(&reply, ECHO_REPLY_V4, 42));
+        assert!(!super::is_echo_reply(&reply, ECHO_REPLY_V4, 43));
+        assert!(!super::is_echo_reply(&reply[..4], ECHO_REPLY_V4, 42));
+    }
+
+    #[test]
+    fn ipv4_headers_are_skipped() {
+        let mut packet = vec![0x45];
+        packet.extend([0; 19]);
+        packet.extend(super::echo_request(ECHO_REPLY_V4, 7));
+
+        assert!(super::is_echo_reply(&packet, ECHO_REPLY_V4, 7));
+    }
+}
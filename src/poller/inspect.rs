@@ -0,0 +1,348 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use reqwest::Url;
+use serde::Serialize;
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{ClientConfig, ProtocolVersion, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+use crate::credentials::CredentialCipher;
+use crate::persistence::{AddressFamily, ConnectionMode, Origin};
+use crate::poller::clients::HttpClients;
+use crate::poller::http::{self, HttpFailure};
+use crate::poller::{CheckKind, FailureReason};
+
+/// The most of a response body an inspection shows.
+const PREVIEW_BYTES: usize = 64 * 1024;
+
+/// How long each stage of an inspection took.
+#[derive(Debug, Serialize)]
+pub struct Timings {
+    /// Resolving the host, unless the origin is an address.
+    pub dns_millis: Option<i64>,
+    pub connect_millis: Option<i64>,
+    pub tls_millis: Option<i64>,
+    /// From sending the request until the headers of the final response arrived, including any
+    /// redirects along the way.
+    pub headers_millis: i64,
+    pub body_millis: i64,
+    pub total_millis: i64,
+}
+
+/// What was negotiated when connecting to an origin over TLS.
+#[derive(Debug, Serialize)]
+pub struct TlsDetails {
+    pub protocol: String,
+    pub cipher_suite: String,
+    pub alpn: Option<String>,
+    /// How many certificates the server presented, including its own.
+    pub certificates: usize,
+    /// The SHA-256 fingerprint of the certificate of the server.
+    pub fingerprint: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Header {
+    pub name: String,
+    pub value: String,
+}
+
+/// Everything about a single ad-hoc request to an origin.
+#[derive(Debug, Serialize)]
+pub struct Inspection {
+    pub url: String,
+    pub redirects: Option<String>,
+    pub status: u16,
+    pub version: String,
+    pub remote_address: Option<String>,
+    pub headers: Vec<Header>,
+    /// Up to [`PREVIEW_BYTES`] of the body.
+    pub body: String,
+    pub body_truncated: bool,
+    pub timings: Timings,
+    pub tls: Option<TlsDetails>,
+    /// Proxies make their own connections, so connections cannot be broken down through them.
+    pub proxied: bool,
+    /// Why the connection could not be broken down, such as a failed TLS handshake.
+    pub connection_error: Option<String>,
+}
+
+/// How connecting to an origin went, measured on a connection of its own as the client does not
+/// expose the stages of its connections.
+#[derive(Default)]
+struct Connection {
+    dns_millis: Option<i64>,
+    connect_millis: Option<i64>,
+    tls_millis: Option<i64>,
+    tls: Option<TlsDetails>,
+}
+
+/// Makes ad-hoc requests to origins the same way the poller checks them, for debugging them.
+pub struct Inspector {
+    clients: Arc<HttpClients>,
+    credentials: Option<CredentialCipher>,
+    default_timeout: Duration,
+    connector: TlsConnector,
+}
+
+impl Inspector {
+    pub(super) fn new(
+        clients: Arc<HttpClients>,
+        credentials: Option<CredentialCipher>,
+        default_timeout: Duration,
+    ) -> Self {
+        Self {
+            clients,
+            credentials,
+            default_timeout,
+            connector: connector(),
+        }
+    }
+
+    /// Makes the request configured for an origin, following redirects as its policy allows but
+    /// without checking the response against its configuration.
+    pub async fn inspect(&self, origin: &Origin) -> Result<Inspection, HttpFailure> {
+        let CheckKind::Http(uri) = CheckKind::from_uri(&origin.uri) else {
+            return Err(HttpFailure {
+                reason: FailureReason::BadRequest,
+                detail: Some(String::from("only HTTP origins can be inspected")),
+                captured_headers: None,
+                remote_address: None,
+            });
+        };
+
+        let timeout = origin
+            .timeout_millis
+            .map_or(self.default_timeout, |millis| {
+                Duration::from_millis(millis as u64)
+            });
+
+        // Checking both families separately is for the poller, one request is enough here
+        let family = match origin.address_family {
+            AddressFamily::Both => AddressFamily::Any,
+            family => family,
+        };
+
+        let proxied = self.clients.proxied(origin);
+        let url = Url::parse(uri).map_err(|_| FailureReason::BadRequest)?;
+
+        let (connection, connection_error) = if proxied {
+            (Connection::default(), None)
+        } else {
+            match tokio::time::timeout(timeout, self.connect(&url, family)).await {
+                Ok(Ok(connection)) => (connection, None),
+                Ok(Err(e)) => (Connection::default(), Some(e)),
+                Err(_) => (
+                    Connection::default(),
+                    Some(String::from("timed out while connecting")),
+                ),
+            }
+        };
+
+        let (client, headers) = self
+            .clients
+            .prepare(
+                origin,
+                uri,
+                family,
+                origin.connection_mode == ConnectionMode::Fresh,
+                self.credentials.as_ref(),
+            )
+            .await?;
+
+        let start = Instant::now();
+        let (mut response, redirects) = http::send(&client, origin, uri, timeout, headers).await?;
+        let headers_millis = start.elapsed().as_millis() as i64;
+
+        let response_headers = response
+            .headers()
+            .iter()
+            .map(|(name, value)| Header {
+                name: name.to_string(),
+                value: String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            })
+            .collect();
+
+        let status = response.status().as_u16();
+        let version = http::version_name(response.version()).to_owned();
+        let remote_address = response.remote_addr().map(|addr| addr.ip().to_string());
+        let final_url = response.url().to_string();
+
+        let mut body = Vec::new();
+        let mut body_truncated = false;
+
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| HttpFailure::from(FailureReason::from(e)))?
+        {
+            let remaining = PREVIEW_BYTES - body.len();
+
+            // Stop rather than downloading a body nobody will see
+            if chunk.len() > remaining {
+                body.extend_from_slice(&chunk[..remaining]);
+                body_truncated = true;
+                break;
+            }
+
+            body.extend_from_slice(&chunk);
+        }
+
+        let total_millis = start.elapsed().as_millis() as i64;
+
+        Ok(Inspection {
+            url: final_url,
+            redirects: redirects.map(|redirects| redirects.chain),
+            status,
+            version,
+            remote_address,
+            headers: response_headers,
+            body: String::from_utf8_lossy(&body).into_owned(),
+            body_truncated,
+            timings: Timings {
+                dns_millis: connection.dns_millis,
+                connect_millis: connection.connect_millis,
+                tls_millis: connection.tls_millis,
+                headers_millis,
+                body_millis: total_millis - headers_millis,
+                total_millis,
+            },
+            tls: connection.tls,
+            proxied,
+            connection_error,
+        })
+    }
+
+    /// Resolves the host of `url`, connects to it over `family` and performs a TLS handshake if
+    /// it uses HTTPS, timing each stage.
+    async fn connect(&self, url: &Url, family: AddressFamily) -> Result<Connection, String> {
+        let host = url
+            .host_str()
+            .ok_or_else(|| String::from("the URI has no host"))?
+            .trim_start_matches('[')
+            .trim_end_matches(']');
+
+        let port = url
+            .port_or_known_default()
+            .ok_or_else(|| String::from("the URI has no port"))?;
+
+        let mut connection = Connection::default();
+
+        let addr = match host.parse() {
+            Ok(ip) => SocketAddr::new(ip, port),
+            Err(_) => {
+                let start = Instant::now();
+                let addr = tokio::net::lookup_host((host, port))
+                    .await
+                    .map_err(|e| format!("failed to resolve {host}: {e}"))?
+                    .find(|addr| family.allows(addr.ip()))
+                    .ok_or_else(|| format!("{host} has no {} addresses", family.name()))?;
+
+                connection.dns_millis = Some(start.elapsed().as_millis() as i64);
+                addr
+            }
+        };
+
+        let start = Instant::now();
+        let stream = TcpStream::connect(addr)
+            .await
+            .map_err(|e| format!("failed to connect to {addr}: {e}"))?;
+
+        connection.connect_millis = Some(start.elapsed().as_millis() as i64);
+
+        if url.scheme() != "https" {
+            return Ok(connection);
+        }
+
+        let name = ServerName::try_from(host.to_owned())
+            .map_err(|e| format!("{host} is not a valid server name: {e}"))?;
+
+        let start = Instant::now();
+        let stream = self
+            .connector
+            .connect(name, stream)
+            .await
+            .map_err(|e| format!("the TLS handshake failed: {e}"))?;
+
+        connection.tls_millis = Some(start.elapsed().as_millis() as i64);
+
+        let (_, session) = stream.get_ref();
+        let certificates = session.peer_certificates().unwrap_or_default();
+
+        connection.tls = Some(TlsDetails {
+            protocol: session
+                .protocol_version()
+                .map(protocol_name)
+                .unwrap_or_default(),
+            cipher_suite: session
+                .negotiated_cipher_suite()
+                .map(|suite| format!("{:?}", suite.suite()))
+                .unwrap_or_default(),
+            alpn: session
+                .alpn_protocol()
+                .map(|protocol| String::from_utf8_lossy(protocol).into_owned()),
+            certificates: certificates.len(),
+            fingerprint: certificates
+                .first()
+                .map(|certificate| fingerprint(certificate)),
+        });
+
+        Ok(connection)
+    }
+}
+
+fn protocol_name(version: ProtocolVersion) -> String {
+    match version {
+        ProtocolVersion::TLSv1_2 => String::from("TLS 1.2"),
+        ProtocolVersion::TLSv1_3 => String::from("TLS 1.3"),
+        other => format!("{other:?}"),
+    }
+}
+
+/// Formats the SHA-256 digest of `bytes` as colon separated pairs of hex digits, the way browsers
+/// show certificate fingerprints.
+fn fingerprint(bytes: &[u8]) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA256, bytes);
+
+    hex::encode_upper(digest)
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| String::from_utf8_lossy(pair))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Builds a TLS connector that offers the same protocols as the client checks are made with, so
+/// the negotiated one is what a check would use.
+fn connector() -> TlsConnector {
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+
+    let provider = tokio_rustls::rustls::crypto::ring::default_provider();
+    let mut config = ClientConfig::builder_with_provider(Arc::new(provider))
+        .with_safe_default_protocol_versions()
+        .expect("failed to configure the inspection TLS protocol versions")
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    TlsConnector::from(Arc::new(config))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn fingerprints_are_colon_separated() {
+        let fingerprint = super::fingerprint(b"");
+
+        assert_eq!(
+            fingerprint,
+            "E3:B0:C4:42:98:FC:1C:14:9A:FB:F4:C8:99:6F:B9:24:27:AE:41:E4:64:9B:93:4C:A4:95:99:1B:78:52:B8:55"
+        );
+    }
+}
@@ -0,0 +1,465 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use color_eyre::eyre::{eyre, Result};
+use futures_util::future::BoxFuture;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE, COOKIE, SET_COOKIE};
+use serde_json::Value;
+
+use crate::persistence::{JourneyStep, Origin};
+use crate::poller::assertion::JsonAssertion;
+use crate::poller::check::{Check, CheckContext, CheckOutcome};
+use crate::poller::status::ExpectedStatus;
+use crate::poller::FailureReason;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Source {
+    Json(JsonAssertion),
+    Header(HeaderName),
+}
+
+/// Saves part of a step's response for later steps to use as `{{name}}`, written as
+/// `token = $.access_token` for a value in a JSON body or `csrf = header:X-CSRF-Token` for a
+/// header.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Extraction {
+    name: String,
+    source: Source,
+}
+
+impl Extraction {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let (name, source) = expression.split_once('=').ok_or_else(|| {
+            eyre!("extractions are written as name = $.path or name = header:Name")
+        })?;
+
+        let name = name.trim();
+
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(eyre!(
+                "'{name}' is not a valid name, use letters, digits and underscores"
+            ));
+        }
+
+        let source = source.trim();
+
+        let source = match source.strip_prefix("header:") {
+            Some(header) => {
+                let header = header.trim();
+
+                Source::Header(
+                    HeaderName::from_bytes(header.as_bytes())
+                        .map_err(|_| eyre!("'{header}' is not a header name"))?,
+                )
+            }
+            None => {
+                let path = JsonAssertion::parse(source)?;
+
+                if !path.is_path() {
+                    return Err(eyre!("extractions take a path without a comparison"));
+                }
+
+                Source::Json(path)
+            }
+        };
+
+        Ok(Self {
+            name: name.to_owned(),
+            source,
+        })
+    }
+
+    /// Finds the value in a response, using strings as they are and other JSON values as JSON.
+    fn extract(&self, headers: &HeaderMap, body: &str) -> Option<String> {
+        match &self.source {
+            Source::Header(name) => headers.get(name)?.to_str().ok().map(str::to_owned),
+            Source::Json(path) => {
+                let document: Value = serde_json::from_str(body).ok()?;
+
+                match path.select(&document)? {
+                    Value::String(value) => Some(value.clone()),
+                    value => Some(value.to_string()),
+                }
+            }
+        }
+    }
+}
+
+/// Replaces each `{{name}}` with the value extracted under that name, returning the name of the
+/// first one that has not been extracted.
+fn substitute(template: &str, variables: &HashMap<String, String>) -> Result<String, String> {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+
+        let Some(end) = after.find("}}") else {
+            output.push_str(&rest[start..]);
+            return Ok(output);
+        };
+
+        let name = after[..end].trim();
+        let value = variables.get(name).ok_or_else(|| name.to_owned())?;
+
+        output.push_str(value);
+        rest = &after[end + 2..];
+    }
+
+    output.push_str(rest);
+
+    Ok(output)
+}
+
+/// Parses headers written one per line as `Name: value`, ignoring blank lines.
+fn parse_headers(text: &str) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| eyre!("'{line}' is not written as Name: value"))?;
+
+        let name = HeaderName::from_bytes(name.trim().as_bytes())
+            .map_err(|_| eyre!("'{}' is not a header name", name.trim()))?;
+        let value = HeaderValue::from_str(value.trim())
+            .map_err(|_| eyre!("the value of {name} cannot be sent in a header"))?;
+
+        headers.append(name, value);
+    }
+
+    Ok(headers)
+}
+
+/// The cookies set by earlier steps, which are sent with every later one.
+///
+/// Journeys are expected to stay on one site, so the domain and path of cookies are not checked.
+#[derive(Debug, Default)]
+struct CookieJar {
+    cookies: BTreeMap<String, String>,
+}
+
+impl CookieJar {
+    fn store(&mut self, headers: &HeaderMap) {
+        for header in headers.get_all(SET_COOKIE) {
+            let Ok(header) = header.to_str() else {
+                continue;
+            };
+
+            let mut parts = header.split(';');
+            let pair = parts.next().unwrap_or_default();
+
+            let Some((name, value)) = pair.split_once('=') else {
+                continue;
+            };
+
+            let (name, value) = (name.trim(), value.trim());
+
+            // Servers clear cookies by setting them to expire straight away
+            let cleared = value.is_empty()
+                || parts.any(|attribute| match attribute.split_once('=') {
+                    Some((key, age)) => {
+                        key.trim().eq_ignore_ascii_case("max-age")
+                            && age.trim().parse::<i64>().is_ok_and(|age| age <= 0)
+                    }
+                    None => false,
+                });
+
+            if cleared {
+                self.cookies.remove(name);
+            } else {
+                self.cookies.insert(name.to_owned(), value.to_owned());
+            }
+        }
+    }
+
+    fn header(&self) -> Option<String> {
+        let pairs: Vec<_> = self
+            .cookies
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect();
+
+        (!pairs.is_empty()).then(|| pairs.join("; "))
+    }
+}
+
+/// Checks that a step can be made, other than any values it uses not having been extracted.
+pub fn validate_step(step: &JourneyStep) -> Result<()> {
+    if step.request_body.is_some() && !step.method.allows_body() {
+        return Err(eyre!(
+            "{} requests cannot have a body",
+            step.method.as_str()
+        ));
+    }
+
+    if let Some(request_headers) = &step.request_headers {
+        parse_headers(request_headers)?;
+    }
+
+    if let Some(expected_status) = &step.expected_status {
+        ExpectedStatus::parse(expected_status)?;
+    }
+
+    if let Some(extraction) = &step.extraction {
+        Extraction::parse(extraction)?;
+    }
+
+    Ok(())
+}
+
+/// Why a journey failed, naming the step that failed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct JourneyFailure {
+    pub reason: FailureReason,
+    pub detail: String,
+}
+
+/// Makes each step of a journey in turn, returning the status of the last one.
+///
+/// Steps do not follow redirects, so cookies set by a redirecting response are kept. A step passes
+/// if it responds with one of its expected status codes, or anything below 400 otherwise.
+pub async fn run(
+    client: &reqwest::Client,
+    steps: &[JourneyStep],
+    timeout: Duration,
+) -> Result<u16, JourneyFailure> {
+    if steps.is_empty() {
+        return Err(JourneyFailure {
+            reason: FailureReason::BadRequest,
+            detail: String::from("the journey has no steps"),
+        });
+    }
+
+    let mut variables = HashMap::new();
+    let mut cookies = CookieJar::default();
+    let mut status = 0;
+
+    for (i, step) in steps.iter().enumerate() {
+        status = make(client, step, &mut variables, &mut cookies, timeout)
+            .await
+            .map_err(|(reason, detail)| JourneyFailure {
+                reason,
+                detail: format!(
+                    "step {} ({} {}): {detail}",
+                    i + 1,
+                    step.method.as_str(),
+                    step.url
+                ),
+            })?;
+    }
+
+    Ok(status)
+}
+
+async fn make(
+    client: &reqwest::Client,
+    step: &JourneyStep,
+    variables: &mut HashMap<String, String>,
+    cookies: &mut CookieJar,
+    timeout: Duration,
+) -> Result<u16, (FailureReason, String)> {
+    let fill = |template: &str| {
+        substitute(template, variables).map_err(|name| {
+            (
+                FailureReason::BadRequest,
+                format!("nothing was extracted as {name} by an earlier step"),
+            )
+        })
+    };
+
+    let invalid = |e: color_eyre::eyre::Report| (FailureReason::BadRequest, e.to_string());
+
+    let url = fill(&step.url)?;
+
+    let mut headers = match &step.request_headers {
+        Some(request_headers) => parse_headers(&fill(request_headers)?).map_err(invalid)?,
+        None => HeaderMap::new(),
+    };
+
+    if let Some(cookie) = cookies
+        .header()
+        .and_then(|c| HeaderValue::from_str(&c).ok())
+    {
+        headers.insert(COOKIE, cookie);
+    }
+
+    if let Some(content_type) = &step.request_content_type {
+        let content_type = HeaderValue::from_str(content_type)
+            .map_err(|_| invalid(eyre!("'{content_type}' is not a valid content type")))?;
+
+        headers.insert(CONTENT_TYPE, content_type);
+    }
+
+    let mut request = client
+        .request(step.method.into(), &url)
+        .headers(headers)
+        .timeout(timeout);
+
+    if let Some(body) = &step.request_body {
+        request = request.body(fill(body)?);
+    }
+
+    let response = request.send().await.map_err(|e| {
+        let detail = e.to_string();
+        (FailureReason::from(e), detail)
+    })?;
+
+    cookies.store(response.headers());
+
+    let status = response.status().as_u16();
+    let headers = response.headers().clone();
+
+    let expected = match &step.expected_status {
+        Some(expected_status) => ExpectedStatus::parse(expected_status)
+            .map_err(invalid)?
+            .contains(status),
+        None => status < 400,
+    };
+
+    if !expected {
+        return Err((
+            FailureReason::UnexpectedStatus,
+            format!("responded {status}"),
+        ));
+    }
+
+    if let Some(expression) = &step.extraction {
+        let extraction = Extraction::parse(expression).map_err(invalid)?;
+
+        let body = response.text().await.map_err(|e| {
+            (
+                FailureReason::InvalidBody,
+                format!("could not read the body: {e}"),
+            )
+        })?;
+
+        let value = extraction.extract(&headers, &body).ok_or_else(|| {
+            (
+                FailureReason::InvalidBody,
+                format!("could not extract '{expression}' from the response"),
+            )
+        })?;
+
+        variables.insert(extraction.name, value);
+    }
+
+    Ok(status)
+}
+
+/// Makes the sequence of requests of origins written as `journey://name`.
+pub struct JourneyChecker;
+
+impl Check for JourneyChecker {
+    fn execute<'a>(
+        &'a self,
+        origin: &'a Origin,
+        context: &'a CheckContext<'a>,
+    ) -> BoxFuture<'a, CheckOutcome> {
+        Box::pin(async move {
+            let steps =
+                crate::persistence::fetch_journey_steps(context.pool, origin.origin_uid).await;
+
+            let steps = match steps {
+                Ok(steps) => steps,
+                Err(e) => {
+                    tracing::warn!(%e, "failed to fetch journey steps");
+                    return CheckOutcome::new(Err(FailureReason::Unknown));
+                }
+            };
+
+            match run(context.http_clients.shared(), &steps, context.timeout).await {
+                Ok(status) => CheckOutcome::new(Ok((Some(status), context.elapsed_millis()))),
+                Err(failure) => CheckOutcome::failed(failure.reason, failure.detail),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use color_eyre::eyre::Result;
+    use reqwest::header::{HeaderMap, HeaderValue, SET_COOKIE};
+
+    use crate::poller::journey::{CookieJar, Extraction};
+
+    #[test]
+    fn values_are_extracted_from_json_bodies_and_headers() -> Result<()> {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-csrf-token", HeaderValue::from_static("abc123"));
+
+        let body = r#"{"access_token": "secret", "user": {"id": 42}}"#;
+
+        let token = Extraction::parse("token = $.access_token")?;
+        assert_eq!(token.extract(&headers, body).as_deref(), Some("secret"));
+
+        let id = Extraction::parse("user_id=$.user.id")?;
+        assert_eq!(id.extract(&headers, body).as_deref(), Some("42"));
+
+        let csrf = Extraction::parse("csrf = header:X-CSRF-Token")?;
+        assert_eq!(csrf.extract(&headers, body).as_deref(), Some("abc123"));
+
+        let missing = Extraction::parse("missing = $.refresh_token")?;
+        assert_eq!(missing.extract(&headers, body), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_extractions_are_rejected() {
+        assert!(Extraction::parse("$.access_token").is_err());
+        assert!(Extraction::parse("access token = $.access_token").is_err());
+        assert!(Extraction::parse("token = $.status == \"ok\"").is_err());
+        assert!(Extraction::parse("token = header:Not A Header").is_err());
+    }
+
+    #[test]
+    fn extracted_values_are_substituted() {
+        let variables = HashMap::from([(String::from("token"), String::from("secret"))]);
+
+        assert_eq!(
+            super::substitute("Authorization: Bearer {{ token }}", &variables),
+            Ok(String::from("Authorization: Bearer secret"))
+        );
+        assert_eq!(
+            super::substitute("{{token}}{{token}}", &variables),
+            Ok(String::from("secretsecret"))
+        );
+        assert_eq!(
+            super::substitute("left {{ open", &variables),
+            Ok(String::from("left {{ open"))
+        );
+        assert_eq!(
+            super::substitute("{{ session }}", &variables),
+            Err(String::from("session"))
+        );
+    }
+
+    #[test]
+    fn cookies_are_kept_until_cleared() {
+        let mut jar = CookieJar::default();
+
+        let mut headers = HeaderMap::new();
+        headers.append(
+            SET_COOKIE,
+            HeaderValue::from_static("session=abc; Path=/; HttpOnly"),
+        );
+        headers.append(SET_COOKIE, HeaderValue::from_static("theme=dark"));
+        jar.store(&headers);
+
+        assert_eq!(jar.header().as_deref(), Some("session=abc; theme=dark"));
+
+        let mut headers = HeaderMap::new();
+        headers.append(SET_COOKIE, HeaderValue::from_static("session=; Max-Age=0"));
+        headers.append(
+            SET_COOKIE,
+            HeaderValue::from_static("theme=light; Max-Age=0"),
+        );
+        jar.store(&headers);
+
+        assert_eq!(jar.header(), None);
+    }
+}
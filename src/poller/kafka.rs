@@ -0,0 +1,294 @@
+use color_eyre::eyre::{eyre, Result};
+use reqwest::Url;
+use sqlx::types::chrono::Utc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+const PRODUCE_API_KEY: i16 = 0;
+/// The oldest version of the produce API that Kafka 4 still accepts.
+const PRODUCE_API_VERSION: i16 = 3;
+const CLIENT_ID: &str = "uptime";
+/// Wait for the partition leader to write the record, but not for it to be replicated.
+const ACKS: i16 = 1;
+const TIMEOUT_MILLIS: i32 = 5000;
+
+/// A minimal Kafka producer, opened on first use and reopened after errors.
+///
+/// Records are written to partition 0 of each topic on the configured broker, which must be the
+/// leader of that partition. This covers single broker setups without pulling in a full client.
+pub struct KafkaConnection {
+    pub(super) address: String,
+    stream: Option<TcpStream>,
+    correlation_id: i32,
+}
+
+impl KafkaConnection {
+    /// Parses `kafka://host:port/prefix`, returning the connection and the topic prefix.
+    pub fn from_url(url: &Url) -> Result<(Self, String)> {
+        let host = url
+            .host_str()
+            .ok_or_else(|| eyre!("Kafka URLs need a host"))?;
+        let port = url.port().unwrap_or(9092);
+        let prefix = url.path().trim_start_matches('/').replace('/', ".");
+
+        let valid = |c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-');
+
+        if prefix.is_empty() || !prefix.chars().all(valid) {
+            return Err(eyre!(
+                "Kafka URLs need a topic prefix, such as kafka://localhost:9092/uptime"
+            ));
+        }
+
+        let connection = Self {
+            address: format!("{host}:{port}"),
+            stream: None,
+            correlation_id: 0,
+        };
+
+        Ok((connection, prefix))
+    }
+
+    pub async fn produce(&mut self, topic: &str, key: &[u8], value: &[u8]) -> Result<()> {
+        if self.stream.is_none() {
+            self.stream = Some(TcpStream::connect(&self.address).await?);
+        }
+
+        self.correlation_id = self.correlation_id.wrapping_add(1);
+
+        let batch = record_batch(key, value, Utc::now().timestamp_millis());
+        let request = produce_request(self.correlation_id, topic, &batch);
+
+        let stream = self
+            .stream
+            .as_mut()
+            .expect("connection was just established");
+        let result = exchange(stream, &request, self.correlation_id).await;
+
+        // Reconnect on the next record rather than reusing a connection in an unknown state
+        if result.is_err() {
+            self.stream = None;
+        }
+
+        result
+    }
+}
+
+async fn exchange(stream: &mut TcpStream, request: &[u8], correlation_id: i32) -> Result<()> {
+    stream.write_all(request).await?;
+
+    let length = stream.read_i32().await?;
+    let mut response = vec![0; usize::try_from(length)?];
+    stream.read_exact(&mut response).await?;
+
+    let error_code = parse_produce_response(&response, correlation_id)?;
+
+    if error_code != 0 {
+        return Err(eyre!(
+            "Kafka rejected the record with error code {error_code}"
+        ));
+    }
+
+    Ok(())
+}
+
+fn put_string(buffer: &mut Vec<u8>, value: &str) {
+    buffer.extend_from_slice(&(value.len() as i16).to_be_bytes());
+    buffer.extend_from_slice(value.as_bytes());
+}
+
+fn put_varint(buffer: &mut Vec<u8>, value: i64) {
+    let mut zigzag = ((value << 1) ^ (value >> 63)) as u64;
+
+    while zigzag >= 0x80 {
+        buffer.push((zigzag as u8) | 0x80);
+        zigzag >>= 7;
+    }
+
+    buffer.push(zigzag as u8);
+}
+
+/// The Castagnoli CRC used to checksum record batches.
+fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0_u32;
+
+    for &byte in data {
+        crc ^= u32::from(byte);
+
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0x82f6_3b78
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    !crc
+}
+
+/// Encodes a version 2 record batch holding a single record.
+fn record_batch(key: &[u8], value: &[u8], timestamp: i64) -> Vec<u8> {
+    let mut record = Vec::new();
+    record.push(0); // attributes
+    put_varint(&mut record, 0); // timestamp delta
+    put_varint(&mut record, 0); // offset delta
+    put_varint(&mut record, key.len() as i64);
+    record.extend_from_slice(key);
+    put_varint(&mut record, value.len() as i64);
+    record.extend_from_slice(value);
+    put_varint(&mut record, 0); // headers
+
+    // Everything covered by the checksum, from the attributes onwards
+    let mut checked = Vec::new();
+    checked.extend_from_slice(&0_i16.to_be_bytes()); // attributes
+    checked.extend_from_slice(&0_i32.to_be_bytes()); // last offset delta
+    checked.extend_from_slice(&timestamp.to_be_bytes());
+    checked.extend_from_slice(&timestamp.to_be_bytes());
+    checked.extend_from_slice(&(-1_i64).to_be_bytes()); // producer id
+    checked.extend_from_slice(&(-1_i16).to_be_bytes()); // producer epoch
+    checked.extend_from_slice(&(-1_i32).to_be_bytes()); // base sequence
+    checked.extend_from_slice(&1_i32.to_be_bytes());
+    put_varint(&mut checked, record.len() as i64);
+    checked.extend_from_slice(&record);
+
+    let mut batch = Vec::new();
+    batch.extend_from_slice(&0_i64.to_be_bytes()); // base offset
+                                                   // The batch length counts everything after itself: the leader epoch, magic and checksum
+    batch.extend_from_slice(&((4 + 1 + 4 + checked.len()) as i32).to_be_bytes());
+    batch.extend_from_slice(&(-1_i32).to_be_bytes()); // partition leader epoch
+    batch.push(2); // magic
+    batch.extend_from_slice(&crc32c(&checked).to_be_bytes());
+    batch.extend_from_slice(&checked);
+
+    batch
+}
+
+fn produce_request(correlation_id: i32, topic: &str, batch: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&PRODUCE_API_KEY.to_be_bytes());
+    body.extend_from_slice(&PRODUCE_API_VERSION.to_be_bytes());
+    body.extend_from_slice(&correlation_id.to_be_bytes());
+    put_string(&mut body, CLIENT_ID);
+    body.extend_from_slice(&(-1_i16).to_be_bytes()); // no transactional id
+    body.extend_from_slice(&ACKS.to_be_bytes());
+    body.extend_from_slice(&TIMEOUT_MILLIS.to_be_bytes());
+    body.extend_from_slice(&1_i32.to_be_bytes()); // topics
+    put_string(&mut body, topic);
+    body.extend_from_slice(&1_i32.to_be_bytes()); // partitions
+    body.extend_from_slice(&0_i32.to_be_bytes()); // partition index
+    body.extend_from_slice(&(batch.len() as i32).to_be_bytes());
+    body.extend_from_slice(batch);
+
+    let mut request = (body.len() as i32).to_be_bytes().to_vec();
+    request.extend_from_slice(&body);
+
+    request
+}
+
+/// Returns the error code for the single partition written to.
+fn parse_produce_response(response: &[u8], correlation_id: i32) -> Result<i16> {
+    let invalid = || eyre!("received an invalid produce response from Kafka");
+
+    let i32_at = |at: usize| -> Result<i32> {
+        let bytes = response.get(at..at + 4).ok_or_else(invalid)?;
+        Ok(i32::from_be_bytes(bytes.try_into()?))
+    };
+
+    let i16_at = |at: usize| -> Result<i16> {
+        let bytes = response.get(at..at + 2).ok_or_else(invalid)?;
+        Ok(i16::from_be_bytes(bytes.try_into()?))
+    };
+
+    if i32_at(0)? != correlation_id {
+        return Err(eyre!("received a response to a different Kafka request"));
+    }
+
+    if i32_at(4)? != 1 {
+        return Err(invalid());
+    }
+
+    let topic_length = usize::try_from(i16_at(8)?).map_err(|_| invalid())?;
+    let partitions_at = 10 + topic_length;
+
+    if i32_at(partitions_at)? != 1 {
+        return Err(invalid());
+    }
+
+    // Skip the partition index to reach its error code
+    i16_at(partitions_at + 8)
+}
+
+#[cfg(test)]
+mod tests {
+    use color_eyre::eyre::Result;
+    use reqwest::Url;
+
+    use crate::poller::kafka::KafkaConnection;
+
+    #[test]
+    fn crc32c_matches_the_reference_value() {
+        assert_eq!(super::crc32c(b"123456789"), 0xe306_9283);
+    }
+
+    #[test]
+    fn varints_are_zigzag_encoded() {
+        let encode = |value| {
+            let mut buffer = Vec::new();
+            super::put_varint(&mut buffer, value);
+            buffer
+        };
+
+        assert_eq!(encode(0), [0x00]);
+        assert_eq!(encode(-1), [0x01]);
+        assert_eq!(encode(1), [0x02]);
+        assert_eq!(encode(64), [0x80, 0x01]);
+        assert_eq!(encode(300), [0xd8, 0x04]);
+    }
+
+    #[test]
+    fn record_batches_describe_their_length_and_checksum() {
+        let batch = super::record_batch(b"key", b"value", 1_700_000_000_000);
+
+        let length = i32::from_be_bytes(batch[8..12].try_into().unwrap());
+        assert_eq!(length as usize, batch.len() - 12);
+
+        // The magic byte follows the partition leader epoch
+        assert_eq!(batch[16], 2);
+
+        let crc = u32::from_be_bytes(batch[17..21].try_into().unwrap());
+        assert_eq!(crc, super::crc32c(&batch[21..]));
+    }
+
+    #[test]
+    fn produce_responses_report_partition_errors() -> Result<()> {
+        let mut response = Vec::new();
+        response.extend_from_slice(&7_i32.to_be_bytes());
+        response.extend_from_slice(&1_i32.to_be_bytes());
+        response.extend_from_slice(&6_i16.to_be_bytes());
+        response.extend_from_slice(b"uptime");
+        response.extend_from_slice(&1_i32.to_be_bytes());
+        response.extend_from_slice(&0_i32.to_be_bytes());
+        response.extend_from_slice(&6_i16.to_be_bytes()); // NOT_LEADER_OR_FOLLOWER
+        response.extend_from_slice(&[0; 20]);
+
+        assert_eq!(super::parse_produce_response(&response, 7)?, 6);
+        assert!(super::parse_produce_response(&response, 8).is_err());
+        assert!(super::parse_produce_response(&response[..12], 7).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn topic_prefixes_are_taken_from_the_path() -> Result<()> {
+        let (connection, prefix) =
+            KafkaConnection::from_url(&Url::parse("kafka://broker/uptime/events")?)?;
+
+        assert_eq!(connection.address, "broker:9092");
+        assert_eq!(prefix, "uptime.events");
+
+        assert!(KafkaConnection::from_url(&Url::parse("kafka://broker:9092")?).is_err());
+        assert!(KafkaConnection::from_url(&Url::parse("kafka://broker/up%20time")?).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,379 @@
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use reqwest::Url;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufStream};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::TlsConnector;
+
+use crate::persistence::Origin;
+use crate::poller::check::{Check, CheckContext, CheckOutcome};
+use crate::poller::protocol::{host_and_port, ProtocolFailure};
+use crate::poller::{clients, smtp, FailureReason};
+
+/// The longest reply line that will be read, far above the short replies these checks receive.
+const MAX_LINE_BYTES: u64 = 4096;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Protocol {
+    Redis,
+    Memcached,
+}
+
+/// A cache server written as `redis://host:port`, `rediss://host:port` or `memcached://host:port`,
+/// defaulting to ports 6379, 6379 and 11211 respectively.
+///
+/// Redis servers are sent `PING` and Memcached servers are asked for their version, after logging
+/// in with the credentials of the origin if it has any.
+#[derive(Debug, PartialEq, Eq)]
+pub struct KeyValueCheck {
+    host: String,
+    port: u16,
+    protocol: Protocol,
+    tls: bool,
+}
+
+impl KeyValueCheck {
+    pub fn parse(uri: &str) -> Option<Self> {
+        let url = Url::parse(uri).ok()?;
+
+        let (protocol, tls, default_port) = match url.scheme() {
+            "redis" => (Protocol::Redis, false, 6379),
+            "rediss" => (Protocol::Redis, true, 6379),
+            "memcached" => (Protocol::Memcached, false, 11211),
+            _ => return None,
+        };
+
+        if url.query().is_some() {
+            return None;
+        }
+
+        let (host, port) = host_and_port(&url, default_port)?;
+
+        Some(Self {
+            host,
+            port,
+            protocol,
+            tls,
+        })
+    }
+}
+
+/// Encodes a Redis command as an array of bulk strings, which every version accepts.
+fn redis_command(arguments: &[&str]) -> String {
+    let mut command = format!("*{}\r\n", arguments.len());
+
+    for argument in arguments {
+        command.push_str(&format!("${}\r\n{argument}\r\n", argument.len()));
+    }
+
+    command
+}
+
+/// Explains an error reply from Redis, such as `-NOAUTH Authentication required.`.
+fn redis_error(line: &str, answering: &str) -> ProtocolFailure {
+    let error = line.trim_start_matches('-');
+    let code = error.split_whitespace().next().unwrap_or_default();
+
+    let reason = match code {
+        "NOAUTH" | "WRONGPASS" | "NOPERM" => FailureReason::AuthenticationFailed,
+        // Servers loading their dataset or catching up with a primary cannot answer yet
+        "LOADING" | "MASTERDOWN" | "BUSY" => FailureReason::NotServing,
+        _ => FailureReason::UnexpectedStatus,
+    };
+
+    ProtocolFailure::new(reason, format!("answered {answering} with {error}"))
+}
+
+struct Session<S> {
+    stream: BufStream<S>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Session<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            stream: BufStream::new(stream),
+        }
+    }
+
+    async fn send(&mut self, command: &str) -> Result<String, ProtocolFailure> {
+        self.stream
+            .write_all(command.as_bytes())
+            .await
+            .map_err(ProtocolFailure::disconnected)?;
+
+        self.stream
+            .flush()
+            .await
+            .map_err(ProtocolFailure::disconnected)?;
+
+        let mut line = String::new();
+
+        let length = (&mut self.stream)
+            .take(MAX_LINE_BYTES)
+            .read_line(&mut line)
+            .await
+            .map_err(ProtocolFailure::disconnected)?;
+
+        if length == 0 {
+            return Err(ProtocolFailure::new(
+                FailureReason::ConnectionFailure,
+                String::from("connection was closed by the server"),
+            ));
+        }
+
+        Ok(line.trim_end().to_owned())
+    }
+
+    async fn redis(mut self, login: Option<&(String, String)>) -> Result<(), ProtocolFailure> {
+        if let Some((username, password)) = login {
+            // Servers without users only take a password, which is the same as the default user
+            let command = match username.as_str() {
+                "" | "default" => redis_command(&["AUTH", password]),
+                username => redis_command(&["AUTH", username, password]),
+            };
+
+            let reply = self.send(&command).await?;
+
+            // Any error logging in means the login was not accepted
+            if reply != "+OK" {
+                return Err(ProtocolFailure {
+                    reason: FailureReason::AuthenticationFailed,
+                    ..redis_error(&reply, "AUTH")
+                });
+            }
+        }
+
+        let reply = self.send(&redis_command(&["PING"])).await?;
+
+        if reply.starts_with('-') {
+            return Err(redis_error(&reply, "PING"));
+        }
+
+        if reply != "+PONG" {
+            return Err(ProtocolFailure::new(
+                FailureReason::InvalidBody,
+                format!("answered PING with {reply} instead of PONG"),
+            ));
+        }
+
+        // Ends the session politely, ignoring servers that hang up without answering
+        let _ = self.send(&redis_command(&["QUIT"])).await;
+
+        Ok(())
+    }
+
+    async fn memcached(mut self, login: Option<&(String, String)>) -> Result<(), ProtocolFailure> {
+        // Servers started with an authentication file take the login as the value of any `set`
+        if let Some((username, password)) = login {
+            let login = format!("{username} {password}");
+            let reply = self
+                .send(&format!("set auth 0 0 {}\r\n{login}\r\n", login.len()))
+                .await?;
+
+            if reply != "STORED" {
+                return Err(ProtocolFailure::new(
+                    FailureReason::AuthenticationFailed,
+                    format!("rejected the login as {username}: {reply}"),
+                ));
+            }
+        }
+
+        let reply = self.send("version\r\n").await?;
+
+        if !reply.starts_with("VERSION ") {
+            let reason = if reply.contains("unauthenticated") {
+                FailureReason::AuthenticationFailed
+            } else {
+                FailureReason::UnexpectedStatus
+            };
+
+            return Err(ProtocolFailure::new(
+                reason,
+                format!("answered version with {reply}"),
+            ));
+        }
+
+        // Memcached closes the connection rather than answering `quit`
+        let _ = self.stream.write_all(b"quit\r\n").await;
+        let _ = self.stream.flush().await;
+
+        Ok(())
+    }
+}
+
+async fn converse<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: S,
+    check: &KeyValueCheck,
+    login: Option<&(String, String)>,
+) -> Result<(), ProtocolFailure> {
+    let session = Session::new(stream);
+
+    match check.protocol {
+        Protocol::Redis => session.redis(login).await,
+        Protocol::Memcached => session.memcached(login).await,
+    }
+}
+
+async fn open(
+    connector: &TlsConnector,
+    check: &KeyValueCheck,
+    login: Option<&(String, String)>,
+) -> Result<(), ProtocolFailure> {
+    let stream = TcpStream::connect((check.host.as_str(), check.port))
+        .await
+        .map_err(|e| {
+            ProtocolFailure::new(
+                FailureReason::ConnectionFailure,
+                format!("could not connect: {e}"),
+            )
+        })?;
+
+    if !check.tls {
+        return converse(stream, check, login).await;
+    }
+
+    let server_name = ServerName::try_from(check.host.as_str()).map_err(|_| {
+        ProtocolFailure::new(
+            FailureReason::BadRequest,
+            format!("{} cannot be used to verify a certificate", check.host),
+        )
+    })?;
+
+    let stream = connector
+        .connect(server_name.to_owned(), stream)
+        .await
+        .map_err(|e| {
+            ProtocolFailure::new(
+                FailureReason::ConnectionFailure,
+                format!("TLS handshake failed: {e}"),
+            )
+        })?;
+
+    converse(stream, check, login).await
+}
+
+/// Connects to the cache server and asks whether it is serving.
+pub async fn check(
+    connector: &TlsConnector,
+    check: &KeyValueCheck,
+    login: Option<&(String, String)>,
+    timeout: Duration,
+) -> Result<(), ProtocolFailure> {
+    tokio::time::timeout(timeout, open(connector, check, login))
+        .await
+        .map_err(|_| {
+            ProtocolFailure::new(
+                FailureReason::RequestTimeout,
+                format!("did not finish the conversation within {timeout:?}"),
+            )
+        })?
+}
+
+/// Pings origins written as `redis://host:port` and asks origins written as
+/// `memcached://host:port` for their version.
+pub struct KeyValueChecker {
+    connector: TlsConnector,
+}
+
+impl Default for KeyValueChecker {
+    fn default() -> Self {
+        Self {
+            connector: smtp::connector(),
+        }
+    }
+}
+
+impl Check for KeyValueChecker {
+    fn execute<'a>(
+        &'a self,
+        origin: &'a Origin,
+        context: &'a CheckContext<'a>,
+    ) -> BoxFuture<'a, CheckOutcome> {
+        Box::pin(async move {
+            let Some(parsed) = KeyValueCheck::parse(&origin.uri) else {
+                return CheckOutcome::new(Err(FailureReason::BadRequest));
+            };
+
+            let login = match clients::username_and_password(origin, context.credentials) {
+                Ok(login) => login,
+                Err(detail) => return CheckOutcome::failed(FailureReason::BadRequest, detail),
+            };
+
+            match check(&self.connector, &parsed, login.as_ref(), context.timeout).await {
+                Ok(()) => CheckOutcome::new(Ok((None, context.elapsed_millis()))),
+                Err(failure) => CheckOutcome::failed(failure.reason, failure.detail),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::poller::keyvalue::{KeyValueCheck, Protocol};
+    use crate::poller::FailureReason;
+
+    #[test]
+    fn checks_are_parsed_from_uris() {
+        assert_eq!(
+            KeyValueCheck::parse("redis://cache.internal"),
+            Some(KeyValueCheck {
+                host: String::from("cache.internal"),
+                port: 6379,
+                protocol: Protocol::Redis,
+                tls: false,
+            })
+        );
+        assert_eq!(
+            KeyValueCheck::parse("rediss://cache.example.com:6380"),
+            Some(KeyValueCheck {
+                host: String::from("cache.example.com"),
+                port: 6380,
+                protocol: Protocol::Redis,
+                tls: true,
+            })
+        );
+        assert_eq!(
+            KeyValueCheck::parse("memcached://sessions.internal"),
+            Some(KeyValueCheck {
+                host: String::from("sessions.internal"),
+                port: 11211,
+                protocol: Protocol::Memcached,
+                tls: false,
+            })
+        );
+
+        assert_eq!(KeyValueCheck::parse("redis://cache.internal?db=2"), None);
+        assert_eq!(KeyValueCheck::parse("https://cache.internal"), None);
+    }
+
+    #[test]
+    fn redis_commands_are_arrays_of_bulk_strings() {
+        assert_eq!(super::redis_command(&["PING"]), "*1\r\n$4\r\nPING\r\n");
+        assert_eq!(
+            super::redis_command(&["AUTH", "uptime", "s3cret"]),
+            "*3\r\n$4\r\nAUTH\r\n$6\r\nuptime\r\n$6\r\ns3cret\r\n"
+        );
+    }
+
+    #[test]
+    fn redis_errors_are_given_reasons() {
+        let failure = super::redis_error("-NOAUTH Authentication required.", "PING");
+
+        assert_eq!(failure.reason, FailureReason::AuthenticationFailed);
+        assert_eq!(
+            failure.detail,
+            "answered PING with NOAUTH Authentication required."
+        );
+
+        assert_eq!(
+            super::redis_error("-LOADING Redis is loading the dataset in memory", "PING").reason,
+            FailureReason::NotServing
+        );
+        assert_eq!(
+            super::redis_error("-ERR unknown command", "PING").reason,
+            FailureReason::UnexpectedStatus
+        );
+    }
+}
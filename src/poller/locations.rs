@@ -0,0 +1,67 @@
+use color_eyre::eyre::{eyre, Result};
+
+/// The location origins are limited to when they should be checked by pollers without one.
+pub const LOCAL: &str = "local";
+
+/// The locations of the pollers an origin is checked by, such as `local, eu-west`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProbeLocations {
+    locations: Vec<String>,
+}
+
+impl ProbeLocations {
+    /// Parses a comma separated list of locations.
+    pub fn parse(value: &str) -> Result<Self> {
+        let locations = value
+            .split(',')
+            .map(|location| {
+                let location = location.trim();
+
+                if location.is_empty() || location.contains(char::is_whitespace) {
+                    return Err(eyre!("'{location}' is not a location such as eu-west"));
+                }
+
+                Ok(location.to_owned())
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { locations })
+    }
+
+    /// Whether a poller in `location` checks the origin, where pollers without one are [`LOCAL`].
+    pub fn includes(&self, location: Option<&str>) -> bool {
+        let location = location.unwrap_or(LOCAL);
+
+        self.locations.iter().any(|candidate| candidate == location)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use color_eyre::eyre::Result;
+
+    use crate::poller::locations::ProbeLocations;
+
+    #[test]
+    fn pollers_without_a_location_are_local() -> Result<()> {
+        let locations = ProbeLocations::parse("local, eu-west")?;
+
+        assert!(locations.includes(None));
+        assert!(locations.includes(Some("eu-west")));
+        assert!(!locations.includes(Some("us-east")));
+
+        let remote = ProbeLocations::parse("us-east")?;
+
+        assert!(!remote.includes(None));
+        assert!(remote.includes(Some("us-east")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_lists_are_rejected() {
+        assert!(ProbeLocations::parse("").is_err());
+        assert!(ProbeLocations::parse("eu-west,").is_err());
+        assert!(ProbeLocations::parse("eu west").is_err());
+    }
+}
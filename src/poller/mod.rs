@@ -1,20 +1,120 @@
 use std::fmt::{self, Display};
+use std::future::Future;
+use std::net::IpAddr;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
 use color_eyre::eyre::Result;
-use sqlx::types::chrono::Utc;
-use sqlx::PgPool;
+use futures_util::StreamExt;
+use reqwest::header::HeaderValue;
+use sha2::{Digest, Sha256};
+use sqlx::postgres::{PgListener, PgNotification};
+use sqlx::types::chrono::{DateTime, Utc};
+use sqlx::{Connection, PgConnection, PgPool};
+use tokio::sync::{broadcast, mpsc, watch, Mutex};
 use uuid::Uuid;
 
-use crate::persistence::Origin;
+use crate::credentials::CredentialCipher;
+use crate::locale::{Alert, Locale};
+use crate::persistence::{
+    AddressFamily, AlertKind, AlertWindow, DestinationKind, DomainRegistration, HttpMethod,
+    MaintenanceMode, MaintenanceWindow, Origin, QueryDetails, QueryFailureDetails,
+    TracerouteCapture, Transaction, Watcher,
+};
+use crate::poller::check::{CheckContext, CheckOutcome, CheckRegistry};
+use crate::poller::clock::{Clock, SystemClock};
+use crate::poller::events::Event;
+use crate::poller::external::CommandChecker;
+use crate::poller::push::Delivery;
+use crate::poller::slack::SlackWebhook;
 
-#[derive(Copy, Clone, Debug, sqlx::Type)]
+mod assertion;
+mod cache;
+mod check;
+mod clients;
+mod clock;
+mod commands;
+mod cron;
+mod database;
+mod diagnosis;
+mod dns;
+mod download;
+mod events;
+mod external;
+mod fallback;
+mod firehose;
+mod ftp;
+mod grpc;
+mod headers;
+mod heartbeat;
+mod http;
+mod icmp;
+mod inspect;
+mod journey;
+mod kafka;
+mod keyvalue;
+mod locations;
+mod mqtt;
+mod nats;
+mod networks;
+mod notifiers;
+mod ntp;
+mod protocol;
+mod push;
+mod rdap;
+mod redirect;
+mod scheduler;
+mod slack;
+mod smtp;
+mod status;
+mod tcp;
+mod timing;
+mod traceroute;
+mod websocket;
+
+pub use crate::poller::assertion::JsonAssertion;
+pub use crate::poller::commands::{CheckNowError, Command, NotifierHealth, PollerHandle};
+pub use crate::poller::cron::CronSchedule;
+pub use crate::poller::diagnosis::FailureDiagnostics;
+pub use crate::poller::download::format_throughput;
+pub use crate::poller::events::EventBus;
+pub use crate::poller::external::ExternalCommands;
+pub use crate::poller::fallback::FallbackChannel;
+pub use crate::poller::firehose::{CheckResult, Firehose};
+pub use crate::poller::headers::{CapturedHeaders, ExpectedHeaders};
+pub use crate::poller::inspect::{Inspection, Inspector};
+pub use crate::poller::journey::validate_step;
+pub use crate::poller::locations::ProbeLocations;
+pub use crate::poller::networks::ExpectedNetworks;
+pub use crate::poller::notifiers::{AnyNotifier, NotifierKind};
+pub use crate::poller::push::{VapidKeys, WebPush};
+pub use crate::poller::rdap::DomainExpiry;
+pub use crate::poller::scheduler::{RoundSummary, SchedulerState};
+pub use crate::poller::status::ExpectedStatus;
+pub use crate::poller::timing::Timings;
+pub use crate::poller::traceroute::Traceroute;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, sqlx::Type)]
 pub enum FailureReason {
     RequestTimeout,
     Redirection,
     BadRequest,
     ConnectionFailure,
     InvalidBody,
+    DnsMismatch,
+    ForbiddenContent,
+    JsonAssertionFailed,
+    HeaderAssertionFailed,
+    SlowDownload,
+    ClockDrift,
+    CommandFailed,
+    AuthenticationFailed,
+    UnexpectedStatus,
+    NotServing,
+    CacheValidationFailed,
+    HeartbeatMissed,
     Unknown,
 }
 
@@ -26,6 +126,18 @@ impl FailureReason {
             Self::BadRequest => "BadRequest",
             Self::ConnectionFailure => "ConnectionFailure",
             Self::InvalidBody => "InvalidBody",
+            Self::DnsMismatch => "DnsMismatch",
+            Self::ForbiddenContent => "ForbiddenContent",
+            Self::JsonAssertionFailed => "JsonAssertionFailed",
+            Self::HeaderAssertionFailed => "HeaderAssertionFailed",
+            Self::SlowDownload => "SlowDownload",
+            Self::ClockDrift => "ClockDrift",
+            Self::CommandFailed => "CommandFailed",
+            Self::AuthenticationFailed => "AuthenticationFailed",
+            Self::UnexpectedStatus => "UnexpectedStatus",
+            Self::NotServing => "NotServing",
+            Self::CacheValidationFailed => "CacheValidationFailed",
+            Self::HeartbeatMissed => "HeartbeatMissed",
             Self::Unknown => "Unknown",
         }
     }
@@ -39,6 +151,176 @@ impl Display for FailureReason {
     }
 }
 
+/// How an origin is checked, decided by the scheme of its URI.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum CheckKind<'a> {
+    /// Makes a request with the method and body of the origin, which is a GET by default, and
+    /// records the status.
+    Http(&'a str),
+    /// Opens a TCP connection to the `host:port` address, for origins written as `tcp://host:port`.
+    Tcp(&'a str),
+    /// Sends an ICMP echo request to the host, for origins written as `icmp://host`.
+    Icmp(&'a str),
+    /// Resolves a record and optionally compares the answers, for origins written as
+    /// `dns://name?type=MX&expect=mail.example.com`.
+    Dns(&'a str),
+    /// Calls the standard gRPC health checking service, for origins written as
+    /// `grpc://host:port/service` or `grpcs://host:port/service`.
+    Grpc(&'a str),
+    /// Performs the opening handshake, for origins written as `ws://host/path` or
+    /// `wss://host/path`.
+    WebSocket(&'a str),
+    /// Waits for the greeting of a mail server, for origins written as `smtp://host:port` or
+    /// `smtps://host:port`.
+    Smtp(&'a str),
+    /// Connects to a broker and pings it or makes a round trip through a topic, for origins
+    /// written as `mqtt://host:port?topic=name` or `mqtts://host:port?topic=name`.
+    Mqtt(&'a str),
+    /// Pings a Redis server or asks a Memcached server for its version, for origins written as
+    /// `redis://host:port`, `rediss://host:port` or `memcached://host:port`.
+    KeyValue(&'a str),
+    /// Connects to a database and runs `SELECT 1`, for origins written as DSNs such as
+    /// `postgres://user@host:port/name` or `mysql://user@host:port/name`.
+    Database(&'a str),
+    /// Compares the clock of a time server with the poller's, for origins written as
+    /// `ntp://host:port`.
+    Ntp(&'a str),
+    /// Checks that the other forms of a canonical URL permanently redirect to it, for origins
+    /// written as `redirect://www.example.com/path`.
+    Redirect(&'a str),
+    /// Downloads a large asset and measures the throughput, for origins written as
+    /// `download+https://mirror.example.com/file.iso`.
+    Download(&'a str),
+    /// Logs into a file exchange server, for origins written as `ftp://host:port/path?list` or
+    /// `sftp://host:port`.
+    FileTransfer(&'a str),
+    /// Runs an executable from the commands directory, for origins written as
+    /// `command://name?arg=value`.
+    Command,
+    /// Waits for pings from the job being monitored rather than making a request, for origins
+    /// written as `heartbeat://nightly-backup`.
+    Heartbeat,
+    /// Makes the origin's sequence of requests, for origins written as `journey://checkout`.
+    Journey,
+}
+
+impl<'a> CheckKind<'a> {
+    fn from_uri(uri: &'a str) -> Self {
+        if let Some(address) = uri.strip_prefix("tcp://") {
+            return Self::Tcp(address.trim_end_matches('/'));
+        }
+
+        if let Some(host) = uri.strip_prefix("icmp://") {
+            return Self::Icmp(host.trim_end_matches('/'));
+        }
+
+        if uri.starts_with("dns://") {
+            return Self::Dns(uri);
+        }
+
+        if uri.starts_with("grpc://") || uri.starts_with("grpcs://") {
+            return Self::Grpc(uri);
+        }
+
+        if uri.starts_with("ws://") || uri.starts_with("wss://") {
+            return Self::WebSocket(uri);
+        }
+
+        if uri.starts_with("smtp://") || uri.starts_with("smtps://") {
+            return Self::Smtp(uri);
+        }
+
+        if uri.starts_with("mqtt://") || uri.starts_with("mqtts://") {
+            return Self::Mqtt(uri);
+        }
+
+        if ["redis://", "rediss://", "memcached://"]
+            .iter()
+            .any(|scheme| uri.starts_with(scheme))
+        {
+            return Self::KeyValue(uri);
+        }
+
+        if ["postgres://", "postgresql://", "mysql://", "mariadb://"]
+            .iter()
+            .any(|scheme| uri.starts_with(scheme))
+        {
+            return Self::Database(uri);
+        }
+
+        if uri.starts_with("ntp://") {
+            return Self::Ntp(uri);
+        }
+
+        if uri.starts_with("redirect://") {
+            return Self::Redirect(uri);
+        }
+
+        if uri.starts_with("download+http://") || uri.starts_with("download+https://") {
+            return Self::Download(uri);
+        }
+
+        if uri.starts_with("ftp://") || uri.starts_with("sftp://") {
+            return Self::FileTransfer(uri);
+        }
+
+        if uri.starts_with("command://") {
+            return Self::Command;
+        }
+
+        if uri.starts_with("heartbeat://") {
+            return Self::Heartbeat;
+        }
+
+        if uri.starts_with("journey://") {
+            return Self::Journey;
+        }
+
+        Self::Http(uri)
+    }
+
+    /// The name the check for this kind of origin is registered under.
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Http(_) => "http",
+            Self::Tcp(_) => "tcp",
+            Self::Icmp(_) => "icmp",
+            Self::Dns(_) => "dns",
+            Self::Grpc(_) => "grpc",
+            Self::WebSocket(_) => "websocket",
+            Self::Smtp(_) => "smtp",
+            Self::Mqtt(_) => "mqtt",
+            Self::KeyValue(_) => "keyvalue",
+            Self::Database(_) => "database",
+            Self::Ntp(_) => "ntp",
+            Self::Redirect(_) => "redirect",
+            Self::Download(_) => "download",
+            Self::FileTransfer(_) => "ftp",
+            Self::Command => "command",
+            Self::Heartbeat => "heartbeat",
+            Self::Journey => "journey",
+        }
+    }
+}
+
+impl From<HttpMethod> for reqwest::Method {
+    fn from(method: HttpMethod) -> Self {
+        match method {
+            HttpMethod::Get => Self::GET,
+            HttpMethod::Head => Self::HEAD,
+            HttpMethod::Post => Self::POST,
+            HttpMethod::Put => Self::PUT,
+        }
+    }
+}
+
+impl FailureReason {
+    /// Whether the origin could not be reached at all, rather than responding incorrectly.
+    fn is_connection_level(self) -> bool {
+        matches!(self, Self::ConnectionFailure | Self::RequestTimeout)
+    }
+}
+
 impl From<reqwest::Error> for FailureReason {
     fn from(error: reqwest::Error) -> Self {
         if error.is_timeout() {
@@ -65,31 +347,95 @@ impl From<reqwest::Error> for FailureReason {
     }
 }
 
+/// Where alerts are sent, such as an SNS topic or a Slack webhook.
+///
+/// Implementations can be written with `async fn`, as long as the future they return is `Send`.
 pub trait Notifier {
-    async fn notify(&self, topic: &str, subject: &str, message: &str) -> Result<()>;
-}
-
-impl Notifier for aws_sdk_sns::Client {
-    async fn notify(&self, topic: &str, subject: &str, message: &str) -> Result<()> {
-        self.publish()
-            .topic_arn(topic)
-            .subject(subject)
-            .message(message)
-            .send()
-            .await?;
-
-        Ok(())
-    }
+    /// Sends an alert with `subject` and `message` to `topic`, which is the SNS topic ARN or
+    /// webhook URL the poller was configured with.
+    fn notify(
+        &self,
+        topic: &str,
+        subject: &str,
+        message: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
 }
 
+/// When failures of an origin become an alert.
 #[derive(Copy, Clone, Debug)]
 pub struct AlertThreshold {
     /// The number of failures that need to occur for a notification to be sent.
     failure_limit: u16,
-    /// The window where failures must have occurred.
+    /// The window where failures must have occurred, for origins with rolling alert windows.
     window_period: chrono::Duration,
     /// The minimum amount of time between notifications.
     cooldown: chrono::Duration,
+    /// The number of locations the failures need to come from, so a blip in the network of one
+    /// poller does not alert on its own.
+    location_limit: u16,
+    /// How often adaptive origins are checked between rounds while they are failing.
+    recheck_interval: Duration,
+    /// How many checks in a row an adaptive origin must pass before it goes back to being checked
+    /// once a round.
+    stable_checks: u16,
+    /// Whether every origin is checked more often while failing, rather than only those with
+    /// adaptive polling turned on.
+    adapt_all_origins: bool,
+}
+
+impl AlertThreshold {
+    /// Alerts once an origin fails `failure_limit` times within `window_period`, at most once
+    /// every `cooldown`.
+    pub fn new(
+        failure_limit: u16,
+        window_period: chrono::Duration,
+        cooldown: chrono::Duration,
+    ) -> Self {
+        Self {
+            failure_limit,
+            window_period,
+            cooldown,
+            location_limit: 1,
+            recheck_interval: RECHECK_INTERVAL,
+            stable_checks: STABLE_CHECKS,
+            adapt_all_origins: false,
+        }
+    }
+
+    /// Only alerts once the failures come from at least `location_limit` locations, for
+    /// deployments with pollers in several of them.
+    pub fn with_location_limit(self, location_limit: u16) -> Self {
+        Self {
+            location_limit: location_limit.max(1),
+            ..self
+        }
+    }
+
+    /// Checks failing adaptive origins every `recheck_interval`, which is limited to the time
+    /// between rounds as rounds check every origin anyway.
+    pub fn with_recheck_interval(self, recheck_interval: Duration) -> Self {
+        Self {
+            recheck_interval: recheck_interval.clamp(MIN_RECHECK_INTERVAL, ROUND_INTERVAL),
+            ..self
+        }
+    }
+
+    /// Keeps checking adaptive origins more often until they pass `stable_checks` checks in a row.
+    pub fn with_stable_checks(self, stable_checks: u16) -> Self {
+        Self {
+            stable_checks: stable_checks.max(1),
+            ..self
+        }
+    }
+
+    /// Treats every origin as adaptive, so any origin that starts failing is checked more often
+    /// until it recovers.
+    pub fn with_all_origins_adaptive(self) -> Self {
+        Self {
+            adapt_all_origins: true,
+            ..self
+        }
+    }
 }
 
 impl Default for AlertThreshold {
@@ -98,14 +444,79 @@ impl Default for AlertThreshold {
             failure_limit: 3,
             window_period: chrono::Duration::minutes(5),
             cooldown: chrono::Duration::hours(1),
+            location_limit: 1,
+            recheck_interval: RECHECK_INTERVAL,
+            stable_checks: STABLE_CHECKS,
+            adapt_all_origins: false,
         }
     }
 }
 
+/// How long the poller waits between rounds of checks.
+const ROUND_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The advisory lock held by whichever running poller rechecks unstable origins and checks changed
+/// ones, so that only one instance does. Pollers with a location use [`leader_lock`] instead.
+const LEADER_LOCK: i64 = 0x7570_7469_6d65;
+
+/// The advisory lock of the leader of the pollers in `location`, so that each location has one.
+fn leader_lock(location: Option<&str>) -> i64 {
+    let Some(location) = location else {
+        return LEADER_LOCK;
+    };
+
+    let digest = Sha256::digest(location.as_bytes());
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&digest[..8]);
+
+    LEADER_LOCK ^ i64::from_be_bytes(bytes)
+}
+
+/// How often adaptive origins are checked between rounds while they are failing, unless configured
+/// otherwise.
+const RECHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// The most often adaptive origins can be checked, so a failing origin is not hammered.
+const MIN_RECHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many checks in a row an adaptive origin must pass before it goes back to being checked
+/// once a round, unless configured otherwise.
+const STABLE_CHECKS: u16 = 3;
+
+/// How long the poller waits before retrying a failed check, doubling with each retry.
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// How long checks of origins without their own timeout can take before they fail.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The longest a check is delayed from the start of its round, unless configured otherwise.
+const DEFAULT_JITTER: Duration = Duration::from_secs(20);
+
+/// How many origins are checked at once during a round, unless configured otherwise.
+const DEFAULT_CONCURRENCY: NonZeroUsize = match NonZeroUsize::new(16) {
+    Some(concurrency) => concurrency,
+    None => unreachable!(),
+};
+
+/// How the poller alerts, and the defaults for checks of origins that do not set their own.
 #[derive(Clone, Debug)]
 pub struct PollerConfiguration {
     alert_threshold: AlertThreshold,
     topic: String,
+    default_timeout: Duration,
+    /// The language alerts are written in, unless a destination asks for another.
+    locale: Locale,
+    /// The HTTP or SOCKS5 proxy HTTP checks are sent through, unless an origin has its own.
+    proxy: Option<reqwest::Proxy>,
+    /// Where the poller checks from, such as a region, which is recorded with every check.
+    location: Option<String>,
+    /// The User-Agent HTTP checks send, unless an origin has its own.
+    user_agent: HeaderValue,
+    /// How many origins are checked at once during a round.
+    concurrency: NonZeroUsize,
+    /// The longest a check is delayed from the start of its round, so that rounds spread their
+    /// checks out rather than starting them all at once.
+    jitter: Duration,
 }
 
 impl PollerConfiguration {
@@ -113,15 +524,144 @@ impl PollerConfiguration {
         Self {
             alert_threshold,
             topic: topic.into(),
+            default_timeout: DEFAULT_TIMEOUT,
+            locale: Locale::default(),
+            proxy: None,
+            location: None,
+            user_agent: HeaderValue::from_static(clients::DEFAULT_USER_AGENT),
+            concurrency: DEFAULT_CONCURRENCY,
+            jitter: DEFAULT_JITTER,
+        }
+    }
+
+    pub fn with_locale(self, locale: Locale) -> Self {
+        Self { locale, ..self }
+    }
+
+    pub fn with_default_timeout(self, default_timeout: Duration) -> Self {
+        Self {
+            default_timeout,
+            ..self
+        }
+    }
+
+    pub fn with_proxy(self, proxy: reqwest::Proxy) -> Self {
+        Self {
+            proxy: Some(proxy),
+            ..self
+        }
+    }
+
+    /// Tags the poller with the location it checks from, so origins can be limited to the
+    /// pollers in some locations.
+    pub fn with_location<T: Into<String>>(self, location: T) -> Self {
+        Self {
+            location: Some(location.into()),
+            ..self
+        }
+    }
+
+    pub fn with_user_agent(self, user_agent: HeaderValue) -> Self {
+        Self { user_agent, ..self }
+    }
+
+    pub fn with_concurrency(self, concurrency: NonZeroUsize) -> Self {
+        Self {
+            concurrency,
+            ..self
+        }
+    }
+
+    /// Delays each check by up to `jitter` from the start of its round, which is limited to the
+    /// time between rounds.
+    pub fn with_jitter(self, jitter: Duration) -> Self {
+        Self {
+            jitter: jitter.min(ROUND_INTERVAL),
+            ..self
         }
     }
 }
 
+/// What a single attempt at checking an origin found, before it is recorded.
+struct Attempt {
+    started_at: DateTime<Utc>,
+    outcome: CheckOutcome,
+}
+
+/// Checks every origin in the database each round, recording the results and alerting through
+/// `N` when origins keep failing.
+///
+/// Extra integrations are added with the `with_*` methods before calling [`Poller::run`], and the
+/// running poller is controlled through its [`PollerHandle`].
 pub struct Poller<N> {
     pool: PgPool,
-    http_client: reqwest::Client,
+    /// Identifies the origins this poller has claimed when several instances are running.
+    instance_uid: Uuid,
+    /// Never follow redirects themselves, so checks can apply the redirect policy of each origin.
+    http_clients: Arc<clients::HttpClients>,
+    checks: CheckRegistry,
     notifier: N,
+    slack: SlackWebhook,
+    push: Option<WebPush>,
+    firehose: Option<Firehose>,
+    events: Option<EventBus>,
+    traceroute: Option<Traceroute>,
+    domain_expiry: Option<DomainExpiry>,
+    diagnostics: Option<FailureDiagnostics>,
+    fallback: Option<FallbackChannel>,
+    credentials: Option<CredentialCipher>,
     configuration: PollerConfiguration,
+    commands: Mutex<mpsc::Receiver<Command>>,
+    scheduler: scheduler::Scheduler,
+    notifier_health: watch::Sender<NotifierHealth>,
+    /// Every check result, for anyone waiting on a check they asked for.
+    completed: broadcast::Sender<CheckResult>,
+    handle: PollerHandle,
+    paused: Arc<AtomicBool>,
+    /// When checks happened and alerting windows and cooldowns are measured from.
+    clock: Arc<dyn Clock>,
+}
+
+async fn next_notification(listener: &mut Option<PgListener>) -> sqlx::Result<PgNotification> {
+    match listener {
+        Some(listener) => listener.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Whether `origin` should be checked in a round starting at `now`, which it always should unless
+/// its schedule has not matched since `since`. Schedules that cannot be parsed are checked every
+/// round rather than silently never.
+fn is_due(origin: &Origin, since: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    let Some(schedule) = origin.schedule.as_deref() else {
+        return true;
+    };
+
+    match CronSchedule::parse(schedule) {
+        Ok(schedule) => schedule.matches_between(since, now),
+        Err(e) => {
+            let origin_uid = origin.origin_uid;
+            tracing::warn!(%e, %origin_uid, "checking an origin with an invalid schedule every round");
+            true
+        }
+    }
+}
+
+/// What the maintenance windows underway at `at` do with `origin`, where skipping its checks takes
+/// precedence over silencing them if several cover it.
+fn maintenance_mode(
+    windows: &[MaintenanceWindow],
+    origin: &Origin,
+    at: DateTime<Utc>,
+) -> Option<MaintenanceMode> {
+    windows
+        .iter()
+        .filter(|window| window.covers(origin) && window.is_active(at))
+        .map(|window| window.mode)
+        .reduce(|mode, other| match other {
+            MaintenanceMode::Skip => other,
+            MaintenanceMode::Silence => mode,
+        })
 }
 
 impl<N: Notifier> Poller<N> {
@@ -131,112 +671,915 @@ impl<N: Notifier> Poller<N> {
         notifier: N,
         configuration: PollerConfiguration,
     ) -> Self {
+        let (sender, receiver) = mpsc::channel(commands::QUEUE_CAPACITY);
+        let paused = Arc::new(AtomicBool::new(false));
+        let scheduler = scheduler::Scheduler::default();
+        let notifier_health = watch::Sender::new(NotifierHealth::default());
+        let (completed, _) = broadcast::channel(commands::COMPLETED_CAPACITY);
+
         Self {
             pool,
-            http_client,
+            instance_uid: Uuid::new_v4(),
+            slack: SlackWebhook::new(http_client),
+            push: None,
+            firehose: None,
+            events: None,
+            traceroute: None,
+            domain_expiry: None,
+            diagnostics: None,
+            fallback: None,
+            credentials: None,
+            http_clients: Arc::new(clients::HttpClients::new(
+                configuration.proxy.clone(),
+                configuration.user_agent.clone(),
+            )),
+            checks: CheckRegistry::builtin(),
             notifier,
             configuration,
+            commands: Mutex::new(receiver),
+            handle: PollerHandle::new(
+                sender,
+                Arc::clone(&paused),
+                scheduler.subscribe(),
+                notifier_health.subscribe(),
+                completed.clone(),
+            ),
+            scheduler,
+            notifier_health,
+            completed,
+            paused,
+            clock: Arc::new(SystemClock),
         }
     }
 
+    /// A handle for sending commands to the poller once it is running.
+    pub fn handle(&self) -> PollerHandle {
+        self.handle.clone()
+    }
+
+    /// Makes ad-hoc requests to origins the same way the poller checks them, including with any
+    /// credentials or proxy they have.
+    pub fn inspector(&self) -> Inspector {
+        Inspector::new(
+            Arc::clone(&self.http_clients),
+            self.credentials.clone(),
+            self.configuration.default_timeout,
+        )
+    }
+
+    /// Also sends outage alerts to every browser subscribed to push notifications.
+    pub fn with_web_push(mut self, push: WebPush) -> Self {
+        self.push = Some(push);
+        self
+    }
+
+    /// Also streams every check result to the firehose as it happens.
+    pub fn with_firehose(mut self, firehose: Firehose) -> Self {
+        self.firehose = Some(firehose);
+        self
+    }
+
+    /// Also publishes domain events, such as incidents opening, to the event bus.
+    pub fn with_event_bus(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Also captures a traceroute when an origin keeps failing with connection errors.
+    pub fn with_traceroute(mut self, traceroute: Traceroute) -> Self {
+        self.traceroute = Some(traceroute);
+        self
+    }
+
+    /// Also looks up when the domain of each origin expires once a day, alerting as it gets close.
+    pub fn with_domain_expiry(mut self, domain_expiry: DomainExpiry) -> Self {
+        self.domain_expiry = Some(domain_expiry);
+        self
+    }
+
+    /// Also gathers diagnostics when a check cannot reach its origin, which are attached to the
+    /// failure and included in outage alerts.
+    pub fn with_failure_diagnostics(mut self, diagnostics: FailureDiagnostics) -> Self {
+        self.diagnostics = Some(diagnostics);
+        self
+    }
+
+    /// Also tells a fallback channel when alerts start or stop failing to send to the topic.
+    pub fn with_fallback_channel(mut self, fallback: FallbackChannel) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Runs origins written as `command://name` from the directory of `commands`, which are
+    /// refused without one.
+    pub fn with_external_commands(mut self, commands: ExternalCommands) -> Self {
+        self.checks = self.checks.with("command", CommandChecker::new(commands));
+        self
+    }
+
+    /// Decrypts the credentials and client certificates of origins that have them, so their
+    /// checks can authenticate.
+    pub fn with_credentials(mut self, credentials: CredentialCipher) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Checks origins in rounds and handles commands from its handle, without returning.
     pub async fn run(&self) {
+        // Without a listener the poller still works, but only sees changes on its next round
+        let mut listener = match self.listen().await {
+            Ok(listener) => Some(listener),
+            Err(e) => {
+                tracing::warn!(%e, "failed to listen for origin changes");
+                None
+            }
+        };
+
+        // Only one poller runs at a time, so this is never contended
+        let mut commands = self.commands.lock().await;
+
+        // The connection holding the leader lock, while this instance has it
+        let mut leader = None;
+
         loop {
-            if let Err(e) = self.query_all_origins().await {
+            let leading = self.lead(&mut leader).await;
+
+            if self.paused.load(Ordering::Relaxed) {
+                tracing::debug!("skipping a round as the poller is paused");
+            } else if let Err(e) = self.query_all_origins().await {
                 tracing::warn!(%e, "failed to query all the origins");
             }
 
-            tokio::time::sleep(Duration::from_secs(60)).await;
+            if let Some(summary) = self
+                .scheduler
+                .waiting_until(self.clock.now() + ROUND_INTERVAL)
+            {
+                let slowest = summary.slowest.as_ref();
+
+                tracing::info!(
+                    origins_checked = summary.origins_checked,
+                    successes = summary.successes,
+                    failures = summary.total_failures(),
+                    failures_by_reason = ?summary.failures,
+                    duration_millis = summary.duration_millis,
+                    slowest_origin_uid = slowest.map(|slowest| slowest.origin_uid.to_string()),
+                    slowest_uri = slowest.map(|slowest| slowest.uri.as_str()),
+                    slowest_duration_millis = slowest.map(|slowest| slowest.duration_millis),
+                    "finished a round of checks"
+                );
+            }
+
+            let next_round = tokio::time::sleep(ROUND_INTERVAL);
+            tokio::pin!(next_round);
+
+            let recheck_interval = self.configuration.alert_threshold.recheck_interval;
+            let mut rechecks = tokio::time::interval_at(
+                tokio::time::Instant::now() + recheck_interval,
+                recheck_interval,
+            );
+
+            loop {
+                tokio::select! {
+                    () = &mut next_round => break,
+                    _ = rechecks.tick(), if leading => self.recheck_unstable_origins().await,
+                    notification = next_notification(&mut listener) => match notification {
+                        Ok(notification) if leading => self.handle_notification(&notification).await,
+                        Ok(_) => tracing::debug!("leaving an origin change to the leader"),
+                        Err(e) => {
+                            tracing::warn!(%e, "stopped listening for origin changes");
+                            listener = None;
+                        }
+                    },
+                    Some(command) = commands.recv() => {
+                        if self.handle_command(command).await {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether this instance is the one rechecking unstable origins and checking changed ones,
+    /// becoming it if no other instance is. `leader` keeps the connection the lock is held on,
+    /// which releases it when it closes.
+    async fn lead(&self, leader: &mut Option<PgConnection>) -> bool {
+        if let Some(connection) = leader {
+            if connection.ping().await.is_ok() {
+                return true;
+            }
+
+            tracing::warn!("lost the connection holding the leader lock");
+            *leader = None;
+        }
+
+        let result = async {
+            // Detaching the connection from the pool stops the lock outliving it on reuse
+            let mut connection = self.pool.acquire().await?.detach();
+            let key = leader_lock(self.configuration.location.as_deref());
+            let locked = crate::persistence::try_advisory_lock(&mut connection, key).await?;
+
+            Ok::<_, color_eyre::Report>(locked.then_some(connection))
+        }
+        .await;
+
+        match result {
+            Ok(Some(connection)) => {
+                tracing::info!(
+                    instance_uid = %self.instance_uid,
+                    location = ?self.configuration.location,
+                    "leading the running pollers"
+                );
+                *leader = Some(connection);
+                true
+            }
+            Ok(None) => false,
+            Err(e) => {
+                tracing::warn!(%e, "failed to try to lead the running pollers");
+                false
+            }
+        }
+    }
+
+    /// Carries out a command, returning whether a new round should start immediately.
+    async fn handle_command(&self, command: Command) -> bool {
+        tracing::info!(?command, "received a command");
+
+        match command {
+            Command::CheckNow(origin_uid) => {
+                self.check_origin_now(origin_uid).await;
+                false
+            }
+            Command::Pause => {
+                self.paused.store(true, Ordering::Relaxed);
+                false
+            }
+            Command::Resume => {
+                self.paused.store(false, Ordering::Relaxed);
+                true
+            }
+            Command::Reload => true,
+        }
+    }
+
+    async fn listen(&self) -> Result<PgListener> {
+        let mut listener = PgListener::connect_with(&self.pool).await?;
+
+        listener.listen(crate::persistence::ORIGIN_CHANGED).await?;
+
+        Ok(listener)
+    }
+
+    /// Checks an origin as soon as it is added or edited, unless the poller is paused.
+    async fn handle_notification(&self, notification: &PgNotification) {
+        let Ok(origin_uid) = notification.payload().parse::<Uuid>() else {
+            tracing::warn!(payload = %notification.payload(), "received an invalid origin notification");
+            return;
+        };
+
+        if self.paused.load(Ordering::Relaxed) {
+            tracing::debug!(%origin_uid, "ignoring an origin change as the poller is paused");
+            return;
+        }
+
+        // Pausing an origin changes it too, which should not check it one last time
+        if let Ok(Some(origin)) = crate::persistence::fetch_origin(&self.pool, origin_uid).await {
+            if origin.paused {
+                tracing::debug!(%origin_uid, "ignoring a change to a paused origin");
+                return;
+            }
+        }
+
+        self.check_origin_now(origin_uid).await;
+    }
+
+    async fn check_origin_now(&self, origin_uid: Uuid) {
+        tracing::info!(%origin_uid, "checking an origin immediately");
+
+        let result = async {
+            let windows = crate::persistence::fetch_maintenance_windows(&self.pool).await?;
+
+            match crate::persistence::fetch_origin(&self.pool, origin_uid).await? {
+                Some(origin) if self.probes(&origin) => self.check_origin(origin, &windows).await,
+                // The origin was removed before it could be checked, or is checked elsewhere
+                _ => Ok(()),
+            }
+        }
+        .await;
+
+        if let Err(e) = result {
+            tracing::warn!(%origin_uid, %e, "failed to check an origin immediately");
+        }
+    }
+
+    /// Checks the adaptive origins that are failing or have only just recovered, so they are seen
+    /// coming back up without waiting for the next round.
+    async fn recheck_unstable_origins(&self) {
+        if self.paused.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let AlertThreshold {
+            stable_checks,
+            adapt_all_origins,
+            ..
+        } = self.configuration.alert_threshold;
+
+        let origin_uids = match crate::persistence::fetch_unstable_origin_uids(
+            &self.pool,
+            i64::from(stable_checks),
+            adapt_all_origins,
+        )
+        .await
+        {
+            Ok(origin_uids) => origin_uids,
+            Err(e) => {
+                tracing::warn!(%e, "failed to find the origins to recheck");
+                return;
+            }
+        };
+
+        let windows = match crate::persistence::fetch_maintenance_windows(&self.pool).await {
+            Ok(windows) => windows,
+            Err(e) => {
+                tracing::warn!(%e, "failed to find the maintenance windows");
+                return;
+            }
+        };
+
+        for origin_uid in origin_uids {
+            tracing::debug!(%origin_uid, "rechecking an origin that is not yet stable");
+
+            let result = match crate::persistence::fetch_origin(&self.pool, origin_uid).await {
+                Ok(Some(origin)) if self.probes(&origin) => {
+                    self.check_origin(origin, &windows).await
+                }
+                Ok(_) => Ok(()),
+                Err(e) => Err(e),
+            };
+
+            if let Err(e) = result {
+                tracing::warn!(%origin_uid, %e, "failed to recheck an origin");
+            }
         }
     }
 
     async fn query_all_origins(&self) -> Result<()> {
+        // Find all the available origins, leaving those limited to other locations to the pollers
+        // there
+        let origins = crate::persistence::fetch_origins(&self.pool).await?;
+        let windows = crate::persistence::fetch_maintenance_windows(&self.pool).await?;
+        let windows = &windows;
+
+        // Paused origins are never due, and those with a schedule are only due if it matched since
+        // the previous round started
+        let now = self.clock.now();
+        let previous_round_at = self.scheduler.subscribe().borrow().round_started_at;
+        let since = previous_round_at.unwrap_or(now - chrono::Duration::minutes(1));
+
+        // Checking origins in the order they start keeps waiting ones from holding up the rest
+        let mut origins: Vec<_> = origins
+            .into_iter()
+            .filter(|origin| self.probes(origin))
+            .map(|origin| {
+                let skipped = maintenance_mode(windows, &origin, self.clock.now())
+                    == Some(MaintenanceMode::Skip);
+                let due = !origin.paused && !skipped && is_due(&origin, since, now);
+                let starts_at = due.then(|| scheduler::jittered(now, self.configuration.jitter));
+
+                (starts_at, origin)
+            })
+            .collect();
+
+        // Other instances in the same location may have already claimed some of the origins due
+        // this round
+        let due: Vec<_> = origins
+            .iter()
+            .filter(|(starts_at, _)| starts_at.is_some())
+            .map(|(_, origin)| origin.origin_uid)
+            .collect();
+
+        let claimed = crate::persistence::claim_origins(
+            &self.pool,
+            self.instance_uid,
+            self.configuration.location.as_deref().unwrap_or_default(),
+            &due,
+            now,
+            now + ROUND_INTERVAL,
+        )
+        .await?;
+
+        for (starts_at, origin) in &mut origins {
+            if !claimed.contains(&origin.origin_uid) {
+                *starts_at = None;
+            }
+        }
+
+        origins.sort_by_key(|(starts_at, _)| (starts_at.is_none(), *starts_at));
+        self.scheduler.round_started(&origins);
+
+        let due = origins
+            .into_iter()
+            .filter_map(|(starts_at, origin)| Some((starts_at?, origin)));
+
+        // Each origin is still recorded in its own transaction, so they can be checked in any order
+        // and one failing to be checked leaves the rest of the round running
+        futures_util::stream::iter(due)
+            .for_each_concurrent(
+                self.configuration.concurrency.get(),
+                |(starts_at, origin)| async move {
+                    if let Ok(delay) = (starts_at - self.clock.now()).to_std() {
+                        tokio::time::sleep(delay).await;
+                    }
+
+                    let origin_uid = origin.origin_uid;
+
+                    if let Err(e) = self.check_domain_expiry(&origin, windows).await {
+                        tracing::warn!(%origin_uid, %e, "failed to check the domain expiry of an origin");
+                    }
+
+                    if let Err(e) = self.check_origin(origin, windows).await {
+                        tracing::warn!(%origin_uid, %e, "failed to check an origin");
+                    }
+                },
+            )
+            .await;
+
+        Ok(())
+    }
+
+    /// Whether the poller checks `origin`, which is every poller unless the origin is limited to
+    /// some locations.
+    fn probes(&self, origin: &Origin) -> bool {
+        let Some(probe_locations) = &origin.probe_locations else {
+            return true;
+        };
+
+        match ProbeLocations::parse(probe_locations) {
+            Ok(probe_locations) => probe_locations.includes(self.configuration.location.as_deref()),
+            Err(e) => {
+                // Checking from everywhere beats leaving the origin unchecked
+                tracing::warn!(origin_uid = %origin.origin_uid, %probe_locations, %e, "ignoring invalid probe locations");
+                true
+            }
+        }
+    }
+
+    /// Checks an origin over each family it is checked over, unless a maintenance window in
+    /// `windows` is skipping its checks.
+    async fn check_origin(&self, origin: Origin, windows: &[MaintenanceWindow]) -> Result<()> {
+        let maintenance = maintenance_mode(windows, &origin, self.clock.now());
+
+        if maintenance == Some(MaintenanceMode::Skip) {
+            tracing::debug!(origin_uid = %origin.origin_uid, "skipping a check of an origin under maintenance");
+            return Ok(());
+        }
+
+        let silenced = maintenance == Some(MaintenanceMode::Silence);
+
+        // Only HTTP checks, including downloads, can be made over a particular family
+        let families = match CheckKind::from_uri(&origin.uri) {
+            CheckKind::Http(_) | CheckKind::Download(_) => origin.address_family.checks(),
+            _ => AddressFamily::Any.checks(),
+        };
+
+        self.scheduler.check_started();
+
+        let started_at = self.clock.now();
+        let mut failures = Vec::with_capacity(families.len());
+
+        let result = async {
+            for &family in families {
+                failures.push(self.probe(&origin, family, silenced).await?);
+            }
+
+            Ok(())
+        }
+        .await;
+
+        let duration_millis = (self.clock.now() - started_at).num_milliseconds();
+        self.scheduler
+            .check_finished(origin.origin_uid, duration_millis, &failures);
+
+        result
+    }
+
+    /// Checks an origin over `family` if it is an HTTP check, retrying failures as many times as
+    /// the origin allows before recording the outcome, and returns why the check failed if it did.
+    /// `silenced` checks are recorded without counting towards alerts or sending any.
+    async fn probe(
+        &self,
+        origin: &Origin,
+        family: AddressFamily,
+        silenced: bool,
+    ) -> Result<Option<FailureReason>> {
         let Self {
-            pool, http_client, ..
+            pool, http_clients, ..
         } = self;
 
-        // Find all the available origins
-        let origins = crate::persistence::fetch_origins(pool).await?;
-        let timeout = Duration::from_secs(3);
-
-        for Origin { origin_uid, uri } in origins {
-            let mut tx = pool.begin().await?;
-            let start = Utc::now();
-
-            match http_client.get(&uri).timeout(timeout).send().await {
-                Ok(res) => {
-                    let status = res.status();
-                    let latency_millis = (Utc::now() - start).num_milliseconds();
-
-                    let query_uid = crate::persistence::insert_query(
-                        &mut tx,
-                        origin_uid,
-                        status.as_u16(),
-                        latency_millis,
-                        start,
-                    )
-                    .await?;
+        let Origin {
+            origin_uid,
+            ref uri,
+            alerts_enabled,
+            baseline_origin_uid,
+            relative_latency_limit_millis,
+            timeout_millis,
+            ref expected_networks,
+            alert_window,
+            ..
+        } = *origin;
 
-                    tracing::info!(
-                        %origin_uid,
-                        %query_uid,
-                        %status,
-                        %latency_millis,
-                        "made a request to the origin"
-                    );
-                }
-                Err(e) => {
-                    let failure_reason = FailureReason::from(e);
+        let alerts_enabled = alerts_enabled && !silenced;
 
-                    let query_failure_uid = crate::persistence::insert_query_failure(
-                        &mut tx,
-                        origin_uid,
-                        failure_reason,
-                        start,
-                    )
+        let timeout = timeout_millis.map_or(self.configuration.default_timeout, |millis| {
+            Duration::from_millis(millis as u64)
+        });
+
+        // Only needed to detect state changes, so avoid the query if nothing will be published
+        let previously_succeeded = match &self.events {
+            Some(_) => crate::persistence::fetch_latest_outcome(pool, origin_uid, family).await?,
+            None => None,
+        };
+
+        let mut attempt = self.attempt(origin, family, timeout).await;
+        let mut attempts = 1;
+
+        while attempts <= origin.retries {
+            let Err(failure_reason) = attempt.outcome.result else {
+                break;
+            };
+
+            let backoff = RETRY_BACKOFF * 2u32.pow(attempts as u32 - 1);
+
+            tracing::info!(
+                %origin_uid,
+                ?family,
+                %failure_reason,
+                failure_detail = ?attempt.outcome.failure_detail,
+                attempt = attempts,
+                ?backoff,
+                "retrying a failed check of the origin"
+            );
+
+            tokio::time::sleep(backoff).await;
+
+            attempt = self.attempt(origin, family, timeout).await;
+            attempts += 1;
+        }
+
+        let Attempt {
+            started_at: start,
+            outcome:
+                CheckOutcome {
+                    result: outcome,
+                    failure_detail,
+                    redirect_chain,
+                    redirects,
+                    mut remote_address,
+                    body_bytes,
+                    http_version,
+                    captured_headers,
+                    timings,
+                    cold_latency_millis,
+                    body_hash,
+                    throughput_bytes_per_second,
+                    clock_offset_millis,
+                },
+        } = attempt;
+
+        // Gathered before the transaction starts, as a traceroute can take a while
+        let diagnostics = match (outcome, &self.diagnostics) {
+            (Err(failure_reason), Some(diagnostics)) if failure_reason.is_connection_level() => {
+                diagnostics.diagnose(uri, timeout).await
+            }
+            _ => None,
+        };
+
+        // Behind a proxy the address is the proxy's, which says nothing about the origin
+        if matches!(
+            CheckKind::from_uri(uri),
+            CheckKind::Http(_) | CheckKind::Download(_)
+        ) && http_clients.proxied(origin)
+        {
+            remote_address = None;
+        }
+
+        let mut tx = pool.begin().await?;
+
+        match outcome {
+            Ok((status, latency_millis)) => {
+                let query_uid = crate::persistence::insert_query(
+                    &mut tx,
+                    origin_uid,
+                    status,
+                    latency_millis,
+                    start,
+                )
+                .await?;
+
+                let details = QueryDetails {
+                    address_family: family,
+                    attempts: (attempts > 1).then_some(attempts),
+                    location: self.configuration.location.as_deref(),
+                    remote_address,
+                    body_bytes,
+                    http_version,
+                    timings: timings.as_ref(),
+                    throughput_bytes_per_second,
+                    clock_offset_millis,
+                    cold_latency_millis,
+                    captured_headers: captured_headers.as_deref(),
+                    final_url: redirects
+                        .as_ref()
+                        .map(|redirects| redirects.final_url.as_str()),
+                    redirect_chain: redirects.as_ref().map(|redirects| redirects.chain.as_str()),
+                };
+
+                crate::persistence::set_query_details(&mut tx, query_uid, &details).await?;
+
+                tracing::info!(
+                    %origin_uid,
+                    %query_uid,
+                    ?family,
+                    ?status,
+                    %latency_millis,
+                    ?remote_address,
+                    ?body_bytes,
+                    ?http_version,
+                    ?timings,
+                    ?cold_latency_millis,
+                    ?throughput_bytes_per_second,
+                    ?clock_offset_millis,
+                    %attempts,
+                    final_url = redirects.as_ref().map(|redirects| redirects.final_url.as_str()),
+                    "made a request to the origin"
+                );
+            }
+            Err(failure_reason) => {
+                let query_failure_uid = crate::persistence::insert_query_failure(
+                    &mut tx,
+                    origin_uid,
+                    failure_reason,
+                    failure_detail.as_deref(),
+                    start,
+                )
+                .await?;
+
+                let details = QueryFailureDetails {
+                    during_maintenance: silenced,
+                    address_family: family,
+                    attempts: (attempts > 1).then_some(attempts),
+                    location: self.configuration.location.as_deref(),
+                    remote_address,
+                    captured_headers: captured_headers.as_deref(),
+                    diagnostics: diagnostics.as_deref(),
+                };
+
+                crate::persistence::set_query_failure_details(&mut tx, query_failure_uid, &details)
                     .await?;
 
-                    tracing::warn!(
-                        %origin_uid,
-                        %query_failure_uid,
-                        %failure_reason,
-                        "failed to make a request to the origin"
-                    );
-                }
+                tracing::warn!(
+                    %origin_uid,
+                    %query_failure_uid,
+                    ?family,
+                    %failure_reason,
+                    ?failure_detail,
+                    ?remote_address,
+                    ?captured_headers,
+                    %attempts,
+                    "failed to make a request to the origin"
+                );
+            }
+        }
+
+        tx.commit().await?;
+
+        if let (Err(failure_reason), Some(traceroute)) = (outcome, &self.traceroute) {
+            if failure_reason.is_connection_level() {
+                self.capture_traceroute(traceroute, origin_uid, uri).await?;
+            }
+        }
+
+        let result = CheckResult {
+            origin_uid,
+            uri: uri.clone(),
+            queried_at: start,
+            success: outcome.is_ok(),
+            status: outcome.ok().and_then(|(status, _)| status),
+            latency_millis: outcome.ok().map(|(_, latency_millis)| latency_millis),
+            failure_reason: outcome.err().map(|reason| reason.as_str()),
+        };
+
+        if let Some(events) = &self.events {
+            let succeeded = outcome.is_ok();
+
+            if previously_succeeded.is_some_and(|previous| previous != succeeded) {
+                events.publish(Event::StateChanged {
+                    origin_uid,
+                    uri: uri.clone(),
+                    up: succeeded,
+                    changed_at: start,
+                });
+            }
+
+            if !succeeded && previously_succeeded != Some(false) {
+                events.publish(Event::IncidentOpened {
+                    origin_uid,
+                    uri: uri.clone(),
+                    started_at: start,
+                });
             }
 
-            tx.commit().await?;
+            events.publish(Event::CheckCompleted(result.clone()));
+        }
+
+        // Nobody may be waiting on the result, which is fine
+        let _ = self.completed.send(result.clone());
+
+        if let Some(firehose) = &self.firehose {
+            firehose.record(result);
+        }
+
+        // Check whether we need to notify someone
+        if alerts_enabled {
+            self.check_for_pending_notifications(origin_uid, uri, alert_window)
+                .await?;
+        }
+
+        self.record_health_score(origin_uid).await?;
+
+        if let Some(chain) = redirect_chain {
+            self.record_redirect_chain(origin_uid, uri, &chain, alerts_enabled)
+                .await?;
+        }
+
+        if let Some(body_hash) = body_hash {
+            self.record_body_change(origin_uid, uri, &body_hash, alerts_enabled)
+                .await?;
+        }
 
-            // Check whether we need to notify someone
-            self.check_for_pending_notifications(origin_uid, &uri)
+        if alerts_enabled {
+            if let (Some(baseline_origin_uid), Some(limit_millis)) =
+                (baseline_origin_uid, relative_latency_limit_millis)
+            {
+                self.check_relative_latency(
+                    origin_uid,
+                    uri,
+                    alert_window,
+                    baseline_origin_uid,
+                    limit_millis,
+                )
                 .await?;
+            }
+
+            if let (Some(expected_networks), Some(remote_address)) =
+                (expected_networks, remote_address)
+            {
+                self.check_remote_address(origin_uid, uri, expected_networks, remote_address)
+                    .await?;
+            }
+        }
+
+        Ok(outcome.err())
+    }
+
+    /// Checks an origin once over `family`, without recording anything.
+    async fn attempt(&self, origin: &Origin, family: AddressFamily, timeout: Duration) -> Attempt {
+        let started_at = self.clock.now();
+
+        let context = CheckContext {
+            pool: &self.pool,
+            http_clients: &self.http_clients,
+            credentials: self.credentials.as_ref(),
+            family,
+            timeout,
+            clock: self.clock.as_ref(),
+            started_at,
+        };
+
+        let kind = CheckKind::from_uri(&origin.uri);
+
+        let outcome = match self.checks.get(&kind) {
+            Some(check) => check.execute(origin, &context).await,
+            None => CheckOutcome::failed(
+                FailureReason::BadRequest,
+                format!("no check is registered for {} origins", kind.name()),
+            ),
+        };
+
+        Attempt {
+            started_at,
+            outcome,
         }
+    }
+
+    /// Captures the path to the origin in the background once its current incident has lasted for
+    /// enough checks, so slow traceroutes do not hold up the round.
+    async fn capture_traceroute(
+        &self,
+        traceroute: &Traceroute,
+        origin_uid: Uuid,
+        uri: &str,
+    ) -> Result<()> {
+        let Some(host) = traceroute::host(uri) else {
+            return Ok(());
+        };
+
+        let incident = crate::persistence::fetch_ongoing_incident(&self.pool, origin_uid).await?;
+
+        let Some(incident) = incident.filter(|i| traceroute.is_due(i.failure_count)) else {
+            return Ok(());
+        };
+
+        let pool = self.pool.clone();
+        let traceroute = traceroute.clone();
+        let clock = Arc::clone(&self.clock);
+
+        tokio::spawn(async move {
+            let output = match traceroute.capture(&host).await {
+                Ok(output) => output,
+                Err(e) => {
+                    tracing::warn!(%origin_uid, %host, %e, "failed to capture a traceroute");
+                    return;
+                }
+            };
+
+            let capture = TracerouteCapture {
+                incident_started_at: incident.started_at,
+                host,
+                output,
+                captured_at: clock.now(),
+            };
+
+            match crate::persistence::insert_traceroute(&pool, origin_uid, &capture).await {
+                Ok(()) => {
+                    tracing::info!(%origin_uid, host = %capture.host, "captured a traceroute")
+                }
+                Err(e) => tracing::warn!(%origin_uid, %e, "failed to store a traceroute"),
+            }
+        });
 
         Ok(())
     }
 
-    async fn check_for_pending_notifications(&self, origin_uid: Uuid, uri: &str) -> Result<()> {
-        let PollerConfiguration {
-            alert_threshold,
-            topic,
-        } = &self.configuration;
+    async fn record_health_score(&self, origin_uid: Uuid) -> Result<()> {
+        let inputs = crate::persistence::fetch_health_inputs(
+            &self.pool,
+            origin_uid,
+            crate::health::AVAILABILITY_WINDOW,
+            crate::health::LATENCY_WINDOW,
+            crate::health::BASELINE_WINDOW,
+            self.clock.now(),
+        )
+        .await?;
+
+        let Some(score) = crate::health::score(&inputs) else {
+            return Ok(());
+        };
+
+        crate::persistence::insert_health_score(&self.pool, origin_uid, score, self.clock.now())
+            .await?;
+
+        tracing::debug!(%origin_uid, %score, "recorded a health score");
+
+        Ok(())
+    }
+
+    async fn check_for_pending_notifications(
+        &self,
+        origin_uid: Uuid,
+        uri: &str,
+        alert_window: AlertWindow,
+    ) -> Result<()> {
+        let alert_threshold = &self.configuration.alert_threshold;
+        let now = self.clock.now();
+        let since = alert_window.start(now, alert_threshold.window_period);
 
         let exceeded = crate::persistence::failure_rate_exceeded(
             &self.pool,
             origin_uid,
             alert_threshold.failure_limit,
-            alert_threshold.window_period,
+            alert_threshold.location_limit,
+            since,
+            now,
         )
         .await?;
 
         if !exceeded {
-            tracing::debug!(%origin_uid, ?alert_threshold, "failure rate has not been exceeded");
+            tracing::debug!(%origin_uid, ?alert_threshold, ?alert_window, "failure rate has not been exceeded");
             return Ok(());
         }
 
+        // Pollers in other locations see the same failures, so only one of them can decide to alert
+        let mut tx = self.pool.begin().await?;
+        crate::persistence::lock_origin_alerts(&mut tx, origin_uid).await?;
+
         let cooled_down = crate::persistence::latest_notification_older_than(
-            &self.pool,
+            &mut tx,
             origin_uid,
+            AlertKind::Outage,
             alert_threshold.cooldown,
+            now,
         )
         .await?;
 
@@ -245,22 +1588,491 @@ impl<N: Notifier> Poller<N> {
             return Ok(());
         }
 
-        let subject = "Outage detected";
-        let message = format!("The failure rate of {uri} exceeds the SLA");
+        let diagnostics =
+            crate::persistence::fetch_latest_failure_diagnostics(&self.pool, origin_uid).await?;
 
-        self.notifier
-            .notify(topic, "Outage detected", &message)
-            .await?;
+        let alert = Alert::Outage {
+            uri,
+            diagnostics: diagnostics.as_deref(),
+        };
+
+        let notification_uid = self.record_alert(&mut tx, origin_uid, &alert).await?;
+        tx.commit().await?;
+
+        self.deliver_alert(origin_uid, notification_uid, &alert)
+            .await
+    }
+
+    /// Alerts if the origin has been slower than its baseline by more than the limit over the
+    /// alerting window, which points to a regression in the service rather than the network.
+    async fn check_relative_latency(
+        &self,
+        origin_uid: Uuid,
+        uri: &str,
+        alert_window: AlertWindow,
+        baseline_origin_uid: Uuid,
+        limit_millis: i64,
+    ) -> Result<()> {
+        let AlertThreshold {
+            window_period,
+            cooldown,
+            ..
+        } = self.configuration.alert_threshold;
+
+        let now = self.clock.now();
+
+        let relative_latency_millis = crate::persistence::fetch_relative_latency(
+            &self.pool,
+            origin_uid,
+            baseline_origin_uid,
+            alert_window.start(now, window_period),
+        )
+        .await?;
+
+        let Some(relative_latency_millis) = relative_latency_millis else {
+            return Ok(());
+        };
+
+        if relative_latency_millis <= limit_millis {
+            tracing::debug!(%origin_uid, %relative_latency_millis, %limit_millis, "relative latency is within the limit");
+            return Ok(());
+        }
+
+        let mut tx = self.pool.begin().await?;
+        crate::persistence::lock_origin_alerts(&mut tx, origin_uid).await?;
+
+        let cooled_down = crate::persistence::latest_notification_older_than(
+            &mut tx,
+            origin_uid,
+            AlertKind::LatencyRegression,
+            cooldown,
+            now,
+        )
+        .await?;
+
+        if !cooled_down {
+            tracing::debug!(%origin_uid, %relative_latency_millis, "relative latency is over the limit, but a notification has been sent recently");
+            return Ok(());
+        }
+
+        let alert = Alert::LatencyRegression {
+            uri,
+            relative_latency_millis,
+            limit_millis,
+        };
+
+        let notification_uid = self.record_alert(&mut tx, origin_uid, &alert).await?;
+        tx.commit().await?;
+
+        self.deliver_alert(origin_uid, notification_uid, &alert)
+            .await
+    }
+
+    /// Alerts when an origin is served from outside the networks it is expected to be in, which
+    /// can mean its DNS was hijacked or it was moved to another provider without notice.
+    async fn check_remote_address(
+        &self,
+        origin_uid: Uuid,
+        uri: &str,
+        expected_networks: &str,
+        remote_address: IpAddr,
+    ) -> Result<()> {
+        // Networks are validated when they are saved, but imported configurations may not be
+        let Ok(expected) = ExpectedNetworks::parse(expected_networks) else {
+            tracing::warn!(%origin_uid, %expected_networks, "ignoring invalid expected networks");
+            return Ok(());
+        };
+
+        if expected.contains(remote_address) {
+            tracing::debug!(%origin_uid, %remote_address, "origin was served from an expected network");
+            return Ok(());
+        }
 
-        let created_at = Utc::now();
+        let mut tx = self.pool.begin().await?;
+        crate::persistence::lock_origin_alerts(&mut tx, origin_uid).await?;
 
-        let notification_uid = crate::persistence::insert_notification(
-            &self.pool, origin_uid, topic, subject, &message, created_at,
+        let cooldown = self.configuration.alert_threshold.cooldown;
+        let cooled_down = crate::persistence::latest_notification_older_than(
+            &mut tx,
+            origin_uid,
+            AlertKind::UnexpectedAddress,
+            cooldown,
+            self.clock.now(),
         )
         .await?;
 
+        if !cooled_down {
+            tracing::debug!(%origin_uid, %remote_address, "origin was served from an unexpected address, but a notification has been sent recently");
+            return Ok(());
+        }
+
+        let address = remote_address.to_string();
+        let alert = Alert::UnexpectedAddress {
+            uri,
+            address: &address,
+        };
+
+        let notification_uid = self.record_alert(&mut tx, origin_uid, &alert).await?;
+        tx.commit().await?;
+
+        self.deliver_alert(origin_uid, notification_uid, &alert)
+            .await
+    }
+
+    /// Looks up when the domain of an origin expires if it has not been looked up for a day,
+    /// alerting if it is within the warning period.
+    ///
+    /// Lookups only happen once a day, so there is no cooldown on these alerts and they repeat
+    /// daily until the domain is renewed. Lookups during a maintenance window in `windows` that
+    /// silences the origin are still recorded, but not alerted on.
+    async fn check_domain_expiry(
+        &self,
+        origin: &Origin,
+        windows: &[MaintenanceWindow],
+    ) -> Result<()> {
+        let Some(domain_expiry) = &self.domain_expiry else {
+            return Ok(());
+        };
+
+        let Some(host) = traceroute::host(&origin.uri) else {
+            return Ok(());
+        };
+
+        let origin_uid = origin.origin_uid;
+        let now = self.clock.now();
+
+        let previous =
+            crate::persistence::fetch_domain_registration(&self.pool, origin_uid).await?;
+
+        if previous.is_some_and(|previous| now - previous.checked_at < rdap::LOOKUP_INTERVAL) {
+            return Ok(());
+        }
+
+        // Failed lookups are retried on the next round rather than being recorded
+        let registration = match domain_expiry.lookup(&host).await {
+            Ok(registration) => registration,
+            Err(e) => {
+                tracing::warn!(%origin_uid, %host, %e, "failed to look up the domain registration");
+                return Ok(());
+            }
+        };
+
+        // Hosts no registry knows of are still recorded, so they are not looked up every round
+        let registration = match registration {
+            Some(registration) => DomainRegistration {
+                domain: registration.domain,
+                expires_at: registration.expires_at,
+                checked_at: now,
+            },
+            None => DomainRegistration {
+                domain: host,
+                expires_at: None,
+                checked_at: now,
+            },
+        };
+
+        crate::persistence::upsert_domain_registration(&self.pool, origin_uid, &registration)
+            .await?;
+
+        tracing::info!(%origin_uid, domain = %registration.domain, expires_at = ?registration.expires_at, "looked up the domain registration");
+
+        let Some(expires_at) = registration.expires_at else {
+            return Ok(());
+        };
+
+        let silenced = maintenance_mode(windows, origin, now) == Some(MaintenanceMode::Silence);
+
+        if !origin.alerts_enabled || silenced || !domain_expiry.is_expiring(expires_at, now) {
+            return Ok(());
+        }
+
+        // Rounded down, so a domain that expired earlier today has -1 days left rather than 0
+        let days_left = (expires_at - now).num_seconds().div_euclid(86_400);
+
+        let expires_on = expires_at.format("%Y-%m-%d").to_string();
+        let alert = Alert::DomainExpiring {
+            uri: &origin.uri,
+            domain: &registration.domain,
+            expires_on: &expires_on,
+            days_left,
+        };
+
+        self.send_alert(origin_uid, &alert).await
+    }
+
+    /// Records the redirects an audit followed if they differ from last time, alerting on the
+    /// change as it usually means a configuration was changed along with something else.
+    async fn record_redirect_chain(
+        &self,
+        origin_uid: Uuid,
+        uri: &str,
+        chain: &str,
+        alerts_enabled: bool,
+    ) -> Result<()> {
+        let previous = crate::persistence::fetch_redirect_chains(&self.pool, origin_uid, 1)
+            .await?
+            .pop();
+
+        if previous
+            .as_ref()
+            .is_some_and(|previous| previous.chain == chain)
+        {
+            return Ok(());
+        }
+
+        crate::persistence::insert_redirect_chain(&self.pool, origin_uid, chain, self.clock.now())
+            .await?;
+
+        tracing::info!(%origin_uid, %chain, "recorded a new redirect chain");
+
+        // The first chain for an origin is not a change from anything
+        let Some(previous) = previous else {
+            return Ok(());
+        };
+
+        if !alerts_enabled {
+            return Ok(());
+        }
+
+        let alert = Alert::RedirectChainChanged {
+            uri,
+            previous: &previous.chain,
+            current: chain,
+        };
+
+        self.send_alert(origin_uid, &alert).await
+    }
+
+    /// Records the hash of a response body if it differs from last time, alerting on the change as
+    /// content changing outside of a deploy can mean the origin was defaced.
+    async fn record_body_change(
+        &self,
+        origin_uid: Uuid,
+        uri: &str,
+        body_hash: &str,
+        alerts_enabled: bool,
+    ) -> Result<()> {
+        let previous = crate::persistence::fetch_body_changes(&self.pool, origin_uid, 1)
+            .await?
+            .pop();
+
+        if previous
+            .as_ref()
+            .is_some_and(|previous| previous.body_hash == body_hash)
+        {
+            return Ok(());
+        }
+
+        crate::persistence::insert_body_change(&self.pool, origin_uid, body_hash, self.clock.now())
+            .await?;
+
+        tracing::info!(%origin_uid, %body_hash, "recorded a new response body");
+
+        // The first body for an origin is not a change from anything
+        let Some(previous) = previous else {
+            return Ok(());
+        };
+
+        if !alerts_enabled {
+            return Ok(());
+        }
+
+        let alert = Alert::BodyChanged {
+            uri,
+            previous: &previous.body_hash,
+            current: body_hash,
+        };
+
+        self.send_alert(origin_uid, &alert).await
+    }
+
+    /// Sends an alert to the topic and everyone watching the origin, recording it as a
+    /// notification in the locale of the deployment.
+    async fn send_alert(&self, origin_uid: Uuid, alert: &Alert<'_>) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let notification_uid = self.record_alert(&mut tx, origin_uid, alert).await?;
+        tx.commit().await?;
+
+        self.deliver_alert(origin_uid, notification_uid, alert)
+            .await
+    }
+
+    /// Records an alert as a notification in `tx`, so it is only seen once whatever decided to
+    /// send it is committed too.
+    ///
+    /// Nothing is sent here, as `tx` may be holding [`crate::persistence::lock_origin_alerts`]
+    /// and the other pollers should not wait on the network to find out an alert was sent.
+    async fn record_alert(
+        &self,
+        tx: &mut Transaction,
+        origin_uid: Uuid,
+        alert: &Alert<'_>,
+    ) -> Result<Uuid> {
+        let locale = self.configuration.locale;
+
+        crate::persistence::insert_notification(
+            tx,
+            origin_uid,
+            alert.kind(),
+            &self.configuration.topic,
+            alert.subject(locale),
+            &alert.message(locale),
+            self.clock.now(),
+        )
+        .await
+    }
+
+    /// Sends an alert recorded by [`Poller::record_alert`] once it has been committed.
+    ///
+    /// If the topic cannot be reached the notification is removed again, so the cooldown does not
+    /// stop the next round from retrying it.
+    async fn deliver_alert(
+        &self,
+        origin_uid: Uuid,
+        notification_uid: Uuid,
+        alert: &Alert<'_>,
+    ) -> Result<()> {
+        let topic = &self.configuration.topic;
+        let locale = self.configuration.locale;
+        let subject = alert.subject(locale);
+        let message = alert.message(locale);
+
+        let sent = self.notifier.notify(topic, subject, &message).await;
+        let sent_at = self.clock.now();
+
+        let previous = self.notifier_health.borrow().clone();
+
+        self.notifier_health.send_modify(|health| match &sent {
+            Ok(()) => health.record_sent(sent_at),
+            Err(e) => health.record_failure(sent_at, e.to_string()),
+        });
+
+        self.alert_fallback_channel(&previous).await;
+
+        if let Err(e) = sent {
+            crate::persistence::delete_notification(&self.pool, notification_uid).await?;
+            return Err(e);
+        }
+
         tracing::info!(%origin_uid, %notification_uid, "routed a new notification");
 
+        if let Some(events) = &self.events {
+            events.publish(Event::NotificationSent {
+                origin_uid,
+                notification_uid,
+                subject: subject.to_owned(),
+                sent_at,
+            });
+        }
+
+        self.notify_watchers(origin_uid, alert).await?;
+        self.notify_push_subscribers(origin_uid, subject, &message)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Tells the fallback channel, if there is one, when the last alert made the notifier start or
+    /// stop failing.
+    async fn alert_fallback_channel(&self, previous: &NotifierHealth) {
+        let Some(fallback) = &self.fallback else {
+            return;
+        };
+
+        let current = self.notifier_health.borrow().clone();
+
+        let Some(alert) = fallback::meta_alert(&self.configuration.topic, previous, &current)
+        else {
+            return;
+        };
+
+        // The topic is already broken, so all that can be done if this fails too is log it
+        if let Err(e) = self
+            .slack
+            .notify(fallback.webhook_url(), alert.subject, &alert.message)
+            .await
+        {
+            tracing::error!(%e, "failed to send an alert to the fallback channel");
+        }
+    }
+
+    /// Sends an alert to the personal destination of everyone with the origin on their watchlist,
+    /// in the locale each destination asked for.
+    async fn notify_watchers(&self, origin_uid: Uuid, alert: &Alert<'_>) -> Result<()> {
+        let watchers = crate::persistence::fetch_watchers(&self.pool, origin_uid).await?;
+
+        for Watcher {
+            username,
+            destination,
+        } in watchers
+        {
+            let locale = destination.locale.unwrap_or(self.configuration.locale);
+            let subject = alert.subject(locale);
+            let message = alert.message(locale);
+
+            let result = match destination.kind {
+                DestinationKind::Sns => {
+                    self.notifier
+                        .notify(&destination.target, subject, &message)
+                        .await
+                }
+                DestinationKind::Slack => {
+                    self.slack
+                        .notify(&destination.target, subject, &message)
+                        .await
+                }
+            };
+
+            // A broken personal destination should not stop anyone else from being alerted
+            match result {
+                Ok(()) => tracing::info!(%origin_uid, %username, "routed a personal notification"),
+                Err(e) => {
+                    tracing::warn!(%origin_uid, %username, %e, "failed to route a personal notification")
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pushes an alert to every subscribed browser, forgetting subscriptions that have expired.
+    async fn notify_push_subscribers(
+        &self,
+        origin_uid: Uuid,
+        subject: &str,
+        message: &str,
+    ) -> Result<()> {
+        let Some(push) = &self.push else {
+            return Ok(());
+        };
+
+        let payload = serde_json::json!({
+            "title": subject,
+            "body": message,
+            "url": format!("/origins/{origin_uid}"),
+        })
+        .to_string();
+
+        let subscriptions = crate::persistence::fetch_push_subscriptions(&self.pool).await?;
+
+        for subscription in subscriptions {
+            match push.send(&subscription, payload.as_bytes()).await {
+                Ok(Delivery::Delivered) => {}
+                Ok(Delivery::Expired) => {
+                    crate::persistence::expire_push_subscription(
+                        &self.pool,
+                        &subscription.endpoint,
+                    )
+                    .await?;
+
+                    tracing::info!(%origin_uid, "removed an expired push subscription");
+                }
+                Err(e) => tracing::warn!(%origin_uid, %e, "failed to send a push notification"),
+            }
+        }
+
+        tracing::info!(%origin_uid, "pushed a notification to subscribed browsers");
+
         Ok(())
     }
 }
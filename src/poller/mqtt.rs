@@ -0,0 +1,521 @@
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use reqwest::Url;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::TlsConnector;
+use uuid::Uuid;
+
+use crate::persistence::Origin;
+use crate::poller::check::{Check, CheckContext, CheckOutcome};
+use crate::poller::protocol::{host_and_port, ProtocolFailure};
+use crate::poller::{clients, smtp, FailureReason};
+
+/// How long the broker waits without hearing from the poller before disconnecting it, which only
+/// needs to outlast a single check.
+const KEEP_ALIVE_SECONDS: u16 = 60;
+
+/// The largest packet that will be read, far above the acknowledgements and probe messages a
+/// check receives.
+const MAX_PACKET_BYTES: usize = 64 * 1024;
+
+/// The identifier of the only subscription a check makes.
+const SUBSCRIPTION_ID: u16 = 1;
+
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const SUBSCRIBE: u8 = 0x82;
+const SUBACK: u8 = 0x90;
+const PINGREQ: u8 = 0xc0;
+const PINGRESP: u8 = 0xd0;
+const DISCONNECT: u8 = 0xe0;
+
+/// A broker written as `mqtt://host:port` or `mqtts://host:port`, defaulting to ports 1883 and 8883
+/// respectively.
+///
+/// By default the poller connects and pings the broker. A `topic` parameter instead subscribes to
+/// the topic and publishes a message to it, waiting for the message to come back.
+#[derive(Debug, PartialEq, Eq)]
+pub struct MqttCheck {
+    host: String,
+    port: u16,
+    tls: bool,
+    topic: Option<String>,
+}
+
+impl MqttCheck {
+    pub fn parse(uri: &str) -> Option<Self> {
+        let url = Url::parse(uri).ok()?;
+
+        let (tls, default_port) = match url.scheme() {
+            "mqtt" => (false, 1883),
+            "mqtts" => (true, 8883),
+            _ => return None,
+        };
+
+        // An unencoded wildcard such as `sensors/#` starts a fragment rather than ending the topic
+        if url.fragment().is_some() {
+            return None;
+        }
+
+        let mut topic = None;
+
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                // Wildcards would match messages published by everything else as well
+                "topic" if !value.is_empty() && !value.contains(['+', '#']) => {
+                    topic = Some(value.into_owned())
+                }
+                _ => return None,
+            }
+        }
+
+        let (host, port) = host_and_port(&url, default_port)?;
+
+        Some(Self {
+            host,
+            port,
+            tls,
+            topic,
+        })
+    }
+}
+
+fn malformed(detail: &str) -> ProtocolFailure {
+    ProtocolFailure::new(
+        FailureReason::InvalidBody,
+        format!("sent a malformed packet: {detail}"),
+    )
+}
+
+/// Appends the remaining length of a packet in the variable length encoding MQTT uses.
+fn encode_length(buffer: &mut Vec<u8>, mut length: usize) {
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+
+        if length > 0 {
+            byte |= 0x80;
+        }
+
+        buffer.push(byte);
+
+        if length == 0 {
+            return;
+        }
+    }
+}
+
+/// Appends a string with the two byte length prefix MQTT uses.
+fn encode_string(buffer: &mut Vec<u8>, value: &str) {
+    buffer.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    buffer.extend_from_slice(value.as_bytes());
+}
+
+/// Builds a packet from its first byte and everything after the remaining length.
+fn packet(kind: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![kind];
+    encode_length(&mut packet, body.len());
+    packet.extend_from_slice(body);
+
+    packet
+}
+
+fn connect(client_id: &str, login: Option<&(String, String)>) -> Vec<u8> {
+    let mut body = Vec::new();
+    encode_string(&mut body, "MQTT");
+    body.push(4);
+
+    // Clean sessions leave nothing behind on the broker once the check disconnects
+    let mut flags = 0x02;
+
+    if login.is_some() {
+        flags |= 0x80 | 0x40;
+    }
+
+    body.push(flags);
+    body.extend_from_slice(&KEEP_ALIVE_SECONDS.to_be_bytes());
+    encode_string(&mut body, client_id);
+
+    if let Some((username, password)) = login {
+        encode_string(&mut body, username);
+        encode_string(&mut body, password);
+    }
+
+    packet(CONNECT, &body)
+}
+
+fn subscribe(topic: &str) -> Vec<u8> {
+    let mut body = SUBSCRIPTION_ID.to_be_bytes().to_vec();
+    encode_string(&mut body, topic);
+    body.push(0);
+
+    packet(SUBSCRIBE, &body)
+}
+
+fn publish(topic: &str, payload: &str) -> Vec<u8> {
+    let mut body = Vec::new();
+    encode_string(&mut body, topic);
+    body.extend_from_slice(payload.as_bytes());
+
+    packet(PUBLISH, &body)
+}
+
+/// Explains why a broker refused a connection, from the return code of its `CONNACK`.
+fn refusal(code: u8) -> ProtocolFailure {
+    let (reason, explanation) = match code {
+        1 => (
+            FailureReason::UnexpectedStatus,
+            "does not support MQTT 3.1.1",
+        ),
+        2 => (
+            FailureReason::UnexpectedStatus,
+            "rejected the client identifier",
+        ),
+        3 => (FailureReason::NotServing, "is unavailable"),
+        4 => (
+            FailureReason::AuthenticationFailed,
+            "rejected the username or password",
+        ),
+        5 => (FailureReason::AuthenticationFailed, "is not authorised"),
+        _ => (FailureReason::UnexpectedStatus, "refused the connection"),
+    };
+
+    ProtocolFailure::new(
+        reason,
+        format!("{explanation}, returning code {code} to CONNECT"),
+    )
+}
+
+/// Splits the body of a `PUBLISH` packet sent at QoS `qos` into its topic and payload.
+fn parse_publish(qos: u8, body: &[u8]) -> Option<(&str, &[u8])> {
+    let length = usize::from(u16::from_be_bytes([*body.first()?, *body.get(1)?]));
+    let topic = std::str::from_utf8(body.get(2..2 + length)?).ok()?;
+
+    // Messages above QoS 0 carry a packet identifier before their payload
+    let payload_start = if qos > 0 { 4 + length } else { 2 + length };
+
+    Some((topic, body.get(payload_start..)?))
+}
+
+struct Session<S> {
+    stream: S,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Session<S> {
+    async fn send(&mut self, packet: &[u8]) -> Result<(), ProtocolFailure> {
+        self.stream
+            .write_all(packet)
+            .await
+            .map_err(ProtocolFailure::disconnected)?;
+
+        self.stream
+            .flush()
+            .await
+            .map_err(ProtocolFailure::disconnected)
+    }
+
+    /// Reads the next packet, returning its first byte and its body.
+    async fn receive(&mut self) -> Result<(u8, Vec<u8>), ProtocolFailure> {
+        let read = |e: std::io::Error| match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => ProtocolFailure::new(
+                FailureReason::ConnectionFailure,
+                String::from("connection was closed by the broker"),
+            ),
+            _ => ProtocolFailure::disconnected(e),
+        };
+
+        let kind = self.stream.read_u8().await.map_err(read)?;
+        let mut length = 0;
+
+        for shift in 0..4 {
+            let byte = self.stream.read_u8().await.map_err(read)?;
+            length |= usize::from(byte & 0x7f) << (7 * shift);
+
+            if byte & 0x80 == 0 {
+                break;
+            }
+
+            if shift == 3 {
+                return Err(malformed("the remaining length is too long"));
+            }
+        }
+
+        if length > MAX_PACKET_BYTES {
+            return Err(malformed(&format!(
+                "a packet of {length} bytes is larger than {MAX_PACKET_BYTES}"
+            )));
+        }
+
+        let mut body = vec![0; length];
+        self.stream.read_exact(&mut body).await.map_err(read)?;
+
+        Ok((kind, body))
+    }
+
+    /// Reads the next packet, failing unless it is of the `expected` kind.
+    async fn expect(&mut self, expected: u8, name: &str) -> Result<Vec<u8>, ProtocolFailure> {
+        let (kind, body) = self.receive().await?;
+
+        if kind & 0xf0 != expected {
+            return Err(ProtocolFailure::new(
+                FailureReason::UnexpectedStatus,
+                format!("sent packet type {} instead of {name}", kind >> 4),
+            ));
+        }
+
+        Ok(body)
+    }
+
+    async fn connect(&mut self, login: Option<&(String, String)>) -> Result<(), ProtocolFailure> {
+        // Identifiers of at most 23 characters must be accepted by every broker
+        let client_id = format!("uptime-{}", &Uuid::new_v4().simple().to_string()[..16]);
+
+        self.send(&connect(&client_id, login)).await?;
+
+        let body = self.expect(CONNACK, "CONNACK").await?;
+
+        match body[..] {
+            [_, 0] => Ok(()),
+            [_, code] => Err(refusal(code)),
+            _ => Err(malformed("CONNACK is not 2 bytes long")),
+        }
+    }
+
+    async fn ping(&mut self) -> Result<(), ProtocolFailure> {
+        self.send(&[PINGREQ, 0]).await?;
+        self.expect(PINGRESP, "PINGRESP").await?;
+
+        Ok(())
+    }
+
+    /// Subscribes to `topic` and publishes a unique message to it, waiting for the broker to
+    /// deliver it back.
+    async fn round_trip(&mut self, topic: &str) -> Result<(), ProtocolFailure> {
+        self.send(&subscribe(topic)).await?;
+
+        let body = self.expect(SUBACK, "SUBACK").await?;
+
+        // A return code of 0x80 means the broker refused the subscription, such as for its ACL
+        let granted = body.get(2).is_some_and(|code| *code != 0x80);
+
+        if !granted {
+            return Err(ProtocolFailure::new(
+                FailureReason::AuthenticationFailed,
+                format!("refused the subscription to {topic}"),
+            ));
+        }
+
+        let payload = Uuid::new_v4().to_string();
+        self.send(&publish(topic, &payload)).await?;
+
+        // Others may publish to the topic too, so only the message the check sent counts
+        loop {
+            let (kind, body) = self.receive().await?;
+
+            if kind & 0xf0 != PUBLISH {
+                continue;
+            }
+
+            let qos = (kind >> 1) & 0x03;
+            let (received_topic, received) =
+                parse_publish(qos, &body).ok_or_else(|| malformed("PUBLISH is truncated"))?;
+
+            if received_topic == topic && received == payload.as_bytes() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Ends the session politely, ignoring brokers that have already hung up.
+    async fn disconnect(mut self) {
+        let _ = self.send(&[DISCONNECT, 0]).await;
+    }
+}
+
+async fn converse<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: S,
+    check: &MqttCheck,
+    login: Option<&(String, String)>,
+) -> Result<(), ProtocolFailure> {
+    let mut session = Session { stream };
+    session.connect(login).await?;
+
+    match &check.topic {
+        Some(topic) => session.round_trip(topic).await?,
+        None => session.ping().await?,
+    }
+
+    session.disconnect().await;
+
+    Ok(())
+}
+
+async fn open(
+    connector: &TlsConnector,
+    check: &MqttCheck,
+    login: Option<&(String, String)>,
+) -> Result<(), ProtocolFailure> {
+    let stream = TcpStream::connect((check.host.as_str(), check.port))
+        .await
+        .map_err(|e| {
+            ProtocolFailure::new(
+                FailureReason::ConnectionFailure,
+                format!("could not connect: {e}"),
+            )
+        })?;
+
+    if !check.tls {
+        return converse(stream, check, login).await;
+    }
+
+    let server_name = ServerName::try_from(check.host.as_str()).map_err(|_| {
+        ProtocolFailure::new(
+            FailureReason::BadRequest,
+            format!("{} cannot be used to verify a certificate", check.host),
+        )
+    })?;
+
+    let stream = connector
+        .connect(server_name.to_owned(), stream)
+        .await
+        .map_err(|e| {
+            ProtocolFailure::new(
+                FailureReason::ConnectionFailure,
+                format!("TLS handshake failed: {e}"),
+            )
+        })?;
+
+    converse(stream, check, login).await
+}
+
+/// Connects to the broker and pings it or makes a round trip through its topic.
+pub async fn check(
+    connector: &TlsConnector,
+    check: &MqttCheck,
+    login: Option<&(String, String)>,
+    timeout: Duration,
+) -> Result<(), ProtocolFailure> {
+    tokio::time::timeout(timeout, open(connector, check, login))
+        .await
+        .map_err(|_| {
+            let waiting_for = match &check.topic {
+                Some(topic) => format!("the message published to {topic}"),
+                None => String::from("the conversation"),
+            };
+
+            ProtocolFailure::new(
+                FailureReason::RequestTimeout,
+                format!("did not finish {waiting_for} within {timeout:?}"),
+            )
+        })?
+}
+
+/// Connects to origins written as `mqtt://host:port`, logging in with the origin's credentials if
+/// it has any.
+pub struct MqttChecker {
+    connector: TlsConnector,
+}
+
+impl Default for MqttChecker {
+    fn default() -> Self {
+        Self {
+            connector: smtp::connector(),
+        }
+    }
+}
+
+impl Check for MqttChecker {
+    fn execute<'a>(
+        &'a self,
+        origin: &'a Origin,
+        context: &'a CheckContext<'a>,
+    ) -> BoxFuture<'a, CheckOutcome> {
+        Box::pin(async move {
+            let Some(parsed) = MqttCheck::parse(&origin.uri) else {
+                return CheckOutcome::new(Err(FailureReason::BadRequest));
+            };
+
+            let login = match clients::username_and_password(origin, context.credentials) {
+                Ok(login) => login,
+                Err(detail) => return CheckOutcome::failed(FailureReason::BadRequest, detail),
+            };
+
+            match check(&self.connector, &parsed, login.as_ref(), context.timeout).await {
+                Ok(()) => CheckOutcome::new(Ok((None, context.elapsed_millis()))),
+                Err(failure) => CheckOutcome::failed(failure.reason, failure.detail),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::poller::mqtt::MqttCheck;
+
+    #[test]
+    fn checks_are_parsed_from_uris() {
+        assert_eq!(
+            MqttCheck::parse("mqtt://broker.example.com"),
+            Some(MqttCheck {
+                host: String::from("broker.example.com"),
+                port: 1883,
+                tls: false,
+                topic: None,
+            })
+        );
+        assert_eq!(
+            MqttCheck::parse("mqtts://broker.example.com?topic=uptime/probe"),
+            Some(MqttCheck {
+                host: String::from("broker.example.com"),
+                port: 8883,
+                tls: true,
+                topic: Some(String::from("uptime/probe")),
+            })
+        );
+
+        assert_eq!(
+            MqttCheck::parse("mqtt://broker.example.com?topic=sensors/#"),
+            None
+        );
+        assert_eq!(MqttCheck::parse("mqtt://broker.example.com?topic="), None);
+        assert_eq!(MqttCheck::parse("mqtt://broker.example.com?qos=1"), None);
+        assert_eq!(MqttCheck::parse("https://broker.example.com"), None);
+    }
+
+    #[test]
+    fn remaining_lengths_use_seven_bits_a_byte() {
+        let encoded = |length| {
+            let mut buffer = Vec::new();
+            super::encode_length(&mut buffer, length);
+            buffer
+        };
+
+        assert_eq!(encoded(0), [0x00]);
+        assert_eq!(encoded(127), [0x7f]);
+        assert_eq!(encoded(128), [0x80, 0x01]);
+        assert_eq!(encoded(16_383), [0xff, 0x7f]);
+        assert_eq!(encoded(16_384), [0x80, 0x80, 0x01]);
+    }
+
+    #[test]
+    fn published_messages_are_split_into_topics_and_payloads() {
+        let body = [&[0, 5][..], b"a/b/c", b"hello"].concat();
+
+        assert_eq!(
+            super::parse_publish(0, &body),
+            Some(("a/b/c", &b"hello"[..]))
+        );
+
+        let body = [&[0, 5][..], b"a/b/c", &[0, 7], b"hello"].concat();
+
+        assert_eq!(
+            super::parse_publish(1, &body),
+            Some(("a/b/c", &b"hello"[..]))
+        );
+        assert_eq!(super::parse_publish(0, &[0, 5, b'a']), None);
+    }
+}
@@ -0,0 +1,142 @@
+use color_eyre::eyre::{eyre, Result};
+use reqwest::Url;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+
+/// A publish-only connection to a NATS server, opened on first use and reopened after errors.
+pub struct NatsConnection {
+    pub(super) address: String,
+    stream: Option<TcpStream>,
+}
+
+impl NatsConnection {
+    /// Parses `nats://host:port/subject`, returning the connection and the subject, with any
+    /// slashes in the path treated as separators between tokens.
+    pub fn from_url(url: &Url) -> Result<(Self, String)> {
+        let host = url
+            .host_str()
+            .ok_or_else(|| eyre!("NATS URLs need a host"))?;
+        let port = url.port().unwrap_or(4222);
+        let subject = url.path().trim_start_matches('/').replace('/', ".");
+
+        if subject.is_empty() || subject.contains(char::is_whitespace) {
+            return Err(eyre!(
+                "NATS URLs need a subject, such as nats://localhost:4222/uptime"
+            ));
+        }
+
+        let connection = Self {
+            address: format!("{host}:{port}"),
+            stream: None,
+        };
+
+        Ok((connection, subject))
+    }
+
+    pub async fn publish(&mut self, subject: &str, payload: &[u8]) -> Result<()> {
+        if self.stream.is_none() {
+            self.stream = Some(connect(&self.address).await?);
+        }
+
+        let stream = self
+            .stream
+            .as_mut()
+            .expect("connection was just established");
+        let result = publish(stream, subject, payload).await;
+
+        // Reconnect on the next message rather than writing to a broken connection
+        if result.is_err() {
+            self.stream = None;
+        }
+
+        result
+    }
+}
+
+async fn connect(address: &str) -> Result<TcpStream> {
+    let mut stream = TcpStream::connect(address).await?;
+
+    stream
+        .write_all(b"CONNECT {\"verbose\":false,\"pedantic\":false}\r\n")
+        .await?;
+
+    Ok(stream)
+}
+
+async fn publish(stream: &mut TcpStream, subject: &str, payload: &[u8]) -> Result<()> {
+    // The server periodically pings idle clients and disconnects those that never answer
+    let mut buffer = [0; 4096];
+
+    loop {
+        match stream.try_read(&mut buffer) {
+            Ok(0) => return Err(eyre!("the NATS server closed the connection")),
+            Ok(length) => {
+                if buffer[..length].windows(4).any(|w| w == b"PING") {
+                    stream.write_all(b"PONG\r\n").await?;
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let mut message = format!("PUB {subject} {}\r\n", payload.len()).into_bytes();
+    message.extend_from_slice(payload);
+    message.extend_from_slice(b"\r\n");
+
+    stream.write_all(&message).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use color_eyre::eyre::Result;
+    use reqwest::Url;
+    use tokio::io::AsyncReadExt;
+
+    use crate::poller::nats::NatsConnection;
+
+    #[test]
+    fn subjects_are_taken_from_the_path() -> Result<()> {
+        let (connection, subject) =
+            NatsConnection::from_url(&Url::parse("nats://localhost/uptime/checks")?)?;
+
+        assert_eq!(connection.address, "localhost:4222");
+        assert_eq!(subject, "uptime.checks");
+
+        assert!(NatsConnection::from_url(&Url::parse("nats://localhost:4222")?).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn messages_are_published() -> Result<()> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = Url::parse(&format!("nats://{}/uptime", listener.local_addr()?))?;
+
+        let (mut connection, subject) = NatsConnection::from_url(&url)?;
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await?;
+            let mut received = Vec::new();
+
+            while !received.ends_with(b"{}\r\n") {
+                let mut buffer = [0; 512];
+                let length = stream.read(&mut buffer).await?;
+                received.extend_from_slice(&buffer[..length]);
+            }
+
+            Ok::<_, std::io::Error>(String::from_utf8_lossy(&received).into_owned())
+        });
+
+        connection.publish(&subject, b"{}").await?;
+
+        let received = server.await??;
+
+        assert!(received.starts_with("CONNECT "));
+        assert!(received.ends_with("PUB uptime 2\r\n{}\r\n"));
+
+        Ok(())
+    }
+}
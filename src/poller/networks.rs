@@ -0,0 +1,115 @@
+use std::net::IpAddr;
+
+use color_eyre::eyre::{eyre, Result};
+
+/// A block of addresses written in CIDR notation, such as `203.0.113.0/24`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Network {
+    address: IpAddr,
+    prefix_len: u32,
+}
+
+impl Network {
+    fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        let invalid = || eyre!("'{value}' is not an address or network such as 203.0.113.0/24");
+
+        let (address, prefix_len) = match value.split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len)),
+            None => (value, None),
+        };
+
+        let address: IpAddr = address.parse().map_err(|_| invalid())?;
+        let max_len = match address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len.parse().map_err(|_| invalid())?,
+            None => max_len,
+        };
+
+        if prefix_len > max_len {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            address,
+            prefix_len,
+        })
+    }
+
+    fn contains(&self, address: IpAddr) -> bool {
+        match (self.address, address) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_len).unwrap_or(0);
+                u32::from(network) & mask == u32::from(address) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_len).unwrap_or(0);
+                u128::from(network) & mask == u128::from(address) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The networks an origin is expected to be served from, such as the ranges of its hosting
+/// provider.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExpectedNetworks {
+    networks: Vec<Network>,
+}
+
+impl ExpectedNetworks {
+    /// Parses a comma separated list of addresses and networks.
+    pub fn parse(value: &str) -> Result<Self> {
+        let networks = value
+            .split(',')
+            .map(Network::parse)
+            .collect::<Result<_>>()?;
+
+        Ok(Self { networks })
+    }
+
+    pub fn contains(&self, address: IpAddr) -> bool {
+        self.networks
+            .iter()
+            .any(|network| network.contains(address))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use color_eyre::eyre::Result;
+
+    use crate::poller::networks::ExpectedNetworks;
+
+    #[test]
+    fn addresses_and_networks_can_be_combined() -> Result<()> {
+        let expected = ExpectedNetworks::parse("203.0.113.0/24, 198.51.100.7,2001:db8::/32")?;
+
+        assert!(expected.contains("203.0.113.1".parse()?));
+        assert!(expected.contains("203.0.113.255".parse()?));
+        assert!(expected.contains("198.51.100.7".parse()?));
+        assert!(expected.contains("2001:db8::1".parse()?));
+        assert!(!expected.contains("203.0.114.1".parse()?));
+        assert!(!expected.contains("198.51.100.8".parse()?));
+        assert!(!expected.contains("2001:db9::1".parse()?));
+
+        // Everything is within a network with no prefix
+        assert!(ExpectedNetworks::parse("0.0.0.0/0")?.contains("192.0.2.1".parse()?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_lists_are_rejected() {
+        assert!(ExpectedNetworks::parse("").is_err());
+        assert!(ExpectedNetworks::parse("example.com").is_err());
+        assert!(ExpectedNetworks::parse("203.0.113.0/33").is_err());
+        assert!(ExpectedNetworks::parse("2001:db8::/129").is_err());
+        assert!(ExpectedNetworks::parse("203.0.113.0/24,").is_err());
+    }
+}
@@ -0,0 +1,125 @@
+use color_eyre::eyre::{eyre, Result};
+
+use crate::poller::slack::SlackWebhook;
+use crate::poller::Notifier;
+
+/// A way of sending alerts that this build was compiled with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NotifierKind {
+    /// Publishes to an SNS topic, which needs the `sns` feature.
+    #[cfg(feature = "sns")]
+    Sns,
+    /// Posts to a Slack incoming webhook.
+    Slack,
+}
+
+impl NotifierKind {
+    /// Every kind of notifier this build can send alerts through, the first being the default.
+    pub const COMPILED: &'static [Self] = &[
+        #[cfg(feature = "sns")]
+        Self::Sns,
+        Self::Slack,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "sns")]
+            Self::Sns => "sns",
+            Self::Slack => "slack",
+        }
+    }
+
+    /// The variable holding where alerts are sent, such as the ARN of the SNS topic.
+    pub fn topic_variable(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "sns")]
+            Self::Sns => "SNS_TOPIC",
+            Self::Slack => "SLACK_WEBHOOK_URL",
+        }
+    }
+
+    /// The kind of notifier named in `NOTIFIER`, or the default if it is not set.
+    pub fn from_env() -> Result<Self> {
+        let Ok(name) = std::env::var("NOTIFIER") else {
+            return Ok(Self::COMPILED[0]);
+        };
+
+        Self::parse(&name)
+    }
+
+    fn parse(name: &str) -> Result<Self> {
+        Self::COMPILED
+            .iter()
+            .copied()
+            .find(|kind| kind.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::COMPILED.iter().map(Self::name).collect();
+
+                eyre!(
+                    "NOTIFIER '{name}' is not one of {}, which are the notifiers this build includes",
+                    names.join(", ")
+                )
+            })
+    }
+
+    /// Builds the notifier, loading any credentials it needs from the environment.
+    pub async fn build(self, http_client: reqwest::Client) -> AnyNotifier {
+        match self {
+            #[cfg(feature = "sns")]
+            Self::Sns => {
+                let sdk_config =
+                    aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+                AnyNotifier::Sns(aws_sdk_sns::Client::new(&sdk_config))
+            }
+            Self::Slack => AnyNotifier::Slack(SlackWebhook::new(http_client)),
+        }
+    }
+}
+
+/// Whichever notifier was chosen at runtime, so the poller and digest do not need to know.
+#[derive(Clone)]
+pub enum AnyNotifier {
+    #[cfg(feature = "sns")]
+    Sns(aws_sdk_sns::Client),
+    Slack(SlackWebhook),
+}
+
+impl Notifier for AnyNotifier {
+    async fn notify(&self, topic: &str, subject: &str, message: &str) -> Result<()> {
+        match self {
+            #[cfg(feature = "sns")]
+            Self::Sns(client) => client.notify(topic, subject, message).await,
+            Self::Slack(webhook) => webhook.notify(topic, subject, message).await,
+        }
+    }
+}
+
+#[cfg(feature = "sns")]
+impl Notifier for aws_sdk_sns::Client {
+    async fn notify(&self, topic: &str, subject: &str, message: &str) -> Result<()> {
+        self.publish()
+            .topic_arn(topic)
+            .subject(subject)
+            .message(message)
+            .send()
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::poller::notifiers::NotifierKind;
+
+    #[test]
+    fn notifiers_are_chosen_from_those_compiled_in() {
+        assert_eq!(NotifierKind::parse("slack").unwrap(), NotifierKind::Slack);
+
+        #[cfg(feature = "sns")]
+        assert_eq!(NotifierKind::parse("sns").unwrap(), NotifierKind::Sns);
+
+        assert!(NotifierKind::parse("carrier-pigeon").is_err());
+    }
+}
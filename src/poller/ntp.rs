@@ -0,0 +1,353 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use futures_util::future::BoxFuture;
+use reqwest::Url;
+use tokio::net::UdpSocket;
+
+use crate::persistence::Origin;
+use crate::poller::check::{Check, CheckContext, CheckOutcome};
+use crate::poller::protocol::{host_and_port, ProtocolFailure};
+use crate::poller::FailureReason;
+
+/// How far a server's clock can be from the poller's before the check fails, if the origin does
+/// not say otherwise.
+const DEFAULT_MAX_OFFSET_MILLIS: i64 = 1000;
+
+/// Seconds between the NTP epoch in 1900 and the Unix epoch in 1970.
+const UNIX_EPOCH_NTP_SECONDS: i64 = 2_208_988_800;
+
+/// The length of an NTP packet without extensions or authentication.
+const PACKET_BYTES: usize = 48;
+
+/// A request from a version 4 client, with no leap second warning.
+const CLIENT_REQUEST: u8 = 0b00_100_011;
+
+/// A time server written as `ntp://host:port`, defaulting to port 123, whose clock is compared
+/// with the poller's.
+///
+/// The query can set how far apart the clocks can be in milliseconds with `max_offset_ms`, as in
+/// `ntp://time.example.com?max_offset_ms=250`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct NtpCheck {
+    host: String,
+    port: u16,
+    max_offset_millis: i64,
+}
+
+impl NtpCheck {
+    pub fn parse(uri: &str) -> Option<Self> {
+        let url = Url::parse(uri).ok()?;
+
+        if url.scheme() != "ntp" {
+            return None;
+        }
+
+        let mut max_offset_millis = DEFAULT_MAX_OFFSET_MILLIS;
+
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "max_offset_ms" => max_offset_millis = value.parse().ok().filter(|ms| *ms > 0)?,
+                _ => return None,
+            }
+        }
+
+        let (host, port) = host_and_port(&url, 123)?;
+
+        Some(Self {
+            host,
+            port,
+            max_offset_millis,
+        })
+    }
+}
+
+/// How the server's clock compared with the poller's.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Measurement {
+    /// How far ahead of the poller's clock the server's is, which is negative if it is behind.
+    pub offset_millis: i64,
+    /// How long the request and response spent travelling, without the time the server took.
+    pub round_trip_millis: i64,
+}
+
+/// Encodes a time as an NTP timestamp, in seconds since 1900 with a 32 bit fraction.
+fn to_timestamp(at: DateTime<Utc>) -> u64 {
+    let seconds = (at.timestamp() + UNIX_EPOCH_NTP_SECONDS) as u64;
+    let fraction = (u64::from(at.timestamp_subsec_nanos()) << 32) / 1_000_000_000;
+
+    (seconds << 32) | fraction
+}
+
+/// Decodes an NTP timestamp into nanoseconds since the Unix epoch.
+fn to_unix_nanos(timestamp: u64) -> i64 {
+    let mut seconds = (timestamp >> 32) as i64;
+
+    // The seconds wrap around in 2036, after which timestamps without the top bit set are in the
+    // next era
+    if seconds & 0x8000_0000 == 0 {
+        seconds += 1 << 32;
+    }
+
+    // Rounded to the nearest nanosecond so times survive being encoded and decoded again
+    let nanos = ((timestamp & 0xffff_ffff) * 1_000_000_000 + (1 << 31)) >> 32;
+
+    (seconds - UNIX_EPOCH_NTP_SECONDS) * 1_000_000_000 + nanos as i64
+}
+
+fn request(sent_at: DateTime<Utc>) -> [u8; PACKET_BYTES] {
+    let mut packet = [0; PACKET_BYTES];
+
+    packet[0] = CLIENT_REQUEST;
+    packet[40..48].copy_from_slice(&to_timestamp(sent_at).to_be_bytes());
+
+    packet
+}
+
+fn timestamp_at(packet: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&packet[offset..offset + 8]);
+
+    u64::from_be_bytes(bytes)
+}
+
+/// Compares the server's clock with the poller's from its `response` to a request sent at
+/// `sent_at`, which arrived at `received_at`.
+fn measure(
+    response: &[u8],
+    sent_at: DateTime<Utc>,
+    received_at: DateTime<Utc>,
+) -> Result<Measurement, ProtocolFailure> {
+    let invalid =
+        |detail: &str| ProtocolFailure::new(FailureReason::InvalidBody, detail.to_owned());
+
+    if response.len() < PACKET_BYTES {
+        return Err(invalid("answered with a truncated packet"));
+    }
+
+    let leap_indicator = response[0] >> 6;
+    let mode = response[0] & 0b111;
+    let stratum = response[1];
+
+    if mode != 4 {
+        return Err(invalid("did not answer as a server"));
+    }
+
+    // Servers send a kiss code such as RATE or DENY instead of the time when they refuse to serve
+    if stratum == 0 {
+        let code = String::from_utf8_lossy(&response[12..16]);
+
+        return Err(ProtocolFailure::new(
+            FailureReason::NotServing,
+            format!(
+                "refused to answer with kiss code {}",
+                code.trim_end_matches('\0')
+            ),
+        ));
+    }
+
+    if leap_indicator == 3 {
+        return Err(ProtocolFailure::new(
+            FailureReason::NotServing,
+            String::from("is not synchronised to a reference clock"),
+        ));
+    }
+
+    // Answers to someone else's request, or forged ones, do not echo our transmit time
+    if timestamp_at(response, 24) != to_timestamp(sent_at) {
+        return Err(invalid("answered a different request"));
+    }
+
+    let sent = to_unix_nanos(to_timestamp(sent_at));
+    let server_received = to_unix_nanos(timestamp_at(response, 32));
+    let server_sent = to_unix_nanos(timestamp_at(response, 40));
+    let received = to_unix_nanos(to_timestamp(received_at));
+
+    let offset = ((server_received - sent) + (server_sent - received)) / 2;
+    let round_trip = (received - sent) - (server_sent - server_received);
+
+    Ok(Measurement {
+        offset_millis: offset / 1_000_000,
+        round_trip_millis: round_trip.max(0) / 1_000_000,
+    })
+}
+
+async fn exchange(check: &NtpCheck) -> Result<Measurement, ProtocolFailure> {
+    let connection_failure =
+        |detail: String| ProtocolFailure::new(FailureReason::ConnectionFailure, detail);
+
+    let address = tokio::net::lookup_host((check.host.as_str(), check.port))
+        .await
+        .ok()
+        .and_then(|mut addresses| addresses.next())
+        .ok_or_else(|| connection_failure(format!("could not resolve {}", check.host)))?;
+
+    let local: SocketAddr = match address {
+        SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+        SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+    };
+
+    let socket = UdpSocket::bind(local)
+        .await
+        .map_err(|e| connection_failure(format!("could not open a socket: {e}")))?;
+
+    socket
+        .connect(address)
+        .await
+        .map_err(|e| connection_failure(format!("could not reach {address}: {e}")))?;
+
+    let sent_at = Utc::now();
+
+    socket
+        .send(&request(sent_at))
+        .await
+        .map_err(|e| connection_failure(format!("could not send the request: {e}")))?;
+
+    let mut response = [0; 1024];
+
+    let length = socket
+        .recv(&mut response)
+        .await
+        .map_err(|e| connection_failure(format!("did not receive an answer: {e}")))?;
+
+    measure(&response[..length], sent_at, Utc::now())
+}
+
+/// Asks the server for the time and fails if its clock is further from the poller's than the
+/// check allows.
+pub async fn check(check: &NtpCheck, timeout: Duration) -> Result<Measurement, ProtocolFailure> {
+    let measurement = tokio::time::timeout(timeout, exchange(check))
+        .await
+        .map_err(|_| {
+            ProtocolFailure::new(
+                FailureReason::RequestTimeout,
+                format!("did not answer within {timeout:?}"),
+            )
+        })??;
+
+    if measurement.offset_millis.abs() > check.max_offset_millis {
+        let direction = if measurement.offset_millis > 0 {
+            "ahead of"
+        } else {
+            "behind"
+        };
+
+        return Err(ProtocolFailure::new(
+            FailureReason::ClockDrift,
+            format!(
+                "the clock is {}ms {direction} the poller's, more than the {}ms allowed",
+                measurement.offset_millis.abs(),
+                check.max_offset_millis
+            ),
+        ));
+    }
+
+    Ok(measurement)
+}
+
+/// Compares the clock of origins written as `ntp://host:port` with the poller's, recording the
+/// round trip as the latency.
+pub struct NtpChecker;
+
+impl Check for NtpChecker {
+    fn execute<'a>(
+        &'a self,
+        origin: &'a Origin,
+        context: &'a CheckContext<'a>,
+    ) -> BoxFuture<'a, CheckOutcome> {
+        Box::pin(async move {
+            let Some(parsed) = NtpCheck::parse(&origin.uri) else {
+                return CheckOutcome::new(Err(FailureReason::BadRequest));
+            };
+
+            match check(&parsed, context.timeout).await {
+                Ok(measurement) => CheckOutcome {
+                    clock_offset_millis: Some(measurement.offset_millis),
+                    ..CheckOutcome::new(Ok((None, measurement.round_trip_millis)))
+                },
+                Err(failure) => CheckOutcome::failed(failure.reason, failure.detail),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use crate::poller::ntp::NtpCheck;
+    use crate::poller::FailureReason;
+
+    #[test]
+    fn checks_are_parsed_from_uris() {
+        assert_eq!(
+            NtpCheck::parse("ntp://time.example.com"),
+            Some(NtpCheck {
+                host: String::from("time.example.com"),
+                port: 123,
+                max_offset_millis: 1000,
+            })
+        );
+        assert_eq!(
+            NtpCheck::parse("ntp://10.0.0.1:1123?max_offset_ms=250"),
+            Some(NtpCheck {
+                host: String::from("10.0.0.1"),
+                port: 1123,
+                max_offset_millis: 250,
+            })
+        );
+
+        assert_eq!(
+            NtpCheck::parse("ntp://time.example.com?max_offset_ms=0"),
+            None
+        );
+        assert_eq!(NtpCheck::parse("ntp://time.example.com?stratum=2"), None);
+    }
+
+    #[test]
+    fn timestamps_survive_a_round_trip() {
+        let at =
+            Utc.with_ymd_and_hms(2026, 10, 16, 9, 30, 15).unwrap() + Duration::milliseconds(250);
+        let nanos = super::to_unix_nanos(super::to_timestamp(at));
+
+        assert!((nanos - at.timestamp_nanos_opt().unwrap()).abs() < 10);
+
+        // Times after the seconds wrap around in 2036 are still understood
+        let at = Utc.with_ymd_and_hms(2040, 1, 1, 0, 0, 0).unwrap();
+        let nanos = super::to_unix_nanos(super::to_timestamp(at));
+
+        assert_eq!(nanos / 1_000_000_000, at.timestamp());
+    }
+
+    #[test]
+    fn offsets_are_measured_from_the_four_timestamps() {
+        let sent_at = Utc.with_ymd_and_hms(2026, 10, 16, 9, 30, 0).unwrap();
+        let received_at = sent_at + Duration::milliseconds(40);
+
+        // A server 2 seconds ahead that took 10ms to answer
+        let mut response = super::request(sent_at);
+        response[0] = 0b00_100_100;
+        response[1] = 2;
+        response[24..32].copy_from_slice(&super::to_timestamp(sent_at).to_be_bytes());
+
+        let server_received = sent_at + Duration::milliseconds(2015);
+        let server_sent = server_received + Duration::milliseconds(10);
+        response[32..40].copy_from_slice(&super::to_timestamp(server_received).to_be_bytes());
+        response[40..48].copy_from_slice(&super::to_timestamp(server_sent).to_be_bytes());
+
+        let measurement = super::measure(&response, sent_at, received_at).unwrap();
+
+        assert_eq!(measurement.offset_millis, 2000);
+        assert_eq!(measurement.round_trip_millis, 30);
+
+        // Kiss codes are passed on
+        response[1] = 0;
+        response[12..16].copy_from_slice(b"RATE");
+
+        let failure = super::measure(&response, sent_at, received_at).unwrap_err();
+
+        assert_eq!(failure.reason, FailureReason::NotServing);
+        assert_eq!(failure.detail, "refused to answer with kiss code RATE");
+    }
+}
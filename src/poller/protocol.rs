@@ -0,0 +1,57 @@
+use reqwest::Url;
+
+use crate::poller::FailureReason;
+
+/// The host and port a check of `url` connects to, using `default_port` if it does not name one.
+pub fn host_and_port(url: &Url, default_port: u16) -> Option<(String, u16)> {
+    // IPv6 addresses are bracketed in URLs but not when connecting
+    let host = url
+        .host_str()?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_owned();
+
+    Some((host, url.port().unwrap_or(default_port)))
+}
+
+/// Why a check failed, along with what the server said so problems can be diagnosed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ProtocolFailure {
+    pub reason: FailureReason,
+    pub detail: String,
+}
+
+impl ProtocolFailure {
+    pub fn new(reason: FailureReason, detail: String) -> Self {
+        Self { reason, detail }
+    }
+
+    pub fn disconnected(error: std::io::Error) -> Self {
+        Self::new(
+            FailureReason::ConnectionFailure,
+            format!("connection was lost: {error}"),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+
+    use crate::poller::protocol::host_and_port;
+
+    #[test]
+    fn hosts_are_unbracketed_and_ports_default() {
+        let url = Url::parse("smtp://mail.example.com").unwrap();
+        assert_eq!(
+            host_and_port(&url, 25),
+            Some((String::from("mail.example.com"), 25))
+        );
+
+        let url = Url::parse("smtp://[::1]:2525").unwrap();
+        assert_eq!(host_and_port(&url, 25), Some((String::from("::1"), 2525)));
+
+        let url = Url::parse("smtp:no-host").unwrap();
+        assert_eq!(host_and_port(&url, 25), None);
+    }
+}
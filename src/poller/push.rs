@@ -0,0 +1,342 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Utc;
+use color_eyre::eyre::{eyre, Result};
+use reqwest::header::{AUTHORIZATION, CONTENT_ENCODING, CONTENT_TYPE};
+use reqwest::{StatusCode, Url};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use ring::{aead, agreement, hkdf};
+use serde_json::json;
+
+use crate::persistence::PushSubscription;
+
+/// How long push services should hold on to an alert for an offline browser, in seconds.
+const TIME_TO_LIVE: u32 = 24 * 60 * 60;
+
+/// The size of each encrypted record, which only matters to the receiver as payloads fit in one.
+const RECORD_SIZE: u32 = 4096;
+
+/// How long the VAPID token presented to push services is valid for, in seconds.
+const TOKEN_LIFETIME: i64 = 12 * 60 * 60;
+
+/// The application server keys from RFC 8292 that identify us to push services.
+pub struct VapidKeys {
+    key_pair: EcdsaKeyPair,
+    /// A `mailto:` or `https:` URI push services can use to contact us.
+    subject: String,
+}
+
+impl VapidKeys {
+    pub fn new(key_pair: EcdsaKeyPair, subject: String) -> Self {
+        Self { key_pair, subject }
+    }
+
+    /// Reads the base64url encoded `VAPID_PUBLIC_KEY` and `VAPID_PRIVATE_KEY` along with
+    /// `VAPID_SUBJECT`, returning `None` if Web Push has not been configured.
+    pub fn from_env() -> Result<Option<Self>> {
+        let (Ok(public_key), Ok(private_key)) = (
+            std::env::var("VAPID_PUBLIC_KEY"),
+            std::env::var("VAPID_PRIVATE_KEY"),
+        ) else {
+            return Ok(None);
+        };
+
+        let subject = std::env::var("VAPID_SUBJECT")
+            .map_err(|_| eyre!("VAPID_SUBJECT must be set when VAPID keys are configured"))?;
+
+        let key_pair = EcdsaKeyPair::from_private_key_and_public_key(
+            &ECDSA_P256_SHA256_FIXED_SIGNING,
+            &decode(&private_key)?,
+            &decode(&public_key)?,
+            &SystemRandom::new(),
+        )
+        .map_err(|e| eyre!("invalid VAPID keys: {e}"))?;
+
+        Ok(Some(Self::new(key_pair, subject)))
+    }
+
+    /// The key browsers need when subscribing, encoded as base64url.
+    pub fn public_key(&self) -> String {
+        URL_SAFE_NO_PAD.encode(self.key_pair.public_key().as_ref())
+    }
+
+    /// Creates the signed token that authorises a push to the given endpoint.
+    fn token(&self, endpoint: &Url, rng: &dyn SecureRandom) -> Result<String> {
+        let header = URL_SAFE_NO_PAD.encode(r#"{"typ":"JWT","alg":"ES256"}"#);
+        let claims = json!({
+            "aud": endpoint.origin().ascii_serialization(),
+            "exp": Utc::now().timestamp() + TOKEN_LIFETIME,
+            "sub": self.subject,
+        });
+
+        let unsigned = format!("{header}.{}", URL_SAFE_NO_PAD.encode(claims.to_string()));
+        let signature = self
+            .key_pair
+            .sign(rng, unsigned.as_bytes())
+            .map_err(|e| eyre!("failed to sign VAPID token: {e}"))?;
+
+        Ok(format!(
+            "{unsigned}.{}",
+            URL_SAFE_NO_PAD.encode(signature.as_ref())
+        ))
+    }
+}
+
+fn decode(value: &str) -> Result<Vec<u8>> {
+    Ok(URL_SAFE_NO_PAD.decode(value.trim().trim_end_matches('='))?)
+}
+
+/// Whether a push was accepted, or the subscription no longer exists and should be forgotten.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Delivery {
+    Delivered,
+    Expired,
+}
+
+/// Sends outage alerts to browsers and installed web apps through their push services.
+pub struct WebPush {
+    http_client: reqwest::Client,
+    keys: VapidKeys,
+    rng: SystemRandom,
+}
+
+impl WebPush {
+    pub fn new(http_client: reqwest::Client, keys: VapidKeys) -> Self {
+        Self {
+            http_client,
+            keys,
+            rng: SystemRandom::new(),
+        }
+    }
+
+    pub async fn send(&self, subscription: &PushSubscription, payload: &[u8]) -> Result<Delivery> {
+        let endpoint = Url::parse(&subscription.endpoint)?;
+
+        let body = encrypt(
+            &decode(&subscription.p256dh)?,
+            &decode(&subscription.auth)?,
+            payload,
+            &self.rng,
+        )?;
+
+        let authorization = format!(
+            "vapid t={}, k={}",
+            self.keys.token(&endpoint, &self.rng)?,
+            self.keys.public_key()
+        );
+
+        let response = self
+            .http_client
+            .post(endpoint)
+            .header(AUTHORIZATION, authorization)
+            .header(CONTENT_ENCODING, "aes128gcm")
+            .header(CONTENT_TYPE, "application/octet-stream")
+            .header("TTL", TIME_TO_LIVE)
+            .body(body)
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND | StatusCode::GONE => Ok(Delivery::Expired),
+            _ => {
+                response.error_for_status()?;
+                Ok(Delivery::Delivered)
+            }
+        }
+    }
+}
+
+struct Length(usize);
+
+impl hkdf::KeyType for Length {
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+fn expand(prk: &hkdf::Prk, info: &[&[u8]], length: usize) -> Result<Vec<u8>> {
+    let mut output = vec![0; length];
+
+    prk.expand(info, Length(length))
+        .and_then(|okm| okm.fill(&mut output))
+        .map_err(|_| eyre!("failed to expand key material"))?;
+
+    Ok(output)
+}
+
+/// The content encryption key and nonce for a message, derived as described in RFC 8291.
+fn derive(
+    ecdh_secret: &[u8],
+    auth_secret: &[u8],
+    salt: &[u8],
+    receiver_public_key: &[u8],
+    sender_public_key: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, auth_secret).extract(ecdh_secret);
+    let ikm = expand(
+        &prk,
+        &[
+            &b"WebPush: info\0"[..],
+            receiver_public_key,
+            sender_public_key,
+        ],
+        32,
+    )?;
+
+    let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, salt).extract(&ikm);
+    let key = expand(&prk, &[&b"Content-Encoding: aes128gcm\0"[..]], 16)?;
+    let nonce = expand(&prk, &[&b"Content-Encoding: nonce\0"[..]], 12)?;
+
+    Ok((key, nonce))
+}
+
+fn cipher(key: &[u8]) -> Result<aead::LessSafeKey> {
+    let key = aead::UnboundKey::new(&aead::AES_128_GCM, key)
+        .map_err(|_| eyre!("invalid content encryption key"))?;
+
+    Ok(aead::LessSafeKey::new(key))
+}
+
+/// Encrypts a payload for a subscription using the `aes128gcm` content encoding from RFC 8188.
+fn encrypt(
+    receiver_public_key: &[u8],
+    auth_secret: &[u8],
+    payload: &[u8],
+    rng: &dyn SecureRandom,
+) -> Result<Vec<u8>> {
+    let private_key = agreement::EphemeralPrivateKey::generate(&agreement::ECDH_P256, rng)
+        .map_err(|_| eyre!("failed to generate an ephemeral key"))?;
+    let sender_public_key = private_key
+        .compute_public_key()
+        .map_err(|_| eyre!("failed to compute the ephemeral public key"))?;
+
+    let mut salt = [0; 16];
+    rng.fill(&mut salt)
+        .map_err(|_| eyre!("failed to generate a salt"))?;
+
+    let ecdh_secret = agreement::agree_ephemeral(
+        private_key,
+        &agreement::UnparsedPublicKey::new(&agreement::ECDH_P256, receiver_public_key),
+        |secret| secret.to_vec(),
+    )
+    .map_err(|_| eyre!("subscription has an invalid public key"))?;
+
+    let (key, nonce) = derive(
+        &ecdh_secret,
+        auth_secret,
+        &salt,
+        receiver_public_key,
+        sender_public_key.as_ref(),
+    )?;
+
+    // Everything fits in one record, which is marked as the last with a trailing delimiter
+    let mut record = payload.to_vec();
+    record.push(2);
+
+    let nonce = aead::Nonce::try_assume_unique_for_key(&nonce)
+        .map_err(|_| eyre!("invalid nonce length"))?;
+    cipher(&key)?
+        .seal_in_place_append_tag(nonce, aead::Aad::empty(), &mut record)
+        .map_err(|_| eyre!("failed to encrypt the payload"))?;
+
+    let mut body = Vec::with_capacity(21 + sender_public_key.as_ref().len() + record.len());
+    body.extend_from_slice(&salt);
+    body.extend_from_slice(&RECORD_SIZE.to_be_bytes());
+    body.push(sender_public_key.as_ref().len() as u8);
+    body.extend_from_slice(sender_public_key.as_ref());
+    body.extend_from_slice(&record);
+
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use reqwest::Url;
+    use ring::rand::SystemRandom;
+    use ring::signature::{
+        EcdsaKeyPair, UnparsedPublicKey, ECDSA_P256_SHA256_FIXED, ECDSA_P256_SHA256_FIXED_SIGNING,
+    };
+    use ring::{aead, agreement};
+
+    use crate::poller::push::VapidKeys;
+
+    #[test]
+    fn payloads_can_be_decrypted_by_the_subscriber() {
+        let rng = SystemRandom::new();
+        let auth_secret = [7; 16];
+
+        let receiver_private_key =
+            agreement::EphemeralPrivateKey::generate(&agreement::ECDH_P256, &rng).unwrap();
+        let receiver_public_key = receiver_private_key.compute_public_key().unwrap();
+
+        let body = super::encrypt(
+            receiver_public_key.as_ref(),
+            &auth_secret,
+            b"Outage detected",
+            &rng,
+        )
+        .unwrap();
+
+        // Parse the header the way a browser would
+        let salt = &body[..16];
+        assert_eq!(body[16..20], super::RECORD_SIZE.to_be_bytes());
+        let key_length = usize::from(body[20]);
+        let sender_public_key = &body[21..21 + key_length];
+        let mut record = body[21 + key_length..].to_vec();
+
+        let ecdh_secret = agreement::agree_ephemeral(
+            receiver_private_key,
+            &agreement::UnparsedPublicKey::new(&agreement::ECDH_P256, sender_public_key),
+            |secret| secret.to_vec(),
+        )
+        .unwrap();
+
+        let (key, nonce) = super::derive(
+            &ecdh_secret,
+            &auth_secret,
+            salt,
+            receiver_public_key.as_ref(),
+            sender_public_key,
+        )
+        .unwrap();
+
+        let nonce = aead::Nonce::try_assume_unique_for_key(&nonce).unwrap();
+        let plaintext = super::cipher(&key)
+            .unwrap()
+            .open_in_place(nonce, aead::Aad::empty(), &mut record)
+            .unwrap();
+
+        assert_eq!(plaintext, b"Outage detected\x02");
+    }
+
+    #[test]
+    fn tokens_are_signed_for_the_push_service() {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        let key_pair =
+            EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+                .unwrap();
+
+        let keys = VapidKeys::new(key_pair, String::from("mailto:ops@example.com"));
+        let endpoint = Url::parse("https://push.example.com/send/abc123").unwrap();
+
+        let token = keys.token(&endpoint, &rng).unwrap();
+        let (unsigned, signature) = token.rsplit_once('.').unwrap();
+        let (_, claims) = unsigned.split_once('.').unwrap();
+
+        let claims: serde_json::Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(claims).unwrap()).unwrap();
+        assert_eq!(claims["aud"], "https://push.example.com");
+        assert_eq!(claims["sub"], "mailto:ops@example.com");
+
+        let public_key = URL_SAFE_NO_PAD.decode(keys.public_key()).unwrap();
+        let signature = URL_SAFE_NO_PAD.decode(signature).unwrap();
+
+        assert!(UnparsedPublicKey::new(&ECDSA_P256_SHA256_FIXED, public_key)
+            .verify(unsigned.as_bytes(), &signature)
+            .is_ok());
+    }
+}
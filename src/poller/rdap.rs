@@ -0,0 +1,244 @@
+use std::net::IpAddr;
+use std::time::Duration;
+
+use color_eyre::eyre::{eyre, Result};
+use reqwest::header::ACCEPT;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use sqlx::types::chrono::{DateTime, Utc};
+
+/// Where lookups start if `RDAP_URL` is not set, which redirects to the registry of each domain.
+const DEFAULT_RDAP_URL: &str = "https://rdap.org";
+
+/// How often the registration of an origin's domain is looked up, as expiry dates only change when
+/// the domain is renewed.
+pub const LOOKUP_INTERVAL: chrono::Duration = chrono::Duration::days(1);
+
+/// The longest a registry can take to answer before the lookup is abandoned until the next round.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Looks up when the domains of origins expire over RDAP, so they can be renewed before they lapse
+/// and take everything served from them down.
+#[derive(Clone, Debug)]
+pub struct DomainExpiry {
+    http_client: reqwest::Client,
+    rdap_url: String,
+    warn_within: chrono::Duration,
+}
+
+/// What a registry said about a domain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Registration {
+    /// The registered domain, which may be a parent of the origin's host.
+    pub domain: String,
+    /// When the registration lapses, which registries are not required to publish.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+struct DomainResponse {
+    #[serde(default)]
+    events: Vec<DomainEvent>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DomainEvent {
+    event_action: String,
+    event_date: DateTime<Utc>,
+}
+
+impl DomainExpiry {
+    /// Creates lookups against the RDAP service at `rdap_url`, warning about domains that expire
+    /// within `warn_within`.
+    pub fn new<T: Into<String>>(
+        http_client: reqwest::Client,
+        rdap_url: T,
+        warn_within: chrono::Duration,
+    ) -> Self {
+        Self {
+            http_client,
+            rdap_url: rdap_url.into(),
+            warn_within,
+        }
+    }
+
+    /// Reads how many days before expiry to warn from `DOMAIN_EXPIRY_WARN_DAYS`, returning `None`
+    /// if it is not set, and the service to look domains up with from `RDAP_URL`.
+    pub fn from_env(http_client: reqwest::Client) -> Result<Option<Self>> {
+        let Ok(value) = std::env::var("DOMAIN_EXPIRY_WARN_DAYS") else {
+            return Ok(None);
+        };
+
+        let days: i64 = value.parse().ok().filter(|days| *days > 0).ok_or_else(|| {
+            eyre!("DOMAIN_EXPIRY_WARN_DAYS must be a positive number of days, not '{value}'")
+        })?;
+
+        let rdap_url = std::env::var("RDAP_URL").unwrap_or_else(|_| DEFAULT_RDAP_URL.to_owned());
+
+        Ok(Some(Self::new(
+            http_client,
+            rdap_url,
+            chrono::Duration::days(days),
+        )))
+    }
+
+    /// Whether a domain that expires at `expires_at` is close enough to expiring to warn about.
+    pub fn is_expiring(&self, expires_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        expires_at - now <= self.warn_within
+    }
+
+    /// Looks up the registration of the domain `host` belongs to, returning `None` if no registry
+    /// knows of it, such as for internal hosts.
+    ///
+    /// Registries only know of registered domains rather than the hosts under them, so each parent
+    /// of the host is tried in turn until one is found.
+    pub async fn lookup(&self, host: &str) -> Result<Option<Registration>> {
+        let base = self.rdap_url.trim_end_matches('/');
+
+        for domain in candidates(host) {
+            let response = self
+                .http_client
+                .get(format!("{base}/domain/{domain}"))
+                .header(ACCEPT, "application/rdap+json")
+                .timeout(TIMEOUT)
+                .send()
+                .await?;
+
+            if response.status() == StatusCode::NOT_FOUND {
+                continue;
+            }
+
+            let response: DomainResponse = response.error_for_status()?.json().await?;
+
+            let expires_at = response
+                .events
+                .into_iter()
+                .find(|event| event.event_action == "expiration")
+                .map(|event| event.event_date);
+
+            return Ok(Some(Registration { domain, expires_at }));
+        }
+
+        Ok(None)
+    }
+}
+
+/// The domains `host` could be registered under, from the host itself down to its last two
+/// labels, or nothing for addresses and single labels.
+fn candidates(host: &str) -> Vec<String> {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+
+    if host.parse::<IpAddr>().is_ok() {
+        return Vec::new();
+    }
+
+    let labels: Vec<_> = host.split('.').collect();
+
+    (0..labels.len().saturating_sub(1))
+        .map(|start| labels[start..].join("."))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use color_eyre::eyre::Result;
+
+    use crate::poller::rdap::{DomainExpiry, Registration};
+
+    #[test]
+    fn hosts_are_looked_up_under_each_of_their_parents() {
+        assert_eq!(
+            super::candidates("WWW.Shop.example.co.uk."),
+            [
+                "www.shop.example.co.uk",
+                "shop.example.co.uk",
+                "example.co.uk",
+                "co.uk"
+            ]
+        );
+        assert_eq!(super::candidates("example.com"), ["example.com"]);
+        assert!(super::candidates("localhost").is_empty());
+        assert!(super::candidates("127.0.0.1").is_empty());
+        assert!(super::candidates("::1").is_empty());
+    }
+
+    #[tokio::test]
+    async fn expiry_is_read_from_the_registered_parent_domain() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+
+        server
+            .mock("GET", "/domain/www.example.com")
+            .with_status(404)
+            .create_async()
+            .await;
+
+        server
+            .mock("GET", "/domain/example.com")
+            .with_header("content-type", "application/rdap+json")
+            .with_body(
+                r#"{
+                    "ldhName": "example.com",
+                    "events": [
+                        {"eventAction": "registration", "eventDate": "1995-08-14T04:00:00Z"},
+                        {"eventAction": "expiration", "eventDate": "2027-08-13T04:00:00Z"}
+                    ]
+                }"#,
+            )
+            .create_async()
+            .await;
+
+        let domain_expiry = DomainExpiry::new(
+            reqwest::Client::new(),
+            server.url(),
+            chrono::Duration::days(30),
+        );
+
+        assert_eq!(
+            domain_expiry.lookup("www.example.com").await?,
+            Some(Registration {
+                domain: String::from("example.com"),
+                expires_at: Some(Utc.with_ymd_and_hms(2027, 8, 13, 4, 0, 0).unwrap()),
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn domains_are_expiring_once_within_the_warning_period() {
+        let domain_expiry = DomainExpiry::new(
+            reqwest::Client::new(),
+            "https://rdap.example",
+            chrono::Duration::days(30),
+        );
+
+        let now = Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap();
+
+        assert!(!domain_expiry.is_expiring(now + chrono::Duration::days(31), now));
+        assert!(domain_expiry.is_expiring(now + chrono::Duration::days(30), now));
+        assert!(domain_expiry.is_expiring(now - chrono::Duration::days(1), now));
+    }
+
+    #[tokio::test]
+    async fn unknown_domains_have_no_registration() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+
+        server
+            .mock("GET", mockito::Matcher::Any)
+            .with_status(404)
+            .create_async()
+            .await;
+
+        let domain_expiry = DomainExpiry::new(
+            reqwest::Client::new(),
+            server.url(),
+            chrono::Duration::days(30),
+        );
+
+        assert_eq!(domain_expiry.lookup("db.internal").await?, None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,504 @@
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use reqwest::header::{HeaderMap, AUTHORIZATION, CONTENT_TYPE, LOCATION};
+use reqwest::redirect::Policy;
+use reqwest::{Method, StatusCode, Url};
+
+use crate::persistence::Origin;
+use crate::poller::check::{Check, CheckContext, CheckOutcome};
+use crate::poller::FailureReason;
+
+/// The most redirects followed before giving up, matching the default policy of reqwest.
+pub const MAX_HOPS: usize = 10;
+
+/// One response on the way to the final destination of a request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hop {
+    pub url: Url,
+    pub status: StatusCode,
+    /// Where the response redirected to, if it was a redirect.
+    pub location: Option<Url>,
+}
+
+/// Every response from the first request to the one that did not redirect.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Chain {
+    hops: Vec<Hop>,
+}
+
+impl Chain {
+    pub fn first(&self) -> &Hop {
+        self.hops.first().expect("chains always have a response")
+    }
+
+    pub fn destination(&self) -> &Hop {
+        self.hops.last().expect("chains always have a response")
+    }
+
+    pub fn redirects(&self) -> &[Hop] {
+        &self.hops[..self.hops.len() - 1]
+    }
+}
+
+impl Display for Chain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hops: Vec<_> = self
+            .hops
+            .iter()
+            .map(|hop| format!("{} {}", hop.url, hop.status.as_u16()))
+            .collect();
+
+        write!(f, "{}", hops.join(" -> "))
+    }
+}
+
+/// Why following or auditing redirects failed, with the responses seen along the way.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RedirectFailure {
+    pub reason: FailureReason,
+    pub detail: String,
+}
+
+impl RedirectFailure {
+    fn redirection(detail: String) -> Self {
+        Self {
+            reason: FailureReason::Redirection,
+            detail,
+        }
+    }
+}
+
+fn describe(hops: &[Hop]) -> String {
+    Chain {
+        hops: hops.to_vec(),
+    }
+    .to_string()
+}
+
+/// The request to make to the first URL of a chain.
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: Method,
+    pub headers: HeaderMap,
+    pub body: Option<String>,
+}
+
+impl Request {
+    pub fn get() -> Self {
+        Self {
+            method: Method::GET,
+            headers: HeaderMap::new(),
+            body: None,
+        }
+    }
+
+    /// The request to make after a redirect, which becomes a GET without a body unless the status
+    /// asks for the method to be kept, as browsers do.
+    fn redirected(mut self, status: StatusCode) -> Self {
+        let keeps_method = matches!(
+            status,
+            StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT
+        );
+
+        if keeps_method || self.method == Method::GET || self.method == Method::HEAD {
+            return self;
+        }
+
+        self.headers.remove(CONTENT_TYPE);
+
+        Self {
+            method: Method::GET,
+            headers: self.headers,
+            body: None,
+        }
+    }
+}
+
+/// Where a response redirects to, resolving relative locations against the URL requested.
+fn location(url: &Url, response: &reqwest::Response) -> Option<Url> {
+    let redirects = matches!(
+        response.status(),
+        StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT
+    );
+
+    if !redirects {
+        return None;
+    }
+
+    let location = response.headers().get(LOCATION)?.to_str().ok()?;
+
+    url.join(location).ok()
+}
+
+/// Starts building a client that never follows redirects itself, so every hop can be inspected.
+pub fn builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder().redirect(Policy::none())
+}
+
+/// Follows redirects from `url` one at a time, failing on loops and after `max_hops` redirects.
+///
+/// A limit of zero returns the first response as it is, even if it redirects. Responses without a
+/// usable `Location` header end the chain, as there is nowhere to follow them to.
+pub async fn follow(
+    client: &reqwest::Client,
+    request: Request,
+    url: Url,
+    max_hops: usize,
+    timeout: Duration,
+) -> Result<(Chain, reqwest::Response), RedirectFailure> {
+    let mut hops: Vec<Hop> = Vec::new();
+    let mut request = request;
+    let mut next = url;
+
+    loop {
+        let mut builder = client
+            .request(request.method.clone(), next.clone())
+            .headers(request.headers.clone())
+            .timeout(timeout);
+
+        if let Some(body) = &request.body {
+            builder = builder.body(body.clone());
+        }
+
+        let response = builder.send().await.map_err(|e| RedirectFailure {
+            detail: format!("could not request {next}: {e}"),
+            reason: e.into(),
+        })?;
+
+        let status = response.status();
+        let location = location(&next, &response);
+
+        hops.push(Hop {
+            url: next,
+            status,
+            location: location.clone(),
+        });
+
+        let Some(location) = location.filter(|_| max_hops > 0) else {
+            return Ok((Chain { hops }, response));
+        };
+
+        if hops.len() > max_hops {
+            return Err(RedirectFailure::redirection(format!(
+                "followed more than {max_hops} redirects: {}",
+                describe(&hops)
+            )));
+        }
+
+        if hops.iter().any(|hop| hop.url == location) {
+            return Err(RedirectFailure::redirection(format!(
+                "redirect loop: {} -> {location}",
+                describe(&hops)
+            )));
+        }
+
+        request = request.redirected(status);
+
+        // Credentials are only meant for the origin they were configured for, as browsers do
+        if hops.iter().any(|hop| hop.url.origin() != location.origin()) {
+            request.headers.remove(AUTHORIZATION);
+        }
+
+        next = location;
+    }
+}
+
+/// A canonical URL written as `redirect://www.example.com/path`, whose other forms should all
+/// permanently redirect to `https://www.example.com/path`.
+///
+/// The other forms are the plain HTTP version and the HTTP and HTTPS versions of each alias. The
+/// alias defaults to the `www.` counterpart of apex and `www.` hosts, and `alias` parameters
+/// replace it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RedirectCheck {
+    canonical: Url,
+    variants: Vec<Url>,
+}
+
+/// The host that should redirect to `host`, if there is a conventional one.
+fn default_alias(host: &str) -> Option<String> {
+    if let Some(apex) = host.strip_prefix("www.") {
+        return Some(apex.to_owned());
+    }
+
+    // Subdomains such as `api.example.com` rarely have a `www.` form
+    (host.matches('.').count() == 1).then(|| format!("www.{host}"))
+}
+
+impl RedirectCheck {
+    pub fn parse(uri: &str) -> Option<Self> {
+        let url = Url::parse(uri).ok()?;
+
+        if url.scheme() != "redirect" {
+            return None;
+        }
+
+        let host = url.host_str()?;
+        let mut aliases = Vec::new();
+
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "alias" => aliases.push(value.into_owned()),
+                _ => return None,
+            }
+        }
+
+        if aliases.is_empty() {
+            aliases.extend(default_alias(host));
+        }
+
+        let with =
+            |scheme: &str, host: &str| Url::parse(&format!("{scheme}://{host}{}", url.path())).ok();
+
+        let canonical = with("https", host)?;
+        let mut variants = vec![with("http", host)?];
+
+        for alias in &aliases {
+            variants.push(with("http", alias)?);
+            variants.push(with("https", alias)?);
+        }
+
+        Some(Self {
+            canonical,
+            variants,
+        })
+    }
+}
+
+/// Checks that the canonical URL responds directly and every other form permanently redirects to
+/// it, returning the chains that were followed.
+pub async fn audit(
+    client: &reqwest::Client,
+    check: &RedirectCheck,
+    timeout: Duration,
+) -> Result<String, RedirectFailure> {
+    let (canonical, _) = follow(
+        client,
+        Request::get(),
+        check.canonical.clone(),
+        MAX_HOPS,
+        timeout,
+    )
+    .await?;
+    let destination = canonical.destination();
+
+    if !canonical.redirects().is_empty() {
+        return Err(RedirectFailure::redirection(format!(
+            "the canonical URL redirects: {canonical}"
+        )));
+    }
+
+    if !destination.status.is_success() {
+        return Err(RedirectFailure {
+            reason: FailureReason::UnexpectedStatus,
+            detail: format!(
+                "the canonical URL responded {}: {canonical}",
+                destination.status.as_u16()
+            ),
+        });
+    }
+
+    let mut chains = vec![canonical.to_string()];
+
+    for variant in &check.variants {
+        let (chain, _) = follow(client, Request::get(), variant.clone(), MAX_HOPS, timeout).await?;
+
+        if chain.destination().url != check.canonical {
+            return Err(RedirectFailure::redirection(format!(
+                "{variant} does not end at {}: {chain}",
+                check.canonical
+            )));
+        }
+
+        // Temporary redirects are not remembered by browsers or passed on by search engines
+        let temporary = chain.redirects().iter().find(|hop| {
+            !matches!(
+                hop.status,
+                StatusCode::MOVED_PERMANENTLY | StatusCode::PERMANENT_REDIRECT
+            )
+        });
+
+        if let Some(hop) = temporary {
+            return Err(RedirectFailure::redirection(format!(
+                "{} redirects with a temporary {}: {chain}",
+                hop.url,
+                hop.status.as_u16()
+            )));
+        }
+
+        chains.push(chain.to_string());
+    }
+
+    Ok(chains.join("\n"))
+}
+
+/// Audits the redirects to the canonical URL of origins written as `redirect://host/path`.
+pub struct RedirectChecker;
+
+impl Check for RedirectChecker {
+    fn execute<'a>(
+        &'a self,
+        origin: &'a Origin,
+        context: &'a CheckContext<'a>,
+    ) -> BoxFuture<'a, CheckOutcome> {
+        Box::pin(async move {
+            let Some(check) = RedirectCheck::parse(&origin.uri) else {
+                return CheckOutcome::new(Err(FailureReason::BadRequest));
+            };
+
+            match audit(context.http_clients.shared(), &check, context.timeout).await {
+                Ok(chain) => CheckOutcome {
+                    redirect_chain: Some(chain),
+                    ..CheckOutcome::new(Ok((None, context.elapsed_millis())))
+                },
+                Err(failure) => CheckOutcome::failed(failure.reason, failure.detail),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+
+    use crate::poller::redirect::RedirectCheck;
+    use crate::poller::FailureReason;
+
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+    fn urls(values: &[&str]) -> Vec<Url> {
+        values.iter().map(|value| value.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn apex_and_www_hosts_are_aliases_of_each_other() {
+        let check = RedirectCheck::parse("redirect://www.example.com").unwrap();
+
+        assert_eq!(check.canonical.as_str(), "https://www.example.com/");
+        assert_eq!(
+            check.variants,
+            urls(&[
+                "http://www.example.com/",
+                "http://example.com/",
+                "https://example.com/",
+            ])
+        );
+
+        let check = RedirectCheck::parse("redirect://example.com/blog").unwrap();
+
+        assert_eq!(check.canonical.as_str(), "https://example.com/blog");
+        assert_eq!(
+            check.variants,
+            urls(&[
+                "http://example.com/blog",
+                "http://www.example.com/blog",
+                "https://www.example.com/blog",
+            ])
+        );
+    }
+
+    #[test]
+    fn aliases_can_be_given_explicitly() {
+        let check =
+            RedirectCheck::parse("redirect://api.example.com?alias=api.example.net").unwrap();
+
+        assert_eq!(
+            check.variants,
+            urls(&[
+                "http://api.example.com/",
+                "http://api.example.net/",
+                "https://api.example.net/",
+            ])
+        );
+
+        // Subdomains only check the plain HTTP version by default
+        let check = RedirectCheck::parse("redirect://api.example.com").unwrap();
+
+        assert_eq!(check.variants, urls(&["http://api.example.com/"]));
+
+        assert_eq!(
+            RedirectCheck::parse("redirect://example.com?follow=3"),
+            None
+        );
+        assert_eq!(RedirectCheck::parse("https://example.com"), None);
+    }
+
+    #[tokio::test]
+    async fn relative_locations_are_followed() {
+        let mut server = mockito::Server::new_async().await;
+
+        server
+            .mock("GET", "/old")
+            .with_status(301)
+            .with_header("location", "/new")
+            .create_async()
+            .await;
+
+        server
+            .mock("GET", "/new")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let url = format!("{}/old", server.url()).parse().unwrap();
+        let (chain, _) = super::follow(
+            &super::builder().build().unwrap(),
+            super::Request::get(),
+            url,
+            super::MAX_HOPS,
+            TIMEOUT,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            chain.to_string(),
+            format!("{0}/old 301 -> {0}/new 200", server.url())
+        );
+    }
+
+    #[tokio::test]
+    async fn redirect_loops_are_detected() {
+        let mut server = mockito::Server::new_async().await;
+
+        server
+            .mock("GET", "/a")
+            .with_status(302)
+            .with_header("location", "/b")
+            .create_async()
+            .await;
+
+        server
+            .mock("GET", "/b")
+            .with_status(302)
+            .with_header("location", "/a")
+            .create_async()
+            .await;
+
+        let url = format!("{}/a", server.url()).parse().unwrap();
+        let failure = super::follow(
+            &super::builder().build().unwrap(),
+            super::Request::get(),
+            url,
+            super::MAX_HOPS,
+            TIMEOUT,
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(failure.reason, FailureReason::Redirection);
+        assert_eq!(
+            failure.detail,
+            format!(
+                "redirect loop: {0}/a 302 -> {0}/b 302 -> {0}/a",
+                server.url()
+            )
+        );
+    }
+}
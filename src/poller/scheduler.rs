@@ -0,0 +1,246 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::Serialize;
+use tokio::sync::watch;
+use uuid::Uuid;
+
+use crate::persistence::Origin;
+use crate::poller::FailureReason;
+
+/// When an origin was last checked and when it will next be checked.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ScheduledOrigin {
+    pub origin_uid: Uuid,
+    pub uri: String,
+    pub last_checked_at: Option<DateTime<Utc>>,
+    /// When the origin is next due, which is when its check starts in the current round if it is
+    /// still waiting for it.
+    pub next_check_at: Option<DateTime<Utc>>,
+}
+
+/// The origin that took the longest to check in a round.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SlowestOrigin {
+    pub origin_uid: Uuid,
+    pub uri: String,
+    pub duration_millis: i64,
+}
+
+/// How a round of checks went, so its health can be seen without reading every check.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct RoundSummary {
+    pub origins_checked: usize,
+    /// The number of checks that succeeded, which can be more than the number of origins if some
+    /// are checked over both address families.
+    pub successes: usize,
+    /// The number of checks that failed for each reason.
+    pub failures: BTreeMap<&'static str, usize>,
+    pub duration_millis: i64,
+    pub slowest: Option<SlowestOrigin>,
+}
+
+impl RoundSummary {
+    pub fn total_failures(&self) -> usize {
+        self.failures.values().sum()
+    }
+}
+
+/// What the poller is doing, so operators can tell whether it is keeping up.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct SchedulerState {
+    /// Whether a round of checks is underway.
+    pub running: bool,
+    pub round_started_at: Option<DateTime<Utc>>,
+    pub last_round_duration_millis: Option<i64>,
+    pub next_round_at: Option<DateTime<Utc>>,
+    /// The number of checks currently being made, including ones requested outside of rounds.
+    pub in_flight: usize,
+    /// The number of origins still waiting to be checked in the current round.
+    pub backlog: usize,
+    /// Every origin in the order the current or previous round checked them.
+    pub origins: Vec<ScheduledOrigin>,
+    /// How the most recently finished round went.
+    pub last_round: Option<RoundSummary>,
+    /// How the current round is going, which only becomes the summary once it finishes.
+    #[serde(skip)]
+    current_round: RoundSummary,
+}
+
+/// When a check starts in a round that starts at `now`, at a random point up to `jitter` later so
+/// the checks of origins sharing a round do not all start at once.
+pub fn jittered(now: DateTime<Utc>, jitter: Duration) -> DateTime<Utc> {
+    let millis = rand::thread_rng().gen_range(0..=jitter.as_millis() as i64);
+
+    now + chrono::Duration::milliseconds(millis)
+}
+
+/// Publishes the state of the poller as it changes.
+pub struct Scheduler {
+    state: watch::Sender<SchedulerState>,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self {
+            state: watch::Sender::new(SchedulerState::default()),
+        }
+    }
+}
+
+impl Scheduler {
+    /// A view of the state that always reflects the latest update.
+    pub fn subscribe(&self) -> watch::Receiver<SchedulerState> {
+        self.state.subscribe()
+    }
+
+    /// Starts a round that checks `origins` in order at the time paired with each, carrying over
+    /// when each was last checked. Origins paired with `None` are not due this round.
+    pub fn round_started(&self, origins: &[(Option<DateTime<Utc>>, Origin)]) {
+        let now = Utc::now();
+
+        self.state.send_modify(|state| {
+            let origins: Vec<_> = origins
+                .iter()
+                .map(|(starts_at, origin)| ScheduledOrigin {
+                    origin_uid: origin.origin_uid,
+                    uri: origin.uri.clone(),
+                    last_checked_at: state
+                        .origins
+                        .iter()
+                        .find(|scheduled| scheduled.origin_uid == origin.origin_uid)
+                        .and_then(|scheduled| scheduled.last_checked_at),
+                    next_check_at: *starts_at,
+                })
+                .collect();
+
+            state.running = true;
+            state.round_started_at = Some(now);
+            state.next_round_at = None;
+            state.backlog = origins
+                .iter()
+                .filter(|scheduled| scheduled.next_check_at.is_some())
+                .count();
+            state.origins = origins;
+            state.current_round = RoundSummary::default();
+        });
+    }
+
+    pub fn check_started(&self) {
+        self.state.send_modify(|state| state.in_flight += 1);
+    }
+
+    /// Records that an origin was checked, taking it off the backlog if the round was waiting
+    /// for it. `failures` holds the reason each check failed, or `None` for each that succeeded.
+    pub fn check_finished(
+        &self,
+        origin_uid: Uuid,
+        duration_millis: i64,
+        failures: &[Option<FailureReason>],
+    ) {
+        let now = Utc::now();
+
+        self.state.send_modify(|state| {
+            state.in_flight = state.in_flight.saturating_sub(1);
+
+            // Checks requested between rounds do not count towards either of them
+            if state.running {
+                let round = &mut state.current_round;
+
+                for failure in failures {
+                    match failure {
+                        Some(reason) => *round.failures.entry(reason.as_str()).or_default() += 1,
+                        None => round.successes += 1,
+                    }
+                }
+
+                round.origins_checked += 1;
+
+                let slower_seen = round
+                    .slowest
+                    .as_ref()
+                    .is_some_and(|slowest| slowest.duration_millis >= duration_millis);
+
+                if !slower_seen {
+                    round.slowest = state
+                        .origins
+                        .iter()
+                        .find(|scheduled| scheduled.origin_uid == origin_uid)
+                        .map(|scheduled| SlowestOrigin {
+                            origin_uid,
+                            uri: scheduled.uri.clone(),
+                            duration_millis,
+                        });
+                }
+            }
+
+            let Some(scheduled) = state
+                .origins
+                .iter_mut()
+                .find(|scheduled| scheduled.origin_uid == origin_uid)
+            else {
+                return;
+            };
+
+            scheduled.last_checked_at = Some(now);
+
+            if state.running && scheduled.next_check_at.take().is_some() {
+                state.backlog = state.backlog.saturating_sub(1);
+            }
+        });
+    }
+
+    /// Ends the current round if there is one, with every origin due again at `next_round_at`,
+    /// returning how the round went.
+    pub fn waiting_until(&self, next_round_at: DateTime<Utc>) -> Option<RoundSummary> {
+        let now = Utc::now();
+        let mut finished = None;
+
+        self.state.send_modify(|state| {
+            if state.running {
+                state.running = false;
+                state.last_round_duration_millis = state
+                    .round_started_at
+                    .map(|started_at| (now - started_at).num_milliseconds());
+
+                let mut summary = std::mem::take(&mut state.current_round);
+                summary.duration_millis = state.last_round_duration_millis.unwrap_or_default();
+
+                state.last_round = Some(summary.clone());
+                finished = Some(summary);
+            }
+
+            state.backlog = 0;
+            state.next_round_at = Some(next_round_at);
+
+            for scheduled in &mut state.origins {
+                scheduled.next_check_at = Some(next_round_at);
+            }
+        });
+
+        finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::Utc;
+
+    #[test]
+    fn jittered_starts_stay_within_the_jitter() {
+        let now = Utc::now();
+
+        for _ in 0..100 {
+            let starts_at = super::jittered(now, Duration::from_secs(20));
+
+            assert!(starts_at >= now);
+            assert!(starts_at <= now + chrono::Duration::seconds(20));
+        }
+
+        assert_eq!(super::jittered(now, Duration::ZERO), now);
+    }
+}
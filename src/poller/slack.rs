@@ -0,0 +1,40 @@
+use color_eyre::eyre::Result;
+use reqwest::header::CONTENT_TYPE;
+use serde::Serialize;
+
+use crate::poller::Notifier;
+
+/// Posts notifications to Slack incoming webhooks, treating the webhook URL as the topic.
+#[derive(Clone)]
+pub struct SlackWebhook {
+    http_client: reqwest::Client,
+}
+
+impl SlackWebhook {
+    pub fn new(http_client: reqwest::Client) -> Self {
+        Self { http_client }
+    }
+}
+
+#[derive(Serialize)]
+struct Payload {
+    text: String,
+}
+
+impl Notifier for SlackWebhook {
+    async fn notify(&self, topic: &str, subject: &str, message: &str) -> Result<()> {
+        let payload = Payload {
+            text: format!("*{subject}*\n{message}"),
+        };
+
+        self.http_client
+            .post(topic)
+            .header(CONTENT_TYPE, "application/json")
+            .body(serde_json::to_string(&payload)?)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
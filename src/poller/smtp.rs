@@ -0,0 +1,440 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use reqwest::Url;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufStream};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+use crate::persistence::Origin;
+use crate::poller::check::{Check, CheckContext, CheckOutcome};
+use crate::poller::protocol::{host_and_port, ProtocolFailure};
+use crate::poller::FailureReason;
+
+/// The name the poller introduces itself with, as it has no public hostname of its own.
+const EHLO_DOMAIN: &str = "localhost";
+
+/// The longest reply line that will be read, well above the 512 bytes servers are allowed.
+const MAX_LINE_BYTES: u64 = 4096;
+
+/// The most lines a single reply can have, as servers only list a handful of extensions.
+const MAX_REPLY_LINES: usize = 64;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Security {
+    /// Plaintext throughout, as is usual on port 25.
+    None,
+    /// TLS from the moment the connection opens, as is usual on port 465.
+    Implicit,
+    /// Upgraded to TLS with `STARTTLS` after the greeting, as is usual on port 587.
+    StartTls,
+}
+
+/// A mail server written as `smtp://host:port` or `smtps://host:port`, defaulting to ports 25 and
+/// 465 respectively.
+///
+/// By default only the greeting is waited for. An `ehlo` parameter also introduces the poller,
+/// and `starttls` upgrades plaintext connections to TLS before introducing it again.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SmtpCheck {
+    host: String,
+    port: u16,
+    security: Security,
+    ehlo: bool,
+}
+
+impl SmtpCheck {
+    pub fn parse(uri: &str) -> Option<Self> {
+        let url = Url::parse(uri).ok()?;
+
+        let (mut security, default_port) = match url.scheme() {
+            "smtp" => (Security::None, 25),
+            "smtps" => (Security::Implicit, 465),
+            _ => return None,
+        };
+
+        let mut ehlo = false;
+
+        for (key, _) in url.query_pairs() {
+            match key.as_ref() {
+                "ehlo" => ehlo = true,
+                // Connections that start with TLS cannot be upgraded to it
+                "starttls" if security == Security::None => security = Security::StartTls,
+                _ => return None,
+            }
+        }
+
+        let (host, port) = host_and_port(&url, default_port)?;
+
+        Some(Self {
+            host,
+            port,
+            security,
+            ehlo: ehlo || security == Security::StartTls,
+        })
+    }
+}
+
+/// A complete reply from the server, which may have spanned several lines.
+#[derive(Debug, PartialEq, Eq)]
+struct Reply {
+    code: u16,
+    lines: Vec<String>,
+}
+
+impl Reply {
+    fn summary(&self) -> String {
+        format!(
+            "{} {}",
+            self.code,
+            self.lines.first().map_or("", String::as_str)
+        )
+    }
+}
+
+/// Splits a reply line into its code, whether it is the last line, and its text, which FTP
+/// servers reply in the same form as.
+pub fn parse_reply_line(line: &str) -> Option<(u16, bool, &str)> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    let code = line.get(..3)?;
+
+    if !code.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+
+    let (last, text) = match line.get(3..4) {
+        None => (true, ""),
+        Some(" ") => (true, &line[4..]),
+        Some("-") => (false, &line[4..]),
+        Some(_) => return None,
+    };
+
+    Some((code.parse().ok()?, last, text))
+}
+
+/// Builds the TLS connector used for `smtps://` origins and `STARTTLS`.
+pub fn connector() -> TlsConnector {
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+
+    let provider = tokio_rustls::rustls::crypto::ring::default_provider();
+    let config = ClientConfig::builder_with_provider(Arc::new(provider))
+        .with_safe_default_protocol_versions()
+        .expect("failed to configure the SMTP TLS protocol versions")
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    TlsConnector::from(Arc::new(config))
+}
+
+struct Session<S> {
+    stream: BufStream<S>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Session<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            stream: BufStream::new(stream),
+        }
+    }
+
+    async fn reply(&mut self) -> Result<Reply, ProtocolFailure> {
+        let mut code = None;
+        let mut lines = Vec::new();
+
+        while lines.len() < MAX_REPLY_LINES {
+            let mut line = String::new();
+
+            let length = (&mut self.stream)
+                .take(MAX_LINE_BYTES)
+                .read_line(&mut line)
+                .await
+                .map_err(ProtocolFailure::disconnected)?;
+
+            if length == 0 {
+                return Err(ProtocolFailure::new(
+                    FailureReason::ConnectionFailure,
+                    String::from("connection was closed by the server"),
+                ));
+            }
+
+            let malformed = || {
+                ProtocolFailure::new(
+                    FailureReason::InvalidBody,
+                    format!("sent a malformed reply: {}", line.trim_end()),
+                )
+            };
+
+            let (line_code, last, text) = parse_reply_line(&line).ok_or_else(malformed)?;
+
+            // Every line of a reply must share the same code
+            if *code.get_or_insert(line_code) != line_code {
+                return Err(malformed());
+            }
+
+            lines.push(text.to_owned());
+
+            if last {
+                return Ok(Reply {
+                    code: line_code,
+                    lines,
+                });
+            }
+        }
+
+        Err(ProtocolFailure::new(
+            FailureReason::InvalidBody,
+            format!("sent a reply longer than {MAX_REPLY_LINES} lines"),
+        ))
+    }
+
+    async fn greeting(&mut self) -> Result<(), ProtocolFailure> {
+        let reply = self.reply().await?;
+
+        // Servers that are up but refusing mail greet with 421 or 554 instead
+        if reply.code != 220 {
+            return Err(ProtocolFailure::new(
+                FailureReason::NotServing,
+                format!("greeted with {} instead of 220", reply.summary()),
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn command(&mut self, command: &str, expected: u16) -> Result<Reply, ProtocolFailure> {
+        self.stream
+            .write_all(format!("{command}\r\n").as_bytes())
+            .await
+            .map_err(ProtocolFailure::disconnected)?;
+
+        self.stream
+            .flush()
+            .await
+            .map_err(ProtocolFailure::disconnected)?;
+
+        let reply = self.reply().await?;
+
+        if reply.code != expected {
+            let verb = command.split_whitespace().next().unwrap_or(command);
+
+            return Err(ProtocolFailure::new(
+                FailureReason::UnexpectedStatus,
+                format!(
+                    "answered {verb} with {} instead of {expected}",
+                    reply.summary()
+                ),
+            ));
+        }
+
+        Ok(reply)
+    }
+
+    async fn ehlo(&mut self) -> Result<Reply, ProtocolFailure> {
+        self.command(&format!("EHLO {EHLO_DOMAIN}"), 250).await
+    }
+
+    /// Ends the session politely, ignoring servers that hang up without answering.
+    async fn quit(mut self) {
+        let _ = self.command("QUIT", 221).await;
+    }
+
+    fn into_inner(self) -> S {
+        self.stream.into_inner()
+    }
+}
+
+async fn handshake(
+    connector: &TlsConnector,
+    stream: TcpStream,
+    host: &str,
+) -> Result<tokio_rustls::client::TlsStream<TcpStream>, ProtocolFailure> {
+    let server_name = ServerName::try_from(host).map_err(|_| {
+        ProtocolFailure::new(
+            FailureReason::BadRequest,
+            format!("{host} cannot be used to verify a certificate"),
+        )
+    })?;
+
+    connector
+        .connect(server_name.to_owned(), stream)
+        .await
+        .map_err(|e| {
+            ProtocolFailure::new(
+                FailureReason::ConnectionFailure,
+                format!("TLS handshake failed: {e}"),
+            )
+        })
+}
+
+async fn converse(connector: &TlsConnector, check: &SmtpCheck) -> Result<(), ProtocolFailure> {
+    let stream = TcpStream::connect((check.host.as_str(), check.port))
+        .await
+        .map_err(|e| {
+            ProtocolFailure::new(
+                FailureReason::ConnectionFailure,
+                format!("could not connect: {e}"),
+            )
+        })?;
+
+    let session = match check.security {
+        Security::None | Security::StartTls => {
+            let mut session = Session::new(stream);
+            session.greeting().await?;
+
+            if check.security == Security::None {
+                if check.ehlo {
+                    session.ehlo().await?;
+                }
+
+                session.quit().await;
+                return Ok(());
+            }
+
+            let extensions = session.ehlo().await?;
+            let advertised = extensions
+                .lines
+                .iter()
+                .any(|line| line.eq_ignore_ascii_case("STARTTLS"));
+
+            if !advertised {
+                return Err(ProtocolFailure::new(
+                    FailureReason::UnexpectedStatus,
+                    String::from("does not advertise STARTTLS in its EHLO reply"),
+                ));
+            }
+
+            session.command("STARTTLS", 220).await?;
+
+            // Servers forget everything said before the upgrade, so the poller introduces itself again
+            let stream = handshake(connector, session.into_inner(), &check.host).await?;
+            let mut session = Session::new(stream);
+            session.ehlo().await?;
+
+            session
+        }
+        Security::Implicit => {
+            let stream = handshake(connector, stream, &check.host).await?;
+            let mut session = Session::new(stream);
+            session.greeting().await?;
+
+            if check.ehlo {
+                session.ehlo().await?;
+            }
+
+            session
+        }
+    };
+
+    session.quit().await;
+
+    Ok(())
+}
+
+/// Connects to the mail server and holds the configured conversation with it.
+pub async fn check(
+    connector: &TlsConnector,
+    check: &SmtpCheck,
+    timeout: Duration,
+) -> Result<(), ProtocolFailure> {
+    tokio::time::timeout(timeout, converse(connector, check))
+        .await
+        .map_err(|_| {
+            ProtocolFailure::new(
+                FailureReason::RequestTimeout,
+                format!("did not finish the conversation within {timeout:?}"),
+            )
+        })?
+}
+
+/// Waits for the greeting of origins written as `smtp://host:port`.
+pub struct SmtpChecker {
+    connector: TlsConnector,
+}
+
+impl Default for SmtpChecker {
+    fn default() -> Self {
+        Self {
+            connector: connector(),
+        }
+    }
+}
+
+impl Check for SmtpChecker {
+    fn execute<'a>(
+        &'a self,
+        origin: &'a Origin,
+        context: &'a CheckContext<'a>,
+    ) -> BoxFuture<'a, CheckOutcome> {
+        Box::pin(async move {
+            let Some(parsed) = SmtpCheck::parse(&origin.uri) else {
+                return CheckOutcome::new(Err(FailureReason::BadRequest));
+            };
+
+            match check(&self.connector, &parsed, context.timeout).await {
+                Ok(()) => CheckOutcome::new(Ok((None, context.elapsed_millis()))),
+                Err(failure) => CheckOutcome::failed(failure.reason, failure.detail),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::poller::smtp::{Security, SmtpCheck};
+
+    #[test]
+    fn checks_are_parsed_from_uris() {
+        assert_eq!(
+            SmtpCheck::parse("smtp://mail.example.com"),
+            Some(SmtpCheck {
+                host: String::from("mail.example.com"),
+                port: 25,
+                security: Security::None,
+                ehlo: false,
+            })
+        );
+        assert_eq!(
+            SmtpCheck::parse("smtp://mail.example.com:587?starttls"),
+            Some(SmtpCheck {
+                host: String::from("mail.example.com"),
+                port: 587,
+                security: Security::StartTls,
+                ehlo: true,
+            })
+        );
+        assert_eq!(
+            SmtpCheck::parse("smtps://mail.example.com?ehlo"),
+            Some(SmtpCheck {
+                host: String::from("mail.example.com"),
+                port: 465,
+                security: Security::Implicit,
+                ehlo: true,
+            })
+        );
+
+        assert_eq!(SmtpCheck::parse("smtps://mail.example.com?starttls"), None);
+        assert_eq!(SmtpCheck::parse("smtp://mail.example.com?auth"), None);
+        assert_eq!(SmtpCheck::parse("https://mail.example.com"), None);
+    }
+
+    #[test]
+    fn reply_lines_are_split_into_codes_and_text() {
+        assert_eq!(
+            super::parse_reply_line("220 mail.example.com ESMTP ready\r\n"),
+            Some((220, true, "mail.example.com ESMTP ready"))
+        );
+        assert_eq!(
+            super::parse_reply_line("250-STARTTLS\r\n"),
+            Some((250, false, "STARTTLS"))
+        );
+        assert_eq!(super::parse_reply_line("250\r\n"), Some((250, true, "")));
+
+        assert_eq!(super::parse_reply_line("OK\r\n"), None);
+        assert_eq!(super::parse_reply_line("250+STARTTLS\r\n"), None);
+    }
+}
@@ -0,0 +1,77 @@
+use std::ops::RangeInclusive;
+
+use color_eyre::eyre::{eyre, Result};
+
+/// The status codes an origin is expected to respond with, such as `200-299,301`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExpectedStatus {
+    ranges: Vec<RangeInclusive<u16>>,
+}
+
+fn parse_code(code: &str) -> Result<u16> {
+    code.trim()
+        .parse()
+        .ok()
+        .filter(|code| (100..=599).contains(code))
+        .ok_or_else(|| eyre!("'{}' is not a status code between 100 and 599", code.trim()))
+}
+
+impl ExpectedStatus {
+    /// Parses a comma separated list of status codes and inclusive ranges.
+    pub fn parse(value: &str) -> Result<Self> {
+        let ranges = value
+            .split(',')
+            .map(|part| {
+                let range = match part.split_once('-') {
+                    Some((start, end)) => parse_code(start)?..=parse_code(end)?,
+                    None => {
+                        let code = parse_code(part)?;
+                        code..=code
+                    }
+                };
+
+                if range.is_empty() {
+                    return Err(eyre!("'{}' ends before it starts", part.trim()));
+                }
+
+                Ok(range)
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { ranges })
+    }
+
+    pub fn contains(&self, status: u16) -> bool {
+        self.ranges.iter().any(|range| range.contains(&status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use color_eyre::eyre::Result;
+
+    use crate::poller::status::ExpectedStatus;
+
+    #[test]
+    fn codes_and_ranges_can_be_combined() -> Result<()> {
+        let expected = ExpectedStatus::parse("200-299, 301,404")?;
+
+        assert!(expected.contains(200));
+        assert!(expected.contains(299));
+        assert!(expected.contains(301));
+        assert!(expected.contains(404));
+        assert!(!expected.contains(302));
+        assert!(!expected.contains(500));
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_lists_are_rejected() {
+        assert!(ExpectedStatus::parse("").is_err());
+        assert!(ExpectedStatus::parse("2xx").is_err());
+        assert!(ExpectedStatus::parse("299-200").is_err());
+        assert!(ExpectedStatus::parse("200,").is_err());
+        assert!(ExpectedStatus::parse("700").is_err());
+    }
+}
@@ -0,0 +1,101 @@
+use std::io::ErrorKind;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use tokio::net::TcpStream;
+
+use crate::persistence::Origin;
+use crate::poller::check::{Check, CheckContext, CheckOutcome};
+use crate::poller::{CheckKind, FailureReason};
+
+/// Checks that something is listening at `address`, written as `host:port`, returning the address
+/// the host resolved to.
+pub async fn connect(address: &str, timeout: Duration) -> Result<IpAddr, FailureReason> {
+    let has_port = address
+        .rsplit_once(':')
+        .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
+
+    if !has_port {
+        return Err(FailureReason::BadRequest);
+    }
+
+    match tokio::time::timeout(timeout, TcpStream::connect(address)).await {
+        Ok(Ok(stream)) => stream
+            .peer_addr()
+            .map(|peer| peer.ip())
+            .map_err(|_| FailureReason::ConnectionFailure),
+        Ok(Err(e)) if e.kind() == ErrorKind::TimedOut => Err(FailureReason::RequestTimeout),
+        Ok(Err(_)) => Err(FailureReason::ConnectionFailure),
+        Err(_) => Err(FailureReason::RequestTimeout),
+    }
+}
+
+/// Connects to origins written as `tcp://host:port`.
+pub struct TcpChecker;
+
+impl Check for TcpChecker {
+    fn execute<'a>(
+        &'a self,
+        origin: &'a Origin,
+        context: &'a CheckContext<'a>,
+    ) -> BoxFuture<'a, CheckOutcome> {
+        Box::pin(async move {
+            let CheckKind::Tcp(address) = CheckKind::from_uri(&origin.uri) else {
+                return CheckOutcome::new(Err(FailureReason::BadRequest));
+            };
+
+            match connect(address, context.timeout).await {
+                Ok(remote_address) => CheckOutcome {
+                    remote_address: Some(remote_address),
+                    ..CheckOutcome::new(Ok((None, context.elapsed_millis())))
+                },
+                Err(reason) => CheckOutcome::new(Err(reason)),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::net::TcpListener;
+
+    use crate::poller::FailureReason;
+
+    const TIMEOUT: Duration = Duration::from_secs(1);
+
+    #[tokio::test]
+    async fn connects_to_listening_ports() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        assert_eq!(
+            super::connect(&address.to_string(), TIMEOUT).await,
+            Ok(address.ip())
+        );
+    }
+
+    #[tokio::test]
+    async fn closed_ports_are_connection_failures() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        assert_eq!(
+            super::connect(&address, TIMEOUT).await,
+            Err(FailureReason::ConnectionFailure)
+        );
+    }
+
+    #[tokio::test]
+    async fn addresses_need_a_port() {
+        for address in ["localhost", "localhost:", ":5432", "localhost:database"] {
+            assert_eq!(
+                super::connect(address, TIMEOUT).await,
+                Err(FailureReason::BadRequest)
+            );
+        }
+    }
+}
@@ -1,11 +1,32 @@
 use std::{collections::HashMap, sync::Arc, time::Duration};
 
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{TimeZone, Utc};
 use color_eyre::eyre::Result;
-use sqlx::PgPool;
+use ring::agreement;
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use sqlx::{Connection, PgPool};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
-use crate::poller::{AlertThreshold, FailureReason, Notifier, Poller, PollerConfiguration};
+use crate::credentials::{CredentialCipher, Credentials};
+use crate::locale::{Alert, Locale};
+use crate::persistence::{
+    AddressFamily, AlertKind, AlertWindow, ConnectionMode, DestinationKind, HttpMethod,
+    HttpVersion, JourneyStep, MaintenanceMode, MaintenanceWindow, PushSubscription,
+    QueryFailureDetails, Recurrence, UserDestination,
+};
+use crate::poller::clock::ManualClock;
+use crate::poller::events::Publisher;
+use crate::poller::firehose::Sink;
+use crate::poller::slack::SlackWebhook;
+use crate::poller::{
+    AlertThreshold, CheckKind, CheckNowError, Command, DomainExpiry, EventBus, ExternalCommands,
+    FailureDiagnostics, FailureReason, FallbackChannel, Firehose, Notifier, Poller,
+    PollerConfiguration, Traceroute, VapidKeys, WebPush, STABLE_CHECKS,
+};
 
 const SNS_TOPIC: &str = "some-sns-topic";
 
@@ -44,19 +65,19 @@ impl Notifier for MockSnsClient {
 
 async fn fetch_latest_query_status(pool: &PgPool, uri: &str) -> Result<Option<u16>> {
     let successes =
-        crate::persistence::fetch_origins_with_most_recent_success_metrics(&pool).await?;
+        crate::persistence::fetch_origins_with_most_recent_success_metrics(pool).await?;
 
     let status = successes
         .into_iter()
-        .filter_map(|r| (r.uri == uri).then_some(r.status as u16))
-        .next();
+        .filter_map(|r| (r.uri == uri).then_some(r.status.map(|status| status as u16)))
+        .next()
+        .flatten();
 
     Ok(status)
 }
 
 async fn fetch_latest_query_failure(pool: &PgPool, uri: &str) -> Result<Option<String>> {
-    let failures =
-        crate::persistence::fetch_origins_with_most_recent_failure_metrics(&pool).await?;
+    let failures = crate::persistence::fetch_origins_with_most_recent_failure_metrics(pool).await?;
 
     let failure_reason = failures
         .into_iter()
@@ -69,7 +90,9 @@ async fn fetch_latest_query_failure(pool: &PgPool, uri: &str) -> Result<Option<S
 fn create_poller(pool: &PgPool) -> Poller<MockSnsClient> {
     let http_client = reqwest::Client::new();
     let sns_client = MockSnsClient::default();
-    let configuration = PollerConfiguration::new(AlertThreshold::default(), SNS_TOPIC);
+    // Rounds start every check at once, so tests do not wait out the jitter
+    let configuration =
+        PollerConfiguration::new(AlertThreshold::default(), SNS_TOPIC).with_jitter(Duration::ZERO);
 
     Poller::new(pool.clone(), http_client, sns_client.clone(), configuration)
 }
@@ -102,7 +125,7 @@ async fn can_query_all_origins(pool: PgPool) -> Result<()> {
 }
 
 #[sqlx::test]
-async fn can_record_client_failures(pool: PgPool) -> Result<()> {
+async fn origins_can_be_checked_with_other_methods(pool: PgPool) -> Result<()> {
     let mut server = mockito::Server::new_async().await;
     let uri = server.url();
 
@@ -110,125 +133,3878 @@ async fn can_record_client_failures(pool: PgPool) -> Result<()> {
 
     let origin_uid = Uuid::new_v4();
     crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+    crate::persistence::set_origin_request(
+        &pool,
+        origin_uid,
+        HttpMethod::Post,
+        Some(r#"{"check":"deep"}"#),
+        Some("application/json"),
+    )
+    .await?;
 
     let mock = server
+        .mock("POST", "/")
+        .match_header("content-type", "application/json")
+        .match_body(r#"{"check":"deep"}"#)
+        .with_status(200)
+        .create_async()
+        .await;
+
+    poller.query_all_origins().await?;
+
+    mock.assert_async().await;
+
+    let status = fetch_latest_query_status(&pool, &uri).await?;
+
+    assert_eq!(status, Some(200));
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn head_requests_fall_back_to_get_when_rejected(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let uri = server.url();
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+    crate::persistence::set_origin_request(&pool, origin_uid, HttpMethod::Head, None, None).await?;
+
+    let head = server
+        .mock("HEAD", "/")
+        .with_status(405)
+        .create_async()
+        .await;
+
+    let get = server
         .mock("GET", "/")
-        .with_status(404)
+        .with_status(200)
+        .create_async()
+        .await;
+
+    poller.query_all_origins().await?;
+
+    head.assert_async().await;
+    get.assert_async().await;
+
+    let status = fetch_latest_query_status(&pool, &uri).await?;
+
+    assert_eq!(status, Some(200));
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn checks_send_the_user_agent_of_the_origin_over_the_default(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let uri = server.url();
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+
+    let default = server
+        .mock("GET", "/")
+        .match_header("user-agent", crate::poller::clients::DEFAULT_USER_AGENT)
+        .with_status(200)
+        .create_async()
+        .await;
+
+    poller.query_all_origins().await?;
+    default.assert_async().await;
+
+    let user_agent = "Mozilla/5.0 (compatible; uptime)";
+    crate::persistence::set_origin_request_options(&pool, origin_uid, Some(user_agent), false)
+        .await?;
+
+    let custom = server
+        .mock("GET", "/")
+        .match_header("user-agent", user_agent)
+        .with_status(200)
+        .create_async()
+        .await;
+
+    poller.query_all_origins().await?;
+    custom.assert_async().await;
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn cache_busting_adds_a_random_query_parameter(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let uri = format!("{}/health?verbose=1", server.url());
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+    crate::persistence::set_origin_request_options(&pool, origin_uid, None, true).await?;
+
+    let mock = server
+        .mock("GET", "/health")
+        .match_query(mockito::Matcher::AllOf(vec![
+            mockito::Matcher::UrlEncoded(String::from("verbose"), String::from("1")),
+            mockito::Matcher::Regex(String::from("uptime_cache_bust=[0-9a-f]{16}")),
+        ]))
+        .with_status(200)
+        .expect(2)
         .create_async()
         .await;
 
     poller.query_all_origins().await?;
+    poller.query_all_origins().await?;
 
     mock.assert_async().await;
 
     let status = fetch_latest_query_status(&pool, &uri).await?;
 
-    assert_eq!(status, Some(404));
+    assert_eq!(status, Some(200));
 
     Ok(())
 }
 
 #[sqlx::test]
-async fn can_record_query_failures(pool: PgPool) -> Result<()> {
-    // intentionally invalid TLD
-    let uri = "https://mozilla.rust";
+async fn origins_measuring_both_connection_modes_record_the_cold_latency(
+    pool: PgPool,
+) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let uri = server.url();
 
     let poller = create_poller(&pool);
 
     let origin_uid = Uuid::new_v4();
-    crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+    crate::persistence::set_origin_connection_mode(&pool, origin_uid, ConnectionMode::Both).await?;
+
+    // Once over the reused connection and once over a new one
+    let mock = server
+        .mock("GET", "/")
+        .with_status(200)
+        .expect(2)
+        .create_async()
+        .await;
 
     poller.query_all_origins().await?;
 
-    let failure_reason = fetch_latest_query_failure(&pool, uri).await?;
+    mock.assert_async().await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 10).await?;
+
+    assert_eq!(checks.len(), 1);
+    assert_eq!(checks[0].status, Some(200));
+    assert!(checks[0].cold_latency_millis.is_some());
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn responses_without_expected_headers_fail(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let uri = server.url();
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+    crate::persistence::set_origin_expected_headers(
+        &pool,
+        origin_uid,
+        Some("Strict-Transport-Security\nContent-Type: application/json"),
+    )
+    .await?;
+
+    let secure = server
+        .mock("GET", "/")
+        .with_status(200)
+        .with_header("strict-transport-security", "max-age=31536000")
+        .with_header("content-type", "application/json; charset=utf-8")
+        .create_async()
+        .await;
+
+    poller.query_all_origins().await?;
+    secure.assert_async().await;
+
+    assert_eq!(fetch_latest_query_status(&pool, &uri).await?, Some(200));
+
+    secure.remove_async().await;
+
+    // Someone removed HSTS at the edge
+    let insecure = server
+        .mock("GET", "/")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .create_async()
+        .await;
+
+    poller.query_all_origins().await?;
+    insecure.assert_async().await;
+
+    let failure_reason = fetch_latest_query_failure(&pool, &uri).await?;
 
     assert_eq!(
         failure_reason.as_deref(),
-        Some(FailureReason::BadRequest.as_str())
+        Some(FailureReason::HeaderAssertionFailed.as_str())
+    );
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 10).await?;
+
+    assert_eq!(
+        checks
+            .last()
+            .and_then(|check| check.failure_detail.as_deref()),
+        Some("the strict-transport-security header is missing")
     );
 
     Ok(())
 }
 
 #[sqlx::test]
-async fn can_route_alerts_to_clients(pool: PgPool) -> Result<()> {
-    // intentionally invalid TLD
-    let uri = "https://mozilla.rust";
+async fn forbidden_text_fails_successful_responses(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let uri = server.url();
 
     let poller = create_poller(&pool);
 
     let origin_uid = Uuid::new_v4();
-    crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+    crate::persistence::set_origin_forbidden_text(&pool, origin_uid, Some("maintenance mode"))
+        .await?;
 
-    // Make 3 queries, all of which fail
-    for _ in 0..3 {
-        poller.query_all_origins().await?;
-    }
+    let mock = server
+        .mock("GET", "/")
+        .with_status(200)
+        .with_body("<p>The site is in Maintenance Mode</p>")
+        .create_async()
+        .await;
 
-    let map = poller.notifier.sent_messages.read().await;
-    let messages = &map[SNS_TOPIC];
+    poller.query_all_origins().await?;
 
-    let expected_message = format!("The failure rate of {uri} exceeds the SLA");
+    mock.assert_async().await;
 
-    assert_eq!(messages.len(), 1);
-    assert_eq!(messages[0].subject, "Outage detected");
-    assert_eq!(messages[0].message, expected_message);
+    let failure_reason = fetch_latest_query_failure(&pool, &uri).await?;
+
+    assert_eq!(
+        failure_reason.as_deref(),
+        Some(FailureReason::ForbiddenContent.as_str())
+    );
 
     Ok(())
 }
 
 #[sqlx::test]
-async fn alerts_are_not_constantly_routed(pool: PgPool) -> Result<()> {
-    // intentionally invalid TLD
-    let uri = "https://mozilla.rust";
+async fn can_record_client_failures(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let uri = server.url();
 
     let poller = create_poller(&pool);
 
     let origin_uid = Uuid::new_v4();
-    crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
 
-    // Make 3 queries, all of which fail to trigger an alert
-    for _ in 0..3 {
-        poller.query_all_origins().await?;
-    }
+    let mock = server
+        .mock("GET", "/")
+        .with_status(404)
+        .create_async()
+        .await;
 
-    // Trigger another query which fails
     poller.query_all_origins().await?;
 
-    // Check we only sent a single message
-    let map = poller.notifier.sent_messages.read().await;
+    mock.assert_async().await;
 
-    assert_eq!(map[SNS_TOPIC].len(), 1);
+    let status = fetch_latest_query_status(&pool, &uri).await?;
+
+    assert_eq!(status, Some(404));
 
     Ok(())
 }
 
 #[sqlx::test]
-async fn alerts_can_cooldown_after_firing(pool: PgPool) -> Result<()> {
+async fn can_record_query_failures(pool: PgPool) -> Result<()> {
     // intentionally invalid TLD
     let uri = "https://mozilla.rust";
 
-    let mut poller = create_poller(&pool);
-    poller.configuration.alert_threshold.cooldown = chrono::Duration::milliseconds(100);
+    let poller = create_poller(&pool);
 
     let origin_uid = Uuid::new_v4();
     crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
 
-    // Make 3 queries, all of which fail to trigger an alert
-    for _ in 0..3 {
-        poller.query_all_origins().await?;
-    }
+    poller.query_all_origins().await?;
 
-    // Wait a bit for the cooldown
-    tokio::time::sleep(Duration::from_millis(100)).await;
+    let failure_reason = fetch_latest_query_failure(&pool, uri).await?;
 
-    // Trigger another query which fails
-    poller.query_all_origins().await?;
+    assert_eq!(
+        failure_reason.as_deref(),
+        Some(FailureReason::BadRequest.as_str())
+    );
 
-    // Check we sent 2 messages since the cooldown had passed
-    let map = poller.notifier.sent_messages.read().await;
+    Ok(())
+}
 
-    assert_eq!(map[SNS_TOPIC].len(), 2);
+#[test]
+fn check_kinds_are_chosen_by_scheme() {
+    assert_eq!(
+        CheckKind::from_uri("https://example.com"),
+        CheckKind::Http("https://example.com")
+    );
+    assert_eq!(
+        CheckKind::from_uri("tcp://db.internal:5432"),
+        CheckKind::Tcp("db.internal:5432")
+    );
+    assert_eq!(
+        CheckKind::from_uri("icmp://router.internal/"),
+        CheckKind::Icmp("router.internal")
+    );
+    assert_eq!(
+        CheckKind::from_uri("dns://example.com?type=MX"),
+        CheckKind::Dns("dns://example.com?type=MX")
+    );
+    assert_eq!(
+        CheckKind::from_uri("grpcs://api.example.com/payments.v1.Payments"),
+        CheckKind::Grpc("grpcs://api.example.com/payments.v1.Payments")
+    );
+    assert_eq!(
+        CheckKind::from_uri("wss://realtime.example.com/socket"),
+        CheckKind::WebSocket("wss://realtime.example.com/socket")
+    );
+    assert_eq!(
+        CheckKind::from_uri("smtp://mail.example.com:587?starttls"),
+        CheckKind::Smtp("smtp://mail.example.com:587?starttls")
+    );
+    assert_eq!(
+        CheckKind::from_uri("mqtts://broker.example.com?topic=uptime/probe"),
+        CheckKind::Mqtt("mqtts://broker.example.com?topic=uptime/probe")
+    );
+    assert_eq!(
+        CheckKind::from_uri("rediss://cache.example.com"),
+        CheckKind::KeyValue("rediss://cache.example.com")
+    );
+    assert_eq!(
+        CheckKind::from_uri("memcached://sessions.internal"),
+        CheckKind::KeyValue("memcached://sessions.internal")
+    );
+    assert_eq!(
+        CheckKind::from_uri("postgres://uptime@db.internal/app"),
+        CheckKind::Database("postgres://uptime@db.internal/app")
+    );
+    assert_eq!(
+        CheckKind::from_uri("mysql://uptime@db.internal/app"),
+        CheckKind::Database("mysql://uptime@db.internal/app")
+    );
+    assert_eq!(
+        CheckKind::from_uri("ntp://time.example.com"),
+        CheckKind::Ntp("ntp://time.example.com")
+    );
+    assert_eq!(
+        CheckKind::from_uri("redirect://www.example.com"),
+        CheckKind::Redirect("redirect://www.example.com")
+    );
+    assert_eq!(
+        CheckKind::from_uri("download+https://mirror.example.com/ubuntu.iso"),
+        CheckKind::Download("download+https://mirror.example.com/ubuntu.iso")
+    );
+    assert_eq!(
+        CheckKind::from_uri("ftp://files.example.com/outgoing?list"),
+        CheckKind::FileTransfer("ftp://files.example.com/outgoing?list")
+    );
+    assert_eq!(
+        CheckKind::from_uri("sftp://files.example.com"),
+        CheckKind::FileTransfer("sftp://files.example.com")
+    );
+    assert_eq!(
+        CheckKind::from_uri("command://check_vpn.sh?arg=10.0.0.1"),
+        CheckKind::Command
+    );
+    assert_eq!(
+        CheckKind::from_uri("heartbeat://nightly-backup"),
+        CheckKind::Heartbeat
+    );
+    assert_eq!(
+        CheckKind::from_uri("journey://checkout"),
+        CheckKind::Journey
+    );
+}
+
+#[sqlx::test]
+async fn can_check_tcp_origins(pool: PgPool) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let uri = format!("tcp://{}", listener.local_addr()?);
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+
+    poller.query_all_origins().await?;
+
+    let successes =
+        crate::persistence::fetch_origins_with_most_recent_success_metrics(&pool).await?;
+
+    // TCP checks succeed without an HTTP status
+    assert!(successes.iter().any(|r| r.uri == uri && r.status.is_none()));
+
+    drop(listener);
+    poller.query_all_origins().await?;
+
+    let failure_reason = fetch_latest_query_failure(&pool, &uri).await?;
+
+    assert_eq!(
+        failure_reason.as_deref(),
+        Some(FailureReason::ConnectionFailure.as_str())
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn can_check_websocket_origins(pool: PgPool) -> Result<()> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let uri = format!("ws://{}/socket#ping", listener.local_addr()?);
+
+    // Accept the handshake and answer the ping that follows it
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+
+            while !request.ends_with(b"\r\n\r\n") {
+                let length = stream.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..length]);
+            }
+
+            let request = String::from_utf8(request).unwrap();
+            let key = request
+                .lines()
+                .filter_map(|line| line.split_once(": "))
+                .find(|(name, _)| name.eq_ignore_ascii_case("sec-websocket-key"))
+                .map(|(_, key)| key.to_owned())
+                .unwrap();
+
+            let response = format!(
+                "HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\nUpgrade: websocket\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                super::websocket::accept_key(&key)
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+
+            let mut ping = [0; 6];
+            stream.read_exact(&mut ping).await.unwrap();
+            stream.write_all(&[0x8a, 0]).await.unwrap();
+        }
+    });
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    assert_eq!(fetch_latest_query_status(&pool, &uri).await?, Some(101));
+
+    // Plain HTTP endpoints respond without upgrading the connection
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/")
+        .with_status(200)
+        .create_async()
+        .await;
+
+    let uri = server.url().replacen("http", "ws", 1);
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    let failure_reason = fetch_latest_query_failure(&pool, &uri).await?;
+
+    assert_eq!(
+        failure_reason.as_deref(),
+        Some(FailureReason::UnexpectedStatus.as_str())
+    );
+
+    Ok(())
+}
+
+/// Runs a mail server that greets each connection with `greeting` and accepts every command.
+async fn spawn_smtp_server(greeting: &'static str) -> Result<std::net::SocketAddr> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let address = listener.local_addr()?;
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+
+            writer.write_all(greeting.as_bytes()).await.unwrap();
+
+            while let Ok(Some(line)) = lines.next_line().await {
+                let reply = match line.split_whitespace().next() {
+                    Some("EHLO") => "250-mail.example.com\r\n250 SIZE 10240000\r\n",
+                    Some("QUIT") => "221 Bye\r\n",
+                    _ => "502 Command not implemented\r\n",
+                };
+
+                writer.write_all(reply.as_bytes()).await.unwrap();
+            }
+        }
+    });
+
+    Ok(address)
+}
+
+#[sqlx::test]
+async fn can_check_smtp_servers(pool: PgPool) -> Result<()> {
+    let poller = create_poller(&pool);
+
+    let ready = spawn_smtp_server("220 mail.example.com ESMTP\r\n").await?;
+    let uri = format!("smtp://{ready}?ehlo");
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    let successes =
+        crate::persistence::fetch_origins_with_most_recent_success_metrics(&pool).await?;
+
+    assert!(successes.iter().any(|r| r.uri == uri && r.status.is_none()));
+
+    // Servers refusing mail greet clients with an error instead
+    let refusing = spawn_smtp_server("554 No SMTP service here\r\n").await?;
+    let uri = format!("smtp://{refusing}");
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    let failure_reason = fetch_latest_query_failure(&pool, &uri).await?;
+
+    assert_eq!(
+        failure_reason.as_deref(),
+        Some(FailureReason::NotServing.as_str())
+    );
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(
+        checks[0].failure_detail.as_deref(),
+        Some("greeted with 554 No SMTP service here instead of 220")
+    );
+
+    // The test server does not offer STARTTLS, so it cannot be upgraded
+    let uri = format!("smtp://{ready}?starttls");
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(
+        checks[0].failure_detail.as_deref(),
+        Some("does not advertise STARTTLS in its EHLO reply")
+    );
+
+    Ok(())
+}
+
+/// Runs an FTP server that accepts the logins in `accounts` and lists every directory as a single
+/// file.
+async fn spawn_ftp_server(accounts: &'static [(&str, &str)]) -> Result<std::net::SocketAddr> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let address = listener.local_addr()?;
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let mut username = String::new();
+            let mut data = None;
+
+            writer
+                .write_all(b"220 files.example.com FTP\r\n")
+                .await
+                .unwrap();
+
+            while let Ok(Some(line)) = lines.next_line().await {
+                let (command, argument) = line.split_once(' ').unwrap_or((&line, ""));
+
+                let reply = match command {
+                    "USER" => {
+                        username = argument.to_owned();
+                        String::from("331 Password required\r\n")
+                    }
+                    "PASS" if accounts.contains(&(username.as_str(), argument)) => {
+                        String::from("230 Logged in\r\n")
+                    }
+                    "PASS" => String::from("530 Login incorrect.\r\n"),
+                    "EPSV" => {
+                        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+                        let port = listener.local_addr().unwrap().port();
+                        data = Some(listener);
+
+                        format!("229 Entering Extended Passive Mode (|||{port}|)\r\n")
+                    }
+                    "LIST" => {
+                        let (mut connection, _) = data.take().unwrap().accept().await.unwrap();
+
+                        writer
+                            .write_all(b"150 Here comes the listing\r\n")
+                            .await
+                            .unwrap();
+                        connection
+                            .write_all(b"-rw-r--r-- 1 ftp ftp 42 Oct 15 09:00 report.csv\r\n")
+                            .await
+                            .unwrap();
+                        drop(connection);
+
+                        String::from("226 Directory send OK\r\n")
+                    }
+                    "QUIT" => String::from("221 Goodbye\r\n"),
+                    _ => String::from("502 Command not implemented\r\n"),
+                };
+
+                writer.write_all(reply.as_bytes()).await.unwrap();
+            }
+        }
+    });
+
+    Ok(address)
+}
+
+#[sqlx::test]
+async fn can_check_ftp_servers(pool: PgPool) -> Result<()> {
+    let cipher = CredentialCipher::new([3; 32]);
+    let poller = create_poller(&pool).with_credentials(cipher.clone());
+
+    let public = spawn_ftp_server(&[("anonymous", "anonymous@")]).await?;
+    let uri = format!("ftp://{public}/outgoing?list");
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(checks[0].failure_reason, None);
+    assert_eq!(checks[0].body_bytes, Some(49));
+
+    // Private servers reject anonymous logins until the origin is given credentials
+    let private = spawn_ftp_server(&[("exchange", "s3cret")]).await?;
+    let uri = format!("ftp://{private}");
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(
+        checks[0].failure_reason.as_deref(),
+        Some(FailureReason::AuthenticationFailed.as_str())
+    );
+    assert_eq!(
+        checks[0].failure_detail.as_deref(),
+        Some("rejected the login as anonymous: 530 Login incorrect.")
+    );
+
+    let credentials = Credentials::Basic {
+        username: String::from("exchange"),
+        password: String::from("s3cret"),
+    };
+    let encrypted = cipher.encrypt(origin_uid, &credentials)?;
+    crate::persistence::set_origin_credentials(&pool, origin_uid, Some(&encrypted)).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(checks[0].failure_reason, None);
+
+    Ok(())
+}
+
+/// Runs an MQTT broker that answers connections with `return_code` and delivers every message
+/// published to it back to the publisher.
+async fn spawn_mqtt_broker(return_code: u8) -> Result<std::net::SocketAddr> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let address = listener.local_addr()?;
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                while let Ok(kind) = stream.read_u8().await {
+                    // Every packet a check sends is short enough for a single byte of length
+                    let length = stream.read_u8().await.unwrap();
+                    let mut body = vec![0; usize::from(length)];
+                    stream.read_exact(&mut body).await.unwrap();
+
+                    let reply = match kind & 0xf0 {
+                        0x10 => vec![0x20, 2, 0, return_code],
+                        0x80 => vec![0x90, 3, body[0], body[1], 0],
+                        0x30 => [&[kind, length][..], &body].concat(),
+                        0xc0 => vec![0xd0, 0],
+                        _ => return,
+                    };
+
+                    stream.write_all(&reply).await.unwrap();
+                }
+            });
+        }
+    });
+
+    Ok(address)
+}
+
+#[sqlx::test]
+async fn can_check_mqtt_brokers(pool: PgPool) -> Result<()> {
+    let poller = create_poller(&pool);
+
+    let broker = spawn_mqtt_broker(0).await?;
+
+    for uri in [
+        format!("mqtt://{broker}"),
+        format!("mqtt://{broker}?topic=uptime/probe"),
+    ] {
+        let origin_uid = Uuid::new_v4();
+        crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+
+        poller.check_origin_now(origin_uid).await;
+
+        let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+        assert_eq!(checks[0].failure_reason, None, "{uri} failed");
+        assert!(checks[0].latency_millis.is_some());
+    }
+
+    let refusing = spawn_mqtt_broker(4).await?;
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &format!("mqtt://{refusing}")).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(
+        checks[0].failure_reason.as_deref(),
+        Some(FailureReason::AuthenticationFailed.as_str())
+    );
+    assert_eq!(
+        checks[0].failure_detail.as_deref(),
+        Some("rejected the username or password, returning code 4 to CONNECT")
+    );
+
+    Ok(())
+}
+
+/// Runs a Redis server that requires `password` before answering `PING`, and a Memcached server
+/// that always answers with its version, returning their addresses.
+async fn spawn_cache_servers(
+    password: &'static str,
+) -> Result<(std::net::SocketAddr, std::net::SocketAddr)> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let redis = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let memcached = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addresses = (redis.local_addr()?, memcached.local_addr()?);
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = redis.accept().await {
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let mut authenticating = false;
+            let mut authenticated = false;
+
+            // Only the bulk strings of each command matter, as every argument is on its own line
+            while let Ok(Some(line)) = lines.next_line().await {
+                let reply = match line.as_str() {
+                    "AUTH" => {
+                        authenticating = true;
+                        continue;
+                    }
+                    line if authenticating && !line.starts_with(['*', '$']) => {
+                        authenticating = false;
+                        authenticated = line == password;
+
+                        if authenticated {
+                            "+OK\r\n"
+                        } else {
+                            "-WRONGPASS invalid username-password pair\r\n"
+                        }
+                    }
+                    "PING" if authenticated => "+PONG\r\n",
+                    "PING" => "-NOAUTH Authentication required.\r\n",
+                    "QUIT" => "+OK\r\n",
+                    _ => continue,
+                };
+
+                writer.write_all(reply.as_bytes()).await.unwrap();
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = memcached.accept().await {
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+
+            while let Ok(Some(line)) = lines.next_line().await {
+                if line == "version" {
+                    writer.write_all(b"VERSION 1.6.21\r\n").await.unwrap();
+                }
+            }
+        }
+    });
+
+    Ok(addresses)
+}
+
+#[sqlx::test]
+async fn can_check_cache_servers(pool: PgPool) -> Result<()> {
+    let cipher = CredentialCipher::new([4; 32]);
+    let poller = create_poller(&pool).with_credentials(cipher.clone());
+
+    let (redis, memcached) = spawn_cache_servers("s3cret").await?;
+
+    let origin_uid = Uuid::new_v4();
+    let uri = format!("memcached://{memcached}");
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(checks[0].failure_reason, None);
+
+    // Redis refuses to answer until the origin is given its password
+    let origin_uid = Uuid::new_v4();
+    let uri = format!("redis://{redis}");
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(
+        checks[0].failure_reason.as_deref(),
+        Some(FailureReason::AuthenticationFailed.as_str())
+    );
+    assert_eq!(
+        checks[0].failure_detail.as_deref(),
+        Some("answered PING with NOAUTH Authentication required.")
+    );
+
+    let credentials = Credentials::Basic {
+        username: String::from("default"),
+        password: String::from("s3cret"),
+    };
+    let encrypted = cipher.encrypt(origin_uid, &credentials)?;
+    crate::persistence::set_origin_credentials(&pool, origin_uid, Some(&encrypted)).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(checks[0].failure_reason, None);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn can_check_databases(pool: PgPool) -> Result<()> {
+    let cipher = CredentialCipher::new([5; 32]);
+    let poller = create_poller(&pool).with_credentials(cipher.clone());
+
+    // Checks the database the tests run against, with its password moved into the credentials
+    let mut dsn = reqwest::Url::parse(&std::env::var("DATABASE_URL")?)?;
+    let credentials = Credentials::Basic {
+        username: dsn.username().to_owned(),
+        password: dsn.password().unwrap_or_default().to_owned(),
+    };
+    dsn.set_password(None).unwrap();
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, dsn.as_str()).await?;
+
+    let encrypted = cipher.encrypt(origin_uid, &credentials)?;
+    crate::persistence::set_origin_credentials(&pool, origin_uid, Some(&encrypted)).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(checks[0].failure_reason, None);
+    assert!(checks[0].latency_millis.is_some());
+
+    // Nothing listens on a port that was just released
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let address = listener.local_addr()?;
+    drop(listener);
+
+    let origin_uid = Uuid::new_v4();
+    let uri = format!("mysql://uptime@{address}/app");
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(
+        checks[0].failure_reason.as_deref(),
+        Some(FailureReason::ConnectionFailure.as_str())
+    );
+
+    Ok(())
+}
+
+/// Answers NTP requests with a clock that is `skew` ahead of the real one.
+async fn spawn_time_server(skew: chrono::Duration) -> Result<std::net::SocketAddr> {
+    let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await?;
+    let address = socket.local_addr()?;
+
+    let timestamp = |at: chrono::DateTime<Utc>| {
+        let seconds = (at.timestamp() + 2_208_988_800) as u64;
+        let fraction = (u64::from(at.timestamp_subsec_nanos()) << 32) / 1_000_000_000;
+
+        ((seconds << 32) | fraction).to_be_bytes()
+    };
+
+    tokio::spawn(async move {
+        let mut request = [0; 48];
+
+        while let Ok((_, peer)) = socket.recv_from(&mut request).await {
+            let now = timestamp(Utc::now() + skew);
+
+            let mut response = [0; 48];
+            response[0] = 0b00_100_100;
+            response[1] = 2;
+            response[24..32].copy_from_slice(&request[40..48]);
+            response[32..40].copy_from_slice(&now);
+            response[40..48].copy_from_slice(&now);
+
+            socket.send_to(&response, peer).await.unwrap();
+        }
+    });
+
+    Ok(address)
+}
+
+#[sqlx::test]
+async fn ntp_checks_record_the_offset_and_fail_when_clocks_drift(pool: PgPool) -> Result<()> {
+    let poller = create_poller(&pool);
+
+    let address = spawn_time_server(chrono::Duration::zero()).await?;
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &format!("ntp://{address}")).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(checks[0].failure_reason, None);
+    assert!(checks[0]
+        .clock_offset_millis
+        .is_some_and(|offset| offset.abs() < 100));
+
+    // A server a minute fast is well outside the allowed 250ms
+    let address = spawn_time_server(chrono::Duration::minutes(1)).await?;
+    let uri = format!("ntp://{address}?max_offset_ms=250");
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(
+        checks[0].failure_reason.as_deref(),
+        Some(FailureReason::ClockDrift.as_str())
+    );
+    assert!(checks[0]
+        .failure_detail
+        .as_deref()
+        .is_some_and(|detail| detail.contains("ahead of the poller's")));
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn can_check_sftp_servers(pool: PgPool) -> Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let poller = create_poller(&pool);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let address = listener.local_addr()?;
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            stream.write_all(b"SSH-2.0-OpenSSH_9.6\r\n").await.unwrap();
+        }
+    });
+
+    let uri = format!("sftp://{address}");
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(checks[0].failure_reason, None);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn downloads_record_their_throughput_and_fail_when_too_slow(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let poller = create_poller(&pool);
+
+    // Twice the limit, so only the first megabyte is downloaded
+    let mock = server
+        .mock("GET", "/asset.bin")
+        .with_status(200)
+        .with_body(vec![0; 2 * 1024 * 1024])
+        .expect(2)
+        .create_async()
+        .await;
+
+    let uri = format!("download+{}/asset.bin#limit_mb=1", server.url());
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(checks[0].status, Some(200));
+    assert!(checks[0].throughput_bytes_per_second.is_some());
+    assert!(checks[0]
+        .body_bytes
+        .is_some_and(|bytes| bytes >= 1024 * 1024));
+
+    // Faster than a megabyte a millisecond is out of reach
+    let uri = format!(
+        "download+{}/asset.bin#limit_mb=1&min_mb_per_second=1000000",
+        server.url()
+    );
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    mock.assert_async().await;
+
+    let failure_reason = fetch_latest_query_failure(&pool, &uri).await?;
+
+    assert_eq!(
+        failure_reason.as_deref(),
+        Some(FailureReason::SlowDownload.as_str())
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn commands_fail_with_what_they_printed_on_non_zero_exits(pool: PgPool) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let directory = std::env::temp_dir().join(format!("uptime-commands-{}", Uuid::new_v4()));
+    let script = directory.join("check.sh");
+
+    std::fs::create_dir(&directory)?;
+    std::fs::write(
+        &script,
+        "#!/bin/sh\n[ \"$1\" = \"up\" ] && exit 0\necho \"vpn is $1\" >&2\nexit 3\n",
+    )?;
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
+
+    let poller = create_poller(&pool).with_external_commands(ExternalCommands::new(&directory));
+
+    let up = "command://check.sh?arg=up";
+    let up_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, up_uid, up).await?;
+
+    let down = "command://check.sh?arg=down";
+    let down_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, down_uid, down).await?;
+
+    poller.check_origin_now(up_uid).await;
+    poller.check_origin_now(down_uid).await;
+
+    std::fs::remove_dir_all(&directory)?;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, up_uid, 1).await?;
+
+    assert_eq!(checks[0].failure_reason, None);
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, down_uid, 1).await?;
+
+    assert_eq!(
+        checks[0].failure_reason.as_deref(),
+        Some(FailureReason::CommandFailed.as_str())
+    );
+    assert_eq!(
+        checks[0].failure_detail.as_deref(),
+        Some("check.sh exited with status 3: vpn is down")
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn commands_only_inherit_allowed_variables(pool: PgPool) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let directory = std::env::temp_dir().join(format!("uptime-commands-{}", Uuid::new_v4()));
+    let script = directory.join("env.sh");
+
+    std::fs::create_dir(&directory)?;
+    std::fs::write(
+        &script,
+        "#!/bin/sh
+env >&2
+exit 1
+",
+    )?;
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
+
+    // Stands in for the secrets the poller is configured with
+    std::env::set_var("UPTIME_TEST_SECRET", "hunter2");
+
+    let poller = create_poller(&pool).with_external_commands(ExternalCommands::new(&directory));
+
+    let uri = "command://env.sh";
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    std::fs::remove_dir_all(&directory)?;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+    let printed = checks[0].failure_detail.as_deref().unwrap_or_default();
+
+    assert!(printed.contains(&format!("UPTIME_ORIGIN_UID={origin_uid}")));
+    assert!(printed.contains("PATH="));
+    assert!(!printed.contains("UPTIME_TEST_SECRET"));
+    assert!(!printed.contains("DATABASE_URL"));
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn redirect_chain_changes_are_alerted_on(pool: PgPool) -> Result<()> {
+    let poller = create_poller(&pool);
+
+    let uri = "redirect://www.example.com";
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+
+    let before = "http://example.com/ 301 -> https://www.example.com/ 200";
+    let after =
+        "http://example.com/ 301 -> https://example.com/ 301 -> https://www.example.com/ 200";
+
+    // Seeing the same chain again is not a change
+    poller
+        .record_redirect_chain(origin_uid, uri, before, true)
+        .await?;
+    poller
+        .record_redirect_chain(origin_uid, uri, before, true)
+        .await?;
+
+    assert!(poller.notifier.sent_messages.read().await.is_empty());
+
+    poller
+        .record_redirect_chain(origin_uid, uri, after, true)
+        .await?;
+
+    let map = poller.notifier.sent_messages.read().await;
+    let messages = &map[SNS_TOPIC];
+
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].subject, "Redirect chain changed");
+
+    let chains = crate::persistence::fetch_redirect_chains(&pool, origin_uid, 10).await?;
+
+    assert_eq!(chains.len(), 2);
+    assert_eq!(chains[0].chain, after);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn response_body_changes_are_recorded_and_alerted_on(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let uri = server.url();
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+    crate::persistence::set_origin_body_change_detection(&pool, origin_uid, true).await?;
+
+    let original = server
+        .mock("GET", "/")
+        .with_status(200)
+        .with_body("<h1>Welcome</h1>")
+        .expect(2)
+        .create_async()
+        .await;
+
+    // Seeing the same body again is not a change
+    poller.query_all_origins().await?;
+    poller.query_all_origins().await?;
+    original.assert_async().await;
+
+    assert!(poller.notifier.sent_messages.read().await.is_empty());
+
+    original.remove_async().await;
+
+    let defaced = server
+        .mock("GET", "/")
+        .with_status(200)
+        .with_body("<h1>Hacked</h1>")
+        .create_async()
+        .await;
+
+    poller.query_all_origins().await?;
+    defaced.assert_async().await;
+
+    let map = poller.notifier.sent_messages.read().await;
+    let messages = &map[SNS_TOPIC];
+
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].subject, "Content changed");
+
+    let changes = crate::persistence::fetch_body_changes(&pool, origin_uid, 10).await?;
+
+    assert_eq!(changes.len(), 2);
+    assert_eq!(
+        changes[0].body_hash,
+        hex::encode(ring::digest::digest(
+            &ring::digest::SHA256,
+            b"<h1>Hacked</h1>"
+        ))
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn origins_served_from_unexpected_networks_are_alerted_on(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    server
+        .mock("GET", mockito::Matcher::Any)
+        .with_status(200)
+        .create_async()
+        .await;
+
+    let poller = create_poller(&pool);
+
+    let expected_uid = Uuid::new_v4();
+    let expected_uri = format!("{}/expected", server.url());
+    crate::persistence::insert_origin(&pool, expected_uid, &expected_uri).await?;
+    crate::persistence::set_origin_expected_networks(&pool, expected_uid, Some("127.0.0.0/8"))
+        .await?;
+
+    poller.check_origin_now(expected_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, expected_uid, 1).await?;
+
+    assert_eq!(checks[0].remote_address.as_deref(), Some("127.0.0.1"));
+    assert!(poller.notifier.sent_messages.read().await.is_empty());
+
+    let moved_uid = Uuid::new_v4();
+    let moved_uri = format!("{}/moved", server.url());
+    crate::persistence::insert_origin(&pool, moved_uid, &moved_uri).await?;
+    crate::persistence::set_origin_expected_networks(&pool, moved_uid, Some("203.0.113.0/24"))
+        .await?;
+
+    // The check itself still succeeds, but the move is only alerted on once
+    poller.check_origin_now(moved_uid).await;
+    poller.check_origin_now(moved_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, moved_uid, 2).await?;
+
+    assert!(checks.iter().all(|check| check.status == Some(200)));
+
+    let map = poller.notifier.sent_messages.read().await;
+    let messages = &map[SNS_TOPIC];
+
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].subject, "Unexpected address detected");
+    assert!(messages[0].message.contains("127.0.0.1"));
+
+    let health = poller.handle().notifier_health();
+    assert!(health.last_sent_at.is_some());
+    assert_eq!(health.consecutive_failures, 0);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn response_body_sizes_are_recorded(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    server
+        .mock("GET", "/declared")
+        .with_status(200)
+        .with_body("x".repeat(2048))
+        .create_async()
+        .await;
+
+    // Without a Content-Length the body has to be downloaded to know its size
+    server
+        .mock("GET", "/streamed")
+        .with_status(200)
+        .with_chunked_body(|writer| writer.write_all(b"hello world"))
+        .create_async()
+        .await;
+
+    let poller = create_poller(&pool);
+
+    let declared_uid = Uuid::new_v4();
+    let declared_uri = format!("{}/declared", server.url());
+    crate::persistence::insert_origin(&pool, declared_uid, &declared_uri).await?;
+
+    let streamed_uid = Uuid::new_v4();
+    let streamed_uri = format!("{}/streamed", server.url());
+    crate::persistence::insert_origin(&pool, streamed_uid, &streamed_uri).await?;
+
+    poller.query_all_origins().await?;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, declared_uid, 1).await?;
+    assert_eq!(checks[0].body_bytes, Some(2048));
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, streamed_uid, 1).await?;
+    assert_eq!(checks[0].body_bytes, Some(11));
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn http_versions_are_recorded_and_can_be_forced(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    server
+        .mock("GET", "/")
+        .with_status(200)
+        .expect(2)
+        .create_async()
+        .await;
+
+    let poller = create_poller(&pool);
+
+    let negotiated_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, negotiated_uid, &server.url()).await?;
+
+    // Plaintext connections only use HTTP/2 when the client insists on it
+    let forced_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, forced_uid, &server.url()).await?;
+    crate::persistence::set_origin_http_version(&pool, forced_uid, HttpVersion::Http2).await?;
+
+    poller.query_all_origins().await?;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, negotiated_uid, 1).await?;
+    assert_eq!(checks[0].http_version.as_deref(), Some("HTTP/1.1"));
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, forced_uid, 1).await?;
+    assert_eq!(checks[0].http_version.as_deref(), Some("HTTP/2"));
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn failed_checks_are_retried_before_being_recorded(pool: PgPool) -> Result<()> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let uri = format!("http://{}", listener.local_addr()?);
+
+    // Drop the first connection without responding, then serve the retry
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        drop(stream);
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0; 1024];
+
+        while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+            let read = stream.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..read]);
+        }
+
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+            .await
+            .unwrap();
+    });
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+    crate::persistence::set_origin_retries(&pool, origin_uid, 1).await?;
+
+    poller.query_all_origins().await?;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 10).await?;
+
+    assert_eq!(checks.len(), 1);
+    assert_eq!(checks[0].status, Some(200));
+    assert_eq!(checks[0].attempts, Some(2));
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn selected_response_headers_are_captured(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    server
+        .mock("GET", "/healthy")
+        .with_status(200)
+        .with_header("x-request-id", "abc123")
+        .with_header("x-powered-by", "php")
+        .create_async()
+        .await;
+
+    server
+        .mock("GET", "/degraded")
+        .with_status(503)
+        .with_header("x-request-id", "def456")
+        .create_async()
+        .await;
+
+    let poller = create_poller(&pool);
+
+    let healthy_uid = Uuid::new_v4();
+    let healthy_uri = format!("{}/healthy", server.url());
+    crate::persistence::insert_origin(&pool, healthy_uid, &healthy_uri).await?;
+    crate::persistence::set_origin_captured_headers(&pool, healthy_uid, Some("X-Request-Id"))
+        .await?;
+
+    // Headers are kept when the response fails the check, as that is when they are needed
+    let degraded_uid = Uuid::new_v4();
+    let degraded_uri = format!("{}/degraded", server.url());
+    crate::persistence::insert_origin(&pool, degraded_uid, &degraded_uri).await?;
+    crate::persistence::set_origin_expected_status(&pool, degraded_uid, Some("200")).await?;
+    crate::persistence::set_origin_captured_headers(&pool, degraded_uid, Some("X-Request-Id"))
+        .await?;
+
+    poller.query_all_origins().await?;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, healthy_uid, 1).await?;
+    assert_eq!(
+        checks[0].captured_headers.as_deref(),
+        Some("x-request-id: abc123")
+    );
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, degraded_uid, 1).await?;
+    assert_eq!(
+        checks[0].failure_reason.as_deref(),
+        Some(FailureReason::UnexpectedStatus.as_str())
+    );
+    assert_eq!(
+        checks[0].captured_headers.as_deref(),
+        Some("x-request-id: def456")
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn heartbeats_fail_once_their_grace_period_passes_without_a_ping(pool: PgPool) -> Result<()> {
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, "heartbeat://nightly-backup").await?;
+
+    let created_at = chrono::Utc::now() - chrono::Duration::hours(2);
+    crate::persistence::upsert_heartbeat(&pool, origin_uid, "some-token", 3600, created_at).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(
+        checks[0].failure_reason.as_deref(),
+        Some(FailureReason::HeartbeatMissed.as_str())
+    );
+
+    let pinged =
+        crate::persistence::record_heartbeat_ping(&pool, "some-token", chrono::Utc::now()).await?;
+
+    assert_eq!(pinged, Some(origin_uid));
+
+    poller.check_origin_now(origin_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(checks[0].failure_reason, None);
+
+    // Unknown tokens do not ping anything
+    let pinged =
+        crate::persistence::record_heartbeat_ping(&pool, "other-token", chrono::Utc::now()).await?;
+
+    assert_eq!(pinged, None);
+
+    Ok(())
+}
+
+fn journey_step(method: HttpMethod, url: String) -> JourneyStep {
+    JourneyStep {
+        journey_step_uid: Uuid::new_v4(),
+        method,
+        url,
+        request_headers: None,
+        request_body: None,
+        request_content_type: None,
+        expected_status: None,
+        extraction: None,
+    }
+}
+
+#[sqlx::test]
+async fn journeys_pass_cookies_and_extracted_values_between_steps(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let poller = create_poller(&pool);
+
+    let login = server
+        .mock("POST", "/login")
+        .match_body("username=uptime")
+        .with_status(200)
+        .with_header("set-cookie", "session=abc; Path=/; HttpOnly")
+        .with_body(r#"{"token": "t1"}"#)
+        .create_async()
+        .await;
+
+    let dashboard = server
+        .mock("GET", "/dashboard")
+        .match_header("cookie", "session=abc")
+        .match_header("authorization", "Bearer t1")
+        .with_status(200)
+        .create_async()
+        .await;
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, "journey://dashboard").await?;
+
+    let mut step = journey_step(HttpMethod::Post, format!("{}/login", server.url()));
+    step.request_body = Some(String::from("username=uptime"));
+    step.request_content_type = Some(String::from("application/x-www-form-urlencoded"));
+    step.extraction = Some(String::from("token = $.token"));
+    crate::persistence::insert_journey_step(&pool, origin_uid, &step).await?;
+
+    let mut step = journey_step(HttpMethod::Get, format!("{}/dashboard", server.url()));
+    step.request_headers = Some(String::from("Authorization: Bearer {{ token }}"));
+    crate::persistence::insert_journey_step(&pool, origin_uid, &step).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    login.assert_async().await;
+    dashboard.assert_async().await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(checks[0].status, Some(200));
+
+    // A later step failing fails the whole journey, naming the step
+    let mut step = journey_step(HttpMethod::Get, format!("{}/missing", server.url()));
+    step.expected_status = Some(String::from("200"));
+    crate::persistence::insert_journey_step(&pool, origin_uid, &step).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(
+        checks[0].failure_reason.as_deref(),
+        Some(FailureReason::UnexpectedStatus.as_str())
+    );
+    assert_eq!(
+        checks[0].failure_detail.as_deref(),
+        Some(format!("step 3 (GET {}/missing): responded 501", server.url()).as_str())
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn unreachable_origins_are_diagnosed(pool: PgPool) -> Result<()> {
+    let poller = create_poller(&pool).with_failure_diagnostics(FailureDiagnostics::new(None));
+
+    // Binding and dropping a listener leaves a port that refuses connections
+    let closed = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await?
+        .local_addr()?;
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &format!("tcp://{closed}")).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    let diagnostics =
+        crate::persistence::fetch_latest_failure_diagnostics(&pool, origin_uid).await?;
+
+    let diagnostics = diagnostics.unwrap();
+
+    assert!(diagnostics.starts_with("DNS: 127.0.0.1 resolved in"));
+    assert!(diagnostics.contains(&format!("TCP {closed}: failed after")));
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn failing_alerts_are_reported_to_the_fallback_channel(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let topic = format!("{}/topic", server.url());
+
+    let http_client = reqwest::Client::new();
+    let notifier = SlackWebhook::new(http_client.clone());
+    let configuration = PollerConfiguration::new(AlertThreshold::default(), &topic);
+
+    let poller = Poller::new(pool.clone(), http_client, notifier, configuration)
+        .with_fallback_channel(FallbackChannel::new(format!("{}/fallback", server.url())));
+
+    let topic_mock = server
+        .mock("POST", "/topic")
+        .with_status(403)
+        .expect(4)
+        .create_async()
+        .await;
+
+    // Only the alert that tips the notifier into failing is reported, not every one after it
+    let fallback_mock = server
+        .mock("POST", "/fallback")
+        .match_body(mockito::Matcher::Regex(String::from(
+            "Alerts are failing to send",
+        )))
+        .with_status(200)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, "https://example.com").await?;
+
+    let alert = Alert::Outage {
+        uri: "https://example.com",
+        diagnostics: None,
+    };
+
+    for _ in 0..4 {
+        assert!(poller.send_alert(origin_uid, &alert).await.is_err());
+    }
+
+    topic_mock.assert_async().await;
+    fallback_mock.assert_async().await;
+
+    let health = poller.handle().notifier_health();
+    assert_eq!(health.consecutive_failures, 4);
+    assert!(health.is_failing());
+
+    // None of them went out, so none of them hold back the next attempt
+    let notifications = crate::persistence::fetch_unacknowledged_notifications(&pool, 10).await?;
+    assert!(notifications.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn alert_windows_start_at_the_beginning_of_their_period() {
+    // A Thursday, so the week started on the 12th
+    let now = Utc.with_ymd_and_hms(2026, 10, 15, 12, 34, 56).unwrap();
+    let rolling = chrono::Duration::minutes(5);
+
+    assert_eq!(AlertWindow::Rolling.start(now, rolling), now - rolling);
+    assert_eq!(
+        AlertWindow::Day.start(now, rolling),
+        Utc.with_ymd_and_hms(2026, 10, 15, 0, 0, 0).unwrap()
+    );
+    assert_eq!(
+        AlertWindow::Week.start(now, rolling),
+        Utc.with_ymd_and_hms(2026, 10, 12, 0, 0, 0).unwrap()
+    );
+    assert_eq!(
+        AlertWindow::Month.start(now, rolling),
+        Utc.with_ymd_and_hms(2026, 10, 1, 0, 0, 0).unwrap()
+    );
+}
+
+#[sqlx::test]
+async fn calendar_alert_windows_count_failures_since_the_period_started(
+    pool: PgPool,
+) -> Result<()> {
+    let mut poller = create_poller(&pool);
+    poller.clock = Arc::new(ManualClock::new(
+        Utc.with_ymd_and_hms(2026, 10, 15, 12, 0, 0).unwrap(),
+    ));
+
+    let uri = "https://example.com";
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+
+    // Failures from the start of the month are long outside the default rolling window
+    let month_started_at = Utc.with_ymd_and_hms(2026, 10, 1, 0, 0, 0).unwrap();
+
+    let mut tx = pool.begin().await?;
+
+    for seconds in 1..=3 {
+        crate::persistence::insert_query_failure(
+            &mut tx,
+            origin_uid,
+            FailureReason::ConnectionFailure,
+            None,
+            month_started_at + chrono::Duration::seconds(seconds),
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    poller
+        .check_for_pending_notifications(origin_uid, uri, AlertWindow::Month)
+        .await?;
+
+    let map = poller.notifier.sent_messages.read().await;
+    let messages = &map[SNS_TOPIC];
+
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].subject, "Outage detected");
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn http_checks_record_how_long_each_phase_took(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let uri = server.url();
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+
+    server
+        .mock("GET", "/")
+        .with_status(200)
+        .create_async()
+        .await;
+
+    poller.query_all_origins().await?;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    // The mock server is plain HTTP on an address, so there is nothing to resolve or handshake
+    assert_eq!(checks[0].dns_millis, None);
+    assert!(checks[0].connect_millis.is_some());
+    assert_eq!(checks[0].tls_millis, None);
+    assert!(checks[0].first_byte_millis.is_some());
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn failures_record_the_address_they_connected_to(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let uri = server.url();
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+    crate::persistence::set_origin_expected_status(&pool, origin_uid, Some("200")).await?;
+
+    server
+        .mock("GET", "/")
+        .with_status(503)
+        .create_async()
+        .await;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let tcp_uid = Uuid::new_v4();
+    let tcp_uri = format!("tcp://{}", listener.local_addr()?);
+    crate::persistence::insert_origin(&pool, tcp_uid, &tcp_uri).await?;
+
+    poller.query_all_origins().await?;
+
+    let failures = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(
+        failures[0].failure_reason.as_deref(),
+        Some(FailureReason::UnexpectedStatus.as_str())
+    );
+    assert_eq!(failures[0].remote_address.as_deref(), Some("127.0.0.1"));
+
+    let connections = crate::persistence::fetch_recent_checks(&pool, tcp_uid, 1).await?;
+
+    assert_eq!(connections[0].remote_address.as_deref(), Some("127.0.0.1"));
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn domains_close_to_expiring_are_alerted_on_once_a_day(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let now = Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap();
+
+    server
+        .mock("GET", "/domain/shop.example.com")
+        .with_status(404)
+        .create_async()
+        .await;
+
+    let registry = server
+        .mock("GET", "/domain/example.com")
+        .with_header("content-type", "application/rdap+json")
+        .with_body(
+            r#"{"events": [{"eventAction": "expiration", "eventDate": "2026-10-26T12:00:00Z"}]}"#,
+        )
+        .expect(2)
+        .create_async()
+        .await;
+
+    let clock = ManualClock::new(now);
+
+    let mut poller = create_poller(&pool).with_domain_expiry(DomainExpiry::new(
+        reqwest::Client::new(),
+        server.url(),
+        chrono::Duration::days(30),
+    ));
+    poller.clock = Arc::new(clock.clone());
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, "https://shop.example.com").await?;
+
+    let origin = crate::persistence::fetch_origin(&pool, origin_uid)
+        .await?
+        .expect("origin was just inserted");
+
+    poller.check_domain_expiry(&origin, &[]).await?;
+
+    let registration = crate::persistence::fetch_domain_registration(&pool, origin_uid)
+        .await?
+        .expect("registration was not recorded");
+
+    assert_eq!(registration.domain, "example.com");
+    assert_eq!(
+        registration.expires_at,
+        Some(Utc.with_ymd_and_hms(2026, 10, 26, 12, 0, 0).unwrap())
+    );
+
+    // Looked up again the same day, the registry is not asked again
+    clock.advance(chrono::Duration::hours(1));
+    poller.check_domain_expiry(&origin, &[]).await?;
+
+    clock.advance(chrono::Duration::days(1));
+    poller.check_domain_expiry(&origin, &[]).await?;
+
+    registry.assert_async().await;
+
+    let map = poller.notifier.sent_messages.read().await;
+    let messages = &map[SNS_TOPIC];
+
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0].subject, "Domain registration expiring");
+    assert_eq!(
+        messages[0].message,
+        "The domain example.com of https://shop.example.com expires on 2026-10-26, in 10 days"
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn expiring_domains_are_not_alerted_on_during_silenced_maintenance(
+    pool: PgPool,
+) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let now = Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap();
+
+    server
+        .mock("GET", "/domain/example.com")
+        .with_header("content-type", "application/rdap+json")
+        .with_body(
+            r#"{"events": [{"eventAction": "expiration", "eventDate": "2026-10-26T12:00:00Z"}]}"#,
+        )
+        .create_async()
+        .await;
+
+    let mut poller = create_poller(&pool).with_domain_expiry(DomainExpiry::new(
+        reqwest::Client::new(),
+        server.url(),
+        chrono::Duration::days(30),
+    ));
+    poller.clock = Arc::new(ManualClock::new(now));
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, "https://example.com").await?;
+    crate::persistence::set_origin_tags(&pool, origin_uid, &[String::from("deploying")]).await?;
+
+    let origin = crate::persistence::fetch_origin(&pool, origin_uid)
+        .await?
+        .expect("origin was just inserted");
+
+    let window = MaintenanceWindow {
+        starts_at: now - chrono::Duration::hours(1),
+        ends_at: now + chrono::Duration::hours(1),
+        ..maintenance_window(MaintenanceMode::Silence)
+    };
+
+    poller.check_domain_expiry(&origin, &[window]).await?;
+
+    // The lookup is still recorded, so it is not repeated once the window is over
+    let registration = crate::persistence::fetch_domain_registration(&pool, origin_uid).await?;
+    assert!(registration.is_some());
+
+    let map = poller.notifier.sent_messages.read().await;
+    assert!(map.get(SNS_TOPIC).is_none());
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn inspections_show_the_full_response_without_recording_a_check(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    server
+        .mock("GET", "/")
+        .with_status(503)
+        .with_header("x-served-by", "edge-1")
+        .with_body("x".repeat(100 * 1024))
+        .create_async()
+        .await;
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &server.url()).await?;
+
+    let origin = crate::persistence::fetch_origin(&pool, origin_uid)
+        .await?
+        .unwrap();
+
+    let inspection = create_poller(&pool)
+        .inspector()
+        .inspect(&origin)
+        .await
+        .unwrap();
+
+    assert_eq!(inspection.status, 503);
+    assert_eq!(inspection.version, "HTTP/1.1");
+    assert!(inspection
+        .headers
+        .iter()
+        .any(|header| header.name == "x-served-by" && header.value == "edge-1"));
+
+    // Only the start of large bodies is kept
+    assert_eq!(inspection.body.len(), 64 * 1024);
+    assert!(inspection.body_truncated);
+
+    // Plain HTTP to an address has nothing to resolve or negotiate
+    assert_eq!(inspection.connection_error, None);
+    assert_eq!(inspection.timings.dns_millis, None);
+    assert!(inspection.timings.connect_millis.is_some());
+    assert!(inspection.tls.is_none());
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+    assert!(checks.is_empty());
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn can_check_dns_records(pool: PgPool) -> Result<()> {
+    let server = tokio::net::UdpSocket::bind("127.0.0.1:0").await?;
+    let address = server.local_addr()?;
+
+    // Answer every query with the same address
+    tokio::spawn(async move {
+        let mut buffer = [0; 512];
+
+        while let Ok((length, peer)) = server.recv_from(&mut buffer).await {
+            let response = super::dns::tests::response(
+                &buffer[..length],
+                &[(super::dns::RecordType::A, vec![192, 0, 2, 1])],
+            );
+
+            let _ = server.send_to(&response, peer).await;
+        }
+    });
+
+    let matching = format!("dns://example.com?expect=192.0.2.1&server={address}");
+    let mismatched = format!("dns://example.com?expect=192.0.2.2&server={address}");
+
+    let poller = create_poller(&pool);
+
+    crate::persistence::insert_origin(&pool, Uuid::new_v4(), &matching).await?;
+    let mismatched_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, mismatched_uid, &mismatched).await?;
+
+    poller.query_all_origins().await?;
+
+    let successes =
+        crate::persistence::fetch_origins_with_most_recent_success_metrics(&pool).await?;
+
+    assert!(successes
+        .iter()
+        .any(|r| r.uri == matching && r.status.is_none()));
+
+    let failure_reason = fetch_latest_query_failure(&pool, &mismatched).await?;
+
+    assert_eq!(
+        failure_reason.as_deref(),
+        Some(FailureReason::DnsMismatch.as_str())
+    );
+
+    // The resolver and its answers are kept to explain the failure
+    let checks = crate::persistence::fetch_recent_checks(&pool, mismatched_uid, 1).await?;
+
+    let expected = format!(
+        "resolver {address} answered example.com A with 192.0.2.1 (ttl 60s) but expected 192.0.2.2"
+    );
+
+    assert_eq!(checks[0].failure_detail.as_deref(), Some(expected.as_str()));
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn check_results_are_streamed_to_the_firehose(pool: PgPool) -> Result<()> {
+    // intentionally invalid TLD
+    let uri = "https://mozilla.rust";
+
+    let mut server = mockito::Server::new_async().await;
+    let sink = Sink::parse(reqwest::Client::new(), &format!("{}/checks", server.url()))?;
+
+    let poller = create_poller(&pool).with_firehose(Firehose::spawn(sink));
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+
+    let mock = server
+        .mock("POST", "/checks")
+        .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+            "origin_uid": origin_uid,
+            "uri": uri,
+            "success": false,
+            "failure_reason": "BadRequest",
+        })))
+        .with_status(204)
+        .create_async()
+        .await;
+
+    poller.query_all_origins().await?;
+
+    // Results are delivered in the background, so give it a moment to arrive
+    for _ in 0..50 {
+        if mock.matched_async().await {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    mock.assert_async().await;
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn incidents_are_published_to_the_event_bus(pool: PgPool) -> Result<()> {
+    use tokio::io::AsyncReadExt;
+
+    // intentionally invalid TLD
+    let uri = "https://mozilla.rust";
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let (publisher, prefix) =
+        Publisher::parse(&format!("nats://{}/uptime", listener.local_addr()?))?;
+
+    let poller = create_poller(&pool).with_event_bus(EventBus::spawn(publisher, prefix));
+
+    crate::persistence::insert_origin(&pool, Uuid::new_v4(), uri).await?;
+
+    poller.query_all_origins().await?;
+
+    let (mut stream, _) = listener.accept().await?;
+    let mut received = String::new();
+
+    while !received.contains("PUB uptime.check.completed") {
+        let mut buffer = [0; 4096];
+        let length = stream.read(&mut buffer).await?;
+
+        assert!(length > 0, "the event bus disconnected early");
+        received.push_str(&String::from_utf8_lossy(&buffer[..length]));
+    }
+
+    // The first check failing opens an incident, but is not a change of state
+    assert!(received.contains("PUB uptime.incident.opened"));
+    assert!(!received.contains("PUB uptime.state.changed"));
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn new_origins_are_checked_immediately(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let uri = server.url();
+
+    let mock = server
+        .mock("GET", "/")
+        .with_status(200)
+        .create_async()
+        .await;
+
+    let poller = create_poller(&pool);
+
+    let added = async {
+        // Give the poller time to start listening and finish its first round
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        crate::persistence::insert_origin(&pool, Uuid::new_v4(), &uri).await?;
+
+        for _ in 0..50 {
+            if fetch_latest_query_status(&pool, &uri).await?.is_some() {
+                return Ok(());
+            }
+
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        Err(color_eyre::eyre::eyre!("the new origin was not checked"))
+    };
+
+    // The next round is a minute away, so only the notification can cause the check
+    tokio::select! {
+        () = poller.run() => unreachable!("the poller never stops"),
+        result = added => result?,
+    }
+
+    mock.assert_async().await;
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn commands_control_the_poller(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let uri = server.url();
+
+    let mock = server
+        .mock("GET", "/")
+        .with_status(200)
+        .create_async()
+        .await;
+
+    let poller = create_poller(&pool);
+    let handle = poller.handle();
+
+    assert!(handle.send(Command::Reload));
+    assert_eq!(poller.commands.lock().await.try_recv(), Ok(Command::Reload));
+
+    assert!(!poller.handle_command(Command::Pause).await);
+    assert!(handle.is_paused());
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+
+    // Origins can still be checked on request while the poller is paused
+    assert!(!poller.handle_command(Command::CheckNow(origin_uid)).await);
+
+    mock.assert_async().await;
+    assert_eq!(fetch_latest_query_status(&pool, &uri).await?, Some(200));
+
+    // Resuming starts a new round straight away
+    assert!(poller.handle_command(Command::Resume).await);
+    assert!(!handle.is_paused());
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn checks_asked_for_on_demand_return_their_result(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let mock = server
+        .mock("GET", "/")
+        .with_status(200)
+        .create_async()
+        .await;
+
+    let poller = create_poller(&pool);
+    let handle = poller.handle();
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &server.url()).await?;
+
+    let (result, _) = tokio::join!(
+        handle.check_now(origin_uid, Duration::from_secs(10)),
+        async {
+            let command = poller.commands.lock().await.recv().await.unwrap();
+            poller.handle_command(command).await
+        }
+    );
+
+    mock.assert_async().await;
+
+    let result = result.unwrap();
+
+    assert_eq!(result.origin_uid, origin_uid);
+    assert!(result.success);
+    assert_eq!(result.status, Some(200));
+
+    // Nothing answers while the poller is not taking commands
+    assert_eq!(
+        handle
+            .check_now(origin_uid, Duration::from_millis(50))
+            .await
+            .unwrap_err(),
+        CheckNowError::TimedOut
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn can_route_alerts_to_clients(pool: PgPool) -> Result<()> {
+    // intentionally invalid TLD
+    let uri = "https://mozilla.rust";
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+
+    // Make 3 queries, all of which fail
+    for _ in 0..3 {
+        poller.query_all_origins().await?;
+    }
+
+    let map = poller.notifier.sent_messages.read().await;
+    let messages = &map[SNS_TOPIC];
+
+    let expected_message = format!("The failure rate of {uri} exceeds the SLA");
+
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].subject, "Outage detected");
+    assert_eq!(messages[0].message, expected_message);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn alerts_are_not_routed_for_origins_with_alerts_disabled(pool: PgPool) -> Result<()> {
+    // intentionally invalid TLD
+    let uri = "https://mozilla.rust";
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+
+    let mut tx = pool.begin().await?;
+    crate::persistence::set_origin_alerts_enabled(&mut tx, origin_uid, false).await?;
+    tx.commit().await?;
+
+    // Make 3 queries, all of which fail
+    for _ in 0..3 {
+        poller.query_all_origins().await?;
+    }
+
+    let map = poller.notifier.sent_messages.read().await;
+
+    assert!(map.get(SNS_TOPIC).is_none());
+
+    Ok(())
+}
+
+/// A window covering the hour either side of now.
+fn maintenance_window(mode: MaintenanceMode) -> MaintenanceWindow {
+    let now = Utc::now();
+
+    MaintenanceWindow {
+        maintenance_window_uid: Uuid::new_v4(),
+        origin_uid: None,
+        tag: Some(String::from("deploying")),
+        starts_at: now - chrono::Duration::hours(1),
+        ends_at: now + chrono::Duration::hours(1),
+        recurrence: Recurrence::Once,
+        mode,
+        description: Some(String::from("Planned deploy")),
+    }
+}
+
+#[sqlx::test]
+async fn maintenance_windows_can_skip_checks(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let uri = server.url();
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+    crate::persistence::set_origin_tags(&pool, origin_uid, &[String::from("deploying")]).await?;
+
+    let window = maintenance_window(MaintenanceMode::Skip);
+    crate::persistence::insert_maintenance_window(&pool, &window).await?;
+
+    let mock = server
+        .mock("GET", "/")
+        .with_status(200)
+        .expect(0)
+        .create_async()
+        .await;
+
+    poller.query_all_origins().await?;
+    poller.check_origin_now(origin_uid).await;
+
+    mock.assert_async().await;
+
+    // Checks resume once the window is removed
+    crate::persistence::delete_maintenance_window(&pool, window.maintenance_window_uid).await?;
+
+    let mock = server
+        .mock("GET", "/")
+        .with_status(200)
+        .create_async()
+        .await;
+
+    poller.query_all_origins().await?;
+
+    mock.assert_async().await;
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn failures_during_silenced_maintenance_do_not_alert(pool: PgPool) -> Result<()> {
+    // intentionally invalid TLD
+    let uri = "https://mozilla.rust";
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+    crate::persistence::set_origin_tags(&pool, origin_uid, &[String::from("deploying")]).await?;
+
+    let window = maintenance_window(MaintenanceMode::Silence);
+    crate::persistence::insert_maintenance_window(&pool, &window).await?;
+
+    // Make 3 queries, all of which fail and are still recorded
+    for _ in 0..3 {
+        poller.query_all_origins().await?;
+    }
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 3).await?;
+    assert_eq!(checks.len(), 3);
+
+    // Failures from the window do not count towards the threshold once it is over
+    crate::persistence::delete_maintenance_window(&pool, window.maintenance_window_uid).await?;
+    poller.query_all_origins().await?;
+
+    let map = poller.notifier.sent_messages.read().await;
+
+    assert!(map.get(SNS_TOPIC).is_none());
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn alerts_are_routed_to_watchers(pool: PgPool) -> Result<()> {
+    // intentionally invalid TLD
+    let uri = "https://mozilla.rust";
+    let personal_topic = "personal-sns-topic";
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+
+    let destination = UserDestination {
+        kind: DestinationKind::Sns,
+        target: personal_topic.to_owned(),
+        locale: None,
+    };
+
+    crate::persistence::set_favorite_origin(&pool, "alice", origin_uid, true).await?;
+    crate::persistence::upsert_user_destination(&pool, "alice", &destination).await?;
+
+    // Users without a destination only see the origin on their dashboard
+    crate::persistence::set_favorite_origin(&pool, "bob", origin_uid, true).await?;
+
+    // Make 3 queries, all of which fail
+    for _ in 0..3 {
+        poller.query_all_origins().await?;
+    }
+
+    let map = poller.notifier.sent_messages.read().await;
+
+    assert_eq!(map[SNS_TOPIC].len(), 1);
+    assert_eq!(map[personal_topic], map[SNS_TOPIC]);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn alerts_are_written_in_the_locale_of_each_destination(pool: PgPool) -> Result<()> {
+    // intentionally invalid TLD
+    let uri = "https://mozilla.rust";
+    let personal_topic = "personal-sns-topic";
+
+    let configuration = PollerConfiguration::new(AlertThreshold::default(), SNS_TOPIC)
+        .with_locale(Locale::Fr)
+        .with_jitter(Duration::ZERO);
+    let poller = Poller::new(
+        pool.clone(),
+        reqwest::Client::new(),
+        MockSnsClient::default(),
+        configuration,
+    );
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+
+    let destination = UserDestination {
+        kind: DestinationKind::Sns,
+        target: personal_topic.to_owned(),
+        locale: Some(Locale::De),
+    };
+
+    crate::persistence::set_favorite_origin(&pool, "alice", origin_uid, true).await?;
+    crate::persistence::upsert_user_destination(&pool, "alice", &destination).await?;
+
+    // Make 3 queries, all of which fail
+    for _ in 0..3 {
+        poller.query_all_origins().await?;
+    }
+
+    let map = poller.notifier.sent_messages.read().await;
+
+    assert_eq!(
+        map[SNS_TOPIC],
+        vec![Message::new(
+            "Panne détectée",
+            "Le taux d'échec de https://mozilla.rust dépasse le SLA"
+        )]
+    );
+    assert_eq!(
+        map[personal_topic],
+        vec![Message::new(
+            "Ausfall erkannt",
+            "Die Fehlerrate von https://mozilla.rust überschreitet das SLA"
+        )]
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn alerts_can_be_routed_to_slack(pool: PgPool) -> Result<()> {
+    // intentionally invalid TLD
+    let uri = "https://mozilla.rust";
+
+    let mut server = mockito::Server::new_async().await;
+    let webhook = server
+        .mock("POST", "/webhook")
+        .match_header("content-type", "application/json")
+        .with_status(200)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+
+    let destination = UserDestination {
+        kind: DestinationKind::Slack,
+        target: format!("{}/webhook", server.url()),
+        locale: None,
+    };
+
+    crate::persistence::set_favorite_origin(&pool, "alice", origin_uid, true).await?;
+    crate::persistence::upsert_user_destination(&pool, "alice", &destination).await?;
+
+    // Make 3 queries, all of which fail
+    for _ in 0..3 {
+        poller.query_all_origins().await?;
+    }
+
+    webhook.assert_async().await;
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn expired_push_subscriptions_are_removed(pool: PgPool) -> Result<()> {
+    // intentionally invalid TLD
+    let uri = "https://mozilla.rust";
+
+    let mut server = mockito::Server::new_async().await;
+    let push_service = server
+        .mock("POST", "/push/abc123")
+        .match_header("content-encoding", "aes128gcm")
+        .with_status(410)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let rng = SystemRandom::new();
+    let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+    let key_pair =
+        EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+    let keys = VapidKeys::new(key_pair, String::from("mailto:ops@example.com"));
+
+    let poller = create_poller(&pool).with_web_push(WebPush::new(reqwest::Client::new(), keys));
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+
+    let browser_key = agreement::EphemeralPrivateKey::generate(&agreement::ECDH_P256, &rng)
+        .unwrap()
+        .compute_public_key()
+        .unwrap();
+
+    let subscription = PushSubscription {
+        endpoint: format!("{}/push/abc123", server.url()),
+        p256dh: URL_SAFE_NO_PAD.encode(browser_key.as_ref()),
+        auth: URL_SAFE_NO_PAD.encode([7; 16]),
+    };
+
+    crate::persistence::upsert_push_subscription(&pool, "alice", &subscription).await?;
+
+    // Make 3 queries, all of which fail
+    for _ in 0..3 {
+        poller.query_all_origins().await?;
+    }
+
+    push_service.assert_async().await;
+
+    assert!(crate::persistence::fetch_push_subscriptions(&pool)
+        .await?
+        .is_empty());
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn alerts_are_not_constantly_routed(pool: PgPool) -> Result<()> {
+    // intentionally invalid TLD
+    let uri = "https://mozilla.rust";
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+
+    // Make 3 queries, all of which fail to trigger an alert
+    for _ in 0..3 {
+        poller.query_all_origins().await?;
+    }
+
+    // Trigger another query which fails
+    poller.query_all_origins().await?;
+
+    // Check we only sent a single message
+    let map = poller.notifier.sent_messages.read().await;
+
+    assert_eq!(map[SNS_TOPIC].len(), 1);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn alerts_can_cooldown_after_firing(pool: PgPool) -> Result<()> {
+    // intentionally invalid TLD
+    let uri = "https://mozilla.rust";
+
+    let clock = ManualClock::new(Utc::now());
+
+    let mut poller = create_poller(&pool);
+    poller.configuration.alert_threshold.cooldown = chrono::Duration::minutes(1);
+    poller.clock = Arc::new(clock.clone());
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+
+    // Make 3 queries, all of which fail to trigger an alert
+    for _ in 0..3 {
+        poller.query_all_origins().await?;
+    }
+
+    // Part of the way through the cooldown, failures do not alert again
+    clock.advance(chrono::Duration::seconds(30));
+    poller.query_all_origins().await?;
+
+    assert_eq!(
+        poller.notifier.sent_messages.read().await[SNS_TOPIC].len(),
+        1
+    );
+
+    // Once the cooldown has passed, they do
+    clock.advance(chrono::Duration::seconds(30));
+    poller.query_all_origins().await?;
+
+    let map = poller.notifier.sent_messages.read().await;
+
+    assert_eq!(map[SNS_TOPIC].len(), 2);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn cooldowns_only_hold_back_alerts_of_the_same_kind(pool: PgPool) -> Result<()> {
+    // intentionally invalid TLD
+    let uri = "https://mozilla.rust";
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+
+    // A latency regression was alerted on moments ago
+    let mut tx = pool.begin().await?;
+    crate::persistence::insert_notification(
+        &mut tx,
+        origin_uid,
+        AlertKind::LatencyRegression,
+        SNS_TOPIC,
+        "Latency regression detected",
+        "message",
+        sqlx::types::chrono::Utc::now(),
+    )
+    .await?;
+    tx.commit().await?;
+
+    // Make 3 queries, all of which fail to trigger an alert
+    for _ in 0..3 {
+        poller.query_all_origins().await?;
+    }
+
+    let map = poller.notifier.sent_messages.read().await;
+    let messages = &map[SNS_TOPIC];
+
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].subject, "Outage detected");
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn records_health_scores_for_queried_origins(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let uri = server.url();
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+
+    server
+        .mock("GET", "/")
+        .with_status(200)
+        .create_async()
+        .await;
+
+    poller.query_all_origins().await?;
+
+    let scores = crate::persistence::fetch_recent_health_scores(&pool, 1).await?;
+
+    assert_eq!(scores.len(), 1);
+    assert_eq!(scores[0].origin_uid, origin_uid);
+    assert_eq!(scores[0].score, 100);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn json_assertions_fail_unexpected_responses(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let uri = server.url();
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+    crate::persistence::set_origin_json_assertion(&pool, origin_uid, Some(r#"$.status == "ok""#))
+        .await?;
+
+    let mock = server
+        .mock("GET", "/")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"status":"degraded"}"#)
+        .create_async()
+        .await;
+
+    poller.query_all_origins().await?;
+
+    mock.assert_async().await;
+
+    let failure_reason = fetch_latest_query_failure(&pool, &uri).await?;
+
+    assert_eq!(
+        failure_reason.as_deref(),
+        Some(FailureReason::JsonAssertionFailed.as_str())
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn unexpected_statuses_are_recorded_as_failures(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let uri = server.url();
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+    crate::persistence::set_origin_expected_status(&pool, origin_uid, Some("200-299")).await?;
+
+    let mock = server
+        .mock("GET", "/")
+        .with_status(500)
+        .create_async()
+        .await;
+
+    poller.query_all_origins().await?;
+
+    mock.assert_async().await;
+
+    let failure_reason = fetch_latest_query_failure(&pool, &uri).await?;
+
+    assert_eq!(
+        failure_reason.as_deref(),
+        Some(FailureReason::UnexpectedStatus.as_str())
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn cache_validation_requires_conditional_requests_to_be_honoured(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let poller = create_poller(&pool);
+
+    let honoured = format!("{}/honoured", server.url());
+    let honoured_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, honoured_uid, &honoured).await?;
+    crate::persistence::set_origin_cache_validation(&pool, honoured_uid, true).await?;
+
+    server
+        .mock("GET", "/honoured")
+        .match_header("if-none-match", mockito::Matcher::Missing)
+        .with_status(200)
+        .with_header("cache-control", "max-age=60")
+        .with_header("etag", "\"v1\"")
+        .create_async()
+        .await;
+
+    let revalidated = server
+        .mock("GET", "/honoured")
+        .match_header("if-none-match", "\"v1\"")
+        .with_status(304)
+        .with_header("etag", "\"v1\"")
+        .create_async()
+        .await;
+
+    poller.check_origin_now(honoured_uid).await;
+    poller.check_origin_now(honoured_uid).await;
+
+    revalidated.assert_async().await;
+    assert_eq!(
+        fetch_latest_query_status(&pool, &honoured).await?,
+        Some(304)
+    );
+
+    // Ignoring the conditional headers means caches can never revalidate
+    let ignored = format!("{}/ignored", server.url());
+    let ignored_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, ignored_uid, &ignored).await?;
+    crate::persistence::set_origin_cache_validation(&pool, ignored_uid, true).await?;
+
+    server
+        .mock("GET", "/ignored")
+        .with_status(200)
+        .with_header("cache-control", "max-age=60")
+        .with_header("etag", "\"v1\"")
+        .expect(2)
+        .create_async()
+        .await;
+
+    poller.check_origin_now(ignored_uid).await;
+    poller.check_origin_now(ignored_uid).await;
+
+    let failure_reason = fetch_latest_query_failure(&pool, &ignored).await?;
+
+    assert_eq!(
+        failure_reason.as_deref(),
+        Some(FailureReason::CacheValidationFailed.as_str())
+    );
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, ignored_uid, 1).await?;
+
+    assert_eq!(
+        checks[0].failure_detail.as_deref(),
+        Some("responded 200 OK to If-None-Match \"v1\" despite sending the same ETag")
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn rounds_check_origins_concurrently(pool: PgPool) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let address = listener.local_addr()?;
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+
+                let response = "HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n";
+                let _ = tokio::io::AsyncWriteExt::write_all(&mut stream, response.as_bytes()).await;
+            });
+        }
+    });
+
+    let configuration = PollerConfiguration::new(AlertThreshold::default(), SNS_TOPIC)
+        .with_concurrency(std::num::NonZeroUsize::new(4).unwrap())
+        .with_jitter(Duration::ZERO);
+    let poller = Poller::new(
+        pool.clone(),
+        reqwest::Client::new(),
+        MockSnsClient::default(),
+        configuration,
+    );
+
+    let mut origin_uids = Vec::new();
+
+    for path in ["a", "b", "c", "d"] {
+        let origin_uid = Uuid::new_v4();
+        crate::persistence::insert_origin(&pool, origin_uid, &format!("http://{address}/{path}"))
+            .await?;
+        origin_uids.push(origin_uid);
+    }
+
+    let started = std::time::Instant::now();
+    poller.query_all_origins().await?;
+
+    // Checked one after another, the round would take at least two seconds
+    assert!(started.elapsed() < Duration::from_millis(1500));
+
+    for origin_uid in origin_uids {
+        let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+        assert_eq!(checks[0].status, Some(200));
+    }
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn rounds_only_check_origins_their_schedules_match(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let uri = server.url();
+
+    let poller = create_poller(&pool);
+
+    let due_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, due_uid, &format!("{uri}/due")).await?;
+    crate::persistence::set_origin_schedule(&pool, due_uid, Some("* * * * *")).await?;
+
+    // Half an hour away, so the schedule cannot match however long the test takes to get here
+    let minute = (chrono::Timelike::minute(&Utc::now()) + 30) % 60;
+    let waiting_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, waiting_uid, &format!("{uri}/waiting")).await?;
+    crate::persistence::set_origin_schedule(&pool, waiting_uid, Some(&format!("{minute} * * * *")))
+        .await?;
+
+    let due = server
+        .mock("GET", "/due")
+        .with_status(200)
+        .create_async()
+        .await;
+    let waiting = server
+        .mock("GET", "/waiting")
+        .with_status(200)
+        .expect(0)
+        .create_async()
+        .await;
+
+    poller.query_all_origins().await?;
+
+    due.assert_async().await;
+    waiting.assert_async().await;
+
+    let state = poller.scheduler.subscribe().borrow().clone();
+    let waiting = state
+        .origins
+        .iter()
+        .find(|scheduled| scheduled.origin_uid == waiting_uid)
+        .unwrap();
+
+    assert_eq!(waiting.next_check_at, None);
+    assert_eq!(state.backlog, 0);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn paused_origins_are_not_checked_until_resumed(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &server.url()).await?;
+    crate::persistence::set_origin_paused(&pool, origin_uid, true).await?;
+
+    // Only the round after the origin is resumed checks it
+    let mock = server
+        .mock("GET", "/")
+        .with_status(200)
+        .expect(1)
+        .create_async()
+        .await;
+
+    poller.query_all_origins().await?;
+
+    crate::persistence::set_origin_paused(&pool, origin_uid, false).await?;
+    poller.query_all_origins().await?;
+
+    mock.assert_async().await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 10).await?;
+    assert_eq!(checks.len(), 1);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn origins_are_only_checked_by_one_of_several_pollers(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let origin_uids = [Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()];
+
+    for (i, origin_uid) in origin_uids.iter().enumerate() {
+        let uri = format!("{}/{i}", server.url());
+        crate::persistence::insert_origin(&pool, *origin_uid, &uri).await?;
+    }
+
+    let mock = server
+        .mock("GET", mockito::Matcher::Any)
+        .with_status(200)
+        .expect(origin_uids.len() * 2)
+        .create_async()
+        .await;
+
+    // Replicas running their rounds at the same time share the origins between them
+    let first = create_poller(&pool);
+    let second = create_poller(&pool);
+
+    let (first_result, second_result) =
+        tokio::join!(first.query_all_origins(), second.query_all_origins());
+    first_result?;
+    second_result?;
+
+    // Rounds until the claims expire leave the origins to whichever claimed them
+    second.query_all_origins().await?;
+    first.query_all_origins().await?;
+
+    mock.assert_async().await;
+
+    for origin_uid in origin_uids {
+        let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 10).await?;
+        assert_eq!(checks.len(), 2);
+    }
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn only_one_poller_leads(pool: PgPool) -> Result<()> {
+    let first = create_poller(&pool);
+    let second = create_poller(&pool);
+
+    let mut first_leader = None;
+    let mut second_leader = None;
+
+    assert!(first.lead(&mut first_leader).await);
+    assert!(!second.lead(&mut second_leader).await);
+
+    // Leaders keep leading, and another takes over once they stop
+    assert!(first.lead(&mut first_leader).await);
+
+    first_leader.take().unwrap().close().await?;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert!(second.lead(&mut second_leader).await);
+
+    Ok(())
+}
+
+fn create_located_poller(
+    pool: &PgPool,
+    location: &str,
+    alert_threshold: AlertThreshold,
+) -> Poller<MockSnsClient> {
+    let configuration = PollerConfiguration::new(alert_threshold, SNS_TOPIC)
+        .with_jitter(Duration::ZERO)
+        .with_location(location);
+
+    Poller::new(
+        pool.clone(),
+        reqwest::Client::new(),
+        MockSnsClient::default(),
+        configuration,
+    )
+}
+
+#[sqlx::test]
+async fn pollers_in_each_location_check_every_origin(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let origin_uids = [Uuid::new_v4(), Uuid::new_v4()];
+
+    for (i, origin_uid) in origin_uids.iter().enumerate() {
+        let uri = format!("{}/{i}", server.url());
+        crate::persistence::insert_origin(&pool, *origin_uid, &uri).await?;
+    }
+
+    let mock = server
+        .mock("GET", mockito::Matcher::Any)
+        .with_status(200)
+        .expect(origin_uids.len() * 2)
+        .create_async()
+        .await;
+
+    let first = create_located_poller(&pool, "eu-west-1", AlertThreshold::default());
+    let second = create_located_poller(&pool, "us-east-1", AlertThreshold::default());
+
+    let (first_result, second_result) =
+        tokio::join!(first.query_all_origins(), second.query_all_origins());
+    first_result?;
+    second_result?;
+
+    mock.assert_async().await;
+
+    for origin_uid in origin_uids {
+        let history = crate::persistence::fetch_history_page(&pool, origin_uid, None, 10).await?;
+
+        let mut locations: Vec<_> = history
+            .into_iter()
+            .filter_map(|entry| entry.location)
+            .collect();
+        locations.sort();
+
+        assert_eq!(locations, ["eu-west-1", "us-east-1"]);
+    }
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn alerts_wait_for_failures_from_enough_locations(pool: PgPool) -> Result<()> {
+    // intentionally invalid TLD
+    let uri = "https://mozilla.rust";
+
+    let alert_threshold = AlertThreshold::default().with_location_limit(2);
+    let first = create_located_poller(&pool, "eu-west-1", alert_threshold);
+    let second = create_located_poller(&pool, "us-east-1", alert_threshold);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+
+    // Enough failures to alert, but all of them from the same location
+    for _ in 0..3 {
+        first.query_all_origins().await?;
+    }
+
+    assert!(first.notifier.sent_messages.read().await.is_empty());
+
+    second.query_all_origins().await?;
+
+    let map = second.notifier.sent_messages.read().await;
+    assert_eq!(map.get(SNS_TOPIC).map(Vec::len), Some(1));
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn pollers_in_several_locations_alert_once(pool: PgPool) -> Result<()> {
+    let uri = "https://mozilla.rust";
+
+    let alert_threshold = AlertThreshold::default().with_location_limit(2);
+    let first = create_located_poller(&pool, "eu-west-1", alert_threshold);
+    let second = create_located_poller(&pool, "us-east-1", alert_threshold);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+
+    let mut tx = pool.begin().await?;
+    let now = Utc::now();
+
+    for location in ["eu-west-1", "us-east-1"] {
+        for _ in 0..2 {
+            let query_failure_uid = crate::persistence::insert_query_failure(
+                &mut tx,
+                origin_uid,
+                FailureReason::ConnectionFailure,
+                None,
+                now,
+            )
+            .await?;
+
+            let details = QueryFailureDetails {
+                location: Some(location),
+                ..Default::default()
+            };
+            crate::persistence::set_query_failure_details(&mut tx, query_failure_uid, &details)
+                .await?;
+        }
+    }
+
+    tx.commit().await?;
+
+    // Both locations see the outage at the same time, but only one of them alerts on it
+    let (first_result, second_result) = tokio::join!(
+        first.check_for_pending_notifications(origin_uid, uri, AlertWindow::Rolling),
+        second.check_for_pending_notifications(origin_uid, uri, AlertWindow::Rolling),
+    );
+
+    first_result?;
+    second_result?;
+
+    let first_sent = first.notifier.sent_messages.read().await.len();
+    let second_sent = second.notifier.sent_messages.read().await.len();
+
+    assert_eq!(first_sent + second_sent, 1);
+
+    Ok(())
+}
+
+/// Takes the alerting lock of an origin while sending, which only works if whoever is sending has
+/// let go of it already.
+#[derive(Clone, Debug)]
+struct LockingSnsClient {
+    pool: PgPool,
+    origin_uid: Uuid,
+    inner: MockSnsClient,
+}
+
+impl Notifier for LockingSnsClient {
+    async fn notify(&self, topic: &str, subject: &str, message: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let lock = crate::persistence::lock_origin_alerts(&mut tx, self.origin_uid);
+
+        tokio::time::timeout(Duration::from_secs(1), lock).await??;
+        tx.commit().await?;
+
+        self.inner.notify(topic, subject, message).await
+    }
+}
+
+#[sqlx::test]
+async fn alerts_are_sent_after_letting_go_of_the_lock(pool: PgPool) -> Result<()> {
+    let uri = "https://mozilla.rust";
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+
+    let notifier = LockingSnsClient {
+        pool: pool.clone(),
+        origin_uid,
+        inner: MockSnsClient::default(),
+    };
+
+    let configuration = PollerConfiguration::new(AlertThreshold::default(), SNS_TOPIC);
+    let poller = Poller::new(
+        pool.clone(),
+        reqwest::Client::new(),
+        notifier,
+        configuration,
+    );
+
+    let mut tx = pool.begin().await?;
+
+    for _ in 0..3 {
+        crate::persistence::insert_query_failure(
+            &mut tx,
+            origin_uid,
+            FailureReason::ConnectionFailure,
+            None,
+            Utc::now(),
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    // Sending while holding the lock would leave the notifier waiting on it until it timed out
+    poller
+        .check_for_pending_notifications(origin_uid, uri, AlertWindow::Rolling)
+        .await?;
+
+    let sent_messages = poller.notifier.inner.sent_messages.read().await;
+    assert_eq!(sent_messages.get(SNS_TOPIC).map(Vec::len), Some(1));
+
+    let notifications = crate::persistence::fetch_unacknowledged_notifications(&pool, 10).await?;
+    assert_eq!(notifications.len(), 1);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn origins_can_have_their_own_timeouts(pool: PgPool) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let uri = format!("http://{}", listener.local_addr()?);
+
+    // Every response takes a moment, which only some origins are willing to wait for
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+
+                let response = "HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n";
+                let _ = tokio::io::AsyncWriteExt::write_all(&mut stream, response.as_bytes()).await;
+            });
+        }
+    });
+
+    let configuration = PollerConfiguration::new(AlertThreshold::default(), SNS_TOPIC)
+        .with_default_timeout(Duration::from_millis(100));
+    let poller = Poller::new(
+        pool.clone(),
+        reqwest::Client::new(),
+        MockSnsClient::default(),
+        configuration,
+    );
+
+    let impatient_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, impatient_uid, &uri).await?;
+
+    poller.check_origin_now(impatient_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, impatient_uid, 1).await?;
+
+    assert_eq!(
+        checks[0].failure_reason.as_deref(),
+        Some(FailureReason::RequestTimeout.as_str())
+    );
+
+    let patient_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, patient_uid, &uri).await?;
+    crate::persistence::set_origin_timeout(&pool, patient_uid, Some(2000)).await?;
+
+    poller.check_origin_now(patient_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, patient_uid, 1).await?;
+
+    assert_eq!(checks[0].status, Some(200));
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn redirects_follow_the_policy_of_each_origin(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    server
+        .mock("GET", "/old")
+        .with_status(301)
+        .with_header("location", "/new")
+        .create_async()
+        .await;
+
+    server
+        .mock("GET", "/new")
+        .with_status(200)
+        .create_async()
+        .await;
+
+    let poller = create_poller(&pool);
+
+    let followed = format!("{}/old", server.url());
+    let followed_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, followed_uid, &followed).await?;
+
+    poller.check_origin_now(followed_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, followed_uid, 1).await?;
+
+    assert_eq!(checks[0].status, Some(200));
+    assert_eq!(
+        checks[0].final_url.as_deref(),
+        Some(format!("{}/new", server.url()).as_str())
+    );
+
+    // Origins that should redirect are checked without following them
+    let unfollowed_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, unfollowed_uid, &followed).await?;
+    crate::persistence::set_origin_redirect_policy(&pool, unfollowed_uid, Some(0), Some("/new"))
+        .await?;
+
+    poller.check_origin_now(unfollowed_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, unfollowed_uid, 1).await?;
+
+    assert_eq!(checks[0].status, Some(301));
+    assert_eq!(checks[0].final_url, None);
+
+    let misdirected_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, misdirected_uid, &followed).await?;
+    crate::persistence::set_origin_redirect_policy(&pool, misdirected_uid, Some(0), Some("/moved"))
+        .await?;
+
+    poller.check_origin_now(misdirected_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, misdirected_uid, 1).await?;
+
+    assert_eq!(
+        checks[0].failure_detail.as_deref(),
+        Some(format!("redirected to {0}/new instead of {0}/moved", server.url()).as_str())
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn origins_much_slower_than_their_baseline_are_alerted_on(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let uri = server.url();
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    let baseline_origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+    crate::persistence::insert_origin(&pool, baseline_origin_uid, "https://baseline.com").await?;
+    crate::persistence::set_origin_baseline(
+        &pool,
+        origin_uid,
+        Some(baseline_origin_uid),
+        Some(100),
+    )
+    .await?;
+
+    // The origin has been slow while the baseline has stayed fast
+    let mut tx = pool.begin().await?;
+    let now = sqlx::types::chrono::Utc::now();
+    crate::persistence::insert_query(&mut tx, origin_uid, Some(200), 800, now).await?;
+    crate::persistence::insert_query(&mut tx, baseline_origin_uid, Some(200), 20, now).await?;
+    tx.commit().await?;
+
+    let mock = server
+        .mock("GET", "/")
+        .with_status(200)
+        .create_async()
+        .await;
+
+    poller.check_origin_now(origin_uid).await;
+
+    mock.assert_async().await;
+
+    let map = poller.notifier.sent_messages.read().await;
+    let messages = &map[SNS_TOPIC];
+
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].subject, "Latency regression detected");
+    assert!(messages[0].message.starts_with(&format!("{uri} is ")));
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn traceroutes_are_captured_for_connection_failures(pool: PgPool) -> Result<()> {
+    // Nothing listens on port 1, so connections are refused
+    let uri = "tcp://127.0.0.1:1";
+
+    let poller = create_poller(&pool).with_traceroute(Traceroute::new("echo", 2)?);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+
+    poller.check_origin_now(origin_uid).await;
+    poller.check_origin_now(origin_uid).await;
+
+    // The traceroute runs in the background, so give it a moment to finish
+    let since = sqlx::types::chrono::Utc::now() - chrono::Duration::hours(1);
+    let mut traceroutes = Vec::new();
+
+    for _ in 0..50 {
+        traceroutes = crate::persistence::fetch_traceroutes(&pool, origin_uid, since).await?;
+
+        if !traceroutes.is_empty() {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    assert_eq!(traceroutes.len(), 1);
+    assert_eq!(traceroutes[0].host, "127.0.0.1");
+    assert_eq!(traceroutes[0].output.trim(), "127.0.0.1");
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn credentials_are_only_sent_to_their_origin(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    let mut elsewhere = mockito::Server::new_async().await;
+
+    let protected = server
+        .mock("GET", "/")
+        .match_header("authorization", "Bearer s3cret")
+        .with_status(302)
+        .with_header("location", &elsewhere.url())
+        .create_async()
+        .await;
+
+    let leaked = elsewhere
+        .mock("GET", "/")
+        .match_header("authorization", mockito::Matcher::Missing)
+        .with_status(200)
+        .create_async()
+        .await;
+
+    let cipher = CredentialCipher::new([1; 32]);
+    let poller = create_poller(&pool).with_credentials(cipher.clone());
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &server.url()).await?;
+
+    let credentials = Credentials::Bearer {
+        token: String::from("s3cret"),
+    };
+    let encrypted = cipher.encrypt(origin_uid, &credentials)?;
+    crate::persistence::set_origin_credentials(&pool, origin_uid, Some(&encrypted)).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    protected.assert_async().await;
+    leaked.assert_async().await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(checks[0].status, Some(200));
+
+    // Without the key, the credentials cannot be used and the check fails rather than going
+    // unauthenticated
+    create_poller(&pool).check_origin_now(origin_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(
+        checks[0].failure_reason.as_deref(),
+        Some(FailureReason::BadRequest.as_str())
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn the_scheduler_publishes_its_progress(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    server
+        .mock("GET", "/")
+        .with_status(200)
+        .create_async()
+        .await;
+
+    let poller = create_poller(&pool);
+    let handle = poller.handle();
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &server.url()).await?;
+
+    assert!(handle.scheduler().origins.is_empty());
+
+    poller.query_all_origins().await?;
+
+    let state = handle.scheduler();
+
+    assert!(state.running);
+    assert_eq!(state.in_flight, 0);
+    assert_eq!(state.backlog, 0);
+    assert_eq!(state.origins.len(), 1);
+    assert_eq!(state.origins[0].origin_uid, origin_uid);
+    assert!(state.origins[0].last_checked_at.is_some());
+
+    let next_round_at = chrono::Utc::now() + chrono::Duration::minutes(1);
+    poller.scheduler.waiting_until(next_round_at);
+
+    let state = handle.scheduler();
+
+    assert!(!state.running);
+    assert!(state.last_round_duration_millis.is_some());
+    assert_eq!(state.origins[0].next_check_at, Some(next_round_at));
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn each_round_is_summarised(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    server
+        .mock("GET", "/")
+        .with_status(200)
+        .create_async()
+        .await;
+
+    let poller = create_poller(&pool);
+    let handle = poller.handle();
+
+    let up = Uuid::new_v4();
+    let down = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, up, &server.url()).await?;
+    crate::persistence::insert_origin(&pool, down, "tcp://127.0.0.1:1").await?;
+
+    poller.query_all_origins().await?;
+
+    // Nothing is summarised until the round finishes
+    assert_eq!(handle.scheduler().last_round, None);
+
+    let summary = poller
+        .scheduler
+        .waiting_until(chrono::Utc::now() + chrono::Duration::minutes(1))
+        .expect("a round was running");
+
+    assert_eq!(summary.origins_checked, 2);
+    assert_eq!(summary.successes, 1);
+    assert_eq!(
+        summary
+            .failures
+            .get(FailureReason::ConnectionFailure.as_str()),
+        Some(&1)
+    );
+    assert_eq!(summary.total_failures(), 1);
+    assert!(summary.slowest.is_some());
+    assert_eq!(handle.scheduler().last_round, Some(summary));
+
+    // Checks made between rounds are left out of both
+    poller.check_origin_now(up).await;
+
+    assert!(poller
+        .scheduler
+        .waiting_until(chrono::Utc::now() + chrono::Duration::minutes(1))
+        .is_none());
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn adaptive_origins_are_rechecked_until_they_are_stable(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let down = server
+        .mock("GET", "/")
+        .with_status(500)
+        .create_async()
+        .await;
+
+    let poller = create_poller(&pool);
+
+    let adaptive_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, adaptive_uid, &server.url()).await?;
+    crate::persistence::set_origin_expected_status(&pool, adaptive_uid, Some("200")).await?;
+    crate::persistence::set_origin_adaptive_polling(&pool, adaptive_uid, true).await?;
+
+    // Failing origins without adaptive polling wait for the next round
+    let fixed_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, fixed_uid, "tcp://127.0.0.1:1").await?;
+
+    poller.query_all_origins().await?;
+
+    let unstable =
+        crate::persistence::fetch_unstable_origin_uids(&pool, STABLE_CHECKS.into(), false).await?;
+
+    assert_eq!(unstable, vec![adaptive_uid]);
+
+    down.remove_async().await;
+    server
+        .mock("GET", "/")
+        .with_status(200)
+        .create_async()
+        .await;
+
+    // A single success is not enough to go back to the normal interval
+    for _ in 1..STABLE_CHECKS {
+        poller.recheck_unstable_origins().await;
+
+        let unstable =
+            crate::persistence::fetch_unstable_origin_uids(&pool, STABLE_CHECKS.into(), false)
+                .await?;
+
+        assert_eq!(unstable, vec![adaptive_uid]);
+    }
+
+    poller.recheck_unstable_origins().await;
+
+    let unstable =
+        crate::persistence::fetch_unstable_origin_uids(&pool, STABLE_CHECKS.into(), false).await?;
+    assert!(unstable.is_empty());
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, adaptive_uid, 10).await?;
+    assert_eq!(checks.len(), 1 + STABLE_CHECKS as usize);
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, fixed_uid, 10).await?;
+    assert_eq!(checks.len(), 1);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn every_origin_can_be_rechecked_while_failing(pool: PgPool) -> Result<()> {
+    let alert_threshold = AlertThreshold::default()
+        .with_recheck_interval(Duration::from_secs(10))
+        .with_stable_checks(1)
+        .with_all_origins_adaptive();
+
+    let configuration =
+        PollerConfiguration::new(alert_threshold, SNS_TOPIC).with_jitter(Duration::ZERO);
+
+    let poller = Poller::new(
+        pool.clone(),
+        reqwest::Client::new(),
+        MockSnsClient::default(),
+        configuration,
+    );
+
+    // Origins are rechecked without having adaptive polling turned on themselves
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, "tcp://127.0.0.1:1").await?;
+
+    poller.query_all_origins().await?;
+    poller.recheck_unstable_origins().await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 10).await?;
+    assert_eq!(checks.len(), 2);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn checks_are_sent_through_proxies(pool: PgPool) -> Result<()> {
+    let mut shared = mockito::Server::new_async().await;
+    let mut dedicated = mockito::Server::new_async().await;
+
+    // The backend cannot be resolved, so checks only succeed if a proxy forwards them
+    let shared_mock = shared
+        .mock("GET", mockito::Matcher::Any)
+        .match_header("host", "backend.invalid")
+        .with_status(200)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let dedicated_mock = dedicated
+        .mock("GET", mockito::Matcher::Any)
+        .match_header("host", "backend.invalid")
+        .with_status(200)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let configuration = PollerConfiguration::new(AlertThreshold::default(), SNS_TOPIC)
+        .with_proxy(reqwest::Proxy::all(shared.url())?)
+        .with_jitter(Duration::ZERO);
+    let poller = Poller::new(
+        pool.clone(),
+        reqwest::Client::new(),
+        MockSnsClient::default(),
+        configuration,
+    );
+
+    let through_shared = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, through_shared, "http://backend.invalid/a").await?;
+
+    let through_dedicated = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, through_dedicated, "http://backend.invalid/b").await?;
+    crate::persistence::set_origin_proxy(&pool, through_dedicated, Some(&dedicated.url())).await?;
+
+    let unusable = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, unusable, "http://backend.invalid/c").await?;
+    crate::persistence::set_origin_proxy(&pool, unusable, Some("not a proxy")).await?;
+
+    poller.query_all_origins().await?;
+
+    shared_mock.assert_async().await;
+    dedicated_mock.assert_async().await;
+
+    for origin_uid in [through_shared, through_dedicated] {
+        let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+        assert_eq!(checks[0].status, Some(200));
+    }
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, unusable, 1).await?;
+
+    assert_eq!(
+        checks[0].failure_reason.as_deref(),
+        Some(FailureReason::BadRequest.as_str())
+    );
+    assert!(checks[0]
+        .failure_detail
+        .as_deref()
+        .is_some_and(|detail| detail.contains("proxy")));
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn unusable_client_certificates_fail_checks(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let mock = server
+        .mock("GET", "/")
+        .with_status(200)
+        .expect(0)
+        .create_async()
+        .await;
+
+    let cipher = CredentialCipher::new([2; 32]);
+    let poller = create_poller(&pool).with_credentials(cipher.clone());
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &server.url()).await?;
+
+    let encrypted = cipher.encrypt_identity(origin_uid, b"not a certificate")?;
+    crate::persistence::set_origin_client_identity(&pool, origin_uid, Some(&encrypted)).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    // The check should not quietly fall back to connecting without the certificate
+    mock.assert_async().await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 1).await?;
+
+    assert_eq!(
+        checks[0].failure_reason.as_deref(),
+        Some(FailureReason::BadRequest.as_str())
+    );
+    assert!(checks[0]
+        .failure_detail
+        .as_deref()
+        .is_some_and(|detail| detail.contains("client certificate")));
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn origins_can_be_checked_over_each_address_family(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    // Only reachable over IPv4, like a service whose IPv6 route has broken
+    server
+        .mock("GET", "/")
+        .with_status(200)
+        .create_async()
+        .await;
+
+    let poller = create_poller(&pool);
+
+    let origin_uid = Uuid::new_v4();
+    crate::persistence::insert_origin(&pool, origin_uid, &server.url()).await?;
+    crate::persistence::set_origin_address_family(&pool, origin_uid, AddressFamily::Both).await?;
+
+    poller.check_origin_now(origin_uid).await;
+
+    let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 2).await?;
+
+    assert_eq!(checks.len(), 2);
+
+    let ipv4 = checks
+        .iter()
+        .find(|check| check.address_family.as_deref() == Some("ipv4"))
+        .unwrap();
+    let ipv6 = checks
+        .iter()
+        .find(|check| check.address_family.as_deref() == Some("ipv6"))
+        .unwrap();
+
+    assert_eq!(ipv4.status, Some(200));
+    assert_eq!(
+        ipv6.failure_reason.as_deref(),
+        Some(FailureReason::BadRequest.as_str())
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn origins_are_only_checked_from_their_probe_locations(pool: PgPool) -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+
+    let everywhere_uid = Uuid::new_v4();
+    let local_uid = Uuid::new_v4();
+    let remote_uid = Uuid::new_v4();
+
+    for (origin_uid, probe_locations) in [
+        (everywhere_uid, None),
+        (local_uid, Some("local")),
+        (remote_uid, Some("eu-west-1, us-east-1")),
+    ] {
+        let uri = format!("{}/{origin_uid}", server.url());
+        crate::persistence::insert_origin(&pool, origin_uid, &uri).await?;
+        crate::persistence::set_origin_probe_locations(&pool, origin_uid, probe_locations).await?;
+    }
+
+    // The unrestricted origin is checked from both places, and the others from one each
+    let mock = server
+        .mock("GET", mockito::Matcher::Any)
+        .with_status(200)
+        .expect(4)
+        .create_async()
+        .await;
+
+    let local = create_poller(&pool);
+    let located = create_located_poller(&pool, "eu-west-1", AlertThreshold::default());
+
+    local.query_all_origins().await?;
+    located.query_all_origins().await?;
+
+    mock.assert_async().await;
+
+    for (origin_uid, expected) in [
+        (everywhere_uid, vec![None, Some("eu-west-1")]),
+        (local_uid, vec![None]),
+        (remote_uid, vec![Some("eu-west-1")]),
+    ] {
+        let checks = crate::persistence::fetch_recent_checks(&pool, origin_uid, 10).await?;
+        let locations: Vec<_> = checks
+            .iter()
+            .map(|check| check.location.as_deref())
+            .collect();
+
+        assert_eq!(locations, expected);
+    }
 
     Ok(())
 }
@@ -0,0 +1,155 @@
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use reqwest::Url;
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::TlsConnector;
+
+use crate::persistence::AddressFamily;
+
+/// How long each phase of an HTTP check took, so slowness can be put down to DNS, the network,
+/// TLS or the application itself.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Timings {
+    /// How long the host took to resolve, which is `None` for addresses written in the URL.
+    pub dns_millis: Option<i64>,
+    pub connect_millis: Option<i64>,
+    /// How long the TLS handshake took, which is `None` for plain HTTP.
+    pub tls_millis: Option<i64>,
+    /// How long the request took to get the headers of its final response back.
+    pub first_byte_millis: Option<i64>,
+}
+
+impl Timings {
+    /// Describes the phases that were timed, such as `DNS 4ms, connect 12ms`.
+    pub fn describe(&self) -> Option<String> {
+        let phases: Vec<_> = [
+            ("DNS", self.dns_millis),
+            ("connect", self.connect_millis),
+            ("TLS", self.tls_millis),
+            ("first byte", self.first_byte_millis),
+        ]
+        .into_iter()
+        .filter_map(|(name, millis)| Some(format!("{name} {}ms", millis?)))
+        .collect();
+
+        (!phases.is_empty()).then(|| phases.join(", "))
+    }
+}
+
+fn millis_since(start: Instant) -> i64 {
+    start.elapsed().as_millis() as i64
+}
+
+/// Times resolving the host of `uri` over `family`, connecting to it and completing a TLS
+/// handshake for HTTPS.
+///
+/// This opens a connection of its own, as the client reuses connections between checks and would
+/// otherwise skip these phases. Phases after one that fails are left out, since the request itself
+/// will report the failure.
+pub async fn measure(
+    connector: &TlsConnector,
+    uri: &str,
+    family: AddressFamily,
+    timeout: Duration,
+) -> Timings {
+    let mut timings = Timings::default();
+
+    let Ok(url) = Url::parse(uri) else {
+        return timings;
+    };
+
+    let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+        return timings;
+    };
+
+    // IPv6 addresses keep their brackets when written as a host
+    let literal = host.trim_start_matches('[').trim_end_matches(']');
+
+    let address = match literal.parse() {
+        Ok(ip) => SocketAddr::new(ip, port),
+        Err(_) => {
+            let start = Instant::now();
+            let resolved = tokio::time::timeout(timeout, tokio::net::lookup_host((host, port)));
+
+            let Ok(Ok(mut addresses)) = resolved.await else {
+                return timings;
+            };
+
+            timings.dns_millis = Some(millis_since(start));
+
+            match addresses.find(|address| family.allows(address.ip())) {
+                Some(address) => address,
+                None => return timings,
+            }
+        }
+    };
+
+    let start = Instant::now();
+
+    let Ok(Ok(stream)) = tokio::time::timeout(timeout, TcpStream::connect(address)).await else {
+        return timings;
+    };
+
+    timings.connect_millis = Some(millis_since(start));
+
+    if url.scheme() != "https" {
+        return timings;
+    }
+
+    let Ok(server_name) = ServerName::try_from(literal.to_owned()) else {
+        return timings;
+    };
+
+    let start = Instant::now();
+
+    if let Ok(Ok(_)) = tokio::time::timeout(timeout, connector.connect(server_name, stream)).await {
+        timings.tls_millis = Some(millis_since(start));
+    }
+
+    timings
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use color_eyre::eyre::Result;
+    use tokio::net::TcpListener;
+
+    use crate::persistence::AddressFamily;
+    use crate::poller::timing::Timings;
+
+    #[tokio::test]
+    async fn plain_http_is_timed_without_dns_or_tls() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let uri = format!("http://{}/health", listener.local_addr()?);
+
+        let connector = crate::poller::smtp::connector();
+        let timings =
+            super::measure(&connector, &uri, AddressFamily::Any, Duration::from_secs(1)).await;
+
+        assert_eq!(timings.dns_millis, None);
+        assert!(timings.connect_millis.is_some());
+        assert_eq!(timings.tls_millis, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn only_timed_phases_are_described() {
+        let timings = Timings {
+            dns_millis: None,
+            connect_millis: Some(12),
+            tls_millis: Some(40),
+            first_byte_millis: Some(150),
+        };
+
+        assert_eq!(
+            timings.describe().as_deref(),
+            Some("connect 12ms, TLS 40ms, first byte 150ms")
+        );
+        assert_eq!(Timings::default().describe(), None);
+    }
+}
@@ -0,0 +1,136 @@
+use std::process::Stdio;
+use std::time::Duration;
+
+use color_eyre::eyre::{eyre, Result};
+use tokio::process::Command;
+
+/// The number of consecutive connection failures before a traceroute is captured, if not
+/// configured.
+const DEFAULT_AFTER_FAILURES: i64 = 3;
+
+/// The longest a traceroute can run for before it is abandoned.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Captures the network path to an origin once it has failed enough consecutive checks with
+/// connection errors, as diagnostic context for the incident.
+#[derive(Clone, Debug)]
+pub struct Traceroute {
+    program: String,
+    args: Vec<String>,
+    after_failures: i64,
+}
+
+impl Traceroute {
+    /// Creates a traceroute that runs `command`, such as `traceroute -n -q 1` or `tcptraceroute`,
+    /// with the host appended.
+    pub fn new(command: &str, after_failures: i64) -> Result<Self> {
+        let mut words = command.split_whitespace().map(str::to_owned);
+
+        let program = words
+            .next()
+            .ok_or_else(|| eyre!("the traceroute command cannot be empty"))?;
+
+        Ok(Self {
+            program,
+            args: words.collect(),
+            after_failures,
+        })
+    }
+
+    /// Reads the command from `TRACEROUTE_COMMAND`, returning `None` if it is not set.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Ok(command) = std::env::var("TRACEROUTE_COMMAND") else {
+            return Ok(None);
+        };
+
+        let after_failures = match std::env::var("TRACEROUTE_AFTER_FAILURES") {
+            Ok(value) => value.parse()?,
+            Err(_) => DEFAULT_AFTER_FAILURES,
+        };
+
+        Ok(Some(Self::new(&command, after_failures)?))
+    }
+
+    /// Whether a failure streak has just reached the point a traceroute should be captured, which
+    /// only happens once per streak.
+    pub fn is_due(&self, consecutive_failures: i64) -> bool {
+        consecutive_failures == self.after_failures
+    }
+
+    /// Runs the command against the host, returning everything it printed.
+    pub async fn capture(&self, host: &str) -> Result<String> {
+        let child = Command::new(&self.program)
+            .args(&self.args)
+            .arg(host)
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .output();
+
+        let output = tokio::time::timeout(TIMEOUT, child)
+            .await
+            .map_err(|_| eyre!("traceroute to {host} did not finish within {TIMEOUT:?}"))??;
+
+        let mut path = String::from_utf8_lossy(&output.stdout).into_owned();
+        path.push_str(&String::from_utf8_lossy(&output.stderr));
+
+        Ok(path)
+    }
+}
+
+/// The host to trace the path to for an origin, or `None` for checks without a single host to
+/// reach, such as DNS lookups, heartbeats, journeys and commands.
+pub fn host(uri: &str) -> Option<String> {
+    let hostless = ["dns://", "heartbeat://", "journey://", "command://"];
+
+    if hostless.iter().any(|scheme| uri.starts_with(scheme)) {
+        return None;
+    }
+
+    let url = reqwest::Url::parse(uri).ok()?;
+    let host = url.host_str()?;
+
+    // IPv6 addresses are bracketed in URLs but not on the command line
+    Some(
+        host.trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_owned(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use color_eyre::eyre::Result;
+
+    use crate::poller::traceroute::Traceroute;
+
+    #[test]
+    fn traceroutes_are_only_due_once_per_streak() -> Result<()> {
+        let traceroute = Traceroute::new("traceroute -n", 3)?;
+
+        assert!(!traceroute.is_due(2));
+        assert!(traceroute.is_due(3));
+        assert!(!traceroute.is_due(4));
+
+        Ok(())
+    }
+
+    #[test]
+    fn hosts_are_taken_from_each_kind_of_origin() {
+        assert_eq!(
+            super::host("https://example.com/health").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(
+            super::host("tcp://db.internal:5432").as_deref(),
+            Some("db.internal")
+        );
+        assert_eq!(super::host("icmp://[::1]").as_deref(), Some("::1"));
+        assert_eq!(super::host("dns://example.com?type=MX"), None);
+        assert_eq!(super::host("journey://checkout"), None);
+    }
+
+    #[test]
+    fn empty_commands_are_rejected() {
+        assert!(Traceroute::new("  ", 3).is_err());
+    }
+}
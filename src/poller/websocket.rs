@@ -0,0 +1,259 @@
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use futures_util::future::BoxFuture;
+use reqwest::header::{
+    CONNECTION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_VERSION, UPGRADE,
+};
+use reqwest::{StatusCode, Url};
+use ring::digest::{digest, SHA1_FOR_LEGACY_USE_ONLY};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::persistence::Origin;
+use crate::poller::check::{Check, CheckContext, CheckOutcome};
+use crate::poller::FailureReason;
+
+/// Appended to the key to prove the server understood the handshake, as defined in RFC 6455.
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xa;
+
+/// A WebSocket endpoint written as `ws://host/path` or `wss://host/path`.
+///
+/// Adding `#ping` also sends a ping frame once the handshake completes and waits for the pong, as
+/// some servers accept connections without being able to serve them.
+#[derive(Debug, PartialEq, Eq)]
+pub struct WebSocketCheck {
+    url: Url,
+    ping: bool,
+}
+
+impl WebSocketCheck {
+    pub fn parse(uri: &str) -> Option<Self> {
+        let mut url = Url::parse(uri).ok()?;
+
+        let scheme = match url.scheme() {
+            "ws" => "http",
+            "wss" => "https",
+            _ => return None,
+        };
+
+        // Fragments are never sent to the server, so they are free to configure the check
+        let ping = url.fragment() == Some("ping");
+        url.set_fragment(None);
+        url.set_scheme(scheme).ok()?;
+
+        Some(Self { url, ping })
+    }
+}
+
+/// Builds a client that only speaks HTTP/1.1, as HTTP/2 connections cannot be upgraded.
+pub fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .http1_only()
+        .build()
+        .expect("failed to build the WebSocket client")
+}
+
+/// The `Sec-WebSocket-Accept` value a server must respond with for a key.
+pub fn accept_key(key: &str) -> String {
+    let hash = digest(
+        &SHA1_FOR_LEGACY_USE_ONLY,
+        format!("{key}{HANDSHAKE_GUID}").as_bytes(),
+    );
+
+    STANDARD.encode(hash)
+}
+
+/// Performs the opening handshake, and optionally a ping, returning the status of the upgrade.
+pub async fn check(
+    client: &reqwest::Client,
+    check: &WebSocketCheck,
+    timeout: Duration,
+) -> Result<u16, FailureReason> {
+    let key = STANDARD.encode(rand::random::<[u8; 16]>());
+
+    let response = client
+        .get(check.url.clone())
+        .header(CONNECTION, "Upgrade")
+        .header(UPGRADE, "websocket")
+        .header(SEC_WEBSOCKET_VERSION, "13")
+        .header(SEC_WEBSOCKET_KEY, &key)
+        .timeout(timeout)
+        .send()
+        .await?;
+
+    let status = response.status();
+
+    if status != StatusCode::SWITCHING_PROTOCOLS {
+        return Err(FailureReason::UnexpectedStatus);
+    }
+
+    let accepted = response
+        .headers()
+        .get(SEC_WEBSOCKET_ACCEPT)
+        .is_some_and(|accept| accept.as_bytes() == accept_key(&key).as_bytes());
+
+    if !accepted {
+        return Err(FailureReason::InvalidBody);
+    }
+
+    if check.ping {
+        let mut stream = response.upgrade().await?;
+
+        tokio::time::timeout(timeout, ping(&mut stream))
+            .await
+            .map_err(|_| FailureReason::RequestTimeout)??;
+    }
+
+    Ok(status.as_u16())
+}
+
+/// Sends a ping and waits for the pong, skipping any messages the server sends first.
+async fn ping<S>(stream: &mut S) -> Result<(), FailureReason>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    // Clients must mask every frame, even one without a payload
+    let mask: [u8; 4] = rand::random();
+    let mut frame = vec![0x80 | OPCODE_PING, 0x80];
+    frame.extend_from_slice(&mask);
+
+    stream
+        .write_all(&frame)
+        .await
+        .map_err(|_| FailureReason::ConnectionFailure)?;
+
+    loop {
+        let (opcode, length) = read_frame_header(stream)
+            .await
+            .map_err(|_| FailureReason::ConnectionFailure)??;
+
+        tokio::io::copy(&mut (&mut *stream).take(length), &mut tokio::io::sink())
+            .await
+            .map_err(|_| FailureReason::ConnectionFailure)?;
+
+        match opcode {
+            OPCODE_PONG => return Ok(()),
+            OPCODE_CLOSE => return Err(FailureReason::ConnectionFailure),
+            _ => continue,
+        }
+    }
+}
+
+/// Reads the opcode and payload length of a frame sent by the server.
+async fn read_frame_header<S>(stream: &mut S) -> std::io::Result<Result<(u8, u64), FailureReason>>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut header = [0; 2];
+    stream.read_exact(&mut header).await?;
+
+    // Servers must never mask their frames
+    if header[1] & 0x80 != 0 {
+        return Ok(Err(FailureReason::InvalidBody));
+    }
+
+    let length = match header[1] & 0x7f {
+        126 => u64::from(stream.read_u16().await?),
+        127 => stream.read_u64().await?,
+        length => u64::from(length),
+    };
+
+    Ok(Ok((header[0] & 0x0f, length)))
+}
+
+/// Performs the opening handshake with origins written as `ws://host/path`.
+pub struct WebSocketChecker {
+    client: reqwest::Client,
+}
+
+impl Default for WebSocketChecker {
+    fn default() -> Self {
+        Self { client: client() }
+    }
+}
+
+impl Check for WebSocketChecker {
+    fn execute<'a>(
+        &'a self,
+        origin: &'a Origin,
+        context: &'a CheckContext<'a>,
+    ) -> BoxFuture<'a, CheckOutcome> {
+        Box::pin(async move {
+            let Some(parsed) = WebSocketCheck::parse(&origin.uri) else {
+                return CheckOutcome::new(Err(FailureReason::BadRequest));
+            };
+
+            let result = check(&self.client, &parsed, context.timeout)
+                .await
+                .map(|status| (Some(status), context.elapsed_millis()));
+
+            CheckOutcome::new(result)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::poller::websocket::WebSocketCheck;
+    use crate::poller::FailureReason;
+
+    #[test]
+    fn accept_keys_match_the_specification() {
+        // The example handshake from RFC 6455
+        assert_eq!(
+            super::accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn checks_are_parsed_from_uris() {
+        let check = WebSocketCheck::parse("wss://realtime.example.com/socket#ping").unwrap();
+
+        assert_eq!(check.url.as_str(), "https://realtime.example.com/socket");
+        assert!(check.ping);
+
+        let check = WebSocketCheck::parse("ws://localhost:8080").unwrap();
+
+        assert_eq!(check.url.as_str(), "http://localhost:8080/");
+        assert!(!check.ping);
+
+        assert!(WebSocketCheck::parse("https://example.com").is_none());
+    }
+
+    #[tokio::test]
+    async fn pongs_are_found_after_other_messages() {
+        // A text message, then the pong
+        let mut stream = server_sending(&[0x81, 2, b'h', b'i', 0x8a, 0]);
+        assert_eq!(super::ping(&mut stream).await, Ok(()));
+
+        let mut stream = server_sending(&[0x88, 0]);
+        assert_eq!(
+            super::ping(&mut stream).await,
+            Err(FailureReason::ConnectionFailure)
+        );
+    }
+
+    /// A stream that yields `incoming` and accepts anything written to it.
+    fn server_sending(incoming: &[u8]) -> tokio::io::DuplexStream {
+        let (client, mut server) = tokio::io::duplex(1024);
+
+        let incoming = incoming.to_vec();
+        tokio::spawn(async move {
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+            server.write_all(&incoming).await.unwrap();
+
+            // Keep the connection open until the client is done with it
+            let mut sink = Vec::new();
+            let _ = server.read_to_end(&mut sink).await;
+        });
+
+        client
+    }
+}
@@ -0,0 +1,137 @@
+use chrono::Duration;
+use serde::Serialize;
+
+use crate::persistence::LocationStatus;
+
+/// How far back uptime is measured when breaking it down by location.
+pub const WINDOW: Duration = Duration::days(1);
+
+/// How widespread an outage of an origin checked from several locations is.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outage {
+    /// Only some locations are failing, which can be a problem with the network near them rather
+    /// than with the origin.
+    Partial,
+    /// Every location is failing.
+    Global,
+}
+
+impl Outage {
+    /// The outage across `locations`, if any of them are failing.
+    pub fn of(locations: &[LocationStatus]) -> Option<Self> {
+        let failing = locations
+            .iter()
+            .filter(|location| !location.operational)
+            .count();
+
+        match failing {
+            0 => None,
+            failing if failing == locations.len() => Some(Self::Global),
+            _ => Some(Self::Partial),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use color_eyre::eyre::Result;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    use crate::persistence::{LocationStatus, QueryDetails, QueryFailureDetails};
+    use crate::poller::FailureReason;
+    use crate::regions::{Outage, WINDOW};
+
+    fn location(name: &str, operational: bool) -> LocationStatus {
+        LocationStatus {
+            origin_uid: Uuid::nil(),
+            location: name.to_owned(),
+            operational,
+            uptime: 1.0,
+            queried_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn outages_are_partial_until_every_location_fails() {
+        let up = [location("eu-west", true), location("us-east", true)];
+        assert_eq!(Outage::of(&up), None);
+
+        let partial = [location("eu-west", false), location("us-east", true)];
+        assert_eq!(Outage::of(&partial), Some(Outage::Partial));
+
+        let global = [location("eu-west", false), location("us-east", false)];
+        assert_eq!(Outage::of(&global), Some(Outage::Global));
+
+        assert_eq!(Outage::of(&[]), None);
+    }
+
+    #[sqlx::test]
+    async fn locations_are_judged_by_their_latest_check(pool: PgPool) -> Result<()> {
+        let origin_uid = Uuid::new_v4();
+        crate::persistence::insert_origin(&pool, origin_uid, "https://example.com").await?;
+
+        let now = Utc::now();
+        let mut tx = pool.begin().await?;
+
+        for minutes_ago in [3, 2] {
+            let query_uid = crate::persistence::insert_query(
+                &mut tx,
+                origin_uid,
+                Some(200),
+                50,
+                now - Duration::minutes(minutes_ago),
+            )
+            .await?;
+            let details = QueryDetails {
+                location: Some("eu-west"),
+                ..Default::default()
+            };
+            crate::persistence::set_query_details(&mut tx, query_uid, &details).await?;
+        }
+
+        // Only the most recent check from a location decides whether it is failing
+        let query_uid =
+            crate::persistence::insert_query(&mut tx, origin_uid, Some(200), 50, now).await?;
+        let details = QueryDetails {
+            location: Some("us-east"),
+            ..Default::default()
+        };
+        crate::persistence::set_query_details(&mut tx, query_uid, &details).await?;
+
+        let query_failure_uid = crate::persistence::insert_query_failure(
+            &mut tx,
+            origin_uid,
+            FailureReason::RequestTimeout,
+            None,
+            now - Duration::minutes(1),
+        )
+        .await?;
+        let details = QueryFailureDetails {
+            location: Some("eu-west"),
+            ..Default::default()
+        };
+        crate::persistence::set_query_failure_details(&mut tx, query_failure_uid, &details).await?;
+
+        // Checks without a location are left out of the breakdown
+        crate::persistence::insert_query(&mut tx, origin_uid, Some(200), 50, now).await?;
+        tx.commit().await?;
+
+        let locations =
+            crate::persistence::fetch_location_statuses(&pool, Some(origin_uid), now - WINDOW)
+                .await?;
+
+        let summary: Vec<_> = locations
+            .iter()
+            .map(|location| (location.location.as_str(), location.operational))
+            .collect();
+        assert_eq!(summary, [("eu-west", false), ("us-east", true)]);
+        assert!((locations[0].uptime - 2.0 / 3.0).abs() < f64::EPSILON);
+
+        assert_eq!(Outage::of(&locations), Some(Outage::Partial));
+
+        Ok(())
+    }
+}
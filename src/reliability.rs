@@ -0,0 +1,100 @@
+use chrono::Duration;
+
+use crate::persistence::Incident;
+
+/// The period reliability metrics are calculated over if none is given.
+pub const DEFAULT_WINDOW: Duration = Duration::days(90);
+
+/// Mean time to recovery and mean time between failures for an origin.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Reliability {
+    pub incidents: usize,
+    /// The average length of incidents that have been resolved.
+    pub mean_time_to_recovery: Option<Duration>,
+    /// The average time from an incident resolving to the next one starting.
+    pub mean_time_between_failures: Option<Duration>,
+}
+
+fn mean(durations: &[Duration]) -> Option<Duration> {
+    let total: Duration = durations.iter().copied().sum();
+    let count = i32::try_from(durations.len()).ok().filter(|&n| n > 0)?;
+
+    Some(total / count)
+}
+
+/// Measures an origin's reliability from its incidents, which must be ordered by when they started.
+pub fn measure(incidents: &[Incident]) -> Reliability {
+    let recoveries: Vec<_> = incidents
+        .iter()
+        .filter_map(|incident| Some(incident.resolved_at? - incident.started_at))
+        .collect();
+
+    let uptimes: Vec<_> = incidents
+        .windows(2)
+        .filter_map(|pair| Some(pair[1].started_at - pair[0].resolved_at?))
+        .collect();
+
+    Reliability {
+        incidents: incidents.len(),
+        mean_time_to_recovery: mean(&recoveries),
+        mean_time_between_failures: mean(&uptimes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, Utc};
+    use uuid::Uuid;
+
+    use crate::persistence::Incident;
+    use crate::reliability::Reliability;
+
+    fn incident(started_at: DateTime<Utc>, resolved_after: Option<Duration>) -> Incident {
+        Incident {
+            origin_uid: Uuid::nil(),
+            uri: String::from("https://example.com"),
+            started_at,
+            resolved_at: resolved_after.map(|duration| started_at + duration),
+            failure_count: 3,
+            cost_per_minute_cents: None,
+        }
+    }
+
+    #[test]
+    fn recoveries_and_uptimes_are_averaged() {
+        let start = Utc::now() - Duration::days(1);
+
+        let incidents = [
+            incident(start, Some(Duration::minutes(10))),
+            incident(start + Duration::hours(2), Some(Duration::minutes(20))),
+            incident(start + Duration::hours(6), None),
+        ];
+
+        // Uptimes of 1h50m and 3h40m between the incidents
+        assert_eq!(
+            super::measure(&incidents),
+            Reliability {
+                incidents: 3,
+                mean_time_to_recovery: Some(Duration::minutes(15)),
+                mean_time_between_failures: Some(Duration::minutes(165)),
+            }
+        );
+    }
+
+    #[test]
+    fn metrics_are_missing_without_enough_incidents() {
+        assert_eq!(
+            super::measure(&[]),
+            Reliability {
+                incidents: 0,
+                mean_time_to_recovery: None,
+                mean_time_between_failures: None,
+            }
+        );
+
+        let ongoing = [incident(Utc::now(), None)];
+
+        assert_eq!(super::measure(&ongoing).mean_time_to_recovery, None);
+        assert_eq!(super::measure(&ongoing).mean_time_between_failures, None);
+    }
+}
@@ -0,0 +1,377 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::approval::ChangeKind;
+use crate::config::{ConfigurationDiff, MonitorConfiguration, IMPORT_DRAFT_NAME};
+use crate::persistence::{
+    Draft, DraftSummary, HistoryEntry, HistoryPosition, NotificationEntry, NotificationPosition,
+};
+use crate::poller::{CheckNowError, CheckResult};
+use crate::regions::{self, Outage};
+use crate::router::changes::Submission;
+use crate::router::cursors::{Page, PageRequest};
+use crate::router::identity::User;
+use crate::router::{check_now_timeout, ApplicationState};
+
+type ApiError = (StatusCode, String);
+
+fn draft_not_found() -> ApiError {
+    (StatusCode::NOT_FOUND, String::from("draft not found"))
+}
+
+fn origin_not_found() -> ApiError {
+    (StatusCode::NOT_FOUND, String::from("origin not found"))
+}
+
+fn draft_already_promoted() -> ApiError {
+    (
+        StatusCode::CONFLICT,
+        String::from("draft has already been promoted"),
+    )
+}
+
+pub async fn export_configuration(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+) -> Json<MonitorConfiguration> {
+    let configuration = crate::config::export(&pool)
+        .await
+        .expect("failed to export configuration");
+
+    Json(configuration)
+}
+
+/// Imports a configuration, going through the approval flow if it would disable alerts.
+pub async fn import_configuration(
+    State(state): State<ApplicationState>,
+    user: Option<User>,
+    Json(configuration): Json<MonitorConfiguration>,
+) -> Result<StatusCode, ApiError> {
+    configuration
+        .check_version()
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+
+    let destructive = crate::config::import_is_destructive(&state.pool, &configuration)
+        .await
+        .expect("failed to compare configurations");
+
+    if !destructive {
+        crate::config::import(&state.pool, &configuration)
+            .await
+            .expect("failed to import configuration");
+
+        return Ok(StatusCode::NO_CONTENT);
+    }
+
+    // The configuration is kept as a draft until the import is approved
+    let draft_uid =
+        crate::persistence::insert_draft(&state.pool, IMPORT_DRAFT_NAME, &configuration)
+            .await
+            .expect("failed to store configuration");
+
+    let description = format!(
+        "Import a configuration of {} origins",
+        configuration.origins.len()
+    );
+
+    let submission = crate::router::changes::submit(
+        &state,
+        user,
+        ChangeKind::ImportConfiguration,
+        draft_uid,
+        description,
+    )
+    .await?;
+
+    let status = match submission {
+        Submission::Applied => StatusCode::NO_CONTENT,
+        Submission::Pending => StatusCode::ACCEPTED,
+    };
+
+    Ok(status)
+}
+
+/// Checks an origin straight away, outside of the rounds, and returns the result.
+pub async fn check_origin(
+    State(ApplicationState { pool, poller, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+) -> Result<Json<CheckResult>, ApiError> {
+    let origin = crate::persistence::fetch_origin(&pool, origin_uid)
+        .await
+        .expect("failed to fetch origin")
+        .ok_or_else(origin_not_found)?;
+
+    let timeout = check_now_timeout(&origin);
+
+    match poller.check_now(origin_uid, timeout).await {
+        Ok(result) => Ok(Json(result)),
+        Err(CheckNowError::Busy) => Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            String::from("the poller is too busy to take more requests, try again shortly"),
+        )),
+        Err(CheckNowError::TimedOut) => Err((
+            StatusCode::GATEWAY_TIMEOUT,
+            format!(
+                "the check did not finish within {}s, or was skipped by a maintenance window",
+                timeout.as_secs()
+            ),
+        )),
+    }
+}
+
+async fn set_origin_paused(
+    pool: &sqlx::PgPool,
+    origin_uid: Uuid,
+    paused: bool,
+) -> Result<StatusCode, ApiError> {
+    crate::persistence::fetch_origin(pool, origin_uid)
+        .await
+        .expect("failed to fetch origin")
+        .ok_or_else(origin_not_found)?;
+
+    crate::persistence::set_origin_paused(pool, origin_uid, paused)
+        .await
+        .expect("failed to set origin paused state");
+
+    tracing::info!(%origin_uid, %paused, "changed whether an origin is paused");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Stops checking an origin until it is resumed, keeping its history.
+pub async fn pause_origin(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    set_origin_paused(&pool, origin_uid, true).await
+}
+
+pub async fn resume_origin(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    set_origin_paused(&pool, origin_uid, false).await
+}
+
+/// Lists the checks of an origin, newest first, a page at a time.
+pub async fn origin_history(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Query(page): Query<PageRequest>,
+) -> Result<Json<Page<HistoryEntry>>, ApiError> {
+    let after = page.after::<HistoryPosition>()?;
+    let limit = page.limit()?;
+
+    crate::persistence::fetch_origin(&pool, origin_uid)
+        .await
+        .expect("failed to fetch origin")
+        .ok_or_else(origin_not_found)?;
+
+    let entries = crate::persistence::fetch_history_page(&pool, origin_uid, after, limit + 1)
+        .await
+        .expect("failed to fetch origin history");
+
+    Ok(Json(Page::new(entries, limit, HistoryEntry::position)))
+}
+
+#[derive(Deserialize)]
+pub struct NotificationFilter {
+    /// Only lists the notifications about this origin.
+    origin_uid: Option<Uuid>,
+}
+
+/// Lists the notifications that have been sent, newest first, a page at a time.
+pub async fn list_notifications(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Query(NotificationFilter { origin_uid }): Query<NotificationFilter>,
+    Query(page): Query<PageRequest>,
+) -> Result<Json<Page<NotificationEntry>>, ApiError> {
+    let after = page.after::<NotificationPosition>()?;
+    let limit = page.limit()?;
+
+    let entries = crate::persistence::fetch_notification_page(&pool, origin_uid, after, limit + 1)
+        .await
+        .expect("failed to fetch notifications");
+
+    Ok(Json(Page::new(entries, limit, NotificationEntry::position)))
+}
+
+pub async fn list_drafts(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+) -> Json<Vec<DraftSummary>> {
+    let drafts = crate::persistence::fetch_drafts(&pool)
+        .await
+        .expect("failed to fetch drafts");
+
+    Json(drafts)
+}
+
+#[derive(Deserialize)]
+pub struct DraftCreationRequest {
+    name: String,
+    /// The initial contents of the draft, defaulting to a copy of the live configuration.
+    configuration: Option<MonitorConfiguration>,
+}
+
+#[derive(Serialize)]
+pub struct DraftCreated {
+    draft_uid: Uuid,
+}
+
+pub async fn create_draft(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Json(DraftCreationRequest {
+        name,
+        configuration,
+    }): Json<DraftCreationRequest>,
+) -> Result<(StatusCode, Json<DraftCreated>), ApiError> {
+    let configuration = match configuration {
+        Some(configuration) => configuration,
+        None => crate::config::export(&pool)
+            .await
+            .expect("failed to export configuration"),
+    };
+
+    configuration
+        .check_version()
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+
+    let draft_uid = crate::persistence::insert_draft(&pool, &name, &configuration)
+        .await
+        .expect("failed to insert draft");
+
+    tracing::info!(%draft_uid, %name, "created a configuration draft");
+
+    Ok((StatusCode::CREATED, Json(DraftCreated { draft_uid })))
+}
+
+pub async fn get_draft(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(draft_uid): Path<Uuid>,
+) -> Result<Json<Draft>, ApiError> {
+    let draft = crate::persistence::fetch_draft(&pool, draft_uid)
+        .await
+        .expect("failed to fetch draft")
+        .ok_or_else(draft_not_found)?;
+
+    Ok(Json(draft))
+}
+
+pub async fn update_draft(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(draft_uid): Path<Uuid>,
+    Json(configuration): Json<MonitorConfiguration>,
+) -> Result<StatusCode, ApiError> {
+    configuration
+        .check_version()
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+
+    let updated = crate::persistence::update_draft_document(&pool, draft_uid, &configuration)
+        .await
+        .expect("failed to update draft");
+
+    if !updated {
+        return Err(draft_already_promoted_or_missing(&pool, draft_uid).await);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn diff_draft(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(draft_uid): Path<Uuid>,
+) -> Result<Json<ConfigurationDiff>, ApiError> {
+    let draft = crate::persistence::fetch_draft(&pool, draft_uid)
+        .await
+        .expect("failed to fetch draft")
+        .ok_or_else(draft_not_found)?;
+
+    let live = crate::persistence::fetch_origin_configurations(&pool)
+        .await
+        .expect("failed to fetch live configuration");
+
+    Ok(Json(crate::config::diff(&live, &draft.document.origins)))
+}
+
+pub async fn promote_draft(
+    State(state): State<ApplicationState>,
+    user: Option<User>,
+    Path(draft_uid): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    let submission = crate::router::drafts::submit_promotion(&state, user, draft_uid).await?;
+
+    let status = match submission {
+        Submission::Applied => StatusCode::NO_CONTENT,
+        Submission::Pending => StatusCode::ACCEPTED,
+    };
+
+    Ok(status)
+}
+
+async fn draft_already_promoted_or_missing(pool: &sqlx::PgPool, draft_uid: Uuid) -> ApiError {
+    let exists = crate::persistence::fetch_draft(pool, draft_uid)
+        .await
+        .expect("failed to fetch draft")
+        .is_some();
+
+    if exists {
+        draft_already_promoted()
+    } else {
+        draft_not_found()
+    }
+}
+
+/// How an origin has fared from one of the locations checking it.
+#[derive(Serialize)]
+pub struct Region {
+    location: String,
+    operational: bool,
+    /// The percentage of checks from the location that passed.
+    uptime_percent: f64,
+    queried_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct RegionBreakdown {
+    /// Whether some or all of the locations see the origin failing.
+    outage: Option<Outage>,
+    /// How far back uptime is measured.
+    window_hours: i64,
+    regions: Vec<Region>,
+}
+
+/// Breaks the current status and uptime of an origin down by the locations checking it.
+pub async fn origin_regions(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+) -> Result<Json<RegionBreakdown>, ApiError> {
+    crate::persistence::fetch_origin(&pool, origin_uid)
+        .await
+        .expect("failed to fetch origin")
+        .ok_or_else(origin_not_found)?;
+
+    let locations = crate::persistence::fetch_location_statuses(
+        &pool,
+        Some(origin_uid),
+        Utc::now() - regions::WINDOW,
+    )
+    .await
+    .expect("failed to fetch location statuses");
+
+    Ok(Json(RegionBreakdown {
+        outage: Outage::of(&locations),
+        window_hours: regions::WINDOW.num_hours(),
+        regions: locations
+            .into_iter()
+            .map(|location| Region {
+                location: location.location,
+                operational: location.operational,
+                uptime_percent: location.uptime * 100.0,
+                queried_at: location.queried_at,
+            })
+            .collect(),
+    }))
+}
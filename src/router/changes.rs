@@ -0,0 +1,227 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::Redirect;
+use axum::Json;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::approval::{ChangeKind, Decision, DecisionOutcome, PolicyViolation};
+use crate::persistence::PendingChange;
+use crate::router::identity::User;
+use crate::router::ApplicationState;
+use crate::templates::RenderedTemplate;
+
+/// The number of changes to show, including those that have already been decided.
+const CHANGE_HISTORY_LENGTH: i64 = 50;
+
+type ChangeError = (StatusCode, String);
+
+fn rejection(violation: PolicyViolation) -> ChangeError {
+    let status = match violation {
+        PolicyViolation::Unauthenticated => StatusCode::UNAUTHORIZED,
+        PolicyViolation::NotAdmin | PolicyViolation::SelfApproval => StatusCode::FORBIDDEN,
+    };
+
+    (status, violation.to_string())
+}
+
+pub enum Submission {
+    /// The change was applied immediately.
+    Applied,
+    /// The change is waiting for a second admin to approve it.
+    Pending,
+}
+
+/// Applies a destructive change, or records it for approval if the policy requires one.
+pub async fn submit(
+    state: &ApplicationState,
+    user: Option<User>,
+    kind: ChangeKind,
+    target_uid: Uuid,
+    description: String,
+) -> Result<Submission, ChangeError> {
+    let ApplicationState {
+        pool,
+        approval_policy,
+        ..
+    } = state;
+
+    if !approval_policy.required() {
+        crate::approval::apply_now(pool, kind, target_uid)
+            .await
+            .expect("failed to apply change");
+
+        return Ok(Submission::Applied);
+    }
+
+    let requested_by = user.map(|User(user)| user);
+
+    approval_policy
+        .check_request(requested_by.as_deref())
+        .map_err(rejection)?;
+
+    let requested_by = requested_by.unwrap_or_default();
+
+    let change_uid = crate::persistence::insert_pending_change(
+        pool,
+        kind.as_str(),
+        target_uid,
+        &description,
+        &requested_by,
+    )
+    .await
+    .expect("failed to record pending change");
+
+    // A missing identifier means the same change is already waiting for approval
+    if let Some(change_uid) = change_uid {
+        tracing::info!(%change_uid, kind = kind.as_str(), %target_uid, %requested_by, "requested a change");
+    }
+
+    Ok(Submission::Pending)
+}
+
+impl Submission {
+    /// Where to send the user after submitting a change from the dashboard.
+    pub fn redirect(&self) -> Redirect {
+        match self {
+            Self::Applied => Redirect::to("/"),
+            Self::Pending => Redirect::to("/changes"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ChangeRow {
+    change_uid: Uuid,
+    description: String,
+    status: String,
+    requested_by: String,
+    requested_at: String,
+    decided_by: Option<String>,
+    decided_at: Option<String>,
+}
+
+impl From<PendingChange> for ChangeRow {
+    fn from(change: PendingChange) -> Self {
+        Self {
+            change_uid: change.change_uid,
+            description: change.description,
+            status: change.status,
+            requested_by: change.requested_by,
+            requested_at: change.requested_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+            decided_by: change.decided_by,
+            decided_at: change
+                .decided_at
+                .map(|at| at.format("%Y-%m-%d %H:%M UTC").to_string()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ChangesContext {
+    changes: Vec<ChangeRow>,
+    approval_required: bool,
+}
+
+pub async fn changes_page(
+    State(ApplicationState {
+        pool,
+        template_engine,
+        approval_policy,
+        ..
+    }): State<ApplicationState>,
+) -> RenderedTemplate {
+    let changes = crate::persistence::fetch_pending_changes(&pool, CHANGE_HISTORY_LENGTH)
+        .await
+        .expect("failed to fetch pending changes")
+        .into_iter()
+        .map(ChangeRow::from)
+        .collect();
+
+    let context = ChangesContext {
+        changes,
+        approval_required: approval_policy.required(),
+    };
+
+    template_engine
+        .render_serialized("changes.tera.html", &context)
+        .expect("failed to render template")
+}
+
+async fn decide(
+    state: &ApplicationState,
+    user: Option<User>,
+    change_uid: Uuid,
+    decision: Decision,
+) -> Result<(), ChangeError> {
+    let user = user.map(|User(user)| user);
+
+    let outcome = crate::approval::decide(
+        &state.pool,
+        &state.approval_policy,
+        user.as_deref(),
+        change_uid,
+        decision,
+    )
+    .await
+    .expect("failed to decide on change");
+
+    match outcome {
+        DecisionOutcome::Decided => Ok(()),
+        DecisionOutcome::NotFound => Err((
+            StatusCode::NOT_FOUND,
+            String::from("change not found or already decided"),
+        )),
+        DecisionOutcome::Forbidden(violation) => Err(rejection(violation)),
+    }
+}
+
+pub async fn approve(
+    State(state): State<ApplicationState>,
+    user: Option<User>,
+    Path(change_uid): Path<Uuid>,
+) -> Result<Redirect, ChangeError> {
+    decide(&state, user, change_uid, Decision::Approve).await?;
+
+    Ok(Redirect::to("/changes"))
+}
+
+pub async fn reject(
+    State(state): State<ApplicationState>,
+    user: Option<User>,
+    Path(change_uid): Path<Uuid>,
+) -> Result<Redirect, ChangeError> {
+    decide(&state, user, change_uid, Decision::Reject).await?;
+
+    Ok(Redirect::to("/changes"))
+}
+
+pub async fn list(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+) -> Json<Vec<PendingChange>> {
+    let changes = crate::persistence::fetch_pending_changes(&pool, CHANGE_HISTORY_LENGTH)
+        .await
+        .expect("failed to fetch pending changes");
+
+    Json(changes)
+}
+
+pub async fn approve_api(
+    State(state): State<ApplicationState>,
+    user: Option<User>,
+    Path(change_uid): Path<Uuid>,
+) -> Result<StatusCode, ChangeError> {
+    decide(&state, user, change_uid, Decision::Approve).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn reject_api(
+    State(state): State<ApplicationState>,
+    user: Option<User>,
+    Path(change_uid): Path<Uuid>,
+) -> Result<StatusCode, ChangeError> {
+    decide(&state, user, change_uid, Decision::Reject).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,116 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::Redirect;
+use axum::Json;
+use chrono::Utc;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::poller::{Command, RoundSummary, SchedulerState};
+use crate::router::{time_since, ApplicationState};
+use crate::templates::RenderedTemplate;
+
+fn send(state: &ApplicationState, command: Command) -> Result<Redirect, (StatusCode, String)> {
+    if !state.poller.send(command) {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            String::from("the poller is too busy to take more requests, try again shortly"),
+        ));
+    }
+
+    Ok(Redirect::to("/"))
+}
+
+pub async fn pause(
+    State(state): State<ApplicationState>,
+) -> Result<Redirect, (StatusCode, String)> {
+    send(&state, Command::Pause)
+}
+
+pub async fn resume(
+    State(state): State<ApplicationState>,
+) -> Result<Redirect, (StatusCode, String)> {
+    send(&state, Command::Resume)
+}
+
+pub async fn reload(
+    State(state): State<ApplicationState>,
+) -> Result<Redirect, (StatusCode, String)> {
+    send(&state, Command::Reload)
+}
+
+#[derive(Serialize)]
+pub struct SchedulerResponse {
+    paused: bool,
+    #[serde(flatten)]
+    state: SchedulerState,
+}
+
+pub async fn scheduler(
+    State(ApplicationState { poller, .. }): State<ApplicationState>,
+) -> Json<SchedulerResponse> {
+    Json(SchedulerResponse {
+        paused: poller.is_paused(),
+        state: poller.scheduler(),
+    })
+}
+
+#[derive(Serialize)]
+struct ScheduledRow {
+    origin_uid: Uuid,
+    uri: String,
+    last_checked: Option<String>,
+    /// How long until the next check, or `None` if it is waiting in the current round.
+    next_check_in: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SchedulerContext {
+    paused: bool,
+    running: bool,
+    round_started: Option<String>,
+    last_round_duration_millis: Option<i64>,
+    next_round_in: Option<String>,
+    in_flight: usize,
+    backlog: usize,
+    origins: Vec<ScheduledRow>,
+    last_round: Option<RoundSummary>,
+}
+
+pub async fn scheduler_page(
+    State(ApplicationState {
+        template_engine,
+        poller,
+        ..
+    }): State<ApplicationState>,
+) -> RenderedTemplate {
+    let state = poller.scheduler();
+    let now = Utc::now();
+
+    let origins = state
+        .origins
+        .into_iter()
+        .map(|origin| ScheduledRow {
+            origin_uid: origin.origin_uid,
+            uri: origin.uri,
+            last_checked: origin.last_checked_at.map(time_since),
+            next_check_in: origin.next_check_at.filter(|at| *at > now).map(time_since),
+        })
+        .collect();
+
+    let context = SchedulerContext {
+        paused: poller.is_paused(),
+        running: state.running,
+        round_started: state.round_started_at.map(time_since),
+        last_round_duration_millis: state.last_round_duration_millis,
+        next_round_in: state.next_round_at.filter(|at| *at > now).map(time_since),
+        in_flight: state.in_flight,
+        backlog: state.backlog,
+        origins,
+        last_round: state.last_round,
+    };
+
+    template_engine
+        .render_serialized("scheduler.tera.html", &context)
+        .expect("failed to render template")
+}
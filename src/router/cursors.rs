@@ -0,0 +1,190 @@
+use axum::http::StatusCode;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// The number of entries on a page if the client does not ask for a number.
+const DEFAULT_PAGE_SIZE: i64 = 100;
+
+/// The most entries a client can ask for on a page.
+const MAX_PAGE_SIZE: i64 = 1000;
+
+#[derive(Deserialize)]
+pub struct PageRequest {
+    /// The `next_cursor` of the previous page, or nothing for the first page.
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    cursor: Option<String>,
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    limit: Option<i64>,
+}
+
+impl PageRequest {
+    /// The position the page starts after, decoded from the cursor.
+    pub fn after<P: DeserializeOwned>(&self) -> Result<Option<P>, (StatusCode, String)> {
+        self.cursor.as_deref().map(decode).transpose()
+    }
+
+    pub fn limit(&self) -> Result<i64, (StatusCode, String)> {
+        match self.limit {
+            None => Ok(DEFAULT_PAGE_SIZE),
+            Some(limit @ 1..=MAX_PAGE_SIZE) => Ok(limit),
+            Some(_) => Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("the limit must be between 1 and {MAX_PAGE_SIZE}"),
+            )),
+        }
+    }
+}
+
+/// A page of entries, along with the cursor to fetch the next page with if there is one.
+#[derive(Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// Builds a page from up to `limit + 1` entries, where the extra entry only shows that there
+    /// is another page.
+    pub fn new<P: Serialize>(mut items: Vec<T>, limit: i64, position: impl Fn(&T) -> P) -> Self {
+        let limit = usize::try_from(limit).unwrap_or_default();
+
+        if items.len() <= limit {
+            return Self {
+                items,
+                next_cursor: None,
+            };
+        }
+
+        items.truncate(limit);
+        let next_cursor = items.last().map(|last| encode(&position(last)));
+
+        Self { items, next_cursor }
+    }
+}
+
+/// Encodes a position as a cursor, which clients treat as opaque.
+fn encode<P: Serialize>(position: &P) -> String {
+    let json = serde_json::to_vec(position).expect("failed to serialize cursor");
+
+    URL_SAFE_NO_PAD.encode(json)
+}
+
+fn decode<P: DeserializeOwned>(cursor: &str) -> Result<P, (StatusCode, String)> {
+    let invalid = || {
+        (
+            StatusCode::BAD_REQUEST,
+            String::from("the cursor is not valid"),
+        )
+    };
+
+    let json = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+
+    serde_json::from_slice(&json).map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use chrono::{Duration, Utc};
+    use color_eyre::eyre::Result;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    use crate::persistence::{HistoryPosition, NotificationPosition};
+    use crate::poller::FailureReason;
+    use crate::router::cursors::{decode, encode, Page, PageRequest};
+
+    #[test]
+    fn cursors_round_trip_positions() {
+        let position = NotificationPosition {
+            created_at: Utc::now(),
+            id: 42,
+        };
+
+        let decoded: NotificationPosition = decode(&encode(&position)).unwrap();
+        assert_eq!(decoded, position);
+
+        let invalid = decode::<NotificationPosition>("not a cursor").unwrap_err();
+        assert_eq!(invalid.0, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn limits_are_bounded() {
+        let request = |limit| PageRequest {
+            cursor: None,
+            limit,
+        };
+
+        assert_eq!(request(None).limit().unwrap(), 100);
+        assert_eq!(request(Some(5)).limit().unwrap(), 5);
+        assert!(request(Some(0)).limit().is_err());
+        assert!(request(Some(1001)).limit().is_err());
+    }
+
+    #[test]
+    fn pages_only_have_a_cursor_if_more_entries_follow() {
+        let page = Page::new(vec![1, 2, 3], 3, |n| *n);
+        assert_eq!(page.items, [1, 2, 3]);
+        assert_eq!(page.next_cursor, None);
+
+        let page = Page::new(vec![1, 2, 3, 4], 3, |n| *n);
+        assert_eq!(page.items, [1, 2, 3]);
+        assert_eq!(page.next_cursor, Some(encode(&3)));
+    }
+
+    #[sqlx::test]
+    async fn history_pages_cover_every_check_once(pool: PgPool) -> Result<()> {
+        let origin_uid = Uuid::new_v4();
+        crate::persistence::insert_origin(&pool, origin_uid, "https://example.com").await?;
+
+        // Successes and failures share times, so pages have to order them consistently
+        let start = Utc::now() - Duration::hours(1);
+
+        let mut tx = pool.begin().await?;
+        for minute in 0..5 {
+            let queried_at = start + Duration::minutes(minute);
+
+            crate::persistence::insert_query(&mut tx, origin_uid, Some(200), 50, queried_at)
+                .await?;
+            crate::persistence::insert_query(&mut tx, origin_uid, Some(200), 60, queried_at)
+                .await?;
+            crate::persistence::insert_query_failure(
+                &mut tx,
+                origin_uid,
+                FailureReason::RequestTimeout,
+                None,
+                queried_at,
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        let mut seen = Vec::new();
+        let mut after: Option<HistoryPosition> = None;
+
+        loop {
+            let entries =
+                crate::persistence::fetch_history_page(&pool, origin_uid, after, 4).await?;
+
+            let Some(last) = entries.last() else {
+                break;
+            };
+
+            after = Some(last.position());
+            seen.extend(entries.iter().map(|entry| entry.position()));
+        }
+
+        assert_eq!(seen.len(), 15);
+
+        let mut sorted = seen.clone();
+        sorted.sort_by_key(|position| {
+            std::cmp::Reverse((position.queried_at, position.failed, position.id))
+        });
+        sorted.dedup();
+        assert_eq!(sorted, seen);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,370 @@
+use std::collections::HashSet;
+
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::Level;
+
+use crate::diagnostics::{ConfigurationValue, InternalError, RecentErrors, COMMIT, VERSION};
+use crate::persistence::{AppliedMigration, IndexSize, TableSize};
+use crate::router::identity::User;
+use crate::router::origins::format_bytes;
+use crate::router::{time_since, ApplicationState};
+use crate::templates::RenderedTemplate;
+
+#[derive(Serialize)]
+struct BuildInfo {
+    version: &'static str,
+    commit: Option<&'static str>,
+    profile: &'static str,
+    uptime: String,
+}
+
+#[derive(Serialize)]
+struct PoolStats {
+    connections: u32,
+    idle_connections: usize,
+    max_connections: u32,
+    database_size: String,
+}
+
+#[derive(Serialize)]
+struct PollerStatus {
+    paused: bool,
+    running: bool,
+    in_flight: usize,
+    backlog: usize,
+    origins: usize,
+    last_round_duration_millis: Option<i64>,
+    next_round_in: Option<String>,
+}
+
+#[derive(Serialize)]
+struct NotifierStatus {
+    last_sent: Option<String>,
+    last_failed: Option<String>,
+    last_error: Option<String>,
+    consecutive_failures: u32,
+}
+
+#[derive(Serialize)]
+struct MigrationRow {
+    version: i64,
+    description: String,
+}
+
+#[derive(Serialize)]
+struct MigrationStatus {
+    applied: usize,
+    latest: Option<MigrationRow>,
+    latest_installed: Option<String>,
+    /// Migrations the binary knows about that have not been run.
+    pending: Vec<MigrationRow>,
+    /// Migrations that were started but did not finish.
+    failed: Vec<MigrationRow>,
+}
+
+impl MigrationStatus {
+    fn new(applied: Vec<AppliedMigration>) -> Self {
+        let versions: HashSet<_> = applied.iter().map(|migration| migration.version).collect();
+
+        let pending = sqlx::migrate!()
+            .iter()
+            .filter(|migration| !migration.migration_type.is_down_migration())
+            .filter(|migration| !versions.contains(&migration.version))
+            .map(|migration| MigrationRow {
+                version: migration.version,
+                description: migration.description.to_string(),
+            })
+            .collect();
+
+        let failed = applied
+            .iter()
+            .filter(|migration| !migration.success)
+            .map(|migration| MigrationRow {
+                version: migration.version,
+                description: migration.description.clone(),
+            })
+            .collect();
+
+        let latest = applied.last();
+
+        Self {
+            applied: applied.len(),
+            latest: latest.map(|migration| MigrationRow {
+                version: migration.version,
+                description: migration.description.clone(),
+            }),
+            latest_installed: latest.map(|migration| time_since(migration.installed_on)),
+            pending,
+            failed,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorRow {
+    logged: String,
+    level: String,
+    target: String,
+    message: String,
+    fields: String,
+}
+
+impl From<InternalError> for ErrorRow {
+    fn from(error: InternalError) -> Self {
+        Self {
+            logged: time_since(error.logged_at),
+            level: error.level.to_string(),
+            target: error.target,
+            message: error.message,
+            fields: error.fields,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorSummary {
+    /// How many have been logged since the application started, including those no longer kept.
+    recorded: u64,
+    limit: usize,
+}
+
+impl From<&RecentErrors> for ErrorSummary {
+    fn from(errors: &RecentErrors) -> Self {
+        Self {
+            recorded: errors.recorded(),
+            limit: errors.limit(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TableRow {
+    name: String,
+    estimated_rows: i64,
+    table_size: String,
+    index_size: String,
+    dead_rows: i64,
+    last_analyzed: Option<String>,
+    last_vacuumed: Option<String>,
+}
+
+impl From<TableSize> for TableRow {
+    fn from(table: TableSize) -> Self {
+        Self {
+            name: table.name,
+            estimated_rows: table.estimated_rows,
+            table_size: format_bytes(table.table_bytes),
+            index_size: format_bytes(table.index_bytes),
+            dead_rows: table.dead_rows,
+            last_analyzed: table.last_analyzed_at.map(time_since),
+            last_vacuumed: table.last_vacuumed_at.map(time_since),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct IndexRow {
+    name: String,
+    table_name: String,
+    size: String,
+    scans: i64,
+}
+
+impl From<IndexSize> for IndexRow {
+    fn from(index: IndexSize) -> Self {
+        Self {
+            name: index.name,
+            table_name: index.table_name,
+            size: format_bytes(index.bytes),
+            scans: index.scans,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct DiagnosticsContext {
+    build: BuildInfo,
+    configuration: Vec<ConfigurationValue>,
+    pool: PoolStats,
+    poller: PollerStatus,
+    notifier: NotifierStatus,
+    migrations: MigrationStatus,
+    error_summary: ErrorSummary,
+    errors: Vec<ErrorRow>,
+    tables: Vec<TableRow>,
+    indexes: Vec<IndexRow>,
+}
+
+/// Rejects requests from anyone other than an admin, as diagnostics include the configuration and
+/// internal errors, which are only partly redacted.
+fn require_admin(
+    state: &ApplicationState,
+    user: Option<User>,
+) -> Result<String, (StatusCode, String)> {
+    let Some(User(user)) = user else {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            String::from("the request did not identify a user"),
+        ));
+    };
+
+    if !state.approval_policy.is_admin(&user) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            String::from("only admins can view diagnostics"),
+        ));
+    }
+
+    Ok(user)
+}
+
+pub async fn diagnostics_page(
+    State(state): State<ApplicationState>,
+    user: Option<User>,
+) -> Result<RenderedTemplate, (StatusCode, String)> {
+    let user = require_admin(&state, user)?;
+
+    let ApplicationState {
+        pool,
+        template_engine,
+        poller,
+        recent_errors,
+        started_at,
+        ..
+    } = state;
+
+    let database_bytes = crate::persistence::fetch_database_bytes(&pool)
+        .await
+        .expect("failed to fetch database size");
+
+    let migrations = crate::persistence::fetch_applied_migrations(&pool)
+        .await
+        .expect("failed to fetch applied migrations");
+
+    let tables = crate::persistence::fetch_table_sizes(&pool)
+        .await
+        .expect("failed to fetch table sizes");
+
+    let indexes = crate::persistence::fetch_index_sizes(&pool)
+        .await
+        .expect("failed to fetch index sizes");
+
+    let scheduler = poller.scheduler();
+    let notifier = poller.notifier_health();
+    let now = Utc::now();
+
+    let context = DiagnosticsContext {
+        build: BuildInfo {
+            version: VERSION,
+            commit: COMMIT,
+            profile: if cfg!(debug_assertions) {
+                "debug"
+            } else {
+                "release"
+            },
+            uptime: time_since(started_at),
+        },
+        configuration: crate::diagnostics::configuration(),
+        pool: PoolStats {
+            connections: pool.size(),
+            idle_connections: pool.num_idle(),
+            max_connections: pool.options().get_max_connections(),
+            database_size: format_bytes(database_bytes),
+        },
+        poller: PollerStatus {
+            paused: poller.is_paused(),
+            running: scheduler.running,
+            in_flight: scheduler.in_flight,
+            backlog: scheduler.backlog,
+            origins: scheduler.origins.len(),
+            last_round_duration_millis: scheduler.last_round_duration_millis,
+            next_round_in: scheduler
+                .next_round_at
+                .filter(|at| *at > now)
+                .map(time_since),
+        },
+        notifier: NotifierStatus {
+            last_sent: notifier.last_sent_at.map(time_since),
+            last_failed: notifier.last_failed_at.map(time_since),
+            last_error: notifier.last_error,
+            consecutive_failures: notifier.consecutive_failures,
+        },
+        migrations: MigrationStatus::new(migrations),
+        error_summary: ErrorSummary::from(&recent_errors),
+        errors: recent_errors
+            .recent()
+            .into_iter()
+            .map(ErrorRow::from)
+            .collect(),
+        tables: tables.into_iter().map(TableRow::from).collect(),
+        indexes: indexes.into_iter().map(IndexRow::from).collect(),
+    };
+
+    tracing::info!(%user, "viewed the diagnostics");
+
+    Ok(template_engine
+        .render_serialized("diagnostics.tera.html", &context)
+        .expect("failed to render template"))
+}
+
+#[derive(Deserialize)]
+pub struct ErrorsQuery {
+    /// Only returns errors, leaving out warnings.
+    #[serde(default)]
+    errors_only: bool,
+}
+
+#[derive(Serialize)]
+pub struct ErrorEntry {
+    logged_at: DateTime<Utc>,
+    level: String,
+    target: String,
+    message: String,
+    fields: String,
+}
+
+impl From<InternalError> for ErrorEntry {
+    fn from(error: InternalError) -> Self {
+        Self {
+            logged_at: error.logged_at,
+            level: error.level.to_string(),
+            target: error.target,
+            message: error.message,
+            fields: error.fields,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ErrorsResponse {
+    #[serde(flatten)]
+    summary: ErrorSummary,
+    errors: Vec<ErrorEntry>,
+}
+
+/// Returns the warnings and errors logged recently, newest first, for tooling that watches the
+/// application without access to its logs.
+pub async fn errors(
+    State(state): State<ApplicationState>,
+    user: Option<User>,
+    Query(ErrorsQuery { errors_only }): Query<ErrorsQuery>,
+) -> Result<Json<ErrorsResponse>, (StatusCode, String)> {
+    require_admin(&state, user)?;
+
+    let errors = state
+        .recent_errors
+        .recent()
+        .into_iter()
+        .filter(|error| !errors_only || error.level == Level::ERROR)
+        .map(ErrorEntry::from)
+        .collect();
+
+    Ok(Json(ErrorsResponse {
+        summary: ErrorSummary::from(&state.recent_errors),
+        errors,
+    }))
+}
@@ -0,0 +1,112 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::Redirect;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::approval::ChangeKind;
+use crate::config::ConfigurationDiff;
+use crate::router::changes::{self, Submission};
+use crate::router::identity::User;
+use crate::router::ApplicationState;
+use crate::templates::RenderedTemplate;
+
+#[derive(Serialize)]
+struct DraftContext {
+    draft_uid: Uuid,
+    name: String,
+    created_at: String,
+    promoted_at: Option<String>,
+    diff: ConfigurationDiff,
+    unchanged: bool,
+}
+
+pub async fn review(
+    State(ApplicationState {
+        pool,
+        template_engine,
+        ..
+    }): State<ApplicationState>,
+    Path(draft_uid): Path<Uuid>,
+) -> Result<RenderedTemplate, StatusCode> {
+    let draft = crate::persistence::fetch_draft(&pool, draft_uid)
+        .await
+        .expect("failed to fetch draft")
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let live = crate::persistence::fetch_origin_configurations(&pool)
+        .await
+        .expect("failed to fetch live configuration");
+
+    let diff = crate::config::diff(&live, &draft.document.origins);
+
+    let context = DraftContext {
+        draft_uid,
+        name: draft.name,
+        created_at: draft.created_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+        promoted_at: draft
+            .promoted_at
+            .map(|at| at.format("%Y-%m-%d %H:%M UTC").to_string()),
+        unchanged: diff.is_empty(),
+        diff,
+    };
+
+    let rendered = template_engine
+        .render_serialized("draft.tera.html", &context)
+        .expect("failed to render template");
+
+    Ok(rendered)
+}
+
+/// Promotes a draft, going through the approval flow if promoting it would be destructive.
+pub async fn submit_promotion(
+    state: &ApplicationState,
+    user: Option<User>,
+    draft_uid: Uuid,
+) -> Result<Submission, (StatusCode, String)> {
+    let draft = crate::persistence::fetch_draft(&state.pool, draft_uid)
+        .await
+        .expect("failed to fetch draft")
+        .ok_or((StatusCode::NOT_FOUND, String::from("draft not found")))?;
+
+    if draft.promoted_at.is_some() {
+        return Err((
+            StatusCode::CONFLICT,
+            String::from("draft has already been promoted"),
+        ));
+    }
+
+    let live = crate::persistence::fetch_origin_configurations(&state.pool)
+        .await
+        .expect("failed to fetch live configuration");
+
+    let diff = crate::config::diff(&live, &draft.document.origins);
+    let description = format!("Promote draft '{}'", draft.name);
+
+    if diff.is_destructive() {
+        return changes::submit(
+            state,
+            user,
+            ChangeKind::PromoteDraft,
+            draft_uid,
+            description,
+        )
+        .await;
+    }
+
+    crate::approval::apply_now(&state.pool, ChangeKind::PromoteDraft, draft_uid)
+        .await
+        .expect("failed to promote draft");
+
+    Ok(Submission::Applied)
+}
+
+pub async fn promote(
+    State(state): State<ApplicationState>,
+    user: Option<User>,
+    Path(draft_uid): Path<Uuid>,
+) -> Result<Redirect, (StatusCode, String)> {
+    let submission = submit_promotion(&state, user, draft_uid).await?;
+
+    Ok(submission.redirect())
+}
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::extract::{Path, State};
+use axum::http::header::CONTENT_SECURITY_POLICY;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Result;
+use serde::Serialize;
+use sqlx::PgPool;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::router::{time_since, ApplicationState};
+
+/// The number of checks shown in the uptime bar.
+const UPTIME_BAR_LENGTH: i64 = 90;
+
+/// How long the state of an origin is reused for before it is fetched again, so that a popular wiki
+/// page does not query the database for every viewer.
+const CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Where the embed can be framed from, in `frame-ancestors` syntax.
+const DEFAULT_FRAME_ANCESTORS: &str = "'self'";
+
+#[derive(Clone)]
+pub struct EmbedSettings {
+    frame_ancestors: String,
+    cache: EmbedCache,
+}
+
+impl EmbedSettings {
+    pub fn new(frame_ancestors: String) -> Self {
+        Self {
+            frame_ancestors,
+            cache: EmbedCache::default(),
+        }
+    }
+
+    /// Reads the allowed parents from the space separated `EMBED_FRAME_ANCESTORS`, only allowing
+    /// the dashboard itself to frame embeds if it is not set.
+    pub fn from_env() -> Self {
+        let frame_ancestors = std::env::var("EMBED_FRAME_ANCESTORS")
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .unwrap_or_else(|| String::from(DEFAULT_FRAME_ANCESTORS));
+
+        Self::new(frame_ancestors)
+    }
+}
+
+/// The latest state of an origin, as shown in its embed.
+struct EmbedState {
+    uri: String,
+    operational: Option<bool>,
+    queried_at: Option<DateTime<Utc>>,
+    uptime_bar: Vec<bool>,
+}
+
+/// The state of each origin along with when it was fetched.
+type CachedStates = HashMap<Uuid, (Instant, Arc<EmbedState>)>;
+
+#[derive(Clone, Default)]
+struct EmbedCache {
+    entries: Arc<RwLock<CachedStates>>,
+}
+
+impl EmbedCache {
+    /// Returns the state of the origin, fetching it if it is not cached or the entry is stale. Only
+    /// public origins can be embedded, so private ones are treated as missing.
+    async fn get(&self, pool: &PgPool, origin_uid: Uuid) -> Result<Option<Arc<EmbedState>>> {
+        if let Some((fetched_at, state)) = self.entries.read().await.get(&origin_uid) {
+            if fetched_at.elapsed() < CACHE_TTL {
+                return Ok(Some(Arc::clone(state)));
+            }
+        }
+
+        let Some(uri) = crate::persistence::fetch_public_origin_uri(pool, origin_uid).await? else {
+            return Ok(None);
+        };
+
+        let checks =
+            crate::persistence::fetch_recent_checks(pool, origin_uid, UPTIME_BAR_LENGTH).await?;
+
+        let latest = checks.last();
+        let state = Arc::new(EmbedState {
+            uri,
+            operational: latest.map(|check| check.failure_reason.is_none()),
+            queried_at: latest.map(|check| check.queried_at),
+            uptime_bar: checks
+                .iter()
+                .map(|check| check.failure_reason.is_none())
+                .collect(),
+        });
+
+        let mut entries = self.entries.write().await;
+
+        // Drop anything stale while we hold the lock, so deleted origins do not linger forever
+        entries.retain(|_, (fetched_at, _)| fetched_at.elapsed() < CACHE_TTL);
+        entries.insert(origin_uid, (Instant::now(), Arc::clone(&state)));
+
+        Ok(Some(state))
+    }
+}
+
+#[derive(Serialize)]
+struct EmbedContext<'a> {
+    origin_uid: Uuid,
+    uri: &'a str,
+    operational: Option<bool>,
+    queried: Option<String>,
+    uptime: Option<String>,
+    uptime_bar: &'a [bool],
+}
+
+pub async fn embed(
+    State(ApplicationState {
+        pool,
+        template_engine,
+        embed,
+        ..
+    }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let state = embed
+        .cache
+        .get(&pool, origin_uid)
+        .await
+        .expect("failed to fetch origin state")
+        .ok_or((StatusCode::NOT_FOUND, String::from("origin not found")))?;
+
+    let successes = state.uptime_bar.iter().filter(|success| **success).count();
+    let uptime = (!state.uptime_bar.is_empty())
+        .then(|| successes as f64 / state.uptime_bar.len() as f64 * 100.0)
+        .map(|uptime| format!("{uptime:.1}%"));
+
+    let context = EmbedContext {
+        origin_uid,
+        uri: &state.uri,
+        operational: state.operational,
+        queried: state.queried_at.map(time_since),
+        uptime,
+        uptime_bar: &state.uptime_bar,
+    };
+
+    let rendered = template_engine
+        .render_serialized("embed.tera.html", &context)
+        .expect("failed to render template");
+
+    let policy = format!("frame-ancestors {}", embed.frame_ancestors);
+
+    Ok(([(CONTENT_SECURITY_POLICY, policy)], rendered))
+}
+
+#[cfg(test)]
+mod tests {
+    use color_eyre::eyre::Result;
+    use sqlx::types::chrono::Utc;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    use crate::persistence::Visibility;
+    use crate::router::embed::EmbedCache;
+
+    #[sqlx::test]
+    async fn origin_state_is_reused_until_it_expires(pool: PgPool) -> Result<()> {
+        let cache = EmbedCache::default();
+
+        let origin_uid = Uuid::new_v4();
+        crate::persistence::insert_origin(&pool, origin_uid, "https://example.com").await?;
+        crate::persistence::set_origin_visibility(&pool, origin_uid, Visibility::Public).await?;
+
+        let state = cache.get(&pool, origin_uid).await?.unwrap();
+        assert!(state.uptime_bar.is_empty());
+
+        let mut tx = pool.begin().await?;
+        crate::persistence::insert_query(&mut tx, origin_uid, Some(200), 50, Utc::now()).await?;
+        tx.commit().await?;
+
+        // The check is not visible until the cached state expires
+        let state = cache.get(&pool, origin_uid).await?.unwrap();
+        assert!(state.uptime_bar.is_empty());
+
+        assert!(cache.get(&pool, Uuid::new_v4()).await?.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn private_origins_cannot_be_embedded(pool: PgPool) -> Result<()> {
+        let cache = EmbedCache::default();
+
+        let origin_uid = Uuid::new_v4();
+        crate::persistence::insert_origin(&pool, origin_uid, "https://internal.example.com")
+            .await?;
+
+        assert!(cache.get(&pool, origin_uid).await?.is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,357 @@
+use axum::body::Body;
+use axum::extract::{Query, State};
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::http::StatusCode;
+use axum::response::Response;
+use chrono::{DateTime, Days, NaiveDate, NaiveTime, Utc};
+use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
+use serde::Deserialize;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::persistence::{Incident, NotificationExport, Origin};
+use crate::router::ApplicationState;
+
+/// The number of rendered rows that can be buffered before the database stream is paused.
+const CHANNEL_CAPACITY: usize = 64;
+
+type Row = Result<String, sqlx::Error>;
+
+type ExportError = (StatusCode, String);
+
+/// The start of the first day to include and the end of the last, either of which may be open.
+type Bounds = (Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+
+#[derive(Deserialize)]
+pub struct DateRange {
+    /// The first day to include in the export.
+    from: Option<NaiveDate>,
+    /// The last day to include in the export.
+    to: Option<NaiveDate>,
+}
+
+impl DateRange {
+    fn bounds(&self) -> Result<Bounds, ExportError> {
+        let start = self
+            .from
+            .map(|date| date.and_time(NaiveTime::MIN).and_utc());
+
+        // `to` is inclusive, so the exclusive bound is the start of the following day
+        let end = self
+            .to
+            .map(|date| {
+                date.checked_add_days(Days::new(1)).ok_or((
+                    StatusCode::BAD_REQUEST,
+                    String::from("the end of the range is too far in the future"),
+                ))
+            })
+            .transpose()?
+            .map(|date| date.and_time(NaiveTime::MIN).and_utc());
+
+        Ok((start, end))
+    }
+}
+
+trait CsvRecord {
+    const HEADER: &'static [&'static str];
+
+    fn fields(&self) -> Vec<String>;
+}
+
+impl CsvRecord for NotificationExport {
+    const HEADER: &'static [&'static str] = &[
+        "notification_uid",
+        "origin_uid",
+        "uri",
+        "topic",
+        "subject",
+        "message",
+        "created_at",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.notification_uid.to_string(),
+            self.origin_uid.to_string(),
+            self.uri.clone(),
+            self.topic.clone(),
+            self.subject.clone(),
+            self.message.clone(),
+            self.created_at.to_rfc3339(),
+        ]
+    }
+}
+
+impl CsvRecord for Incident {
+    const HEADER: &'static [&'static str] = &[
+        "origin_uid",
+        "uri",
+        "started_at",
+        "resolved_at",
+        "duration_seconds",
+        "failure_count",
+        "estimated_cost",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        let resolved_at = self.resolved_at.map(|r| r.to_rfc3339());
+        let duration = self
+            .resolved_at
+            .map(|r| (r - self.started_at).num_seconds().to_string());
+
+        let estimated_cost = self
+            .estimated_cost_cents()
+            .map(|cents| format!("{:.2}", cents as f64 / 100.0));
+
+        vec![
+            self.origin_uid.to_string(),
+            self.uri.clone(),
+            self.started_at.to_rfc3339(),
+            resolved_at.unwrap_or_default(),
+            duration.unwrap_or_default(),
+            self.failure_count.to_string(),
+            estimated_cost.unwrap_or_default(),
+        ]
+    }
+}
+
+impl CsvRecord for Origin {
+    const HEADER: &'static [&'static str] = &[
+        "origin_uid",
+        "uri",
+        "expected_status",
+        "expected_latency_millis",
+        "relative_latency_limit_millis",
+        "json_assertion",
+        "forbidden_text",
+        "expected_headers",
+        "expected_location",
+        "expected_networks",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        let expectations = self.expectations();
+
+        vec![
+            self.origin_uid.to_string(),
+            self.uri.clone(),
+            expectations.expected_status.unwrap_or_default(),
+            expectations
+                .expected_latency_millis
+                .map(|latency| latency.to_string())
+                .unwrap_or_default(),
+            expectations
+                .relative_latency_limit_millis
+                .map(|limit| limit.to_string())
+                .unwrap_or_default(),
+            expectations.json_assertion.unwrap_or_default(),
+            expectations.forbidden_text.unwrap_or_default(),
+            expectations.expected_headers.unwrap_or_default(),
+            expectations.expected_location.unwrap_or_default(),
+            expectations.expected_networks.unwrap_or_default(),
+        ]
+    }
+}
+
+fn escape(field: &str) -> String {
+    // Spreadsheets evaluate cells starting with these as formulas, which an origin's URI or an
+    // alert message could use to run commands on the machine of whoever opens the export
+    let field = if field.starts_with(['=', '+', '-', '@']) {
+        format!("'{field}")
+    } else {
+        field.to_owned()
+    };
+
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+fn to_line<S: AsRef<str>>(fields: &[S]) -> String {
+    let mut line = fields
+        .iter()
+        .map(|field| escape(field.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    line.push_str("\r\n");
+    line
+}
+
+async fn forward_rows<R: CsvRecord>(mut rows: BoxStream<'_, sqlx::Result<R>>, tx: Sender<Row>) {
+    if tx.send(Ok(to_line(R::HEADER))).await.is_err() {
+        return;
+    }
+
+    while let Some(row) = rows.next().await {
+        let row = row.map(|record| to_line(record.fields().as_slice()));
+        let failed = row.is_err();
+
+        // Stop if the client has gone away or the database stream has errored
+        if tx.send(row).await.is_err() || failed {
+            break;
+        }
+    }
+}
+
+fn csv_response(filename: &str, rx: Receiver<Row>) -> Response {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "text/csv")
+        .header(
+            CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{filename}\""),
+        )
+        .body(Body::from_stream(ReceiverStream::new(rx)))
+        .unwrap()
+}
+
+pub async fn notifications(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Query(range): Query<DateRange>,
+) -> Result<Response, ExportError> {
+    let (start, end) = range.bounds()?;
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+
+    tokio::spawn(async move {
+        let rows = crate::persistence::stream_notifications(&pool, start, end);
+        forward_rows(rows, tx).await;
+    });
+
+    Ok(csv_response("notifications.csv", rx))
+}
+
+pub async fn incidents(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Query(range): Query<DateRange>,
+) -> Result<Response, ExportError> {
+    let (start, end) = range.bounds()?;
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+
+    tokio::spawn(async move {
+        let rows = crate::persistence::stream_incidents(&pool, start, end);
+        forward_rows(rows, tx).await;
+    });
+
+    Ok(csv_response("incidents.csv", rx))
+}
+
+/// Exports every origin with what its checks are expected to see, such as statuses, latency and
+/// assertions, so the results can be read against the configuration.
+pub async fn origins(State(ApplicationState { pool, .. }): State<ApplicationState>) -> Response {
+    let mut origins = crate::persistence::fetch_origins(&pool)
+        .await
+        .expect("failed to fetch origins");
+
+    origins.sort_by(|a, b| a.uri.cmp(&b.uri));
+
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+
+    tokio::spawn(async move {
+        let rows = futures_util::stream::iter(origins.into_iter().map(Ok)).boxed();
+        forward_rows(rows, tx).await;
+    });
+
+    csv_response("origins.csv", rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use color_eyre::eyre::Result;
+    use futures_util::TryStreamExt;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    use crate::persistence::AlertKind;
+    use crate::router::export::{to_line, DateRange};
+
+    #[test]
+    fn fields_are_only_quoted_when_they_need_to_be() {
+        let line = to_line(&["plain", "a,b", "say \"hi\"", "two\nlines", ""]);
+
+        assert_eq!(line, "plain,\"a,b\",\"say \"\"hi\"\"\",\"two\nlines\",\r\n");
+    }
+
+    #[test]
+    fn fields_that_look_like_formulas_are_escaped() {
+        let line = to_line(&[
+            "=HYPERLINK(\"https://evil.com\")",
+            "+1",
+            "-2",
+            "@SUM(A1)",
+            "a=b",
+        ]);
+
+        assert_eq!(
+            line,
+            "\"'=HYPERLINK(\"\"https://evil.com\"\")\",'+1,'-2,'@SUM(A1),a=b\r\n"
+        );
+    }
+
+    #[test]
+    fn ranges_ending_too_far_in_the_future_are_rejected() {
+        let range = DateRange {
+            from: None,
+            to: Some(NaiveDate::MAX),
+        };
+
+        assert!(range.bounds().is_err());
+    }
+
+    #[sqlx::test]
+    async fn ranges_include_the_whole_of_the_last_day(pool: PgPool) -> Result<()> {
+        let origin_uid = Uuid::new_v4();
+        crate::persistence::insert_origin(&pool, origin_uid, "https://example.com").await?;
+
+        let mut tx = pool.begin().await?;
+        for (subject, created_at) in [
+            (
+                "before",
+                Utc.with_ymd_and_hms(2026, 10, 14, 23, 59, 59).unwrap(),
+            ),
+            (
+                "first",
+                Utc.with_ymd_and_hms(2026, 10, 15, 0, 0, 0).unwrap(),
+            ),
+            (
+                "last",
+                Utc.with_ymd_and_hms(2026, 10, 16, 23, 59, 59).unwrap(),
+            ),
+            (
+                "after",
+                Utc.with_ymd_and_hms(2026, 10, 17, 0, 0, 0).unwrap(),
+            ),
+        ] {
+            crate::persistence::insert_notification(
+                &mut tx,
+                origin_uid,
+                AlertKind::Outage,
+                "topic",
+                subject,
+                "message",
+                created_at,
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        let range = DateRange {
+            from: NaiveDate::from_ymd_opt(2026, 10, 15),
+            to: NaiveDate::from_ymd_opt(2026, 10, 16),
+        };
+
+        let (start, end) = range.bounds().unwrap();
+        let exported: Vec<_> = crate::persistence::stream_notifications(&pool, start, end)
+            .map_ok(|notification| notification.subject)
+            .try_collect()
+            .await?;
+
+        assert_eq!(exported, ["first", "last"]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::Redirect;
+use axum::Form;
+use chrono::Utc;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::poller::Command;
+use crate::router::ApplicationState;
+
+/// How long a heartbeat can go without a ping if no grace period is given.
+pub(super) const DEFAULT_GRACE_MINUTES: i32 = 60;
+
+/// The longest grace period a heartbeat can have, enough for monthly jobs.
+pub(super) const MAX_GRACE_MINUTES: i32 = 31 * 24 * 60;
+
+/// Creates a token for a heartbeat's ping URL, long enough that it cannot be guessed.
+pub(super) fn generate_token() -> String {
+    hex::encode(rand::random::<[u8; 16]>())
+}
+
+pub(super) fn validate_grace(grace_minutes: i32) -> Result<(), (StatusCode, String)> {
+    if !(1..=MAX_GRACE_MINUTES).contains(&grace_minutes) {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("the grace period must be between 1 and {MAX_GRACE_MINUTES} minutes"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Records a ping from the job a heartbeat monitors. The token is the only thing identifying the
+/// caller, so this does not require a user.
+pub async fn ping(
+    State(ApplicationState { pool, poller, .. }): State<ApplicationState>,
+    Path(token): Path<String>,
+) -> StatusCode {
+    let origin_uid = crate::persistence::record_heartbeat_ping(&pool, &token, Utc::now())
+        .await
+        .expect("failed to record heartbeat ping");
+
+    let Some(origin_uid) = origin_uid else {
+        return StatusCode::NOT_FOUND;
+    };
+
+    // Check straight away so a heartbeat that was failing recovers without waiting for the round,
+    // but the ping itself has already been recorded if the poller is busy
+    poller.send(Command::CheckNow(origin_uid));
+
+    StatusCode::OK
+}
+
+/// Replaces the token of an origin's ping URL, or creates one if it does not have one yet.
+pub async fn regenerate_token(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+) -> Redirect {
+    crate::persistence::upsert_heartbeat(
+        &pool,
+        origin_uid,
+        &generate_token(),
+        DEFAULT_GRACE_MINUTES * 60,
+        Utc::now(),
+    )
+    .await
+    .expect("failed to regenerate heartbeat token");
+
+    Redirect::to(&format!("/origins/{origin_uid}"))
+}
+
+#[derive(Deserialize)]
+pub struct GraceUpdateRequest {
+    grace_minutes: i32,
+}
+
+pub async fn update_grace(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(GraceUpdateRequest { grace_minutes }): Form<GraceUpdateRequest>,
+) -> Result<Redirect, (StatusCode, String)> {
+    validate_grace(grace_minutes)?;
+
+    crate::persistence::set_heartbeat_grace(&pool, origin_uid, grace_minutes * 60)
+        .await
+        .expect("failed to set heartbeat grace period");
+
+    Ok(Redirect::to(&format!("/origins/{origin_uid}")))
+}
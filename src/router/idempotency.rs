@@ -0,0 +1,309 @@
+use axum::body::{Body, Bytes};
+use axum::extract::{Request, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+
+use crate::persistence::IdempotencyRecord;
+use crate::router::{identity, ApplicationState};
+
+/// The header clients send with a mutation so retries of it are only carried out once.
+const IDEMPOTENCY_KEY: &str = "idempotency-key";
+
+/// The header set on responses replayed from an earlier request with the same key.
+const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
+
+/// The longest key accepted, which is plenty for a UUID or similar.
+const MAX_KEY_LENGTH: usize = 255;
+
+/// The largest request or response body buffered, matching the default limit of the extractors.
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// How long a key is remembered for, after which it can be used again.
+const KEY_LIFETIME_HOURS: i64 = 24;
+
+/// How long a request can go without finishing before its key is assumed to have been abandoned,
+/// such as by the server restarting part way through.
+const ABANDONED_AFTER_MINUTES: i64 = 5;
+
+/// Checks that a key is printable ASCII without spaces and no longer than [`MAX_KEY_LENGTH`].
+fn validate_key(key: &HeaderValue) -> Result<&str, &'static str> {
+    let key = key
+        .to_str()
+        .map_err(|_| "the Idempotency-Key must be printable ASCII")?;
+
+    if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+        return Err("the Idempotency-Key must be between 1 and 255 characters");
+    }
+
+    if !key.bytes().all(|byte| byte.is_ascii_graphic()) {
+        return Err("the Idempotency-Key must be printable ASCII without spaces");
+    }
+
+    Ok(key)
+}
+
+/// Hashes what a request would do, so a key cannot be reused for a different one.
+fn request_hash(method: &Method, path: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+
+    hasher.update(method.as_str());
+    hasher.update(b"\n");
+    hasher.update(path);
+    hasher.update(b"\n");
+    hasher.update(body);
+
+    hex::encode(hasher.finalize())
+}
+
+fn replay(record: IdempotencyRecord) -> Response {
+    let Some(status) = record
+        .status
+        .and_then(|status| StatusCode::from_u16(status as u16).ok())
+    else {
+        return (
+            StatusCode::CONFLICT,
+            "a request with this Idempotency-Key is still being processed",
+        )
+            .into_response();
+    };
+
+    let mut response = Response::new(Body::from(record.response_body.unwrap_or_default()));
+    *response.status_mut() = status;
+
+    let headers = response.headers_mut();
+    headers.insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+
+    if let Some(content_type) = record
+        .content_type
+        .and_then(|content_type| HeaderValue::from_str(&content_type).ok())
+    {
+        headers.insert(CONTENT_TYPE, content_type);
+    }
+
+    response
+}
+
+/// Carries out POST and PUT requests with an `Idempotency-Key` header at most once, replaying the
+/// response of the first to any retries with the same key by the same user.
+///
+/// Keys cannot be reused for a different request, and are forgotten if the first request fails
+/// with a server error so it can be retried.
+pub async fn idempotent(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !matches!(*request.method(), Method::POST | Method::PUT) {
+        return next.run(request).await;
+    }
+
+    let Some(key) = request.headers().get(IDEMPOTENCY_KEY) else {
+        return next.run(request).await;
+    };
+
+    let key = match validate_key(key) {
+        Ok(key) => key.to_owned(),
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
+    // Keys are scoped to the user, so nobody else can be replayed the response to their request
+    let username = identity::username(request.headers())
+        .unwrap_or_default()
+        .to_owned();
+
+    let (parts, body) = request.into_parts();
+
+    let Ok(body) = axum::body::to_bytes(body, MAX_BODY_BYTES).await else {
+        return (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "the request body is too large",
+        )
+            .into_response();
+    };
+
+    let path = parts
+        .uri
+        .path_and_query()
+        .map_or_else(|| parts.uri.path(), |path| path.as_str());
+    let hash = request_hash(&parts.method, path, &body);
+
+    let now = Utc::now();
+
+    crate::persistence::delete_expired_idempotency_keys(
+        &pool,
+        now - chrono::Duration::hours(KEY_LIFETIME_HOURS),
+        now - chrono::Duration::minutes(ABANDONED_AFTER_MINUTES),
+    )
+    .await
+    .expect("failed to delete expired idempotency keys");
+
+    let existing = crate::persistence::claim_idempotency_key(&pool, &username, &key, &hash, now)
+        .await
+        .expect("failed to claim idempotency key");
+
+    if let Some(record) = existing {
+        if record.request_hash != hash {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "the Idempotency-Key has already been used for a different request",
+            )
+                .into_response();
+        }
+
+        tracing::info!(%key, %username, "replaying the response to an idempotent request");
+
+        return replay(record);
+    }
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+
+    let (parts, body) = response.into_parts();
+
+    let body: Bytes = match axum::body::to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(body) if !parts.status.is_server_error() => body,
+        result => {
+            crate::persistence::release_idempotency_key(&pool, &username, &key)
+                .await
+                .expect("failed to release idempotency key");
+
+            let body = result.unwrap_or_default();
+            return Response::from_parts(parts, Body::from(body));
+        }
+    };
+
+    let content_type = parts
+        .headers
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok());
+
+    crate::persistence::complete_idempotency_key(
+        &pool,
+        &username,
+        &key,
+        parts.status.as_u16() as i16,
+        content_type,
+        &body,
+        Utc::now(),
+    )
+    .await
+    .expect("failed to complete idempotency key");
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{HeaderValue, Method};
+    use chrono::{Duration, Utc};
+    use color_eyre::eyre::Result;
+    use sqlx::PgPool;
+
+    #[test]
+    fn keys_are_validated() {
+        let key = HeaderValue::from_static("5f0c6a9e-8d1b-4c3e-9a7f-2b6d4e8f1a3c");
+        assert!(super::validate_key(&key).is_ok());
+
+        assert!(super::validate_key(&HeaderValue::from_static("")).is_err());
+        assert!(super::validate_key(&HeaderValue::from_static("with spaces")).is_err());
+
+        let long = HeaderValue::from_str(&"a".repeat(256)).unwrap();
+        assert!(super::validate_key(&long).is_err());
+    }
+
+    #[test]
+    fn requests_are_hashed_by_what_they_do() {
+        let hash = super::request_hash(&Method::POST, "/api/v1/drafts", b"{}");
+
+        assert_eq!(
+            hash,
+            super::request_hash(&Method::POST, "/api/v1/drafts", b"{}")
+        );
+        assert_ne!(
+            hash,
+            super::request_hash(&Method::PUT, "/api/v1/drafts", b"{}")
+        );
+        assert_ne!(
+            hash,
+            super::request_hash(&Method::POST, "/api/v1/drafts", b"{\"name\":\"\"}")
+        );
+    }
+
+    #[sqlx::test]
+    async fn keys_are_claimed_once_until_they_expire(pool: PgPool) -> Result<()> {
+        let now = Utc::now();
+
+        let first =
+            crate::persistence::claim_idempotency_key(&pool, "alice", "key", "hash", now).await?;
+        assert!(first.is_none());
+
+        // Retries before the first request finishes are told it is still in progress
+        let retry =
+            crate::persistence::claim_idempotency_key(&pool, "alice", "key", "hash", now).await?;
+        assert!(retry.is_some_and(|record| record.status.is_none()));
+
+        crate::persistence::complete_idempotency_key(
+            &pool,
+            "alice",
+            "key",
+            201,
+            Some("application/json"),
+            b"{}",
+            now,
+        )
+        .await?;
+
+        let retry = crate::persistence::claim_idempotency_key(&pool, "alice", "key", "hash", now)
+            .await?
+            .unwrap();
+
+        assert_eq!(retry.status, Some(201));
+        assert_eq!(retry.response_body.as_deref(), Some(&b"{}"[..]));
+
+        // Once the key has expired it can be claimed afresh
+        crate::persistence::delete_expired_idempotency_keys(&pool, now + Duration::seconds(1), now)
+            .await?;
+
+        let reused =
+            crate::persistence::claim_idempotency_key(&pool, "alice", "key", "other", now).await?;
+        assert!(reused.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn keys_are_scoped_to_the_user(pool: PgPool) -> Result<()> {
+        let now = Utc::now();
+
+        crate::persistence::claim_idempotency_key(&pool, "alice", "key", "hash", now).await?;
+        crate::persistence::complete_idempotency_key(
+            &pool,
+            "alice",
+            "key",
+            200,
+            Some("application/json"),
+            b"{\"approved\":true}",
+            now,
+        )
+        .await?;
+
+        // Someone else using the same key makes their own request rather than seeing the response
+        let other =
+            crate::persistence::claim_idempotency_key(&pool, "bob", "key", "hash", now).await?;
+        assert!(other.is_none());
+
+        // Releasing their key leaves the first one alone
+        crate::persistence::release_idempotency_key(&pool, "bob", "key").await?;
+
+        let retry = crate::persistence::claim_idempotency_key(&pool, "alice", "key", "hash", now)
+            .await?
+            .unwrap();
+
+        assert_eq!(retry.status, Some(200));
+
+        Ok(())
+    }
+}
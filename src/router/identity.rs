@@ -0,0 +1,30 @@
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, StatusCode};
+
+/// The header set by the authenticating reverse proxy in front of the dashboard.
+const USER_HEADER: &str = "x-forwarded-user";
+
+/// The user making a request, as identified by the reverse proxy.
+pub struct User(pub String);
+
+/// Reads the user from the headers of a request, if the reverse proxy identified one.
+pub fn username(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(USER_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for User {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        let user = username(&parts.headers).ok_or(StatusCode::UNAUTHORIZED)?;
+
+        Ok(Self(user.to_owned()))
+    }
+}
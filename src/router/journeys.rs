@@ -0,0 +1,90 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::Redirect;
+use axum::Form;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::persistence::{HttpMethod, JourneyStep};
+use crate::router::ApplicationState;
+
+/// The most steps a journey can have, so one origin cannot hold up a round for long.
+pub(super) const MAX_STEPS: usize = 10;
+
+#[derive(Deserialize)]
+pub struct StepCreationRequest {
+    #[serde(default)]
+    method: HttpMethod,
+    url: String,
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    request_headers: Option<String>,
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    request_body: Option<String>,
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    request_content_type: Option<String>,
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    expected_status: Option<String>,
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    extraction: Option<String>,
+}
+
+/// Adds a step to the end of a journey.
+pub async fn add_step(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(StepCreationRequest {
+        method,
+        url,
+        request_headers,
+        request_body,
+        request_content_type,
+        expected_status,
+        extraction,
+    }): Form<StepCreationRequest>,
+) -> Result<Redirect, (StatusCode, String)> {
+    let steps = crate::persistence::fetch_journey_steps(&pool, origin_uid)
+        .await
+        .expect("failed to fetch journey steps");
+
+    if steps.len() >= MAX_STEPS {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("journeys can have at most {MAX_STEPS} steps"),
+        ));
+    }
+
+    let step = JourneyStep {
+        journey_step_uid: Uuid::new_v4(),
+        method,
+        url,
+        request_headers,
+        request_body,
+        request_content_type,
+        expected_status,
+        extraction,
+    };
+
+    crate::poller::validate_step(&step).map_err(|e| {
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("invalid journey step: {e}"),
+        )
+    })?;
+
+    crate::persistence::insert_journey_step(&pool, origin_uid, &step)
+        .await
+        .expect("failed to insert journey step");
+
+    Ok(Redirect::to(&format!("/origins/{origin_uid}")))
+}
+
+pub async fn delete_step(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path((origin_uid, journey_step_uid)): Path<(Uuid, Uuid)>,
+) -> Redirect {
+    crate::persistence::delete_journey_step(&pool, origin_uid, journey_step_uid)
+        .await
+        .expect("failed to delete journey step");
+
+    Redirect::to(&format!("/origins/{origin_uid}"))
+}
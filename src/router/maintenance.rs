@@ -0,0 +1,287 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::Redirect;
+use axum::Form;
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::persistence::{MaintenanceMode, MaintenanceWindow, Recurrence};
+use crate::router::ApplicationState;
+use crate::templates::RenderedTemplate;
+
+/// The format of `datetime-local` inputs, whose values are taken to be in UTC.
+const INPUT_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+#[derive(Serialize)]
+struct WindowRow {
+    maintenance_window_uid: Uuid,
+    /// The URI of the origin the window covers, or the tag it covers every origin with.
+    origin: Option<String>,
+    tag: Option<String>,
+    starts_at: String,
+    ends_at: String,
+    recurrence: &'static str,
+    mode: &'static str,
+    description: Option<String>,
+    active: bool,
+}
+
+#[derive(Serialize)]
+struct OriginOption {
+    origin_uid: Uuid,
+    uri: String,
+}
+
+#[derive(Serialize)]
+struct ChoiceOption {
+    value: &'static str,
+    name: &'static str,
+}
+
+#[derive(Serialize)]
+struct MaintenanceContext {
+    windows: Vec<WindowRow>,
+    origins: Vec<OriginOption>,
+    recurrences: Vec<ChoiceOption>,
+    modes: Vec<ChoiceOption>,
+}
+
+pub async fn maintenance_page(
+    State(ApplicationState {
+        pool,
+        template_engine,
+        ..
+    }): State<ApplicationState>,
+) -> RenderedTemplate {
+    let origins = crate::persistence::fetch_origins(&pool)
+        .await
+        .expect("failed to fetch origins");
+
+    let windows = crate::persistence::fetch_maintenance_windows(&pool)
+        .await
+        .expect("failed to fetch maintenance windows");
+
+    let now = Utc::now();
+
+    let windows = windows
+        .into_iter()
+        .map(|window| WindowRow {
+            maintenance_window_uid: window.maintenance_window_uid,
+            origin: window.origin_uid.and_then(|origin_uid| {
+                origins
+                    .iter()
+                    .find(|origin| origin.origin_uid == origin_uid)
+                    .map(|origin| origin.uri.clone())
+            }),
+            tag: window.tag.clone(),
+            starts_at: window.starts_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+            ends_at: window.ends_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+            recurrence: window.recurrence.name(),
+            mode: window.mode.name(),
+            active: window.is_active(now),
+            description: window.description,
+        })
+        .collect();
+
+    let context = MaintenanceContext {
+        windows,
+        origins: origins
+            .into_iter()
+            .map(|origin| OriginOption {
+                origin_uid: origin.origin_uid,
+                uri: origin.uri,
+            })
+            .collect(),
+        recurrences: Recurrence::ALL
+            .iter()
+            .map(|recurrence| ChoiceOption {
+                value: recurrence.as_str(),
+                name: recurrence.name(),
+            })
+            .collect(),
+        modes: MaintenanceMode::ALL
+            .iter()
+            .map(|mode| ChoiceOption {
+                value: mode.as_str(),
+                name: mode.name(),
+            })
+            .collect(),
+    };
+
+    template_engine
+        .render_serialized("maintenance.tera.html", &context)
+        .expect("failed to render template")
+}
+
+#[derive(Deserialize)]
+pub struct WindowCreationRequest {
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    origin_uid: Option<Uuid>,
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    tag: Option<String>,
+    starts_at: String,
+    ends_at: String,
+    #[serde(default)]
+    recurrence: Recurrence,
+    #[serde(default)]
+    mode: MaintenanceMode,
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    description: Option<String>,
+}
+
+/// Checks that a window covers exactly one origin or tag and ends after it starts, and that
+/// recurring windows end before they next start.
+fn validate_window(window: &MaintenanceWindow) -> Result<(), String> {
+    if window.origin_uid.is_some() == window.tag.is_some() {
+        return Err(String::from("choose either an origin or a tag"));
+    }
+
+    let length = window.ends_at - window.starts_at;
+
+    if length <= chrono::Duration::zero() {
+        return Err(String::from("the window must end after it starts"));
+    }
+
+    if window
+        .recurrence
+        .period()
+        .is_some_and(|period| length >= period)
+    {
+        return Err(format!(
+            "a window repeating {} must be shorter than the time between repeats",
+            window.recurrence.name().to_lowercase()
+        ));
+    }
+
+    Ok(())
+}
+
+pub async fn create_window(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Form(WindowCreationRequest {
+        origin_uid,
+        tag,
+        starts_at,
+        ends_at,
+        recurrence,
+        mode,
+        description,
+    }): Form<WindowCreationRequest>,
+) -> Result<Redirect, (StatusCode, String)> {
+    let parse = |value: &str| {
+        NaiveDateTime::parse_from_str(value, INPUT_FORMAT)
+            .map(|at| at.and_utc())
+            .map_err(|_| {
+                (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    format!("'{value}' is not a date and time such as 2024-01-03T09:30"),
+                )
+            })
+    };
+
+    let window = MaintenanceWindow {
+        maintenance_window_uid: Uuid::new_v4(),
+        origin_uid,
+        tag,
+        starts_at: parse(&starts_at)?,
+        ends_at: parse(&ends_at)?,
+        recurrence,
+        mode,
+        description,
+    };
+
+    validate_window(&window).map_err(|e| {
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("invalid maintenance window: {e}"),
+        )
+    })?;
+
+    crate::persistence::insert_maintenance_window(&pool, &window)
+        .await
+        .expect("failed to insert maintenance window");
+
+    Ok(Redirect::to("/maintenance"))
+}
+
+pub async fn delete_window(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(maintenance_window_uid): Path<Uuid>,
+) -> Redirect {
+    crate::persistence::delete_maintenance_window(&pool, maintenance_window_uid)
+        .await
+        .expect("failed to delete maintenance window");
+
+    Redirect::to("/maintenance")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+    use uuid::Uuid;
+
+    use crate::persistence::{MaintenanceMode, MaintenanceWindow, Recurrence};
+
+    fn window(hours: i64, recurrence: Recurrence) -> MaintenanceWindow {
+        let starts_at = Utc.with_ymd_and_hms(2024, 1, 3, 9, 0, 0).unwrap();
+
+        MaintenanceWindow {
+            maintenance_window_uid: Uuid::new_v4(),
+            origin_uid: Some(Uuid::new_v4()),
+            tag: None,
+            starts_at,
+            ends_at: starts_at + Duration::hours(hours),
+            recurrence,
+            mode: MaintenanceMode::Skip,
+            description: None,
+        }
+    }
+
+    #[test]
+    fn windows_are_validated() {
+        assert_eq!(
+            super::validate_window(&window(2, Recurrence::Daily)),
+            Ok(())
+        );
+        assert_eq!(
+            super::validate_window(&window(48, Recurrence::Once)),
+            Ok(())
+        );
+
+        assert_eq!(
+            super::validate_window(&window(0, Recurrence::Once)),
+            Err(String::from("the window must end after it starts"))
+        );
+        assert_eq!(
+            super::validate_window(&window(24, Recurrence::Daily)),
+            Err(String::from(
+                "a window repeating every day must be shorter than the time between repeats"
+            ))
+        );
+
+        let both = MaintenanceWindow {
+            tag: Some(String::from("production")),
+            ..window(2, Recurrence::Once)
+        };
+
+        assert_eq!(
+            super::validate_window(&both),
+            Err(String::from("choose either an origin or a tag"))
+        );
+    }
+
+    #[test]
+    fn recurring_windows_are_active_each_time_they_repeat() {
+        let window = window(2, Recurrence::Weekly);
+        let at = |day, hour| Utc.with_ymd_and_hms(2024, 1, day, hour, 30, 0).unwrap();
+
+        assert!(window.is_active(at(3, 9)));
+        assert!(!window.is_active(at(3, 11)));
+        assert!(!window.is_active(at(4, 9)));
+        assert!(window.is_active(at(10, 10)));
+
+        // Windows do not repeat backwards from their first occurrence
+        assert!(!window.is_active(at(3, 9) - Duration::weeks(1)));
+    }
+}
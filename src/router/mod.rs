@@ -0,0 +1,1543 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderValue, StatusCode};
+use axum::middleware;
+use axum::response::Redirect;
+use axum::routing::{get, post};
+use axum::{Form, Router};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use color_eyre::eyre::Result;
+use humantime::format_duration;
+use serde::{Deserialize, Deserializer, Serialize};
+use sqlx::PgPool;
+use tower_http::services::ServeDir;
+use uuid::Uuid;
+
+use crate::approval::{ApprovalPolicy, ChangeKind};
+use crate::credentials::CredentialCipher;
+use crate::diagnostics::RecentErrors;
+use crate::locale::Locale;
+use crate::persistence::{
+    AddressFamily, AlertWindow, ConnectionMode, Direction, HttpMethod, HttpVersion, LocationStatus,
+    PeriodComparison, UserPreferences, Visibility,
+};
+use crate::poller::{ExpectedStatus, Inspector, JsonAssertion, PollerHandle, ProbeLocations};
+use crate::regions::{self, Outage};
+use crate::router::identity::User;
+use crate::share::ShareLinkSigner;
+use crate::templates::{RenderedTemplate, TemplateEngine};
+use crate::utils::parse_tags;
+
+mod api;
+mod changes;
+mod control;
+mod cursors;
+mod diagnostics;
+mod drafts;
+mod embed;
+mod export;
+mod heartbeats;
+mod idempotency;
+mod identity;
+mod journeys;
+mod maintenance;
+mod origins;
+mod preconditions;
+mod preferences;
+mod public;
+mod push;
+mod reports;
+
+pub use crate::router::embed::EmbedSettings;
+
+#[derive(Clone)]
+struct ApplicationState {
+    pool: PgPool,
+    template_engine: TemplateEngine,
+    approval_policy: Arc<ApprovalPolicy>,
+    share_links: Arc<ShareLinkSigner>,
+    embed: EmbedSettings,
+    /// The VAPID public key browsers subscribe with, if Web Push is configured.
+    push_public_key: Option<Arc<str>>,
+    /// The language of the public status page.
+    locale: Locale,
+    /// Encrypts the credentials origins are checked with, if a key is configured.
+    credentials: Option<Arc<CredentialCipher>>,
+    poller: PollerHandle,
+    /// Makes ad-hoc requests to origins for admins debugging them.
+    inspector: Arc<Inspector>,
+    recent_errors: RecentErrors,
+    started_at: DateTime<Utc>,
+}
+
+/// How the web interface is configured, mostly from the environment.
+pub struct RouterSettings {
+    pub approval_policy: ApprovalPolicy,
+    pub share_links: ShareLinkSigner,
+    pub embed: EmbedSettings,
+    /// The VAPID public key browsers subscribe with, if Web Push is configured.
+    pub push_public_key: Option<String>,
+    /// The language of the public status page.
+    pub locale: Locale,
+    /// Encrypts the credentials origins are checked with, if a key is configured.
+    pub credentials: Option<CredentialCipher>,
+}
+
+/// Builds the web interface and API, which control the poller through `poller` and make ad-hoc
+/// requests to origins with `inspector`.
+pub fn build(
+    pool: PgPool,
+    settings: RouterSettings,
+    poller: PollerHandle,
+    inspector: Inspector,
+    recent_errors: RecentErrors,
+) -> Result<Router> {
+    let RouterSettings {
+        approval_policy,
+        share_links,
+        embed,
+        push_public_key,
+        locale,
+        credentials,
+    } = settings;
+
+    let template_engine = TemplateEngine::new()?;
+    let state = ApplicationState {
+        pool,
+        template_engine,
+        approval_policy: Arc::new(approval_policy),
+        share_links: Arc::new(share_links),
+        embed,
+        push_public_key: push_public_key.map(Arc::from),
+        locale,
+        credentials: credentials.map(Arc::new),
+        poller,
+        inspector: Arc::new(inspector),
+        recent_errors,
+        started_at: Utc::now(),
+    };
+
+    // Mutations of the API can be retried safely by sending an Idempotency-Key, and pausing or
+    // resuming an origin can be made conditional on its version with If-Match
+    let api = Router::new()
+        .route(
+            "/api/v1/config",
+            get(api::export_configuration).post(api::import_configuration),
+        )
+        .route(
+            "/api/v1/drafts",
+            get(api::list_drafts).post(api::create_draft),
+        )
+        .route(
+            "/api/v1/drafts/:draft_uid",
+            get(api::get_draft).put(api::update_draft),
+        )
+        .route("/api/v1/drafts/:draft_uid/diff", get(api::diff_draft))
+        .route(
+            "/api/v1/origins/:origin_uid/regions",
+            get(api::origin_regions),
+        )
+        .route(
+            "/api/v1/drafts/:draft_uid/promote",
+            post(api::promote_draft),
+        )
+        .route("/api/v1/push/public-key", get(push::public_key))
+        .route(
+            "/api/v1/push/subscriptions",
+            post(push::subscribe).delete(push::unsubscribe),
+        )
+        .route("/api/v1/origins/:origin_uid/check", post(api::check_origin))
+        .route(
+            "/api/v1/origins/:origin_uid/history",
+            get(api::origin_history),
+        )
+        .route("/api/v1/notifications", get(api::list_notifications))
+        .merge(
+            Router::new()
+                .route("/api/v1/origins/:origin_uid/pause", post(api::pause_origin))
+                .route(
+                    "/api/v1/origins/:origin_uid/resume",
+                    post(api::resume_origin),
+                )
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    preconditions::versioned,
+                )),
+        )
+        .route("/api/v1/reports/reliability", get(reports::reliability))
+        .route(
+            "/api/v1/reports/latency-by-location",
+            get(reports::latency_by_location),
+        )
+        .route("/api/v1/scheduler", get(control::scheduler))
+        .route("/api/v1/admin/errors", get(diagnostics::errors))
+        .route("/api/v1/changes", get(changes::list))
+        .route(
+            "/api/v1/changes/:change_uid/approve",
+            post(changes::approve_api),
+        )
+        .route(
+            "/api/v1/changes/:change_uid/reject",
+            post(changes::reject_api),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            idempotency::idempotent,
+        ));
+
+    // Edits of an origin are refused if it has been edited since the client last saw it
+    let origin_edits = Router::new()
+        .route("/origins/:origin_uid", get(origins::detail))
+        .route("/origins/:origin_uid/notes", post(origins::update_notes))
+        .route(
+            "/origins/:origin_uid/baseline",
+            post(origins::update_baseline),
+        )
+        .route(
+            "/origins/:origin_uid/timeout",
+            post(origins::update_timeout),
+        )
+        .route(
+            "/origins/:origin_uid/address-family",
+            post(origins::update_address_family),
+        )
+        .route(
+            "/origins/:origin_uid/expected-latency",
+            post(origins::update_expected_latency),
+        )
+        .route(
+            "/origins/:origin_uid/retries",
+            post(origins::update_retries),
+        )
+        .route(
+            "/origins/:origin_uid/schedule",
+            post(origins::update_schedule),
+        )
+        .route(
+            "/origins/:origin_uid/http-version",
+            post(origins::update_http_version),
+        )
+        .route(
+            "/origins/:origin_uid/connection-mode",
+            post(origins::update_connection_mode),
+        )
+        .route(
+            "/origins/:origin_uid/alert-window",
+            post(origins::update_alert_window),
+        )
+        .route("/origins/:origin_uid/proxy", post(origins::update_proxy))
+        .route(
+            "/origins/:origin_uid/request-options",
+            post(origins::update_request_options),
+        )
+        .route(
+            "/origins/:origin_uid/expected-networks",
+            post(origins::update_expected_networks),
+        )
+        .route(
+            "/origins/:origin_uid/probe-locations",
+            post(origins::update_probe_locations),
+        )
+        .route(
+            "/origins/:origin_uid/captured-headers",
+            post(origins::update_captured_headers),
+        )
+        .route(
+            "/origins/:origin_uid/expected-headers",
+            post(origins::update_expected_headers),
+        )
+        .route(
+            "/origins/:origin_uid/body-change-detection",
+            post(origins::update_body_change_detection),
+        )
+        .route(
+            "/origins/:origin_uid/heartbeat/grace",
+            post(heartbeats::update_grace),
+        )
+        .route(
+            "/origins/:origin_uid/journey/steps",
+            post(journeys::add_step),
+        )
+        .route(
+            "/origins/:origin_uid/journey/steps/:journey_step_uid/delete",
+            post(journeys::delete_step),
+        )
+        .route(
+            "/origins/:origin_uid/credentials",
+            post(origins::update_credentials),
+        )
+        .route(
+            "/origins/:origin_uid/client-certificate",
+            post(origins::update_client_certificate),
+        )
+        .route(
+            "/origins/:origin_uid/visibility",
+            post(update_origin_visibility),
+        )
+        .route("/origins/:origin_uid/cost", post(update_origin_cost))
+        .route("/origins/:origin_uid/tags", post(update_origin_tags))
+        .route("/origins/:origin_uid/alerts", post(update_origin_alerts))
+        .route("/origins/:origin_uid/paused", post(update_origin_paused))
+        .route("/origins/:origin_uid/delete", post(delete_origin))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            preconditions::versioned,
+        ));
+
+    let router = Router::new()
+        .route("/", get(index))
+        .route("/add-origin", get(add_origin_template).post(add_origin))
+        .route("/export/notifications.csv", get(export::notifications))
+        .route("/export/incidents.csv", get(export::incidents))
+        .route("/export/origins.csv", get(export::origins))
+        .route("/origins/:origin_uid/check", post(origins::check_now))
+        .route("/origins/:origin_uid/inspect", post(origins::inspect))
+        .route(
+            "/origins/:origin_uid/heartbeat/token",
+            post(heartbeats::regenerate_token),
+        )
+        .route(
+            "/origins/:origin_uid/share",
+            post(origins::create_share_link),
+        )
+        .route("/shared/:origin_uid", get(origins::shared))
+        .route("/embed/:origin_uid", get(embed::embed))
+        .route("/heartbeat/:token", post(heartbeats::ping))
+        .route("/origins/:origin_uid/pin", post(update_origin_pinned))
+        .route("/origins/:origin_uid/move", post(move_origin))
+        .route(
+            "/origins/:origin_uid/favorite",
+            post(preferences::update_favorite),
+        )
+        .route(
+            "/preferences",
+            get(preferences::preferences_page).post(preferences::update_preferences),
+        )
+        .route(
+            "/preferences/destination",
+            post(preferences::update_destination),
+        )
+        .route(
+            "/maintenance",
+            get(maintenance::maintenance_page).post(maintenance::create_window),
+        )
+        .route(
+            "/maintenance/:maintenance_window_uid/delete",
+            post(maintenance::delete_window),
+        )
+        .route("/changes", get(changes::changes_page))
+        .route("/changes/:change_uid/approve", post(changes::approve))
+        .route("/changes/:change_uid/reject", post(changes::reject))
+        .route("/poller/pause", post(control::pause))
+        .route("/poller/resume", post(control::resume))
+        .route("/poller/reload", post(control::reload))
+        .route("/scheduler", get(control::scheduler_page))
+        .route("/admin/diagnostics", get(diagnostics::diagnostics_page))
+        .route("/status", get(public::status_page))
+        .route("/status.txt", get(public::status_text))
+        .route("/status.json", get(public::status_json))
+        .route("/reports/sla", get(reports::sla_report))
+        .route("/reports/alerts", get(reports::alert_fatigue))
+        .route(
+            "/notifications/:notification_uid/acknowledge",
+            post(reports::acknowledge),
+        )
+        .route("/api/public/origins", get(public::origins))
+        .merge(origin_edits)
+        .merge(api)
+        .route("/drafts/:draft_uid", get(drafts::review))
+        .route("/drafts/:draft_uid/promote", post(drafts::promote))
+        .nest_service("/assets", ServeDir::new("assets"))
+        .with_state(state);
+
+    Ok(router)
+}
+
+/// The number of health scores to show in each origin's trend on the dashboard.
+const HEALTH_TREND_LENGTH: i64 = 24;
+
+/// Changes smaller than this are shown as flat rather than as an improvement or regression.
+const FLAT_THRESHOLD: f64 = 0.005;
+
+/// Converts the cost of downtime per minute into cents, rejecting amounts that are negative or not
+/// numbers.
+fn parse_cost(cost_per_minute: Option<f64>) -> Result<Option<i64>, (StatusCode, String)> {
+    cost_per_minute
+        .map(|amount| {
+            crate::cost::to_cents(amount).ok_or_else(|| {
+                (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    String::from("the cost per minute must be an amount of zero or more"),
+                )
+            })
+        })
+        .transpose()
+}
+
+/// The most redirects an origin can be configured to follow.
+const MAX_REDIRECT_LIMIT: i32 = 20;
+
+/// The longest timeout an origin can be configured with, so one slow origin cannot hold up the
+/// others for too long.
+const MAX_TIMEOUT_MILLIS: i64 = 60_000;
+
+/// The most times a failed check can be retried before it is recorded, so retries cannot hold up
+/// a round for long.
+const MAX_RETRIES: i32 = 3;
+
+/// The schemes an origin's proxy can use.
+const PROXY_SCHEMES: [&str; 4] = ["http", "https", "socks5", "socks5h"];
+
+/// Rejects proxies that are not a URL with one of the [`PROXY_SCHEMES`].
+fn validate_proxy(proxy: Option<&str>) -> Result<(), (StatusCode, String)> {
+    let Some(proxy) = proxy else {
+        return Ok(());
+    };
+
+    let valid = reqwest::Url::parse(proxy)
+        .is_ok_and(|url| PROXY_SCHEMES.contains(&url.scheme()) && url.host_str().is_some());
+
+    if !valid {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            String::from("the proxy must be an http, https, socks5 or socks5h URL"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Rejects User-Agents that cannot be sent as a header.
+fn validate_user_agent(user_agent: Option<&str>) -> Result<(), (StatusCode, String)> {
+    if user_agent.is_some_and(|user_agent| HeaderValue::from_str(user_agent).is_err()) {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            String::from("the User-Agent can only contain visible ASCII characters and spaces"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// How long to wait for a check of `origin` asked for on demand, which is long enough for each of
+/// its attempts to time out.
+fn check_now_timeout(origin: &crate::persistence::Origin) -> Duration {
+    let attempt_millis = origin.timeout_millis.unwrap_or(MAX_TIMEOUT_MILLIS);
+    let attempts = i64::from(origin.retries) + 1;
+
+    Duration::from_millis((attempt_millis * attempts) as u64) + Duration::from_secs(5)
+}
+
+/// Rejects timeouts that are not positive or are longer than [`MAX_TIMEOUT_MILLIS`].
+fn validate_timeout(timeout_millis: Option<i64>) -> Result<(), (StatusCode, String)> {
+    if timeout_millis.is_some_and(|timeout| !(1..=MAX_TIMEOUT_MILLIS).contains(&timeout)) {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("the timeout must be between 1 and {MAX_TIMEOUT_MILLIS} milliseconds"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Rejects retry counts that are negative or more than [`MAX_RETRIES`].
+fn validate_retries(retries: i32) -> Result<(), (StatusCode, String)> {
+    if !(0..=MAX_RETRIES).contains(&retries) {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("checks can be retried between 0 and {MAX_RETRIES} times"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Deserializes empty form fields as `None` rather than failing to parse them.
+fn empty_string_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = Option::<String>::deserialize(deserializer)?;
+
+    match value.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(value) => value.parse().map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+/// Formats the time elapsed since `at` in a human readable way, such as "1m 3s".
+fn time_since(at: DateTime<Utc>) -> String {
+    let delta = (Utc::now() - at).abs();
+    let duration = Duration::from_millis(delta.num_milliseconds() as u64);
+
+    format_duration(duration).to_string()
+}
+
+/// A change in a metric between two periods, ready for display.
+#[derive(Clone, Serialize)]
+struct Delta {
+    arrow: &'static str,
+    improved: bool,
+    label: String,
+}
+
+impl Delta {
+    fn new(change: f64, higher_is_better: bool, label: String) -> Self {
+        let flat = change.abs() < FLAT_THRESHOLD;
+
+        let arrow = if flat {
+            "\u{2192}"
+        } else if change > 0.0 {
+            "\u{25b2}"
+        } else {
+            "\u{25bc}"
+        };
+
+        Self {
+            arrow,
+            improved: flat || (change > 0.0) == higher_is_better,
+            label,
+        }
+    }
+}
+
+#[derive(Clone, Default, Serialize)]
+struct WeekOverWeek {
+    /// The change in uptime, in percentage points.
+    uptime: Option<Delta>,
+    /// The relative change in average latency.
+    latency: Option<Delta>,
+}
+
+impl From<&PeriodComparison> for WeekOverWeek {
+    fn from(comparison: &PeriodComparison) -> Self {
+        let uptime = comparison
+            .current_uptime
+            .zip(comparison.previous_uptime)
+            .map(|(current, previous)| {
+                let change = (current - previous) * 100.0;
+                Delta::new(change, true, format!("{change:+.2}pp"))
+            });
+
+        let latency = comparison
+            .current_latency_millis
+            .zip(comparison.previous_latency_millis)
+            .filter(|(_, previous)| *previous > 0.0)
+            .map(|(current, previous)| {
+                let change = (current - previous) / previous * 100.0;
+                Delta::new(change, false, format!("{change:+.0}%"))
+            });
+
+        Self { uptime, latency }
+    }
+}
+
+#[derive(Serialize)]
+struct IndexOrigin {
+    origin_uid: Uuid,
+    uri: String,
+    public: bool,
+    pinned: bool,
+    alerts_enabled: bool,
+    paused: bool,
+    tags: Vec<String>,
+    favorite: bool,
+    health_trend: Vec<i16>,
+    week_over_week: WeekOverWeek,
+    /// Whether some or all of the locations checking the origin see it failing.
+    outage: Option<Outage>,
+    status: Option<u16>,
+    latency_millis: u64,
+    queried: String,
+    queried_at: String,
+}
+
+#[derive(Serialize)]
+struct OriginFailure {
+    origin_uid: Uuid,
+    uri: String,
+    public: bool,
+    pinned: bool,
+    alerts_enabled: bool,
+    paused: bool,
+    tags: Vec<String>,
+    favorite: bool,
+    health_trend: Vec<i16>,
+    week_over_week: WeekOverWeek,
+    /// Whether some or all of the locations checking the origin see it failing.
+    outage: Option<Outage>,
+    failure_reason: String,
+    queried: String,
+    queried_at: String,
+}
+
+#[derive(Serialize)]
+struct SiblingMember {
+    origin_uid: Uuid,
+    uri: String,
+    failing: bool,
+}
+
+/// The HTTP, HTTPS, apex and www forms of a site, which are only up if every one of them is.
+#[derive(Serialize)]
+struct SiblingGroup {
+    name: String,
+    /// `up` when every sibling passes, `down` when every one fails and `degraded` otherwise.
+    status: &'static str,
+    members: Vec<SiblingMember>,
+}
+
+impl SiblingGroup {
+    fn new(members: Vec<SiblingMember>) -> Self {
+        let failing = members.iter().filter(|member| member.failing).count();
+
+        let status = match failing {
+            0 => "up",
+            failing if failing == members.len() => "down",
+            _ => "degraded",
+        };
+
+        Self {
+            name: crate::siblings::group_name(&members[0].uri),
+            status,
+            members,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct IndexContext {
+    sibling_groups: Vec<SiblingGroup>,
+    origins: Vec<IndexOrigin>,
+    failing_origins: Vec<OriginFailure>,
+    active_count: usize,
+    failing_count: usize,
+    signed_in: bool,
+    available_tags: Vec<String>,
+    tag_filter: Vec<String>,
+    page: usize,
+    pages: usize,
+    polling_paused: bool,
+    /// How many alerts in a row have failed to send, if enough have that alerting looks broken.
+    failing_alerts: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct IndexQuery {
+    /// Comma separated tags to filter by, replacing the user's default filter if present.
+    tags: Option<String>,
+    page: Option<usize>,
+}
+
+/// Returns the rows on the given page, counting from 1.
+fn paginate<T>(rows: Vec<T>, page: usize, page_size: Option<usize>) -> Vec<T> {
+    match page_size {
+        Some(size) => rows
+            .into_iter()
+            .skip((page - 1) * size)
+            .take(size)
+            .collect(),
+        None => rows,
+    }
+}
+
+async fn index(
+    State(ApplicationState {
+        pool,
+        template_engine,
+        poller,
+        ..
+    }): State<ApplicationState>,
+    user: Option<User>,
+    Query(query): Query<IndexQuery>,
+) -> RenderedTemplate {
+    let (preferences, favorites) = match &user {
+        Some(User(username)) => {
+            let preferences = crate::persistence::fetch_user_preferences(&pool, username)
+                .await
+                .expect("failed to fetch user preferences");
+
+            let favorites: HashSet<Uuid> =
+                crate::persistence::fetch_favorite_origins(&pool, username)
+                    .await
+                    .expect("failed to fetch favorite origins")
+                    .into_iter()
+                    .collect();
+
+            (preferences, favorites)
+        }
+        None => (UserPreferences::default(), HashSet::new()),
+    };
+
+    let timezone: Tz = preferences.timezone.parse().unwrap_or(Tz::UTC);
+    let format_time = |at: DateTime<Utc>| {
+        at.with_timezone(&timezone)
+            .format("%Y-%m-%d %H:%M:%S %Z")
+            .to_string()
+    };
+
+    let tag_filter = match query.tags.as_deref() {
+        Some(tags) => parse_tags(tags),
+        None => preferences.tag_filter,
+    };
+
+    let matches_filter =
+        |tags: &[String]| tag_filter.is_empty() || tags.iter().any(|tag| tag_filter.contains(tag));
+
+    let mut health_trends: HashMap<Uuid, Vec<i16>> = HashMap::new();
+
+    for health_score in crate::persistence::fetch_recent_health_scores(&pool, HEALTH_TREND_LENGTH)
+        .await
+        .expect("failed to fetch health scores")
+    {
+        health_trends
+            .entry(health_score.origin_uid)
+            .or_default()
+            .push(health_score.score);
+    }
+
+    let week_over_week: HashMap<Uuid, WeekOverWeek> =
+        crate::persistence::fetch_period_comparisons(&pool, chrono::Duration::weeks(1))
+            .await
+            .expect("failed to fetch weekly comparisons")
+            .iter()
+            .map(|comparison| (comparison.origin_uid, WeekOverWeek::from(comparison)))
+            .collect();
+
+    let mut locations: HashMap<Uuid, Vec<LocationStatus>> = HashMap::new();
+
+    for status in
+        crate::persistence::fetch_location_statuses(&pool, None, Utc::now() - regions::WINDOW)
+            .await
+            .expect("failed to fetch location statuses")
+    {
+        locations.entry(status.origin_uid).or_default().push(status);
+    }
+
+    let outage = |origin_uid: &Uuid| {
+        locations
+            .get(origin_uid)
+            .and_then(|locations| Outage::of(locations))
+    };
+
+    let successes = crate::persistence::fetch_origins_with_most_recent_success_metrics(&pool)
+        .await
+        .expect("failed to fetch origins");
+
+    let failures = crate::persistence::fetch_origins_with_most_recent_failure_metrics(&pool)
+        .await
+        .expect("failed to fetch failing origins");
+
+    let available_tags: BTreeSet<String> = successes
+        .iter()
+        .flat_map(|origin| origin.tags.iter())
+        .chain(failures.iter().flat_map(|origin| origin.tags.iter()))
+        .cloned()
+        .collect();
+
+    let mut origins: Vec<_> = successes
+        .into_iter()
+        .filter(|origin| matches_filter(&origin.tags))
+        .map(|origin| IndexOrigin {
+            origin_uid: origin.origin_uid,
+            uri: origin.uri,
+            public: origin.visibility == Visibility::Public.as_str(),
+            pinned: origin.pinned,
+            alerts_enabled: origin.alerts_enabled,
+            paused: origin.paused,
+            tags: origin.tags,
+            favorite: favorites.contains(&origin.origin_uid),
+            health_trend: health_trends
+                .get(&origin.origin_uid)
+                .cloned()
+                .unwrap_or_default(),
+            week_over_week: week_over_week
+                .get(&origin.origin_uid)
+                .cloned()
+                .unwrap_or_default(),
+            outage: outage(&origin.origin_uid),
+            status: origin.status.map(|status| status as u16),
+            latency_millis: origin.latency_millis as u64,
+            queried: time_since(origin.queried_at),
+            queried_at: format_time(origin.queried_at),
+        })
+        .collect();
+
+    let mut failing_origins: Vec<_> = failures
+        .into_iter()
+        .filter(|origin| matches_filter(&origin.tags))
+        .map(|origin| OriginFailure {
+            origin_uid: origin.origin_uid,
+            uri: origin.uri,
+            public: origin.visibility == Visibility::Public.as_str(),
+            pinned: origin.pinned,
+            alerts_enabled: origin.alerts_enabled,
+            paused: origin.paused,
+            tags: origin.tags,
+            favorite: favorites.contains(&origin.origin_uid),
+            health_trend: health_trends
+                .get(&origin.origin_uid)
+                .cloned()
+                .unwrap_or_default(),
+            week_over_week: week_over_week
+                .get(&origin.origin_uid)
+                .cloned()
+                .unwrap_or_default(),
+            outage: outage(&origin.origin_uid),
+            failure_reason: origin.failure_reason,
+            queried: time_since(origin.queried_at),
+            queried_at: format_time(origin.queried_at),
+        })
+        .collect();
+
+    let mut sibling_members: Vec<(Uuid, Vec<SiblingMember>)> = Vec::new();
+
+    for member in crate::persistence::fetch_sibling_group_members(&pool)
+        .await
+        .expect("failed to fetch sibling groups")
+    {
+        let member_uid = member.sibling_group_uid;
+        let sibling = SiblingMember {
+            origin_uid: member.origin_uid,
+            uri: member.uri,
+            failing: member.failing,
+        };
+
+        // Members arrive in display order, so groups keep the position of their first member
+        match sibling_members
+            .iter_mut()
+            .find(|(uid, _)| *uid == member_uid)
+        {
+            Some((_, members)) => members.push(sibling),
+            None => sibling_members.push((member_uid, vec![sibling])),
+        }
+    }
+
+    let filtered_origins: HashSet<Uuid> = origins
+        .iter()
+        .map(|origin| origin.origin_uid)
+        .chain(failing_origins.iter().map(|origin| origin.origin_uid))
+        .collect();
+
+    let sibling_groups: Vec<_> = sibling_members
+        .into_iter()
+        .filter(|(_, members)| {
+            members
+                .iter()
+                .any(|member| filtered_origins.contains(&member.origin_uid))
+        })
+        .map(|(_, members)| SiblingGroup::new(members))
+        .collect();
+
+    // Favorites come first, otherwise keeping the shared ordering
+    origins.sort_by_key(|origin| !origin.favorite);
+    failing_origins.sort_by_key(|origin| !origin.favorite);
+
+    let active_count = origins.len();
+    let failing_count = failing_origins.len();
+
+    let page_size = preferences.page_size.map(|size| size.max(1) as usize);
+    let pages = page_size.map_or(1, |size| {
+        active_count.max(failing_count).div_ceil(size).max(1)
+    });
+    let page = query.page.unwrap_or(1).clamp(1, pages);
+
+    let notifier = poller.notifier_health();
+    let failing_alerts = notifier
+        .is_failing()
+        .then_some(notifier.consecutive_failures);
+
+    let context = IndexContext {
+        sibling_groups,
+        origins: paginate(origins, page, page_size),
+        failing_origins: paginate(failing_origins, page, page_size),
+        active_count,
+        failing_count,
+        signed_in: user.is_some(),
+        available_tags: available_tags.into_iter().collect(),
+        tag_filter,
+        page,
+        pages,
+        polling_paused: poller.is_paused(),
+        failing_alerts,
+    };
+
+    template_engine
+        .render_serialized("index.tera.html", &context)
+        .expect("failed to render template")
+}
+
+async fn add_origin_template(
+    State(ApplicationState {
+        template_engine, ..
+    }): State<ApplicationState>,
+) -> RenderedTemplate {
+    template_engine
+        .render_contextless("add-origin.tera.html")
+        .expect("failed to render template")
+}
+
+#[derive(Deserialize)]
+struct OriginCreationRequest {
+    uri: String,
+    #[serde(default)]
+    visibility: Visibility,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    cost_per_minute: Option<f64>,
+    #[serde(default)]
+    tags: String,
+    #[serde(default)]
+    method: HttpMethod,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    request_body: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    request_content_type: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    forbidden_text: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    json_assertion: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    expected_status: Option<String>,
+    #[serde(default)]
+    cache_validation: bool,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    redirect_limit: Option<i32>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    expected_location: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    timeout_millis: Option<i64>,
+    #[serde(default)]
+    address_family: AddressFamily,
+    #[serde(default)]
+    http_version: HttpVersion,
+    #[serde(default)]
+    connection_mode: ConnectionMode,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    retries: Option<i32>,
+    #[serde(default)]
+    alert_window: AlertWindow,
+    #[serde(default)]
+    adaptive_polling: bool,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    proxy: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    probe_locations: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    user_agent: Option<String>,
+    #[serde(default)]
+    cache_busting: bool,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    heartbeat_grace_minutes: Option<i32>,
+    /// Whether to also create and group the other HTTP, HTTPS, apex and www forms of the URI.
+    #[serde(default)]
+    siblings: bool,
+}
+
+async fn add_origin(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Form(OriginCreationRequest {
+        uri,
+        visibility,
+        cost_per_minute,
+        tags,
+        method,
+        request_body,
+        request_content_type,
+        forbidden_text,
+        json_assertion,
+        expected_status,
+        cache_validation,
+        redirect_limit,
+        expected_location,
+        timeout_millis,
+        address_family,
+        http_version,
+        connection_mode,
+        retries,
+        alert_window,
+        adaptive_polling,
+        proxy,
+        probe_locations,
+        user_agent,
+        cache_busting,
+        heartbeat_grace_minutes,
+        siblings,
+    }): Form<OriginCreationRequest>,
+) -> Result<Redirect, (StatusCode, String)> {
+    if request_body.is_some() && !method.allows_body() {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("{} requests cannot have a body", method.as_str()),
+        ));
+    }
+
+    if let Some(expression) = &json_assertion {
+        JsonAssertion::parse(expression).map_err(|e| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("invalid JSON assertion: {e}"),
+            )
+        })?;
+    }
+
+    if let Some(expected_status) = &expected_status {
+        ExpectedStatus::parse(expected_status).map_err(|e| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("invalid expected status codes: {e}"),
+            )
+        })?;
+    }
+
+    if redirect_limit.is_some_and(|limit| !(0..=MAX_REDIRECT_LIMIT).contains(&limit)) {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("the redirect limit must be between 0 and {MAX_REDIRECT_LIMIT}"),
+        ));
+    }
+
+    if let Some(expected_location) = &expected_location {
+        reqwest::Url::parse(expected_location).map_err(|e| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("invalid expected location: {e}"),
+            )
+        })?;
+    }
+
+    validate_timeout(timeout_millis)?;
+    validate_retries(retries.unwrap_or_default())?;
+    validate_proxy(proxy.as_deref())?;
+    validate_user_agent(user_agent.as_deref())?;
+
+    if let Some(probe_locations) = &probe_locations {
+        ProbeLocations::parse(probe_locations).map_err(|e| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("invalid probe locations: {e}"),
+            )
+        })?;
+    }
+
+    let heartbeat_grace_minutes =
+        heartbeat_grace_minutes.unwrap_or(heartbeats::DEFAULT_GRACE_MINUTES);
+    heartbeats::validate_grace(heartbeat_grace_minutes)?;
+
+    let cost_per_minute_cents = parse_cost(cost_per_minute)?;
+
+    let uris = if siblings {
+        crate::siblings::expand(&uri).ok_or_else(|| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                String::from("only HTTP and HTTPS origins can have siblings"),
+            )
+        })?
+    } else {
+        vec![uri]
+    };
+
+    let sibling_group_uid = siblings.then(Uuid::new_v4);
+    let tags = parse_tags(&tags);
+
+    // Siblings share the configuration of the origin they were created from
+    for uri in uris {
+        let origin_uid = Uuid::new_v4();
+
+        crate::persistence::insert_origin(&pool, origin_uid, &uri)
+            .await
+            .expect("failed to insert origin");
+
+        // Heartbeats are pinged rather than requested, so they need a URL to be pinged at
+        if uri.starts_with("heartbeat://") {
+            crate::persistence::upsert_heartbeat(
+                &pool,
+                origin_uid,
+                &heartbeats::generate_token(),
+                heartbeat_grace_minutes * 60,
+                Utc::now(),
+            )
+            .await
+            .expect("failed to insert heartbeat");
+        }
+
+        crate::persistence::set_origin_request(
+            &pool,
+            origin_uid,
+            method,
+            request_body.as_deref(),
+            request_content_type.as_deref(),
+        )
+        .await
+        .expect("failed to set origin request");
+
+        if let Some(forbidden_text) = &forbidden_text {
+            crate::persistence::set_origin_forbidden_text(&pool, origin_uid, Some(forbidden_text))
+                .await
+                .expect("failed to set origin forbidden text");
+        }
+
+        if let Some(json_assertion) = &json_assertion {
+            crate::persistence::set_origin_json_assertion(&pool, origin_uid, Some(json_assertion))
+                .await
+                .expect("failed to set origin JSON assertion");
+        }
+
+        if let Some(expected_status) = &expected_status {
+            crate::persistence::set_origin_expected_status(
+                &pool,
+                origin_uid,
+                Some(expected_status),
+            )
+            .await
+            .expect("failed to set origin expected status");
+        }
+
+        if cache_validation {
+            crate::persistence::set_origin_cache_validation(&pool, origin_uid, true)
+                .await
+                .expect("failed to set origin cache validation");
+        }
+
+        if redirect_limit.is_some() || expected_location.is_some() {
+            crate::persistence::set_origin_redirect_policy(
+                &pool,
+                origin_uid,
+                redirect_limit,
+                expected_location.as_deref(),
+            )
+            .await
+            .expect("failed to set origin redirect policy");
+        }
+
+        if timeout_millis.is_some() {
+            crate::persistence::set_origin_timeout(&pool, origin_uid, timeout_millis)
+                .await
+                .expect("failed to set origin timeout");
+        }
+
+        if address_family != AddressFamily::Any {
+            crate::persistence::set_origin_address_family(&pool, origin_uid, address_family)
+                .await
+                .expect("failed to set origin address family");
+        }
+
+        if http_version != HttpVersion::Auto {
+            crate::persistence::set_origin_http_version(&pool, origin_uid, http_version)
+                .await
+                .expect("failed to set origin HTTP version");
+        }
+
+        if connection_mode != ConnectionMode::Reused {
+            crate::persistence::set_origin_connection_mode(&pool, origin_uid, connection_mode)
+                .await
+                .expect("failed to set origin connection mode");
+        }
+
+        if let Some(retries) = retries.filter(|retries| *retries > 0) {
+            crate::persistence::set_origin_retries(&pool, origin_uid, retries)
+                .await
+                .expect("failed to set origin retries");
+        }
+
+        if alert_window != AlertWindow::Rolling {
+            crate::persistence::set_origin_alert_window(&pool, origin_uid, alert_window)
+                .await
+                .expect("failed to set origin alert window");
+        }
+
+        if adaptive_polling {
+            crate::persistence::set_origin_adaptive_polling(&pool, origin_uid, true)
+                .await
+                .expect("failed to set origin adaptive polling");
+        }
+
+        if proxy.is_some() {
+            crate::persistence::set_origin_proxy(&pool, origin_uid, proxy.as_deref())
+                .await
+                .expect("failed to set origin proxy");
+        }
+
+        if probe_locations.is_some() {
+            crate::persistence::set_origin_probe_locations(
+                &pool,
+                origin_uid,
+                probe_locations.as_deref(),
+            )
+            .await
+            .expect("failed to set origin probe locations");
+        }
+
+        if user_agent.is_some() || cache_busting {
+            crate::persistence::set_origin_request_options(
+                &pool,
+                origin_uid,
+                user_agent.as_deref(),
+                cache_busting,
+            )
+            .await
+            .expect("failed to set origin request options");
+        }
+
+        crate::persistence::set_origin_visibility(&pool, origin_uid, visibility)
+            .await
+            .expect("failed to set origin visibility");
+
+        if cost_per_minute_cents.is_some() {
+            crate::persistence::set_origin_cost(&pool, origin_uid, cost_per_minute_cents)
+                .await
+                .expect("failed to set origin cost");
+        }
+
+        if !tags.is_empty() {
+            crate::persistence::set_origin_tags(&pool, origin_uid, &tags)
+                .await
+                .expect("failed to set origin tags");
+        }
+
+        if sibling_group_uid.is_some() {
+            crate::persistence::set_origin_sibling_group(&pool, origin_uid, sibling_group_uid)
+                .await
+                .expect("failed to set origin sibling group");
+        }
+    }
+
+    Ok(Redirect::to("/"))
+}
+
+#[derive(Deserialize)]
+struct VisibilityUpdateRequest {
+    visibility: Visibility,
+}
+
+async fn update_origin_visibility(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(VisibilityUpdateRequest { visibility }): Form<VisibilityUpdateRequest>,
+) -> Redirect {
+    crate::persistence::set_origin_visibility(&pool, origin_uid, visibility)
+        .await
+        .expect("failed to set origin visibility");
+
+    Redirect::to("/")
+}
+
+#[derive(Deserialize)]
+struct PinUpdateRequest {
+    pinned: bool,
+}
+
+async fn update_origin_pinned(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(PinUpdateRequest { pinned }): Form<PinUpdateRequest>,
+) -> Redirect {
+    crate::persistence::set_origin_pinned(&pool, origin_uid, pinned)
+        .await
+        .expect("failed to set origin pinned state");
+
+    Redirect::to("/")
+}
+
+#[derive(Deserialize)]
+struct PauseUpdateRequest {
+    paused: bool,
+}
+
+async fn update_origin_paused(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(PauseUpdateRequest { paused }): Form<PauseUpdateRequest>,
+) -> Redirect {
+    crate::persistence::set_origin_paused(&pool, origin_uid, paused)
+        .await
+        .expect("failed to set origin paused state");
+
+    Redirect::to("/")
+}
+
+#[derive(Deserialize)]
+struct MoveRequest {
+    direction: Direction,
+}
+
+async fn move_origin(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(MoveRequest { direction }): Form<MoveRequest>,
+) -> Redirect {
+    crate::persistence::move_origin(&pool, origin_uid, direction)
+        .await
+        .expect("failed to move origin");
+
+    Redirect::to("/")
+}
+
+#[derive(Deserialize)]
+struct CostUpdateRequest {
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    cost_per_minute: Option<f64>,
+}
+
+async fn update_origin_cost(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(CostUpdateRequest { cost_per_minute }): Form<CostUpdateRequest>,
+) -> Result<Redirect, (StatusCode, String)> {
+    let cents = parse_cost(cost_per_minute)?;
+
+    crate::persistence::set_origin_cost(&pool, origin_uid, cents)
+        .await
+        .expect("failed to set origin cost");
+
+    Ok(Redirect::to("/"))
+}
+
+#[derive(Deserialize)]
+struct TagsUpdateRequest {
+    tags: String,
+}
+
+async fn update_origin_tags(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(TagsUpdateRequest { tags }): Form<TagsUpdateRequest>,
+) -> Redirect {
+    crate::persistence::set_origin_tags(&pool, origin_uid, &parse_tags(&tags))
+        .await
+        .expect("failed to set origin tags");
+
+    Redirect::to("/")
+}
+
+#[derive(Deserialize)]
+struct AlertsUpdateRequest {
+    alerts_enabled: bool,
+}
+
+async fn update_origin_alerts(
+    State(state): State<ApplicationState>,
+    user: Option<User>,
+    Path(origin_uid): Path<Uuid>,
+    Form(AlertsUpdateRequest { alerts_enabled }): Form<AlertsUpdateRequest>,
+) -> Result<Redirect, (StatusCode, String)> {
+    // Turning alerts back on is never destructive, so it does not need approval
+    if alerts_enabled {
+        let mut tx = state
+            .pool
+            .begin()
+            .await
+            .expect("failed to start transaction");
+
+        crate::persistence::set_origin_alerts_enabled(&mut tx, origin_uid, true)
+            .await
+            .expect("failed to enable alerts");
+
+        tx.commit().await.expect("failed to commit transaction");
+
+        return Ok(Redirect::to("/"));
+    }
+
+    let origin = crate::persistence::fetch_origin(&state.pool, origin_uid)
+        .await
+        .expect("failed to fetch origin")
+        .ok_or((StatusCode::NOT_FOUND, String::from("origin not found")))?;
+
+    let description = format!("Disable alerts for {}", origin.uri);
+    let submission = changes::submit(
+        &state,
+        user,
+        ChangeKind::DisableAlerts,
+        origin_uid,
+        description,
+    )
+    .await?;
+
+    Ok(submission.redirect())
+}
+
+async fn delete_origin(
+    State(state): State<ApplicationState>,
+    user: Option<User>,
+    Path(origin_uid): Path<Uuid>,
+) -> Result<Redirect, (StatusCode, String)> {
+    let origin = crate::persistence::fetch_origin(&state.pool, origin_uid)
+        .await
+        .expect("failed to fetch origin")
+        .ok_or((StatusCode::NOT_FOUND, String::from("origin not found")))?;
+
+    let description = format!("Delete {}", origin.uri);
+    let submission = changes::submit(
+        &state,
+        user,
+        ChangeKind::DeleteOrigin,
+        origin_uid,
+        description,
+    )
+    .await?;
+
+    Ok(submission.redirect())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use color_eyre::eyre::Result;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    use crate::persistence::{Direction, PeriodComparison};
+    use crate::poller::FailureReason;
+    use crate::router::WeekOverWeek;
+
+    async fn dashboard_order(pool: &PgPool) -> Result<Vec<String>> {
+        let origins = crate::persistence::fetch_origins_with_most_recent_success_metrics(pool)
+            .await?
+            .into_iter()
+            .map(|origin| origin.uri)
+            .collect();
+
+        Ok(origins)
+    }
+
+    #[sqlx::test]
+    async fn origins_only_move_past_those_pinned_the_same_way(pool: PgPool) -> Result<()> {
+        let mut origin_uids = Vec::new();
+
+        for uri in ["a", "b", "c", "d"] {
+            let origin_uid = Uuid::new_v4();
+            crate::persistence::insert_origin(&pool, origin_uid, uri).await?;
+
+            let mut tx = pool.begin().await?;
+            crate::persistence::insert_query(&mut tx, origin_uid, Some(200), 50, Utc::now())
+                .await?;
+            tx.commit().await?;
+
+            origin_uids.push(origin_uid);
+        }
+
+        let [a, _, c, d] = origin_uids[..] else {
+            unreachable!();
+        };
+
+        crate::persistence::set_origin_pinned(&pool, d, true).await?;
+        assert_eq!(dashboard_order(&pool).await?, ["d", "a", "b", "c"]);
+
+        crate::persistence::move_origin(&pool, c, Direction::Up).await?;
+        assert_eq!(dashboard_order(&pool).await?, ["d", "a", "c", "b"]);
+
+        // Neither the first unpinned origin nor the last pinned one can cross over
+        crate::persistence::move_origin(&pool, a, Direction::Up).await?;
+        crate::persistence::move_origin(&pool, d, Direction::Down).await?;
+        assert_eq!(dashboard_order(&pool).await?, ["d", "a", "c", "b"]);
+
+        crate::persistence::move_origin(&pool, a, Direction::Down).await?;
+        assert_eq!(dashboard_order(&pool).await?, ["d", "c", "a", "b"]);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn weeks_are_compared_with_the_one_before(pool: PgPool) -> Result<()> {
+        let origin_uid = Uuid::new_v4();
+        crate::persistence::insert_origin(&pool, origin_uid, "https://example.com").await?;
+
+        let now = Utc::now();
+        let mut tx = pool.begin().await?;
+
+        // Half of last week's checks failed, and a quarter of this week's
+        for (days_ago, latency_millis) in [(8, Some(100)), (8, None), (9, Some(100)), (9, None)]
+            .into_iter()
+            .chain([(1, Some(150)), (1, Some(150)), (2, Some(150)), (2, None)])
+            // Checks from before the previous week are left out
+            .chain([(20, None)])
+        {
+            let queried_at = now - Duration::days(days_ago);
+
+            match latency_millis {
+                Some(latency_millis) => {
+                    crate::persistence::insert_query(
+                        &mut tx,
+                        origin_uid,
+                        Some(200),
+                        latency_millis,
+                        queried_at,
+                    )
+                    .await?;
+                }
+                None => {
+                    crate::persistence::insert_query_failure(
+                        &mut tx,
+                        origin_uid,
+                        FailureReason::RequestTimeout,
+                        None,
+                        queried_at,
+                    )
+                    .await?;
+                }
+            }
+        }
+        tx.commit().await?;
+
+        let comparisons =
+            crate::persistence::fetch_period_comparisons(&pool, Duration::weeks(1)).await?;
+        assert_eq!(comparisons.len(), 1);
+
+        let comparison = &comparisons[0];
+        assert_eq!(comparison.current_uptime, Some(0.75));
+        assert_eq!(comparison.previous_uptime, Some(0.5));
+        assert_eq!(comparison.current_latency_millis, Some(150.0));
+        assert_eq!(comparison.previous_latency_millis, Some(100.0));
+
+        let week_over_week = WeekOverWeek::from(comparison);
+
+        let uptime = week_over_week.uptime.unwrap();
+        assert_eq!(uptime.label, "+25.00pp");
+        assert_eq!(uptime.arrow, "\u{25b2}");
+        assert!(uptime.improved);
+
+        // Latency going up is a change for the worse
+        let latency = week_over_week.latency.unwrap();
+        assert_eq!(latency.label, "+50%");
+        assert_eq!(latency.arrow, "\u{25b2}");
+        assert!(!latency.improved);
+
+        Ok(())
+    }
+
+    #[test]
+    fn small_or_incomparable_changes_are_not_shown_as_changes() {
+        let week_over_week = WeekOverWeek::from(&PeriodComparison {
+            origin_uid: Uuid::nil(),
+            current_uptime: Some(0.99),
+            previous_uptime: Some(0.99),
+            current_latency_millis: Some(120.0),
+            previous_latency_millis: Some(0.0),
+        });
+
+        let uptime = week_over_week.uptime.unwrap();
+        assert_eq!(uptime.arrow, "\u{2192}");
+        assert!(uptime.improved);
+
+        // A previous latency of zero cannot be compared with
+        assert!(week_over_week.latency.is_none());
+
+        let week_over_week = WeekOverWeek::from(&PeriodComparison {
+            origin_uid: Uuid::nil(),
+            current_uptime: Some(0.99),
+            previous_uptime: None,
+            current_latency_millis: None,
+            previous_latency_millis: None,
+        });
+
+        assert!(week_over_week.uptime.is_none());
+        assert!(week_over_week.latency.is_none());
+    }
+}
@@ -0,0 +1,1584 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::Redirect;
+use axum::Form;
+use chrono::{Duration, Utc};
+use humantime::format_duration;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::credentials::Credentials;
+use crate::persistence::{
+    AddressFamily, AlertWindow, BodyChange, ConnectionMode, DomainRegistration, HttpVersion,
+    JourneyStep, OriginChange, RecentCheck, RecordedRedirectChain, TracerouteCapture,
+};
+use crate::poller::{
+    format_throughput, CapturedHeaders, CheckNowError, CronSchedule, ExpectedHeaders,
+    ExpectedNetworks, Inspection, ProbeLocations, Timings,
+};
+use crate::regions::{self, Outage};
+use crate::router::heartbeats::MAX_GRACE_MINUTES;
+use crate::router::identity::User;
+use crate::router::journeys::MAX_STEPS;
+use crate::router::{
+    check_now_timeout, time_since, validate_proxy, validate_retries, validate_timeout,
+    validate_user_agent, ApplicationState, MAX_RETRIES, MAX_TIMEOUT_MILLIS,
+};
+use crate::share::MAX_SHARE_LINK_HOURS;
+use crate::templates::RenderedTemplate;
+
+/// The number of checks to show on an origin's detail page.
+const RECENT_CHECK_COUNT: i64 = 60;
+
+/// The number of configuration changes to show on an origin's detail page.
+const RECENT_CHANGE_COUNT: i64 = 20;
+
+/// The number of redirect chains to show on an origin's detail page.
+const RECENT_REDIRECT_CHAIN_COUNT: i64 = 5;
+
+/// The number of changes to the response body to show on an origin's detail page.
+const RECENT_BODY_CHANGE_COUNT: i64 = 20;
+
+/// The period an origin's latency is compared with its baseline over on its detail page.
+const RELATIVE_LATENCY_WINDOW: Duration = Duration::hours(1);
+
+/// How long a share link stays valid for if no lifetime is given.
+const DEFAULT_SHARE_LINK_HOURS: i64 = 24;
+
+#[derive(Serialize)]
+struct CheckBar {
+    success: bool,
+    /// The height of the bar as a percentage of the slowest check shown.
+    height: u8,
+    label: String,
+}
+
+#[derive(Serialize)]
+struct ShareLink {
+    path: String,
+    expires_at: String,
+}
+
+#[derive(Serialize)]
+struct HeartbeatEntry {
+    /// Where the job being monitored pings.
+    path: String,
+    grace_minutes: i32,
+    last_ping: Option<String>,
+}
+
+#[derive(Serialize)]
+struct JourneyStepEntry {
+    journey_step_uid: Uuid,
+    method: &'static str,
+    url: String,
+    request_headers: Option<String>,
+    request_body: Option<String>,
+    expected_status: Option<String>,
+    extraction: Option<String>,
+}
+
+impl From<JourneyStep> for JourneyStepEntry {
+    fn from(step: JourneyStep) -> Self {
+        Self {
+            journey_step_uid: step.journey_step_uid,
+            method: step.method.as_str(),
+            url: step.url,
+            request_headers: step.request_headers,
+            request_body: step.request_body,
+            expected_status: step.expected_status,
+            extraction: step.extraction,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ChangeEntry {
+    field: String,
+    from: String,
+    to: String,
+    changed_at: String,
+}
+
+impl From<OriginChange> for ChangeEntry {
+    fn from(change: OriginChange) -> Self {
+        let format = |value: Option<String>| value.unwrap_or_else(|| String::from("(none)"));
+
+        Self {
+            field: change.field,
+            from: format(change.old_value),
+            to: format(change.new_value),
+            changed_at: change.changed_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TracerouteEntry {
+    host: String,
+    output: String,
+    incident_started_at: String,
+    captured_at: String,
+}
+
+impl From<TracerouteCapture> for TracerouteEntry {
+    fn from(capture: TracerouteCapture) -> Self {
+        let format = "%Y-%m-%d %H:%M UTC";
+
+        Self {
+            host: capture.host,
+            output: capture.output,
+            incident_started_at: capture.incident_started_at.format(format).to_string(),
+            captured_at: capture.captured_at.format(format).to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct DomainRegistrationEntry {
+    domain: String,
+    expires_on: Option<String>,
+    /// How many whole days are left before the domain expires, which is negative once it has.
+    days_left: Option<i64>,
+    checked_at: String,
+}
+
+impl From<DomainRegistration> for DomainRegistrationEntry {
+    fn from(registration: DomainRegistration) -> Self {
+        Self {
+            domain: registration.domain,
+            expires_on: registration
+                .expires_at
+                .map(|expires_at| expires_at.format("%Y-%m-%d").to_string()),
+            days_left: registration
+                .expires_at
+                .map(|expires_at| (expires_at - Utc::now()).num_seconds().div_euclid(86_400)),
+            checked_at: registration
+                .checked_at
+                .format("%Y-%m-%d %H:%M UTC")
+                .to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RedirectChainEntry {
+    chain: String,
+    recorded_at: String,
+}
+
+#[derive(Serialize)]
+struct BodyChangeEntry {
+    /// The start of the hash, which is enough to tell bodies apart at a glance.
+    body_hash: String,
+    detected_at: String,
+}
+
+impl From<BodyChange> for BodyChangeEntry {
+    fn from(change: BodyChange) -> Self {
+        Self {
+            body_hash: change.body_hash.chars().take(12).collect(),
+            detected_at: change.detected_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+        }
+    }
+}
+
+impl From<RecordedRedirectChain> for RedirectChainEntry {
+    fn from(recorded: RecordedRedirectChain) -> Self {
+        Self {
+            chain: recorded.chain,
+            recorded_at: recorded
+                .recorded_at
+                .format("%Y-%m-%d %H:%M UTC")
+                .to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Baseline {
+    origin_uid: Uuid,
+    uri: String,
+    /// How much slower the origin was than its baseline, which is negative if it was faster.
+    relative_latency_millis: Option<i64>,
+    limit_millis: Option<i64>,
+}
+
+/// How long a phase of recent checks typically took.
+#[derive(Serialize)]
+struct PhaseTiming {
+    name: &'static str,
+    millis: i64,
+}
+
+/// How the origin has fared from one of the locations checking it.
+#[derive(Serialize)]
+struct Region {
+    location: String,
+    operational: bool,
+    uptime: String,
+    queried: String,
+}
+
+#[derive(Serialize)]
+struct BaselineCandidate {
+    origin_uid: Uuid,
+    uri: String,
+    selected: bool,
+}
+
+#[derive(Serialize)]
+struct OriginContext {
+    origin_uid: Uuid,
+    uri: String,
+    /// The version of the origin the page shows, which edits made from it are checked against.
+    version: i64,
+    operational: Option<bool>,
+    queried: Option<String>,
+    uptime: Option<String>,
+    /// Whether some or all of the locations checking the origin see it failing.
+    outage: Option<Outage>,
+    regions: Vec<Region>,
+    regions_window_hours: i64,
+    average_latency_millis: Option<u64>,
+    /// The size of the latest response body, and the median of the recent ones to compare it to.
+    body_size: Option<String>,
+    typical_body_size: Option<String>,
+    /// The median time each phase of recent HTTP checks took, for phases that were timed.
+    phase_timings: Vec<PhaseTiming>,
+    checks: Vec<CheckBar>,
+    incidents: usize,
+    mean_time_to_recovery: Option<String>,
+    mean_time_between_failures: Option<String>,
+    reliability_window_days: i64,
+    notes: Option<String>,
+    changes: Vec<ChangeEntry>,
+    traceroutes: Vec<TracerouteEntry>,
+    redirect_chains: Vec<RedirectChainEntry>,
+    body_change_detection: bool,
+    body_changes: Vec<BodyChangeEntry>,
+    domain_registration: Option<DomainRegistrationEntry>,
+    baseline: Option<Baseline>,
+    baseline_candidates: Vec<BaselineCandidate>,
+    relative_latency_window_minutes: i64,
+    timeout_millis: Option<i64>,
+    max_timeout_millis: i64,
+    expected_latency_millis: Option<i64>,
+    retries: i32,
+    max_retries: i32,
+    schedule: Option<String>,
+    address_family: AddressFamily,
+    address_families: Vec<AddressFamilyOption>,
+    http_version: HttpVersion,
+    http_versions: Vec<HttpVersionOption>,
+    connection_mode: ConnectionMode,
+    connection_modes: Vec<ConnectionModeOption>,
+    alert_window: AlertWindow,
+    alert_windows: Vec<AlertWindowOption>,
+    /// The period reliability is calculated over, which follows the alert window.
+    reliability_period: String,
+    /// The version of HTTP the most recent successful check was made with.
+    negotiated_http_version: Option<String>,
+    proxy: Option<String>,
+    user_agent: Option<String>,
+    cache_busting: bool,
+    expected_networks: Option<String>,
+    probe_locations: Option<String>,
+    captured_headers: Option<String>,
+    expected_headers: Option<String>,
+    is_heartbeat: bool,
+    /// The ping URL of a heartbeat origin, which is left out of shared views as anyone with it can
+    /// ping the heartbeat.
+    heartbeat: Option<HeartbeatEntry>,
+    max_heartbeat_grace_minutes: i32,
+    is_journey: bool,
+    /// The steps of a journey origin, which are left out of shared views as they can include
+    /// credentials.
+    journey_steps: Vec<JourneyStepEntry>,
+    max_journey_steps: usize,
+    /// The address the most recent successful check was served from.
+    remote_address: Option<String>,
+    has_credentials: bool,
+    has_client_certificate: bool,
+    credentials_configurable: bool,
+    read_only: bool,
+    share_link: Option<ShareLink>,
+    max_share_link_hours: i64,
+}
+
+#[derive(Serialize)]
+struct AddressFamilyOption {
+    value: &'static str,
+    name: &'static str,
+}
+
+#[derive(Serialize)]
+struct HttpVersionOption {
+    value: &'static str,
+    name: &'static str,
+}
+
+#[derive(Serialize)]
+struct ConnectionModeOption {
+    value: &'static str,
+    name: &'static str,
+}
+
+#[derive(Serialize)]
+struct AlertWindowOption {
+    value: &'static str,
+    name: &'static str,
+}
+
+/// Describes the period reliability is calculated over, to finish "Calculated from the incidents".
+fn reliability_period(alert_window: AlertWindow) -> String {
+    match alert_window {
+        AlertWindow::Rolling => format!(
+            "in the last {} days",
+            crate::reliability::DEFAULT_WINDOW.num_days()
+        ),
+        AlertWindow::Day => String::from("since midnight UTC"),
+        AlertWindow::Week => String::from("since the start of the week"),
+        AlertWindow::Month => String::from("since the start of the month"),
+    }
+}
+
+fn format_mean(duration: Option<Duration>) -> Option<String> {
+    duration
+        .and_then(|duration| duration.to_std().ok())
+        .map(|duration| {
+            format_duration(std::time::Duration::from_secs(duration.as_secs())).to_string()
+        })
+}
+
+/// Formats a number of bytes with the largest unit that keeps it above one.
+pub(super) fn format_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+
+    for next in &UNITS[1..] {
+        if value < 1024.0 {
+            break;
+        }
+
+        value /= 1024.0;
+        unit = next;
+    }
+
+    format!("{value:.1} {unit}")
+}
+
+fn timings(check: &RecentCheck) -> Timings {
+    Timings {
+        dns_millis: check.dns_millis,
+        connect_millis: check.connect_millis,
+        tls_millis: check.tls_millis,
+        first_byte_millis: check.first_byte_millis,
+    }
+}
+
+/// Reads how long one phase of a check took from its timings.
+type Phase = fn(&Timings) -> Option<i64>;
+
+/// The median time each phase of `checks` took, which is not thrown off by the odd slow check
+/// the way a mean would be.
+fn phase_timings(checks: &[RecentCheck]) -> Vec<PhaseTiming> {
+    let phases: [(&'static str, Phase); 4] = [
+        ("DNS", |timings| timings.dns_millis),
+        ("Connect", |timings| timings.connect_millis),
+        ("TLS", |timings| timings.tls_millis),
+        ("First byte", |timings| timings.first_byte_millis),
+    ];
+
+    let timings: Vec<_> = checks.iter().map(timings).collect();
+
+    let mut phase_timings: Vec<_> = phases
+        .into_iter()
+        .filter_map(|(name, phase)| {
+            let mut values: Vec<_> = timings.iter().filter_map(phase).collect();
+            values.sort_unstable();
+
+            let millis = *values.get(values.len() / 2)?;
+
+            Some(PhaseTiming { name, millis })
+        })
+        .collect();
+
+    // Origins measuring both connection modes also time a whole request over a new connection
+    let mut cold: Vec<_> = checks
+        .iter()
+        .filter_map(|check| check.cold_latency_millis)
+        .collect();
+    cold.sort_unstable();
+
+    if let Some(millis) = cold.get(cold.len() / 2) {
+        phase_timings.push(PhaseTiming {
+            name: "Cold request",
+            millis: *millis,
+        });
+    }
+
+    phase_timings
+}
+
+fn check_bars(checks: &[RecentCheck]) -> Vec<CheckBar> {
+    let slowest = checks
+        .iter()
+        .filter_map(|check| check.latency_millis)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    checks
+        .iter()
+        .map(|check| {
+            let queried_at = check.queried_at.format("%Y-%m-%d %H:%M:%S UTC");
+            let status = match check.status {
+                Some(status) => format!("{status} in "),
+                None => String::new(),
+            };
+
+            // Origins checked over both families have a check for each, so say which this was
+            let queried_at = match &check.address_family {
+                Some(family) => {
+                    format!(
+                        "{queried_at} over {}",
+                        AddressFamily::from_column(family).name()
+                    )
+                }
+                None => queried_at.to_string(),
+            };
+
+            // Origins checked from several locations have a check from each of them
+            let queried_at = match &check.location {
+                Some(location) => format!("{queried_at} from {location}"),
+                None => queried_at,
+            };
+
+            let size = check
+                .body_bytes
+                .map(|body_bytes| format!(", {}", format_bytes(body_bytes)))
+                .unwrap_or_default();
+
+            let http_version = check
+                .http_version
+                .as_ref()
+                .map(|http_version| format!(" over {http_version}"))
+                .unwrap_or_default();
+
+            let attempts = check
+                .attempts
+                .map(|attempts| format!(" after {attempts} attempts"))
+                .unwrap_or_default();
+
+            let captured_headers = check
+                .captured_headers
+                .as_ref()
+                .map(|headers| format!(" [{}]", headers.replace('\n', "; ")))
+                .unwrap_or_default();
+
+            let phases = timings(check)
+                .describe()
+                .map(|phases| format!(" ({phases})"))
+                .unwrap_or_default();
+
+            let cold = check
+                .cold_latency_millis
+                .map(|cold_latency_millis| format!(", {cold_latency_millis}ms cold"))
+                .unwrap_or_default();
+
+            let throughput = check
+                .throughput_bytes_per_second
+                .map(|throughput| format!(" at {}", format_throughput(throughput)))
+                .unwrap_or_default();
+
+            let clock = check
+                .clock_offset_millis
+                .map(|offset| match offset {
+                    0.. => format!(", clock {offset}ms ahead"),
+                    _ => format!(", clock {}ms behind", -offset),
+                })
+                .unwrap_or_default();
+
+            let served_from = check
+                .remote_address
+                .as_ref()
+                .map(|remote_address| format!(" from {remote_address}"))
+                .unwrap_or_default();
+
+            match (check.latency_millis, &check.failure_reason) {
+                (Some(latency_millis), _) => CheckBar {
+                    success: true,
+                    // Keep fast checks visible rather than letting them shrink to nothing
+                    height: (latency_millis * 100 / slowest).clamp(5, 100) as u8,
+                    label: match &check.final_url {
+                        Some(final_url) => format!(
+                            "{queried_at}: {status}{latency_millis}ms{cold}{phases}{size}{throughput}{clock}{http_version}{served_from}{attempts}{captured_headers} (redirected to {final_url})"
+                        ),
+                        None => format!("{queried_at}: {status}{latency_millis}ms{cold}{phases}{size}{throughput}{clock}{http_version}{served_from}{attempts}{captured_headers}"),
+                    },
+                },
+                (None, reason) => {
+                    let reason = reason.as_deref().unwrap_or("Failed");
+
+                    CheckBar {
+                        success: false,
+                        height: 100,
+                        label: match &check.failure_detail {
+                            Some(detail) => {
+                                format!("{queried_at}: {reason} ({detail}){served_from}{attempts}{captured_headers}")
+                            }
+                            None => format!("{queried_at}: {reason}{served_from}{attempts}{captured_headers}"),
+                        },
+                    }
+                }
+            }
+        })
+        .collect()
+}
+
+async fn render(
+    state: &ApplicationState,
+    origin_uid: Uuid,
+    read_only: bool,
+    share_link: Option<ShareLink>,
+) -> Result<RenderedTemplate, (StatusCode, String)> {
+    let ApplicationState {
+        pool,
+        template_engine,
+        credentials,
+        ..
+    } = state;
+
+    let origin = crate::persistence::fetch_origin(pool, origin_uid)
+        .await
+        .expect("failed to fetch origin")
+        .ok_or((StatusCode::NOT_FOUND, String::from("origin not found")))?;
+
+    let version = crate::persistence::fetch_origin_version(pool, origin_uid)
+        .await
+        .expect("failed to fetch origin version")
+        .unwrap_or_default();
+
+    let checks = crate::persistence::fetch_recent_checks(pool, origin_uid, RECENT_CHECK_COUNT)
+        .await
+        .expect("failed to fetch recent checks");
+
+    let locations = crate::persistence::fetch_location_statuses(
+        pool,
+        Some(origin_uid),
+        Utc::now() - regions::WINDOW,
+    )
+    .await
+    .expect("failed to fetch location statuses");
+
+    let incidents = crate::persistence::fetch_origin_incidents(
+        pool,
+        origin_uid,
+        origin
+            .alert_window
+            .start(Utc::now(), crate::reliability::DEFAULT_WINDOW),
+    )
+    .await
+    .expect("failed to fetch incidents");
+
+    let reliability = crate::reliability::measure(&incidents);
+
+    // Notes, the change log and network paths are for the team, so they are left out of shared
+    // views
+    let (notes, changes, traceroutes) = if read_only {
+        (None, Vec::new(), Vec::new())
+    } else {
+        let changes =
+            crate::persistence::fetch_origin_changes(pool, origin_uid, RECENT_CHANGE_COUNT)
+                .await
+                .expect("failed to fetch origin changes");
+
+        let traceroutes = crate::persistence::fetch_traceroutes(
+            pool,
+            origin_uid,
+            Utc::now() - crate::reliability::DEFAULT_WINDOW,
+        )
+        .await
+        .expect("failed to fetch traceroutes");
+
+        (
+            origin.notes,
+            changes.into_iter().map(ChangeEntry::from).collect(),
+            traceroutes.into_iter().map(TracerouteEntry::from).collect(),
+        )
+    };
+
+    let is_heartbeat = origin.uri.starts_with("heartbeat://");
+
+    let heartbeat = if is_heartbeat && !read_only {
+        crate::persistence::fetch_heartbeat(pool, origin_uid)
+            .await
+            .expect("failed to fetch heartbeat")
+            .map(|heartbeat| HeartbeatEntry {
+                path: format!("/heartbeat/{}", heartbeat.token),
+                grace_minutes: heartbeat.grace_seconds / 60,
+                last_ping: heartbeat.last_ping_at.map(time_since),
+            })
+    } else {
+        None
+    };
+
+    let is_journey = origin.uri.starts_with("journey://");
+
+    let journey_steps = if is_journey && !read_only {
+        crate::persistence::fetch_journey_steps(pool, origin_uid)
+            .await
+            .expect("failed to fetch journey steps")
+            .into_iter()
+            .map(JourneyStepEntry::from)
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let redirect_chains =
+        crate::persistence::fetch_redirect_chains(pool, origin_uid, RECENT_REDIRECT_CHAIN_COUNT)
+            .await
+            .expect("failed to fetch redirect chains")
+            .into_iter()
+            .map(RedirectChainEntry::from)
+            .collect();
+
+    let body_changes =
+        crate::persistence::fetch_body_changes(pool, origin_uid, RECENT_BODY_CHANGE_COUNT)
+            .await
+            .expect("failed to fetch body changes")
+            .into_iter()
+            .map(BodyChangeEntry::from)
+            .collect();
+
+    let domain_registration = crate::persistence::fetch_domain_registration(pool, origin_uid)
+        .await
+        .expect("failed to fetch domain registration")
+        .map(DomainRegistrationEntry::from);
+
+    // Baselines name other origins, so they are also left out of shared views
+    let (baseline, baseline_candidates) = if read_only {
+        (None, Vec::new())
+    } else {
+        let origins = crate::persistence::fetch_origins(pool)
+            .await
+            .expect("failed to fetch origins");
+
+        let baseline = match origins
+            .iter()
+            .find(|candidate| Some(candidate.origin_uid) == origin.baseline_origin_uid)
+        {
+            Some(baseline) => Some(Baseline {
+                origin_uid: baseline.origin_uid,
+                uri: baseline.uri.clone(),
+                relative_latency_millis: crate::persistence::fetch_relative_latency(
+                    pool,
+                    origin_uid,
+                    baseline.origin_uid,
+                    Utc::now() - RELATIVE_LATENCY_WINDOW,
+                )
+                .await
+                .expect("failed to fetch relative latency"),
+                limit_millis: origin.relative_latency_limit_millis,
+            }),
+            None => None,
+        };
+
+        let candidates = origins
+            .into_iter()
+            .filter(|candidate| candidate.origin_uid != origin_uid)
+            .map(|candidate| BaselineCandidate {
+                selected: Some(candidate.origin_uid) == origin.baseline_origin_uid,
+                origin_uid: candidate.origin_uid,
+                uri: candidate.uri,
+            })
+            .collect();
+
+        (baseline, candidates)
+    };
+
+    let latest = checks.last();
+    let successes: Vec<_> = checks.iter().filter_map(|c| c.latency_millis).collect();
+
+    let uptime = (!checks.is_empty())
+        .then(|| successes.len() as f64 / checks.len() as f64 * 100.0)
+        .map(|uptime| format!("{uptime:.1}%"));
+
+    let average_latency_millis = (!successes.is_empty())
+        .then(|| successes.iter().sum::<i64>() as u64 / successes.len() as u64);
+
+    let mut body_sizes: Vec<_> = checks.iter().filter_map(|c| c.body_bytes).collect();
+    let body_size = body_sizes.last().copied().map(format_bytes);
+
+    // The median is not thrown off by the occasional error page the way a mean would be
+    body_sizes.sort_unstable();
+    let typical_body_size = body_sizes
+        .get(body_sizes.len() / 2)
+        .copied()
+        .map(format_bytes);
+
+    let context = OriginContext {
+        origin_uid,
+        uri: origin.uri,
+        version,
+        operational: latest.map(|check| check.failure_reason.is_none()),
+        queried: latest.map(|check| time_since(check.queried_at)),
+        uptime,
+        outage: Outage::of(&locations),
+        regions: locations
+            .into_iter()
+            .map(|location| Region {
+                location: location.location,
+                operational: location.operational,
+                uptime: format!("{:.1}%", location.uptime * 100.0),
+                queried: time_since(location.queried_at),
+            })
+            .collect(),
+        regions_window_hours: regions::WINDOW.num_hours(),
+        average_latency_millis,
+        body_size,
+        typical_body_size,
+        phase_timings: phase_timings(&checks),
+        checks: check_bars(&checks),
+        incidents: reliability.incidents,
+        mean_time_to_recovery: format_mean(reliability.mean_time_to_recovery),
+        mean_time_between_failures: format_mean(reliability.mean_time_between_failures),
+        reliability_window_days: crate::reliability::DEFAULT_WINDOW.num_days(),
+        notes,
+        changes,
+        traceroutes,
+        redirect_chains,
+        body_change_detection: origin.body_change_detection,
+        body_changes,
+        domain_registration,
+        baseline,
+        baseline_candidates,
+        relative_latency_window_minutes: RELATIVE_LATENCY_WINDOW.num_minutes(),
+        timeout_millis: origin.timeout_millis,
+        max_timeout_millis: MAX_TIMEOUT_MILLIS,
+        expected_latency_millis: origin.expected_latency_millis,
+        retries: origin.retries,
+        max_retries: MAX_RETRIES,
+        schedule: origin.schedule,
+        address_family: origin.address_family,
+        address_families: AddressFamily::ALL
+            .iter()
+            .map(|family| AddressFamilyOption {
+                value: family.as_str(),
+                name: family.name(),
+            })
+            .collect(),
+        http_version: origin.http_version,
+        http_versions: HttpVersion::ALL
+            .iter()
+            .map(|version| HttpVersionOption {
+                value: version.as_str(),
+                name: version.name(),
+            })
+            .collect(),
+        connection_mode: origin.connection_mode,
+        connection_modes: ConnectionMode::ALL
+            .iter()
+            .map(|mode| ConnectionModeOption {
+                value: mode.as_str(),
+                name: mode.name(),
+            })
+            .collect(),
+        negotiated_http_version: checks
+            .iter()
+            .rev()
+            .find(|check| check.latency_millis.is_some())
+            .and_then(|check| check.http_version.clone()),
+        alert_window: origin.alert_window,
+        alert_windows: AlertWindow::ALL
+            .iter()
+            .map(|window| AlertWindowOption {
+                value: window.as_str(),
+                name: window.name(),
+            })
+            .collect(),
+        reliability_period: reliability_period(origin.alert_window),
+        proxy: origin.proxy,
+        user_agent: origin.user_agent,
+        cache_busting: origin.cache_busting,
+        expected_networks: origin.expected_networks,
+        probe_locations: origin.probe_locations,
+        captured_headers: origin.captured_headers,
+        expected_headers: origin.expected_headers,
+        is_heartbeat,
+        heartbeat,
+        max_heartbeat_grace_minutes: MAX_GRACE_MINUTES,
+        is_journey,
+        journey_steps,
+        max_journey_steps: MAX_STEPS,
+        remote_address: checks
+            .iter()
+            .rev()
+            .find(|check| check.latency_millis.is_some())
+            .and_then(|check| check.remote_address.clone()),
+        has_credentials: origin.encrypted_credentials.is_some(),
+        has_client_certificate: origin.encrypted_client_identity.is_some(),
+        credentials_configurable: credentials.is_some(),
+        read_only,
+        share_link,
+        max_share_link_hours: MAX_SHARE_LINK_HOURS,
+    };
+
+    Ok(template_engine
+        .render_serialized("origin.tera.html", &context)
+        .expect("failed to render template"))
+}
+
+pub async fn detail(
+    State(state): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+) -> Result<RenderedTemplate, (StatusCode, String)> {
+    render(&state, origin_uid, false, None).await
+}
+
+/// Checks an origin straight away, waiting for the result so it is on the page redirected to.
+pub async fn check_now(
+    State(ApplicationState { pool, poller, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+) -> Result<Redirect, (StatusCode, String)> {
+    let origin = crate::persistence::fetch_origin(&pool, origin_uid)
+        .await
+        .expect("failed to fetch origin")
+        .ok_or((StatusCode::NOT_FOUND, String::from("origin not found")))?;
+
+    match poller
+        .check_now(origin_uid, check_now_timeout(&origin))
+        .await
+    {
+        Err(CheckNowError::Busy) => Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            String::from("the poller is too busy to take more requests, try again shortly"),
+        )),
+        // The check will still show up once it finishes
+        Ok(_) | Err(CheckNowError::TimedOut) => Ok(Redirect::to(&format!("/origins/{origin_uid}"))),
+    }
+}
+
+#[derive(Serialize)]
+struct InspectContext {
+    origin_uid: Uuid,
+    uri: String,
+    inspection: Option<Inspection>,
+    failure: Option<String>,
+}
+
+/// Makes a request to an origin and shows everything about the response, for admins debugging
+/// it.
+pub async fn inspect(
+    State(ApplicationState {
+        pool,
+        template_engine,
+        approval_policy,
+        inspector,
+        ..
+    }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    user: Option<User>,
+) -> Result<RenderedTemplate, (StatusCode, String)> {
+    let Some(User(user)) = user else {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            String::from("the request did not identify a user"),
+        ));
+    };
+
+    // Inspections use the credentials of the origin and show everything it responds with
+    if !approval_policy.is_admin(&user) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            String::from("only admins can inspect origins"),
+        ));
+    }
+
+    let origin = crate::persistence::fetch_origin(&pool, origin_uid)
+        .await
+        .expect("failed to fetch origin")
+        .ok_or((StatusCode::NOT_FOUND, String::from("origin not found")))?;
+
+    tracing::info!(%origin_uid, %user, "inspecting an origin");
+
+    let (inspection, failure) = match inspector.inspect(&origin).await {
+        Ok(inspection) => (Some(inspection), None),
+        Err(failure) => {
+            let failure = match failure.detail {
+                Some(detail) => format!("{}: {detail}", failure.reason),
+                None => failure.reason.to_string(),
+            };
+
+            (None, Some(failure))
+        }
+    };
+
+    let context = InspectContext {
+        origin_uid,
+        uri: origin.uri,
+        inspection,
+        failure,
+    };
+
+    Ok(template_engine
+        .render_serialized("inspect.tera.html", &context)
+        .expect("failed to render template"))
+}
+
+#[derive(Deserialize)]
+pub struct NotesUpdateRequest {
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    notes: Option<String>,
+}
+
+pub async fn update_notes(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(NotesUpdateRequest { notes }): Form<NotesUpdateRequest>,
+) -> Redirect {
+    crate::persistence::set_origin_notes(&pool, origin_uid, notes.as_deref())
+        .await
+        .expect("failed to set origin notes");
+
+    Redirect::to(&format!("/origins/{origin_uid}"))
+}
+
+#[derive(Deserialize)]
+pub struct BaselineUpdateRequest {
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    baseline_origin_uid: Option<Uuid>,
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    relative_latency_limit_millis: Option<i64>,
+}
+
+pub async fn update_baseline(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(BaselineUpdateRequest {
+        baseline_origin_uid,
+        relative_latency_limit_millis,
+    }): Form<BaselineUpdateRequest>,
+) -> Result<Redirect, (StatusCode, String)> {
+    if baseline_origin_uid == Some(origin_uid) {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            String::from("an origin cannot be its own baseline"),
+        ));
+    }
+
+    if relative_latency_limit_millis.is_some_and(|limit| limit < 0) {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            String::from("the relative latency limit cannot be negative"),
+        ));
+    }
+
+    if let Some(baseline_origin_uid) = baseline_origin_uid {
+        crate::persistence::fetch_origin(&pool, baseline_origin_uid)
+            .await
+            .expect("failed to fetch origin")
+            .ok_or((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                String::from("the baseline origin does not exist"),
+            ))?;
+    }
+
+    crate::persistence::set_origin_baseline(
+        &pool,
+        origin_uid,
+        baseline_origin_uid,
+        relative_latency_limit_millis,
+    )
+    .await
+    .expect("failed to set origin baseline");
+
+    Ok(Redirect::to(&format!("/origins/{origin_uid}")))
+}
+
+#[derive(Deserialize)]
+pub struct TimeoutUpdateRequest {
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    timeout_millis: Option<i64>,
+}
+
+pub async fn update_timeout(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(TimeoutUpdateRequest { timeout_millis }): Form<TimeoutUpdateRequest>,
+) -> Result<Redirect, (StatusCode, String)> {
+    validate_timeout(timeout_millis)?;
+
+    crate::persistence::set_origin_timeout(&pool, origin_uid, timeout_millis)
+        .await
+        .expect("failed to set origin timeout");
+
+    Ok(Redirect::to(&format!("/origins/{origin_uid}")))
+}
+
+#[derive(Deserialize)]
+pub struct ExpectedLatencyUpdateRequest {
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    expected_latency_millis: Option<i64>,
+}
+
+pub async fn update_expected_latency(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(ExpectedLatencyUpdateRequest {
+        expected_latency_millis,
+    }): Form<ExpectedLatencyUpdateRequest>,
+) -> Result<Redirect, (StatusCode, String)> {
+    if expected_latency_millis.is_some_and(|latency| latency <= 0) {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            String::from("the expected latency must be positive"),
+        ));
+    }
+
+    crate::persistence::set_origin_expected_latency(&pool, origin_uid, expected_latency_millis)
+        .await
+        .expect("failed to set origin expected latency");
+
+    Ok(Redirect::to(&format!("/origins/{origin_uid}")))
+}
+
+#[derive(Deserialize)]
+pub struct RetriesUpdateRequest {
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    retries: Option<i32>,
+}
+
+pub async fn update_retries(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(RetriesUpdateRequest { retries }): Form<RetriesUpdateRequest>,
+) -> Result<Redirect, (StatusCode, String)> {
+    let retries = retries.unwrap_or_default();
+    validate_retries(retries)?;
+
+    crate::persistence::set_origin_retries(&pool, origin_uid, retries)
+        .await
+        .expect("failed to set origin retries");
+
+    Ok(Redirect::to(&format!("/origins/{origin_uid}")))
+}
+
+#[derive(Deserialize)]
+pub struct ScheduleUpdateRequest {
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    schedule: Option<String>,
+}
+
+pub async fn update_schedule(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(ScheduleUpdateRequest { schedule }): Form<ScheduleUpdateRequest>,
+) -> Result<Redirect, (StatusCode, String)> {
+    if let Some(schedule) = &schedule {
+        CronSchedule::parse(schedule).map_err(|e| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("invalid schedule: {e}"),
+            )
+        })?;
+    }
+
+    crate::persistence::set_origin_schedule(&pool, origin_uid, schedule.as_deref())
+        .await
+        .expect("failed to set origin schedule");
+
+    Ok(Redirect::to(&format!("/origins/{origin_uid}")))
+}
+
+#[derive(Deserialize)]
+pub struct AddressFamilyUpdateRequest {
+    address_family: AddressFamily,
+}
+
+pub async fn update_address_family(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(AddressFamilyUpdateRequest { address_family }): Form<AddressFamilyUpdateRequest>,
+) -> Redirect {
+    crate::persistence::set_origin_address_family(&pool, origin_uid, address_family)
+        .await
+        .expect("failed to set origin address family");
+
+    Redirect::to(&format!("/origins/{origin_uid}"))
+}
+
+#[derive(Deserialize)]
+pub struct HttpVersionUpdateRequest {
+    http_version: HttpVersion,
+}
+
+pub async fn update_http_version(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(HttpVersionUpdateRequest { http_version }): Form<HttpVersionUpdateRequest>,
+) -> Redirect {
+    crate::persistence::set_origin_http_version(&pool, origin_uid, http_version)
+        .await
+        .expect("failed to set origin HTTP version");
+
+    Redirect::to(&format!("/origins/{origin_uid}"))
+}
+
+#[derive(Deserialize)]
+pub struct ConnectionModeUpdateRequest {
+    connection_mode: ConnectionMode,
+}
+
+pub async fn update_connection_mode(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(ConnectionModeUpdateRequest { connection_mode }): Form<ConnectionModeUpdateRequest>,
+) -> Redirect {
+    crate::persistence::set_origin_connection_mode(&pool, origin_uid, connection_mode)
+        .await
+        .expect("failed to set origin connection mode");
+
+    Redirect::to(&format!("/origins/{origin_uid}"))
+}
+
+#[derive(Deserialize)]
+pub struct AlertWindowUpdateRequest {
+    alert_window: AlertWindow,
+}
+
+pub async fn update_alert_window(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(AlertWindowUpdateRequest { alert_window }): Form<AlertWindowUpdateRequest>,
+) -> Redirect {
+    crate::persistence::set_origin_alert_window(&pool, origin_uid, alert_window)
+        .await
+        .expect("failed to set origin alert window");
+
+    Redirect::to(&format!("/origins/{origin_uid}"))
+}
+
+#[derive(Deserialize)]
+pub struct ProxyUpdateRequest {
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    proxy: Option<String>,
+}
+
+pub async fn update_proxy(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(ProxyUpdateRequest { proxy }): Form<ProxyUpdateRequest>,
+) -> Result<Redirect, (StatusCode, String)> {
+    validate_proxy(proxy.as_deref())?;
+
+    crate::persistence::set_origin_proxy(&pool, origin_uid, proxy.as_deref())
+        .await
+        .expect("failed to set origin proxy");
+
+    Ok(Redirect::to(&format!("/origins/{origin_uid}")))
+}
+
+#[derive(Deserialize)]
+pub struct RequestOptionsUpdateRequest {
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    user_agent: Option<String>,
+    #[serde(default)]
+    cache_busting: bool,
+}
+
+pub async fn update_request_options(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(RequestOptionsUpdateRequest {
+        user_agent,
+        cache_busting,
+    }): Form<RequestOptionsUpdateRequest>,
+) -> Result<Redirect, (StatusCode, String)> {
+    validate_user_agent(user_agent.as_deref())?;
+
+    crate::persistence::set_origin_request_options(
+        &pool,
+        origin_uid,
+        user_agent.as_deref(),
+        cache_busting,
+    )
+    .await
+    .expect("failed to set origin request options");
+
+    Ok(Redirect::to(&format!("/origins/{origin_uid}")))
+}
+
+#[derive(Deserialize)]
+pub struct BodyChangeDetectionUpdateRequest {
+    #[serde(default)]
+    body_change_detection: bool,
+}
+
+pub async fn update_body_change_detection(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(BodyChangeDetectionUpdateRequest {
+        body_change_detection,
+    }): Form<BodyChangeDetectionUpdateRequest>,
+) -> Redirect {
+    crate::persistence::set_origin_body_change_detection(&pool, origin_uid, body_change_detection)
+        .await
+        .expect("failed to set origin body change detection");
+
+    Redirect::to(&format!("/origins/{origin_uid}"))
+}
+
+#[derive(Deserialize)]
+pub struct ExpectedNetworksUpdateRequest {
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    expected_networks: Option<String>,
+}
+
+pub async fn update_expected_networks(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(ExpectedNetworksUpdateRequest { expected_networks }): Form<ExpectedNetworksUpdateRequest>,
+) -> Result<Redirect, (StatusCode, String)> {
+    if let Some(expected_networks) = &expected_networks {
+        ExpectedNetworks::parse(expected_networks).map_err(|e| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("invalid expected networks: {e}"),
+            )
+        })?;
+    }
+
+    crate::persistence::set_origin_expected_networks(
+        &pool,
+        origin_uid,
+        expected_networks.as_deref(),
+    )
+    .await
+    .expect("failed to set origin expected networks");
+
+    Ok(Redirect::to(&format!("/origins/{origin_uid}")))
+}
+
+#[derive(Deserialize)]
+pub struct ProbeLocationsUpdateRequest {
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    probe_locations: Option<String>,
+}
+
+pub async fn update_probe_locations(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(ProbeLocationsUpdateRequest { probe_locations }): Form<ProbeLocationsUpdateRequest>,
+) -> Result<Redirect, (StatusCode, String)> {
+    if let Some(probe_locations) = &probe_locations {
+        ProbeLocations::parse(probe_locations).map_err(|e| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("invalid probe locations: {e}"),
+            )
+        })?;
+    }
+
+    crate::persistence::set_origin_probe_locations(&pool, origin_uid, probe_locations.as_deref())
+        .await
+        .expect("failed to set origin probe locations");
+
+    Ok(Redirect::to(&format!("/origins/{origin_uid}")))
+}
+
+#[derive(Deserialize)]
+pub struct CapturedHeadersUpdateRequest {
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    captured_headers: Option<String>,
+}
+
+pub async fn update_captured_headers(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(CapturedHeadersUpdateRequest { captured_headers }): Form<CapturedHeadersUpdateRequest>,
+) -> Result<Redirect, (StatusCode, String)> {
+    if let Some(captured_headers) = &captured_headers {
+        CapturedHeaders::parse(captured_headers).map_err(|e| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("invalid captured headers: {e}"),
+            )
+        })?;
+    }
+
+    crate::persistence::set_origin_captured_headers(&pool, origin_uid, captured_headers.as_deref())
+        .await
+        .expect("failed to set origin captured headers");
+
+    Ok(Redirect::to(&format!("/origins/{origin_uid}")))
+}
+
+#[derive(Deserialize)]
+pub struct ExpectedHeadersUpdateRequest {
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    expected_headers: Option<String>,
+}
+
+pub async fn update_expected_headers(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(ExpectedHeadersUpdateRequest { expected_headers }): Form<ExpectedHeadersUpdateRequest>,
+) -> Result<Redirect, (StatusCode, String)> {
+    if let Some(expected_headers) = &expected_headers {
+        ExpectedHeaders::parse(expected_headers).map_err(|e| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("invalid expected headers: {e}"),
+            )
+        })?;
+    }
+
+    crate::persistence::set_origin_expected_headers(&pool, origin_uid, expected_headers.as_deref())
+        .await
+        .expect("failed to set origin expected headers");
+
+    Ok(Redirect::to(&format!("/origins/{origin_uid}")))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CredentialKind {
+    None,
+    Basic,
+    Bearer,
+}
+
+#[derive(Deserialize)]
+pub struct CredentialsUpdateRequest {
+    kind: CredentialKind,
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    username: Option<String>,
+    #[serde(default)]
+    password: String,
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    token: Option<String>,
+}
+
+impl CredentialsUpdateRequest {
+    fn into_credentials(self) -> Result<Option<Credentials>, (StatusCode, String)> {
+        let invalid = |message: &str| (StatusCode::UNPROCESSABLE_ENTITY, message.to_owned());
+
+        let credentials = match self.kind {
+            CredentialKind::None => return Ok(None),
+            CredentialKind::Basic => {
+                let username = self
+                    .username
+                    .ok_or_else(|| invalid("basic credentials need a username"))?;
+
+                // The username ends at the first colon, so it cannot contain one itself
+                if username.contains(':') {
+                    return Err(invalid("usernames cannot contain a colon"));
+                }
+
+                Credentials::Basic {
+                    username,
+                    password: self.password,
+                }
+            }
+            CredentialKind::Bearer => Credentials::Bearer {
+                token: self
+                    .token
+                    .ok_or_else(|| invalid("bearer credentials need a token"))?,
+            },
+        };
+
+        credentials
+            .authorization()
+            .map_err(|e| invalid(&e.to_string()))?;
+
+        Ok(Some(credentials))
+    }
+}
+
+pub async fn update_credentials(
+    State(ApplicationState {
+        pool, credentials, ..
+    }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(request): Form<CredentialsUpdateRequest>,
+) -> Result<Redirect, (StatusCode, String)> {
+    let encrypted = match request.into_credentials()? {
+        Some(update) => {
+            let cipher = credentials.ok_or((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                String::from("credentials cannot be stored until CREDENTIALS_KEY is set"),
+            ))?;
+
+            let encrypted = cipher
+                .encrypt(origin_uid, &update)
+                .expect("failed to encrypt credentials");
+
+            Some(encrypted)
+        }
+        None => None,
+    };
+
+    crate::persistence::set_origin_credentials(&pool, origin_uid, encrypted.as_deref())
+        .await
+        .expect("failed to set origin credentials");
+
+    Ok(Redirect::to(&format!("/origins/{origin_uid}")))
+}
+
+#[derive(Deserialize)]
+pub struct ClientCertificateUpdateRequest {
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    certificate: Option<String>,
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    private_key: Option<String>,
+}
+
+pub async fn update_client_certificate(
+    State(ApplicationState {
+        pool, credentials, ..
+    }): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(ClientCertificateUpdateRequest {
+        certificate,
+        private_key,
+    }): Form<ClientCertificateUpdateRequest>,
+) -> Result<Redirect, (StatusCode, String)> {
+    let invalid = |message: String| (StatusCode::UNPROCESSABLE_ENTITY, message);
+
+    let encrypted = match (certificate, private_key) {
+        (None, None) => None,
+        (Some(certificate), Some(private_key)) => {
+            let cipher = credentials.ok_or_else(|| {
+                invalid(String::from(
+                    "client certificates cannot be stored until CREDENTIALS_KEY is set",
+                ))
+            })?;
+
+            // Identities are read from a single PEM bundle holding the key and its chain
+            let pem = format!("{}\n{}\n", private_key.trim(), certificate.trim());
+
+            reqwest::Identity::from_pem(pem.as_bytes())
+                .map_err(|e| invalid(format!("invalid client certificate or key: {e}")))?;
+
+            let encrypted = cipher
+                .encrypt_identity(origin_uid, pem.as_bytes())
+                .expect("failed to encrypt client certificate");
+
+            Some(encrypted)
+        }
+        _ => {
+            return Err(invalid(String::from(
+                "client certificates need both a certificate and a private key",
+            )))
+        }
+    };
+
+    crate::persistence::set_origin_client_identity(&pool, origin_uid, encrypted.as_deref())
+        .await
+        .expect("failed to set origin client certificate");
+
+    Ok(Redirect::to(&format!("/origins/{origin_uid}")))
+}
+
+#[derive(Deserialize)]
+pub struct ShareLinkRequest {
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    expires_in_hours: Option<i64>,
+}
+
+pub async fn create_share_link(
+    State(state): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Form(ShareLinkRequest { expires_in_hours }): Form<ShareLinkRequest>,
+) -> Result<RenderedTemplate, (StatusCode, String)> {
+    let hours = expires_in_hours.unwrap_or(DEFAULT_SHARE_LINK_HOURS);
+
+    if !(1..=MAX_SHARE_LINK_HOURS).contains(&hours) {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("share links must expire within 1 to {MAX_SHARE_LINK_HOURS} hours"),
+        ));
+    }
+
+    let expires_at = Utc::now() + Duration::hours(hours);
+    let share_link = ShareLink {
+        path: state.share_links.sign(origin_uid, expires_at),
+        expires_at: expires_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+    };
+
+    tracing::info!(%origin_uid, %expires_at, "created a share link");
+
+    render(&state, origin_uid, false, Some(share_link)).await
+}
+
+#[derive(Deserialize)]
+pub struct SharedQuery {
+    expires: i64,
+    signature: String,
+}
+
+pub async fn shared(
+    State(state): State<ApplicationState>,
+    Path(origin_uid): Path<Uuid>,
+    Query(SharedQuery { expires, signature }): Query<SharedQuery>,
+) -> Result<RenderedTemplate, (StatusCode, String)> {
+    if !state
+        .share_links
+        .verify(origin_uid, expires, &signature, Utc::now())
+    {
+        return Err((
+            StatusCode::FORBIDDEN,
+            String::from("this link is invalid or has expired"),
+        ));
+    }
+
+    render(&state, origin_uid, true, None).await
+}
+
+#[cfg(test)]
+mod tests {
+    use color_eyre::eyre::Result;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    use crate::persistence::Visibility;
+
+    #[sqlx::test]
+    async fn configuration_edits_are_logged_but_notes_are_not(pool: PgPool) -> Result<()> {
+        let origin_uid = Uuid::new_v4();
+        crate::persistence::insert_origin(&pool, origin_uid, "https://example.com").await?;
+
+        crate::persistence::set_origin_visibility(&pool, origin_uid, Visibility::Public).await?;
+        crate::persistence::set_origin_notes(&pool, origin_uid, Some("Behind Cloudflare")).await?;
+        crate::persistence::set_origin_forbidden_text(&pool, origin_uid, Some("maintenance"))
+            .await?;
+
+        let changes = crate::persistence::fetch_origin_changes(&pool, origin_uid, 10).await?;
+        let fields: Vec<_> = changes.iter().map(|c| c.field.as_str()).collect();
+
+        assert_eq!(fields, ["forbidden_text", "visibility"]);
+        assert_eq!(changes[1].old_value.as_deref(), Some("private"));
+        assert_eq!(changes[1].new_value.as_deref(), Some("public"));
+        assert_eq!(changes[0].old_value, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn byte_counts_use_the_largest_fitting_unit() {
+        assert_eq!(super::format_bytes(0), "0 B");
+        assert_eq!(super::format_bytes(1023), "1023 B");
+        assert_eq!(super::format_bytes(1536), "1.5 KB");
+        assert_eq!(super::format_bytes(5 * 1024 * 1024), "5.0 MB");
+        assert_eq!(super::format_bytes(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+}
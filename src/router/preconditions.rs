@@ -0,0 +1,321 @@
+use axum::body::Body;
+use axum::extract::{FromRequest, Path, Request, State};
+use axum::http::header::{ETAG, IF_MATCH};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Form;
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::router::ApplicationState;
+
+/// The largest request body buffered, matching the default limit of the extractors.
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+#[derive(Deserialize)]
+pub struct OriginPath {
+    origin_uid: Uuid,
+}
+
+/// The version of the origin a dashboard form was rendered with, which browsers cannot send as
+/// an `If-Match` header.
+#[derive(Deserialize)]
+struct VersionField {
+    #[serde(default, deserialize_with = "crate::router::empty_string_as_none")]
+    version: Option<i64>,
+}
+
+/// The version an edit was made from, if the client said.
+#[derive(Debug, PartialEq, Eq)]
+enum Precondition {
+    /// An `If-Match` header, which is compared with the entity tag of the current version.
+    IfMatch(String),
+    /// The `version` field of a submitted form.
+    Version(i64),
+    None,
+}
+
+impl Precondition {
+    async fn from_request(headers: &HeaderMap, body: &[u8]) -> Result<Self, &'static str> {
+        if let Some(if_match) = headers.get(IF_MATCH) {
+            let if_match = if_match
+                .to_str()
+                .map_err(|_| "the If-Match header must be printable ASCII")?;
+
+            return Ok(Self::IfMatch(if_match.to_owned()));
+        }
+
+        let mut request = Request::new(Body::from(body.to_vec()));
+        *request.method_mut() = axum::http::Method::POST;
+        *request.headers_mut() = headers.clone();
+
+        // Requests without a form, such as JSON ones, have no version field to check
+        let version = Form::<VersionField>::from_request(request, &())
+            .await
+            .ok()
+            .and_then(|Form(field)| field.version);
+
+        Ok(version.map_or(Self::None, Self::Version))
+    }
+
+    /// Whether the edit was made from `version`, or did not say what it was made from.
+    fn is_met_by(&self, version: i64) -> bool {
+        match self {
+            Self::IfMatch(if_match) => if_match.split(',').map(str::trim).any(|tag| {
+                // Weak tags never match, as edits need the exact version
+                tag == "*" || tag == entity_tag(version)
+            }),
+            Self::Version(expected) => *expected == version,
+            Self::None => true,
+        }
+    }
+
+    /// Whether the edit was made from a particular version, rather than from any at all.
+    fn names_version(&self) -> bool {
+        match self {
+            Self::IfMatch(if_match) => if_match.split(',').all(|tag| tag.trim() != "*"),
+            Self::Version(_) => true,
+            Self::None => false,
+        }
+    }
+
+    fn failed(&self) -> Response {
+        match self {
+            Self::IfMatch(_) => (
+                StatusCode::PRECONDITION_FAILED,
+                "the origin has been edited since the version in If-Match",
+            )
+                .into_response(),
+            _ => (
+                StatusCode::CONFLICT,
+                "the origin has been edited by someone else since this page was loaded, reload it \
+                 and try again",
+            )
+                .into_response(),
+        }
+    }
+}
+
+/// The entity tag of an origin at `version`.
+fn entity_tag(version: i64) -> String {
+    format!("\"{version}\"")
+}
+
+fn with_entity_tag(mut response: Response, version: i64) -> Response {
+    if let Ok(tag) = HeaderValue::from_str(&entity_tag(version)) {
+        response.headers_mut().insert(ETAG, tag);
+    }
+
+    response
+}
+
+/// Refuses edits to an origin made from an older version of it than the current one, so people
+/// editing it at the same time cannot overwrite each other's changes.
+///
+/// The version is sent as an `If-Match` header, or as the `version` field of dashboard forms, and
+/// responses carry the version as their `ETag`. Edits that do not say which version they were
+/// made from are carried out regardless. Edits that do say claim that version by incrementing it
+/// only if the origin is still at it, so of two edits made from the same version at once only one
+/// is carried out. The version is also bumped by the database whenever an edit changes the origin.
+pub async fn versioned(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Path(OriginPath { origin_uid }): Path<OriginPath>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method().is_safe() {
+        let response = next.run(request).await;
+
+        return tag_with_version(&pool, origin_uid, response).await;
+    }
+
+    let (parts, body) = request.into_parts();
+
+    let Ok(body) = axum::body::to_bytes(body, MAX_BODY_BYTES).await else {
+        return (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "the request body is too large",
+        )
+            .into_response();
+    };
+
+    let precondition = match Precondition::from_request(&parts.headers, &body).await {
+        Ok(precondition) => precondition,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
+    let Some(version) = crate::persistence::fetch_origin_version(&pool, origin_uid)
+        .await
+        .expect("failed to fetch origin version")
+    else {
+        if matches!(precondition, Precondition::IfMatch(_)) {
+            return precondition.failed();
+        }
+
+        // Whatever the request was for will report the origin missing
+        return next.run(Request::from_parts(parts, Body::from(body))).await;
+    };
+
+    if !precondition.is_met_by(version) {
+        tracing::info!(%origin_uid, version, ?precondition, "refused an edit of an outdated origin");
+
+        return precondition.failed();
+    }
+
+    let claimed = !precondition.names_version()
+        || crate::persistence::claim_origin_version(&pool, origin_uid, version)
+            .await
+            .expect("failed to claim origin version");
+
+    if !claimed {
+        tracing::info!(%origin_uid, version, "refused an edit of an origin edited at the same time");
+
+        return precondition.failed();
+    }
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+
+    tag_with_version(&pool, origin_uid, response).await
+}
+
+/// Tags a successful response with the version of the origin after the request was handled.
+async fn tag_with_version(pool: &PgPool, origin_uid: Uuid, response: Response) -> Response {
+    let status = response.status();
+
+    if !(status.is_success() || status.is_redirection()) {
+        return response;
+    }
+
+    let version = crate::persistence::fetch_origin_version(pool, origin_uid)
+        .await
+        .expect("failed to fetch origin version");
+
+    match version {
+        Some(version) => with_entity_tag(response, version),
+        None => response,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::header::{CONTENT_TYPE, IF_MATCH};
+    use axum::http::{HeaderMap, HeaderValue};
+    use color_eyre::eyre::Result;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    use crate::persistence::Direction;
+    use crate::router::preconditions::Precondition;
+
+    #[tokio::test]
+    async fn preconditions_come_from_if_match_or_the_form() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+
+        let precondition = Precondition::from_request(&headers, b"timeout_millis=500&version=3")
+            .await
+            .unwrap();
+        assert_eq!(precondition, Precondition::Version(3));
+
+        let precondition = Precondition::from_request(&headers, b"timeout_millis=500")
+            .await
+            .unwrap();
+        assert_eq!(precondition, Precondition::None);
+
+        headers.insert(IF_MATCH, HeaderValue::from_static("\"4\""));
+
+        let precondition = Precondition::from_request(&headers, b"version=3")
+            .await
+            .unwrap();
+        assert_eq!(precondition, Precondition::IfMatch(String::from("\"4\"")));
+    }
+
+    #[test]
+    fn preconditions_are_met_by_the_version_they_name() {
+        assert!(Precondition::IfMatch(String::from("\"2\"")).is_met_by(2));
+        assert!(Precondition::IfMatch(String::from("\"1\", \"2\"")).is_met_by(2));
+        assert!(Precondition::IfMatch(String::from("*")).is_met_by(2));
+        assert!(Precondition::Version(2).is_met_by(2));
+        assert!(Precondition::None.is_met_by(2));
+
+        assert!(!Precondition::IfMatch(String::from("\"1\"")).is_met_by(2));
+        assert!(!Precondition::IfMatch(String::from("W/\"2\"")).is_met_by(2));
+        assert!(!Precondition::Version(1).is_met_by(2));
+    }
+
+    #[test]
+    fn only_preconditions_naming_a_version_claim_it() {
+        assert!(Precondition::IfMatch(String::from("\"2\"")).names_version());
+        assert!(Precondition::Version(2).names_version());
+
+        assert!(!Precondition::IfMatch(String::from("*")).names_version());
+        assert!(!Precondition::None.names_version());
+    }
+
+    #[sqlx::test]
+    async fn versions_can_only_be_claimed_once(pool: PgPool) -> Result<()> {
+        let origin_uid = Uuid::new_v4();
+        crate::persistence::insert_origin(&pool, origin_uid, "https://example.com").await?;
+
+        assert!(crate::persistence::claim_origin_version(&pool, origin_uid, 1).await?);
+
+        // A second edit made from the same version lost the race
+        assert!(!crate::persistence::claim_origin_version(&pool, origin_uid, 1).await?);
+
+        let version = crate::persistence::fetch_origin_version(&pool, origin_uid).await?;
+        assert_eq!(version, Some(2));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn reordering_origins_does_not_change_their_versions(pool: PgPool) -> Result<()> {
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+        crate::persistence::insert_origin(&pool, first, "https://example.com").await?;
+        crate::persistence::insert_origin(&pool, second, "https://example.org").await?;
+
+        crate::persistence::move_origin(&pool, second, Direction::Up).await?;
+
+        assert_eq!(
+            crate::persistence::fetch_origin_version(&pool, first).await?,
+            Some(1)
+        );
+        assert_eq!(
+            crate::persistence::fetch_origin_version(&pool, second).await?,
+            Some(1)
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn only_edits_that_change_the_origin_increment_the_version(pool: PgPool) -> Result<()> {
+        let origin_uid = Uuid::new_v4();
+        crate::persistence::insert_origin(&pool, origin_uid, "https://example.com").await?;
+
+        let version = crate::persistence::fetch_origin_version(&pool, origin_uid).await?;
+        assert_eq!(version, Some(1));
+
+        crate::persistence::set_origin_paused(&pool, origin_uid, true).await?;
+
+        let version = crate::persistence::fetch_origin_version(&pool, origin_uid).await?;
+        assert_eq!(version, Some(2));
+
+        // Saving the same configuration again changes nothing
+        crate::persistence::set_origin_paused(&pool, origin_uid, true).await?;
+
+        let version = crate::persistence::fetch_origin_version(&pool, origin_uid).await?;
+        assert_eq!(version, Some(2));
+
+        let missing = crate::persistence::fetch_origin_version(&pool, Uuid::new_v4()).await?;
+        assert_eq!(missing, None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::Redirect;
+use axum::Form;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::locale::Locale;
+use crate::persistence::{DestinationKind, UserDestination, UserPreferences, WatchedOrigin};
+use crate::router::identity::User;
+use crate::router::{empty_string_as_none, ApplicationState};
+use crate::templates::RenderedTemplate;
+use crate::utils::parse_tags;
+
+#[derive(Serialize)]
+struct LocaleOption {
+    code: &'static str,
+    name: &'static str,
+}
+
+#[derive(Serialize)]
+struct PreferencesContext {
+    username: String,
+    timezone: String,
+    page_size: Option<i32>,
+    tag_filter: String,
+    destination: Option<UserDestination>,
+    locales: Vec<LocaleOption>,
+    watchlist: Vec<WatchedOrigin>,
+    push_public_key: Option<String>,
+    push_subscriptions: i64,
+}
+
+pub async fn preferences_page(
+    State(ApplicationState {
+        pool,
+        template_engine,
+        push_public_key,
+        ..
+    }): State<ApplicationState>,
+    User(username): User,
+) -> RenderedTemplate {
+    let preferences = crate::persistence::fetch_user_preferences(&pool, &username)
+        .await
+        .expect("failed to fetch user preferences");
+
+    let destination = crate::persistence::fetch_user_destination(&pool, &username)
+        .await
+        .expect("failed to fetch user destination");
+
+    let watchlist = crate::persistence::fetch_watchlist(&pool, &username)
+        .await
+        .expect("failed to fetch watchlist");
+
+    let push_subscriptions = crate::persistence::count_push_subscriptions(&pool, &username)
+        .await
+        .expect("failed to count push subscriptions");
+
+    let context = PreferencesContext {
+        username,
+        timezone: preferences.timezone,
+        page_size: preferences.page_size,
+        tag_filter: preferences.tag_filter.join(", "),
+        destination,
+        locales: Locale::ALL
+            .into_iter()
+            .map(|locale| LocaleOption {
+                code: locale.as_str(),
+                name: locale.name(),
+            })
+            .collect(),
+        watchlist,
+        push_public_key: push_public_key.as_deref().map(String::from),
+        push_subscriptions,
+    };
+
+    template_engine
+        .render_serialized("preferences.tera.html", &context)
+        .expect("failed to render template")
+}
+
+#[derive(Deserialize)]
+pub struct PreferencesUpdateRequest {
+    timezone: String,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    page_size: Option<i32>,
+    #[serde(default)]
+    tag_filter: String,
+}
+
+pub async fn update_preferences(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    User(username): User,
+    Form(PreferencesUpdateRequest {
+        timezone,
+        page_size,
+        tag_filter,
+    }): Form<PreferencesUpdateRequest>,
+) -> Result<Redirect, (StatusCode, String)> {
+    let timezone = timezone.trim();
+
+    if timezone.parse::<Tz>().is_err() {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("unknown timezone '{timezone}'"),
+        ));
+    }
+
+    if page_size.is_some_and(|size| size < 1) {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            String::from("page size must be at least 1"),
+        ));
+    }
+
+    let preferences = UserPreferences {
+        timezone: timezone.to_owned(),
+        page_size,
+        tag_filter: parse_tags(&tag_filter),
+    };
+
+    crate::persistence::upsert_user_preferences(&pool, &username, &preferences)
+        .await
+        .expect("failed to update user preferences");
+
+    Ok(Redirect::to("/"))
+}
+
+#[derive(Deserialize)]
+pub struct FavoriteUpdateRequest {
+    favorite: bool,
+}
+
+pub async fn update_favorite(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    User(username): User,
+    Path(origin_uid): Path<Uuid>,
+    Form(FavoriteUpdateRequest { favorite }): Form<FavoriteUpdateRequest>,
+) -> Redirect {
+    crate::persistence::set_favorite_origin(&pool, &username, origin_uid, favorite)
+        .await
+        .expect("failed to update favorite origin");
+
+    Redirect::to("/")
+}
+
+#[derive(Deserialize)]
+pub struct DestinationUpdateRequest {
+    kind: DestinationKind,
+    #[serde(default)]
+    target: String,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    locale: Option<Locale>,
+}
+
+/// Sets where alerts for the user's watchlist are sent, removing the destination if the target is
+/// left empty.
+pub async fn update_destination(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    User(username): User,
+    Form(DestinationUpdateRequest {
+        kind,
+        target,
+        locale,
+    }): Form<DestinationUpdateRequest>,
+) -> Result<Redirect, (StatusCode, String)> {
+    let target = target.trim();
+
+    if target.is_empty() {
+        crate::persistence::delete_user_destination(&pool, &username)
+            .await
+            .expect("failed to delete user destination");
+
+        return Ok(Redirect::to("/preferences"));
+    }
+
+    if kind == DestinationKind::Slack && !target.starts_with("https://") {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            String::from("Slack webhooks must be https URLs"),
+        ));
+    }
+
+    let destination = UserDestination {
+        kind,
+        target: target.to_owned(),
+        locale,
+    };
+
+    crate::persistence::upsert_user_destination(&pool, &username, &destination)
+        .await
+        .expect("failed to update user destination");
+
+    Ok(Redirect::to("/preferences"))
+}
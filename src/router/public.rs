@@ -0,0 +1,208 @@
+use axum::extract::State;
+use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use axum::response::IntoResponse;
+use axum::Json;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+
+use crate::locale::StatusPageText;
+use crate::persistence::PublicOrigin;
+use crate::router::{time_since, ApplicationState};
+use crate::templates::RenderedTemplate;
+
+#[derive(Serialize)]
+struct StatusOrigin {
+    uri: String,
+    operational: bool,
+    queried: String,
+}
+
+#[derive(Serialize)]
+struct StatusContext {
+    text: StatusPageText,
+    origins: Vec<StatusOrigin>,
+    all_operational: bool,
+}
+
+pub async fn status_page(
+    State(ApplicationState {
+        pool,
+        template_engine,
+        locale,
+        ..
+    }): State<ApplicationState>,
+) -> RenderedTemplate {
+    let origins: Vec<_> = crate::persistence::fetch_public_origins(&pool)
+        .await
+        .expect("failed to fetch public origins")
+        .into_iter()
+        .map(|origin| StatusOrigin {
+            uri: origin.uri,
+            operational: origin.operational,
+            queried: locale.checked_ago(&time_since(origin.queried_at)),
+        })
+        .collect();
+
+    let all_operational = origins.iter().all(|origin| origin.operational);
+
+    let context = StatusContext {
+        text: locale.status_page(),
+        origins,
+        all_operational,
+    };
+
+    template_engine
+        .render_serialized("status.tera.html", &context)
+        .expect("failed to render template")
+}
+
+pub async fn origins(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+) -> Json<Vec<PublicOrigin>> {
+    let origins = crate::persistence::fetch_public_origins(&pool)
+        .await
+        .expect("failed to fetch public origins");
+
+    Json(origins)
+}
+
+/// A public origin as written by the minimal status endpoints, for scripts, displays and screen
+/// readers that cannot use the HTML status page.
+#[derive(Debug, PartialEq, Serialize)]
+struct StatusLine {
+    name: String,
+    /// Either `operational` or `outage`.
+    state: &'static str,
+    last_checked: DateTime<Utc>,
+}
+
+impl From<PublicOrigin> for StatusLine {
+    fn from(origin: PublicOrigin) -> Self {
+        Self {
+            name: origin.uri,
+            state: if origin.operational {
+                "operational"
+            } else {
+                "outage"
+            },
+            last_checked: origin.queried_at,
+        }
+    }
+}
+
+async fn fetch_status_lines(state: &ApplicationState) -> Vec<StatusLine> {
+    crate::persistence::fetch_public_origins(&state.pool)
+        .await
+        .expect("failed to fetch public origins")
+        .into_iter()
+        .map(StatusLine::from)
+        .collect()
+}
+
+/// Writes one line per origin with its name, state and last check, separated by spaces.
+fn to_text(lines: &[StatusLine]) -> String {
+    lines
+        .iter()
+        .map(|line| {
+            format!(
+                "{} {} {}\n",
+                line.name,
+                line.state,
+                line.last_checked.to_rfc3339_opts(SecondsFormat::Secs, true)
+            )
+        })
+        .collect()
+}
+
+pub async fn status_text(State(state): State<ApplicationState>) -> impl IntoResponse {
+    let lines = fetch_status_lines(&state).await;
+
+    (
+        [
+            (CONTENT_TYPE, "text/plain; charset=utf-8"),
+            (CACHE_CONTROL, "no-store"),
+        ],
+        to_text(&lines),
+    )
+}
+
+pub async fn status_json(State(state): State<ApplicationState>) -> impl IntoResponse {
+    let lines = fetch_status_lines(&state).await;
+
+    ([(CACHE_CONTROL, "no-store")], Json(lines))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use color_eyre::eyre::Result;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    use crate::persistence::Visibility;
+    use crate::poller::FailureReason;
+    use crate::router::public::StatusLine;
+
+    #[test]
+    fn status_lines_are_written_one_per_origin() {
+        let lines = [
+            StatusLine {
+                name: String::from("https://example.com"),
+                state: "operational",
+                last_checked: Utc.with_ymd_and_hms(2026, 10, 16, 9, 0, 0).unwrap(),
+            },
+            StatusLine {
+                name: String::from("tcp://db.example.com:5432"),
+                state: "outage",
+                last_checked: Utc.with_ymd_and_hms(2026, 10, 16, 9, 0, 30).unwrap(),
+            },
+        ];
+
+        assert_eq!(
+            super::to_text(&lines),
+            "https://example.com operational 2026-10-16T09:00:00Z\n\
+             tcp://db.example.com:5432 outage 2026-10-16T09:00:30Z\n"
+        );
+    }
+
+    #[sqlx::test]
+    async fn private_origins_are_never_shown_publicly(pool: PgPool) -> Result<()> {
+        let public_uid = Uuid::new_v4();
+        let private_uid = Uuid::new_v4();
+
+        crate::persistence::insert_origin(&pool, public_uid, "https://example.com").await?;
+        crate::persistence::insert_origin(&pool, private_uid, "https://internal.example.com")
+            .await?;
+
+        crate::persistence::set_origin_visibility(&pool, public_uid, Visibility::Public).await?;
+        crate::persistence::set_origin_visibility(&pool, private_uid, Visibility::Private).await?;
+
+        let mut tx = pool.begin().await?;
+        crate::persistence::insert_query(&mut tx, public_uid, Some(200), 50, Utc::now()).await?;
+        crate::persistence::insert_query_failure(
+            &mut tx,
+            private_uid,
+            FailureReason::RequestTimeout,
+            None,
+            Utc::now(),
+        )
+        .await?;
+        tx.commit().await?;
+
+        let origins: Vec<_> = crate::persistence::fetch_public_origins(&pool)
+            .await?
+            .into_iter()
+            .map(|origin| (origin.uri, origin.operational))
+            .collect();
+
+        assert_eq!(origins, [(String::from("https://example.com"), true)]);
+
+        // Hiding an origin takes it off the page straight away
+        crate::persistence::set_origin_visibility(&pool, public_uid, Visibility::Private).await?;
+
+        let origins = crate::persistence::fetch_public_origins(&pool).await?;
+        assert!(origins.is_empty());
+
+        Ok(())
+    }
+}
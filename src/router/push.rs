@@ -0,0 +1,94 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::persistence::PushSubscription;
+use crate::router::identity::User;
+use crate::router::ApplicationState;
+
+type ApiError = (StatusCode, String);
+
+#[derive(Serialize)]
+pub struct PublicKeyResponse {
+    public_key: String,
+}
+
+pub async fn public_key(
+    State(ApplicationState {
+        push_public_key, ..
+    }): State<ApplicationState>,
+) -> Result<Json<PublicKeyResponse>, ApiError> {
+    let public_key = push_public_key.ok_or((
+        StatusCode::NOT_FOUND,
+        String::from("push notifications are not configured"),
+    ))?;
+
+    Ok(Json(PublicKeyResponse {
+        public_key: public_key.to_string(),
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct SubscriptionKeys {
+    p256dh: String,
+    auth: String,
+}
+
+/// The subscription as returned by `PushSubscription.toJSON()` in the browser.
+#[derive(Deserialize)]
+pub struct SubscriptionRequest {
+    endpoint: String,
+    keys: SubscriptionKeys,
+}
+
+pub async fn subscribe(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    User(username): User,
+    Json(SubscriptionRequest { endpoint, keys }): Json<SubscriptionRequest>,
+) -> Result<StatusCode, ApiError> {
+    if !endpoint.starts_with("https://") {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            String::from("push endpoints must be https URLs"),
+        ));
+    }
+
+    let subscription = PushSubscription {
+        endpoint,
+        p256dh: keys.p256dh,
+        auth: keys.auth,
+    };
+
+    crate::persistence::upsert_push_subscription(&pool, &username, &subscription)
+        .await
+        .expect("failed to store push subscription");
+
+    tracing::info!(%username, "subscribed a browser to push notifications");
+
+    Ok(StatusCode::CREATED)
+}
+
+#[derive(Deserialize)]
+pub struct UnsubscribeRequest {
+    endpoint: String,
+}
+
+pub async fn unsubscribe(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    User(username): User,
+    Json(UnsubscribeRequest { endpoint }): Json<UnsubscribeRequest>,
+) -> Result<StatusCode, ApiError> {
+    let removed = crate::persistence::remove_push_subscription(&pool, &username, &endpoint)
+        .await
+        .expect("failed to remove push subscription");
+
+    if !removed {
+        return Err((
+            StatusCode::NOT_FOUND,
+            String::from("subscription not found"),
+        ));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,576 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::Redirect;
+use axum::Json;
+use chrono::{Datelike, Months, NaiveDate, NaiveTime, Utc};
+use futures_util::TryStreamExt;
+use humantime::format_duration;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::persistence::{AlertFatigue, Expectations, Incident, OriginReport};
+use crate::router::identity::User;
+use crate::router::ApplicationState;
+use crate::templates::RenderedTemplate;
+
+#[derive(Deserialize)]
+pub struct ReportPeriod {
+    /// The month to report on, formatted as `YYYY-MM`. Defaults to the current month.
+    month: Option<String>,
+}
+
+impl ReportPeriod {
+    fn first_day(&self) -> Option<NaiveDate> {
+        match &self.month {
+            Some(month) => NaiveDate::parse_from_str(&format!("{month}-01"), "%Y-%m-%d").ok(),
+            None => Utc::now().date_naive().with_day(1),
+        }
+    }
+}
+
+fn format_seconds(seconds: f64) -> String {
+    format_duration(Duration::from_secs(seconds.max(0.0) as u64)).to_string()
+}
+
+#[derive(Serialize)]
+struct ReportRow {
+    uri: String,
+    uptime: Option<String>,
+    incidents: i64,
+    downtime: String,
+    estimated_cost: Option<String>,
+}
+
+impl ReportRow {
+    fn estimated_cost_cents(report: &OriginReport) -> Option<i64> {
+        let downtime = chrono::Duration::milliseconds((report.downtime_seconds * 1000.0) as i64);
+
+        report
+            .cost_per_minute_cents
+            .map(|cost| crate::cost::estimate(downtime, cost))
+    }
+}
+
+impl From<&OriginReport> for ReportRow {
+    fn from(report: &OriginReport) -> Self {
+        let checks = report.successes + report.failures;
+        let uptime = (checks > 0)
+            .then(|| format!("{:.3}%", report.successes as f64 / checks as f64 * 100.0));
+
+        Self {
+            uri: report.uri.clone(),
+            uptime,
+            incidents: report.incidents,
+            downtime: format_seconds(report.downtime_seconds),
+            estimated_cost: Self::estimated_cost_cents(report).map(crate::cost::format_cents),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ReportIncident {
+    uri: String,
+    started_at: String,
+    duration: String,
+    ongoing: bool,
+    estimated_cost: Option<String>,
+}
+
+impl From<&Incident> for ReportIncident {
+    fn from(incident: &Incident) -> Self {
+        Self {
+            uri: incident.uri.clone(),
+            started_at: incident.started_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+            duration: format_seconds(incident.duration().num_seconds() as f64),
+            ongoing: incident.resolved_at.is_none(),
+            estimated_cost: incident
+                .estimated_cost_cents()
+                .map(crate::cost::format_cents),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SlaReportContext {
+    month: String,
+    previous_month: String,
+    next_month: String,
+    rows: Vec<ReportRow>,
+    incidents: Vec<ReportIncident>,
+    total_downtime: String,
+    total_cost: Option<String>,
+}
+
+pub async fn sla_report(
+    State(ApplicationState {
+        pool,
+        template_engine,
+        ..
+    }): State<ApplicationState>,
+    Query(period): Query<ReportPeriod>,
+) -> Result<RenderedTemplate, (StatusCode, &'static str)> {
+    let invalid_month = (
+        StatusCode::BAD_REQUEST,
+        "month must be formatted as YYYY-MM",
+    );
+
+    let first_day = period.first_day().ok_or(invalid_month)?;
+    let next_month = first_day
+        .checked_add_months(Months::new(1))
+        .ok_or(invalid_month)?;
+    let previous_month = first_day
+        .checked_sub_months(Months::new(1))
+        .ok_or(invalid_month)?;
+
+    let start = first_day.and_time(NaiveTime::MIN).and_utc();
+    let end = next_month.and_time(NaiveTime::MIN).and_utc();
+
+    let reports = crate::persistence::fetch_origin_reports(&pool, start, end)
+        .await
+        .expect("failed to fetch origin reports");
+
+    let incidents: Vec<Incident> =
+        crate::persistence::stream_incidents(&pool, Some(start), Some(end))
+            .try_collect()
+            .await
+            .expect("failed to fetch incidents");
+
+    let total_downtime: f64 = reports.iter().map(|r| r.downtime_seconds).sum();
+
+    let costs: Vec<_> = reports
+        .iter()
+        .filter_map(ReportRow::estimated_cost_cents)
+        .collect();
+
+    let total_cost = (!costs.is_empty()).then(|| crate::cost::format_cents(costs.iter().sum()));
+
+    let context = SlaReportContext {
+        month: first_day.format("%B %Y").to_string(),
+        previous_month: previous_month.format("%Y-%m").to_string(),
+        next_month: next_month.format("%Y-%m").to_string(),
+        rows: reports.iter().map(ReportRow::from).collect(),
+        incidents: incidents.iter().map(ReportIncident::from).collect(),
+        total_downtime: format_seconds(total_downtime),
+        total_cost,
+    };
+
+    let rendered = template_engine
+        .render_serialized("sla-report.tera.html", &context)
+        .expect("failed to render template");
+
+    Ok(rendered)
+}
+
+/// Alerts whose incident resolved itself within this long are counted as likely noise.
+const AUTO_RECOVERY_WINDOW: chrono::Duration = chrono::Duration::minutes(5);
+
+/// The number of weeks the alert fatigue report covers by default.
+const DEFAULT_FATIGUE_WEEKS: u32 = 8;
+
+/// The number of unacknowledged alerts listed on the alert fatigue report.
+const UNACKNOWLEDGED_LIMIT: i64 = 25;
+
+fn percentage(count: i64, total: i64) -> String {
+    if total == 0 {
+        return String::from("\u{2014}");
+    }
+
+    format!("{:.0}%", count as f64 / total as f64 * 100.0)
+}
+
+#[derive(Serialize)]
+struct FatigueRow {
+    uri: String,
+    week: String,
+    notifications: i64,
+    ack_rate: String,
+    auto_recovered: i64,
+    noise_rate: String,
+}
+
+impl From<&AlertFatigue> for FatigueRow {
+    fn from(fatigue: &AlertFatigue) -> Self {
+        Self {
+            uri: fatigue.uri.clone(),
+            week: fatigue.week.format("%Y-%m-%d").to_string(),
+            notifications: fatigue.notifications,
+            ack_rate: percentage(fatigue.acknowledged, fatigue.notifications),
+            auto_recovered: fatigue.auto_recovered,
+            noise_rate: percentage(fatigue.auto_recovered, fatigue.notifications),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct UnacknowledgedRow {
+    notification_uid: Uuid,
+    uri: String,
+    subject: String,
+    created_at: String,
+}
+
+#[derive(Serialize)]
+struct AlertFatigueContext {
+    weeks: u32,
+    rows: Vec<FatigueRow>,
+    total_notifications: i64,
+    ack_rate: String,
+    noise_rate: String,
+    unacknowledged: Vec<UnacknowledgedRow>,
+}
+
+#[derive(Deserialize)]
+pub struct FatiguePeriod {
+    weeks: Option<u32>,
+}
+
+pub async fn alert_fatigue(
+    State(ApplicationState {
+        pool,
+        template_engine,
+        ..
+    }): State<ApplicationState>,
+    Query(FatiguePeriod { weeks }): Query<FatiguePeriod>,
+) -> RenderedTemplate {
+    let weeks = weeks.unwrap_or(DEFAULT_FATIGUE_WEEKS).clamp(1, 52);
+    let since = Utc::now() - chrono::Duration::weeks(i64::from(weeks));
+
+    let fatigue = crate::persistence::fetch_alert_fatigue(&pool, since, AUTO_RECOVERY_WINDOW)
+        .await
+        .expect("failed to fetch alert fatigue");
+
+    let unacknowledged =
+        crate::persistence::fetch_unacknowledged_notifications(&pool, UNACKNOWLEDGED_LIMIT)
+            .await
+            .expect("failed to fetch unacknowledged notifications")
+            .into_iter()
+            .map(|notification| UnacknowledgedRow {
+                notification_uid: notification.notification_uid,
+                uri: notification.uri,
+                subject: notification.subject,
+                created_at: notification
+                    .created_at
+                    .format("%Y-%m-%d %H:%M UTC")
+                    .to_string(),
+            })
+            .collect();
+
+    let total_notifications = fatigue.iter().map(|f| f.notifications).sum();
+    let acknowledged = fatigue.iter().map(|f| f.acknowledged).sum();
+    let auto_recovered = fatigue.iter().map(|f| f.auto_recovered).sum();
+
+    let context = AlertFatigueContext {
+        weeks,
+        rows: fatigue.iter().map(FatigueRow::from).collect(),
+        total_notifications,
+        ack_rate: percentage(acknowledged, total_notifications),
+        noise_rate: percentage(auto_recovered, total_notifications),
+        unacknowledged,
+    };
+
+    template_engine
+        .render_serialized("alert-fatigue.tera.html", &context)
+        .expect("failed to render template")
+}
+
+pub async fn acknowledge(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    user: Option<User>,
+    Path(notification_uid): Path<Uuid>,
+) -> Redirect {
+    let acknowledged_by = user.map(|User(username)| username);
+
+    crate::persistence::acknowledge_notification(
+        &pool,
+        notification_uid,
+        acknowledged_by.as_deref(),
+    )
+    .await
+    .expect("failed to acknowledge notification");
+
+    Redirect::to("/reports/alerts")
+}
+
+#[derive(Deserialize)]
+pub struct ReliabilityPeriod {
+    /// The number of days to calculate the metrics over, defaulting to 90.
+    days: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct OriginReliability {
+    origin_uid: Uuid,
+    uri: String,
+    incidents: usize,
+    mean_time_to_recovery_seconds: Option<i64>,
+    mean_time_between_failures_seconds: Option<i64>,
+    expectations: Expectations,
+}
+
+pub async fn reliability(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Query(ReliabilityPeriod { days }): Query<ReliabilityPeriod>,
+) -> Json<Vec<OriginReliability>> {
+    let window = days
+        .map(|days| chrono::Duration::days(days.clamp(1, 365)))
+        .unwrap_or(crate::reliability::DEFAULT_WINDOW);
+
+    let origins = crate::persistence::fetch_origins(&pool)
+        .await
+        .expect("failed to fetch origins");
+
+    let incidents: Vec<Incident> =
+        crate::persistence::stream_incidents(&pool, Some(Utc::now() - window), None)
+            .try_collect()
+            .await
+            .expect("failed to fetch incidents");
+
+    // Incidents are streamed oldest first, so each origin's stay in order
+    let mut by_origin: HashMap<Uuid, Vec<Incident>> = HashMap::new();
+
+    for incident in incidents {
+        by_origin
+            .entry(incident.origin_uid)
+            .or_default()
+            .push(incident);
+    }
+
+    let reliability = origins
+        .into_iter()
+        .map(|origin| {
+            let incidents = by_origin.remove(&origin.origin_uid).unwrap_or_default();
+            let reliability = crate::reliability::measure(&incidents);
+
+            OriginReliability {
+                expectations: origin.expectations(),
+                origin_uid: origin.origin_uid,
+                uri: origin.uri,
+                incidents: reliability.incidents,
+                mean_time_to_recovery_seconds: reliability
+                    .mean_time_to_recovery
+                    .map(|d| d.num_seconds()),
+                mean_time_between_failures_seconds: reliability
+                    .mean_time_between_failures
+                    .map(|d| d.num_seconds()),
+            }
+        })
+        .collect();
+
+    Json(reliability)
+}
+
+/// How long latency is aggregated over for routing if the client does not say.
+const DEFAULT_LATENCY_WINDOW_HOURS: i64 = 24;
+
+#[derive(Deserialize)]
+pub struct LatencyPeriod {
+    /// The number of hours to aggregate latency over, defaulting to 24.
+    hours: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct RegionLatency {
+    location: String,
+    p50_millis: f64,
+    p95_millis: f64,
+    samples: i64,
+}
+
+#[derive(Serialize)]
+pub struct OriginLatencies {
+    origin_uid: Uuid,
+    uri: String,
+    regions: Vec<RegionLatency>,
+}
+
+/// Exports the median and 95th percentile latency of each origin from each location checking it,
+/// for traffic managers to weight routing with.
+pub async fn latency_by_location(
+    State(ApplicationState { pool, .. }): State<ApplicationState>,
+    Query(LatencyPeriod { hours }): Query<LatencyPeriod>,
+) -> Json<Vec<OriginLatencies>> {
+    let window = chrono::Duration::hours(
+        hours
+            .unwrap_or(DEFAULT_LATENCY_WINDOW_HOURS)
+            .clamp(1, 24 * 30),
+    );
+
+    let latencies = crate::persistence::fetch_location_latencies(&pool, Utc::now() - window)
+        .await
+        .expect("failed to fetch location latencies");
+
+    // Latencies arrive grouped by origin, so each origin only needs comparing with the last
+    let mut origins: Vec<OriginLatencies> = Vec::new();
+
+    for latency in latencies {
+        let region = RegionLatency {
+            location: latency.location,
+            p50_millis: latency.p50_millis,
+            p95_millis: latency.p95_millis,
+            samples: latency.samples,
+        };
+
+        match origins.last_mut() {
+            Some(origin) if origin.origin_uid == latency.origin_uid => origin.regions.push(region),
+            _ => origins.push(OriginLatencies {
+                origin_uid: latency.origin_uid,
+                uri: latency.uri,
+                regions: vec![region],
+            }),
+        }
+    }
+
+    Json(origins)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use color_eyre::eyre::Result;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    use crate::persistence::{AlertKind, QueryDetails};
+    use crate::poller::FailureReason;
+    use crate::router::reports::AUTO_RECOVERY_WINDOW;
+
+    #[sqlx::test]
+    async fn alerts_for_incidents_that_quickly_recover_are_noise(pool: PgPool) -> Result<()> {
+        let origin_uid = Uuid::new_v4();
+        crate::persistence::insert_origin(&pool, origin_uid, "https://example.com").await?;
+
+        let failed_at = Utc::now() - Duration::hours(1);
+
+        let mut tx = pool.begin().await?;
+        crate::persistence::insert_query_failure(
+            &mut tx,
+            origin_uid,
+            FailureReason::RequestTimeout,
+            None,
+            failed_at,
+        )
+        .await?;
+        crate::persistence::insert_query(
+            &mut tx,
+            origin_uid,
+            Some(200),
+            50,
+            failed_at + Duration::minutes(2),
+        )
+        .await?;
+        tx.commit().await?;
+
+        let mut tx = pool.begin().await?;
+        let notification_uid = crate::persistence::insert_notification(
+            &mut tx,
+            origin_uid,
+            AlertKind::Outage,
+            "topic",
+            "Outage detected",
+            "message",
+            failed_at,
+        )
+        .await?;
+        tx.commit().await?;
+
+        assert!(
+            crate::persistence::acknowledge_notification(&pool, notification_uid, Some("alice"))
+                .await?
+        );
+
+        // Notifications can only be acknowledged once
+        assert!(
+            !crate::persistence::acknowledge_notification(&pool, notification_uid, None).await?
+        );
+
+        let fatigue = crate::persistence::fetch_alert_fatigue(
+            &pool,
+            failed_at - Duration::days(1),
+            AUTO_RECOVERY_WINDOW,
+        )
+        .await?;
+
+        assert_eq!(fatigue.len(), 1);
+        assert_eq!(fatigue[0].notifications, 1);
+        assert_eq!(fatigue[0].acknowledged, 1);
+        assert_eq!(fatigue[0].auto_recovered, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn percentages_are_rounded_and_handle_empty_totals() {
+        assert_eq!(super::percentage(1, 3), "33%");
+        assert_eq!(super::percentage(2, 2), "100%");
+        assert_eq!(super::percentage(0, 0), "\u{2014}");
+    }
+
+    #[sqlx::test]
+    async fn latencies_are_aggregated_per_location(pool: PgPool) -> Result<()> {
+        let origin_uid = Uuid::new_v4();
+        crate::persistence::insert_origin(&pool, origin_uid, "https://example.com").await?;
+
+        let now = Utc::now();
+        let mut tx = pool.begin().await?;
+
+        for (location, latency_millis) in (1..=100)
+            .map(|latency_millis| ("eu-west", latency_millis))
+            .chain([("us-east", 200)])
+        {
+            let query_uid = crate::persistence::insert_query(
+                &mut tx,
+                origin_uid,
+                Some(200),
+                latency_millis,
+                now - Duration::minutes(1),
+            )
+            .await?;
+            let details = QueryDetails {
+                location: Some(location),
+                ..Default::default()
+            };
+            crate::persistence::set_query_details(&mut tx, query_uid, &details).await?;
+        }
+
+        // Checks from before the window are left out
+        let query_uid = crate::persistence::insert_query(
+            &mut tx,
+            origin_uid,
+            Some(200),
+            5000,
+            now - Duration::days(2),
+        )
+        .await?;
+        let details = QueryDetails {
+            location: Some("us-east"),
+            ..Default::default()
+        };
+        crate::persistence::set_query_details(&mut tx, query_uid, &details).await?;
+        tx.commit().await?;
+
+        let latencies =
+            crate::persistence::fetch_location_latencies(&pool, now - Duration::days(1)).await?;
+
+        let summary: Vec<_> = latencies
+            .iter()
+            .map(|latency| {
+                (
+                    latency.location.as_str(),
+                    latency.p50_millis,
+                    latency.p95_millis,
+                    latency.samples,
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            summary,
+            [("eu-west", 50.5, 95.05, 100), ("us-east", 200.0, 200.0, 1)]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The longest a share link can stay valid for, in hours.
+pub const MAX_SHARE_LINK_HOURS: i64 = 7 * 24;
+
+/// Signs and verifies read-only links to an origin's detail page, so they can be handed to people
+/// without an account.
+#[derive(Clone)]
+pub struct ShareLinkSigner {
+    key: Vec<u8>,
+}
+
+impl ShareLinkSigner {
+    pub fn new(key: Vec<u8>) -> Self {
+        Self { key }
+    }
+
+    /// Reads the signing key from `SHARE_LINK_SECRET`, falling back to a random key which means
+    /// links stop working when the server restarts.
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("SHARE_LINK_SECRET") {
+            Ok(secret) if secret.len() < 32 => Err(eyre!(
+                "SHARE_LINK_SECRET must be at least 32 characters long"
+            )),
+            Ok(secret) => Ok(Self::new(secret.into_bytes())),
+            Err(_) => {
+                tracing::warn!(
+                    "SHARE_LINK_SECRET is not set, share links will not survive a restart"
+                );
+
+                let mut key = vec![0; 32];
+                rand::thread_rng().fill_bytes(&mut key);
+
+                Ok(Self::new(key))
+            }
+        }
+    }
+
+    fn mac(&self, origin_uid: Uuid, expires: i64) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any size");
+        mac.update(format!("{origin_uid}:{expires}").as_bytes());
+
+        mac
+    }
+
+    /// Returns the path of a link to the origin that is valid until `expires_at`.
+    pub fn sign(&self, origin_uid: Uuid, expires_at: DateTime<Utc>) -> String {
+        let expires = expires_at.timestamp();
+        let signature = hex::encode(self.mac(origin_uid, expires).finalize().into_bytes());
+
+        format!("/shared/{origin_uid}?expires={expires}&signature={signature}")
+    }
+
+    /// Checks that a link was signed by this server for the origin and has not yet expired.
+    pub fn verify(
+        &self,
+        origin_uid: Uuid,
+        expires: i64,
+        signature: &str,
+        now: DateTime<Utc>,
+    ) -> bool {
+        if expires <= now.timestamp() {
+            return false;
+        }
+
+        let Ok(signature) = hex::decode(signature) else {
+            return false;
+        };
+
+        self.mac(origin_uid, expires)
+            .verify_slice(&signature)
+            .is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    use crate::share::ShareLinkSigner;
+
+    fn signer() -> ShareLinkSigner {
+        ShareLinkSigner::new(b"an-entirely-unguessable-secret-key".to_vec())
+    }
+
+    /// Pulls the expiry and signature back out of a signed link.
+    fn parameters(link: &str) -> (i64, String) {
+        let (_, query) = link.split_once('?').unwrap();
+        let (expires, signature) = query.split_once('&').unwrap();
+
+        (
+            expires.trim_start_matches("expires=").parse().unwrap(),
+            signature.trim_start_matches("signature=").to_owned(),
+        )
+    }
+
+    #[test]
+    fn signed_links_can_be_verified() {
+        let origin_uid = Uuid::new_v4();
+        let now = Utc::now();
+
+        let link = signer().sign(origin_uid, now + Duration::hours(1));
+        let (expires, signature) = parameters(&link);
+
+        assert!(link.starts_with(&format!("/shared/{origin_uid}?")));
+        assert!(signer().verify(origin_uid, expires, &signature, now));
+    }
+
+    #[test]
+    fn links_cannot_be_reused_for_other_origins_or_extended() {
+        let origin_uid = Uuid::new_v4();
+        let now = Utc::now();
+
+        let (expires, signature) = parameters(&signer().sign(origin_uid, now + Duration::hours(1)));
+
+        assert!(!signer().verify(Uuid::new_v4(), expires, &signature, now));
+        assert!(!signer().verify(origin_uid, expires + 3600, &signature, now));
+        assert!(!signer().verify(origin_uid, expires, "not-hex", now));
+    }
+
+    #[test]
+    fn expired_links_are_rejected() {
+        let origin_uid = Uuid::new_v4();
+        let now = Utc::now();
+
+        let (expires, signature) = parameters(&signer().sign(origin_uid, now + Duration::hours(1)));
+
+        assert!(!signer().verify(origin_uid, expires, &signature, now + Duration::hours(2)));
+    }
+}
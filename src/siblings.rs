@@ -0,0 +1,106 @@
+use reqwest::Url;
+
+/// The `www.` counterpart of apex and `www.` hosts, as subdomains such as `api.example.com` rarely
+/// have one.
+fn counterpart(host: &str) -> Option<String> {
+    if let Some(apex) = host.strip_prefix("www.") {
+        return Some(apex.to_owned());
+    }
+
+    (host.matches('.').count() == 1).then(|| format!("www.{host}"))
+}
+
+/// Expands an HTTP or HTTPS URI into itself followed by its other forms, which are the other
+/// scheme and the HTTP and HTTPS versions of its apex or `www.` counterpart.
+pub fn expand(uri: &str) -> Option<Vec<String>> {
+    let url = Url::parse(uri).ok()?;
+
+    let other_scheme = match url.scheme() {
+        "http" => "https",
+        "https" => "http",
+        _ => return None,
+    };
+
+    let host = url.host_str()?;
+    let mut uris = vec![url.to_string()];
+
+    let mut with = |scheme: &str, host: &str| -> Option<()> {
+        let mut sibling = url.clone();
+        sibling.set_scheme(scheme).ok()?;
+        sibling.set_host(Some(host)).ok()?;
+
+        // Ports only make sense for the scheme they were given with
+        sibling.set_port(None).ok()?;
+        uris.push(sibling.to_string());
+
+        Some(())
+    };
+
+    with(other_scheme, host)?;
+
+    if let Some(counterpart) = counterpart(host) {
+        with("https", &counterpart)?;
+        with("http", &counterpart)?;
+    }
+
+    Some(uris)
+}
+
+/// The name shown for a group of siblings, which is the host without any `www.` prefix.
+pub fn group_name(uri: &str) -> String {
+    let Ok(url) = Url::parse(uri) else {
+        return uri.to_owned();
+    };
+
+    let host = url.host_str().unwrap_or(uri);
+
+    host.strip_prefix("www.").unwrap_or(host).to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn apex_and_www_forms_are_both_expanded() {
+        assert_eq!(
+            super::expand("https://example.com/health"),
+            Some(vec![
+                String::from("https://example.com/health"),
+                String::from("http://example.com/health"),
+                String::from("https://www.example.com/health"),
+                String::from("http://www.example.com/health"),
+            ])
+        );
+
+        assert_eq!(
+            super::expand("http://www.example.com"),
+            Some(vec![
+                String::from("http://www.example.com/"),
+                String::from("https://www.example.com/"),
+                String::from("https://example.com/"),
+                String::from("http://example.com/"),
+            ])
+        );
+    }
+
+    #[test]
+    fn subdomains_only_expand_their_scheme() {
+        assert_eq!(
+            super::expand("https://api.example.com:8443/"),
+            Some(vec![
+                String::from("https://api.example.com:8443/"),
+                String::from("http://api.example.com/"),
+            ])
+        );
+
+        assert_eq!(super::expand("tcp://example.com:22"), None);
+    }
+
+    #[test]
+    fn groups_are_named_after_their_apex() {
+        assert_eq!(super::group_name("http://www.example.com/"), "example.com");
+        assert_eq!(
+            super::group_name("https://api.example.com/"),
+            "api.example.com"
+        );
+    }
+}
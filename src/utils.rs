@@ -6,3 +6,30 @@ pub fn get_env_var(key: &str) -> Result<String> {
 
     Ok(value)
 }
+
+/// Parses a comma separated list of tags, normalising them to lowercase and removing duplicates.
+pub fn parse_tags(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = input
+        .split(',')
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+
+    tags.sort();
+    tags.dedup();
+
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn tags_are_normalised() {
+        assert_eq!(
+            super::parse_tags(" Production, api,,production "),
+            vec![String::from("api"), String::from("production")]
+        );
+
+        assert!(super::parse_tags("").is_empty());
+    }
+}